@@ -1,14 +1,15 @@
 #!/bin/bash
 
-DIR='./assets/downloads'
-FILES=(
-    "$DIR/earth.jpg" 'https://i.imgur.com/2kbKhHA.jpg'
-)
-for i in $(seq 0 2 $((${#FILES[@]} - 1))); do
-    FILE="${FILES[$i]}"
-    LINK="${FILES[$(($i + 1))]}"
+MANIFEST='./assets/downloads.txt'
+while read -r FILE HASH LINK; do
+    if [[ -z "$FILE" || "$FILE" == \#* ]]; then
+        continue
+    fi
     if [[ ! -e "$FILE" ]]; then
         echo "downloading $LINK to $FILE"
         curl "$LINK" -o "$FILE"
     fi
-done
+    if [[ "$HASH" != '-' ]] && ! echo "$HASH  $FILE" | sha256sum --check --status; then
+        echo "sha256 mismatch for $FILE"
+    fi
+done < "$MANIFEST"
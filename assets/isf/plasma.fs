@@ -0,0 +1,29 @@
+/*{
+    "DESCRIPTION": "Classic plasma effect, serves as an example for ISF import",
+    "CREDIT": "shaderpixel",
+    "ISFVSN": "2",
+    "CATEGORIES": ["Generator"],
+    "INPUTS": [
+        { "NAME": "speed", "LABEL": "Speed", "TYPE": "float", "DEFAULT": 1.0, "MIN": 0.0, "MAX": 5.0 },
+        { "NAME": "scale", "LABEL": "Scale", "TYPE": "float", "DEFAULT": 8.0, "MIN": 1.0, "MAX": 32.0 },
+        { "NAME": "tint", "LABEL": "Tint", "TYPE": "color", "DEFAULT": [0.2, 0.6, 1.0, 1.0] },
+        { "NAME": "rings", "LABEL": "Rings", "TYPE": "bool", "DEFAULT": true }
+    ]
+}*/
+
+void main() {
+    vec2 uv = isf_FragNormCoord * scale;
+    float t = TIME * speed;
+
+    float v = sin(uv.x + t);
+    v += sin((uv.y + t) * 0.5);
+    v += sin((uv.x + uv.y + t) * 0.5);
+    if (rings) {
+        vec2 c = uv + vec2(sin(t / 3.0), cos(t / 2.0)) * scale * 0.5;
+        v += sin(sqrt(dot(c, c) + 1.0) + t);
+    }
+    v *= 0.5;
+
+    vec3 color = vec3(sin(v * 3.14159), sin(v * 3.14159 + 2.094), sin(v * 3.14159 + 4.188)) * 0.5 + 0.5;
+    gl_FragColor = vec4(color * tint.rgb, 1.0);
+}
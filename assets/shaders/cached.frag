@@ -0,0 +1,21 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Draws an exhibit that was rendered into a cache at a lower rate, see `ArtCache`.
+// The container is drawn with the vertex shader of the exhibit, the cache has the size of the screen.
+layout(set = 0, binding = 16) uniform sampler2D cacheColor;
+layout(set = 0, binding = 17) uniform sampler2D cacheDepth;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec4 color = texelFetch(cacheColor, texel, 0);
+    float depth = texelFetch(cacheDepth, texel, 0).r;
+    // the cache is cleared to transparent with the far depth
+    if (color.a <= 0.0 && depth >= 1.0) {
+        discard;
+    }
+    outColor = color;
+    gl_FragDepth = depth;
+}
@@ -0,0 +1,32 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+// Covers the screen with one triangle to draw the fragment shader of an exhibit shown full screen
+// at the resolution of the window, see `FullscreenMapping` in src/camera.rs.
+// The view maps the local space of the exhibit to the screen, so the fragment shader gets the same
+// inputs as with art2d.vert where the front of the container would be.
+
+layout(location = 0) in vec3 position;
+
+layout(set = 0, binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+layout(location = 0) out vec3 fragPos;
+layout(location = 1) out vec3 fragNorm;
+layout(location = 2) out vec3 cameraPos;
+
+void main() {
+    mat4 screen_to_local = inverse(ubo.proj * ubo.view * ubo.model);
+    // the triangle is in normalized device coordinates with y down, the view has y up
+    vec2 ndc = vec2(position.x, -position.y);
+
+    fragPos = (screen_to_local * vec4(ndc, 0.0, 1.0)).xyz;
+    // the front of flat exhibits faces towards -z in local space
+    fragNorm = normalize(mat3(transpose(inverse(ubo.model))) * vec3(0.0, 0.0, -1.0));
+    cameraPos = (screen_to_local * vec4(0.0, 0.0, 1.0, 1.0)).xyz;
+
+    gl_Position = vec4(position.xy, 0.0, 1.0);
+}
@@ -7,12 +7,14 @@ layout(location = 2) in float cameraDistToContainer;
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options[2];
+    vec4 options[OPTION_VEC4S];
     float time;
 } ubo;
 
 layout(location = 0) out vec4 outColor;
 
+#include "includes/cost.glsl"
+
 const float maxDist = 1000.;
 const float epsilon = 0.001;
 const int steps = 500;
@@ -191,6 +193,7 @@ vec3 raymarch(vec3 rayOrigin, vec3 rayDir, inout vec3 transmittance, inout vec3
     vec3 SigmaE = 1.0 - gemColor;
 
     for (int i = 0; i < steps; i++){
+        COST_STEP();
         prev_pos = pos;
         pos = rayOrigin + rayDir * t;
         prev_dist = m_dist;
@@ -278,4 +281,5 @@ void main(){
     }
 
     outColor = color;
+    COST_OUTPUT(outColor);
 }
@@ -0,0 +1,19 @@
+// The camera the exhibit is seen from, the reflected one in the mirror.
+// It is the same for all exhibits, see `CameraUniforms` in src/vulkan/global_set.rs.
+#ifndef CAMERA_GLSL
+#define CAMERA_GLSL
+
+layout(set = 1, binding = 1) uniform CameraUniforms {
+    // world space, w is 1
+    vec4 position;
+    // normalized view direction in world space, w is 0
+    vec4 forward;
+    // vertical field of view in radians
+    float fov;
+    float aspect_ratio;
+    // distances of the clip planes, set in the options
+    float near;
+    float far;
+} camera;
+
+#endif
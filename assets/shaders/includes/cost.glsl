@@ -0,0 +1,26 @@
+// Cost heatmap of ray marchers, enabled for all exhibits in the options.
+// Call COST_STEP() once per iteration of the expensive loops and COST_OUTPUT(outColor)
+// at the end of `main`. Both do nothing unless the shader is compiled with COST_HEATMAP.
+
+#ifdef COST_HEATMAP
+
+// iterations shown in red, the same for all exhibits so they can be compared
+#define COST_MAX 256.0
+
+int costSteps = 0;
+
+// blue for no iterations over green to red for COST_MAX or more
+vec3 costHeatmap(float t) {
+    t = clamp(t, 0.0, 1.0);
+    return clamp(vec3(4.0 * t - 2.0, 2.0 - abs(4.0 * t - 2.0), 2.0 - 4.0 * t), 0.0, 1.0);
+}
+
+#define COST_STEP() costSteps++
+#define COST_OUTPUT(color) color = vec4(costHeatmap(float(costSteps) / COST_MAX), 1.0)
+
+#else
+
+#define COST_STEP()
+#define COST_OUTPUT(color)
+
+#endif
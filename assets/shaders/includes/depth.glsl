@@ -0,0 +1,16 @@
+// Depth of points found by ray marching, so exhibits intersect each other and the gallery.
+// Build the exhibit with `frag_depth` and write `gl_FragDepth` on every path of `main`,
+// `gl_FragCoord.z` keeps the depth of the container where nothing is hit.
+
+// the uniforms of the vertex shader
+layout(set = 0, binding = 0) uniform TransformUniforms {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} transform;
+
+// depth of a point in the local space of the container, the space of fragPos
+float localDepth(vec3 localPos) {
+    vec4 clip = transform.proj * transform.view * transform.model * vec4(localPos, 1.0);
+    return clip.z / clip.w;
+}
@@ -9,6 +9,7 @@ vec3 estimate_normal(vec3 p) {
 
 int ray_march(vec3 pos, vec3 ray_dir, inout float dist) {
     for (int i = 0; i < MAX_STEPS; i++) {
+        COST_STEP();
         vec3 ray_pos = pos + ray_dir * dist;
         float de = sdf_scene(ray_pos);
 
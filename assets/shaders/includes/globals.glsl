@@ -0,0 +1,15 @@
+// Values that are the same for all exhibits, see `GlobalUniforms` in src/vulkan/global_set.rs.
+
+layout(set = 1, binding = 0) uniform GlobalUniforms {
+    vec4 light_pos;
+    // seconds of the gallery, keeps running while an exhibit is paused or slowed down,
+    // exhibits use ubo.time and ubo.delta_time instead
+    float time;
+    // seconds since the previous frame, also for paused exhibits
+    float delta_time;
+    uint frame_index;
+    // 1 if the user is sensitive to motion and flicker, exhibits should calm down their effects
+    uint reduced_motion;
+    // from 0 to 1, lowered by the auto quality when the frame rate drops, scale iteration counts with it
+    float detail;
+} globals;
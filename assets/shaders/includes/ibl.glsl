@@ -0,0 +1,42 @@
+// Image based lighting from the environment map loaded at startup.
+// All directions are expected in world space.
+
+layout(set = 1, binding = 2) uniform samplerCube envMap;
+layout(set = 1, binding = 3) uniform samplerCube envIrradiance;
+layout(set = 1, binding = 4) uniform samplerCube envPrefiltered;
+
+// must match PREFILTERED_MIP_LEVELS - 1 in src/vulkan/environment.rs
+const float ENV_PREFILTERED_MAX_LOD = 4.0;
+
+vec3 envBackground(vec3 dir) {
+    return texture(envMap, dir).rgb;
+}
+
+vec3 envDiffuse(vec3 normal) {
+    return texture(envIrradiance, normal).rgb;
+}
+
+vec3 envSpecular(vec3 reflected, float roughness) {
+    return textureLod(envPrefiltered, reflected, roughness * ENV_PREFILTERED_MAX_LOD).rgb;
+}
+
+// analytic approximation of the split sum brdf lookup table
+// from <https://www.unrealengine.com/en-US/blog/physically-based-shading-on-mobile>
+vec3 envBrdfApprox(vec3 specularColor, float roughness, float NoV) {
+    const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+    const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+    vec4 r = roughness * c0 + c1;
+    float a004 = min(r.x * r.x, exp2(-9.28 * NoV)) * r.x + r.y;
+    vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+    return specularColor * ab.x + ab.y;
+}
+
+// Ambient lighting of a surface with the given material, viewDir points from the camera to the surface.
+vec3 envLighting(vec3 albedo, float metallic, float roughness, vec3 normal, vec3 viewDir) {
+    float NoV = max(dot(normal, -viewDir), 0.0);
+    vec3 specularColor = mix(vec3(0.04), albedo, metallic);
+    vec3 diffuse = envDiffuse(normal) * albedo * (1.0 - metallic);
+    vec3 specular = envSpecular(reflect(viewDir, normal), roughness)
+        * envBrdfApprox(specularColor, roughness, NoV);
+    return diffuse + specular;
+}
@@ -0,0 +1,33 @@
+// State of the keyboard, updated every frame.
+// Keys are indexed by their JavaScript key code like on Shadertoy,
+// letters and digits use their uppercase ASCII code, e.g. 65 for A.
+
+layout(set = 1, binding = 8) uniform sampler2D keyboardTex;
+
+const int KEY_BACKSPACE = 8;
+const int KEY_TAB = 9;
+const int KEY_ENTER = 13;
+const int KEY_SHIFT = 16;
+const int KEY_CTRL = 17;
+const int KEY_ALT = 18;
+const int KEY_ESCAPE = 27;
+const int KEY_SPACE = 32;
+const int KEY_LEFT = 37;
+const int KEY_UP = 38;
+const int KEY_RIGHT = 39;
+const int KEY_DOWN = 40;
+
+// whether the key is held down
+bool keyDown(int key) {
+    return texelFetch(keyboardTex, ivec2(key, 0), 0).r > 0.5;
+}
+
+// whether the key went down this frame
+bool keyPressed(int key) {
+    return texelFetch(keyboardTex, ivec2(key, 0), 0).g > 0.5;
+}
+
+// flips every time the key is pressed
+bool keyToggled(int key) {
+    return texelFetch(keyboardTex, ivec2(key, 0), 0).b > 0.5;
+}
@@ -2,8 +2,9 @@
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options[2];
+    vec4 options[OPTION_VEC4S];
     float time;
+    float seed;
 } ubo;
 
 vec3 calc_lightning(vec3 color, vec3 pos, vec3 normal) {
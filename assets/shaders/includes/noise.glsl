@@ -0,0 +1,43 @@
+// Tileable noise textures generated at startup.
+// Coordinates are in texels, so the noise repeats every NOISE_*_SIZE units.
+
+layout(set = 1, binding = 5) uniform sampler2D noise2d;
+layout(set = 1, binding = 6) uniform sampler3D noise3d;
+layout(set = 1, binding = 7) uniform sampler2D blueNoiseTex;
+
+// must match the sizes in src/vulkan/noise.rs
+const float NOISE_2D_SIZE = 256.0;
+const float NOISE_3D_SIZE = 32.0;
+const int BLUE_NOISE_SIZE = 64;
+
+// fractal Perlin noise in [0; 1]
+float perlinNoise(vec2 p) {
+    return texture(noise2d, p / NOISE_2D_SIZE).r;
+}
+
+float perlinNoise(vec3 p) {
+    return texture(noise3d, p / NOISE_3D_SIZE).r;
+}
+
+// distance to the closest Worley feature point in [0; 1]
+float worleyNoise(vec2 p) {
+    return texture(noise2d, p / NOISE_2D_SIZE).g;
+}
+
+float worleyNoise(vec3 p) {
+    return texture(noise3d, p / NOISE_3D_SIZE).g;
+}
+
+// value noise, trilinear interpolated white noise between integer coordinates
+float valueNoise(vec2 p) {
+    return texture(noise2d, (p + 0.5) / NOISE_2D_SIZE).b;
+}
+
+float valueNoise(vec3 p) {
+    return texture(noise3d, (p + 0.5) / NOISE_3D_SIZE).b;
+}
+
+// blue noise for dithering, usually sampled with gl_FragCoord.xy
+float blueNoise(vec2 fragCoord) {
+    return texelFetch(blueNoiseTex, ivec2(fragCoord) % BLUE_NOISE_SIZE, 0).r;
+}
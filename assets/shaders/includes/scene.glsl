@@ -0,0 +1,25 @@
+// The gallery without any exhibits, rendered before the scene from the same camera.
+// Only rendered if a shader includes this file, the projection uses the planes of the camera.
+#include "camera.glsl"
+
+#define SCENE_NEAR camera.near
+#define SCENE_FAR camera.far
+
+layout(set = 0, binding = 14) uniform sampler2D sceneColorTex;
+layout(set = 0, binding = 15) uniform sampler2D sceneDepthTex;
+
+// position of the fragment on the screen from (0, 0) at the top left to (1, 1)
+vec2 screenUv() {
+    return gl_FragCoord.xy / vec2(textureSize(sceneColorTex, 0));
+}
+
+// color of the gallery behind the point on the screen, offset it for refraction
+vec3 sceneColor(vec2 uv) {
+    return texture(sceneColorTex, uv).rgb;
+}
+
+// distance along the view axis from the camera to the gallery
+float sceneDistance(vec2 uv) {
+    float depth = texture(sceneDepthTex, uv).r;
+    return SCENE_NEAR * SCENE_FAR / (SCENE_FAR - depth * (SCENE_FAR - SCENE_NEAR));
+}
@@ -1,5 +1,6 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
+#include "includes/globals.glsl"
 
 layout(location = 0) in vec3 fragPos;
 layout(location = 1) in vec3 cameraPos;
@@ -7,7 +8,7 @@ layout(location = 2) in float cameraDistToContainer;
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options[2];
+    vec4 options[OPTION_VEC4S];
     float time;
 } ubo;
 
@@ -19,7 +20,8 @@ const float INSIDE_SCALE = 4.5;
 const float MAX_DIST = INSIDE_SCALE * 2.0;
 
 float scaleFactor = ubo.options[0][0];
-int maxIterations = int(ubo.options[0][1]);
+// fewer iterations when the auto quality lowers the detail
+int maxIterations = max(1, int(round(ubo.options[0][1] * globals.detail)));
 float epsilon = ubo.options[0][2];
 bool enable_shadows = bool(ubo.options[0][3]);
 
@@ -56,6 +58,8 @@ float sdf_scene(vec3 pos) {
     return (length(v) - constant1) / dr - constant2;
 }
 
+#include "includes/cost.glsl"
+#include "includes/depth.glsl"
 #include "includes/fractal.glsl"
 
 void main() {
@@ -67,10 +71,13 @@ void main() {
 
     if (dist >= MAX_DIST || steps == MAX_STEPS) {
         outColor = vec4(0.0, 0.0, 0.0, 0.4);
+        gl_FragDepth = gl_FragCoord.z;
     } else {
+        gl_FragDepth = localDepth((pos + dir * dist) / INSIDE_SCALE);
         const vec3 ambient_color = vec3(0.5, 0.4, 0.4);
         const vec3 diffuse_color = vec3(0.4, 0.4, 0.5);
         vec3 color = calc_lightning(pos, dir, dist, steps, ambient_color, diffuse_color);
         outColor = vec4(color, 1.0);
     }
+    COST_OUTPUT(outColor);
 }
@@ -5,12 +5,6 @@ layout(location = 0) in vec3 fragPos;
 layout(location = 1) in vec3 cameraPos;
 layout(location = 2) in float cameraDistToContainer;
 
-layout(set = 0, binding = 1) uniform UniformBufferObject {
-    vec4 light_pos;
-    vec4 options[2];
-    float time;
-} ubo;
-
 layout(location = 0) out vec4 outColor;
 
 const int MAX_STEPS = 256;
@@ -18,12 +12,14 @@ const float INSIDE_SCALE = 1.2;
 const float MAX_DIST = INSIDE_SCALE * 2.0;
 const float BAILOUT = 4.0;
 
-float power = ubo.options[0][0];
-int maxIterations = int(ubo.options[0][1]);
-float epsilon = ubo.options[0][2];
-int color_index = int(ubo.options[0][3]);
-bool enable_shadows = bool(ubo.options[1][0]);
-bool enable_animation = bool(ubo.options[1][1]);
+// the uniforms and the OPT_ macros come from the prelude, see src/prelude.rs
+float power = float(OPT_POWER);
+// fewer iterations when the auto quality lowers the detail
+int maxIterations = max(1, int(round(float(OPT_ITERATIONS) * globals.detail)));
+float epsilon = OPT_EPSILON;
+int color_index = OPT_COLORINDEX;
+bool enable_shadows = OPT_SHADOWS;
+bool enable_animation = OPT_ANIMATE;
 
 float sdf_scene(vec3 pos) {
     vec3 z = pos;
@@ -52,6 +48,8 @@ float sdf_scene(vec3 pos) {
     return 0.5 * log(r) * r / dr;
 }
 
+#include "includes/cost.glsl"
+#include "includes/depth.glsl"
 #include "includes/fractal.glsl"
 #include "includes/palette.glsl"
 
@@ -67,11 +65,14 @@ void main() {
 
     if (dist >= MAX_DIST) {
         outColor = vec4(0.0, 0.0, 0.0, 0.4);
+        gl_FragDepth = gl_FragCoord.z;
     } else {
+        gl_FragDepth = localDepth((pos + dir * dist) / INSIDE_SCALE);
         // const vec3 ambient_color = vec3(float(steps / MAX_STEPS), 0.2, 0.4);
         const vec3 ambient_color = getPalette(length(pos + dir * dist) * 1.0, color_index);
         const vec3 diffuse_color = vec3(0.5, 0.5, 0.5);
         vec3 color = calc_lightning(pos, dir, dist, steps, ambient_color, diffuse_color);
         outColor = vec4(color, 1.0);
     }
+    COST_OUTPUT(outColor);
 }
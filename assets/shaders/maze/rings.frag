@@ -0,0 +1,19 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#include "includes/lightning.glsl"
+#include "includes/palette.glsl"
+
+// Concentric rings drifting outwards, an example exhibit for the maze.
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 fragNorm;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec2 uv = fragPos.xy; // [-1; 1]
+    float dist = length(uv);
+    float rings = sin(dist * 20.0 - ubo.time * 2.0) * 0.5 + 0.5;
+    vec3 color = palette(dist + ubo.time * 0.1, PAL1) * rings;
+    outColor = vec4(calc_lightning(color, fragPos, normalize(fragNorm)), 1.0);
+}
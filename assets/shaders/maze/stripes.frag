@@ -0,0 +1,19 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#include "includes/lightning.glsl"
+#include "includes/palette.glsl"
+
+// Diagonal stripes bending with a sine wave, an example exhibit for the maze.
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 fragNorm;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    vec2 uv = fragPos.xy; // [-1; 1]
+    float wave = uv.x + uv.y + sin(uv.y * 3.0 + ubo.time) * 0.3;
+    float stripes = smoothstep(0.4, 0.6, fract(wave * 4.0));
+    vec3 color = mix(palette(ubo.time * 0.05, PAL4), palette(ubo.time * 0.05 + 0.5, PAL4), stripes);
+    outColor = vec4(calc_lightning(color, fragPos, normalize(fragNorm)), 1.0);
+}
@@ -7,12 +7,14 @@ layout(location = 2) in float cameraDistToContainer;
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options[2];
+    vec4 options[OPTION_VEC4S];
     float time;
 } ubo;
 
 layout(location = 0) out vec4 outColor;
 
+#include "includes/cost.glsl"
+
 const float EPS = 0.00001;
 const int MAX_MENGER_DEPTH = 8;
 const vec4 CONTAINER_COLOR = vec4(0.0, 0.0, 0.0, 0.4);
@@ -82,6 +84,7 @@ bool menger_shadow(vec3 corner_start, float size_start, vec3 dir, vec3 pos) {
         if (i == menger_depth * 4) {
             break;
         }
+        COST_STEP();
 
         float size = size_start;
         vec3 corner = corner_start;
@@ -111,6 +114,7 @@ vec4 menger(vec3 corner_start, float size_start, vec3 dir, inout vec3 pos, float
         if (i == menger_depth * 4) {
             break;
         }
+        COST_STEP();
 
         float size = size_start;
         vec3 corner = corner_start;
@@ -169,20 +173,17 @@ vec4 get_color(vec3 pos) {
 
 void main() {
     outColor = get_color(fragPos);
-    if (!enable_mssa) {
-        return;
-    }
-
-    if (length(dFdx(outColor)) > 0.01) {
+    if (enable_mssa && length(dFdx(outColor)) > 0.01) {
         vec3 dx = dFdx(fragPos);
         outColor += get_color(fragPos - 0.25 * dx);
         outColor += get_color(fragPos + 0.25 * dx);
         outColor /= 3.0;
     }
-    if (length(dFdy(outColor)) > 0.01) {
+    if (enable_mssa && length(dFdy(outColor)) > 0.01) {
         vec3 dy = dFdy(fragPos);
         outColor += get_color(fragPos - 0.25 * dy);
         outColor += get_color(fragPos + 0.25 * dy);
         outColor /= 3.0;
     }
+    COST_OUTPUT(outColor);
 }
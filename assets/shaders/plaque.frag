@@ -0,0 +1,30 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#include "includes/lightning.glsl"
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 fragNorm;
+layout(location = 2) in vec2 fragUv;
+
+// signed distance field of the glyphs in the red channel, see src/text.rs
+layout(set = 0, binding = 2) uniform sampler2D font_atlas;
+
+layout(location = 0) out vec4 outColor;
+
+const vec3 PLAQUE_COLOR = vec3(0.12, 0.09, 0.06);
+const vec3 TEXT_COLOR = vec3(0.93, 0.82, 0.55);
+
+void main() {
+    // the background quad has negative texture coordinates
+    if (fragUv.x < 0.0) {
+        outColor = vec4(calc_lightning(PLAQUE_COLOR, fragPos, normalize(fragNorm)), 1.0);
+        return;
+    }
+    float dist = texture(font_atlas, fragUv).r;
+    float width = max(fwidth(dist), 0.001);
+    float alpha = smoothstep(0.5 - width, 0.5 + width, dist);
+    if (alpha <= 0.0) {
+        discard;
+    }
+    outColor = vec4(calc_lightning(TEXT_COLOR, fragPos, normalize(fragNorm)), alpha);
+}
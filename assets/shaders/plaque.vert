@@ -0,0 +1,26 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 normal;
+layout(location = 2) in vec2 tex_coords;
+
+layout(binding = 0) uniform UniformBufferObject {
+    mat4 model;
+    mat4 view;
+    mat4 proj;
+} ubo;
+
+layout(location = 0) out vec3 fragPos;
+layout(location = 1) out vec3 fragNorm;
+layout(location = 2) out vec2 fragUv;
+
+void main() {
+    vec4 world_pos = ubo.model * vec4(position, 1.0);
+    fragPos = world_pos.xyz;
+    fragNorm = normalize(mat3(transpose(inverse(ubo.model))) * normal);
+    fragUv = tex_coords;
+
+    gl_Position = ubo.proj * ubo.view * world_pos;
+    gl_Position.y = -gl_Position.y;
+}
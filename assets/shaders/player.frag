@@ -10,5 +10,8 @@ layout(location = 0) out vec4 outColor;
 void main() {
     vec3 norm = normalize(fragNorm);
     vec3 color = norm;
+    // walk state written by src/avatar.rs: speed, walk phase, crouched, fly mode
+    vec4 walk = ubo.options[0];
+    color *= 1.0 - 0.3 * walk.z;
     outColor = vec4(calc_lightning(color, fragPos, norm), 1.0);
 }
@@ -7,7 +7,7 @@ layout(location = 2) in vec3 cameraPos;
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options[2];
+    vec4 options[OPTION_VEC4S];
     float time;
 } ubo;
 
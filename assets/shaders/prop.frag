@@ -0,0 +1,55 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 cameraPos;
+layout(location = 2) in float cameraDistToContainer;
+
+layout(location = 0) out vec4 outColor;
+
+#include "includes/depth.glsl"
+#include "includes/lightning.glsl"
+
+vec3 base_color = ubo.options[0].rgb;
+// 0 is a ball and 1 a cube, set from the `PropShape` of the exhibit
+int shape = int(ubo.options[1][0]);
+
+// distance along dir to where the ray enters the shape, negative if it misses
+float intersect(vec3 origin, vec3 dir, out vec3 normal) {
+    if (shape == 0) {
+        float b = dot(origin, dir);
+        float h = b * b - dot(origin, origin) + 1.0;
+        if (h < 0.0) {
+            return -1.0;
+        }
+        float t = -b - sqrt(h);
+        normal = origin + dir * t;
+        return t;
+    }
+    vec3 inv = 1.0 / dir;
+    vec3 t0 = (-1.0 - origin) * inv;
+    vec3 t1 = (1.0 - origin) * inv;
+    vec3 near = min(t0, t1);
+    float t_near = max(max(near.x, near.y), near.z);
+    float t_far = min(min(max(t0.x, t1.x), max(t0.y, t1.y)), max(t0.z, t1.z));
+    if (t_near > t_far) {
+        return -1.0;
+    }
+    normal = -sign(dir) * step(near.yzx, near) * step(near.zxy, near);
+    return t_near;
+}
+
+void main() {
+    vec3 dir = normalize(fragPos - cameraPos);
+    vec3 normal;
+    float t = intersect(cameraPos, dir, normal);
+    if (t < 0.0) {
+        discard;
+    }
+    vec3 hit = cameraPos + dir * t;
+    gl_FragDepth = localDepth(hit);
+
+    vec3 world_pos = (transform.model * vec4(hit, 1.0)).xyz;
+    vec3 world_normal = normalize(transpose(inverse(mat3(transform.model))) * normal);
+    outColor = vec4(calc_lightning(base_color, world_pos, world_normal), 1.0);
+}
@@ -1,27 +1,154 @@
 #version 450
 #extension GL_ARB_separate_shader_objects : enable
 
+#define PI 3.1415926535
+
 layout(location = 0) in vec3 fragPos;
 layout(location = 1) in vec3 cameraPos;
 layout(location = 2) in float cameraDistToContainer;
 
 layout(set = 0, binding = 1) uniform UniformBufferObject {
     vec4 light_pos;
-    vec4 options;
+    vec4 options[OPTION_VEC4S];
     float time;
 } ubo;
 
 layout(location = 0) out vec4 outColor;
 
+float rayleighScale = ubo.options[0][0];
+float mieScale = ubo.options[0][1];
+float sunSize = ubo.options[0][2];
+float starBrightness = ubo.options[0][3];
+float skyRotation = ubo.options[1][0];
+bool physicalSky = bool(ubo.options[1][1]);
+
+// all distances in meters
+const float EARTH_RADIUS = 6371e3;
+const float ATMOSPHERE_RADIUS = 6471e3;
+const vec3 RAYLEIGH_COEF = vec3(5.5e-6, 13.0e-6, 22.4e-6);
+const float MIE_COEF = 21e-6;
+const float RAYLEIGH_HEIGHT = 8e3;
+const float MIE_HEIGHT = 1.2e3;
+const float MIE_G = 0.758;
+const float SUN_INTENSITY = 22.0;
+// angular radius of the sun as seen from earth
+const float SUN_ANGULAR_RADIUS = 0.00465;
+const int STEPS_PRIMARY = 16;
+const int STEPS_LIGHT = 8;
+
 // stolen from <https://www.shadertoy.com/view/3s3GDn>
 float getGlow(float dist, float radius, float intensity){
     return max(0.0, pow(radius/max(dist, 1e-5), intensity));
 }
 
-void main() {
-    vec3 dir = normalize(fragPos - cameraPos);
+// returns the distances to the two intersections, x > y if there are none
+vec2 raySphere(vec3 ro, vec3 rd, float radius) {
+    float b = dot(ro, rd);
+    float c = dot(ro, ro) - radius * radius;
+    float d = b * b - c;
+    if (d < 0.0) {
+        return vec2(1e5, -1e5);
+    }
+    d = sqrt(d);
+    return vec2(-b - d, -b + d);
+}
+
+// single scattering Rayleigh and Mie atmosphere,
+// see <https://developer.nvidia.com/gpugems/gpugems2/part-ii-shading-lighting-and-shadows/chapter-16-accurate-atmospheric-scattering>
+vec3 atmosphere(vec3 dir, vec3 sunDir) {
+    vec3 ro = vec3(0.0, EARTH_RADIUS + 1.0, 0.0);
+    float tMax = raySphere(ro, dir, ATMOSPHERE_RADIUS).y;
+    vec2 ground = raySphere(ro, dir, EARTH_RADIUS);
+    if (ground.x > 0.0) {
+        tMax = min(tMax, ground.x);
+    }
+    float stepSize = tMax / float(STEPS_PRIMARY);
+
+    vec3 rayleighCoef = RAYLEIGH_COEF * rayleighScale;
+    float mieCoef = MIE_COEF * mieScale;
+
+    float mu = dot(dir, sunDir);
+    float g2 = MIE_G * MIE_G;
+    float phaseRayleigh = 3.0 / (16.0 * PI) * (1.0 + mu * mu);
+    float phaseMie = 3.0 / (8.0 * PI) * ((1.0 - g2) * (1.0 + mu * mu))
+        / ((2.0 + g2) * pow(1.0 + g2 - 2.0 * MIE_G * mu, 1.5));
+
+    vec3 totalRayleigh = vec3(0.0);
+    vec3 totalMie = vec3(0.0);
+    float opticalRayleigh = 0.0;
+    float opticalMie = 0.0;
+    for (int i = 0; i < STEPS_PRIMARY; ++i) {
+        vec3 pos = ro + dir * stepSize * (float(i) + 0.5);
+        float height = length(pos) - EARTH_RADIUS;
+        float stepRayleigh = exp(-height / RAYLEIGH_HEIGHT) * stepSize;
+        float stepMie = exp(-height / MIE_HEIGHT) * stepSize;
+        opticalRayleigh += stepRayleigh;
+        opticalMie += stepMie;
+
+        float lightStepSize = raySphere(pos, sunDir, ATMOSPHERE_RADIUS).y / float(STEPS_LIGHT);
+        float lightRayleigh = 0.0;
+        float lightMie = 0.0;
+        for (int j = 0; j < STEPS_LIGHT; ++j) {
+            vec3 lightPos = pos + sunDir * lightStepSize * (float(j) + 0.5);
+            float lightHeight = length(lightPos) - EARTH_RADIUS;
+            lightRayleigh += exp(-lightHeight / RAYLEIGH_HEIGHT) * lightStepSize;
+            lightMie += exp(-lightHeight / MIE_HEIGHT) * lightStepSize;
+        }
+
+        vec3 attenuation = exp(-(rayleighCoef * (opticalRayleigh + lightRayleigh)
+            + mieCoef * 1.1 * (opticalMie + lightMie)));
+        totalRayleigh += stepRayleigh * attenuation;
+        totalMie += stepMie * attenuation;
+    }
+
+    return SUN_INTENSITY * (phaseRayleigh * rayleighCoef * totalRayleigh + phaseMie * mieCoef * totalMie);
+}
+
+float hash(vec3 p) {
+    return fract(sin(dot(p, vec3(12.9898, 78.233, 45.164))) * 43758.5453);
+}
+
+float stars(vec3 dir) {
+    // let the stars turn with the sun
+    float c = cos(skyRotation);
+    float s = sin(skyRotation);
+    dir.xy = mat2(c, s, -s, c) * dir.xy;
+
+    vec3 p = dir * 200.0;
+    vec3 cell = floor(p);
+    float h = hash(cell);
+    float star = step(0.998, h) * smoothstep(0.3, 0.0, length(fract(p) - 0.5));
+    float twinkle = 0.7 + 0.3 * sin(ubo.time * 3.0 + h * 100.0);
+    return star * twinkle;
+}
+
+vec3 physical(vec3 dir) {
+    vec3 sunDir = normalize(ubo.light_pos.xyz);
+    vec3 color = atmosphere(dir, sunDir);
+
+    float sunRadius = SUN_ANGULAR_RADIUS * sunSize;
+    float sun = smoothstep(cos(sunRadius), cos(sunRadius * 0.8), dot(dir, sunDir));
+    float aboveHorizon = smoothstep(-0.01, 0.01, dir.y);
+    color += vec3(1.0, 0.95, 0.85) * SUN_INTENSITY * sun * aboveHorizon;
+
+    float night = smoothstep(0.1, -0.1, sunDir.y);
+    color += vec3(stars(dir)) * starBrightness * night * aboveHorizon;
+
+    // simple exposure tone mapping
+    return 1.0 - exp(-color);
+}
+
+vec3 gradient(vec3 dir) {
     vec3 sun_dir = normalize(vec3(1.0, 1.0, 1.0));
     float sun_angle = dot(dir, sun_dir);
+    return dir * 0.4 + 0.4 + getGlow(1 - sun_angle, 0.00015, 0.5);
+}
 
-    outColor = vec4(dir * 0.4 + 0.4, 1.0) + getGlow(1 - sun_angle, 0.00015, 0.5);
+void main() {
+    vec3 dir = normalize(fragPos - cameraPos);
+    if (physicalSky) {
+        outColor = vec4(physical(dir), 1.0);
+    } else {
+        outColor = vec4(gradient(dir), 1.0);
+    }
 }
@@ -0,0 +1,19 @@
+// Audio shader of the solar system, a slow drone with a chime every few seconds.
+// See src/vulkan/sound.rs for how it is rendered. All periods divide the 60 looped seconds.
+
+const float PI = 3.14159265;
+
+float tone(float freq, float time) {
+    return sin(2. * PI * freq * time);
+}
+
+vec2 mainSound(int samp, float time) {
+    float drone = tone(55., time) * (0.6 + 0.4 * sin(time * 2. * PI / 20.))
+        + 0.5 * tone(82.5, time) * (0.6 + 0.4 * sin(time * 2. * PI / 30. + 1.));
+
+    float beat = mod(time, 6.);
+    float chime = tone(880., time) * exp(-beat * 4.) + 0.5 * tone(1320., time) * exp(-beat * 6.);
+
+    float pan = 0.5 + 0.3 * sin(time * 2. * PI / 6.);
+    return vec2(0.1 * drone + 0.2 * chime * (1. - pan), 0.1 * drone + 0.2 * chime * pan);
+}
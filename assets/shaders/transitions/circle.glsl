@@ -0,0 +1,11 @@
+// The new frame opens in a growing circle around the center, an example for a custom transition.
+
+uniform float smoothness; // = 0.05
+uniform vec2 center; // = vec2(0.5, 0.5)
+
+vec4 transition(vec2 uv) {
+    vec2 offset = (uv - center) * vec2(ratio, 1.);
+    float radius = progress * (length(vec2(ratio, 1.)) / 2. + smoothness);
+    float t = smoothstep(radius - smoothness, radius, length(offset));
+    return mix(getToColor(uv), getFromColor(uv), t);
+}
@@ -0,0 +1,5 @@
+// Fades from the old to the new frame, see src/vulkan/transition.rs for the format.
+
+vec4 transition(vec2 uv) {
+    return mix(getFromColor(uv), getToColor(uv), progress);
+}
@@ -0,0 +1,9 @@
+// The new frame is wiped in from the left behind a soft edge.
+
+uniform float smoothness; // = 0.1
+
+vec4 transition(vec2 uv) {
+    float edge = progress * (1. + smoothness);
+    float t = smoothstep(edge - smoothness, edge, uv.x);
+    return mix(getToColor(uv), getFromColor(uv), t);
+}
@@ -1,3 +1,5 @@
+#include "includes/noise.glsl"
+
 const float PI2 = 6.283185;
 
 const float maxDist = 10.0;
@@ -13,12 +15,6 @@ float railRotNb = 3.0;
 float railRotationSpeed = 1.0;
 vec2 objId;
 
-float hash13(vec3 p3) {
-    p3 = fract(p3 * 0.1031);
-    p3 += dot(p3, p3.zyx + 31.32);
-    return fract((p3.x + p3.y) * p3.z);
-}
-
 float sdfSphere(vec3 pos, float s) {
     return length(pos) - s;
 }
@@ -100,34 +96,9 @@ float sdfMap(vec3 pos) {
     return sdf;
 }
 
-float trilinearInterpolation(vec3 p) {
-    vec3 gridPos = floor(p);
-    vec3 frac = fract(p);
-
-    // sample the 8 surrounding points
-    float c000 = hash13(gridPos + vec3(0,0,0));
-    float c100 = hash13(gridPos + vec3(1,0,0));
-    float c010 = hash13(gridPos + vec3(0,1,0));
-    float c110 = hash13(gridPos + vec3(1,1,0));
-    float c001 = hash13(gridPos + vec3(0,0,1));
-    float c101 = hash13(gridPos + vec3(1,0,1));
-    float c011 = hash13(gridPos + vec3(0,1,1));
-    float c111 = hash13(gridPos + vec3(1,1,1));
-
-    float c00 = mix(c000, c100, frac.x);
-    float c01 = mix(c001, c101, frac.x);
-    float c10 = mix(c010, c110, frac.x);
-    float c11 = mix(c011, c111, frac.x);
-
-    float c0 = mix(c00, c10, frac.y);
-    float c1 = mix(c01, c11, frac.y);
-
-    return mix(c0, c1, frac.z);
-}
-
 vec3 get3dColorGradient(vec3 pos){
-    return getPalette(trilinearInterpolation(pos + time * 0.2) * 2.0, color_index);
-    // return palette(trilinearInterpolation(pos + time * 0.2) * 2.0, PAL3);
+    return getPalette(valueNoise(pos + time * 0.2) * 2.0, color_index);
+    // return palette(valueNoise(pos + time * 0.2) * 2.0, PAL3);
 }
 
 vec3 raymarch(vec3 rayOrigin, vec3 rayDir){
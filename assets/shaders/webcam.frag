@@ -0,0 +1,54 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#include "includes/lightning.glsl"
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 fragNorm;
+
+layout(set = 0, binding = 2) uniform sampler2D webcam;
+
+layout(location = 0) out vec4 outColor;
+
+int effect = int(ubo.options[0][0]);
+bool mirrored = bool(ubo.options[0][1]);
+
+float luminance(vec3 color) {
+    return dot(color, vec3(0.2126, 0.7152, 0.0722));
+}
+
+vec3 sobel(vec2 uv) {
+    vec2 texel = 1.0 / vec2(textureSize(webcam, 0));
+    float gx = 0.0;
+    float gy = 0.0;
+    for (int y = -1; y <= 1; ++y) {
+        for (int x = -1; x <= 1; ++x) {
+            float l = luminance(texture(webcam, uv + vec2(x, y) * texel).rgb);
+            gx += l * float(x) * (y == 0 ? 2.0 : 1.0);
+            gy += l * float(y) * (x == 0 ? 2.0 : 1.0);
+        }
+    }
+    return vec3(length(vec2(gx, gy)));
+}
+
+vec3 thermal(float t) {
+    return clamp(vec3(t * 3.0, t * 3.0 - 1.0, t * 3.0 - 2.0), 0.0, 1.0)
+        + vec3(0.0, 0.0, 0.5) * (1.0 - t);
+}
+
+void main() {
+    vec2 uv = fragPos.xy * 0.5 + 0.5; // [0; 1]
+    if (mirrored) {
+        uv.x = 1.0 - uv.x;
+    }
+
+    vec3 color = texture(webcam, uv).rgb;
+    if (effect == 1) {
+        color = sobel(uv);
+    } else if (effect == 2) {
+        color = floor(color * 4.0 + 0.5) / 4.0;
+    } else if (effect == 3) {
+        color = thermal(luminance(color));
+    }
+
+    outColor = vec4(calc_lightning(color, fragPos, normalize(fragNorm)), 1.0);
+}
@@ -0,0 +1,345 @@
+//! Keyframe animations of the transform and the options of an exhibit.
+//!
+//! An `Animation` is a set of tracks that each animate one property. The keyframes of a
+//! track are interpolated with the easing of the earlier keyframe, so exhibits can move,
+//! rotate and morph on a schedule without an update function.
+//!
+//! The animations of all exhibits are stored in the timeline of the scene layout, see
+//! `crate::model::env_generator::Layout`, which is edited with the timeline window of the GUI.
+
+use crate::art::{ArtObject, ArtOption};
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use glam::{Mat4, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Keeps the value until the next keyframe.
+    Step,
+}
+
+impl Easing {
+    /// Maps the progress `t` in [0, 1] between two keyframes.
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1. - (1. - t) * (1. - t),
+            Self::EaseInOut if t < 0.5 => 2. * t * t,
+            Self::EaseInOut => 1. - (2. - 2. * t).powi(2) / 2.,
+            Self::Step => 0.,
+        }
+    }
+}
+
+impl Easing {
+    pub const ALL: [Self; 5] = [Self::Linear, Self::EaseIn, Self::EaseOut, Self::EaseInOut, Self::Step];
+}
+
+/// What happens after the last keyframe.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Repeat {
+    /// Keeps the value of the last keyframe.
+    #[default]
+    Once,
+    /// Starts again at the first keyframe.
+    Loop,
+    /// Plays backwards to the first keyframe and then forwards again.
+    PingPong,
+}
+
+pub trait Interpolate: Clone {
+    fn interpolate(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.lerp(*other, t)
+    }
+}
+
+impl Interpolate for Quat {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.slerp(*other, t)
+    }
+}
+
+impl Interpolate for Vec<f32> {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        self.iter().zip(other).map(|(a, b)| a.interpolate(b, t)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    /// Seconds since the start of the animation.
+    pub time: f32,
+    pub value: T,
+    /// Easing towards the next keyframe.
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframes<T> {
+    /// Sorted by time.
+    keyframes: Vec<Keyframe<T>>,
+    pub repeat: Repeat,
+}
+
+impl<T: Interpolate> Keyframes<T> {
+    pub fn new(repeat: Repeat) -> Self {
+        Self { keyframes: Vec::new(), repeat }
+    }
+
+    /// Adds a keyframe, keyframes can be added in any order.
+    pub fn key(mut self, time: f32, value: T, easing: Easing) -> Self {
+        self.insert(Keyframe { time, value, easing });
+        self
+    }
+
+    /// Inserts a keyframe at the position of its time and returns its index.
+    pub fn insert(&mut self, keyframe: Keyframe<T>) -> usize {
+        let idx = self.keyframes.partition_point(|other| other.time <= keyframe.time);
+        self.keyframes.insert(idx, keyframe);
+        idx
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe<T>] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |keyframe| keyframe.time)
+    }
+
+    /// Returns the interpolated value at `time` or `None` if there are no keyframes.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let duration = self.duration();
+        let time = match self.repeat {
+            _ if duration <= 0. => time,
+            Repeat::Once => time,
+            Repeat::Loop => time.rem_euclid(duration),
+            Repeat::PingPong => duration - (time.rem_euclid(2. * duration) - duration).abs(),
+        };
+        let idx = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        match (idx.checked_sub(1).map(|idx| &self.keyframes[idx]), self.keyframes.get(idx)) {
+            (Some(a), Some(b)) => {
+                let t = (time - a.time) / (b.time - a.time);
+                Some(a.value.interpolate(&b.value, a.easing.apply(t)))
+            }
+            (Some(keyframe), None) | (None, Some(keyframe)) => Some(keyframe.value.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Access to the keyframes of a track independent of the type of their values.
+pub trait KeyframeList {
+    fn times(&self) -> Vec<f32>;
+    fn easing(&self, idx: usize) -> Easing;
+    fn set_easing(&mut self, idx: usize, easing: Easing);
+    /// Changes the time of a keyframe and returns its new index.
+    fn move_key(&mut self, idx: usize, time: f32) -> usize;
+    fn remove(&mut self, idx: usize);
+    fn repeat(&self) -> Repeat;
+    fn set_repeat(&mut self, repeat: Repeat);
+}
+
+impl<T: Interpolate> KeyframeList for Keyframes<T> {
+    fn times(&self) -> Vec<f32> {
+        self.keyframes.iter().map(|keyframe| keyframe.time).collect()
+    }
+
+    fn easing(&self, idx: usize) -> Easing {
+        self.keyframes.get(idx).map(|keyframe| keyframe.easing).unwrap_or_default()
+    }
+
+    fn set_easing(&mut self, idx: usize, easing: Easing) {
+        if let Some(keyframe) = self.keyframes.get_mut(idx) {
+            keyframe.easing = easing;
+        }
+    }
+
+    fn move_key(&mut self, idx: usize, time: f32) -> usize {
+        if idx >= self.keyframes.len() {
+            return idx;
+        }
+        let keyframe = self.keyframes.remove(idx);
+        self.insert(Keyframe { time: time.max(0.), ..keyframe })
+    }
+
+    fn remove(&mut self, idx: usize) {
+        if idx < self.keyframes.len() {
+            self.keyframes.remove(idx);
+        }
+    }
+
+    fn repeat(&self) -> Repeat {
+        self.repeat
+    }
+
+    fn set_repeat(&mut self, repeat: Repeat) {
+        self.repeat = repeat;
+    }
+}
+
+/// A property of an exhibit that is animated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Track {
+    Position(Keyframes<Vec3>),
+    Rotation(Keyframes<Quat>),
+    Scale(Keyframes<Vec3>),
+    /// The values of the option with the label, in the layout of `ArtOptionType::save_value`.
+    Option { label: Cow<'static, str>, keyframes: Keyframes<Vec<f32>> },
+}
+
+impl Track {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Position(_) => "Position",
+            Self::Rotation(_) => "Rotation",
+            Self::Scale(_) => "Scale",
+            Self::Option { label, .. } => label,
+        }
+    }
+
+    pub fn keyframes(&self) -> &dyn KeyframeList {
+        match self {
+            Self::Position(keyframes) | Self::Scale(keyframes) => keyframes,
+            Self::Rotation(keyframes) => keyframes,
+            Self::Option { keyframes, .. } => keyframes,
+        }
+    }
+
+    pub fn keyframes_mut(&mut self) -> &mut dyn KeyframeList {
+        match self {
+            Self::Position(keyframes) | Self::Scale(keyframes) => keyframes,
+            Self::Rotation(keyframes) => keyframes,
+            Self::Option { keyframes, .. } => keyframes,
+        }
+    }
+
+    /// Adds a keyframe at `time` with the current value of the property.
+    /// `matrix` is the transform the animation changes, options are found by label.
+    pub fn insert_current(&mut self, time: f32, matrix: Mat4, options: &[ArtOption]) -> Option<usize> {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        let easing = Easing::default();
+        let idx = match self {
+            Self::Position(keyframes) => keyframes.insert(Keyframe { time, value: translation, easing }),
+            Self::Rotation(keyframes) => keyframes.insert(Keyframe { time, value: rotation, easing }),
+            Self::Scale(keyframes) => keyframes.insert(Keyframe { time, value: scale, easing }),
+            Self::Option { label, keyframes } => {
+                let option = options.iter().find(|option| option.label() == label)?;
+                let mut value = vec![0.; option.ty.slot_count()];
+                option.ty.save_value(&mut value, &mut 0);
+                keyframes.insert(Keyframe { time, value, easing })
+            }
+        };
+        Some(idx)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub tracks: Vec<Track>,
+    /// Seconds since the animation started.
+    pub time: f32,
+    /// The time only advances while playing, the values are applied anyway.
+    pub playing: bool,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self { tracks: Vec::new(), time: 0., playing: true }
+    }
+}
+
+impl Animation {
+    pub fn new(tracks: impl IntoIterator<Item = Track>) -> Self {
+        Self { tracks: tracks.into_iter().collect(), ..Default::default() }
+    }
+
+    /// Time of the last keyframe of all tracks.
+    pub fn duration(&self) -> f32 {
+        self.tracks.iter()
+            .flat_map(|track| track.keyframes().times().last().copied())
+            .fold(0., f32::max)
+    }
+}
+
+/// The tracks of all animated exhibits by exhibit name.
+pub fn timeline(art_objects: &[ArtObject]) -> BTreeMap<String, Vec<Track>> {
+    art_objects.iter()
+        .filter_map(|art| Some((art.name.clone(), art.animation.as_ref()?.tracks.clone())))
+        .filter(|(_, tracks)| !tracks.is_empty())
+        .collect()
+}
+
+/// Replaces the animations of the exhibits in `timeline` by exhibit name,
+/// the animations of the other exhibits are kept.
+pub fn set_timeline(timeline: &BTreeMap<String, Vec<Track>>, art_objects: &mut [ArtObject]) {
+    for (name, tracks) in timeline {
+        match art_objects.iter_mut().find(|art| art.name == *name) {
+            Some(art) => art.animation = Some(Animation::new(tracks.iter().cloned())),
+            None => log::warn!("timeline animates {name}, which does not exist"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_keyframes() {
+        let keyframes = Keyframes::new(Repeat::Once)
+            .key(2., 10., Easing::Linear)
+            .key(0., 0., Easing::EaseIn);
+        assert_eq!(keyframes.sample(-1.), Some(0.));
+        assert_eq!(keyframes.sample(1.), Some(2.5));
+        assert_eq!(keyframes.sample(3.), Some(10.));
+
+        let looped = Keyframes { repeat: Repeat::Loop, ..keyframes.clone() };
+        assert_eq!(looped.sample(3.), Some(2.5));
+        let ping_pong = Keyframes { repeat: Repeat::PingPong, ..keyframes };
+        assert_eq!(ping_pong.sample(3.), Some(2.5));
+        assert_eq!(Keyframes::<f32>::new(Repeat::Loop).sample(1.), None);
+    }
+
+    #[test]
+    fn timeline_roundtrip() {
+        let tracks = vec![
+            Track::Position(Keyframes::new(Repeat::PingPong)
+                .key(0., Vec3::ZERO, Easing::EaseInOut)
+                .key(2., Vec3::new(1., 2., 3.), Easing::Linear)),
+            Track::Rotation(Keyframes::new(Repeat::Loop).key(1., Quat::from_rotation_y(0.5), Easing::Step)),
+            Track::Scale(Keyframes::new(Repeat::Once)),
+            Track::Option {
+                label: "Color".into(),
+                keyframes: Keyframes::new(Repeat::Once).key(0.5, vec![0.25, 0.5, 1.], Easing::EaseOut),
+            },
+        ];
+        let timeline = BTreeMap::from([("Cube", &tracks)]);
+        let json = serde_json::to_string_pretty(&timeline).unwrap();
+        let loaded: BTreeMap<String, Vec<Track>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded["Cube"], tracks);
+        let animation = Animation::new(loaded["Cube"].clone());
+        assert_eq!(animation.duration(), 2.);
+        assert!(animation.playing);
+    }
+}
@@ -1,34 +1,67 @@
 use crate::{
-    art::{ArtObject, ArtUpdateData},
-    camera::{Camera, KeyStates},
+    animation::{set_timeline, timeline},
+    art::{resolve_parents, ArtObject, ArtUpdateData},
+    art_objects::SPAWN,
+    attract::AttractMode,
+    avatar::Avatar,
+    audio_input::AudioInput,
+    binding::{set_bindings, Driver, DriverInputs},
+    camera::{Camera, FullscreenMapping, KeyStates},
+    downloader::Downloader,
+    frame_clock::FrameClock,
+    gallery::{GalleryWatcher, GALLERY_DIR},
     gui::GuiState,
+    keyboard::KeyboardState,
+    midi::Midi,
+    permalink::Permalink,
+    physics::Physics,
+    second_window::{SecondWindow, SecondWindowMode},
+    settings::{Bookmark, Settings, SETTINGS_PATH},
+    stats::VisitorStats,
+    tour::{Tour, TourAction, TourStep},
+    sync::{SyncNode, SyncState},
+    trigger::{leave_portals, set_triggers, update_triggers},
+    uniform_block::set_uniform_values,
+    wallpaper::{Wallpaper, WallpaperLayer},
     model::{
-        env_generator::default_env,
+        env_generator::{default_layout, scene_names, Layout, DEFAULT_SCENE, LAYOUT_PATH},
+        watch::{reload_models, ModelWatcher},
     },
-    vulkan::VkApp,
+    vulkan::{set_include_paths, ClearColors, HotShader, VkApp},
 };
 
 use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
     sync::Arc,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
-use anyhow::Context;
 use egui_winit_vulkano::{Gui, GuiConfig};
-use glam::{Mat4, Vec3, Vec4};
+use glam::{Mat4, Quat, Vec3, Vec4};
 use winit::{
     application::ApplicationHandler,
-    dpi::PhysicalSize,
+    dpi::{LogicalPosition, LogicalSize},
     event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
-    event_loop::ActiveEventLoop,
-    keyboard::{Key, KeyCode, NamedKey, PhysicalKey},
-    window::{Fullscreen, Window, WindowId},
+    event_loop::{ActiveEventLoop, ControlFlow},
+    keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey},
+    window::{Window, WindowId},
 };
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
 const TITLE: &str = "shaderpixel";
-const START_POSITION: Vec3 = Vec3::from_array([0., 1.5, 3.]);
+const ENVIRONMENT_MAP: &str = "assets/downloads/environment.hdr";
+/// Time without resize events after which resizing is considered finished.
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(200);
+/// Time without changes after which the settings are written, to not write while dragging a slider.
+const SETTINGS_DEBOUNCE: Duration = Duration::from_secs(1);
+/// How often the progress of the downloads is checked while the scene is not loaded.
+const DOWNLOAD_POLL: Duration = Duration::from_millis(50);
+/// Speed of the shader time with reduced motion, flashing driven by the time is as much slower.
+const REDUCED_MOTION_TIME_SCALE: f32 = 0.5;
+/// Longest step of the shader time per frame with reduced motion, so hitches cause no sudden jumps.
+const REDUCED_MOTION_MAX_STEP: f32 = 1. / 30.;
 
 #[derive(Debug)]
 struct FpsInfo {
@@ -36,43 +69,170 @@ struct FpsInfo {
     frame_count: u32,
 }
 
+/// Receives the exhibits, the camera and the time in seconds, see `App::on_frame`.
+pub type FrameCallback = dyn FnMut(&mut [ArtObject], &mut Camera, f32);
+
 #[derive(Default)]
 pub struct App {
     pub art_objects: Vec<ArtObject>,
+    /// Downloads missing assets, the scene is loaded once it is finished.
+    pub downloader: Option<Downloader>,
+    /// The window while the scene is not loaded yet.
+    loading_window: Option<Arc<Window>>,
     app: Option<(Arc<Window>, VkApp, Gui)>,
+    /// Detached GUI or another view, see `GuiState::options.second_window`.
+    second_window: Option<SecondWindow>,
     swapchain_dirty: bool,
+    /// Time of the last resize event while the window is being resized.
+    last_resize: Option<Instant>,
     gui_state: GuiState,
     /// Time passed since app start in fractional seconds.
     time: f32,
     /// Information about frame timing.
     fps_info: Option<FpsInfo>,
+    frame_clock: FrameClock,
     /// Information about the current camera position and orientation.
     camera: Camera,
     /// Rembers for some keys if they are pressed
     key_states: KeyStates,
+    /// State of all keys for the keyboard texture of the shaders.
+    keyboard: KeyboardState,
     /// Number of lines scrolled. Used to determine movement speed.
     scroll_lines: f32,
-    /// Current cursor position.
-    cursor_position: Option<[i32; 2]>,
-    /// Movement delta of cursor since last frame.
-    cursor_delta: [i32; 2],
+    /// Current cursor position in logical pixels.
+    cursor_position: Option<[f32; 2]>,
+    /// Movement delta of cursor since last frame in logical pixels.
+    cursor_delta: [f32; 2],
+    /// Exhibit in the center of the screen last frame, named by the HUD.
+    looked_at: Option<usize>,
+    /// Flat exhibit that covers the window instead of the gallery, toggled with V.
+    fullscreen_art: Option<usize>,
+    /// Steps of the tour from the layout, see `crate::tour`.
+    tour_steps: Vec<TourStep>,
+    /// The guided tour while it is running.
+    tour: Option<Tour>,
+    /// Set when the view jumps, a transition from the last frame is started before the next one.
+    transition_pending: bool,
+    /// Modifier keys that are currently pressed.
+    modifiers: ModifiersState,
+    /// Set while the Ctrl that toggled fly mode is held, saving a bookmark with it undoes the toggle.
+    ctrl_toggled_fly: bool,
+    settings: Settings,
+    /// Time of the last change to the settings that is not saved yet.
+    settings_changed: Option<Instant>,
     /// Whether the application is in fullscreen or not.
     is_fullscreen: bool,
     skybox_rotation_angle: f32,
     box_idx: Option<usize>,
     mirror_idx: Option<usize>,
+    /// Groups of the exhibits before the layout replaced them, restored when the scene changes.
+    default_groups: HashMap<String, Vec<String>>,
+    /// Reloads the models of exhibits when their file changes.
+    model_watcher: Option<ModelWatcher>,
+    /// Adds and removes exhibits for the shaders in the gallery directory.
+    gallery: Option<GalleryWatcher>,
+    /// Whether the shaders are compiled for the cost heatmap.
+    cost_heatmap: bool,
+    /// Leader or follower if several instances are synchronized.
+    pub sync: Option<SyncNode>,
+    /// Records anonymous statistics of the visitors, see `crate::stats`.
+    pub stats: Option<VisitorStats>,
+    /// Server for the remote control API.
+    #[cfg(feature = "remote")]
+    pub remote: Option<crate::remote::RemoteServer>,
+    #[cfg(feature = "audio")]
+    audio: Option<crate::audio::Audio>,
+    /// Only started if an option is bound to an audio band.
+    audio_input: Option<AudioInput>,
+    /// Only connected if an option is bound to a MIDI controller.
+    midi: Option<Midi>,
+    /// Only simulated if there are props.
+    physics: Option<Physics>,
+    avatar: Avatar,
+    /// Tours the gallery while nobody uses it.
+    attract: AttractMode,
+    /// Set if running as wallpaper or overlay instead of a normal window.
+    pub wallpaper: Option<Wallpaper>,
+    /// Architecture of the gallery the app starts with, loaded from `LAYOUT_PATH` if not set.
+    pub layout: Option<Layout>,
+    /// Scene that `layout` belongs to, switching back to it does not load a file.
+    layout_scene: String,
+    /// Name of the scene that is shown, `DEFAULT_SCENE` if empty.
+    /// Other scenes are loaded with `Layout::load_scene` when selected in the options.
+    pub scene: String,
+    /// Where the camera starts in the scene, from its layout or `SPAWN` if not set.
+    spawn: Option<Bookmark>,
+    /// View opened at startup instead of the spawn.
+    pub permalink: Option<Permalink>,
+    /// Called every frame after the exhibits and the camera were updated and before drawing,
+    /// so embedders can drive exhibits themselves. Changes to `enable_pipeline` are kept.
+    pub on_frame: Option<Box<FrameCallback>>,
 }
 
 impl App {
-    fn init(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
-        let window_attrs = Window::default_attributes()
-            .with_title(TITLE)
-            .with_inner_size(PhysicalSize::new(WIDTH, HEIGHT));
-        let window = event_loop.create_window(window_attrs).context("Failed to create window")?;
-        let window = Arc::new(window);
+    /// Waits for the downloads to finish or fail while showing their progress in the window title,
+    /// then loads the scene.
+    fn load_scene(&mut self, event_loop: &ActiveEventLoop) {
+        let Some(window) = self.loading_window.as_ref() else { return };
+        if let Some(downloader) = self.downloader.as_ref() {
+            let progress = downloader.progress();
+            if !progress.finished {
+                window.set_title(&format!("{TITLE} - {progress}"));
+                event_loop.set_control_flow(ControlFlow::wait_duration(DOWNLOAD_POLL));
+                return;
+            }
+            if !progress.errors.is_empty() {
+                log::warn!("{} assets failed to download, continuing without them", progress.errors.len());
+            }
+            window.set_title(TITLE);
+            self.downloader = None;
+        }
+        let Some(window) = self.loading_window.take() else { return };
+        event_loop.set_control_flow(ControlFlow::Poll);
+        if let Err(err) = self.init(event_loop, window) {
+            log::error!("Error while starting: {err:?}");
+            event_loop.exit();
+        }
+    }
 
-        let model = default_env().normalize()?;
-        let vk_app = VkApp::new(Arc::clone(&window), model, &self.art_objects)?;
+    fn init(&mut self, event_loop: &ActiveEventLoop, window: Arc<Window>) -> anyhow::Result<()> {
+        if self.scene.is_empty() {
+            self.scene = DEFAULT_SCENE.to_owned();
+        }
+        self.gui_state.options.scene = self.scene.clone();
+        self.gui_state.options.scenes = scene_names();
+        if !self.gui_state.options.scenes.contains(&self.scene) {
+            self.gui_state.options.scenes.push(self.scene.clone());
+        }
+        let layout = self.layout.get_or_insert_with(|| {
+            Layout::load(Path::new(LAYOUT_PATH)).unwrap_or_else(|err| {
+                log::error!("using the default layout: {err:#}");
+                default_layout()
+            })
+        }).clone();
+        self.layout_scene = self.scene.clone();
+        let model = layout.generate().normalize()?;
+        self.apply_layout(&layout);
+        #[cfg(feature = "physics")]
+        if self.art_objects.iter().any(|art| art.prop.is_some()) {
+            let spawn = self.spawn.unwrap_or(SPAWN);
+            self.physics = Physics::new(&model, &self.art_objects, spawn.position)
+                .inspect_err(|err| log::error!("props are not simulated: {err:#}"))
+                .ok();
+        }
+        let mut vk_app = VkApp::new(
+            Arc::clone(&window),
+            model,
+            &self.art_objects,
+            Path::new(ENVIRONMENT_MAP),
+            self.wallpaper.as_ref().is_some_and(Wallpaper::is_transparent),
+        )?;
+        #[cfg(feature = "audio")]
+        {
+            self.audio = crate::audio::Audio::new(layout.ambient.as_deref(), &self.art_objects, &vk_app)
+                .inspect_err(|err| log::error!("audio disabled: {err:#}"))
+                .ok();
+        }
         let gui = Gui::new_with_subpass(
             event_loop,
             vk_app.get_swapchain().surface().clone(),
@@ -83,26 +243,344 @@ impl App {
         );
 
         self.gui_state.options.present_modes = vk_app.get_surface_present_modes()?;
+        self.gui_state.options.actual_image_count = vk_app.image_count();
+        self.frame_clock.set_refresh_rate(window.current_monitor().and_then(|monitor| {
+            monitor.refresh_rate_millihertz()
+        }));
+        self.gui_state.options.set_monitors(window.available_monitors());
+        self.spawn.unwrap_or(SPAWN).apply(&mut self.camera);
+        if let Some(permalink) = self.permalink.take() {
+            match permalink.apply(&mut self.art_objects, &mut self.camera) {
+                Ok(()) => self.gui_state.pin_art(&permalink.art),
+                Err(err) => log::error!("failed to open the view link: {err:#}"),
+            }
+        }
+        if let Some(wallpaper) = self.wallpaper.as_ref() {
+            self.gui_state.toggle_open();
+            if wallpaper.layer == WallpaperLayer::Overlay {
+                if let Err(err) = window.set_cursor_hittest(false) {
+                    log::error!("overlay can not ignore the mouse: {err}");
+                }
+            }
+            wallpaper.setup_scene(&mut self.art_objects, &mut self.camera);
+            if wallpaper.exhibit.is_some() {
+                vk_app.set_environment_visible(false);
+            }
+        }
         self.app = Some((window, vk_app, gui));
         self.swapchain_dirty = true;
-        self.camera.position = START_POSITION;
         self.box_idx = self.art_objects.iter().position(|art| art.name == "Portalbox");
         self.mirror_idx = self.art_objects.iter().position(|art| art.name == "Mirror");
+        self.settings = Settings::load(Path::new(SETTINGS_PATH))
+            .inspect_err(|err| log::error!("failed to load settings: {err:#}"))
+            .unwrap_or_default();
+        self.gui_state.options.movement = self.settings.movement;
+        self.gui_state.options.reduced_motion = self.settings.reduced_motion;
+        self.avatar = Avatar::new(self.settings.avatar.clone());
+        match self.avatar.apply(&mut self.art_objects) {
+            Ok(Some(idx)) => if let Some((_, vk_app, _)) = self.app.as_mut() {
+                vk_app.reload_models(&self.art_objects, &[idx])?;
+            }
+            Ok(None) => (),
+            Err(err) => log::error!("failed to set up the avatar: {err:?}"),
+        }
+        self.model_watcher = ModelWatcher::new(&self.art_objects)
+            .inspect_err(|err| log::error!("failed to watch models: {err:?}"))
+            .ok();
+        self.gallery = GalleryWatcher::new(Path::new(GALLERY_DIR))
+            .inspect_err(|err| log::error!("failed to watch gallery shaders: {err:?}"))
+            .ok();
+        let drivers = || self.art_objects.iter().flat_map(|art| art.bindings.iter().map(|binding| &binding.driver));
+        if drivers().any(|driver| matches!(driver, Driver::AudioBand { .. })) {
+            self.audio_input = AudioInput::start()
+                .inspect_err(|err| log::error!("options bound to the audio input are not driven: {err:#}"))
+                .ok();
+        }
+        if drivers().any(|driver| matches!(driver, Driver::MidiCc { .. })) {
+            self.midi = Midi::connect()
+                .inspect_err(|err| log::error!("options bound to MIDI controllers are not driven: {err:#}"))
+                .ok();
+        }
 
         Ok(())
     }
+
+    /// Applies the settings the layout replaces, the others stay as they are.
+    /// Returns whether the include paths changed, the shaders must then be reloaded.
+    fn apply_layout(&mut self, layout: &Layout) -> bool {
+        if let Some(clear_colors) = layout.clear_colors {
+            self.gui_state.options.clear_colors = clear_colors;
+        }
+        if let Some(interaction) = layout.interaction {
+            self.gui_state.options.interaction = interaction;
+        }
+        if let Some(attract) = layout.attract {
+            self.gui_state.options.attract = attract;
+        }
+        if let Some(color_lut) = layout.color_lut.as_ref() {
+            self.gui_state.options.color_lut = color_lut.display().to_string();
+            self.gui_state.options.apply_color_lut = true;
+        }
+        if let Some(output) = layout.output {
+            self.gui_state.options.output = output;
+        }
+        if let Some(projection) = layout.projection {
+            match projection.validate() {
+                Ok(()) => {
+                    let options = &mut self.gui_state.options;
+                    options.projection = projection;
+                    options.fov = projection.clamp_fov(options.fov);
+                }
+                Err(err) => log::error!("ignoring the projection of the layout: {err:#}"),
+            }
+        }
+        if let Some(transition) = layout.transition.clone() {
+            self.gui_state.options.transition_shader = transition.shader.display().to_string();
+            self.gui_state.options.transition = transition;
+        }
+        self.tour_steps = layout.tour.clone();
+        self.spawn = layout.spawn;
+        set_bindings(&layout.bindings, &mut self.art_objects);
+        set_triggers(&layout.triggers, &mut self.art_objects);
+        set_timeline(&layout.timeline, &mut self.art_objects);
+        set_uniform_values(&layout.uniform_values, &mut self.art_objects);
+        self.apply_groups(&layout.groups);
+        set_include_paths(layout.include_paths.clone())
+    }
+
+    /// Replaces the groups of the exhibits named in `groups`, the ones replaced by the previous layout are restored.
+    fn apply_groups(&mut self, groups: &BTreeMap<String, Vec<String>>) {
+        for (name, default) in self.default_groups.drain() {
+            if let Some(art) = self.art_objects.iter_mut().find(|art| art.name == name) {
+                art.groups = default;
+            }
+        }
+        for (name, groups) in groups {
+            match self.art_objects.iter_mut().find(|art| art.name == *name) {
+                Some(art) => {
+                    let default = std::mem::replace(&mut art.groups, groups.clone());
+                    self.default_groups.insert(name.clone(), default);
+                }
+                None => log::warn!("groups for {name}, which does not exist"),
+            }
+        }
+        let groups = self.art_objects.iter().flat_map(|art| art.groups.iter().map(String::as_str));
+        self.gui_state.options.set_groups(groups);
+    }
+
+    /// Replaces the gallery with the layout of the scene selected in the options,
+    /// the exhibits stay where they are. The selection is reset if the scene fails to load.
+    fn switch_scene(&mut self) {
+        let name = self.gui_state.options.scene.clone();
+        let layout = match self.layout.as_ref() {
+            Some(layout) if name == self.layout_scene => Ok(layout.clone()),
+            _ => Layout::load_scene(&name),
+        };
+        let model = layout.and_then(|layout| Ok((layout.generate().normalize()?, layout)));
+        let result = match (model, self.app.as_mut()) {
+            (Ok((model, layout)), Some((_, vk_app, _))) => {
+                vk_app.set_environment_model(&model).map(|()| (model, layout))
+            }
+            (model, _) => model,
+        };
+        let (model, layout) = match result {
+            Ok(scene) => scene,
+            Err(err) => {
+                log::error!("failed to switch to the scene {name}: {err:#}");
+                self.gui_state.options.scene = self.scene.clone();
+                return;
+            }
+        };
+        log::info!("switching to the scene {name}");
+        self.scene = name;
+        let include_paths_changed = self.apply_layout(&layout);
+        let spawn = self.spawn.unwrap_or(SPAWN);
+        #[cfg(feature = "physics")]
+        if self.art_objects.iter().any(|art| art.prop.is_some()) {
+            self.physics = Physics::new(&model, &self.art_objects, spawn.position)
+                .inspect_err(|err| log::error!("props are not simulated: {err:#}"))
+                .ok();
+        }
+        #[cfg(feature = "audio")]
+        if let Some(audio) = self.audio.as_mut() {
+            if let Err(err) = audio.set_ambient(layout.ambient.as_deref()) {
+                log::error!("failed to play the ambient sound of the scene: {err:#}");
+            }
+        }
+        if let Some((_, vk_app, _)) = self.app.as_mut() {
+            if include_paths_changed {
+                vk_app.reload_shaders();
+            }
+        }
+        spawn.apply(&mut self.camera);
+        self.transition_pending = true;
+    }
+
+    /// Writes the animations of the exhibits to the layout of the current scene and returns its path.
+    fn save_timeline(&mut self) -> anyhow::Result<PathBuf> {
+        let mut layout = match self.layout.as_ref() {
+            Some(layout) if self.scene == self.layout_scene => layout.clone(),
+            _ => Layout::load_scene(&self.scene)?,
+        };
+        layout.timeline = timeline(&self.art_objects);
+        let path = layout.save_scene(&self.scene)?;
+        if self.scene == self.layout_scene {
+            self.layout = Some(layout);
+        }
+        Ok(path)
+    }
+
+    fn save_settings(&mut self) {
+        self.settings_changed = None;
+        if let Err(err) = self.settings.save(Path::new(SETTINGS_PATH)) {
+            log::error!("failed to save settings: {err:#}");
+        }
+    }
+
+    /// Lets the shaders of the nearest shown exhibits compile first when many change at once,
+    /// the shaders of hidden and disabled ones last. Shared shaders follow their nearest exhibit.
+    fn update_shader_priorities(&self) {
+        let mut priorities = HashMap::<*const HotShader, (&Arc<HotShader>, f32)>::new();
+        for art in &self.art_objects {
+            let shown = art.enable_pipeline && !art.hidden;
+            let priority = if shown { art.data.dist_to_camera_sqr } else { f32::INFINITY };
+            let compare = art.compare.as_ref().map(|compare| &compare.shader_frag);
+            for shader in [&art.shader_vert, &art.shader_frag].into_iter().chain(compare) {
+                let entry = priorities.entry(Arc::as_ptr(shader)).or_insert((shader, priority));
+                entry.1 = entry.1.min(priority);
+            }
+        }
+        for (shader, priority) in priorities.into_values() {
+            shader.set_priority(priority);
+        }
+    }
+
+    /// Shows the exhibit in the center of the screen full screen or returns to the gallery.
+    fn toggle_fullscreen_art(&mut self) {
+        if self.fullscreen_art.take().is_some() {
+            self.transition_pending = true;
+            return;
+        }
+        match self.looked_at.and_then(|idx| self.art_objects.get(idx).map(|art| (idx, art))) {
+            Some((idx, art)) if art.can_fullscreen() => {
+                self.fullscreen_art = Some(idx);
+                self.transition_pending = true;
+            }
+            Some((_, art)) => log::info!("{} is not flat and can not be shown full screen", art.name),
+            None => {}
+        }
+    }
+
+    /// Saves the camera pose to the bookmark `slot` while Ctrl is held, else moves the camera to it.
+    fn bookmark(&mut self, slot: usize) {
+        if self.modifiers.control_key() {
+            if std::mem::take(&mut self.ctrl_toggled_fly) {
+                self.camera.fly_mode = !self.camera.fly_mode;
+            }
+            self.settings.bookmarks[slot - 1] = Some(Bookmark::from_camera(&self.camera));
+            self.save_settings();
+            log::info!("saved bookmark {slot}");
+        } else if let Some(bookmark) = self.settings.bookmarks[slot - 1] {
+            bookmark.apply(&mut self.camera);
+            self.transition_pending = true;
+            leave_portals(&mut self.art_objects);
+        }
+    }
+}
+
+/// Shows the inside of the portal the camera is in on the box at `box_idx` and hides everything
+/// behind it, then turns off the disabled exhibits. Galleries without a box have no portals.
+fn update_portal(art_objects: &mut [ArtObject], box_idx: Option<usize>) {
+    if let (Some(box_idx), Some(portal_idx))
+        = (box_idx, art_objects.iter().position(|art| art.data.inside_portal))
+    {
+        let portal_dist = art_objects[portal_idx].data.dist_to_camera_sqr;
+        for art in art_objects.iter_mut() {
+            art.enable_pipeline = art.data.dist_to_camera_sqr > portal_dist;
+        }
+
+        let portal = &art_objects[portal_idx];
+        let (d, vs, fs) = (portal.data, portal.shader_vert.clone(), portal.shader_frag.clone());
+        let box_obj = &mut art_objects[box_idx];
+        box_obj.enable_pipeline = true;
+        box_obj.data.matrix = d.matrix;
+        box_obj.data.option_values = d.option_values;
+        box_obj.data.option_values[1][3] = 1.;
+        box_obj.shader_vert = vs;
+        box_obj.shader_frag = fs;
+    } else {
+        for art in art_objects.iter_mut() {
+            art.enable_pipeline = true;
+        }
+        if let Some(box_idx) = box_idx {
+            art_objects[box_idx].enable_pipeline = false;
+        }
+    }
+    for art in art_objects.iter_mut().filter(|art| art.disabled) {
+        art.enable_pipeline = false;
+    }
+}
+
+/// The bookmark slot of the digit keys 1 to 9.
+fn bookmark_slot(key: KeyCode) -> Option<usize> {
+    let keys = [
+        KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3,
+        KeyCode::Digit4, KeyCode::Digit5, KeyCode::Digit6,
+        KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    ];
+    keys.iter().position(|&digit| digit == key).map(|idx| idx + 1)
 }
 
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        if let Err(err) = self.init(event_loop) {
-            log::error!("Error while starting: {err:?}");
-            event_loop.exit();
+        if self.app.is_some() || self.loading_window.is_some() {
+            return;
+        }
+        let mut window_attrs = Window::default_attributes()
+            .with_title(TITLE)
+            .with_inner_size(LogicalSize::new(WIDTH, HEIGHT));
+        if let Some(wallpaper) = self.wallpaper.as_ref() {
+            window_attrs = wallpaper.window_attributes(window_attrs, event_loop);
+        }
+        match event_loop.create_window(window_attrs) {
+            Ok(window) => {
+                self.loading_window = Some(Arc::new(window));
+                self.load_scene(event_loop);
+            }
+            Err(err) => {
+                log::error!("Failed to create window: {err:?}");
+                event_loop.exit();
+            }
         }
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
-        let Some((window, _, gui)) = self.app.as_mut() else { return };
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, window_id: WindowId, event: WindowEvent) {
+        if matches!(event, WindowEvent::KeyboardInput { .. } | WindowEvent::MouseInput { .. }
+            | WindowEvent::MouseWheel { .. } | WindowEvent::CursorMoved { .. } | WindowEvent::Touch(_))
+        {
+            self.attract.input();
+            if let Some(stats) = self.stats.as_mut() {
+                stats.input();
+            }
+        }
+        if let Some(second) = self.second_window.as_mut().filter(|second| second.target.id() == window_id) {
+            if let Some(gui) = second.gui.as_mut() {
+                gui.update(&event);
+            }
+            match event {
+                // closed in `about_to_wait`
+                WindowEvent::CloseRequested => self.gui_state.options.second_window = SecondWindowMode::Off,
+                WindowEvent::Resized { .. } | WindowEvent::ScaleFactorChanged { .. } => second.target.dirty = true,
+                _ => {}
+            }
+            return;
+        }
+        let Some((window, _, gui)) = self.app.as_mut() else {
+            if let WindowEvent::CloseRequested = event {
+                event_loop.exit();
+            }
+            return;
+        };
         if gui.update(&event) {
             return;
         }
@@ -110,6 +588,7 @@ impl ApplicationHandler for App {
         match event {
             WindowEvent::Resized { .. } => {
                 self.swapchain_dirty = true;
+                self.last_resize = Some(Instant::now());
             }
             WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
                 event:
@@ -134,6 +613,7 @@ impl ApplicationHandler for App {
                 ..
             } => {
                 let pressed = state.is_pressed();
+                self.keyboard.key_event(physical_key_code, pressed);
                 match physical_key_code {
                     KeyCode::KeyW => self.key_states.forward = pressed,
                     KeyCode::KeyA => self.key_states.left = pressed,
@@ -141,43 +621,90 @@ impl ApplicationHandler for App {
                     KeyCode::KeyD => self.key_states.right = pressed,
                     KeyCode::Space => self.key_states.up = pressed,
                     KeyCode::ShiftLeft => self.key_states.down = pressed,
-                    KeyCode::ControlLeft if pressed => self.camera.fly_mode = !self.camera.fly_mode,
+                    KeyCode::Tab => self.key_states.sprint = pressed,
+                    KeyCode::KeyC => self.key_states.crouch = pressed,
+                    KeyCode::KeyE if pressed => {
+                        if let Some(art) = self.looked_at.and_then(|idx| self.art_objects.get(idx)) {
+                            self.gui_state.pin_art(&art.name);
+                        }
+                    }
+                    KeyCode::KeyF if pressed => {
+                        if let Some(art) = self.looked_at.and_then(|idx| self.art_objects.get(idx)) {
+                            self.camera.view_target(art.position());
+                            self.transition_pending = true;
+                        }
+                    }
+                    // undone if Ctrl is used for a bookmark while held, see `bookmark`
+                    KeyCode::ControlLeft => {
+                        if pressed {
+                            self.camera.fly_mode = !self.camera.fly_mode;
+                        }
+                        self.ctrl_toggled_fly = pressed;
+                    }
                     KeyCode::F1 if pressed => {
                         if self.is_fullscreen {
                             window.set_fullscreen(None);
                         } else {
-                            window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+                            window.set_fullscreen(Some(self.gui_state.options.fullscreen()));
                         }
                         self.is_fullscreen = !self.is_fullscreen;
                     }
+                    KeyCode::KeyV if pressed => self.toggle_fullscreen_art(),
+                    KeyCode::KeyT if pressed => {
+                        let action = if self.tour.is_some() { TourAction::End } else { TourAction::Start };
+                        action.apply(&mut self.tour, &self.tour_steps, &self.art_objects);
+                    }
+                    KeyCode::ArrowLeft if pressed => {
+                        TourAction::Previous.apply(&mut self.tour, &self.tour_steps, &self.art_objects);
+                    }
+                    KeyCode::ArrowRight if pressed => {
+                        TourAction::Next.apply(&mut self.tour, &self.tour_steps, &self.art_objects);
+                    }
                     KeyCode::F2 if pressed => self.gui_state.toggle_open(),
+                    KeyCode::F3 if pressed => self.gui_state.toggle_browser(),
+                    KeyCode::F4 if pressed => self.gui_state.toggle_timeline(),
+                    KeyCode::F5 if pressed => self.gui_state.options.reload_shaders = true,
+                    KeyCode::F6 if pressed => self.gui_state.toggle_shader_stats(),
+                    KeyCode::F7 if pressed => self.gui_state.toggle_printf(),
+                    KeyCode::F8 if pressed => self.gui_state.options.cycle_present_mode(),
                     _ => {}
                 }
+                if let Some(slot) = bookmark_slot(physical_key_code).filter(|_| pressed) {
+                    self.bookmark(slot);
+                }
                 match (logical_key.as_ref(), pressed) {
                     (Key::Character("l"), true) => {
-                        self.camera.angle_yaw = 0.;
-                        self.camera.angle_pitch = 0.;
-                        self.camera.position = START_POSITION;
+                        self.spawn.unwrap_or(SPAWN).apply(&mut self.camera);
                         self.scroll_lines = 0.0;
-                        for art_obj in self.art_objects.iter_mut() {
-                            art_obj.data.inside_portal = false;
-                        }
+                        self.transition_pending = true;
+                        leave_portals(&mut self.art_objects);
                     }
                     _ => {}
                 }
             }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
             WindowEvent::MouseInput { button: MouseButton::Left, state, .. } => {
                 self.key_states.lmb = state == ElementState::Pressed;
             }
             WindowEvent::CursorMoved { position, .. } => {
-                let new_pos: (i32, i32) = position.into();
+                let new_pos: LogicalPosition<f32> = position.to_logical(window.scale_factor());
                 if self.key_states.lmb {
                     if let Some(old_pos) = self.cursor_position {
-                        self.cursor_delta[0] += new_pos.0 - old_pos[0];
-                        self.cursor_delta[1] += new_pos.1 - old_pos[1];
+                        self.cursor_delta[0] += new_pos.x - old_pos[0];
+                        self.cursor_delta[1] += new_pos.y - old_pos[1];
                     }
                 }
-                self.cursor_position = Some([new_pos.0, new_pos.1]);
+                self.cursor_position = Some([new_pos.x, new_pos.y]);
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor_position = None;
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // egui-winit already updated the scale of the GUI, the new size follows as resize
+                log::info!("scale factor changed to {scale_factor}");
+                self.swapchain_dirty = true;
             }
             WindowEvent::MouseWheel {
                 delta: MouseScrollDelta::LineDelta(_, v_lines),
@@ -194,7 +721,13 @@ impl ApplicationHandler for App {
             return;
         }
 
-        let (window, vk_app, gui) = self.app.as_mut().unwrap();
+        if self.app.is_some() && self.gui_state.options.scene != self.scene {
+            self.switch_scene();
+        }
+        let Some((window, vk_app, gui)) = self.app.as_mut() else {
+            self.load_scene(event_loop);
+            return;
+        };
 
         // update fps info
         let now = Instant::now();
@@ -204,58 +737,317 @@ impl ApplicationHandler for App {
             frame_count: 0,
         });
         let elapsed = elapsed_dur.unwrap_or_default().as_secs_f32();
-        self.time += elapsed;
+        let snapped = self.frame_clock.tick(elapsed);
+        let mut time_step = if self.gui_state.options.snap_time { snapped } else { elapsed };
+        if self.gui_state.options.reduced_motion {
+            time_step = (time_step * REDUCED_MOTION_TIME_SCALE).min(REDUCED_MOTION_MAX_STEP);
+        }
+        self.time += time_step;
         fps_info.last_frame = now;
         fps_info.frame_count += 1;
 
-        // recreate swapchain if needed
+        if self.gui_state.options.apply_fullscreen {
+            self.gui_state.options.apply_fullscreen = false;
+            if self.is_fullscreen {
+                window.set_fullscreen(Some(self.gui_state.options.fullscreen()));
+            }
+        }
+
+        self.gui_state.options.auto_quality.update(elapsed);
+        let quality = self.gui_state.options.auto_quality.level();
+        if vk_app.set_render_scale(quality.render_scale) {
+            self.gui_state.options.recreate_swapchain = true;
+        }
+
+        // recreate swapchain if needed, while the window is resized only the swapchain is
+        // recreated and the frame is scaled to it until the size did not change for a while
         let extent = window.inner_size();
-        if self.swapchain_dirty || self.gui_state.options.recreate_swapchain {
+        let resize_settled = self.last_resize.is_some_and(|time| time.elapsed() >= RESIZE_DEBOUNCE);
+        if self.swapchain_dirty || resize_settled || self.gui_state.options.recreate_swapchain {
             if extent.width == 0 || extent.height == 0 {
                 return;
             }
-            self.gui_state.options.recreate_swapchain = false;
-            if let Err(err) = vk_app.recreate_swapchain(extent, &self.gui_state.options) {
+            let result = if self.last_resize.is_some() && !resize_settled
+                && !self.gui_state.options.recreate_swapchain
+            {
+                vk_app.resize_swapchain(extent, &self.gui_state.options)
+            } else {
+                self.last_resize = None;
+                self.gui_state.options.recreate_swapchain = false;
+                vk_app.recreate_swapchain(extent, &self.gui_state.options)
+            };
+            if let Err(err) = result {
                 log::error!("error while recreating swapchain, exiting: {err:?}");
                 event_loop.exit();
                 return;
             }
+            self.gui_state.options.actual_image_count = vk_app.image_count();
+            self.frame_clock.set_refresh_rate(window.current_monitor().and_then(|monitor| {
+                monitor.refresh_rate_millihertz()
+            }));
+        }
+
+        let second_mode = self.second_window.as_ref().map_or(SecondWindowMode::Off, SecondWindow::mode);
+        if self.gui_state.options.second_window != second_mode {
+            if self.second_window.take().is_some() {
+                if let Err(err) = vk_app.set_second_view(false) {
+                    log::error!("failed to disable the second view: {err:?}");
+                }
+            }
+            let mode = self.gui_state.options.second_window;
+            if mode != SecondWindowMode::Off {
+                let attributes = Window::default_attributes()
+                    .with_title(format!("{TITLE} - {}", mode.label()))
+                    .with_inner_size(LogicalSize::new(WIDTH, HEIGHT));
+                match SecondWindow::open(mode, attributes, event_loop, vk_app, self.camera.view_matrix()) {
+                    Ok(second) => self.second_window = Some(second),
+                    Err(err) => {
+                        log::error!("failed to open second window: {err:?}");
+                        self.gui_state.options.second_window = SecondWindowMode::Off;
+                    }
+                }
+            }
         }
 
-        // setup nearest_art options
+        if self.gui_state.options.reload_shaders {
+            self.gui_state.options.reload_shaders = false;
+            vk_app.reload_shaders();
+        }
+
+        if let Some(watcher) = self.model_watcher.as_ref() {
+            let changed = watcher.changed().into_iter().map(Path::to_path_buf).collect::<Vec<_>>();
+            let reloaded = reload_models(&mut self.art_objects, changed.iter().map(PathBuf::as_path));
+            if !reloaded.is_empty() {
+                if let Err(err) = vk_app.reload_models(&self.art_objects, &reloaded) {
+                    log::error!("failed to reload models: {err:?}");
+                }
+            }
+        }
+
+        if let Some(gallery) = self.gallery.as_mut() {
+            if gallery.update(&mut self.art_objects, vk_app) {
+                let groups = self.art_objects.iter().flat_map(|art| art.groups.iter().map(String::as_str));
+                self.gui_state.options.set_groups(groups);
+                self.box_idx = self.art_objects.iter().position(|art| art.name == "Portalbox");
+                self.mirror_idx = self.art_objects.iter().position(|art| art.name == "Mirror");
+                // the shaders of new exhibits are not instrumented yet
+                self.cost_heatmap = false;
+                self.fullscreen_art = None;
+            }
+        }
+
+        if self.gui_state.options.cost_heatmap != self.cost_heatmap {
+            self.cost_heatmap = self.gui_state.options.cost_heatmap;
+            for art in self.art_objects.iter() {
+                let compare = art.compare.as_ref().map(|compare| &compare.shader_frag);
+                for shader in std::iter::once(&art.shader_frag).chain(compare) {
+                    shader.set_cost_heatmap(self.cost_heatmap);
+                }
+            }
+        }
+
+        #[cfg(feature = "remote")]
+        if let Some(remote) = self.remote.as_ref() {
+            let options = &mut self.gui_state.options;
+            remote.handle_requests(
+                &mut self.art_objects,
+                &mut self.camera,
+                vk_app,
+                &mut options.scene,
+                &options.scenes,
+            );
+        }
+
+        let sync_state = self.sync.as_mut().and_then(|sync| sync.receive());
+        if let Some(state) = sync_state.as_ref() {
+            self.time = state.time;
+            self.skybox_rotation_angle = state.skybox_rotation_angle;
+        }
+
+        // find the art whose options are shown
         for art in self.art_objects.iter_mut() {
             let dist = self.camera.position.distance_squared(art.position());
             art.data.dist_to_camera_sqr = dist;
+            art.hidden = !art.visible || self.gui_state.options.is_hidden(&art.groups);
         }
-        let mut nearest_art = self.art_objects.iter_mut()
-            .filter(|art| art.enable_pipeline && !art.options.is_empty()
-                && art.data.dist_to_camera_sqr <= 2.25)
-            .min_by(|a, b| {
+        if self.fullscreen_art.is_some_and(|idx| self.art_objects[idx].hidden || self.art_objects[idx].disabled) {
+            self.fullscreen_art = None;
+        }
+        let interaction = self.gui_state.options.interaction;
+        let nearest_art = self.art_objects.iter()
+            .enumerate()
+            .filter(|(_, art)| art.enable_pipeline && !art.hidden && !art.options.is_empty()
+                && art.data.dist_to_camera_sqr <= interaction.radius * interaction.radius)
+            .min_by(|(_, a), (_, b)| {
                 a.data.dist_to_camera_sqr.total_cmp(&b.data.dist_to_camera_sqr)
-            });
+            })
+            .map(|(idx, _)| idx);
+        for (idx, art) in self.art_objects.iter_mut().enumerate() {
+            art.data.paused = art.data.time.paused
+                || interaction.pause_others && nearest_art.is_some_and(|nearest| nearest != idx);
+            art.data.time_offset += time_step * (1. - art.data.speed());
+        }
+        let selected_art = self.gui_state.pinned_art(&self.art_objects)
+            .or(nearest_art.filter(|_| interaction.auto_open));
+
+        // the options before the GUI, to record changes by the visitor
+        let option_values = selected_art.filter(|_| self.stats.is_some())
+            .map(|idx| (idx, self.art_objects[idx].option_values()));
 
-        // render gui
-        self.gui_state.render(gui, &mut nearest_art, elapsed_dur);
+        // render gui, in the second window if it is detached
+        let detached_gui = self.second_window.as_mut().and_then(|second| second.gui.as_mut());
+        let gui_response = self.gui_state.render(
+            detached_gui.unwrap_or(&mut *gui),
+            &mut self.art_objects,
+            selected_art,
+            self.looked_at.filter(|&idx| idx < self.art_objects.len()),
+            elapsed_dur,
+            self.tour.as_ref(),
+        );
+        if let Some(action) = gui_response.tour {
+            action.apply(&mut self.tour, &self.tour_steps, &self.art_objects);
+        }
+        if gui_response.save_timeline {
+            let status = match self.save_timeline() {
+                Ok(path) => format!("saved to {}", path.display()),
+                Err(err) => {
+                    log::error!("failed to save the timeline: {err:?}");
+                    format!("saving failed: {err:#}")
+                }
+            };
+            self.gui_state.set_timeline_status(status);
+        }
+        if let Some(stats) = self.stats.as_mut() {
+            let art_objects = &self.art_objects;
+            let near = art_objects.iter()
+                .filter(|art| art.enable_pipeline && !art.hidden
+                    && art.data.dist_to_camera_sqr <= interaction.radius * interaction.radius)
+                .map(|art| art.name.as_str());
+            let looked_at = self.looked_at.and_then(|idx| art_objects.get(idx)).map(|art| art.name.as_str());
+            let changed_options = option_values
+                .filter(|(idx, values)| art_objects[*idx].option_values() != *values)
+                .map(|(idx, _)| art_objects[idx].name.as_str());
+            stats.update(elapsed, near, looked_at, changed_options);
+        }
 
         // update camera
+        if let Some(idx) = gui_response.teleport_to {
+            // teleport before remembering the old position so no portal is crossed
+            self.camera.view_target(self.art_objects[idx].position());
+            self.transition_pending = true;
+        }
+        if std::mem::take(&mut self.gui_state.options.open_permalink) {
+            let result = Permalink::from_spec(&self.gui_state.options.permalink).and_then(|permalink| {
+                permalink.apply(&mut self.art_objects, &mut self.camera)?;
+                Ok(permalink)
+            });
+            match result {
+                Ok(permalink) => {
+                    self.gui_state.pin_art(&permalink.art);
+                    self.transition_pending = true;
+                }
+                Err(err) => log::error!("failed to open the view link: {err:#}"),
+            }
+        }
+        if let Some(idx) = gui_response.copy_permalink {
+            let link = Permalink::new(&self.art_objects[idx], &self.camera).to_spec();
+            let detached_gui = self.second_window.as_ref().and_then(|second| second.gui.as_ref());
+            detached_gui.unwrap_or(&*gui).context().copy_text(link.clone());
+            log::info!("copied the view link {link}");
+            self.gui_state.options.permalink = link;
+        }
         let old_position = self.camera.position;
-        let delta = elapsed * (self.scroll_lines * 0.4).exp();
-        let x_ratio = self.cursor_delta[0] as f32 / extent.width as f32;
-        let y_ratio = self.cursor_delta[1] as f32 / extent.height as f32;
-        self.camera.update(&self.key_states, delta, x_ratio, y_ratio);
-        self.cursor_delta = [0, 0];
+        let movement = self.gui_state.options.movement;
+        let reduced_motion = self.gui_state.options.reduced_motion;
+        if movement != self.settings.movement || reduced_motion != self.settings.reduced_motion {
+            self.settings.movement = movement;
+            self.settings.reduced_motion = reduced_motion;
+            self.settings_changed = Some(Instant::now());
+        }
+        if self.settings_changed.is_some_and(|time| time.elapsed() >= SETTINGS_DEBOUNCE) {
+            self.save_settings();
+        }
+        let speed = movement.speed(self.camera.fly_mode, self.key_states.sprint, self.scroll_lines);
+        let logical_extent: LogicalSize<f32> = extent.to_logical(window.scale_factor());
+        let x_ratio = self.cursor_delta[0] / logical_extent.width;
+        let y_ratio = self.cursor_delta[1] / logical_extent.height;
+        // the camera waits in front of the exhibit shown full screen
+        if self.fullscreen_art.is_none() {
+            self.camera.update(&self.key_states, speed, elapsed, x_ratio, y_ratio, &movement.smoothing);
+        }
+        self.cursor_delta = [0., 0.];
+        if self.gui_state.options.record_attract != self.attract.is_recording() {
+            if self.gui_state.options.record_attract {
+                self.attract.start_recording();
+            } else if let Err(err) = self.attract.stop_recording() {
+                log::error!("failed to save the attract path: {err:#}");
+            }
+        }
+        if let Some(tour) = self.tour.as_mut() {
+            // reading the steps is no reason to start the attract mode
+            self.attract.input();
+            tour.update(elapsed, &self.art_objects, &mut self.camera);
+        }
+        self.attract.update(elapsed, self.gui_state.options.attract, &mut self.art_objects, &mut self.camera);
+        if let Some(state) = sync_state.as_ref() {
+            self.camera.position = state.position.into();
+            self.camera.angle_yaw = state.angle_yaw;
+            self.camera.angle_pitch = state.angle_pitch;
+        }
         vk_app.view_matrix = self.camera.view_matrix();
+        #[cfg(feature = "audio")]
+        if let Some(audio) = self.audio.as_mut() {
+            let options = &self.gui_state.options;
+            audio.update_shaders(vk_app, self.time);
+            audio.update(&self.camera, &self.art_objects, options.ambient_volume, options.effects_volume);
+        }
 
-        // update options data for nearest_art
-        if let Some(art) = nearest_art.as_mut() {
+        // update options data for the selected art
+        if let Some(art) = selected_art.map(|idx| &mut self.art_objects[idx]) {
             art.save_options();
         }
+        let inputs = DriverInputs {
+            time: self.time,
+            audio_bands: self.audio_input.as_ref().map(AudioInput::bands).unwrap_or_default(),
+            midi_cc: self.midi.as_ref().map(Midi::controllers).unwrap_or_default(),
+        };
+        for art in self.art_objects.iter_mut() {
+            // a transition started in the GUI finishes even when the exhibit is no longer selected
+            if art.update_presets(elapsed) {
+                art.save_options();
+            }
+            // paused exhibits keep their options like their time
+            art.update_randomizer(elapsed * art.data.speed());
+            if art.data.speed() > 0. {
+                art.update_animation(elapsed * art.data.speed());
+            }
+            if art.apply_bindings(&inputs) {
+                art.save_options();
+            }
+        }
+        if let Some(state) = sync_state {
+            for art in self.art_objects.iter_mut() {
+                if let Some(values) = state.options.get(&art.name) {
+                    art.load_option_values(values);
+                    art.save_options();
+                }
+            }
+        }
 
         // update data for all art
         if self.gui_state.options.sun_movement {
             self.skybox_rotation_angle += elapsed * self.gui_state.options.sun_speed;
         }
-        let light_pos = Mat4::from_rotation_y(self.skybox_rotation_angle) * Vec4::splat(100.);
+        let sky = self.gui_state.options.sky;
+        let light_pos = if sky.physical && sky.day_cycle {
+            // rise at +x, culminate tilted towards +z and set at -x
+            let sun_dir = Quat::from_rotation_x(0.4)
+                * Quat::from_rotation_z(self.skybox_rotation_angle)
+                * Vec3::X;
+            (sun_dir * 100.).extend(1.)
+        } else {
+            Mat4::from_rotation_y(self.skybox_rotation_angle) * Vec4::splat(100.)
+        };
         for art in self.art_objects.iter_mut() {
             art.data.light_pos = light_pos;
             if let Some(fn_update_data) = art.fn_update_data.as_ref() {
@@ -263,44 +1055,128 @@ impl ApplicationHandler for App {
                     skybox_rotation_angle: self.skybox_rotation_angle,
                     old_position,
                     new_position: self.camera.position,
-                    camera: self.camera,
+                    sky,
                 });
             }
         }
 
-        // handle portal
-        if let (Some(box_idx), Some(portal_idx))
-            = (self.box_idx, self.art_objects.iter().position(|art| art.data.inside_portal))
-        {
-            let portal_dist = self.art_objects[portal_idx].data.dist_to_camera_sqr;
-            for art in self.art_objects.iter_mut() {
-                art.enable_pipeline = art.data.dist_to_camera_sqr > portal_dist;
-            }
-
-            let portal = &self.art_objects[portal_idx];
-            let (d, vs, fs) = (portal.data, portal.shader_vert.clone(), portal.shader_frag.clone());
-            let box_obj = &mut self.art_objects[box_idx];
-            box_obj.enable_pipeline = true;
-            box_obj.data.matrix = d.matrix;
-            box_obj.data.option_values = d.option_values;
-            box_obj.data.option_values[1][3] = 1.;
-            box_obj.shader_vert = vs;
-            box_obj.shader_frag = fs;
-        } else {
-            for art in self.art_objects.iter_mut() {
-                art.enable_pipeline = true;
+        self.avatar.update(&mut self.art_objects, &self.camera, elapsed);
+        resolve_parents(&mut self.art_objects, &self.camera);
+        update_triggers(&mut self.art_objects, &mut self.camera, old_position);
+        if let Some(physics) = self.physics.as_mut() {
+            physics.update(elapsed, self.camera.position, &mut self.art_objects);
+        }
+
+        update_portal(&mut self.art_objects, self.box_idx);
+        if let Some(on_frame) = self.on_frame.as_mut() {
+            on_frame(&mut self.art_objects, &mut self.camera, self.time);
+            vk_app.view_matrix = self.camera.view_matrix();
+        }
+        self.update_shader_priorities();
+
+        // pass the hit point of the cursor to the hovered art
+        // and find the art in the center of the screen for the HUD
+        let screen = [logical_extent.width, logical_extent.height];
+        let fov = self.gui_state.options.fov;
+        let cursor = self.cursor_position.filter(|_| !gui.context().is_pointer_over_area());
+        let fullscreen = self.fullscreen_art.map(|idx| {
+            let art = &self.art_objects[idx];
+            (art.data.matrix, FullscreenMapping::new(art.bounds(), screen[0] / screen[1]))
+        });
+        let ray = cursor.map(|cursor| match fullscreen.as_ref() {
+            Some((matrix, mapping)) => mapping.cursor_ray(*matrix, cursor, screen),
+            None => self.camera.cursor_ray(cursor, screen, fov),
+        });
+        let center_ray = self.camera.cursor_ray(screen.map(|len| len / 2.), screen, fov);
+        let mut hovered = None;
+        let mut looked_at = None;
+        for (idx, art) in self.art_objects.iter_mut().enumerate() {
+            art.data.mouse = Vec4::ZERO;
+            if !art.enable_pipeline || art.hidden || self.fullscreen_art.is_some_and(|full| full != idx) {
+                continue;
+            }
+            if let Some((dist, hit)) = ray.as_ref().and_then(|ray| art.intersect(ray)) {
+                if hovered.is_none_or(|(_, nearest, _)| dist < nearest) {
+                    hovered = Some((idx, dist, hit));
+                }
             }
-            self.art_objects[self.box_idx.unwrap()].enable_pipeline = false;
+            if let Some((dist, _)) = art.intersect(&center_ray) {
+                if looked_at.is_none_or(|(_, nearest)| dist < nearest) {
+                    looked_at = Some((idx, dist));
+                }
+            }
+        }
+        if let Some((idx, _, hit)) = hovered {
+            self.art_objects[idx].data.mouse = hit.extend(if self.key_states.lmb { 2. } else { 1. });
         }
+        self.looked_at = self.fullscreen_art.or(looked_at.map(|(idx, _)| idx));
 
         // handle mirror
         if let Some(mirror_idx) = self.mirror_idx {
             vk_app.mirror_matrix = self.art_objects[mirror_idx].data.matrix;
         }
 
+        if let Some(sync) = self.sync.as_mut() {
+            sync.send(|| SyncState {
+                time: self.time,
+                skybox_rotation_angle: self.skybox_rotation_angle,
+                position: self.camera.position.to_array(),
+                angle_yaw: self.camera.angle_yaw,
+                angle_pitch: self.camera.angle_pitch,
+                options: self.art_objects.iter()
+                    .filter(|art| !art.options.is_empty())
+                    .map(|art| (art.name.clone(), art.option_values()))
+                    .collect(),
+            });
+        }
+
         // draw and remember if swapchain is dirty
         vk_app.fov = self.gui_state.options.fov;
-        self.swapchain_dirty = match vk_app.draw(self.time, Some(gui), &self.art_objects) {
+        vk_app.near = self.gui_state.options.projection.near;
+        vk_app.far = self.gui_state.options.projection.far;
+        vk_app.ipd = self.gui_state.options.ipd;
+        if self.gui_state.options.stereo != vk_app.stereo_mode() {
+            if let Err(err) = vk_app.set_stereo(self.gui_state.options.stereo) {
+                log::error!("failed to switch stereo mode: {err:?}");
+                self.gui_state.options.stereo = vk_app.stereo_mode();
+            }
+        }
+        if std::mem::take(&mut self.gui_state.options.apply_color_lut) {
+            let path = self.gui_state.options.color_lut.trim();
+            if let Err(err) = vk_app.set_color_lut((!path.is_empty()).then(|| Path::new(path))) {
+                log::error!("failed to load color lookup table: {err:?}");
+            }
+        }
+        vk_app.grading_strength = self.gui_state.options.color_grading;
+        vk_app.reduced_motion = self.gui_state.options.reduced_motion;
+        vk_app.detail = quality.detail;
+        vk_app.set_fullscreen_art(self.fullscreen_art);
+        if self.gui_state.options.output != vk_app.output() {
+            if let Err(err) = vk_app.set_output(self.gui_state.options.output) {
+                log::error!("failed to adjust the output: {err:?}");
+                self.gui_state.options.output = vk_app.output();
+            }
+        }
+        if self.gui_state.options.transition != *vk_app.transition() {
+            if let Err(err) = vk_app.set_transition(&self.gui_state.options.transition) {
+                log::error!("failed to set the transition: {err:?}");
+                self.gui_state.options.transition = vk_app.transition().clone();
+            }
+        }
+        if std::mem::take(&mut self.transition_pending) {
+            vk_app.start_transition();
+        }
+        let options = &self.gui_state.options;
+        vk_app.set_clear_colors(if options.debug_clear { ClearColors::DEBUG } else { options.clear_colors });
+        if let Some(image) = self.keyboard.take_image() {
+            vk_app.set_keyboard(image);
+        }
+        let detached = self.second_window.as_ref().is_some_and(|second| second.gui.is_some());
+        let second_view = self.second_window.as_mut()
+            .filter(|second| second.gui.is_none())
+            .map(|second| (&mut second.target, second.view_matrix));
+        let main_gui = if detached { None } else { Some(gui) };
+        self.swapchain_dirty = match vk_app.draw(self.time, main_gui, &self.art_objects, second_view) {
             Ok(swapchain_dirty) => swapchain_dirty,
             Err(err) => {
                 log::error!("error while drawing, exiting: {err:?}");
@@ -308,9 +1184,40 @@ impl ApplicationHandler for App {
                 false
             }
         };
+        if let Some(SecondWindow { target, gui: Some(gui), .. }) = self.second_window.as_mut() {
+            if let Err(err) = target.draw_gui(gui, vk_app.get_queue()) {
+                log::error!("failed to draw the detached GUI: {err:?}");
+            }
+        }
+        // turned on again in the exhibit browser once the shader is fixed
+        for art_idx in vk_app.take_runaway_exhibits() {
+            self.art_objects[art_idx].disabled = true;
+        }
+        self.gui_state.push_cpu_timings(vk_app.cpu_timings());
+        self.keyboard.end_frame();
     }
 
     fn exiting(&mut self, _: &ActiveEventLoop) {
-        // nothing
+        if let Some(stats) = self.stats.as_mut() {
+            stats.end_visit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::Maze;
+
+    #[test]
+    fn maze_frame_without_portalbox() {
+        let mut art_objects = Maze::from_spec("1").unwrap().generate().unwrap().art_objects;
+        let box_idx = art_objects.iter().position(|art| art.name == "Portalbox");
+        assert_eq!(box_idx, None);
+        art_objects[0].data.inside_portal = true;
+        art_objects[1].disabled = true;
+        update_portal(&mut art_objects, box_idx);
+        assert!(art_objects[0].enable_pipeline);
+        assert!(!art_objects[1].enable_pipeline);
     }
 }
@@ -1,49 +1,285 @@
 use crate::{
-    camera::Camera,
+    animation::{Animation, Track},
+    binding::Binding,
+    camera::{Camera, Ray},
+    gui::SkyOptions,
     model::obj::NormalizedObj,
-    vulkan::HotShader,
+    physics::PropShape,
+    preset::Presets,
+    trigger::{Action, Trigger, Volume},
+    uniform_block::{UniformBlock, UniformType},
+    vulkan::{box_contains, HotShader},
 };
 
+use std::borrow::Cow;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use anyhow::{ensure, Context};
 use egui::Color32;
-use glam::{Mat4, Vec3, Vec4};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use image::RgbaImage;
+use shaderc::ShaderKind;
 
 pub type UpdateFunction = dyn Fn(&mut ArtData, &ArtUpdateData);
 
+/// A looped sound played at the position of an exhibit, heard with the `audio` feature.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sound {
+    /// An audio file loaded through `crate::fs`.
+    File(PathBuf),
+    /// A sine tone with the frequency in hertz.
+    Tone(f32),
+    /// An audio shader rendered on the GPU, see `crate::vulkan::HotShader::new_sound`.
+    Shader(PathBuf),
+}
+
+/// Number of vec4s in the uniform buffer for the option values, shaders get it as the
+/// `OPTION_VEC4S` macro and declare `vec4 options[OPTION_VEC4S]` in the uniform buffer.
+pub const OPTION_VEC4S: usize = 8;
+/// Number of floats available for option values in `ArtData::option_values`.
+pub const MAX_OPTION_SLOTS: usize = 4 * OPTION_VEC4S;
+
 pub struct ArtObject {
     pub name: String,
     pub model: Arc<NormalizedObj>,
     pub shader_vert: Arc<HotShader>,
     pub shader_frag: Arc<HotShader>,
-    pub texture: Option<PathBuf>,
+    pub texture: Option<TextureSource>,
+    /// Shown together with the name on a plaque next to the exhibit.
+    pub description: Option<String>,
     pub options: Vec<ArtOption>,
+    /// Extra uniform buffer with named fields that bound options are written to.
+    pub uniform_block: Option<UniformBlock>,
+    pub presets: Presets,
+    pub randomizer: Randomizer,
+    pub compare: Option<Compare>,
+    /// Attaches the exhibit to another node, `data.matrix` is then computed each frame.
+    pub parent: Option<ArtParent>,
     pub data: ArtData,
     pub fn_update_data: Option<Box<UpdateFunction>>,
+    /// Keyframe animation of the transform and options, see `crate::animation`.
+    pub animation: Option<Animation>,
+    /// Options driven by the time or inputs, see `crate::binding`.
+    pub bindings: Vec<Binding>,
+    /// Volumes that run actions when the camera enters or leaves them, see `crate::trigger`.
+    pub triggers: Vec<Trigger>,
+    /// Moved by the physics simulation, see `crate::physics`.
+    pub prop: Option<PropShape>,
     pub enable_pipeline: bool,
+    /// Turned off in the exhibit browser, `enable_pipeline` is then kept false so the shaders are
+    /// neither compiled nor drawn.
+    pub disabled: bool,
     pub enable_depth_test: bool,
+    /// The fragment shader writes the depth of what it hits, see `assets/shaders/includes/depth.glsl`.
+    /// The back faces of the container are drawn, so it is also shown while the camera is inside.
+    pub frag_depth: bool,
+    /// Transparent exhibits are drawn back to front after the opaque ones, so they blend with them.
+    /// They do not write depth unless they write `gl_FragDepth`, see `frag_depth`.
+    pub is_transparent: bool,
+    /// Renders the exhibit into a cache this many times per second, which is drawn every frame.
+    /// The cache is also refreshed when the view changes, so only still views are faster.
+    pub refresh_rate: Option<f32>,
     pub container_scale: Vec3,
+    /// The model is centered and scaled to fit the unit container when it is set, so models in any
+    /// units can be shown. `container_scale` is applied afterwards.
+    pub auto_fit: bool,
     pub is_mirror: bool,
+    /// Passes the exhibit is drawn in, the avatar is usually only seen in the mirror.
+    pub passes: Passes,
+    /// Names of the groups the exhibit belongs to, groups can be hidden in the GUI.
+    pub groups: Vec<String>,
+    pub sound: Option<Sound>,
+    /// Silences the sound, set by trigger actions.
+    pub sound_muted: bool,
+    /// Can be unchecked in the exhibit browser to hide only this exhibit.
+    pub visible: bool,
+    /// Set each frame if the exhibit or one of its groups is hidden.
+    pub hidden: bool,
+    /// Minimum and maximum corner of the scaled model in local space, used to hit it with the cursor.
+    bounds: [Vec3; 2],
 }
 
 impl ArtObject {
+    /// Starts building an exhibit, see `ArtObjectBuilder`.
+    pub fn builder() -> ArtObjectBuilder {
+        ArtObjectBuilder::default()
+    }
+
     pub fn position(&self) -> Vec3 {
         self.data.position()
     }
 
+    /// Replaces the model, e.g. after its file changed. The GPU buffers must be recreated.
+    pub fn set_model(&mut self, model: Arc<NormalizedObj>) {
+        self.model = if self.auto_fit { Arc::new(model.fitted()) } else { model };
+        self.update_bounds();
+    }
+
+    fn update_bounds(&mut self) {
+        let positions = self.model.vertices.iter().map(|vertex| self.container_scale * Vec3::from(vertex.pos_coords));
+        self.bounds = positions.fold([Vec3::MAX, Vec3::MIN], |[min, max], pos| [min.min(pos), max.max(pos)]);
+    }
+
+    /// Minimum and maximum corner of the scaled model in local space.
+    pub fn bounds(&self) -> [Vec3; 2] {
+        self.bounds
+    }
+
+    /// Whether the model lies in the x-y-plane like the paintings, which can be shown full screen,
+    /// see `crate::camera::FullscreenMapping`. The mirror is excluded as its reflection needs the scene.
+    pub fn can_fullscreen(&self) -> bool {
+        self.bounds[0].z == self.bounds[1].z && self.bounds[0].x < self.bounds[1].x
+            && self.bounds[0].y < self.bounds[1].y && !self.is_mirror
+    }
+
+    /// Whether `point` in world space is inside the container expanded by `margin` in world units.
+    pub fn contains(&self, point: Vec3, margin: f32) -> bool {
+        box_contains(self.data.matrix, self.bounds[0], self.bounds[1], point, margin)
+    }
+
+    /// Returns the distance along `ray` in world space and the local hit point where it enters the
+    /// bounding box of the model, the container the shader draws in.
+    pub fn intersect(&self, ray: &Ray) -> Option<(f32, Vec3)> {
+        let local_ray = ray.transform(self.data.matrix.inverse());
+        let t = local_ray.intersect_box(self.bounds[0], self.bounds[1])?;
+        let hit = local_ray.at(t);
+        Some((ray.origin.distance(self.data.matrix.transform_point3(hit)), hit))
+    }
+
+    /// The name with everything except ascii alphanumerics replaced, to be used in file names.
+    pub fn file_stem(&self) -> String {
+        self.name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect()
+    }
+
+    /// A shader failed to compile and the last version that did is drawn instead.
+    pub fn is_stale(&self) -> bool {
+        self.shader_vert.is_stale() || self.shader_frag.is_stale()
+    }
+
+    /// The values of all options in the layout of `ArtOptionType::save_value`.
+    pub fn option_values(&self) -> Vec<f32> {
+        let mut values = vec![0.; self.options.iter().map(|option| option.ty.slot_count()).sum()];
+        let mut i = 0;
+        for option in self.options.iter() {
+            option.ty.save_value(&mut values, &mut i);
+        }
+        values
+    }
+
+    /// Sets the values of all options from values created by `option_values`.
+    pub fn load_option_values(&mut self, values: &[f32]) {
+        let mut i = 0;
+        for option in self.options.iter_mut() {
+            if i + option.ty.slot_count() > values.len() {
+                break;
+            }
+            option.ty.load_value(values, &mut i);
+        }
+    }
+
+    /// Advances a running preset transition and applies the interpolated values,
+    /// returns `true` if they changed. The options still have to be saved for the shader to see them.
+    pub fn update_presets(&mut self, elapsed: f32) -> bool {
+        let Some(values) = self.presets.update(elapsed) else { return false };
+        self.load_option_values(&values);
+        true
+    }
+
+    /// Sets all options to random values within their ranges and updates the seed.
+    pub fn randomize_options(&mut self) {
+        let seed = self.randomizer.next_seed();
+        for (i, option) in self.options.iter_mut().enumerate() {
+            option.ty.randomize(seed, i as u32);
+        }
+        self.data.seed = seed as f32;
+    }
+
+    /// Randomizes the options if auto randomize is enabled and the interval passed.
+    /// `elapsed` is scaled by the speed of the exhibit, so it does not advance while paused.
+    pub fn update_randomizer(&mut self, elapsed: f32) {
+        if self.randomizer.update(elapsed) {
+            self.randomize_options();
+            self.save_options();
+        }
+    }
+
+    /// The transform that the animation changes, relative to the parent if there is one.
+    pub fn animated_matrix(&self) -> Mat4 {
+        self.parent.as_ref().map_or(self.data.matrix, |parent| parent.local_matrix)
+    }
+
+    /// Advances the animation and applies its values to the transform and the options.
+    /// The transform of attached exhibits is relative to their parent.
+    pub fn update_animation(&mut self, elapsed: f32) {
+        let Some(animation) = self.animation.as_mut() else { return };
+        if animation.playing {
+            animation.time += elapsed;
+        }
+        let time = animation.time;
+
+        let matrix = match self.parent.as_mut() {
+            Some(parent) => &mut parent.local_matrix,
+            None => &mut self.data.matrix,
+        };
+        let (mut scale, mut rotation, mut translation) = matrix.to_scale_rotation_translation();
+        let mut transform_changed = false;
+        let mut options_changed = false;
+        for track in animation.tracks.iter() {
+            match track {
+                Track::Position(keyframes) => if let Some(value) = keyframes.sample(time) {
+                    translation = value;
+                    transform_changed = true;
+                }
+                Track::Rotation(keyframes) => if let Some(value) = keyframes.sample(time) {
+                    rotation = value;
+                    transform_changed = true;
+                }
+                Track::Scale(keyframes) => if let Some(value) = keyframes.sample(time) {
+                    scale = value;
+                    transform_changed = true;
+                }
+                Track::Option { label, keyframes } => {
+                    let option = self.options.iter_mut().find(|option| option.label() == label);
+                    if let (Some(option), Some(values)) = (option, keyframes.sample(time)) {
+                        if values.len() == option.ty.slot_count() {
+                            option.ty.load_value(&values, &mut 0);
+                            options_changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        if transform_changed {
+            *matrix = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+        }
+        if options_changed {
+            self.save_options();
+        }
+    }
+
     pub fn save_options(&mut self) {
+        if let Some(block) = self.uniform_block.as_mut() {
+            block.write(&self.options);
+        }
         if self.options.is_empty() {
             return;
         }
 
-        let mut values = [0.; 8];
-        let mut i = 0;
-        for option in self.options.iter() {
-            option.ty.save_value(&mut values, &mut i);
+        let values = self.option_values();
+        if values.len() > MAX_OPTION_SLOTS {
+            log::error!(
+                "options of {} need {} values, only the first {MAX_OPTION_SLOTS} reach the shader",
+                self.name,
+                values.len(),
+            );
         }
-        let mut chunks = values.chunks(4).map(Vec4::from_slice);
-        self.data.option_values = [chunks.next().unwrap(), chunks.next().unwrap()];
+        let mut padded = [0.; MAX_OPTION_SLOTS];
+        let len = values.len().min(MAX_OPTION_SLOTS);
+        padded[..len].copy_from_slice(&values[..len]);
+        self.data.option_values = std::array::from_fn(|i| Vec4::from_slice(&padded[i * 4..]));
     }
 }
 
@@ -55,23 +291,476 @@ impl Default for ArtObject {
             shader_vert: Default::default(),
             shader_frag: Default::default(),
             texture: Default::default(),
+            description: None,
             options: Default::default(),
+            uniform_block: None,
+            presets: Default::default(),
+            randomizer: Default::default(),
+            compare: None,
+            parent: None,
             data: Default::default(),
             fn_update_data: Default::default(),
+            animation: None,
+            bindings: Vec::new(),
+            triggers: Vec::new(),
+            prop: None,
             enable_pipeline: true,
+            disabled: false,
             enable_depth_test: true,
+            frag_depth: false,
+            is_transparent: false,
+            refresh_rate: None,
             container_scale: Vec3::splat(1.),
+            auto_fit: false,
             is_mirror: false,
+            passes: Passes::default(),
+            groups: Vec::new(),
+            sound: None,
+            sound_muted: false,
+            visible: true,
+            hidden: false,
+            bounds: [Vec3::ZERO; 2],
+        }
+    }
+}
+
+/// Builds an `ArtObject` with chained calls like
+/// `ArtObject::builder().name("Cube").model(cube).frag("cube.frag").at([0., 1., 0.]).build()`.
+/// The transform is combined from `scale`, `rotate_y` and `at`, `build` validates the exhibit.
+pub struct ArtObjectBuilder {
+    art: ArtObject,
+    scale: Vec3,
+    rotation: Quat,
+    position: Vec3,
+    prelude: bool,
+}
+
+impl Default for ArtObjectBuilder {
+    fn default() -> Self {
+        Self {
+            art: ArtObject::default(),
+            scale: Vec3::ONE,
+            rotation: Quat::IDENTITY,
+            position: Vec3::ZERO,
+            prelude: false,
         }
     }
 }
 
+impl ArtObjectBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.art.name = name.into();
+        self
+    }
+
+    pub fn model(mut self, model: Arc<NormalizedObj>) -> Self {
+        self.art.model = model;
+        self
+    }
+
+    /// Loads the vertex shader from `path`.
+    pub fn vert(self, path: impl Into<PathBuf>) -> Self {
+        self.vert_shader(Arc::new(HotShader::new_vert(path)))
+    }
+
+    /// Uses a vertex shader that is shared with other exhibits.
+    pub fn vert_shader(mut self, shader: Arc<HotShader>) -> Self {
+        self.art.shader_vert = shader;
+        self
+    }
+
+    /// Loads the fragment shader from `path`.
+    pub fn frag(self, path: impl Into<PathBuf>) -> Self {
+        self.frag_shader(Arc::new(HotShader::new_frag(path)))
+    }
+
+    /// Uses a fragment shader that is shared with other exhibits.
+    pub fn frag_shader(mut self, shader: Arc<HotShader>) -> Self {
+        self.art.shader_frag = shader;
+        self
+    }
+
+    pub fn texture(mut self, texture: impl Into<TextureSource>) -> Self {
+        self.art.texture = Some(texture.into());
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.art.description = Some(description.into());
+        self
+    }
+
+    pub fn option(mut self, option: ArtOption) -> Self {
+        self.art.options.push(option);
+        self
+    }
+
+    pub fn options(mut self, options: impl IntoIterator<Item = ArtOption>) -> Self {
+        self.art.options.extend(options);
+        self
+    }
+
+    pub fn checkbox(self, label: impl Into<Cow<'static, str>>, checked: bool) -> Self {
+        self.option(ArtOption::checkbox(label, checked))
+    }
+
+    pub fn slider(self, label: impl Into<Cow<'static, str>>, value: f32, min: f32, max: f32) -> Self {
+        self.option(ArtOption::slider_f32(label, value, min, max))
+    }
+
+    pub fn slider_log(self, label: impl Into<Cow<'static, str>>, value: f32, min: f32, max: f32) -> Self {
+        self.option(ArtOption::slider_f32_log(label, value, min, max))
+    }
+
+    pub fn slider_i32(self, label: impl Into<Cow<'static, str>>, value: i32, min: i32, max: i32) -> Self {
+        self.option(ArtOption::slider_i32(label, value, min, max))
+    }
+
+    pub fn stroke(self, label: impl Into<Cow<'static, str>>, width: f32, color: Color32) -> Self {
+        self.option(ArtOption::stroke(label, width, color))
+    }
+
+    pub fn color(self, label: impl Into<Cow<'static, str>>, color: Color32) -> Self {
+        self.option(ArtOption::color(label, color))
+    }
+
+    pub fn vec2(self, label: impl Into<Cow<'static, str>>, value: [f32; 2], min: f32, max: f32) -> Self {
+        self.option(ArtOption::vec2(label, value, min, max))
+    }
+
+    pub fn vec3(self, label: impl Into<Cow<'static, str>>, value: [f32; 3], min: f32, max: f32) -> Self {
+        self.option(ArtOption::vec3(label, value, min, max))
+    }
+
+    pub fn dropdown(self, label: impl Into<Cow<'static, str>>, value: usize, labels: &'static [&'static str]) -> Self {
+        self.option(ArtOption::dropdown(label, value, labels))
+    }
+
+    pub fn angle(self, label: impl Into<Cow<'static, str>>, degrees: f32) -> Self {
+        self.option(ArtOption::angle(label, degrees))
+    }
+
+    /// Adds an extra uniform buffer, see `crate::uniform_block`.
+    pub fn uniform_block<N: Into<Cow<'static, str>>>(
+        mut self,
+        name: impl Into<Cow<'static, str>>,
+        fields: impl IntoIterator<Item = (N, UniformType)>,
+    ) -> Self {
+        self.art.uniform_block = Some(UniformBlock::new(name, fields));
+        self
+    }
+
+    /// Binds the last added option to a field of the uniform block.
+    pub fn bind_field(mut self, field: impl Into<Cow<'static, str>>) -> Self {
+        if let Some(option) = self.art.options.pop() {
+            self.art.options.push(option.with_field(field));
+        }
+        self
+    }
+
+    pub fn at(mut self, position: impl Into<Vec3>) -> Self {
+        self.position = position.into();
+        self
+    }
+
+    pub fn scale(mut self, scale: impl Into<Vec3>) -> Self {
+        self.scale = scale.into();
+        self
+    }
+
+    /// Rotates the exhibit around the y axis, the angle is in degrees.
+    pub fn rotate_y(mut self, degrees: f32) -> Self {
+        self.rotation = Quat::from_rotation_y(degrees.to_radians());
+        self
+    }
+
+    /// Attaches the exhibit to `node`, the transform of the builder is then ignored.
+    pub fn parent(mut self, node: ParentNode, local_matrix: Mat4) -> Self {
+        self.art.parent = Some(ArtParent { node, local_matrix });
+        self
+    }
+
+    pub fn on_update(mut self, fn_update_data: impl Fn(&mut ArtData, &ArtUpdateData) + 'static) -> Self {
+        self.art.fn_update_data = Some(Box::new(fn_update_data));
+        self
+    }
+
+    /// Adds a track to the animation of the exhibit, see `crate::animation`.
+    pub fn animate(mut self, track: Track) -> Self {
+        self.art.animation.get_or_insert_with(Animation::default).tracks.push(track);
+        self
+    }
+
+    pub fn groups<S: Into<String>>(mut self, groups: impl IntoIterator<Item = S>) -> Self {
+        self.art.groups = groups.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn container_scale(mut self, container_scale: impl Into<Vec3>) -> Self {
+        self.art.container_scale = container_scale.into();
+        self
+    }
+
+    /// Fits the model into the unit container, see `ArtObject::auto_fit`.
+    pub fn auto_fit(mut self) -> Self {
+        self.art.auto_fit = true;
+        self
+    }
+
+    /// Adds a trigger that is kept when the layout changes, see `crate::trigger`.
+    pub fn trigger(mut self, volume: Volume, on_enter: Vec<Action>, on_exit: Vec<Action>) -> Self {
+        self.art.triggers.push(Trigger { volume, on_enter, on_exit, inside: false, builtin: true });
+        self
+    }
+
+    pub fn sound(mut self, sound: Sound) -> Self {
+        self.art.sound = Some(sound);
+        self
+    }
+
+    /// Makes the exhibit a prop that falls and can be pushed, see `crate::physics`.
+    pub fn prop(mut self, shape: PropShape) -> Self {
+        self.art.prop = Some(shape);
+        self
+    }
+
+    pub fn mirror(mut self) -> Self {
+        self.art.is_mirror = true;
+        self
+    }
+
+    /// Only drawn in the reflection of the mirror, like the avatar.
+    pub fn mirror_only(mut self) -> Self {
+        self.art.passes.scene = false;
+        self
+    }
+
+    /// Starts with the exhibit not drawn, it can be enabled at runtime like the portal box.
+    pub fn without_pipeline(mut self) -> Self {
+        self.art.enable_pipeline = false;
+        self
+    }
+
+    pub fn without_depth_test(mut self) -> Self {
+        self.art.enable_depth_test = false;
+        self
+    }
+
+    /// For shaders with an alpha below 1, see `ArtObject::is_transparent`.
+    pub fn transparent(mut self) -> Self {
+        self.art.is_transparent = true;
+        self
+    }
+
+    /// For ray marched exhibits writing `gl_FragDepth`, see `ArtObject::frag_depth`.
+    pub fn frag_depth(mut self) -> Self {
+        self.art.frag_depth = true;
+        self
+    }
+
+    /// Renders heavy exhibits at `hz` instead of every frame, see `ArtObject::refresh_rate`.
+    pub fn refresh_rate(mut self, hz: f32) -> Self {
+        self.art.refresh_rate = Some(hz);
+        self
+    }
+
+    /// Generates the uniform declarations and option macros of the fragment shader, see `crate::prelude`.
+    /// The prelude belongs to the shader, exhibits sharing it must have the same options.
+    pub fn prelude(mut self) -> Self {
+        self.prelude = true;
+        self
+    }
+
+    /// Checks that the options fit into the uniform buffer and the shaders are of the right kind.
+    pub fn build(self) -> anyhow::Result<ArtObject> {
+        let mut art = self.art;
+        let slots = art.options.iter().map(|option| option.ty.slot_count()).sum::<usize>();
+        ensure!(
+            slots <= MAX_OPTION_SLOTS,
+            "options of {} need {slots} values, at most {MAX_OPTION_SLOTS} are supported",
+            art.name,
+        );
+        match art.uniform_block.as_ref() {
+            Some(block) => block.validate(&art.options).with_context(|| format!("invalid options of {}", art.name))?,
+            None => ensure!(
+                art.options.iter().all(|option| option.field().is_none()),
+                "options of {} are bound to fields but it has no uniform block",
+                art.name,
+            ),
+        }
+        let tracks = art.animation.iter().flat_map(|animation| animation.tracks.iter());
+        for track in tracks {
+            let Track::Option { label, keyframes } = track else { continue };
+            let option = art.options.iter()
+                .find(|option| option.label() == label)
+                .with_context(|| format!("{} has no option {label} to animate", art.name))?;
+            let slots = option.ty.slot_count();
+            ensure!(
+                keyframes.keyframes().iter().all(|keyframe| keyframe.value.len() == slots),
+                "keyframes of option {label} of {} must have {slots} values",
+                art.name,
+            );
+        }
+        if art.enable_pipeline {
+            let vert_kind = art.shader_vert.shader_kind();
+            ensure!(vert_kind == ShaderKind::Vertex, "vertex shader of {} is a {vert_kind:?} shader", art.name);
+            let frag_kind = art.shader_frag.shader_kind();
+            ensure!(frag_kind == ShaderKind::Fragment, "fragment shader of {} is a {frag_kind:?} shader", art.name);
+        }
+        if self.prelude {
+            let prelude = crate::prelude::glsl_prelude(&art);
+            ensure!(
+                art.shader_frag.prelude().is_none_or(|shared| shared == prelude),
+                "{} shares its fragment shader with an exhibit whose options differ, they need the same prelude",
+                art.name,
+            );
+            art.shader_frag.set_prelude(prelude);
+        }
+        art.data = ArtData::new(Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position));
+        art.set_model(art.model.clone());
+        art.save_options();
+        Ok(art)
+    }
+}
+
+/// Sets the matrix of all attached exhibits from the matrix of their parent.
+/// Must be called after `fn_update_data` so that moving parents are already updated.
+pub fn resolve_parents(art_objects: &mut [ArtObject], camera: &Camera) {
+    let mut resolved = art_objects.iter().map(|art| art.parent.is_none()).collect::<Vec<_>>();
+    // each pass resolves at least one more level of the hierarchy, cycles are never resolved
+    loop {
+        let mut progress = false;
+        for idx in 0..art_objects.len() {
+            if resolved[idx] {
+                continue;
+            }
+            let Some(parent) = art_objects[idx].parent.as_ref() else { continue };
+            let parent_matrix = match &parent.node {
+                ParentNode::Art(name) => {
+                    let parent_idx = art_objects.iter().position(|art| art.name == *name);
+                    match parent_idx {
+                        Some(parent_idx) if resolved[parent_idx] => art_objects[parent_idx].data.matrix,
+                        _ => continue,
+                    }
+                }
+                ParentNode::Player => {
+                    Mat4::from_translation(camera.position) * Mat4::from_rotation_y(-camera.angle_yaw)
+                }
+                ParentNode::Camera => {
+                    Mat4::from_translation(camera.position)
+                        * Mat4::from_rotation_y(-camera.angle_yaw)
+                        * Mat4::from_rotation_x(-camera.angle_pitch)
+                }
+            };
+            art_objects[idx].data.matrix = parent_matrix * parent.local_matrix;
+            resolved[idx] = true;
+            progress = true;
+        }
+        if !progress {
+            break;
+        }
+    }
+}
+
+/// Render passes an exhibit is drawn in, independent of whether it is hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passes {
+    pub scene: bool,
+    pub mirror: bool,
+}
+
+impl Default for Passes {
+    fn default() -> Self {
+        Self { scene: true, mirror: true }
+    }
+}
+
+/// Node an exhibit is attached to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParentNode {
+    /// Another exhibit by name.
+    Art(String),
+    /// The body of the player, follows the camera position and yaw.
+    Player,
+    /// The camera including its pitch, for items held in front of the view.
+    Camera,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtParent {
+    pub node: ParentNode,
+    /// Transform relative to the parent node.
+    pub local_matrix: Mat4,
+}
+
+/// Where the texture bound at binding 2 comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TextureSource {
+    File(PathBuf),
+    /// The latest frame of the webcam, see `crate::webcam`.
+    Webcam,
+    /// An image created at startup like the font atlas of the plaques, see `crate::text`.
+    Generated(Arc<RgbaImage>),
+}
+
+impl From<&str> for TextureSource {
+    fn from(path: &str) -> Self {
+        Self::File(path.into())
+    }
+}
+
+/// A second fragment shader that is drawn instead of the own one right of a split line,
+/// to compare two versions of an exhibit side by side.
+pub struct Compare {
+    pub shader_frag: Arc<HotShader>,
+    /// Describes the shader in the interface.
+    pub label: String,
+    /// Position of the split line as fraction of the screen width.
+    pub split: f32,
+}
+
+/// State of the randomize options mode of an exhibit.
+#[derive(Debug)]
+pub struct Randomizer {
+    /// Randomize the options every `interval` seconds.
+    pub auto: bool,
+    pub interval: f32,
+    elapsed: f32,
+    seed: u32,
+}
+
+impl Default for Randomizer {
+    fn default() -> Self {
+        Self { auto: false, interval: 10., elapsed: 0., seed: 0 }
+    }
+}
+
+impl Randomizer {
+    /// Maximum seed so that it can be represented exactly as a float in the shader.
+    const MAX_SEED: u32 = 1 << 24;
+
+    fn next_seed(&mut self) -> u32 {
+        self.elapsed = 0.;
+        self.seed = (self.seed + 1) % Self::MAX_SEED;
+        self.seed
+    }
+
+    /// Returns true if the options should be randomized now.
+    fn update(&mut self, elapsed: f32) -> bool {
+        if !self.auto {
+            return false;
+        }
+        self.elapsed += elapsed;
+        self.elapsed >= self.interval
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ArtUpdateData {
     pub skybox_rotation_angle: f32,
     pub old_position: Vec3,
     pub new_position: Vec3,
-    pub camera: Camera,
+    pub sky: SkyOptions,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -79,8 +768,19 @@ pub struct ArtData {
     pub dist_to_camera_sqr: f32,
     pub matrix: Mat4,
     pub light_pos: Vec4,
-    pub option_values: [Vec4; 2],
+    pub option_values: [Vec4; OPTION_VEC4S],
+    /// Seed of the last randomization of the options, 0 if they were never randomized.
+    pub seed: f32,
+    /// Local hit point of the cursor ray in xyz, w is 0 if the cursor misses the exhibit,
+    /// 1 if it hovers it and 2 if the left mouse button is pressed as well.
+    pub mouse: Vec4,
     pub inside_portal: bool,
+    /// Stops the time of the shaders and the animation, see `crate::settings::Interaction`.
+    pub paused: bool,
+    /// Time lost while the exhibit was paused or slowed down, its shaders get the global time minus this.
+    pub time_offset: f32,
+    /// Time controls of the exhibit set in the GUI.
+    pub time: ArtTime,
 }
 
 impl ArtData {
@@ -94,6 +794,44 @@ impl ArtData {
     pub fn position(&self) -> Vec3 {
         self.matrix.transform_point3(Vec3::splat(0.))
     }
+
+    /// How fast the time of the exhibit runs compared to the global time, 0 while it is paused.
+    pub fn speed(&self) -> f32 {
+        if self.paused { 0. } else { self.time.speed }
+    }
+
+    /// Time for the shaders of the exhibit at the global time `time`.
+    pub fn shader_time(&self, time: f32) -> f32 {
+        time - self.time_offset + self.time.offset
+    }
+
+    /// Resets the time controls and forgets the time lost while paused or slowed down,
+    /// so the shaders get the global time again.
+    pub fn reset_time(&mut self) {
+        self.time = ArtTime::default();
+        self.time_offset = 0.;
+    }
+}
+
+/// Per exhibit time controls, to freeze or slow down an exhibit for inspection
+/// while the rest of the gallery keeps animating.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ArtTime {
+    /// Seconds added to the time of the shaders.
+    pub offset: f32,
+    /// Multiplier of the time of the shaders and the animation.
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for ArtTime {
+    fn default() -> Self {
+        Self {
+            offset: 0.,
+            speed: 1.,
+            paused: false,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -102,9 +840,28 @@ pub enum ArtOptionType {
     SliderF32 { value: f32, min: f32, max: f32, log: bool },
     SliderI32 { value: i32, min: i32, max: i32 },
     Stroke { width: f32, color: Color32 },
+    /// Color with alpha, not premultiplied.
+    Color { rgba: [u8; 4] },
+    Vec2 { value: [f32; 2], min: f32, max: f32 },
+    Vec3 { value: [f32; 3], min: f32, max: f32 },
+    /// Index into `labels`, which are shown in a dropdown.
+    Enum { value: usize, labels: &'static [&'static str] },
+    /// Direction in degrees, saved in radians.
+    Angle { degrees: f32 },
 }
 
 impl ArtOptionType {
+    /// Number of floats the value takes up in `ArtData::option_values`.
+    pub fn slot_count(&self) -> usize {
+        match self {
+            Self::Checkbox { .. } | Self::SliderF32 { .. } | Self::SliderI32 { .. } => 1,
+            Self::Enum { .. } | Self::Angle { .. } => 1,
+            Self::Vec2 { .. } => 2,
+            Self::Stroke { .. } | Self::Vec3 { .. } => 3,
+            Self::Color { .. } => 4,
+        }
+    }
+
     pub fn save_value(&self, values: &mut [f32], i: &mut usize) {
         match self {
             Self::Checkbox { checked } => {
@@ -125,38 +882,267 @@ impl ArtOptionType {
                     *i += 1;
                 }
             }
+            Self::Color { rgba } => {
+                for &component in rgba.iter() {
+                    values[*i] = component as f32 / 255.;
+                    *i += 1;
+                }
+            }
+            Self::Vec2 { value, .. } => {
+                values[*i..*i + 2].copy_from_slice(value);
+                *i += 2;
+            }
+            Self::Vec3 { value, .. } => {
+                values[*i..*i + 3].copy_from_slice(value);
+                *i += 3;
+            }
+            Self::Enum { value, .. } => {
+                values[*i] = *value as f32;
+                *i += 1;
+            }
+            Self::Angle { degrees } => {
+                values[*i] = degrees.to_radians();
+                *i += 1;
+            }
+        }
+    }
+
+    /// Sets the value to a random one in its range, `seed` and `idx` select the random numbers.
+    fn randomize(&mut self, seed: u32, idx: u32) {
+        let random = |n: u32| random_unit(seed, idx * 4 + n);
+        match self {
+            Self::Checkbox { checked } => *checked = random(0) < 0.5,
+            Self::SliderF32 { value, min, max, log } => {
+                *value = if *log && *min > 0. {
+                    (min.ln() + (max.ln() - min.ln()) * random(0)).exp()
+                } else {
+                    *min + (*max - *min) * random(0)
+                };
+            }
+            Self::SliderI32 { value, min, max } => {
+                let range = (*max - *min + 1) as f32;
+                *value = (*min + (random(0) * range) as i32).min(*max);
+            }
+            Self::Stroke { color, .. } => {
+                let [r, g, b] = [0, 1, 2].map(|n| (random(n) * 256.) as u8);
+                *color = Color32::from_rgb(r, g, b);
+            }
+            // the alpha is kept so that transparent colors stay transparent
+            Self::Color { rgba } => {
+                for (n, component) in rgba[..3].iter_mut().enumerate() {
+                    *component = (random(n as u32) * 256.) as u8;
+                }
+            }
+            Self::Vec2 { value, min, max } => {
+                for (n, component) in value.iter_mut().enumerate() {
+                    *component = *min + (*max - *min) * random(n as u32);
+                }
+            }
+            Self::Vec3 { value, min, max } => {
+                for (n, component) in value.iter_mut().enumerate() {
+                    *component = *min + (*max - *min) * random(n as u32);
+                }
+            }
+            Self::Enum { value, labels } => {
+                *value = ((random(0) * labels.len() as f32) as usize).min(labels.len().saturating_sub(1));
+            }
+            Self::Angle { degrees } => *degrees = random(0) * 360.,
+        }
+    }
+
+    /// Inverse of `save_value`, fractional values are rounded for non float options.
+    pub fn load_value(&mut self, values: &[f32], i: &mut usize) {
+        match self {
+            Self::Checkbox { checked } => {
+                *checked = values[*i] >= 0.5;
+                *i += 1;
+            }
+            Self::SliderF32 { value, min, max, .. } => {
+                *value = values[*i].clamp(*min, *max);
+                *i += 1;
+            }
+            Self::SliderI32 { value, min, max } => {
+                *value = (values[*i].round() as i32).clamp(*min, *max);
+                *i += 1;
+            }
+            Self::Stroke { color, .. } => {
+                let [r, g, b] = [0, 1, 2].map(|c| (values[*i + c] * 255.).round().clamp(0., 255.) as u8);
+                *color = Color32::from_rgb(r, g, b);
+                *i += 3;
+            }
+            Self::Color { rgba } => {
+                *rgba = [0, 1, 2, 3].map(|c| (values[*i + c] * 255.).round().clamp(0., 255.) as u8);
+                *i += 4;
+            }
+            Self::Vec2 { value, min, max } => {
+                *value = [0, 1].map(|c| values[*i + c].clamp(*min, *max));
+                *i += 2;
+            }
+            Self::Vec3 { value, min, max } => {
+                *value = [0, 1, 2].map(|c| values[*i + c].clamp(*min, *max));
+                *i += 3;
+            }
+            Self::Enum { value, labels } => {
+                *value = (values[*i].round().max(0.) as usize).min(labels.len().saturating_sub(1));
+                *i += 1;
+            }
+            Self::Angle { degrees } => {
+                *degrees = values[*i].to_degrees().rem_euclid(360.);
+                *i += 1;
+            }
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct ArtOption {
-    label: &'static str,
+    label: Cow<'static, str>,
     pub ty: ArtOptionType,
+    /// Field of the uniform block of the exhibit the value is written to.
+    field: Option<Cow<'static, str>>,
 }
 
 impl ArtOption {
-    pub fn checkbox(label: &'static str, checked: bool) -> Self {
-        Self { label, ty: ArtOptionType::Checkbox { checked } }
+    pub fn checkbox(label: impl Into<Cow<'static, str>>, checked: bool) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Checkbox { checked }, field: None }
+    }
+
+    pub fn slider_f32(label: impl Into<Cow<'static, str>>, value: f32, min: f32, max: f32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::SliderF32 { value, min, max, log: false }, field: None }
+    }
+
+    pub fn slider_f32_log(label: impl Into<Cow<'static, str>>, value: f32, min: f32, max: f32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::SliderF32 { value, min, max, log: true }, field: None }
+    }
+
+    pub fn slider_i32(label: impl Into<Cow<'static, str>>, value: i32, min: i32, max: i32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::SliderI32 { value, min, max }, field: None }
+    }
+
+    pub fn stroke(label: impl Into<Cow<'static, str>>, width: f32, color: Color32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Stroke { width, color }, field: None }
+    }
+
+    pub fn color(label: impl Into<Cow<'static, str>>, color: Color32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Color { rgba: color.to_srgba_unmultiplied() }, field: None }
     }
 
-    pub fn slider_f32(label: &'static str, value: f32, min: f32, max: f32) -> Self {
-        Self { label, ty: ArtOptionType::SliderF32 { value, min, max, log: false } }
+    pub fn vec2(label: impl Into<Cow<'static, str>>, value: [f32; 2], min: f32, max: f32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Vec2 { value, min, max }, field: None }
     }
 
-    pub fn slider_f32_log(label: &'static str, value: f32, min: f32, max: f32) -> Self {
-        Self { label, ty: ArtOptionType::SliderF32 { value, min, max, log: true } }
+    pub fn vec3(label: impl Into<Cow<'static, str>>, value: [f32; 3], min: f32, max: f32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Vec3 { value, min, max }, field: None }
     }
 
-    pub fn slider_i32(label: &'static str, value: i32, min: i32, max: i32) -> Self {
-        Self { label, ty: ArtOptionType::SliderI32 { value, min, max } }
+    /// Lets the user choose one of `labels`, the shader gets the index.
+    pub fn dropdown(label: impl Into<Cow<'static, str>>, value: usize, labels: &'static [&'static str]) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Enum { value, labels }, field: None }
     }
 
-    pub fn stroke(label: &'static str, width: f32, color: Color32) -> Self {
-        Self { label, ty: ArtOptionType::Stroke { width, color } }
+    pub fn angle(label: impl Into<Cow<'static, str>>, degrees: f32) -> Self {
+        Self { label: label.into(), ty: ArtOptionType::Angle { degrees }, field: None }
+    }
+
+    /// Binds the option to a field of the uniform block, see `crate::uniform_block`.
+    pub fn with_field(mut self, field: impl Into<Cow<'static, str>>) -> Self {
+        self.field = Some(field.into());
+        self
     }
 
     pub fn label(&self) -> &str {
-        self.label
+        &self.label
+    }
+
+    pub fn field(&self) -> Option<&str> {
+        self.field.as_deref()
+    }
+}
+
+/// Random number in [0, 1) using the lowbias32 hash from <https://nullprogram.com/blog/2018/07/31/>.
+fn random_unit(seed: u32, n: u32) -> f32 {
+    let mut x = seed.wrapping_mul(0x9e3779b9) ^ n;
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    (x >> 8) as f32 / (1 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_chain() {
+        let child = |name: &str, parent: &str| ArtObject {
+            name: name.to_owned(),
+            parent: Some(ArtParent {
+                node: ParentNode::Art(parent.to_owned()),
+                local_matrix: Mat4::from_translation(Vec3::X),
+            }),
+            ..Default::default()
+        };
+        // children before their parents to need more than one pass
+        let mut art_objects = vec![
+            child("moon", "planet"),
+            child("planet", "sun"),
+            ArtObject { name: "sun".to_owned(), data: ArtData::new(Mat4::from_translation(Vec3::Y)), ..Default::default() },
+            child("a", "b"),
+            child("b", "a"),
+        ];
+        resolve_parents(&mut art_objects, &Camera::default());
+        assert_eq!(art_objects[0].position(), Vec3::new(2., 1., 0.));
+        assert_eq!(art_objects[1].position(), Vec3::new(1., 1., 0.));
+        // cycles are left alone
+        assert_eq!(art_objects[3].position(), Vec3::ZERO);
+    }
+
+    #[test]
+    fn save_and_load_values() {
+        let mut options = [
+            ArtOptionType::Color { rgba: [255, 0, 51, 128] },
+            ArtOptionType::Vec3 { value: [0.5, -1., 2.], min: -1., max: 2. },
+            ArtOptionType::Enum { value: 2, labels: &["a", "b", "c"] },
+            ArtOptionType::Angle { degrees: 90. },
+        ];
+        let mut values = [0.; 9];
+        let mut i = 0;
+        for option in options.iter() {
+            option.save_value(&mut values, &mut i);
+        }
+        assert_eq!(i, 9);
+        assert_eq!(values[3], 128. / 255.);
+        assert_eq!(values[7], 2.);
+        assert_eq!(values[8], std::f32::consts::FRAC_PI_2);
+
+        values[7] = 5.;
+        let mut i = 0;
+        for option in options.iter_mut() {
+            option.load_value(&values, &mut i);
+        }
+        assert!(matches!(options[0], ArtOptionType::Color { rgba: [255, 0, 51, 128] }));
+        assert!(matches!(options[2], ArtOptionType::Enum { value: 2, .. }));
+        assert!(matches!(options[3], ArtOptionType::Angle { degrees } if (degrees - 90.).abs() < 1e-4));
+    }
+
+    #[test]
+    fn builder_validates() {
+        let builder = || ArtObject::builder().name("Test").vert("test.vert").frag("test.frag");
+        let art = builder().slider("Speed", 2., 0., 10.).at([1., 2., 3.]).build().unwrap();
+        assert_eq!(art.position(), Vec3::new(1., 2., 3.));
+        assert_eq!(art.data.option_values[0].x, 2.);
+
+        let too_many = (0..11).map(|i| ArtOption::stroke(format!("Color {i}"), 1., Color32::WHITE));
+        assert!(builder().options(too_many).build().is_err());
+        let swapped = builder().frag_shader(Arc::new(HotShader::new_vert("test.vert")));
+        assert!(swapped.build().is_err());
+
+        let shared = Arc::new(HotShader::new_frag("test.frag"));
+        let with_prelude = || builder().frag_shader(shared.clone()).prelude();
+        assert!(with_prelude().slider("Speed", 2., 0., 10.).build().is_ok());
+        assert!(with_prelude().slider("Speed", 1., 0., 5.).build().is_ok());
+        assert!(with_prelude().checkbox("Speed", true).build().is_err());
     }
 }
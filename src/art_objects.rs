@@ -1,269 +1,276 @@
 use crate::{
-    art::{ArtData, ArtObject, ArtOption},
+    art::{ArtObject, ArtParent, ParentNode, Sound, TextureSource},
     fs,
+    isf::IsfShader,
     model::obj::NormalizedObj,
+    preset::{Presets, PRESET_DIR},
+    settings::Bookmark,
+    text::FontAtlas,
+    trigger::{Action, Volume},
     vulkan::HotShader,
 };
 
-use std::f32::consts::FRAC_1_SQRT_2;
+use std::path::Path;
 use std::sync::Arc;
 
 use egui::Color32;
-use glam::{Mat4, Quat, Vec3};
+use glam::{Mat4, Quat, Vec3, Vec4};
+
+/// Where the camera starts, also used by the reset key.
+pub const SPAWN: Bookmark = Bookmark { position: Vec3::new(0., 1.5, 3.), yaw: 0., pitch: 0. };
+
+/// Directory scanned for shaders in the Interactive Shader Format.
+const ISF_DIR: &str = "assets/isf";
+/// Positions on the outer side of the big wall where ISF shaders are exhibited.
+const ISF_POSITIONS: [[f32; 3]; 5] = [
+    [6.21, 1.5,  -1.5],
+    [6.21, 1.5,  -4.5],
+    [6.21, 1.5,  -7.5],
+    [6.21, 1.5, -10.5],
+    [6.21, 1.5, -13.5],
+];
+
+/// Distance between the edge of a 2D exhibit and the center of its plaque.
+const PLAQUE_GAP: f32 = 0.35;
+/// Half the width of the pillars the 3D exhibits stand on.
+const PILLAR_HALF_WIDTH: f32 = 0.53;
 
 pub fn get_art_objects() -> anyhow::Result<Vec<ArtObject>> {
-    let model_square = Arc::new(NormalizedObj::from_reader(fs::load("assets/models/square.obj")?)?);
-    let model_cube = Arc::new(NormalizedObj::from_reader(fs::load("assets/models/cube_inside.obj")?)?);
-    let model_teapot = Arc::new(NormalizedObj::from_reader(fs::load("assets/models/teapot.obj")?)?);
+    let model_square = Arc::new(NormalizedObj::load("assets/models/square.obj")?);
+    let model_cube = Arc::new(NormalizedObj::load("assets/models/cube_inside.obj")?);
+    let model_teapot = Arc::new(NormalizedObj::load("assets/models/teapot.obj")?);
 
     let shader_2d = Arc::new(HotShader::new_vert("assets/shaders/art2d.vert"));
     let shader_3d = Arc::new(HotShader::new_vert("assets/shaders/art3d.vert"));
     let shader_pillar = Arc::new(HotShader::new_frag("assets/shaders/pillar.frag"));
 
-    let mut art_objects = vec![
-        ArtObject {
-            name: "Mandelbrot".to_owned(),
-            model: model_square.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mandelbrot.frag")),
-            options: vec![],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(90_f32.to_radians()),
-                [5.99, 1.5, -1.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Sdf Cat".to_owned(),
-            model: model_square.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/sdf_cat.frag")),
-            options: vec![
-                ArtOption::stroke("Color", 1., Color32::from_rgb(255, 76, 76)),
-                ArtOption::slider_f32("Speed", 1., 0., 10.),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(90_f32.to_radians()),
-                [5.99, 1.5, -4.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Colorful Mozaic".to_owned(),
-            model: model_square.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mozaic.frag")),
-            options: vec![
-                ArtOption::slider_f32("Speed", 1., 0., 10.),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(90_f32.to_radians()),
-                [5.99, 1.5, -7.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Mirror".to_owned(),
-            model: model_square.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mirror.frag")),
-            options: vec![
-                ArtOption::checkbox("Invert", false),
-                ArtOption::checkbox("Depth", false),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::new(6.0, 1., 1.0),
-                Quat::from_rotation_y(-90_f32.to_radians()),
-                [-5.99, 1.0, -6.0].into(),
-            )),
-            is_mirror: true,
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Portal".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/portal.frag")),
-            options: vec![
-                ArtOption::slider_i32("Ball number", 5, 1, 100),
-                ArtOption::slider_i32("Rail Rotation", 3, -10, 10),
-                ArtOption::slider_f32("Ball Size", 0.05, 0., 0.2),
-                ArtOption::slider_f32("Rail Size", 0.06, 0., 0.1),
-                ArtOption::slider_f32("Rail width", 0.011, 0., 0.2),
-                ArtOption::slider_i32("ColorIndex", 1, 0, 7),
-                ArtOption::checkbox("Invert", false),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(1.0),
-                Quat::from_rotation_y(90_f32.to_radians()),
-                [6.0, 1.501, 2.0].into(),
-            )),
-            fn_update_data: Some(Box::new(|data, update| {
-                if goes_through_rect(update.old_position, update.new_position, data.matrix) {
-                    data.inside_portal = !data.inside_portal;
-                }
-            })),
-            container_scale: Vec3::new(1., 1.5, 0.5),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Portalbox".to_owned(),
-            model: model_cube.clone(),
-            fn_update_data: Some(Box::new(|data, _| {
+    let mut art_objects = [
+        ArtObject::builder()
+            .name("Mandelbrot")
+            .model(model_square.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/mandelbrot.frag")
+            .description("Points c for which z = z^2 + c stays bounded, colored by escape time.")
+            .scale(Vec3::splat(0.5))
+            .rotate_y(90.)
+            .at([5.99, 1.5, -1.5])
+            .groups(["2D", "fractals"])
+            .build(),
+        ArtObject::builder()
+            .name("Sdf Cat")
+            .model(model_square.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/sdf_cat.frag")
+            .description("A cat drawn entirely with signed distance functions of circles and curves.")
+            .stroke("Color", 1., Color32::from_rgb(255, 76, 76))
+            .slider("Speed", 1., 0., 10.)
+            .scale(Vec3::splat(0.5))
+            .rotate_y(90.)
+            .at([5.99, 1.5, -4.5])
+            .groups(["2D"])
+            .build(),
+        ArtObject::builder()
+            .name("Colorful Mozaic")
+            .model(model_square.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/mozaic.frag")
+            .description("Animated tiles whose colors come from a cosine palette.")
+            .slider("Speed", 1., 0., 10.)
+            .scale(Vec3::splat(0.5))
+            .rotate_y(90.)
+            .at([5.99, 1.5, -7.5])
+            .groups(["2D"])
+            .build(),
+        #[cfg(feature = "webcam")]
+        ArtObject::builder()
+            .name("Webcam")
+            .model(model_square.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/webcam.frag")
+            .texture(TextureSource::Webcam)
+            .description("Your own image from the webcam, with edge detection and thermal effects.")
+            .slider_i32("Effect", 0, 0, 3)
+            .checkbox("Mirror", true)
+            .scale(Vec3::splat(0.5))
+            .rotate_y(90.)
+            .at([5.99, 1.5, -10.5])
+            .groups(["2D"])
+            .build(),
+        ArtObject::builder()
+            .name("Mirror")
+            .model(model_square.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/mirror.frag")
+            .checkbox("Invert", false)
+            .checkbox("Depth", false)
+            .scale([6.0, 1., 1.0])
+            .rotate_y(-90.)
+            .at([-5.99, 1.0, -6.0])
+            .mirror()
+            .build(),
+        ArtObject::builder()
+            .name("Portal")
+            .model(model_cube.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/portal.frag")
+            .transparent()
+            .slider_i32("Ball number", 5, 1, 100)
+            .slider_i32("Rail Rotation", 3, -10, 10)
+            .slider("Ball Size", 0.05, 0., 0.2)
+            .slider("Rail Size", 0.06, 0., 0.1)
+            .slider("Rail width", 0.011, 0., 0.2)
+            .slider_i32("ColorIndex", 1, 0, 7)
+            .checkbox("Invert", false)
+            .rotate_y(90.)
+            .at([6.0, 1.501, 2.0])
+            .sound(Sound::Tone(55.))
+            .trigger(
+                Volume::Crossing,
+                vec![Action::EnterPortal("Portal".to_owned())],
+                vec![Action::LeavePortal("Portal".to_owned())],
+            )
+            .container_scale([1., 1.5, 0.5])
+            .groups(["3D"])
+            .build(),
+        ArtObject::builder()
+            .name("Portalbox")
+            .model(model_cube.clone())
+            .on_update(|data, _| {
                 // draw after all other shaders
                 data.dist_to_camera_sqr = -1.;
-            })),
-            enable_pipeline: false,
-            enable_depth_test: false,
-            container_scale: Vec3::splat(100.),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Player".to_owned(),
-            model: model_teapot.clone(),
-            shader_vert: shader_2d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/player.frag")),
-            fn_update_data: Some(Box::new(|data, update| {
-                let matrix = Mat4::from_scale_rotation_translation(
-                    Vec3::splat(0.4),
-                    Quat::from_rotation_y(90_f32.to_radians()),
-                    Vec3::new(0.0, -1.0, 1.0),
-                );
+            })
+            .without_pipeline()
+            .without_depth_test()
+            .transparent()
+            .container_scale(Vec3::splat(100.))
+            .build(),
+        ArtObject::builder()
+            .name("Player")
+            .model(model_teapot.clone())
+            .vert_shader(shader_2d.clone())
+            .frag("assets/shaders/player.frag")
+            .on_update(|data, _| {
                 data.dist_to_camera_sqr = 0.;
-                data.matrix = Mat4::IDENTITY
-                    * Mat4::from_translation(update.camera.position)
-                    * Mat4::from_rotation_y(-update.camera.angle_yaw)
-                    * matrix;
-            })),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Skybox".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/skybox.frag")),
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(100.),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [0., 0., 0.].into(),
-            )),
-            fn_update_data: Some(Box::new(|data, update| {
+            })
+            .mirror_only()
+            .parent(ParentNode::Player, Mat4::from_scale_rotation_translation(
+                Vec3::splat(0.4),
+                Quat::from_rotation_y(90_f32.to_radians()),
+                Vec3::new(0.0, -1.0, 1.0),
+            ))
+            .build(),
+        ArtObject::builder()
+            .name("Skybox")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/skybox.frag")
+            .scale(Vec3::splat(100.))
+            .on_update(|data, update| {
                 // draw before all other shaders
                 data.dist_to_camera_sqr = f32::MAX;
+                // the physical sky works in world space and gets the sun position from light_pos
+                let rotation = if update.sky.physical { 0. } else { update.skybox_rotation_angle };
                 data.matrix = Mat4::from_scale_rotation_translation(
                     Vec3::splat(100.),
-                    Quat::from_rotation_y(update.skybox_rotation_angle),
+                    Quat::from_rotation_y(rotation),
                     [0., 0., 0.].into(),
                 );
-            })),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Mandelbox".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mandelbox.frag")),
-            options: vec![
-                ArtOption::slider_f32("Scale", 3., -5., 5.),
-                ArtOption::slider_i32("Iterations", 10, 1, 100),
-                ArtOption::slider_f32_log("Epsilon", 0.0002, 0.000001, 0.001),
-                ArtOption::checkbox("Shadows", false),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [-2.5, 1.5, -0.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Mandelbulb".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mandelbulb.frag")),
-            options: vec![
-                ArtOption::slider_i32("Power", 8, 1, 20),
-                ArtOption::slider_i32("Iterations", 10, 1, 100),
-                ArtOption::slider_f32_log("Epsilon", 0.0002, 0.000001, 0.001),
-                ArtOption::slider_i32("ColorIndex", 3, 0, 7),
-                ArtOption::checkbox("Shadows", true),
-                ArtOption::checkbox("Animate", true),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [-2.5, 1.5, -5.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Menger Sponge".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/mengersponge.frag")),
-            options: vec![
-                ArtOption::slider_i32("Depth", 4, 1, 10),
-                ArtOption::checkbox("Shadows", true),
-                ArtOption::checkbox("MSAA", true),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [-2.5, 1.5, -10.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Solar System".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/solar.frag")),
-            texture: Some("assets/downloads/earth.jpg".into()),
-            options: vec![
-                ArtOption::slider_f32("Speed", 1., 0., 10.),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [2.5, 1.5, -10.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Gem".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/gem.frag")),
-            options: vec![
-                ArtOption::slider_i32("GemType", 1, 0, 1),
-                ArtOption::slider_i32("ColorIndex", 2, 0, 7),
-                ArtOption::slider_f32("Speed", 1., 0., 2.),
-                ArtOption::checkbox("Diffuse", true),
-                ArtOption::checkbox("Specular", true),
-            ],
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [2.5, 1.5, -0.5].into(),
-            )),
-            ..Default::default()
-        },
-        ArtObject {
-            name: "Cloudy Cube".to_owned(),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: Arc::new(HotShader::new_frag("assets/shaders/cloudycube.frag")),
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::splat(0.5),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                [2.5, 1.5, -5.5].into(),
-            )),
-            ..Default::default()
-        },
-    ];
+                let sky = update.sky;
+                data.option_values[0] = Vec4::new(sky.rayleigh, sky.mie, sky.sun_size, sky.star_brightness);
+                data.option_values[1] = Vec4::new(update.skybox_rotation_angle, sky.physical as u8 as f32, 0., 0.);
+            })
+            .build(),
+        ArtObject::builder()
+            .name("Mandelbox")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/mandelbox.frag")
+            .transparent()
+            .frag_depth()
+            .description("A box folding fractal rendered by ray marching its distance estimate.")
+            .slider("Scale", 3., -5., 5.)
+            .slider_i32("Iterations", 10, 1, 100)
+            .slider_log("Epsilon", 0.0002, 0.000001, 0.001)
+            .checkbox("Shadows", false)
+            .scale(Vec3::splat(0.5))
+            .at([-2.5, 1.5, -0.5])
+            .groups(["3D", "fractals"])
+            .build(),
+        ArtObject::builder()
+            .name("Mandelbulb")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/mandelbulb.frag")
+            .prelude()
+            .transparent()
+            .frag_depth()
+            .description("A 3D analogue of the Mandelbrot set in spherical coordinates.")
+            .slider_i32("Power", 8, 1, 20)
+            .slider_i32("Iterations", 10, 1, 100)
+            .slider_log("Epsilon", 0.0002, 0.000001, 0.001)
+            .slider_i32("ColorIndex", 3, 0, 7)
+            .checkbox("Shadows", true)
+            .checkbox("Animate", true)
+            .scale(Vec3::splat(0.5))
+            .at([-2.5, 1.5, -5.5])
+            .groups(["3D", "fractals"])
+            .build(),
+        ArtObject::builder()
+            .name("Menger Sponge")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/mengersponge.frag")
+            .transparent()
+            .description("A cube with the middle of each face removed, repeated at every scale.")
+            .slider_i32("Depth", 4, 1, 10)
+            .checkbox("Shadows", true)
+            .checkbox("MSAA", true)
+            .refresh_rate(20.)
+            .scale(Vec3::splat(0.5))
+            .at([-2.5, 1.5, -10.5])
+            .groups(["3D", "fractals"])
+            .build(),
+        ArtObject::builder()
+            .name("Solar System")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/solar.frag")
+            .transparent()
+            .texture("assets/downloads/earth.jpg")
+            .description("The sun with orbiting planets, the earth uses a satellite image.")
+            .slider("Speed", 1., 0., 10.)
+            .scale(Vec3::splat(0.5))
+            .at([2.5, 1.5, -10.5])
+            .sound(Sound::Shader("assets/shaders/solar_sound.glsl".into()))
+            .groups(["3D"])
+            .build(),
+        ArtObject::builder()
+            .name("Gem")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/gem.frag")
+            .transparent()
+            .description("A faceted gem with refraction and specular highlights.")
+            .slider_i32("GemType", 1, 0, 1)
+            .slider_i32("ColorIndex", 2, 0, 7)
+            .slider("Speed", 1., 0., 2.)
+            .checkbox("Diffuse", true)
+            .checkbox("Specular", true)
+            .scale(Vec3::splat(0.5))
+            .at([2.5, 1.5, -0.5])
+            .groups(["3D"])
+            .build(),
+        ArtObject::builder()
+            .name("Cloudy Cube")
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag("assets/shaders/cloudycube.frag")
+            .description("Volumetric clouds ray marched through 3D noise inside a cube.")
+            .scale(Vec3::splat(0.5))
+            .at([2.5, 1.5, -5.5])
+            .groups(["3D"])
+            .build(),
+    ].into_iter().collect::<anyhow::Result<Vec<_>>>()?;
 
     let pillars = [
         [-2.5, 0.5, -10.5],
@@ -273,43 +280,173 @@ pub fn get_art_objects() -> anyhow::Result<Vec<ArtObject>> {
         [-2.5, 0.5,  -0.5],
         [ 2.5, 0.5,  -0.5],
     ];
-    art_objects.extend(pillars.into_iter().enumerate().map(|(i, pillar_pos)| {
-        ArtObject {
-            name: format!("Pillar {i:2}"),
-            model: model_cube.clone(),
-            shader_vert: shader_3d.clone(),
-            shader_frag: shader_pillar.clone(),
-            data: ArtData::new(Mat4::from_scale_rotation_translation(
-                Vec3::new(0.53, 0.499, 0.53),
-                Quat::from_rotation_y(0_f32.to_radians()),
-                pillar_pos.into(),
-            )),
-            ..Default::default()
+    for (i, pillar_pos) in pillars.into_iter().enumerate() {
+        art_objects.push(ArtObject::builder()
+            .name(format!("Pillar {i:2}"))
+            .model(model_cube.clone())
+            .vert_shader(shader_3d.clone())
+            .frag_shader(shader_pillar.clone())
+            .transparent()
+            .scale([0.53, 0.499, 0.53])
+            .at(pillar_pos)
+            .groups(["pillars"])
+            .build()?);
+    }
+
+    // without physics they would float in the air
+    #[cfg(feature = "physics")]
+    {
+        use crate::physics::PropShape;
+
+        let shader_prop = Arc::new(HotShader::new_frag("assets/shaders/prop.frag"));
+        let props = [
+            ("Red Ball", PropShape::Ball, 0.15, [-0.8, 1.0, -3.0], Color32::from_rgb(220, 70, 50)),
+            ("Blue Ball", PropShape::Ball, 0.2, [0.6, 1.2, -4.0], Color32::from_rgb(60, 110, 220)),
+            ("Crate", PropShape::Cube, 0.2, [0.0, 0.5, -7.0], Color32::from_rgb(180, 130, 70)),
+            ("Small Crate", PropShape::Cube, 0.12, [0.1, 1.2, -7.1], Color32::from_rgb(200, 160, 90)),
+        ];
+        for (name, shape, size, position, color) in props {
+            art_objects.push(ArtObject::builder()
+                .name(name)
+                .model(model_cube.clone())
+                .vert_shader(shader_3d.clone())
+                .frag_shader(shader_prop.clone())
+                .frag_depth()
+                .color("Color", color)
+                .on_update(move |data, _| data.option_values[1].x = shape.shader_index())
+                .prop(shape)
+                .scale(Vec3::splat(size))
+                .at(position)
+                .groups(["props"])
+                .build()?);
         }
-    }));
+    }
+
+    art_objects.extend(get_isf_objects(Path::new(ISF_DIR), &model_square, &shader_2d));
 
-    for art in art_objects.iter_mut() {
-        art.save_options();
+    match FontAtlas::new() {
+        Ok(atlas) => {
+            let plaques = get_plaque_objects(&art_objects, &atlas, &model_square);
+            art_objects.extend(plaques);
+        }
+        Err(err) => log::error!("failed to create font atlas, exhibits have no plaques: {err:#}"),
     }
 
+    for art in art_objects.iter() {
+        if let Some(ArtParent { node: ParentNode::Art(parent), .. }) = art.parent.as_ref() {
+            if !art_objects.iter().any(|other| other.name == *parent) {
+                log::error!("parent {parent} of {} does not exist", art.name);
+            }
+        }
+    }
+    for art in art_objects.iter_mut().filter(|art| !art.options.is_empty()) {
+        match Presets::load(Path::new(PRESET_DIR), &art.file_stem()) {
+            Ok(presets) => art.presets = presets,
+            Err(err) => log::error!("failed to load presets of {}: {err:#}", art.name),
+        }
+    }
     Ok(art_objects)
 }
 
-fn goes_through_rect(p0: Vec3, p1: Vec3, matrix: Mat4) -> bool {
-    let dir = p1 - p0;
-    let p_norm = matrix.inverse().transpose().transform_vector3(Vec3::new(0., 0., 1.));
-    let p_pos = matrix.transform_point3(Vec3::new(0., 0., 0.));
-    let dot = p_norm.dot(dir);
-    if dot == 0.0 {
-        return false; // segment [p0,p1] parallel to plane
+/// Creates 2D exhibits for the ISF shaders in `dir`. Shaders that cannot be parsed are skipped.
+fn get_isf_objects(dir: &Path, model: &Arc<NormalizedObj>, shader_vert: &Arc<HotShader>) -> Vec<ArtObject> {
+    let mut paths = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "fs"))
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            log::debug!("not loading ISF shaders from {}: {err}", dir.display());
+            return Vec::new();
+        }
+    };
+    paths.sort();
+    if paths.len() > ISF_POSITIONS.len() {
+        log::warn!("only the first {} of {} ISF shaders are exhibited", ISF_POSITIONS.len(), paths.len());
     }
-    let w = p0 - p_pos;
-    let fac = -p_norm.dot(w) / dot;
-    if !(0.0..1.0).contains(&fac) {
-        return false; // segment [p0,p1] not passing through plane
+
+    let mut art_objects = Vec::new();
+    for path in paths {
+        let isf = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| IsfShader::parse(&source));
+        let isf = match isf {
+            Ok(isf) => isf,
+            Err(err) => {
+                log::error!("failed to load ISF shader {}: {err:#}", path.display());
+                continue;
+            }
+        };
+        let Some(position) = ISF_POSITIONS.get(art_objects.len()) else { break };
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        log::info!("loaded ISF shader {name}");
+        let mut builder = ArtObject::builder()
+            .name(name)
+            .model(model.clone())
+            .vert_shader(shader_vert.clone())
+            .frag_shader(Arc::new(HotShader::new_isf(path.clone())))
+            .options(isf.options())
+            .scale(Vec3::splat(0.5))
+            .rotate_y(-90.)
+            .at(*position)
+            .groups(["2D", "ISF"]);
+        if isf.has_image_input() {
+            builder = builder.texture(TextureSource::Webcam);
+        }
+        if let Some(description) = isf.header.description.as_ref() {
+            builder = builder.description(description);
+        }
+        match builder.build() {
+            Ok(art) => art_objects.push(art),
+            Err(err) => log::error!("failed to load ISF shader {}: {err:#}", path.display()),
+        }
     }
-    let inter = p0 + dir * fac;
-    let corner0 = matrix.transform_point3(Vec3::new(-1., -1., 0.) * FRAC_1_SQRT_2);
-    let corner1 = matrix.transform_point3(Vec3::new( 1.,  1., 0.) * FRAC_1_SQRT_2);
-    (corner0 - inter).dot(corner1 - inter) < 0.0
+    art_objects
+}
+
+/// Creates a plaque showing the name and description for each exhibit with a description.
+/// Plaques of 2D exhibits hang on the wall next to them, the others are on the front of the pillar.
+fn get_plaque_objects(
+    art_objects: &[ArtObject],
+    atlas: &FontAtlas,
+    model_square: &Arc<NormalizedObj>,
+) -> Vec<ArtObject> {
+    let shader_vert = Arc::new(HotShader::new_vert("assets/shaders/plaque.vert"));
+    let shader_frag = Arc::new(HotShader::new_frag("assets/shaders/plaque.frag"));
+    let texture = TextureSource::Generated(atlas.image());
+
+    art_objects.iter().filter_map(|art| {
+        let description = art.description.as_ref()?;
+        let (scale, _, position) = art.data.matrix.to_scale_rotation_translation();
+        // 2D exhibits are seen from their negative z side, the plaque is right of them
+        let (rotation, offset) = if Arc::ptr_eq(&art.model, model_square) {
+            (Quat::from_rotation_y(180_f32.to_radians()), Vec3::new(-scale.x - PLAQUE_GAP, -0.2, -0.01))
+        } else {
+            // face the middle of the room
+            let side = if position.x < 0. { 1. } else { -1. };
+            let rotation = Quat::from_rotation_y(side * 90_f32.to_radians());
+            (rotation, Vec3::new(side * (PILLAR_HALF_WIDTH + 0.01), -0.8, 0.))
+        };
+        let plaque = ArtObject::builder()
+            .name(format!("{} Plaque", art.name))
+            .model(Arc::new(atlas.plaque_model(&art.name, description)))
+            .vert_shader(shader_vert.clone())
+            .frag_shader(shader_frag.clone())
+            .texture(texture.clone())
+            .transparent()
+            // undo the scale of the exhibit so the offset and the plaque are in meters
+            .parent(
+                ParentNode::Art(art.name.clone()),
+                Mat4::from_scale(scale.recip()) * Mat4::from_rotation_translation(rotation, offset),
+            )
+            .groups(["plaques"])
+            .build();
+        match plaque {
+            Ok(plaque) => Some(plaque),
+            Err(err) => {
+                log::error!("failed to create plaque of {}: {err:#}", art.name);
+                None
+            }
+        }
+    }).collect()
 }
@@ -0,0 +1,284 @@
+//! Attract mode for unattended exhibitions.
+//!
+//! After some time without input the camera follows a path through the gallery while the options
+//! of the nearest exhibit slowly change. Any input gives the control back to the visitor and the
+//! options are restored. The path is recorded by walking it with the GUI open and stored in
+//! `attract.json`. Without a recording the camera visits the visible exhibits one after another,
+//! straight through any walls in between.
+
+use crate::{
+    animation::{Easing, Interpolate, Keyframe, Keyframes, Repeat},
+    art::ArtObject,
+    camera::Camera,
+    settings::{Attract, Bookmark},
+};
+
+use std::f32::consts::{PI, TAU};
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+
+/// File the recorded path is stored in.
+pub const ATTRACT_PATH: &str = "attract.json";
+/// Seconds between the poses sampled while recording.
+const RECORD_INTERVAL: f32 = 0.5;
+/// Seconds the generated tour stays at each exhibit.
+const TOUR_STAY: f32 = 8.;
+/// Seconds the generated tour takes from one exhibit to the next.
+const TOUR_WALK: f32 = 4.;
+/// Seconds between changes of the options of the nearest exhibit.
+const OPTION_INTERVAL: f32 = 10.;
+/// Seconds the options take to blend to their new values.
+const OPTION_BLEND: f32 = 6.;
+
+impl Interpolate for Bookmark {
+    fn interpolate(&self, other: &Self, t: f32) -> Self {
+        Self {
+            position: self.position.interpolate(&other.position, t),
+            yaw: self.yaw.interpolate(&other.yaw, t),
+            pitch: self.pitch.interpolate(&other.pitch, t),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AttractMode {
+    /// Seconds since the last input.
+    idle: f32,
+    /// Set by `input` until the next `update`.
+    interrupted: bool,
+    tour: Option<Tour>,
+    recording: Option<Recording>,
+}
+
+struct Tour {
+    path: Keyframes<Bookmark>,
+    time: f32,
+    /// Option values of the exhibits by name before the tour, restored afterwards.
+    saved_options: Vec<(String, Vec<f32>)>,
+    /// Seconds until the options of the nearest exhibit change.
+    next_change: f32,
+    blend: Option<OptionBlend>,
+}
+
+/// Interpolates the options of the exhibit at `art_idx` from `from` to `to`.
+struct OptionBlend {
+    art_idx: usize,
+    from: Vec<f32>,
+    to: Vec<f32>,
+    progress: f32,
+}
+
+struct Recording {
+    path: Keyframes<Bookmark>,
+    time: f32,
+    next_sample: f32,
+}
+
+impl AttractMode {
+    /// Called for every input of the visitor, stops the tour on the next `update`.
+    pub fn input(&mut self) {
+        self.idle = 0.;
+        self.interrupted = true;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Starts the tour after `settings.idle_timeout` seconds without input or moves the camera along it.
+    pub fn update(&mut self, elapsed: f32, settings: Attract, art_objects: &mut [ArtObject], camera: &mut Camera) {
+        if let Some(recording) = self.recording.as_mut() {
+            recording.update(elapsed, camera);
+        }
+        if std::mem::take(&mut self.interrupted) || !settings.enabled {
+            self.stop(art_objects);
+        }
+        self.idle += elapsed;
+        let idle = settings.enabled && self.idle >= settings.idle_timeout;
+        if self.tour.is_none() && self.recording.is_none() && idle {
+            self.start(art_objects, camera);
+        }
+        let Some(tour) = self.tour.as_mut() else { return };
+
+        tour.time += elapsed;
+        if let Some(pose) = tour.path.sample(tour.time) {
+            pose.apply(camera);
+        }
+        if settings.animate_options {
+            tour.update_options(elapsed, art_objects);
+        }
+    }
+
+    fn start(&mut self, art_objects: &[ArtObject], camera: &Camera) {
+        let path = match load_path(Path::new(ATTRACT_PATH)) {
+            Ok(Some(path)) => path,
+            Ok(None) => generated_path(art_objects, camera),
+            Err(err) => {
+                log::error!("failed to load the attract path, visiting the exhibits instead: {err:#}");
+                generated_path(art_objects, camera)
+            }
+        };
+        if path.keyframes().is_empty() {
+            return;
+        }
+        log::info!("starting attract mode after {:.0} seconds without input", self.idle);
+        self.tour = Some(Tour {
+            path,
+            time: 0.,
+            saved_options: art_objects.iter()
+                .filter(|art| !art.options.is_empty())
+                .map(|art| (art.name.clone(), art.option_values()))
+                .collect(),
+            next_change: OPTION_INTERVAL,
+            blend: None,
+        });
+    }
+
+    /// Gives the control back at the current pose and restores the options.
+    fn stop(&mut self, art_objects: &mut [ArtObject]) {
+        let Some(tour) = self.tour.take() else { return };
+        log::info!("stopping attract mode");
+        for (name, values) in tour.saved_options {
+            if let Some(art) = art_objects.iter_mut().find(|art| art.name == name) {
+                art.load_option_values(&values);
+                art.save_options();
+            }
+        }
+    }
+
+    /// Samples the camera pose until `stop_recording` is called.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(Recording { path: Keyframes::new(Repeat::Loop), time: 0., next_sample: 0. });
+    }
+
+    /// Saves the recorded path to `ATTRACT_PATH`, it is played by the next tour.
+    pub fn stop_recording(&mut self) -> anyhow::Result<()> {
+        let Some(recording) = self.recording.take() else { return Ok(()) };
+        let json = serde_json::to_string_pretty(&recording.path)?;
+        fs::write(ATTRACT_PATH, json).with_context(|| format!("failed to write {ATTRACT_PATH}"))?;
+        log::info!("saved attract path of {:.0} seconds", recording.path.duration());
+        Ok(())
+    }
+}
+
+impl Tour {
+    /// Blends the options of the nearest exhibit to random values every `OPTION_INTERVAL` seconds.
+    fn update_options(&mut self, elapsed: f32, art_objects: &mut [ArtObject]) {
+        if let Some(blend) = self.blend.as_mut() {
+            blend.progress = (blend.progress + elapsed / OPTION_BLEND).min(1.);
+            if let Some(art) = art_objects.get_mut(blend.art_idx) {
+                let t = Easing::EaseInOut.apply(blend.progress);
+                art.load_option_values(&blend.from.interpolate(&blend.to, t));
+                art.save_options();
+            }
+            if blend.progress >= 1. {
+                self.blend = None;
+            }
+        }
+
+        self.next_change -= elapsed;
+        if self.next_change > 0. || self.blend.is_some() {
+            return;
+        }
+        self.next_change = OPTION_INTERVAL;
+        let nearest = art_objects.iter()
+            .enumerate()
+            .filter(|(_, art)| !art.options.is_empty() && art.enable_pipeline && !art.hidden)
+            .min_by(|(_, a), (_, b)| a.data.dist_to_camera_sqr.total_cmp(&b.data.dist_to_camera_sqr));
+        let Some((art_idx, _)) = nearest else { return };
+        let art = &mut art_objects[art_idx];
+        let from = art.option_values();
+        art.randomize_options();
+        let to = art.option_values();
+        art.load_option_values(&from);
+        self.blend = Some(OptionBlend { art_idx, from, to, progress: 0. });
+    }
+}
+
+impl Recording {
+    fn update(&mut self, elapsed: f32, camera: &Camera) {
+        self.time += elapsed;
+        if self.time >= self.next_sample {
+            self.next_sample += RECORD_INTERVAL;
+            let value = Bookmark::from_camera(camera);
+            self.path.insert(Keyframe { time: self.time, value, easing: Easing::Linear });
+        }
+    }
+}
+
+/// `None` if nothing was recorded yet.
+fn load_path(path: &Path) -> anyhow::Result<Option<Keyframes<Bookmark>>> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .with_context(|| format!("invalid attract path {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).context(format!("failed to read {}", path.display())),
+    }
+}
+
+/// Visits the visible exhibits from the nearest to the camera onwards, always going to the nearest
+/// that was not visited yet.
+fn generated_path(art_objects: &[ArtObject], camera: &Camera) -> Keyframes<Bookmark> {
+    let mut remaining = art_objects.iter()
+        .filter(|art| art.enable_pipeline && !art.hidden && art.passes.scene && !art.is_mirror)
+        .map(ArtObject::position)
+        .collect::<Vec<_>>();
+    let mut camera = *camera;
+    let mut poses = Vec::with_capacity(remaining.len());
+    while let Some(idx) = (0..remaining.len()).min_by(|&a, &b| {
+        let dist = |idx: usize| camera.position.distance_squared(remaining[idx]);
+        dist(a).total_cmp(&dist(b))
+    }) {
+        camera.view_target(remaining.swap_remove(idx));
+        poses.push(Bookmark::from_camera(&camera));
+    }
+    tour_path(&poses)
+}
+
+/// Stays at each pose for `TOUR_STAY` seconds and returns to the first one at the end.
+/// The yaw turns the short way between the poses.
+fn tour_path(poses: &[Bookmark]) -> Keyframes<Bookmark> {
+    let mut path = Keyframes::new(Repeat::Loop);
+    let mut time = 0.;
+    let mut last_yaw = None;
+    for pose in poses {
+        let mut pose = *pose;
+        if let Some(last_yaw) = last_yaw {
+            pose.yaw = last_yaw + (pose.yaw - last_yaw + PI).rem_euclid(TAU) - PI;
+        }
+        last_yaw = Some(pose.yaw);
+        path.insert(Keyframe { time, value: pose, easing: Easing::Linear });
+        path.insert(Keyframe { time: time + TOUR_STAY, value: pose, easing: Easing::EaseInOut });
+        time += TOUR_STAY + TOUR_WALK;
+    }
+    if let (Some(first), Some(last_yaw)) = (poses.first(), last_yaw) {
+        // the loop jumps from the end to the start, so the yaw has to end where it started
+        let turns = ((last_yaw - first.yaw) / TAU).round();
+        let value = Bookmark { yaw: first.yaw + turns * TAU, ..*first };
+        path.insert(Keyframe { time, value, easing: Easing::Linear });
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use glam::Vec3;
+
+    #[test]
+    fn tour_returns_the_short_way() {
+        let pose = |x: f32, yaw: f32| Bookmark { position: Vec3::new(x, 0., 0.), yaw, pitch: 0. };
+        let path = tour_path(&[pose(0., 3.), pose(1., -3.)]);
+        let keyframes = path.keyframes();
+        assert_eq!(keyframes.len(), 5);
+        // -3 is reached by turning past PI instead of back over 0
+        assert!((keyframes[2].value.yaw - (TAU - 3.)).abs() < 1e-5);
+        let last = keyframes.last().unwrap().value;
+        assert_eq!(last.position, Vec3::ZERO);
+        assert!((last.yaw - 3.).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,202 @@
+//! Ambient loop and looped sounds positioned at the exhibits, played with rodio.
+//!
+//! The sounds of the exhibits are spatial, they are panned between the ears of the camera
+//! and fade out with the distance until they are silent at `EMITTER_RANGE`.
+//! Audio shaders are rendered once they are compiled and follow the shader time.
+
+use crate::{
+    art::{ArtObject, Sound},
+    camera::Camera,
+    vulkan::{HotShader, VkApp, SAMPLE_RATE},
+};
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use glam::Vec3;
+use rodio::{
+    buffer::SamplesBuffer, source::SineWave, Decoder, OutputStream, OutputStreamHandle, Sink, Source, SpatialSink,
+};
+use vulkano::shader::ShaderModule;
+
+/// Distance from which the sound of an exhibit can no longer be heard.
+const EMITTER_RANGE: f32 = 8.;
+/// Half the distance between the ears.
+const EAR_OFFSET: f32 = 0.1;
+/// Amplitude of the tones, they are much louder than most audio files otherwise.
+const TONE_AMPLITUDE: f32 = 0.2;
+/// Seconds an audio shader may be ahead or behind the shader time before it is restarted.
+const MAX_DRIFT: f32 = 0.1;
+
+pub struct Audio {
+    // nothing is played anymore once the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    ambient: Option<Sink>,
+    /// Sinks of the exhibits with a sound, found again by name as the indices can change.
+    emitters: Vec<(String, SpatialSink)>,
+    shaders: Vec<ShaderSound>,
+}
+
+/// An exhibit whose sound is rendered by an audio shader.
+struct ShaderSound {
+    name: String,
+    shader: Arc<HotShader>,
+    /// Module the samples were rendered with.
+    rendered: Option<Arc<ShaderModule>>,
+    /// Interleaved stereo samples, empty until the shader is rendered.
+    samples: Vec<f32>,
+    /// Shader time the playback was started from.
+    started: Option<f32>,
+}
+
+impl Audio {
+    /// Starts the ambient loop and the sounds of the exhibits, they are silent until `update`.
+    pub fn new(ambient: Option<&Path>, art_objects: &[ArtObject], vk_app: &VkApp) -> anyhow::Result<Self> {
+        let (stream, handle) = OutputStream::try_default().context("no audio output device")?;
+        let ambient = ambient_sink(ambient, &handle)?;
+
+        let mut emitters = Vec::new();
+        let mut shaders = Vec::new();
+        for art in art_objects {
+            let Some(sound) = art.sound.as_ref() else { continue };
+            let ears = [[-EAR_OFFSET, 0., 0.], [EAR_OFFSET, 0., 0.]];
+            let sink = SpatialSink::try_new(&handle, art.position().to_array(), ears[0], ears[1])?;
+            sink.set_volume(0.);
+            match sound {
+                Sound::File(path) => match load_loop(path) {
+                    Ok(source) => sink.append(source),
+                    Err(err) => {
+                        log::error!("failed to load sound of {} {}: {err:#}", art.name, path.display());
+                        continue;
+                    }
+                },
+                Sound::Tone(frequency) => sink.append(SineWave::new(*frequency).amplify(TONE_AMPLITUDE)),
+                Sound::Shader(path) => {
+                    let shader = Arc::new(HotShader::new_sound(path));
+                    vk_app.register_shader(&shader);
+                    shaders.push(ShaderSound {
+                        name: art.name.clone(),
+                        shader,
+                        rendered: None,
+                        samples: Vec::new(),
+                        started: None,
+                    });
+                }
+            }
+            emitters.push((art.name.clone(), sink));
+        }
+        Ok(Self { _stream: stream, handle, ambient, emitters, shaders })
+    }
+
+    /// Replaces the ambient loop, e.g. when another scene is loaded. It is silent until `update`.
+    pub fn set_ambient(&mut self, ambient: Option<&Path>) -> anyhow::Result<()> {
+        self.ambient = ambient_sink(ambient, &self.handle)?;
+        Ok(())
+    }
+
+    /// Moves the ears to the camera and sets the volumes, both are from 0 to 1.
+    pub fn update(&self, camera: &Camera, art_objects: &[ArtObject], ambient_volume: f32, effects_volume: f32) {
+        if let Some(ambient) = self.ambient.as_ref() {
+            ambient.set_volume(ambient_volume);
+        }
+
+        let right = Vec3::new(camera.angle_yaw.cos(), 0., camera.angle_yaw.sin());
+        let left_ear = (camera.position - right * EAR_OFFSET).to_array();
+        let right_ear = (camera.position + right * EAR_OFFSET).to_array();
+        for (name, sink) in self.emitters.iter() {
+            let Some(art) = art_objects.iter().find(|art| art.name == *name) else {
+                sink.set_volume(0.);
+                continue;
+            };
+            let position = art.position();
+            let falloff = (1. - camera.position.distance(position) / EMITTER_RANGE).clamp(0., 1.);
+            let volume = if art.hidden || art.sound_muted { 0. } else { effects_volume * falloff * falloff };
+            sink.set_emitter_position(position.to_array());
+            sink.set_left_ear_position(left_ear);
+            sink.set_right_ear_position(right_ear);
+            sink.set_volume(volume);
+        }
+    }
+
+    /// Renders the audio shaders once they are compiled and keeps them in sync with the shader time.
+    pub fn update_shaders(&mut self, vk_app: &VkApp, time: f32) {
+        for sound in self.shaders.iter_mut() {
+            let Some((_, sink)) = self.emitters.iter().find(|(name, _)| *name == sound.name) else { continue };
+            sound.shader.reload(false);
+            if let Ok(Some(module)) = sound.shader.get_module() {
+                if !sound.rendered.as_ref().is_some_and(|rendered| Arc::ptr_eq(rendered, &module)) {
+                    match vk_app.render_sound(module.clone()) {
+                        Ok(samples) => {
+                            sound.samples = samples;
+                            sound.started = None;
+                        }
+                        Err(err) => log::error!("failed to render audio shader of {}: {err:#}", sound.name),
+                    }
+                    sound.rendered = Some(module);
+                }
+            }
+            if sound.samples.is_empty() {
+                continue;
+            }
+
+            // the position of the sink only advances while samples are played
+            let drift = sound.started.map(|start| start + sink.get_pos().as_secs_f32() - time);
+            if drift.is_none_or(|drift| drift.abs() > MAX_DRIFT) {
+                let source = SamplesBuffer::new(2, SAMPLE_RATE, loop_from(&sound.samples, time));
+                sink.clear();
+                sink.append(source.repeat_infinite());
+                sink.play();
+                sound.started = Some(time);
+            }
+        }
+    }
+}
+
+/// Rotates the interleaved stereo samples so they start at `time` seconds into the loop.
+fn loop_from(samples: &[f32], time: f32) -> Vec<f32> {
+    let frames = samples.len() / 2;
+    let frame = (time.max(0.) as f64 * SAMPLE_RATE as f64) as usize % frames.max(1);
+    let mut samples = samples.to_vec();
+    samples.rotate_left(frame * 2);
+    samples
+}
+
+/// A muted sink playing the ambient loop, `None` if there is none or it fails to load.
+fn ambient_sink(ambient: Option<&Path>, handle: &OutputStreamHandle) -> anyhow::Result<Option<Sink>> {
+    Ok(match ambient.map(|path| (path, load_loop(path))) {
+        Some((_, Ok(source))) => {
+            let sink = Sink::try_new(handle)?;
+            sink.set_volume(0.);
+            sink.append(source);
+            Some(sink)
+        }
+        Some((path, Err(err))) => {
+            log::error!("failed to load ambient sound {}: {err:#}", path.display());
+            None
+        }
+        None => None,
+    })
+}
+
+/// Decodes the audio file at `path` and repeats it forever.
+fn load_loop(path: &Path) -> anyhow::Result<impl Source<Item = i16> + Send + 'static> {
+    let decoder = Decoder::new(crate::fs::load(path)?).context("failed to decode")?;
+    Ok(decoder.repeat_infinite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loop_starts_at_time() {
+        let samples = (0..8).map(|i| i as f32).collect::<Vec<_>>();
+        let second = 1. / SAMPLE_RATE as f32;
+        assert_eq!(loop_from(&samples, 0.), samples);
+        assert_eq!(loop_from(&samples, 1.5 * second), [2., 3., 4., 5., 6., 7., 0., 1.]);
+        // wraps around after the 4 frames
+        assert_eq!(loop_from(&samples, 5.5 * second), [2., 3., 4., 5., 6., 7., 0., 1.]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Levels of frequency bands of the default audio input, they can drive options, see `crate::binding`.
+//!
+//! Capturing needs the `audio` feature, without it `AudioInput::start` always fails.
+
+use std::collections::VecDeque;
+use std::f32::consts::{SQRT_2, TAU};
+use std::sync::{Arc, Mutex};
+
+/// Center frequencies of the octave bands in hertz.
+pub const BAND_FREQUENCIES: [f32; 8] = [63., 125., 250., 500., 1000., 2000., 4000., 8000.];
+/// Number of the most recent samples the levels are computed from.
+const WINDOW: usize = 2048;
+/// Levels at or below this many decibels are 0.
+const MIN_DECIBELS: f32 = -60.;
+
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+pub struct AudioInput {
+    /// Mono samples, the newest last.
+    samples: Arc<Mutex<VecDeque<f32>>>,
+    sample_rate: u32,
+    #[cfg(feature = "audio")]
+    _stream: rodio::cpal::Stream,
+}
+
+impl AudioInput {
+    #[cfg(feature = "audio")]
+    pub fn start() -> anyhow::Result<Self> {
+        use anyhow::Context;
+        use rodio::cpal::{
+            self,
+            traits::{DeviceTrait, HostTrait, StreamTrait},
+            SampleFormat,
+        };
+
+        let device = cpal::default_host().default_input_device().context("no audio input device")?;
+        let config = device.default_input_config()?;
+        let channels = config.channels() as usize;
+        let sample_rate = config.sample_rate().0;
+        let samples = Arc::new(Mutex::new(VecDeque::with_capacity(WINDOW)));
+        let on_error = |err| log::error!("audio input failed: {err}");
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => {
+                let samples = samples.clone();
+                device.build_input_stream(&config.config(), move |data: &[f32], _: &_| {
+                    push_frames(&samples, data.iter().copied(), channels);
+                }, on_error, None)?
+            }
+            SampleFormat::I16 => {
+                let samples = samples.clone();
+                device.build_input_stream(&config.config(), move |data: &[i16], _: &_| {
+                    push_frames(&samples, data.iter().map(|&sample| sample as f32 / i16::MAX as f32), channels);
+                }, on_error, None)?
+            }
+            format => anyhow::bail!("unsupported sample format {format}"),
+        };
+        stream.play()?;
+        log::info!("capturing audio input {}", device.name().unwrap_or_default());
+        Ok(Self { samples, sample_rate, _stream: stream })
+    }
+
+    #[cfg(not(feature = "audio"))]
+    pub fn start() -> anyhow::Result<Self> {
+        anyhow::bail!("compiled without the audio feature")
+    }
+
+    /// Levels of the bands in `BAND_FREQUENCIES` from 0 to 1.
+    pub fn bands(&self) -> Vec<f32> {
+        let samples = match self.samples.lock() {
+            Ok(samples) => samples.iter().copied().collect::<Vec<_>>(),
+            Err(_) => return Vec::new(),
+        };
+        band_levels(&samples, self.sample_rate)
+    }
+}
+
+/// Mixes the interleaved frames down to mono and keeps the last `WINDOW` samples.
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+fn push_frames(samples: &Mutex<VecDeque<f32>>, data: impl Iterator<Item = f32>, channels: usize) {
+    let Ok(mut samples) = samples.lock() else { return };
+    let mut data = data.peekable();
+    while data.peek().is_some() {
+        let sum = data.by_ref().take(channels).sum::<f32>();
+        samples.push_back(sum / channels as f32);
+    }
+    let excess = samples.len().saturating_sub(WINDOW);
+    samples.drain(..excess);
+}
+
+/// Level of each band in `BAND_FREQUENCIES`, the loudest of three frequencies spread over the octave
+/// mapped from `MIN_DECIBELS` to 0 dB.
+pub fn band_levels(samples: &[f32], sample_rate: u32) -> Vec<f32> {
+    if samples.is_empty() {
+        return vec![0.; BAND_FREQUENCIES.len()];
+    }
+    // a Hann window reduces the leakage from the other bands
+    let n = samples.len();
+    let windowed = samples.iter().enumerate()
+        .map(|(i, sample)| sample * (0.5 - 0.5 * (TAU * i as f32 / n as f32).cos()))
+        .collect::<Vec<_>>();
+    BAND_FREQUENCIES.iter().map(|&center| {
+        let amplitude = [center / SQRT_2.sqrt(), center, center * SQRT_2.sqrt()].into_iter()
+            .map(|frequency| goertzel(&windowed, frequency / sample_rate as f32))
+            // the Hann window halves the amplitude
+            .fold(0., f32::max) * 4. / n as f32;
+        let decibels = 20. * amplitude.max(1e-6).log10();
+        (1. - decibels / MIN_DECIBELS).clamp(0., 1.)
+    }).collect()
+}
+
+/// Magnitude of the frequency given in cycles per sample.
+fn goertzel(samples: &[f32], frequency: f32) -> f32 {
+    let coefficient = 2. * (TAU * frequency).cos();
+    let (mut s1, mut s2) = (0., 0.);
+    for &sample in samples {
+        let s0 = sample + coefficient * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    (s1 * s1 + s2 * s2 - coefficient * s1 * s2).max(0.).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_is_in_its_band() {
+        let sample_rate = 44_100;
+        let samples = (0..WINDOW)
+            .map(|i| 0.5 * (TAU * 1000. * i as f32 / sample_rate as f32).sin())
+            .collect::<Vec<_>>();
+        let levels = band_levels(&samples, sample_rate);
+        let loudest = (0..levels.len()).max_by(|&a, &b| levels[a].total_cmp(&levels[b])).unwrap();
+        assert_eq!(loudest, 4);
+        // -6 dB
+        assert!((levels[4] - 0.9).abs() < 0.03, "{levels:?}");
+        assert!(levels[0] < 0.5, "{levels:?}");
+        assert_eq!(band_levels(&[], sample_rate), vec![0.; 8]);
+    }
+}
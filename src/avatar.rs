@@ -0,0 +1,159 @@
+//! The body of the player, the exhibit `AVATAR_NAME` attached to `ParentNode::Player`.
+//!
+//! The camera is inside of it, so by default it is only drawn in the mirror. The model, its
+//! placement and where it is drawn are set in the `avatar` section of `settings.json`, e.g.
+//! `{ "avatar": { "model": "assets/models/robot.obj", "scale": 0.5, "first_person": true } }`
+//! or a generated shape like `"model": "primitive:icosphere"`. Scanned models can be welded with
+//! `"weld": 0.001`.
+//!
+//! Every frame the walk state is written to the first option slot of the avatar, so its shaders
+//! can animate it: x is the horizontal speed in units per second, y the phase of the walk cycle
+//! in radians, z is 1 while crouched and w is 1 in fly mode.
+
+use crate::{
+    art::{ArtObject, Passes},
+    camera::Camera,
+    model::obj::NormalizedObj,
+};
+
+use std::f32::consts::TAU;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use glam::{Mat4, Quat, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+/// Name of the exhibit that is the body of the player.
+pub const AVATAR_NAME: &str = "Player";
+/// Walk cycles per unit walked.
+const STRIDE_FREQUENCY: f32 = 0.8;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvatarSettings {
+    /// Obj file of the body, the model of the exhibit is kept if `None`. It is fitted into the unit
+    /// container before `scale` is applied, so exports in any units have the same size.
+    pub model: Option<PathBuf>,
+    /// Merges positions of the model closer than this, e.g. to close the seams of scanned meshes,
+    /// see `crate::model::obj::Obj::weld`.
+    pub weld: Option<f32>,
+    pub scale: f32,
+    /// Position relative to the camera, x is to the right, y up and -z forward.
+    pub offset: Vec3,
+    /// Rotation around the vertical axis in degrees.
+    pub yaw: f32,
+    /// Also drawn in the scene, e.g. for a body that is below the view.
+    pub first_person: bool,
+    pub in_mirror: bool,
+    /// How far the body moves up and down while walking, in units.
+    pub bob_height: f32,
+}
+
+impl Default for AvatarSettings {
+    fn default() -> Self {
+        Self {
+            model: None,
+            weld: None,
+            scale: 0.4,
+            offset: Vec3::new(0., -1., 1.),
+            yaw: 90.,
+            first_person: false,
+            in_mirror: true,
+            bob_height: 0.03,
+        }
+    }
+}
+
+impl AvatarSettings {
+    /// Transform relative to the player while standing still.
+    fn local_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(
+            Vec3::splat(self.scale),
+            Quat::from_rotation_y(self.yaw.to_radians()),
+            self.offset,
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Avatar {
+    pub settings: AvatarSettings,
+    /// Phase of the walk cycle in radians.
+    walk_phase: f32,
+}
+
+impl Avatar {
+    pub fn new(settings: AvatarSettings) -> Self {
+        Self { settings, walk_phase: 0. }
+    }
+
+    /// Applies the settings to the avatar exhibit and returns its index if its model was replaced,
+    /// the GPU buffers of the model must then be recreated.
+    pub fn apply(&self, art_objects: &mut [ArtObject]) -> anyhow::Result<Option<usize>> {
+        let Some(idx) = art_objects.iter().position(|art| art.name == AVATAR_NAME) else {
+            return Ok(None);
+        };
+        let art = &mut art_objects[idx];
+        art.passes = Passes { scene: self.settings.first_person, mirror: self.settings.in_mirror };
+        if let Some(parent) = art.parent.as_mut() {
+            parent.local_matrix = self.settings.local_matrix();
+        }
+        let Some(path) = self.settings.model.as_ref() else { return Ok(None) };
+        let model = NormalizedObj::load_welded(path, self.settings.weld)
+            .with_context(|| format!("failed to load avatar model {}", path.display()))?;
+        art.auto_fit = true;
+        art.set_model(Arc::new(model));
+        Ok(Some(idx))
+    }
+
+    /// Advances the walk cycle, bobs the avatar and writes the walk state to its options.
+    /// Must be called before `resolve_parents`.
+    pub fn update(&mut self, art_objects: &mut [ArtObject], camera: &Camera, elapsed: f32) {
+        let Some(art) = art_objects.iter_mut().find(|art| art.name == AVATAR_NAME) else { return };
+        let velocity = camera.velocity();
+        let speed = if camera.fly_mode { 0. } else { Vec3::new(velocity.x, 0., velocity.z).length() };
+        if speed > 0. {
+            self.walk_phase = (self.walk_phase + speed * elapsed * STRIDE_FREQUENCY * TAU) % TAU;
+        } else {
+            // settle at the bottom of the cycle instead of freezing mid step
+            self.walk_phase = ease_to_rest(self.walk_phase, elapsed);
+        }
+        let bob = self.settings.bob_height * (1. - self.walk_phase.cos()) / 2.;
+        if let Some(parent) = art.parent.as_mut() {
+            parent.local_matrix = Mat4::from_translation(Vec3::Y * bob) * self.settings.local_matrix();
+        }
+        art.data.option_values[0] = Vec4::new(
+            speed,
+            self.walk_phase,
+            camera.crouched as u8 as f32,
+            camera.fly_mode as u8 as f32,
+        );
+    }
+}
+
+/// Moves `phase` towards the nearest multiple of `TAU` in about a quarter second.
+fn ease_to_rest(phase: f32, elapsed: f32) -> f32 {
+    let target = if phase > TAU / 2. { TAU } else { 0. };
+    let phase = target + (phase - target) * (-12. * elapsed).exp();
+    phase % TAU
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_cycle_comes_to_rest() {
+        let mut phase = 1.;
+        for _ in 0..60 {
+            phase = ease_to_rest(phase, 1. / 60.);
+        }
+        assert!(phase < 1e-3, "{phase}");
+        let mut phase = TAU - 1.;
+        for _ in 0..60 {
+            phase = ease_to_rest(phase, 1. / 60.);
+        }
+        assert!(phase < 1e-3 || TAU - phase < 1e-3, "{phase}");
+    }
+}
@@ -0,0 +1,151 @@
+//! Bindings that drive the options of an exhibit without code.
+//!
+//! A binding maps the value of a driver in [0, 1] to the range `min` to `max` and writes it to one
+//! component of an option. The bindings of all exhibits are stored in the `bindings` of the layout, e.g.
+//! `{ "Portal": [{ "option": "Ball Size", "driver": { "Sine": { "period": 4 } }, "max": 0.1 }] }`.
+
+use crate::art::ArtObject;
+
+use std::collections::{BTreeMap, HashMap};
+use std::f32::consts::TAU;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Driver {
+    /// A sine wave of the shader time with the period and phase in seconds.
+    Sine {
+        period: f32,
+        #[serde(default)]
+        phase: f32,
+    },
+    /// The level of a frequency band of the audio input, see `crate::audio_input`.
+    AudioBand { band: usize },
+    /// The distance of the camera to the exhibit, 0 at `near` and 1 at `far`. Ignored if they are equal.
+    CameraDistance { near: f32, far: f32 },
+    /// A control change message on the MIDI channel from 0 to 15, see `crate::midi`.
+    MidiCc { channel: u8, controller: u8 },
+}
+
+/// The values drivers are evaluated with, inputs that are not available are empty.
+#[derive(Debug, Default, Clone)]
+pub struct DriverInputs {
+    pub time: f32,
+    pub audio_bands: Vec<f32>,
+    /// Last value of the controllers by channel and controller number.
+    pub midi_cc: HashMap<(u8, u8), f32>,
+}
+
+impl Driver {
+    /// Returns the value in [0, 1] or `None` if the input is not available.
+    pub fn value(&self, inputs: &DriverInputs, camera_distance: f32) -> Option<f32> {
+        let value = match *self {
+            Self::Sine { period, phase } if period > 0. => {
+                0.5 - 0.5 * ((inputs.time + phase) / period * TAU).cos()
+            }
+            Self::Sine { .. } => return None,
+            Self::AudioBand { band } => *inputs.audio_bands.get(band)?,
+            Self::CameraDistance { near, far } if near != far => (camera_distance - near) / (far - near),
+            Self::CameraDistance { .. } => return None,
+            Self::MidiCc { channel, controller } => *inputs.midi_cc.get(&(channel, controller))?,
+        };
+        Some(value.clamp(0., 1.))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    /// Label of the option.
+    pub option: String,
+    pub driver: Driver,
+    /// Index of the value for options with several, like colors or vectors.
+    #[serde(default)]
+    pub component: usize,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default = "default_max")]
+    pub max: f32,
+}
+
+fn default_max() -> f32 {
+    1.
+}
+
+impl ArtObject {
+    /// Writes the driven values to the bound options, returns `true` if any changed.
+    /// The options still have to be saved for the shader to see them.
+    pub fn apply_bindings(&mut self, inputs: &DriverInputs) -> bool {
+        let camera_distance = self.data.dist_to_camera_sqr.sqrt();
+        let mut changed = false;
+        for binding in self.bindings.iter() {
+            let Some(option) = self.options.iter_mut().find(|option| option.label() == binding.option) else {
+                continue;
+            };
+            let Some(value) = binding.driver.value(inputs, camera_distance) else { continue };
+            let mut values = vec![0.; option.ty.slot_count()];
+            option.ty.save_value(&mut values, &mut 0);
+            let Some(component) = values.get_mut(binding.component) else { continue };
+            *component = binding.min + (binding.max - binding.min) * value;
+            option.ty.load_value(&values, &mut 0);
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Replaces the bindings of the exhibits with `bindings` by exhibit name,
+/// exhibits that are not in it are unbound.
+pub fn set_bindings(bindings: &BTreeMap<String, Vec<Binding>>, art_objects: &mut [ArtObject]) {
+    for art in art_objects.iter_mut() {
+        art.bindings.clear();
+    }
+    for (name, bindings) in bindings {
+        let Some(art) = art_objects.iter_mut().find(|art| art.name == *name) else {
+            log::warn!("bindings for {name}, which does not exist");
+            continue;
+        };
+        for binding in bindings.iter() {
+            if !art.options.iter().any(|option| option.label() == binding.option) {
+                log::warn!("{name} has no option {} to bind", binding.option);
+            }
+            if let Driver::CameraDistance { near, far } = binding.driver {
+                if near == far {
+                    log::warn!("{name} binds {} to a camera distance with near equal to far", binding.option);
+                }
+            }
+        }
+        art.bindings = bindings.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn driver_values() {
+        let mut inputs = DriverInputs { time: 1., ..Default::default() };
+        let sine = Driver::Sine { period: 4., phase: 1. };
+        assert!((sine.value(&inputs, 0.).unwrap() - 1.).abs() < 1e-6);
+        assert_eq!(Driver::AudioBand { band: 2 }.value(&inputs, 0.), None);
+        inputs.midi_cc.insert((0, 7), 0.25);
+        assert_eq!(Driver::MidiCc { channel: 0, controller: 7 }.value(&inputs, 0.), Some(0.25));
+        let distance = Driver::CameraDistance { near: 1., far: 3. };
+        assert_eq!(distance.value(&inputs, 2.), Some(0.5));
+        assert_eq!(distance.value(&inputs, 5.), Some(1.));
+        assert_eq!(Driver::CameraDistance { near: 2., far: 2. }.value(&inputs, 2.), None);
+    }
+
+    #[test]
+    fn parse_binding() {
+        let json = r#"{ "option": "Zoom", "driver": { "Sine": { "period": 4 } }, "max": 2 }"#;
+        let binding: Binding = serde_json::from_str(json).unwrap();
+        assert_eq!(binding, Binding {
+            option: "Zoom".to_owned(),
+            driver: Driver::Sine { period: 4., phase: 0. },
+            component: 0,
+            min: 0.,
+            max: 2.,
+        });
+    }
+}
@@ -1,6 +1,39 @@
 use std::f32::consts::PI;
 
-use glam::{Mat4, Vec3, Vec4};
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+/// Horizontal distance to an exhibit after teleporting to it, close enough to show its options.
+const VIEW_DISTANCE: f32 = 1.2;
+/// Distance of the camera to a flat exhibit that is shown full screen.
+const FULLSCREEN_DISTANCE: f32 = 1.;
+/// How much lower the camera is while crouching.
+const CROUCH_DEPTH: f32 = 0.6;
+/// Factor applied to the speed while crouching.
+const CROUCH_SPEED: f32 = 0.5;
+
+/// Eases the motion in and out instead of starting and stopping instantly.
+/// The rates are per second, the higher they are the faster the input is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Smoothing {
+    pub enabled: bool,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    /// Rate at which the rotation follows the mouse.
+    pub rotation: f32,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            acceleration: 4.,
+            deceleration: 6.,
+            rotation: 10.,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct KeyStates {
@@ -10,6 +43,8 @@ pub struct KeyStates {
     pub right: bool,
     pub up: bool,
     pub down: bool,
+    pub sprint: bool,
+    pub crouch: bool,
     pub lmb: bool,
 }
 
@@ -23,20 +58,55 @@ pub struct Camera {
     pub position: Vec3,
     /// When in fly mode move into the direction the camera is looking, else move on the plane.
     pub fly_mode: bool,
+    /// Whether the position is lowered by crouching.
+    pub crouched: bool,
+    /// Velocity in view space, relative to the yaw in fly mode also to the pitch.
+    velocity: Vec3,
+    /// Rotation of the mouse not yet applied because of the smoothing.
+    pending_rotation: Vec2,
 }
 
 impl Camera {
-    pub fn update(&mut self, key_states: &KeyStates, delta: f32, x_ratio: f32, y_ratio: f32) {
-        if key_states.lmb {
-            self.angle_yaw += x_ratio * PI;
-            self.angle_pitch += y_ratio * PI;
+    /// Moves with `speed` units per second into the direction of the pressed keys.
+    /// Crouching is not possible in fly mode.
+    pub fn update(
+        &mut self,
+        key_states: &KeyStates,
+        mut speed: f32,
+        elapsed: f32,
+        x_ratio: f32,
+        y_ratio: f32,
+        smoothing: &Smoothing,
+    ) {
+        let mut rotation = if key_states.lmb { Vec2::new(x_ratio, y_ratio) * PI } else { Vec2::ZERO };
+        if smoothing.enabled {
+            self.pending_rotation += rotation;
+            rotation = self.pending_rotation * (1. - (-smoothing.rotation * elapsed).exp());
+            self.pending_rotation -= rotation;
         }
-        let translation = Vec4::from_array([
+        self.angle_yaw += rotation.x;
+        self.angle_pitch += rotation.y;
+
+        let crouch = key_states.crouch && !self.fly_mode;
+        if crouch != self.crouched {
+            self.crouched = crouch;
+            self.position.y += if crouch { -CROUCH_DEPTH } else { CROUCH_DEPTH };
+        }
+        if crouch {
+            speed *= CROUCH_SPEED;
+        }
+        let target = Vec3::new(
             (key_states.left    as i8 - key_states.right    as i8) as f32,
             (key_states.down    as i8 - key_states.up       as i8) as f32,
             (key_states.forward as i8 - key_states.backward as i8) as f32,
-            0.
-        ]) * delta * 2.;
+        ) * speed;
+        self.velocity = if smoothing.enabled {
+            let rate = if target == Vec3::ZERO { smoothing.deceleration } else { smoothing.acceleration };
+            target + (self.velocity - target) * (-rate * elapsed).exp()
+        } else {
+            target
+        };
+        let translation = self.velocity.extend(0.) * elapsed;
         let rot = if self.fly_mode {
             Mat4::from_rotation_y(-self.angle_yaw)
                 * Mat4::from_rotation_x(-self.angle_pitch)
@@ -46,9 +116,197 @@ impl Camera {
         self.position += (rot * -translation).truncate();
     }
 
+    /// Moves in front of `target` on the side of the current position and looks at it.
+    pub fn view_target(&mut self, target: Vec3) {
+        let mut dir = self.position - target;
+        dir.y = 0.;
+        let dir = dir.try_normalize().unwrap_or(Vec3::Z);
+        self.position = target + dir * VIEW_DISTANCE;
+        self.angle_yaw = f32::atan2(-dir.x, dir.z);
+        self.angle_pitch = 0.;
+        self.stop();
+    }
+
+    /// Moves the camera by `transform`, only its rotation around the y axis turns the view.
+    /// The motion is kept, so walking through a portal continues on the other side.
+    pub fn transform(&mut self, transform: Mat4) {
+        let (_, rotation, _) = transform.to_scale_rotation_translation();
+        let forward = rotation * Vec3::Z;
+        self.position = transform.transform_point3(self.position);
+        self.angle_yaw -= f32::atan2(forward.x, forward.z);
+    }
+
+    /// Velocity in units per second in view space, see `update`.
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    /// Discards the remaining motion of the smoothing, e.g. after teleporting.
+    pub fn stop(&mut self) {
+        self.velocity = Vec3::ZERO;
+        self.pending_rotation = Vec2::ZERO;
+    }
+
     pub fn view_matrix(&self) -> Mat4 {
         Mat4::from_rotation_x(self.angle_pitch)
             * Mat4::from_rotation_y(self.angle_yaw)
             * Mat4::from_translation(-self.position)
     }
+
+    /// Ray from the camera through the point `cursor` on a screen of size `extent`.
+    /// `fov` is the vertical field of view in degrees.
+    pub fn cursor_ray(&self, cursor: [f32; 2], extent: [f32; 2], fov: f32) -> Ray {
+        let ndc_x = cursor[0] / extent[0] * 2. - 1.;
+        let ndc_y = 1. - cursor[1] / extent[1] * 2.;
+        let tan = (fov.to_radians() / 2.).tan();
+        let aspect = extent[0] / extent[1];
+        let dir = Vec3::new(ndc_x * tan * aspect, ndc_y * tan, -1.);
+        let view_to_world = self.view_matrix().inverse();
+        Ray { origin: self.position, dir: view_to_world.transform_vector3(dir).normalize() }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub dir: Vec3,
+}
+
+impl Ray {
+    pub fn transform(&self, matrix: Mat4) -> Self {
+        Self {
+            origin: matrix.transform_point3(self.origin),
+            dir: matrix.transform_vector3(self.dir),
+        }
+    }
+
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + t * self.dir
+    }
+
+    /// Distance along the ray in units of `dir` to where it enters the box.
+    /// Returns `None` if the box is missed or the origin is inside, like for the skybox.
+    pub fn intersect_box(&self, min: Vec3, max: Vec3) -> Option<f32> {
+        let inv_dir = self.dir.recip();
+        let t1 = (min - self.origin) * inv_dir;
+        let t2 = (max - self.origin) * inv_dir;
+        // NaN from a flat box parallel to the ray is ignored by min and max
+        let t_near = t1.min(t2).max_element();
+        let t_far = t1.max(t2).min_element();
+        (t_near >= 0. && t_near <= t_far).then_some(t_near)
+    }
+}
+
+/// Maps the screen onto the front of a flat exhibit shown full screen, cropping the sides of the
+/// exhibit that do not fit the aspect ratio. Its fragment shader is drawn on a triangle covering the
+/// screen at the resolution of the window, see `assets/shaders/fullscreen.vert`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FullscreenMapping {
+    /// Maps the local space of the exhibit to normalized device coordinates with y up,
+    /// z is the distance in front of the exhibit divided by `FULLSCREEN_DISTANCE`.
+    pub local_to_screen: Mat4,
+}
+
+impl FullscreenMapping {
+    /// `bounds` are the minimum and maximum corner of the exhibit in local space, flat along z.
+    pub fn new([min, max]: [Vec3; 2], aspect: f32) -> Self {
+        let center = (min + max) / 2.;
+        let half = (max - min) / 2.;
+        let (half_width, half_height) = if aspect > half.x / half.y {
+            (half.x, half.x / aspect)
+        } else {
+            (half.y * aspect, half.y)
+        };
+        // the front of flat exhibits faces towards -z in local space, so right on the screen is -x
+        let screen_to_local = Mat4::from_translation(center)
+            * Mat4::from_scale(Vec3::new(-half_width, half_height, -FULLSCREEN_DISTANCE));
+        Self { local_to_screen: screen_to_local.inverse() }
+    }
+
+    /// Ray through the point `cursor` on a screen of size `extent` to the exhibit with the model
+    /// matrix `matrix`, see `Camera::cursor_ray`.
+    pub fn cursor_ray(&self, matrix: Mat4, cursor: [f32; 2], extent: [f32; 2]) -> Ray {
+        let ndc_x = cursor[0] / extent[0] * 2. - 1.;
+        let ndc_y = 1. - cursor[1] / extent[1] * 2.;
+        let origin = self.local_to_screen.inverse().transform_point3(Vec3::new(ndc_x, ndc_y, 1.));
+        Ray { origin, dir: Vec3::Z }.transform(matrix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_target_looks_at_target() {
+        let mut camera = Camera { position: Vec3::new(3., 1.5, -2.), ..Default::default() };
+        let target = Vec3::new(-1., 1.5, 1.);
+        camera.view_target(target);
+        assert!((camera.position.distance(target) - VIEW_DISTANCE).abs() < 1e-5);
+        let target_in_view = camera.view_matrix().transform_point3(target);
+        assert!(target_in_view.truncate().length() < 1e-5);
+        assert!(target_in_view.z < 0.);
+    }
+
+    #[test]
+    fn cursor_ray_hits_box() {
+        let camera = Camera { position: Vec3::new(0., 0., 5.), ..Default::default() };
+        let ray = camera.cursor_ray([400., 300.], [800., 600.], 75.);
+        assert!(ray.dir.distance(Vec3::NEG_Z) < 1e-5);
+        let t = ray.intersect_box(Vec3::splat(-1.), Vec3::splat(1.)).unwrap();
+        assert!(ray.at(t).distance(Vec3::new(0., 0., 1.)) < 1e-5);
+        let corner = camera.cursor_ray([0., 0.], [800., 600.], 75.);
+        assert!(corner.dir.x < 0. && corner.dir.y > 0.);
+        assert_eq!(corner.intersect_box(Vec3::splat(-1.), Vec3::splat(1.)), None);
+        assert_eq!(ray.intersect_box(Vec3::splat(-10.), Vec3::splat(10.)), None);
+    }
+
+    #[test]
+    fn crouch_lowers_and_restores() {
+        let mut camera = Camera { position: Vec3::new(0., 1.5, 0.), ..Default::default() };
+        let mut keys = KeyStates { crouch: true, forward: true, ..Default::default() };
+        camera.update(&keys, 1., 1., 0., 0., &Smoothing::default());
+        assert!((camera.position.y - (1.5 - CROUCH_DEPTH)).abs() < 1e-5);
+        assert!((camera.position.z + CROUCH_SPEED).abs() < 1e-5);
+        keys.crouch = false;
+        camera.update(&keys, 1., 1., 0., 0., &Smoothing::default());
+        assert!((camera.position.y - 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fullscreen_mapping_covers_the_screen() {
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::splat(0.5),
+            glam::Quat::from_rotation_y(90f32.to_radians()),
+            Vec3::new(6., 1.5, -1.5),
+        );
+        let bounds = [Vec3::new(-1., -1., 0.), Vec3::new(1., 1., 0.)];
+        let mapping = FullscreenMapping::new(bounds, 2.);
+        let corner = mapping.local_to_screen.transform_point3(Vec3::new(-1., 0.5, 0.));
+        assert!(corner.distance(Vec3::new(1., 1., 0.)) < 1e-5);
+        let camera = mapping.local_to_screen.inverse().transform_point3(Vec3::new(0., 0., 1.));
+        assert!(camera.z < 0.);
+        let ray = mapping.cursor_ray(matrix, [400., 300.], [800., 600.]);
+        let local_ray = ray.transform(matrix.inverse());
+        let hit = local_ray.at(local_ray.intersect_box(bounds[0], bounds[1]).unwrap());
+        assert!(hit.distance(Vec3::ZERO) < 1e-5);
+    }
+
+    #[test]
+    fn smoothing_eases_in_and_out() {
+        let smoothing = Smoothing { enabled: true, ..Default::default() };
+        let mut camera = Camera::default();
+        let mut keys = KeyStates { forward: true, ..Default::default() };
+        camera.update(&keys, 2., 0.1, 0., 0., &smoothing);
+        let first_step = -camera.position.z;
+        assert!(first_step > 0. && first_step < 0.2);
+        for _ in 0..100 {
+            camera.update(&keys, 2., 0.1, 0., 0., &smoothing);
+        }
+        assert!((camera.velocity.z - 2.).abs() < 1e-3);
+        keys.forward = false;
+        let z = camera.position.z;
+        camera.update(&keys, 2., 0.1, 0., 0., &smoothing);
+        assert!(camera.position.z < z, "keeps moving after the key is released");
+    }
 }
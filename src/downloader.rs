@@ -0,0 +1,253 @@
+//! Downloads assets that are too large or have an incompatible license to be put into the repository.
+//!
+//! The assets are listed in a manifest with one asset per line in the form `<path> <sha256> <url>`.
+//! The hash may be `-` to skip verification. Empty lines and lines starting with `#` are ignored.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: PathBuf,
+    /// Lowercase hex encoded sha256 of the file.
+    pub sha256: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read manifest {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("failed to parse manifest {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut entries = Vec::new();
+        for (line_num, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(path), Some(sha256), Some(url), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                anyhow::bail!("line {}: expected `<path> <sha256> <url>`", line_num + 1);
+            };
+            let sha256 = match sha256 {
+                "-" => None,
+                hash if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    Some(hash.to_ascii_lowercase())
+                }
+                hash => anyhow::bail!("line {}: invalid sha256 {hash}", line_num + 1),
+            };
+            entries.push(ManifestEntry { path: path.into(), sha256, url: url.to_owned() });
+        }
+        Ok(Self { entries })
+    }
+
+    /// Returns the entries whose file is missing or does not match its hash.
+    /// This reads and hashes all files, so it is done on the download thread.
+    pub fn missing(&self) -> Vec<ManifestEntry> {
+        self.entries.iter()
+            .filter(|entry| match fs::read(&entry.path) {
+                Ok(content) => entry.sha256.as_ref().is_some_and(|hash| *hash != sha256_hex(&content)),
+                Err(_) => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DownloadProgress {
+    pub files_total: usize,
+    pub files_done: usize,
+    /// Path of the file currently downloading.
+    pub current: Option<PathBuf>,
+    pub bytes_done: u64,
+    /// Size of the current file if the server sent it.
+    pub bytes_total: Option<u64>,
+    pub errors: Vec<String>,
+    pub finished: bool,
+}
+
+impl fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(current) = self.current.as_ref() else {
+            return write!(f, "downloaded {}/{} assets", self.files_done, self.files_total);
+        };
+        let name = current.file_name().unwrap_or(current.as_os_str()).to_string_lossy();
+        write!(f, "downloading {name} ({}/{})", self.files_done + 1, self.files_total)?;
+        match self.bytes_total {
+            Some(total) if total > 0 => write!(f, " {}%", self.bytes_done * 100 / total),
+            _ => write!(f, " {} KiB", self.bytes_done / 1024),
+        }
+    }
+}
+
+/// Downloads missing assets on a background thread.
+pub struct Downloader {
+    progress: Arc<Mutex<DownloadProgress>>,
+}
+
+impl Downloader {
+    /// Checks which assets are missing and downloads them, both on a background thread.
+    pub fn start(manifest: &Manifest) -> Self {
+        let progress = Arc::new(Mutex::new(DownloadProgress::default()));
+        let progress_thread = progress.clone();
+        let manifest = manifest.clone();
+        thread::spawn(move || {
+            let missing = manifest.missing();
+            if let Ok(mut progress) = progress_thread.lock() {
+                progress.files_total = missing.len();
+            }
+            for entry in missing {
+                log::info!("downloading {} to {}", entry.url, entry.path.display());
+                if let Ok(mut progress) = progress_thread.lock() {
+                    progress.current = Some(entry.path.clone());
+                    progress.bytes_done = 0;
+                    progress.bytes_total = None;
+                }
+                let result = Self::download(&entry, &progress_thread);
+                let Ok(mut progress) = progress_thread.lock() else {
+                    log::error!("Lock poisoned");
+                    return;
+                };
+                if let Err(err) = result {
+                    log::error!("failed to download {}: {err:#}", entry.path.display());
+                    progress.errors.push(format!("{}: {err:#}", entry.path.display()));
+                }
+                progress.files_done += 1;
+            }
+            if let Ok(mut progress) = progress_thread.lock() {
+                progress.current = None;
+                progress.finished = true;
+            }
+        });
+        Self { progress }
+    }
+
+    pub fn progress(&self) -> DownloadProgress {
+        self.progress.lock().map(|progress| progress.clone()).unwrap_or_else(|_| DownloadProgress {
+            errors: vec!["Lock poisoned".to_owned()],
+            finished: true,
+            ..Default::default()
+        })
+    }
+
+    fn download(entry: &ManifestEntry, progress: &Mutex<DownloadProgress>) -> anyhow::Result<()> {
+        let response = ureq::get(&entry.url).call().context("request failed")?;
+        let bytes_total = response.header("Content-Length").and_then(|len| len.parse().ok());
+        if let Ok(mut progress) = progress.lock() {
+            progress.bytes_total = bytes_total;
+        }
+
+        if let Some(dir) = entry.path.parent() {
+            fs::create_dir_all(dir).context("failed to create directory")?;
+        }
+        // download into a temporary file so an interrupted download is not mistaken for a finished one
+        let part_path = part_path(&entry.path).context("invalid path")?;
+        let mut file = File::create(&part_path).context("failed to create file")?;
+        let mut reader = response.into_reader();
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            let len = reader.read(&mut buf).context("failed to read response")?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buf[..len]);
+            file.write_all(&buf[..len]).context("failed to write file")?;
+            if let Ok(mut progress) = progress.lock() {
+                progress.bytes_done += len as u64;
+            }
+        }
+        drop(file);
+
+        let hash = format!("{:x}", hasher.finalize());
+        match entry.sha256.as_ref() {
+            Some(expected) if *expected != hash => {
+                let _ = fs::remove_file(&part_path);
+                anyhow::bail!("sha256 mismatch, expected {expected} got {hash}");
+            }
+            Some(_) => {}
+            None => log::warn!("no sha256 in manifest for {}, downloaded file has {hash}", entry.path.display()),
+        }
+        fs::rename(&part_path, &entry.path).context("failed to move downloaded file")?;
+        Ok(())
+    }
+}
+
+/// Appends `.part` to the file name, so files that only differ in their extension do not share it.
+fn part_path(path: &Path) -> Option<PathBuf> {
+    let mut name = path.file_name()?.to_owned();
+    name.push(".part");
+    Some(path.with_file_name(name))
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let content = r#"
+# comment
+assets/downloads/a.jpg - https://example.com/a.jpg
+
+assets/downloads/b.hdr E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855 https://example.com/b.hdr
+"#;
+        let manifest = Manifest::parse(content).expect("failed to parse");
+        assert_eq!(manifest.entries, [
+            ManifestEntry {
+                path: "assets/downloads/a.jpg".into(),
+                sha256: None,
+                url: "https://example.com/a.jpg".to_owned(),
+            },
+            ManifestEntry {
+                path: "assets/downloads/b.hdr".into(),
+                sha256: Some("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_owned()),
+                url: "https://example.com/b.hdr".to_owned(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn parse_manifest_invalid() {
+        assert!(Manifest::parse("assets/a.jpg https://example.com/a.jpg").is_err());
+        assert!(Manifest::parse("assets/a.jpg 1234 https://example.com/a.jpg").is_err());
+        assert!(Manifest::parse("assets/a.jpg - https://example.com/a.jpg extra").is_err());
+    }
+
+    #[test]
+    fn part_path_keeps_extension() {
+        assert_eq!(part_path(Path::new("assets/a.tar.gz")), Some(PathBuf::from("assets/a.tar.gz.part")));
+        assert_eq!(part_path(Path::new("assets/a")), Some(PathBuf::from("assets/a.part")));
+        assert_eq!(part_path(Path::new("..")), None);
+    }
+
+    #[test]
+    fn sha256_empty() {
+        assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+}
@@ -0,0 +1,150 @@
+//! Export of an exhibit as a standalone bundle.
+//!
+//! The bundle contains the preprocessed vertex and fragment shader with the current
+//! option values baked in as constants and a `exhibit.json` with the metadata,
+//! so a tuned exhibit can be shared without the rest of the application.
+
+use crate::art::{ArtObject, TextureSource};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use glam::Vec4;
+use serde::Serialize;
+
+/// Directory the bundles are written to.
+pub const EXPORT_DIR: &str = "exports";
+const OPTIONS_ACCESS: &str = "ubo.options";
+
+#[derive(Debug, Serialize)]
+struct Metadata<'a> {
+    name: &'a str,
+    vertex_shader: &'a str,
+    fragment_shader: &'a str,
+    texture: Option<String>,
+    options: Vec<OptionMetadata<'a>>,
+    option_values: Vec<[f32; 4]>,
+}
+
+#[derive(Debug, Serialize)]
+struct OptionMetadata<'a> {
+    label: &'a str,
+    values: Vec<f32>,
+}
+
+/// Writes the bundle of `art` into a new directory inside `dir` and returns its path.
+pub fn export_art(art: &ArtObject, dir: &Path) -> anyhow::Result<PathBuf> {
+    let vert = art.shader_vert.preprocessed_source().context("failed to preprocess vertex shader")?;
+    let frag = art.shader_frag.preprocessed_source().context("failed to preprocess fragment shader")?;
+
+    let name = art.file_stem();
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let bundle_dir = dir.join(format!("{name}-{timestamp}"));
+    fs::create_dir_all(&bundle_dir)
+        .with_context(|| format!("failed to create directory {}", bundle_dir.display()))?;
+
+    let vert_name = format!("{name}.vert");
+    let frag_name = format!("{name}.frag");
+    let option_values = art.data.option_values;
+    fs::write(bundle_dir.join(&vert_name), bake_options(&vert, &option_values))?;
+    fs::write(bundle_dir.join(&frag_name), bake_options(&frag, &option_values))?;
+
+    let mut options = Vec::new();
+    let values = art.option_values();
+    let mut i = 0;
+    for option in art.options.iter() {
+        let start = i;
+        i += option.ty.slot_count();
+        options.push(OptionMetadata { label: option.label(), values: values[start..i].to_vec() });
+    }
+    let metadata = Metadata {
+        name: &art.name,
+        vertex_shader: &vert_name,
+        fragment_shader: &frag_name,
+        texture: art.texture.as_ref().map(|texture| match texture {
+            TextureSource::File(path) => path.display().to_string(),
+            TextureSource::Webcam => "webcam".to_owned(),
+            TextureSource::Generated(_) => "generated".to_owned(),
+        }),
+        options,
+        option_values: option_values.iter().map(|values| values.to_array()).collect(),
+    };
+    let metadata = serde_json::to_string_pretty(&metadata)?;
+    fs::write(bundle_dir.join("exhibit.json"), metadata)?;
+
+    log::info!("exported {} to {}", art.name, bundle_dir.display());
+    Ok(bundle_dir)
+}
+
+/// Replaces all reads of `ubo.options` with the given values.
+fn bake_options(source: &str, option_values: &[Vec4]) -> String {
+    let mut baked = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find(OPTIONS_ACCESS) {
+        baked.push_str(&rest[..start]);
+        let after = &rest[start + OPTIONS_ACCESS.len()..];
+        let Some((vec_idx, after_vec)) = parse_index(after) else {
+            baked.push_str(OPTIONS_ACCESS);
+            rest = after;
+            continue;
+        };
+        let Some(vec) = option_values.get(vec_idx) else {
+            // out of bounds, leave it to the compiler to complain
+            baked.push_str(OPTIONS_ACCESS);
+            rest = after;
+            continue;
+        };
+        match parse_index(after_vec) {
+            Some((component, after_component)) if component < 4 => {
+                baked.push_str(&float_literal(vec[component]));
+                rest = after_component;
+            }
+            _ => {
+                let components = vec.to_array().map(float_literal);
+                baked.push_str(&format!("vec4({})", components.join(", ")));
+                rest = after_vec;
+            }
+        }
+    }
+    baked.push_str(rest);
+    baked
+}
+
+/// Parses a constant index like `[1]` at the start of `s`, returns it and the rest of `s`.
+fn parse_index(s: &str) -> Option<(usize, &str)> {
+    let s = s.trim_start().strip_prefix('[')?;
+    let end = s.find(']')?;
+    let idx = s[..end].trim().parse().ok()?;
+    Some((idx, &s[end + 1..]))
+}
+
+fn float_literal(value: f32) -> String {
+    if value < 0. {
+        format!("({value:?})")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bake() {
+        let values = [Vec4::new(1., -0.5, 2.5, 0.), Vec4::new(3., 0., 0., 1.)];
+        let source = "float a = ubo.options[0][1];\nint b = int(ubo.options[1] [0]);\nvec3 c = ubo.options[0].xyz;";
+        assert_eq!(
+            bake_options(source, &values),
+            "float a = (-0.5);\nint b = int(3.0);\nvec3 c = vec4(1.0, (-0.5), 2.5, 0.0).xyz;",
+        );
+    }
+
+    #[test]
+    fn bake_keeps_dynamic_index() {
+        let source = "ubo.options[i][0] + ubo.light_pos.x";
+        assert_eq!(bake_options(source, &[]), source);
+    }
+}
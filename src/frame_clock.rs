@@ -0,0 +1,98 @@
+//! Advances the shader time in multiples of the display refresh interval.
+//!
+//! The time between two frames measured on the CPU jitters by a few milliseconds even when every
+//! frame is shown for exactly one refresh, which makes animations stutter. The measured deltas are
+//! snapped to whole refresh intervals and the difference is carried over, so the time does not drift.
+
+use std::collections::VecDeque;
+
+/// Number of deltas used to measure the refresh interval if the monitor does not report it.
+const SAMPLE_COUNT: usize = 60;
+/// Deltas further than this fraction of an interval from a multiple of it are not snapped.
+const SNAP_TOLERANCE: f32 = 0.25;
+/// Carried over differences larger than this are dropped, e.g. after a stall.
+const MAX_RESIDUAL: f32 = 0.1;
+
+#[derive(Debug, Default)]
+pub struct FrameClock {
+    /// Refresh interval in seconds reported by the monitor.
+    reported_interval: Option<f32>,
+    /// Recent frame deltas, the newest last.
+    samples: VecDeque<f32>,
+    /// Measured time not yet passed on because of snapping.
+    residual: f32,
+}
+
+impl FrameClock {
+    /// Sets the refresh rate of the monitor the window is on, `None` if it is unknown.
+    pub fn set_refresh_rate(&mut self, millihertz: Option<u32>) {
+        self.reported_interval = millihertz.filter(|&rate| rate > 0).map(|rate| 1000. / rate as f32);
+    }
+
+    /// The reported refresh interval or else the median of the recent deltas.
+    /// The measured one is only meaningful if the frames are synchronized to the display.
+    pub fn refresh_interval(&self) -> Option<f32> {
+        if self.reported_interval.is_some() {
+            return self.reported_interval;
+        }
+        if self.samples.len() < SAMPLE_COUNT {
+            return None;
+        }
+        let mut sorted = self.samples.iter().copied().collect::<Vec<_>>();
+        sorted.sort_unstable_by(f32::total_cmp);
+        Some(sorted[sorted.len() / 2])
+    }
+
+    /// Returns the time to advance the animations by for a frame that took `measured` seconds.
+    pub fn tick(&mut self, measured: f32) -> f32 {
+        self.samples.push_back(measured);
+        if self.samples.len() > SAMPLE_COUNT {
+            self.samples.pop_front();
+        }
+
+        let Some(interval) = self.refresh_interval() else { return measured };
+        let total = measured + self.residual;
+        let frames = (total / interval).round();
+        if frames < 1. || (total - frames * interval).abs() > interval * SNAP_TOLERANCE {
+            // faster than the display or irregular, snapping would only add jitter
+            self.residual = 0.;
+            return measured;
+        }
+        let snapped = frames * interval;
+        self.residual = total - snapped;
+        if self.residual.abs() > MAX_RESIDUAL {
+            self.residual = 0.;
+        }
+        snapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_is_snapped_without_drift() {
+        let mut clock = FrameClock::default();
+        clock.set_refresh_rate(Some(60_000));
+        let interval = 1. / 60.;
+        let deltas = [interval + 0.003, interval - 0.003, interval + 0.001, 2. * interval - 0.002];
+        let mut total_measured = 0.;
+        let mut total_snapped = 0.;
+        for delta in deltas {
+            let snapped = clock.tick(delta);
+            let frames = snapped / interval;
+            assert!((frames - frames.round()).abs() < 1e-4, "{snapped} is not a multiple of the interval");
+            total_measured += delta;
+            total_snapped += snapped;
+        }
+        assert!((total_measured - total_snapped).abs() < interval / 2.);
+    }
+
+    #[test]
+    fn fast_frames_are_not_snapped() {
+        let mut clock = FrameClock::default();
+        clock.set_refresh_rate(Some(60_000));
+        assert_eq!(clock.tick(0.004), 0.004);
+    }
+}
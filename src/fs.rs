@@ -1,12 +1,197 @@
-use std::io::{self, Cursor};
-use std::path::Path;
+//! Loading of assets from the file system or from mounted archives.
+//!
+//! Loose files always take precedence over files in archives, so an asset can be
+//! overridden during development by placing it next to the binary.
+//! Archives are zip files, the `.pak` extension is only a naming convention.
+//! With the `embed-assets` feature the default layout, models and shaders are compiled into
+//! the binary and used as a last resort.
 
-pub fn load<P: AsRef<Path>>(path: P) -> Result<Cursor<Vec<u8>>, io::Error> {
-    use std::fs::File;
-    use std::io::Read;
+use std::fs::File;
+use std::io::{self, BufReader, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{LazyLock, Mutex, RwLock};
+
+use zip::{result::ZipError, ZipArchive};
+
+static SOURCES: LazyLock<RwLock<Vec<Box<dyn AssetSource>>>> = LazyLock::new(|| {
+    RwLock::new(vec![Box::new(LooseFiles) as Box<dyn AssetSource>])
+});
+
+/// Something assets can be loaded from.
+pub trait AssetSource: Send + Sync {
+    /// A human readable name used for logging.
+    fn name(&self) -> String;
+
+    /// Reads the whole asset at `path`. Returns `Ok(None)` if the asset does not exist in this source.
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// Loose files relative to the current working directory.
+struct LooseFiles;
+
+impl AssetSource for LooseFiles {
+    fn name(&self) -> String {
+        "loose files".to_owned()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut buf = Vec::new();
+        file.read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    }
+}
+
+/// A zip archive mounted at startup.
+struct Archive {
+    path: PathBuf,
+    archive: Mutex<ZipArchive<BufReader<File>>>,
+}
+
+impl AssetSource for Archive {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        let Some(name) = archive_name(path) else { return Ok(None) };
+        let mut archive = self.archive.lock().map_err(|_| io::Error::other("Lock poisoned"))?;
+        let mut file = match archive.by_name(&name) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut buf = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut buf)?;
+        Ok(Some(buf))
+    }
+}
+
+/// Converts a relative path into the `/` separated form used inside archives.
+/// Returns `None` for paths that can never be inside an archive.
+fn archive_name(path: &Path) -> Option<String> {
+    let mut parts = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_str()?),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Models and shaders compiled into the binary.
+#[cfg(feature = "embed-assets")]
+mod embedded {
+    use super::archive_name;
+
+    use std::path::Path;
+
+    macro_rules! embed {
+        ($($path:literal),* $(,)?) => {
+            &[$(($path, include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path)))),*]
+        };
+    }
+
+    static FILES: &[(&str, &[u8])] = embed![
+        "assets/layout.json",
+        "assets/models/cube_inside.obj",
+        "assets/models/square.obj",
+        "assets/models/teapot.obj",
+        "assets/shaders/art2d.vert",
+        "assets/shaders/art3d.vert",
+        "assets/shaders/cached.frag",
+        "assets/shaders/cloudycube.frag",
+        "assets/shaders/fullscreen.vert",
+        "assets/shaders/gem.frag",
+        "assets/shaders/mandelbox.frag",
+        "assets/shaders/mandelbrot.frag",
+        "assets/shaders/mandelbulb.frag",
+        "assets/shaders/mengersponge.frag",
+        "assets/shaders/mirror.frag",
+        "assets/shaders/mozaic.frag",
+        "assets/shaders/pillar.frag",
+        "assets/shaders/plaque.frag",
+        "assets/shaders/plaque.vert",
+        "assets/shaders/player.frag",
+        "assets/shaders/portal.frag",
+        "assets/shaders/prop.frag",
+        "assets/shaders/sdf_cat.frag",
+        "assets/shaders/skybox.frag",
+        "assets/shaders/solar.frag",
+        "assets/shaders/truchet.frag",
+        "assets/shaders/webcam.frag",
+        "assets/shaders/includes/camera.glsl",
+        "assets/shaders/includes/cost.glsl",
+        "assets/shaders/includes/depth.glsl",
+        "assets/shaders/includes/fractal.glsl",
+        "assets/shaders/includes/globals.glsl",
+        "assets/shaders/includes/ibl.glsl",
+        "assets/shaders/includes/keyboard.glsl",
+        "assets/shaders/includes/lightning.glsl",
+        "assets/shaders/includes/noise.glsl",
+        "assets/shaders/includes/palette.glsl",
+        "assets/shaders/includes/scene.glsl",
+        "assets/shaders/transitions/circle.glsl",
+        "assets/shaders/transitions/crossfade.glsl",
+        "assets/shaders/transitions/wipe.glsl",
+    ];
+
+    pub fn read(path: &Path) -> Option<&'static [u8]> {
+        let name = archive_name(path)?;
+        FILES.iter().find(|(file, _)| *file == name).map(|(_, content)| *content)
+    }
+}
+
+/// Mounts a zip archive. Its files are looked up after loose files and previously mounted archives.
+pub fn mount<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path)?);
+    let archive = ZipArchive::new(file).map_err(io::Error::from)?;
+    log::info!("mounted asset archive {} with {} files", path.display(), archive.len());
+    add_source(Archive {
+        path: path.to_owned(),
+        archive: Mutex::new(archive),
+    });
+    Ok(())
+}
+
+/// Adds a source that is searched after all previously added sources.
+pub fn add_source<S: AssetSource + 'static>(source: S) {
+    let mut sources = SOURCES.write().unwrap_or_else(|err| err.into_inner());
+    sources.push(Box::new(source));
+}
+
+/// Reads the whole asset at `path` from the first source containing it.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, io::Error> {
+    let path = path.as_ref();
+    let sources = SOURCES.read().unwrap_or_else(|err| err.into_inner());
+    for source in sources.iter() {
+        if let Some(buf) = source.read(path)? {
+            log::trace!("loaded {} from {}", path.display(), source.name());
+            return Ok(buf);
+        }
+    }
+    #[cfg(feature = "embed-assets")]
+    if let Some(content) = embedded::read(path) {
+        log::trace!("loaded {} from embedded assets", path.display());
+        return Ok(content.to_vec());
+    }
+    Err(io::Error::new(io::ErrorKind::NotFound, format!("asset {} not found", path.display())))
+}
+
+pub fn read_to_string<P: AsRef<Path>>(path: P) -> Result<String, io::Error> {
+    String::from_utf8(read(path)?).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
 
-    let mut buf = Vec::new();
-    let mut file = File::open(path)?;
-    file.read_to_end(&mut buf)?;
-    Ok(Cursor::new(buf))
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Cursor<Vec<u8>>, io::Error> {
+    read(path).map(Cursor::new)
 }
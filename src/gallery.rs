@@ -0,0 +1,138 @@
+//! Exhibits for the fragment shaders dropped into the gallery directory.
+//!
+//! Every `.frag` file gets a wall quad on the outer side of the mirror wall, it is removed again
+//! when the file is deleted. The shaders get the same uniforms and bindings as the built-in ones.
+
+use crate::{
+    art::ArtObject,
+    model::obj::NormalizedObj,
+    vulkan::{HotShader, VkApp},
+};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use glam::Vec3;
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{self, RecommendedWatcher},
+    DebounceEventResult, Debouncer, RecommendedCache,
+};
+
+/// Directory watched for new shaders, it is created if it does not exist.
+pub const GALLERY_DIR: &str = "assets/shaders/gallery";
+/// Positions on the outer side of the mirror wall where the shaders are exhibited.
+const GALLERY_POSITIONS: [[f32; 3]; 5] = [
+    [-6.21, 1.5,  -0.5],
+    [-6.21, 1.5,  -3.5],
+    [-6.21, 1.5,  -6.5],
+    [-6.21, 1.5,  -9.5],
+    [-6.21, 1.5, -12.5],
+];
+const DEBOUNCE_TIME: Duration = Duration::from_millis(500);
+
+pub struct GalleryWatcher {
+    /// Canonical path of the watched directory, the events contain canonical paths.
+    dir: PathBuf,
+    model: Arc<NormalizedObj>,
+    shader_vert: Arc<HotShader>,
+    /// Files that changed and are not handled yet, initially the shaders already in the directory.
+    pending: BTreeSet<PathBuf>,
+    rx: mpsc::Receiver<DebounceEventResult>,
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+impl GalleryWatcher {
+    pub fn new(dir: &Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let dir = std::fs::canonicalize(dir)?;
+        let pending = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE_TIME, None, tx)?;
+        debouncer.watch(&dir, notify::RecursiveMode::NonRecursive)?;
+        log::debug!("watching gallery shaders in {}", dir.display());
+        Ok(Self {
+            dir,
+            model: Arc::new(NormalizedObj::load("assets/models/square.obj")?),
+            shader_vert: Arc::new(HotShader::new_vert("assets/shaders/art2d.vert")),
+            pending,
+            rx,
+            _debouncer: debouncer,
+        })
+    }
+
+    /// Adds, removes or reloads the exhibits of the shaders that changed since the last call.
+    /// Returns `true` if exhibits were added or removed.
+    pub fn update(&mut self, art_objects: &mut Vec<ArtObject>, vk_app: &mut VkApp) -> bool {
+        for result in self.rx.try_iter() {
+            match result {
+                Ok(events) => {
+                    let paths = events.into_iter().flat_map(|event| event.event.paths);
+                    self.pending.extend(paths);
+                }
+                Err(errors) => log::warn!("watch error: {errors:?}"),
+            }
+        }
+
+        let mut changed = false;
+        for path in std::mem::take(&mut self.pending) {
+            let is_shader = path.extension().is_some_and(|ext| ext == "frag");
+            if !is_shader || path.parent() != Some(self.dir.as_path()) {
+                continue;
+            }
+            let art_idx = art_objects.iter().position(|art| art.shader_frag.path() == Some(path.as_path()));
+            match art_idx {
+                Some(art_idx) if path.is_file() => {
+                    log::info!("shader changed {}", path.display());
+                    art_objects[art_idx].shader_frag.reload(true);
+                }
+                Some(art_idx) => {
+                    let art = art_objects.remove(art_idx);
+                    log::info!("removed gallery shader {}", art.name);
+                    vk_app.remove_art(art_objects, art_idx);
+                    changed = true;
+                }
+                None if path.is_file() => {
+                    let Some(art) = self.create_art(&path, art_objects) else { continue };
+                    log::info!("added gallery shader {}", art.name);
+                    art_objects.push(art);
+                    if let Err(err) = vk_app.add_art(art_objects, art_objects.len() - 1) {
+                        log::error!("failed to add gallery shader {}: {err:?}", path.display());
+                        art_objects.pop();
+                        continue;
+                    }
+                    changed = true;
+                }
+                None => {}
+            }
+        }
+        changed
+    }
+
+    fn create_art(&self, path: &Path, art_objects: &[ArtObject]) -> Option<ArtObject> {
+        let is_taken = |position: Vec3| art_objects.iter().any(|art| {
+            art.shader_frag.path().is_some_and(|path| path.parent() == Some(self.dir.as_path()))
+                && art.position().distance_squared(position) < 1e-4
+        });
+        let position = GALLERY_POSITIONS.into_iter().map(Vec3::from).find(|&pos| !is_taken(pos));
+        let Some(position) = position else {
+            log::warn!("only {} gallery shaders are exhibited, skipping {}", GALLERY_POSITIONS.len(), path.display());
+            return None;
+        };
+        let art = ArtObject::builder()
+            .name(path.file_stem().unwrap_or_default().to_string_lossy())
+            .model(self.model.clone())
+            .vert_shader(self.shader_vert.clone())
+            .frag_shader(Arc::new(HotShader::new_frag(path)))
+            .scale(Vec3::splat(0.5))
+            .rotate_y(90.)
+            .at(position)
+            .groups(["2D", "gallery"])
+            .build();
+        art.inspect_err(|err| log::error!("failed to create exhibit for {}: {err:#}", path.display())).ok()
+    }
+}
@@ -1,27 +1,201 @@
-use crate::art::{ArtObject, ArtOption, ArtOptionType};
+use crate::{
+    animation::{Animation, Easing, Keyframes, Repeat, Track},
+    art::{ArtObject, ArtOption, ArtOptionType, ArtTime, Compare},
+    export::{export_art, EXPORT_DIR},
+    quality::AutoQuality,
+    second_window::SecondWindowMode,
+    settings::{Attract, Interaction, Movement, Projection},
+    tour::{Highlight, Tour, TourAction},
+    vulkan::{
+        debug_printf_enabled, take_printf_messages, ClearColors, CpuTimings, HotShader, OutputAdjustments, StereoMode,
+        TransitionSettings, TransitionStyle,
+    },
+};
 
 use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use egui::{
-    Align2, Color32, CornerRadius, Frame, Id, Theme, Ui, Vec2, Visuals, Window,
+    Align2, Color32, CornerRadius, Frame, Id, Pos2, Rect, Sense, Stroke, Theme, Ui, Vec2, Visuals, Window,
 };
 use egui_winit_vulkano::Gui;
 use vulkano::swapchain::PresentMode;
+use winit::monitor::{MonitorHandle, VideoModeHandle};
+use winit::window::Fullscreen;
 
 const FPS_CHART_MAX_TIME: Duration = Duration::from_secs(5);
+/// Size of the lane of one track in the timeline.
+const TIMELINE_LANE_SIZE: Vec2 = Vec2::new(400., 16.);
+/// Seconds shown in the timeline at least, longer animations are shown completely.
+const TIMELINE_MIN_DURATION: f32 = 10.;
+/// Lines of shader printf output kept in the console.
+const PRINTF_MAX_LINES: usize = 1000;
 
 #[derive(Debug, Clone)]
 pub struct Options {
     pub recreate_swapchain: bool,
+    /// Set to recompile all shaders, even those whose file did not change.
+    pub reload_shaders: bool,
+    /// Show the iterations of the ray marchers instead of their color.
+    pub cost_heatmap: bool,
     pub present_modes: Vec<PresentMode>,
     pub present_mode: PresentMode,
+    /// Requested number of swapchain images, 2 for double and 3 for triple buffering.
+    pub image_count: u32,
+    /// Number of swapchain images the surface actually gave.
+    pub actual_image_count: usize,
     theme: Theme,
+    /// Scale of the interface on top of the scale factor of the display.
+    ui_scale: f32,
+    /// Advance the shader time in whole refresh intervals of the display.
+    pub snap_time: bool,
+    /// Slows down the shader time and tells the shaders to avoid fast motion and flashing.
+    pub reduced_motion: bool,
+    pub auto_quality: AutoQuality,
     pub sun_movement: bool,
     /// Speed of sun in radians per second.
     pub sun_speed: f32,
-    /// FOV in degrees.
+    /// FOV in degrees, within the limits of `projection`.
     pub fov: f32,
+    pub projection: Projection,
+    pub stereo: StereoMode,
+    /// Distance between the eyes in world units for the stereo modes.
+    pub ipd: f32,
+    pub second_window: SecondWindowMode,
+    /// Show the crosshair and the prompts about the exhibit in the center of the screen.
+    pub hud: bool,
+    pub interaction: Interaction,
+    pub attract: Attract,
+    /// Whether the attract path is recorded, the app starts and stops the recording to match.
+    pub record_attract: bool,
+    pub movement: Movement,
+    pub sky: SkyOptions,
+    /// Shown where nothing is drawn.
+    pub clear_colors: ClearColors,
+    /// Clear with `ClearColors::DEBUG` instead, so areas nothing is drawn to are obvious.
+    pub debug_clear: bool,
+    /// Name of the scene whose layout is shown, the app switches when it changes.
+    pub scene: String,
+    /// Names of the scenes that can be selected, see `crate::model::env_generator::scene_names`.
+    pub scenes: Vec<String>,
+    /// Path of the color grading lookup table, empty for none.
+    pub color_lut: String,
+    /// Set if `color_lut` has to be loaded.
+    pub apply_color_lut: bool,
+    /// Link to a view of an exhibit, see `crate::permalink`.
+    pub permalink: String,
+    /// Set if `permalink` has to be opened.
+    pub open_permalink: bool,
+    /// Strength of the color grading from 0 to 1.
+    pub color_grading: f32,
+    pub output: OutputAdjustments,
+    /// Shown when the camera jumps or an exhibit is shown full screen.
+    pub transition: TransitionSettings,
+    /// Path of the transition shader while it is edited, used once it is loaded.
+    pub transition_shader: String,
+    /// Volume of the background loop from 0 to 1.
+    pub ambient_volume: f32,
+    /// Volume of the sounds of the exhibits from 0 to 1.
+    pub effects_volume: f32,
+    /// Names of the exhibit groups and whether they are shown.
+    pub groups: Vec<(String, bool)>,
+    /// Set if the fullscreen settings changed and have to be applied to the window.
+    pub apply_fullscreen: bool,
+    monitors: Vec<MonitorHandle>,
+    /// Monitor used for fullscreen, `None` for the one the window is on.
+    monitor: Option<MonitorHandle>,
+    /// Video modes of the selected monitor, sorted from largest to smallest.
+    video_modes: Vec<VideoModeHandle>,
+    /// Video mode of exclusive fullscreen, `None` for borderless fullscreen.
+    video_mode: Option<VideoModeHandle>,
+}
+
+impl Options {
+    /// Sets the available groups, new ones are shown initially.
+    pub fn set_groups<'a>(&mut self, groups: impl IntoIterator<Item = &'a str>) {
+        let old_groups = std::mem::take(&mut self.groups);
+        for group in groups {
+            if !self.groups.iter().any(|(name, _)| name == group) {
+                let shown = old_groups.iter().find(|(name, _)| name == group).is_none_or(|(_, shown)| *shown);
+                self.groups.push((group.to_owned(), shown));
+            }
+        }
+    }
+
+    /// Switches to the next supported present mode of Fifo, Mailbox and Immediate.
+    pub fn cycle_present_mode(&mut self) {
+        const CYCLE: [PresentMode; 3] = [PresentMode::Fifo, PresentMode::Mailbox, PresentMode::Immediate];
+        let start = CYCLE.iter().position(|&mode| mode == self.present_mode).unwrap_or(0);
+        let next = (1..=CYCLE.len())
+            .map(|offset| CYCLE[(start + offset) % CYCLE.len()])
+            .find(|mode| self.present_modes.contains(mode));
+        if let Some(mode) = next.filter(|&mode| mode != self.present_mode) {
+            log::info!("switching to present mode {mode:?}");
+            self.present_mode = mode;
+            self.recreate_swapchain = true;
+        }
+    }
+
+    /// Sets the monitors that can be selected for fullscreen.
+    pub fn set_monitors(&mut self, monitors: impl IntoIterator<Item = MonitorHandle>) {
+        self.monitors = monitors.into_iter().collect();
+        self.select_monitor(None);
+    }
+
+    fn select_monitor(&mut self, monitor: Option<MonitorHandle>) {
+        self.video_modes = monitor.as_ref()
+            .map(|monitor| monitor.video_modes().collect())
+            .unwrap_or_default();
+        self.video_modes.sort_by_key(|mode| {
+            let size = mode.size();
+            std::cmp::Reverse((size.width, size.height, mode.refresh_rate_millihertz(), mode.bit_depth()))
+        });
+        self.monitor = monitor;
+        self.video_mode = None;
+    }
+
+    /// The fullscreen mode the window is set to when entering fullscreen.
+    pub fn fullscreen(&self) -> Fullscreen {
+        match self.video_mode.clone() {
+            Some(video_mode) => Fullscreen::Exclusive(video_mode),
+            None => Fullscreen::Borderless(self.monitor.clone()),
+        }
+    }
+
+    /// Whether an exhibit in `groups` is hidden because one of its groups is disabled.
+    pub fn is_hidden(&self, groups: &[String]) -> bool {
+        self.groups.iter().any(|(name, shown)| !shown && groups.contains(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SkyOptions {
+    /// Use the physical sky instead of the simple gradient.
+    pub physical: bool,
+    /// Let the sun rise and set instead of circling above the horizon.
+    pub day_cycle: bool,
+    /// Multiplier of the Rayleigh scattering coefficients.
+    pub rayleigh: f32,
+    /// Multiplier of the Mie scattering coefficient.
+    pub mie: f32,
+    /// Multiplier of the apparent size of the sun disc.
+    pub sun_size: f32,
+    pub star_brightness: f32,
+}
+
+impl Default for SkyOptions {
+    fn default() -> Self {
+        Self {
+            physical: true,
+            day_cycle: false,
+            rayleigh: 1.,
+            mie: 1.,
+            sun_size: 4.,
+            star_brightness: 1.,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -33,17 +207,72 @@ pub struct GuiState {
     open_options: bool,
     open_art_options: bool,
     open_welcome: bool,
+    open_browser: bool,
+    open_timeline: bool,
+    open_shader_stats: bool,
+    open_printf: bool,
+    /// Exhibit whose fragment shader is compiled with debug printf.
+    printf_art: Option<String>,
+    /// Output of debug printf, the newest line last.
+    printf_log: VecDeque<String>,
+    /// Track and keyframe index of the keyframe selected in the timeline.
+    timeline_selected: Option<(usize, usize)>,
+    /// Result of the last save of the timeline.
+    timeline_status: Option<String>,
+    /// Text entered in the search box of the exhibit browser.
+    browser_search: String,
+    /// Exhibit opened from the browser whose options are shown instead of the nearest one.
+    pinned_art: Option<String>,
     frame_timings: VecDeque<Duration>,
+    /// CPU stages of the frames in `frame_timings`, the newest first.
+    cpu_timings: VecDeque<CpuTimings>,
+    /// Name of the last exported art and the result of the export.
+    export_status: Option<(String, String)>,
+    /// Name entered for a new preset.
+    preset_name: String,
+    /// Path of the fragment shader entered to compare an exhibit with.
+    compare_path: String,
+    /// Windows that were open before the tour started and the step whose highlight was opened last.
+    tour_state: Option<(OpenWindows, Option<usize>)>,
     pub options: Options,
 }
 
+/// The windows the tour can open, see `GuiState::open_highlighted`.
+#[derive(Debug, Clone)]
+struct OpenWindows {
+    open: bool,
+    open_options: bool,
+    open_art_options: bool,
+    open_browser: bool,
+    open_timeline: bool,
+    pinned_art: Option<String>,
+}
+
+/// Actions requested by the user in the GUI that are handled by the app.
+#[derive(Debug, Default)]
+pub struct GuiResponse {
+    /// Index of the exhibit to teleport to.
+    pub teleport_to: Option<usize>,
+    /// Index of the exhibit whose view is copied to the clipboard as link.
+    pub copy_permalink: Option<usize>,
+    pub tour: Option<TourAction>,
+    /// Whether the animations are written to the layout of the scene.
+    pub save_timeline: bool,
+}
+
 impl GuiState {
+    /// Renders the GUI, the options of the exhibit at index `selected` are shown if there is one.
+    /// The HUD names the exhibit at index `looked_at`, the one in the center of the screen.
+    /// The current step of a `tour` is shown with the part of the interface it highlights.
     pub fn render(
         &mut self,
         gui: &mut Gui,
-        art: &mut Option<&mut ArtObject>,
+        art_objects: &mut [ArtObject],
+        selected: Option<usize>,
+        looked_at: Option<usize>,
         time: Option<Duration>,
-    ) {
+        tour: Option<&Tour>,
+    ) -> GuiResponse {
         let total_time = if let Some(time) = time {
             self.frame_timings.push_front(time);
             let mut total_time = Duration::default();
@@ -58,8 +287,23 @@ impl GuiState {
         };
         let fps = self.frame_timings.len() as f32 / total_time.as_secs_f32();
 
-        if !self.open {
-            return;
+        if debug_printf_enabled() {
+            self.printf_log.extend(take_printf_messages());
+            let excess = self.printf_log.len().saturating_sub(PRINTF_MAX_LINES);
+            self.printf_log.drain(..excess);
+        }
+
+        let mut response = GuiResponse::default();
+        // the HUD belongs over the scene and not into the detached GUI
+        let show_hud = self.options.hud && self.options.second_window != SecondWindowMode::Gui;
+        match tour {
+            Some(tour) => self.open_highlighted(tour),
+            None => if let Some((windows, _)) = self.tour_state.take() {
+                self.restore_windows(windows);
+            }
+        }
+        if !self.open && !show_hud && tour.is_none() {
+            return response;
         }
 
         gui.immediate_ui(|gui| {
@@ -89,6 +333,20 @@ impl GuiState {
             ctx.set_theme(self.options.theme);
             ctx.set_visuals_of(Theme::Dark, dark_theme);
             ctx.set_visuals_of(Theme::Light, light_theme);
+            // the scale factor of the display is applied by egui-winit, this is on top of it
+            if ctx.zoom_factor() != self.options.ui_scale {
+                ctx.set_zoom_factor(self.options.ui_scale);
+            }
+
+            if show_hud {
+                Self::draw_hud(&ctx, looked_at.map(|idx| &art_objects[idx]));
+            }
+            if let Some(tour) = tour {
+                Self::draw_tour(&ctx, tour, self.id_art_options, bg_color, &mut response);
+            }
+            if !self.open {
+                return;
+            }
 
             Window::new(format!("FPS: {fps:.2}"))
                 .id(self.id_fps)
@@ -101,6 +359,8 @@ impl GuiState {
                     Frame::canvas(ui.style())
                         .multiply_with_opacity(0.5)
                         .show(ui, |ui| Self::draw_fps_chart(ui, &self.frame_timings));
+                    egui::CollapsingHeader::new("CPU stages")
+                        .show(ui, |ui| Self::cpu_timings_contents(ui, &self.cpu_timings));
                 });
 
             let options_win = Window::new("Options")
@@ -119,7 +379,7 @@ impl GuiState {
                         });
                 });
 
-            if let Some(art) = art {
+            if let Some(art) = selected.map(|idx| &mut art_objects[idx]) {
                 let offset_y = options_win.map(|win| win.response.rect.bottom()).unwrap_or(0.);
                 Window::new(format!("{} Options", art.name))
                     .id(self.id_art_options)
@@ -129,6 +389,32 @@ impl GuiState {
                     .default_width(300.)
                     .frame(Frame::NONE.fill(bg_color).inner_margin(5))
                     .show(&ctx, |ui| {
+                        if art.is_stale() {
+                            ui.colored_label(Color32::YELLOW, "Stale: the shader failed to compile, \
+                                showing the last version that did. See the log for the errors.");
+                            ui.separator();
+                        }
+                        let warnings = [&art.shader_vert, &art.shader_frag].map(|shader| shader.warnings()).concat();
+                        if !warnings.is_empty() {
+                            ui.collapsing(format!("{} shader warnings", warnings.len()), |ui| {
+                                for warning in warnings {
+                                    ui.label(warning);
+                                }
+                            });
+                            ui.separator();
+                        }
+                        if self.pinned_art.is_some() {
+                            let unpin = ui.button("Show nearest").on_hover_ui(|ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Show the options of the nearest exhibit again \
+                                        instead of the one opened in the exhibit browser.");
+                                });
+                            });
+                            if unpin.clicked() {
+                                self.pinned_art = None;
+                            }
+                            ui.separator();
+                        }
                         egui::Grid::new("art_options_grid")
                             .num_columns(2)
                             .spacing([40.0, 4.0])
@@ -136,9 +422,140 @@ impl GuiState {
                             .show(ui, |ui| {
                                 Self::art_options_grid_contents(ui, &mut art.options);
                             });
+                        ui.separator();
+                        Self::art_presets_contents(ui, art, &mut self.preset_name);
+                        ui.separator();
+                        Self::art_randomize_contents(ui, art);
+                        ui.separator();
+                        Self::art_time_contents(ui, art);
+                        ui.separator();
+                        Self::art_compare_contents(ui, art, &mut self.compare_path);
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            let export = ui.button("Export").on_hover_ui(|ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Write the shaders with the current option values baked in \
+                                        and a metadata file to the exports directory.");
+                                });
+                            });
+                            if export.clicked() {
+                                art.save_options();
+                                let status = match export_art(art, Path::new(EXPORT_DIR)) {
+                                    Ok(dir) => format!("exported to {}", dir.display()),
+                                    Err(err) => {
+                                        log::error!("failed to export {}: {err:?}", art.name);
+                                        format!("export failed: {err:#}")
+                                    }
+                                };
+                                self.export_status = Some((art.name.clone(), status));
+                            }
+                            let copy_link = ui.button("Copy link").on_hover_ui(|ui| {
+                                ui.horizontal_wrapped(|ui| {
+                                    ui.label("Copy the exhibit, the camera pose and the option values \
+                                        to the clipboard to share this view.");
+                                });
+                            });
+                            if copy_link.clicked() {
+                                response.copy_permalink = selected;
+                            }
+                            if let Some((_, status)) = self.export_status.as_ref()
+                                .filter(|(name, _)| *name == art.name)
+                            {
+                                ui.label(status);
+                            }
+                        });
+                    });
+            }
+
+            Window::new("Exhibits")
+                .open(&mut self.open_browser)
+                .default_pos([0., 150.])
+                .resizable(false)
+                .default_width(300.)
+                .frame(Frame::NONE.fill(bg_color).inner_margin(5))
+                .show(&ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Search");
+                        ui.text_edit_singleline(&mut self.browser_search);
+                    });
+                    ui.separator();
+                    let search = self.browser_search.to_lowercase();
+                    egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+                        egui::Grid::new("browser_grid")
+                            .num_columns(5)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                let arts = art_objects.iter_mut()
+                                    .enumerate()
+                                    .filter(|(_, art)| art.name.to_lowercase().contains(&search));
+                                for (idx, art) in arts {
+                                    ui.checkbox(&mut art.visible, art.name.as_str());
+                                    if art.is_stale() {
+                                        ui.colored_label(Color32::YELLOW, "stale").on_hover_text(
+                                            "The shader failed to compile, the last version that did is shown",
+                                        );
+                                    } else {
+                                        ui.label(format!("{:.1} m", art.data.dist_to_camera_sqr.sqrt()));
+                                    }
+                                    let mut enabled = !art.disabled;
+                                    let toggle = ui.checkbox(&mut enabled, "On")
+                                        .on_hover_text("Turn off to stop compiling and drawing the shaders");
+                                    if toggle.changed() {
+                                        art.disabled = !enabled;
+                                    }
+                                    if ui.button("Go to").clicked() {
+                                        response.teleport_to = Some(idx);
+                                    }
+                                    let options = egui::Button::new("Options");
+                                    if ui.add_enabled(!art.options.is_empty(), options).clicked() {
+                                        self.pinned_art = Some(art.name.clone());
+                                        self.open_art_options = true;
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                });
+
+            if let Some(idx) = selected {
+                Window::new(format!("{} Timeline", art_objects[idx].name))
+                    .id(Id::new("Timeline"))
+                    .open(&mut self.open_timeline)
+                    .anchor(Align2::CENTER_BOTTOM, [0., 0.])
+                    .resizable(false)
+                    .frame(Frame::NONE.fill(bg_color).inner_margin(5))
+                    .show(&ctx, |ui| {
+                        Self::timeline_contents(ui, &mut art_objects[idx], &mut self.timeline_selected);
+                        ui.horizontal(|ui| {
+                            let save = ui.button("Save timeline")
+                                .on_hover_text("Write the animations of all exhibits to the layout of the scene");
+                            if save.clicked() {
+                                response.save_timeline = true;
+                            }
+                            if let Some(status) = self.timeline_status.as_ref() {
+                                ui.label(status);
+                            }
+                        });
                     });
             }
 
+            Window::new("Shader compilation")
+                .open(&mut self.open_shader_stats)
+                .default_pos([0., 400.])
+                .resizable(false)
+                .frame(Frame::NONE.fill(bg_color).inner_margin(5))
+                .show(&ctx, |ui| Self::shader_stats_contents(ui, art_objects));
+
+            Window::new("Shader printf")
+                .open(&mut self.open_printf)
+                .anchor(Align2::LEFT_BOTTOM, [0., 0.])
+                .resizable(false)
+                .default_width(400.)
+                .frame(Frame::NONE.fill(bg_color).inner_margin(5))
+                .show(&ctx, |ui| {
+                    Self::printf_contents(ui, art_objects, &mut self.printf_art, &mut self.printf_log);
+                });
+
             let mut clicked = false;
             let _ = Window::new("Welcome to shaderpixel")
                 .open(&mut self.open_welcome)
@@ -152,6 +569,9 @@ impl GuiState {
                     });
 
                     ui.label("Click this window to close it.");
+                    if ui.button("Start tour").on_hover_text("Show me around, press T to end it").clicked() {
+                        response.tour = Some(TourAction::Start);
+                    }
                     ui.separator();
                     egui::Grid::new("art_options_grid")
                         .num_columns(2)
@@ -165,6 +585,7 @@ impl GuiState {
                 self.open_welcome = false;
             }
         });
+        response
     }
 
     pub fn toggle_open(&mut self) {
@@ -175,16 +596,193 @@ impl GuiState {
         self.open_welcome = self.open;
     }
 
+    /// Toggles the exhibit browser and opens the interface if it is hidden.
+    pub fn toggle_browser(&mut self) {
+        self.open_browser = !self.open_browser || !self.open;
+        self.open = true;
+    }
+
+    /// Shows the result of saving the timeline next to the save button.
+    pub fn set_timeline_status(&mut self, status: String) {
+        self.timeline_status = Some(status);
+    }
+
+    /// Toggles the timeline and opens the interface if it is hidden.
+    pub fn toggle_timeline(&mut self) {
+        self.open_timeline = !self.open_timeline || !self.open;
+        self.open = true;
+    }
+
+    /// Toggles the shader compilation statistics and opens the interface if it is hidden.
+    pub fn toggle_shader_stats(&mut self) {
+        self.open_shader_stats = !self.open_shader_stats || !self.open;
+        self.open = true;
+    }
+
+    /// Toggles the shader printf console and opens the interface if it is hidden.
+    pub fn toggle_printf(&mut self) {
+        self.open_printf = !self.open_printf || !self.open;
+        self.open = true;
+    }
+
+    /// Shows the options of `name` instead of the nearest exhibit and opens the interface.
+    pub fn pin_art(&mut self, name: &str) {
+        self.pinned_art = Some(name.to_owned());
+        self.open = true;
+        self.open_art_options = true;
+    }
+
+    /// Opens the part of the interface highlighted by the current step of `tour` once when the step changed,
+    /// so it can be closed again. The windows open before the tour are saved to be restored after it.
+    fn open_highlighted(&mut self, tour: &Tour) {
+        if self.tour_state.is_none() {
+            self.tour_state = Some((self.open_windows(), None));
+        }
+        let (current, _) = tour.progress();
+        if let Some((_, opened)) = self.tour_state.as_mut() {
+            if std::mem::replace(opened, Some(current)) == Some(current) {
+                return;
+            }
+        }
+        let step = tour.step();
+        match step.highlight {
+            Some(Highlight::Hud) | None => return,
+            Some(Highlight::Options) => self.open_options = true,
+            Some(Highlight::ArtOptions) => {
+                if step.art.is_some() {
+                    self.pinned_art.clone_from(&step.art);
+                }
+                self.open_art_options = true;
+            }
+            Some(Highlight::Exhibits) => self.open_browser = true,
+            Some(Highlight::Timeline) => self.open_timeline = true,
+        }
+        self.open = true;
+    }
+
+    fn open_windows(&self) -> OpenWindows {
+        OpenWindows {
+            open: self.open,
+            open_options: self.open_options,
+            open_art_options: self.open_art_options,
+            open_browser: self.open_browser,
+            open_timeline: self.open_timeline,
+            pinned_art: self.pinned_art.clone(),
+        }
+    }
+
+    fn restore_windows(&mut self, windows: OpenWindows) {
+        self.open = windows.open;
+        self.open_options = windows.open_options;
+        self.open_art_options = windows.open_art_options;
+        self.open_browser = windows.open_browser;
+        self.open_timeline = windows.open_timeline;
+        self.pinned_art = windows.pinned_art;
+    }
+
+    /// Index of the exhibit opened from the browser if it still exists.
+    pub fn pinned_art(&self, art_objects: &[ArtObject]) -> Option<usize> {
+        let name = self.pinned_art.as_ref()?;
+        art_objects.iter().position(|art| &art.name == name)
+    }
+
+    /// Draws a crosshair in the center of the screen and below it the name of `art` with the keys
+    /// that act on it.
+    fn draw_hud(ctx: &egui::Context, art: Option<&ArtObject>) {
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        let center = ctx.screen_rect().center();
+        let color = if art.is_some() { Color32::WHITE } else { Color32::from_white_alpha(128) };
+        let outline = Stroke::new(3., Color32::from_black_alpha(96));
+        for dir in [Vec2::X, Vec2::Y] {
+            painter.line_segment([center - dir * 6., center + dir * 6.], outline);
+            painter.line_segment([center - dir * 5., center + dir * 5.], Stroke::new(1., color));
+        }
+        let Some(art) = art else { return };
+        let mut text = art.name.clone();
+        if !art.options.is_empty() {
+            text.push_str("\npress E to open options");
+        }
+        text.push_str("\npress F to teleport");
+        let font = egui::FontId::proportional(14.);
+        let pos = center + Vec2::new(0., 16.);
+        // a shadow keeps the text readable on bright exhibits
+        painter.text(pos + Vec2::splat(1.), Align2::CENTER_TOP, &text, font.clone(), Color32::BLACK);
+        painter.text(pos, Align2::CENTER_TOP, text, font, Color32::WHITE);
+    }
+
+    /// Draws the window of the current step of `tour` and a frame around the part of the interface
+    /// it highlights, where that part was in the last frame.
+    fn draw_tour(ctx: &egui::Context, tour: &Tour, id_art_options: Id, bg_color: Color32, response: &mut GuiResponse) {
+        let step = tour.step();
+        let (current, len) = tour.progress();
+        Window::new(format!("Tour {}/{len}", current + 1))
+            .id(Id::new("Tour"))
+            .anchor(Align2::CENTER_TOP, [0., 0.])
+            .resizable(false)
+            .collapsible(false)
+            .default_width(350.)
+            .frame(Frame::NONE.fill(bg_color).inner_margin(5))
+            .show(ctx, |ui| {
+                ui.heading(&step.title);
+                if !step.text.is_empty() {
+                    ui.label(&step.text);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(current > 0, egui::Button::new("Previous")).clicked() {
+                        response.tour = Some(TourAction::Previous);
+                    }
+                    let last = current + 1 == len;
+                    if ui.button(if last { "Finish" } else { "Next" }).clicked() {
+                        response.tour = Some(TourAction::Next);
+                    }
+                    if !last && ui.button("End tour").clicked() {
+                        response.tour = Some(TourAction::End);
+                    }
+                });
+            });
+
+        let rect = match step.highlight {
+            None => return,
+            Some(Highlight::Hud) => {
+                Some(Rect::from_center_size(ctx.screen_rect().center() + Vec2::new(0., 25.), Vec2::new(220., 90.)))
+            }
+            Some(Highlight::Options) => ctx.memory(|mem| mem.area_rect(Id::new("Options"))),
+            Some(Highlight::ArtOptions) => ctx.memory(|mem| mem.area_rect(id_art_options)),
+            Some(Highlight::Exhibits) => ctx.memory(|mem| mem.area_rect(Id::new("Exhibits"))),
+            Some(Highlight::Timeline) => ctx.memory(|mem| mem.area_rect(Id::new("Timeline"))),
+        };
+        if let Some(rect) = rect {
+            let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, Id::new("Tour highlight")));
+            painter.rect_stroke(rect.expand(3.), 4., Stroke::new(2., Color32::YELLOW), egui::StrokeKind::Outside);
+        }
+    }
+
     fn controls_grid_contents(ui: &mut Ui) {
         let controls = [
             ("WASD", "move around"),
             ("space", "move up"),
             ("left shift", "move down"),
+            ("tab", "sprint"),
+            ("C", "crouch"),
             ("scroll wheel", "change movement speed"),
             ("left control", "toggle fly mode"),
+            ("1 to 9", "go to camera bookmark"),
+            ("ctrl + 1 to 9", "save camera bookmark"),
             ("F1", "toggle fullsceen"),
             ("F2", "toggle interface"),
+            ("F3", "toggle exhibit browser"),
+            ("F4", "toggle timeline"),
+            ("F5", "reload all shaders"),
+            ("F6", "toggle shader compilation statistics"),
+            ("F7", "toggle shader printf console"),
+            ("F8", "cycle present mode"),
+            ("E", "open options of the exhibit in the center"),
+            ("F", "teleport to the exhibit in the center"),
+            ("V", "show the flat exhibit in the center full screen"),
             ("L", "reset position"),
+            ("T", "start or end the guided tour"),
+            ("left / right arrow", "previous / next step of the tour"),
             ("esc", "exit"),
         ];
         for (a, b) in controls {
@@ -194,8 +792,283 @@ impl GuiState {
         }
     }
 
+    fn art_presets_contents(ui: &mut Ui, art: &mut ArtObject, preset_name: &mut String) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            let selected = art.presets.selected().map(|preset| preset.name.clone());
+            let mut clicked = None;
+            egui::ComboBox::from_id_salt("Preset select")
+                .selected_text(selected.as_deref().unwrap_or("custom"))
+                .show_ui(ui, |ui| {
+                    for (idx, preset) in art.presets.iter().enumerate() {
+                        let is_selected = selected.as_ref() == Some(&preset.name);
+                        if ui.selectable_label(is_selected, &preset.name).clicked() {
+                            clicked = Some(idx);
+                        }
+                    }
+                });
+            if let Some(idx) = clicked {
+                let current = art.option_values();
+                art.presets.select(idx, current);
+            }
+            if ui.add_enabled(selected.is_some(), egui::Button::new("Delete")).clicked() {
+                art.presets.remove_selected();
+                changed = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(preset_name).on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Name of the preset, saving with an existing name overwrites it.");
+                });
+            });
+            let name = preset_name.trim();
+            if ui.add_enabled(!name.is_empty(), egui::Button::new("Save preset")).clicked() {
+                art.presets.insert(name.to_owned(), art.option_values());
+                preset_name.clear();
+                changed = true;
+            }
+        });
+        if changed {
+            if let Err(err) = art.presets.save() {
+                log::error!("failed to save presets of {}: {err:#}", art.name);
+            }
+        }
+    }
+
+    /// Shows the playback controls and one lane per track with draggable keyframes.
+    /// Keyframes are selected by clicking and removed by right clicking them.
+    fn timeline_contents(ui: &mut Ui, art: &mut ArtObject, selected: &mut Option<(usize, usize)>) {
+        let matrix = art.animated_matrix();
+        let options = &art.options;
+        let Some(animation) = art.animation.as_mut() else {
+            ui.horizontal(|ui| {
+                ui.label("Not animated");
+                if let Some(track) = Self::add_track_menu(ui, options) {
+                    art.animation = Some(Animation::new([track]));
+                }
+            });
+            ui.separator();
+            return;
+        };
+        ui.horizontal(|ui| {
+            if ui.button(if animation.playing { "Pause" } else { "Play" }).clicked() {
+                animation.playing = !animation.playing;
+            }
+            if ui.button("Rewind").clicked() {
+                animation.time = 0.;
+            }
+            let mut looping = animation.tracks.iter().any(|track| track.keyframes().repeat() == Repeat::Loop);
+            if ui.checkbox(&mut looping, "Loop").changed() {
+                let repeat = if looping { Repeat::Loop } else { Repeat::Once };
+                for track in animation.tracks.iter_mut() {
+                    track.keyframes_mut().set_repeat(repeat);
+                }
+            }
+            ui.add(egui::DragValue::new(&mut animation.time).range(0. ..=f32::MAX).speed(0.05).suffix(" s"));
+            if let Some(track) = Self::add_track_menu(ui, options) {
+                animation.tracks.push(track);
+            }
+        });
+
+        let duration = animation.duration().max(animation.time).max(TIMELINE_MIN_DURATION);
+        let time = animation.time;
+        let mut new_time = None;
+        let mut remove_track = None;
+        egui::Grid::new("timeline_grid").num_columns(2).striped(true).show(ui, |ui| {
+            for (track_idx, track) in animation.tracks.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("x").on_hover_text("Remove the track").clicked() {
+                        remove_track = Some(track_idx);
+                    }
+                    let key = ui.small_button("+").on_hover_text("Add a keyframe with the current value");
+                    if key.clicked() {
+                        if let Some(key_idx) = track.insert_current(time, matrix, options) {
+                            *selected = Some((track_idx, key_idx));
+                        }
+                    }
+                    ui.label(track.label());
+                });
+
+                let (rect, lane) = ui.allocate_exact_size(TIMELINE_LANE_SIZE, Sense::click());
+                let painter = ui.painter_at(rect);
+                painter.rect_filled(rect, 0., ui.visuals().extreme_bg_color);
+                if let Some(pos) = lane.interact_pointer_pos().filter(|_| lane.clicked()) {
+                    new_time = Some((pos.x - rect.left()) / rect.width() * duration);
+                }
+                let to_x = |time: f32| rect.left() + time / duration * rect.width();
+                let times = track.keyframes().times();
+                for (key_idx, &key_time) in times.iter().enumerate() {
+                    let center = Pos2::new(to_x(key_time), rect.center().y);
+                    let key_rect = Rect::from_center_size(center, Vec2::splat(rect.height()));
+                    let id = ui.id().with(("Timeline key", track_idx, key_idx));
+                    let response = ui.interact(key_rect, id, Sense::click_and_drag());
+                    if response.dragged() {
+                        // keyframes can not pass each other so the index stays the same while dragging
+                        let min = key_idx.checked_sub(1).map_or(0., |idx| times[idx]);
+                        let max = times.get(key_idx + 1).copied().unwrap_or(f32::MAX);
+                        let dragged_time = key_time + response.drag_delta().x / rect.width() * duration;
+                        track.keyframes_mut().move_key(key_idx, dragged_time.clamp(min, max));
+                        *selected = Some((track_idx, key_idx));
+                    } else if response.clicked() {
+                        *selected = Some((track_idx, key_idx));
+                    } else if response.secondary_clicked() {
+                        track.keyframes_mut().remove(key_idx);
+                        *selected = None;
+                    }
+                    let color = if *selected == Some((track_idx, key_idx)) {
+                        ui.visuals().selection.bg_fill
+                    } else {
+                        ui.visuals().widgets.inactive.fg_stroke.color
+                    };
+                    let radius = rect.height() * 0.4;
+                    let points = [Vec2::Y * -radius, Vec2::X * radius, Vec2::Y * radius, Vec2::X * -radius];
+                    painter.add(egui::Shape::convex_polygon(points.map(|p| center + p).to_vec(), color, Stroke::NONE));
+                }
+                painter.vline(to_x(time), rect.y_range(), Stroke::new(1., Color32::RED));
+                ui.end_row();
+            }
+        });
+        if let Some(time) = new_time {
+            animation.time = time;
+        }
+        if let Some(track_idx) = remove_track {
+            animation.tracks.remove(track_idx);
+            *selected = None;
+        }
+
+        let track = selected.and_then(|(track_idx, key_idx)| Some((animation.tracks.get_mut(track_idx)?, key_idx)));
+        if let Some((track, key_idx)) = track {
+            ui.horizontal(|ui| {
+                ui.label(format!("{} keyframe {}", track.label(), key_idx + 1));
+                let mut easing = track.keyframes().easing(key_idx);
+                egui::ComboBox::from_id_salt("Easing select")
+                    .selected_text(format!("{easing:?}"))
+                    .show_ui(ui, |ui| {
+                        for value in Easing::ALL {
+                            ui.selectable_value(&mut easing, value, format!("{value:?}"));
+                        }
+                    });
+                track.keyframes_mut().set_easing(key_idx, easing);
+            });
+        }
+        ui.separator();
+    }
+
+    /// Shows a menu of the properties of the exhibit and returns an empty track for the clicked one.
+    fn add_track_menu(ui: &mut Ui, options: &[ArtOption]) -> Option<Track> {
+        ui.menu_button("Add track", |ui| {
+            let mut track = None;
+            if ui.button("Position").clicked() {
+                track = Some(Track::Position(Keyframes::new(Repeat::Once)));
+            }
+            if ui.button("Rotation").clicked() {
+                track = Some(Track::Rotation(Keyframes::new(Repeat::Once)));
+            }
+            if ui.button("Scale").clicked() {
+                track = Some(Track::Scale(Keyframes::new(Repeat::Once)));
+            }
+            for option in options.iter() {
+                if ui.button(option.label()).clicked() {
+                    let label = option.label().to_owned().into();
+                    track = Some(Track::Option { label, keyframes: Keyframes::new(Repeat::Once) });
+                }
+            }
+            if track.is_some() {
+                ui.close_menu();
+            }
+            track
+        }).inner.flatten()
+    }
+
+    fn art_randomize_contents(ui: &mut Ui, art: &mut ArtObject) {
+        ui.horizontal(|ui| {
+            if ui.button("Randomize").clicked() {
+                art.randomize_options();
+            }
+            ui.checkbox(&mut art.randomizer.auto, "every").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Randomize the options automatically after the given number of seconds.");
+                });
+            });
+            ui.add_enabled(
+                art.randomizer.auto,
+                egui::DragValue::new(&mut art.randomizer.interval).range(0.5..=600.).suffix(" s"),
+            );
+        });
+    }
+
+    fn art_time_contents(ui: &mut Ui, art: &mut ArtObject) {
+        let data = &mut art.data;
+        let changed = data.time != ArtTime::default() || data.time_offset != 0.;
+        let time = &mut data.time;
+        let mut reset = false;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut time.paused, "Pause").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Freeze the time of this exhibit while the rest of the gallery keeps animating.");
+                });
+            });
+            ui.label("speed");
+            ui.add(egui::DragValue::new(&mut time.speed).range(0.0..=4.).speed(0.01).suffix("x"));
+            ui.label("offset");
+            ui.add(egui::DragValue::new(&mut time.offset).speed(0.05).suffix(" s"));
+            reset = ui.add_enabled(changed, egui::Button::new("Reset")).clicked();
+        });
+        if reset {
+            data.reset_time();
+        }
+    }
+
+    fn art_compare_contents(ui: &mut Ui, art: &mut ArtObject, compare_path: &mut String) {
+        if let Some(compare) = art.compare.as_mut() {
+            ui.label(format!("Right side: {}", compare.label));
+            let mut stop = false;
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(&mut compare.split, 0.0..=1.0).text("split"));
+                stop = ui.button("Stop").clicked();
+            });
+            if stop {
+                art.compare = None;
+            }
+            return;
+        }
+
+        let snapshot = ui.button("Compare with snapshot").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Show the last successfully compiled shader on the right side, \
+                    further edits to the shader file only change the left side.");
+            });
+        });
+        if snapshot.clicked() {
+            match art.shader_frag.snapshot() {
+                Some(shader) => art.compare = Some(Compare {
+                    shader_frag: Arc::new(shader),
+                    label: "snapshot".to_owned(),
+                    split: 0.5,
+                }),
+                None => log::warn!("{} has no compiled shader to compare with", art.name),
+            }
+        }
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(compare_path).on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Path of a fragment shader to show on the right side.");
+                });
+            });
+            let path = compare_path.trim();
+            if ui.add_enabled(!path.is_empty(), egui::Button::new("Compare")).clicked() {
+                art.compare = Some(Compare {
+                    shader_frag: Arc::new(HotShader::new_frag(path)),
+                    label: path.to_owned(),
+                    split: 0.5,
+                });
+            }
+        });
+    }
+
     fn art_options_grid_contents(ui: &mut Ui, options: &mut [ArtOption]) {
-        for option in options {
+        for (i, option) in options.iter_mut().enumerate() {
             ui.label(option.label());
             match &mut option.ty {
                 ArtOptionType::Checkbox { checked } => {
@@ -213,6 +1086,26 @@ impl GuiState {
                     *width = stroke.width;
                     *color = stroke.color;
                 }
+                ArtOptionType::Color { rgba } => {
+                    ui.color_edit_button_srgba_unmultiplied(rgba);
+                }
+                ArtOptionType::Vec2 { value, min, max } => vector_drag(ui, value, *min, *max),
+                ArtOptionType::Vec3 { value, min, max } => vector_drag(ui, value, *min, *max),
+                ArtOptionType::Enum { value, labels } => {
+                    egui::ComboBox::from_id_salt(("Art option", i))
+                        .selected_text(labels.get(*value).copied().unwrap_or_default())
+                        .show_ui(ui, |ui| {
+                            for (idx, label) in labels.iter().enumerate() {
+                                ui.selectable_value(value, idx, *label);
+                            }
+                        });
+                }
+                ArtOptionType::Angle { degrees } => {
+                    ui.horizontal(|ui| {
+                        angle_dial(ui, degrees);
+                        ui.add(egui::DragValue::new(degrees).range(0. ..=360.).suffix("°"));
+                    });
+                }
             }
             ui.end_row();
         }
@@ -243,9 +1136,17 @@ impl GuiState {
             });
         ui.end_row();
 
+        ui.label("UI scale").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Scales the interface, e.g. to make it readable on high resolution displays.");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.ui_scale, 0.5..=3.0).step_by(0.25).suffix("x"));
+        ui.end_row();
+
         ui.label("Present Mode").on_hover_ui(|ui| {
             ui.horizontal_wrapped(|ui| {
-                ui.label("Sets the vulkan present mode.");
+                ui.label("Sets the vulkan present mode, F8 cycles through Fifo, Mailbox and Immediate.");
             });
         });
         let present_mode_old = state.present_mode;
@@ -261,6 +1162,139 @@ impl GuiState {
             });
         ui.end_row();
 
+        #[cfg(feature = "audio")]
+        {
+            ui.label("Ambient volume").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Change the volume of the background sound.");
+                });
+            });
+            ui.add(egui::Slider::new(&mut state.ambient_volume, 0.0..=1.0));
+            ui.end_row();
+
+            ui.label("Effects volume").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Change the volume of the sounds of the exhibits, like the hum of the portal.");
+                });
+            });
+            ui.add(egui::Slider::new(&mut state.effects_volume, 0.0..=1.0));
+            ui.end_row();
+        }
+
+        ui.label("Smooth time").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Advance the animations in whole refresh intervals of the display \
+                    instead of the measured frame time, which jitters by a few milliseconds.");
+            });
+        });
+        ui.checkbox(&mut state.snap_time, "");
+        ui.end_row();
+
+        ui.label("Reduced motion").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("For people sensitive to motion and flicker. The shader time passes slower, which \
+                    lowers the frequency of flashing effects, and exhibits are asked to calm down.");
+            });
+        });
+        ui.checkbox(&mut state.reduced_motion, "");
+        ui.end_row();
+
+        ui.label("Auto quality").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Lower the resolution and the detail of the exhibits when the frame rate drops below \
+                    the target, e.g. when the GPU throttles, and raise them again once it holds.");
+            });
+        });
+        ui.horizontal(|ui| {
+            let auto_quality = &mut state.auto_quality;
+            ui.checkbox(&mut auto_quality.enabled, "");
+            ui.add_enabled(
+                auto_quality.enabled,
+                egui::DragValue::new(&mut auto_quality.target_fps).range(10.0..=240.).suffix(" fps"),
+            );
+            let level = auto_quality.level();
+            let fps = auto_quality.average().map_or_else(String::new, |average| format!(", {:.0} fps", 1. / average));
+            ui.label(format!("{} ({:.0}%{fps})", level.label, level.render_scale * 100.)).on_hover_ui(|ui| {
+                ui.label(format!("render scale {:.0}%, detail {:.0}%", level.render_scale * 100., level.detail * 100.));
+            });
+        });
+        ui.end_row();
+
+        ui.label("Buffering").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Sets the number of swapchain images. Triple buffering can smooth out uneven frame times \
+                    at the cost of latency. The surface may give more images than requested.");
+            });
+        });
+        ui.horizontal(|ui| {
+            let image_count_old = state.image_count;
+            ui.selectable_value(&mut state.image_count, 2, "Double");
+            ui.selectable_value(&mut state.image_count, 3, "Triple");
+            if state.image_count != image_count_old {
+                state.recreate_swapchain = true;
+            }
+            ui.label(format!("({} images)", state.actual_image_count));
+        });
+        ui.end_row();
+
+        fn monitor_label(monitor: Option<&MonitorHandle>) -> String {
+            match monitor {
+                Some(monitor) => monitor.name().unwrap_or_else(|| "Unnamed".to_owned()),
+                None => "Current".to_owned(),
+            }
+        }
+        fn video_mode_label(mode: Option<&VideoModeHandle>) -> String {
+            match mode {
+                Some(mode) => {
+                    let size = mode.size();
+                    let refresh_rate = mode.refresh_rate_millihertz() as f32 / 1000.;
+                    format!("{}x{} @ {refresh_rate:.0} Hz, {} bit", size.width, size.height, mode.bit_depth())
+                }
+                None => "Borderless".to_owned(),
+            }
+        }
+
+        ui.label("Fullscreen monitor").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Sets the monitor used in fullscreen, toggled with F1.");
+            });
+        });
+        egui::ComboBox::from_id_salt("Monitor select")
+            .selected_text(monitor_label(state.monitor.as_ref()))
+            .show_ui(ui, |ui| {
+                let mut selected = state.monitor.clone();
+                ui.selectable_value(&mut selected, None, monitor_label(None));
+                for monitor in state.monitors.iter() {
+                    let label = monitor_label(Some(monitor));
+                    ui.selectable_value(&mut selected, Some(monitor.clone()), label);
+                }
+                if selected != state.monitor {
+                    state.select_monitor(selected);
+                    state.apply_fullscreen = true;
+                }
+            });
+        ui.end_row();
+
+        ui.label("Fullscreen mode").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Exclusive fullscreen with the selected resolution and refresh rate \
+                    has the lowest latency. Needs a selected monitor.");
+            });
+        });
+        let video_mode_old = state.video_mode.clone();
+        egui::ComboBox::from_id_salt("Video mode select")
+            .selected_text(video_mode_label(state.video_mode.as_ref()))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut state.video_mode, None, video_mode_label(None));
+                for mode in state.video_modes.iter() {
+                    ui.selectable_value(&mut state.video_mode, Some(mode.clone()), video_mode_label(Some(mode)));
+                }
+            });
+        if state.video_mode != video_mode_old {
+            state.apply_fullscreen = true;
+        }
+        ui.end_row();
+
         ui.label("Sun movement").on_hover_ui(|ui| {
             ui.horizontal_wrapped(|ui| {
                 ui.label("Toggle movement of the sun across the sky.");
@@ -277,13 +1311,509 @@ impl GuiState {
         ui.add(egui::Slider::new(&mut state.sun_speed, 0.0..=10.0));
         ui.end_row();
 
+        ui.label("Scene").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Architecture of the gallery, the exhibits stay where they are.");
+            });
+        });
+        egui::ComboBox::from_id_salt("Scene select")
+            .selected_text(state.scene.as_str())
+            .show_ui(ui, |ui| {
+                for scene in state.scenes.iter() {
+                    ui.selectable_value(&mut state.scene, scene.clone(), scene);
+                }
+            });
+        ui.end_row();
+
+        ui.label("Clear colors").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Colors of the scene and the mirror where nothing is drawn.");
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgba_unmultiplied(&mut state.clear_colors.scene);
+            ui.color_edit_button_rgba_unmultiplied(&mut state.clear_colors.mirror);
+            ui.checkbox(&mut state.debug_clear, "debug").on_hover_text("Clear with magenta and yellow instead.");
+        });
+        ui.end_row();
+
+        ui.label("Color grading").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Path of a lookup table applied to the whole frame, either a .cube file or a strip \
+                    image. Leave it empty to turn the grading off.");
+            });
+        });
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut state.color_lut);
+                state.apply_color_lut |= ui.button("Load").clicked();
+            });
+            ui.add(egui::Slider::new(&mut state.color_grading, 0.0..=1.0).text("strength"));
+        });
+        ui.end_row();
+
+        ui.label("Output").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Adjust the final colors for the display, e.g. brighten them for a projector.");
+            });
+        });
+        ui.vertical(|ui| {
+            let output = &mut state.output;
+            ui.add(egui::Slider::new(&mut output.exposure, -3.0..=3.0).text("exposure"))
+                .on_hover_text("Brightens the image by this many stops.");
+            ui.add(egui::Slider::new(&mut output.contrast, 0.0..=2.0).text("contrast"));
+            ui.add(egui::Slider::new(&mut output.saturation, 0.0..=2.0).text("saturation"));
+            ui.add(egui::Slider::new(&mut output.gamma, 0.2..=3.0).text("gamma"))
+                .on_hover_text("Values above 1 brighten the midtones.");
+            if ui.add_enabled(!output.is_neutral(), egui::Button::new("Reset")).clicked() {
+                *output = OutputAdjustments::default();
+            }
+        });
+        ui.end_row();
+
+        ui.label("Transition").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Blends from the old to the new view when the camera jumps, e.g. to a bookmark, \
+                    or an exhibit is shown full screen. A shader is a transition in the format of gl-transitions.");
+            });
+        });
+        ui.vertical(|ui| {
+            let transition = &mut state.transition;
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("Transition select")
+                    .selected_text(transition.style.label())
+                    .show_ui(ui, |ui| {
+                        for style in TransitionStyle::ALL {
+                            ui.selectable_value(&mut transition.style, style, style.label());
+                        }
+                    });
+                ui.add_enabled(
+                    transition.style != TransitionStyle::Off,
+                    egui::DragValue::new(&mut transition.duration).range(0.05..=5.0).speed(0.01).suffix(" s"),
+                );
+            });
+            if transition.style == TransitionStyle::Shader {
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut state.transition_shader);
+                    if ui.button("Load").clicked() {
+                        transition.shader = state.transition_shader.trim().into();
+                    }
+                });
+            }
+        });
+        ui.end_row();
+
+        ui.label("View link").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Paste a link copied from the options of an exhibit to see the same view.");
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut state.permalink);
+            state.open_permalink |= ui.button("Open").clicked();
+        });
+        ui.end_row();
+
         ui.label("FOV").on_hover_ui(|ui| {
             ui.horizontal_wrapped(|ui| {
                 ui.label("Change the field of view.");
             });
         });
-        ui.add(egui::Slider::new(&mut state.fov, 1.0..=179.0).suffix("°"));
+        let projection = &mut state.projection;
+        ui.add(egui::Slider::new(&mut state.fov, projection.min_fov..=projection.max_fov).suffix("°"));
+        ui.end_row();
+
+        ui.label("Clip planes").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Distances of the near and far clip planes. A smaller near plane allows close-ups, \
+                    a farther far plane shows more of large galleries. The further apart they are, \
+                    the less precise the depth buffer gets.");
+            });
+        });
+        ui.horizontal(|ui| {
+            let max_near = projection.far / 10.;
+            ui.add(egui::DragValue::new(&mut projection.near)
+                .range(projection.far / Projection::MAX_DEPTH_RATIO..=max_near)
+                .speed(projection.near * 0.01)
+                .max_decimals(5)
+                .prefix("near "));
+            ui.add(egui::DragValue::new(&mut projection.far)
+                .range(projection.near * 10.0..=projection.near * Projection::MAX_DEPTH_RATIO)
+                .speed(projection.far * 0.01)
+                .prefix("far "));
+            if ui.add_enabled(*projection != Projection::default(), egui::Button::new("Reset")).clicked() {
+                *projection = Projection::default();
+            }
+        });
+        ui.end_row();
+
+        ui.label("Stereo 3D").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Render a view for each eye, side by side for 3D displays or as anaglyph for \
+                    red-cyan glasses. The GUI is only drawn for the right eye.");
+            });
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("Stereo select")
+                .selected_text(state.stereo.label())
+                .show_ui(ui, |ui| {
+                    for mode in StereoMode::ALL {
+                        ui.selectable_value(&mut state.stereo, mode, mode.label());
+                    }
+                });
+            ui.add_enabled(
+                state.stereo != StereoMode::Off,
+                egui::Slider::new(&mut state.ipd, 0.0..=0.2).text("IPD"),
+            ).on_hover_text("Distance between the eyes in units.");
+        });
+        ui.end_row();
+
+        ui.label("Second window").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Open another window that shows this GUI instead of the scene or the scene from \
+                    where the camera is now.");
+            });
+        });
+        egui::ComboBox::from_id_salt("Second window select")
+            .selected_text(state.second_window.label())
+            .show_ui(ui, |ui| {
+                for mode in SecondWindowMode::ALL {
+                    ui.selectable_value(&mut state.second_window, mode, mode.label());
+                }
+            });
+        ui.end_row();
+
+        ui.label("HUD").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Show a crosshair and the name of the exhibit in the center of the screen, even if the \
+                    interface is hidden.");
+            });
+        });
+        ui.checkbox(&mut state.hud, "");
+        ui.end_row();
+
+        ui.label("Interaction radius").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Distance to an exhibit (in units) within which its options are shown.");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.interaction.radius, 0.5..=5.0));
+        ui.end_row();
+
+        ui.label("Auto-open options").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Show the options of the nearest exhibit on their own, else only with E or from the \
+                    browser.");
+            });
+        });
+        ui.checkbox(&mut state.interaction.auto_open, "");
+        ui.end_row();
+
+        ui.label("Pause others").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Stop the time of the other exhibits while one is within the interaction radius.");
+            });
+        });
+        ui.checkbox(&mut state.interaction.pause_others, "");
+        ui.end_row();
+
+        ui.label("Attract mode").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Tour the gallery after some time without input, for unattended exhibitions. Any \
+                    input gives the control back.");
+            });
+        });
+        ui.checkbox(&mut state.attract.enabled, "");
+        ui.end_row();
+
+        ui.label("Idle timeout");
+        ui.add(egui::Slider::new(&mut state.attract.idle_timeout, 10.0..=600.0).suffix(" s"));
+        ui.end_row();
+
+        ui.label("Animate options").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Slowly change the options of the nearest exhibit during the tour.");
+            });
+        });
+        ui.checkbox(&mut state.attract.animate_options, "");
         ui.end_row();
+
+        ui.label("Attract path").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Record the path of the tour by walking it and end where you started, as the tour \
+                    loops. It is saved to attract.json when the recording is stopped. Without a recording the \
+                    tour visits the exhibits.");
+            });
+        });
+        let label = if state.record_attract { "Stop recording" } else { "Record" };
+        if ui.button(label).clicked() {
+            state.record_attract = !state.record_attract;
+        }
+        ui.end_row();
+
+        ui.label("Walk speed").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change the movement speed on the ground (in units per second).");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.movement.walk_speed, 0.1..=10.0));
+        ui.end_row();
+
+        ui.label("Fly speed").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change the movement speed in fly mode (in units per second).");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.movement.fly_speed, 0.1..=10.0));
+        ui.end_row();
+
+        ui.label("Sprint multiplier").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change how much faster you move while holding tab.");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.movement.sprint_multiplier, 1.0..=10.0).suffix("x"));
+        ui.end_row();
+
+        ui.label("Scroll speed").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change how much the scroll wheel changes the movement speed, \
+                    every line scrolled multiplies it by e to the power of this value.");
+            });
+        });
+        ui.add(egui::Slider::new(&mut state.movement.scroll_factor, 0.0..=1.0));
+        ui.end_row();
+
+        let smoothing = &mut state.movement.smoothing;
+        ui.label("Smooth motion").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Ease the movement and rotation in and out instead of starting and stopping instantly, \
+                    e.g. for recording videos.");
+            });
+        });
+        ui.checkbox(&mut smoothing.enabled, "");
+        ui.end_row();
+
+        ui.label("Acceleration").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change how fast the full speed is reached, higher is faster.");
+            });
+        });
+        ui.add_enabled(smoothing.enabled, egui::Slider::new(&mut smoothing.acceleration, 0.5..=20.0));
+        ui.end_row();
+
+        ui.label("Deceleration").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change how fast the camera stops after releasing the keys, higher is faster.");
+            });
+        });
+        ui.add_enabled(smoothing.enabled, egui::Slider::new(&mut smoothing.deceleration, 0.5..=20.0));
+        ui.end_row();
+
+        ui.label("Rotation damping").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Change how fast the rotation follows the mouse, higher is faster.");
+            });
+        });
+        ui.add_enabled(smoothing.enabled, egui::Slider::new(&mut smoothing.rotation, 1.0..=30.0));
+        ui.end_row();
+
+        ui.label("Physical sky").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Toggle between the physical sky and a simple gradient.");
+            });
+        });
+        ui.checkbox(&mut state.sky.physical, "enable");
+        ui.end_row();
+
+        ui.label("Day cycle").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Let the sun rise and set. Else it circles above the horizon.");
+            });
+        });
+        ui.add_enabled(state.sky.physical, egui::Checkbox::new(&mut state.sky.day_cycle, "enable"));
+        ui.end_row();
+
+        let sliders = [
+            ("Rayleigh", "Scale the Rayleigh scattering which makes the sky blue.", &mut state.sky.rayleigh),
+            ("Mie", "Scale the Mie scattering which makes the sky hazy.", &mut state.sky.mie),
+            ("Sun size", "Scale the size of the sun disc.", &mut state.sky.sun_size),
+            ("Stars", "Change the brightness of the stars at night.", &mut state.sky.star_brightness),
+        ];
+        for (label, hover, value) in sliders {
+            ui.label(label).on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(hover);
+                });
+            });
+            ui.add_enabled(state.sky.physical, egui::Slider::new(value, 0.0..=10.0));
+            ui.end_row();
+        }
+
+        if !state.groups.is_empty() {
+            ui.label("Groups").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Show or hide whole groups of exhibits. \
+                        An exhibit is hidden if any of its groups is hidden.");
+                });
+            });
+            ui.vertical(|ui| {
+                for (name, shown) in state.groups.iter_mut() {
+                    ui.checkbox(shown, name.as_str());
+                }
+            });
+            ui.end_row();
+        }
+
+        ui.label("Shaders").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Recompile all shaders, e.g. after an include changed \
+                    or if changes on network drives are missed. Also bound to F5.");
+            });
+        });
+        if ui.button("Reload all").clicked() {
+            state.reload_shaders = true;
+        }
+        ui.end_row();
+
+        ui.label("Cost heatmap").on_hover_ui(|ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Color the ray marched exhibits by the iterations per pixel, \
+                    from blue for none to red for 256 or more. Helps to tune the epsilon and iteration options.");
+            });
+        });
+        ui.checkbox(&mut state.cost_heatmap, "");
+        ui.end_row();
+    }
+
+    /// Lists the shaders of all exhibits, the slowest to compile first.
+    fn shader_stats_contents(ui: &mut Ui, art_objects: &[ArtObject]) {
+        let mut shaders = Vec::<&Arc<HotShader>>::new();
+        for art in art_objects {
+            let compare = art.compare.as_ref().map(|compare| &compare.shader_frag);
+            for shader in [&art.shader_vert, &art.shader_frag].into_iter().chain(compare) {
+                if shader.path().is_some() && !shaders.iter().any(|other| Arc::ptr_eq(other, shader)) {
+                    shaders.push(shader);
+                }
+            }
+        }
+        let mut stats = shaders.into_iter()
+            .map(|shader| (shader.path().unwrap_or(Path::new("")), shader.compile_stats(), shader.warnings()))
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, a, _), (_, b, _)| b.duration.cmp(&a.duration));
+
+        egui::ScrollArea::vertical().max_height(400.).show(ui, |ui| {
+            egui::Grid::new("shader_stats_grid")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Shader");
+                    ui.strong("Time");
+                    ui.strong("SPIR-V");
+                    ui.strong("Compiles");
+                    ui.strong("Warnings");
+                    ui.end_row();
+                    for (path, stats, warnings) in stats {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                        ui.label(name).on_hover_text(path.display().to_string());
+                        match stats.duration {
+                            Some(duration) => ui.label(format!("{:.1} ms", duration.as_secs_f32() * 1000.)),
+                            None => ui.label("-"),
+                        };
+                        ui.label(format!("{:.1} KiB", stats.spirv_size as f32 / 1024.));
+                        ui.label(stats.count.to_string());
+                        if warnings.is_empty() {
+                            ui.label("0");
+                        } else {
+                            ui.colored_label(Color32::YELLOW, warnings.len().to_string())
+                                .on_hover_text(warnings.join("\n"));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    /// Selects the exhibit whose fragment shader prints and shows the output.
+    fn printf_contents(
+        ui: &mut Ui,
+        art_objects: &[ArtObject],
+        printf_art: &mut Option<String>,
+        printf_log: &mut VecDeque<String>,
+    ) {
+        if !debug_printf_enabled() {
+            ui.label("Start with SHADERPIXEL_DEBUG_PRINTF=1 to collect the output of debugPrintfEXT.");
+            return;
+        }
+        let mut selected = printf_art.clone();
+        ui.horizontal(|ui| {
+            ui.label("Exhibit").on_hover_ui(|ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Its fragment shader is compiled with GL_EXT_debug_printf and DEBUG_PRINTF defined. \
+                        Other exhibits using the same shader print too.");
+                });
+            });
+            egui::ComboBox::from_id_salt("printf exhibit")
+                .selected_text(selected.as_deref().unwrap_or("None"))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, None, "None");
+                    for art in art_objects {
+                        ui.selectable_value(&mut selected, Some(art.name.clone()), art.name.as_str());
+                    }
+                });
+            if ui.button("Clear").clicked() {
+                printf_log.clear();
+            }
+        });
+        if selected != *printf_art {
+            let find = |name: &Option<String>| art_objects.iter().find(|art| Some(&art.name) == name.as_ref());
+            if let Some(art) = find(printf_art) {
+                art.shader_frag.set_debug_printf(false);
+            }
+            if let Some(art) = find(&selected) {
+                art.shader_frag.set_debug_printf(true);
+            }
+            *printf_art = selected;
+            printf_log.clear();
+        }
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(200.)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in printf_log.iter() {
+                    ui.monospace(line);
+                }
+            });
+    }
+
+    /// Adds the CPU timings of the last frame, they are kept as long as the frame timings.
+    pub fn push_cpu_timings(&mut self, timings: CpuTimings) {
+        self.cpu_timings.push_front(timings);
+        self.cpu_timings.truncate(self.frame_timings.len().max(1));
+    }
+
+    /// Average and maximum time of each CPU stage over the frames in the chart.
+    fn cpu_timings_contents(ui: &mut Ui, cpu_timings: &VecDeque<CpuTimings>) {
+        if cpu_timings.is_empty() {
+            return;
+        }
+        ui.label("If the acquire stage dominates the CPU waits for the GPU.");
+        egui::Grid::new("cpu_timings_grid").striped(true).show(ui, |ui| {
+            ui.label("Stage");
+            ui.label("Avg");
+            ui.label("Max");
+            ui.end_row();
+            for (idx, (name, _)) in CpuTimings::default().stages().into_iter().enumerate() {
+                let durations = cpu_timings.iter().map(|timings| timings.stages()[idx].1);
+                let max = durations.clone().max().unwrap_or_default();
+                let avg = durations.sum::<Duration>() / cpu_timings.len() as u32;
+                ui.label(name);
+                ui.label(format!("{:.2} ms", avg.as_secs_f32() * 1000.));
+                ui.label(format!("{:.2} ms", max.as_secs_f32() * 1000.));
+                ui.end_row();
+            }
+        });
     }
 
     fn draw_fps_chart(ui: &mut Ui, frame_timings: &VecDeque<Duration>) {
@@ -353,16 +1883,101 @@ impl Default for GuiState {
             open_options: true,
             open_art_options: true,
             open_welcome: true,
+            open_browser: false,
+            open_timeline: false,
+            open_shader_stats: false,
+            open_printf: false,
+            printf_art: None,
+            printf_log: VecDeque::new(),
+            timeline_selected: None,
+            timeline_status: None,
+            browser_search: String::new(),
+            pinned_art: None,
             frame_timings: VecDeque::new(),
+            cpu_timings: VecDeque::new(),
+            export_status: None,
+            preset_name: String::new(),
+            compare_path: String::new(),
+            tour_state: None,
             options: Options {
                 recreate_swapchain: false,
+                reload_shaders: false,
+                cost_heatmap: false,
                 present_modes: Vec::new(),
                 present_mode: PresentMode::Fifo,
+                image_count: 2,
+                actual_image_count: 0,
                 theme: Theme::Dark,
+                ui_scale: 1.,
+                snap_time: true,
+                reduced_motion: false,
+                auto_quality: AutoQuality::default(),
                 sun_movement: true,
                 sun_speed: 0.2,
                 fov: 75.,
+                projection: Projection::default(),
+                stereo: StereoMode::Off,
+                ipd: 0.064,
+                second_window: SecondWindowMode::Off,
+                hud: true,
+                interaction: Interaction::default(),
+                attract: Attract::default(),
+                record_attract: false,
+                movement: Movement::default(),
+                sky: SkyOptions::default(),
+                clear_colors: ClearColors::default(),
+                debug_clear: false,
+                scene: String::new(),
+                scenes: Vec::new(),
+                color_lut: String::new(),
+                apply_color_lut: false,
+                permalink: String::new(),
+                open_permalink: false,
+                color_grading: 1.,
+                output: OutputAdjustments::default(),
+                transition: TransitionSettings::default(),
+                transition_shader: TransitionSettings::default().shader.display().to_string(),
+                ambient_volume: 0.5,
+                effects_volume: 1.,
+                groups: Vec::new(),
+                apply_fullscreen: false,
+                monitors: Vec::new(),
+                monitor: None,
+                video_modes: Vec::new(),
+                video_mode: None,
             },
         }
     }
 }
+
+/// One drag value per component, all in the range `min..=max`.
+fn vector_drag(ui: &mut Ui, values: &mut [f32], min: f32, max: f32) {
+    ui.horizontal(|ui| {
+        for value in values {
+            ui.add(egui::DragValue::new(value).range(min..=max).speed((max - min) / 200.));
+        }
+    });
+}
+
+/// A dial to pick a direction by clicking or dragging, 0 degrees points right
+/// and the angle increases counterclockwise.
+fn angle_dial(ui: &mut Ui, degrees: &mut f32) -> egui::Response {
+    let size = ui.spacing().interact_size.y * 1.5;
+    let (rect, mut response) = ui.allocate_exact_size(Vec2::splat(size), egui::Sense::click_and_drag());
+    if let Some(pos) = response.interact_pointer_pos() {
+        let dir = pos - rect.center();
+        if dir != Vec2::ZERO {
+            *degrees = (-dir.y).atan2(dir.x).to_degrees().rem_euclid(360.);
+            response.mark_changed();
+        }
+    }
+    if ui.is_rect_visible(rect) {
+        let visuals = ui.style().interact(&response);
+        let radius = rect.width() / 2. - visuals.fg_stroke.width;
+        ui.painter().circle(rect.center(), radius, visuals.bg_fill, visuals.bg_stroke);
+        // screen coordinates point down
+        let dir = Vec2::angled(-degrees.to_radians());
+        ui.painter().line_segment([rect.center(), rect.center() + dir * radius], visuals.fg_stroke);
+    }
+    response
+}
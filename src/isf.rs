@@ -0,0 +1,323 @@
+//! Import of shaders in the Interactive Shader Format, see <https://docs.isf.video/>.
+//!
+//! An ISF file is a GLSL fragment shader starting with a comment containing JSON metadata.
+//! The inputs of the metadata are mapped to art options and the standard ISF uniforms to
+//! our uniform buffer, so the file can be compiled like any other shader of a 2D exhibit.
+//! Only single pass shaders without a custom vertex shader are supported and
+//! the image input, if any, is bound to the webcam.
+
+use crate::art::{ArtOption, MAX_OPTION_SLOTS};
+
+use std::fmt::Write;
+
+use anyhow::Context;
+use egui::Color32;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Resolution of the exhibit reported to shaders as `RENDERSIZE`, must match `GLSL_PRELUDE`.
+const RENDER_SIZE: f32 = 1024.;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct IsfHeader {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub credit: Option<String>,
+    #[serde(default)]
+    pub inputs: Vec<IsfInput>,
+    #[serde(default)]
+    pub passes: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IsfType {
+    Event,
+    Bool,
+    Long,
+    Float,
+    Point2D,
+    Color,
+    Image,
+    Audio,
+    #[serde(rename = "audioFFT")]
+    AudioFft,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct IsfInput {
+    pub name: String,
+    #[serde(rename = "TYPE")]
+    pub ty: IsfType,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub min: Option<Value>,
+    #[serde(default)]
+    pub max: Option<Value>,
+    #[serde(default)]
+    pub values: Vec<i32>,
+}
+
+impl IsfInput {
+    fn label(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// Number of option values used by this input.
+    fn slot_count(&self) -> usize {
+        match self.ty {
+            IsfType::Event | IsfType::Bool | IsfType::Long | IsfType::Float => 1,
+            IsfType::Point2D => 2,
+            IsfType::Color => 3,
+            IsfType::Image | IsfType::Audio | IsfType::AudioFft => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct IsfShader {
+    pub header: IsfHeader,
+    body: String,
+    /// Line of the original file the body starts at.
+    body_line: usize,
+}
+
+impl IsfShader {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        let trimmed = source.trim_start();
+        let rest = trimmed.strip_prefix("/*")
+            .ok_or_else(|| anyhow::anyhow!("missing metadata comment at the start of the file"))?;
+        let end = rest.find("*/").ok_or_else(|| anyhow::anyhow!("unterminated metadata comment"))?;
+        let header: IsfHeader = serde_json::from_str(&rest[..end]).context("invalid metadata")?;
+        let body = &rest[end + 2..];
+        let body_line = source[..source.len() - body.len()].lines().count();
+
+        if header.passes.len() > 1 {
+            anyhow::bail!("multiple passes are not supported");
+        }
+        let mut slots = 0;
+        let mut images = 0;
+        for input in header.inputs.iter() {
+            match input.ty {
+                IsfType::Audio | IsfType::AudioFft => {
+                    anyhow::bail!("input {} has unsupported type {:?}", input.name, input.ty);
+                }
+                IsfType::Image => images += 1,
+                _ => slots += input.slot_count(),
+            }
+        }
+        if images > 1 {
+            anyhow::bail!("only one image input is supported, got {images}");
+        }
+        if slots > MAX_OPTION_SLOTS {
+            anyhow::bail!("inputs need {slots} values but only {MAX_OPTION_SLOTS} are available");
+        }
+
+        Ok(Self { header, body: body.to_owned(), body_line })
+    }
+
+    pub fn has_image_input(&self) -> bool {
+        self.header.inputs.iter().any(|input| input.ty == IsfType::Image)
+    }
+
+    /// Options in the same order as the values are read by the generated shader.
+    pub fn options(&self) -> Vec<ArtOption> {
+        let mut options = Vec::new();
+        for input in self.header.inputs.iter() {
+            let default = input.default.as_ref();
+            let label = input.label();
+            match input.ty {
+                IsfType::Event => options.push(ArtOption::checkbox(label, false)),
+                IsfType::Bool => {
+                    options.push(ArtOption::checkbox(label, as_f32(default).unwrap_or(0.) != 0.));
+                }
+                IsfType::Long => {
+                    let (min, max) = match (input.values.iter().min(), input.values.iter().max()) {
+                        (Some(&min), Some(&max)) => (min, max),
+                        _ => (
+                            as_f32(input.min.as_ref()).unwrap_or(0.) as i32,
+                            as_f32(input.max.as_ref()).unwrap_or(1.) as i32,
+                        ),
+                    };
+                    let value = as_f32(default).map_or(min, |value| value as i32);
+                    options.push(ArtOption::slider_i32(label, value, min, max));
+                }
+                IsfType::Float => {
+                    let min = as_f32(input.min.as_ref()).unwrap_or(0.);
+                    let max = as_f32(input.max.as_ref()).unwrap_or(1.);
+                    let value = as_f32(default).unwrap_or(min);
+                    options.push(ArtOption::slider_f32(label, value, min, max));
+                }
+                IsfType::Point2D => {
+                    let value = as_vec(default).unwrap_or_default();
+                    let min = as_vec(input.min.as_ref()).unwrap_or_default();
+                    let max = as_vec(input.max.as_ref()).unwrap_or_default();
+                    for (i, axis) in ["x", "y"].into_iter().enumerate() {
+                        let min = min.get(i).copied().unwrap_or(0.);
+                        let max = max.get(i).copied().unwrap_or(RENDER_SIZE);
+                        let value = value.get(i).copied().unwrap_or(min);
+                        options.push(ArtOption::slider_f32(format!("{label} {axis}"), value, min, max));
+                    }
+                }
+                IsfType::Color => {
+                    let rgba = as_vec(default).unwrap_or_default();
+                    let [r, g, b] = [0, 1, 2].map(|i| {
+                        (rgba.get(i).copied().unwrap_or(1.).clamp(0., 1.) * 255.).round() as u8
+                    });
+                    options.push(ArtOption::stroke(label, 1., Color32::from_rgb(r, g, b)));
+                }
+                IsfType::Image | IsfType::Audio | IsfType::AudioFft => {}
+            }
+        }
+        options
+    }
+
+    /// Translates the shader into GLSL that can be used as fragment shader of a 2D exhibit.
+    pub fn glsl(&self) -> String {
+        let mut glsl = String::from(GLSL_PRELUDE);
+        let mut slot = 0;
+        let mut next_slot = || {
+            let value = format!("ubo.options[{}][{}]", slot / 4, slot % 4);
+            slot += 1;
+            value
+        };
+        for input in self.header.inputs.iter() {
+            let name = &input.name;
+            // writing to a String does not fail
+            let _ = match input.ty {
+                IsfType::Event | IsfType::Bool => writeln!(glsl, "#define {name} ({} > 0.5)", next_slot()),
+                IsfType::Long => writeln!(glsl, "#define {name} int({})", next_slot()),
+                IsfType::Float => writeln!(glsl, "#define {name} {}", next_slot()),
+                IsfType::Point2D => writeln!(glsl, "#define {name} vec2({}, {})", next_slot(), next_slot()),
+                IsfType::Color => {
+                    writeln!(glsl, "#define {name} vec4({}, {}, {}, 1.0)", next_slot(), next_slot(), next_slot())
+                }
+                IsfType::Image => writeln!(glsl, "layout(set = 0, binding = 2) uniform sampler2D {name};"),
+                IsfType::Audio | IsfType::AudioFft => Ok(()),
+            };
+        }
+        let _ = writeln!(glsl, "#line {}", self.body_line);
+
+        for line in self.body.lines() {
+            // our own version directive is already at the top, keep the line to not mess up line numbers
+            if !line.trim_start().starts_with("#version") {
+                let line = line.replace("gl_FragColor", "isf_FragColor").replace("gl_FragCoord", "isf_FragCoord");
+                glsl.push_str(&line);
+            }
+            glsl.push('\n');
+        }
+        glsl
+    }
+}
+
+/// Used as preprocessor of the hot shader, see `HotShader::new_isf`.
+pub fn to_glsl(source: &str) -> anyhow::Result<String> {
+    Ok(IsfShader::parse(source)?.glsl())
+}
+
+const GLSL_PRELUDE: &str = "#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 fragPos;
+layout(location = 1) in vec3 fragNorm;
+
+#include <sp/ubo.glsl>
+
+layout(location = 0) out vec4 isf_FragColor;
+
+#define TIME ubo.time
+#define TIMEDELTA ubo.delta_time
+#define FRAMEINDEX int(ubo.frame_index)
+#define DATE vec4(0.0)
+#define PASSINDEX 0
+#define RENDERSIZE vec2(1024.0)
+#define isf_FragNormCoord (fragPos.xy * 0.5 + 0.5)
+#define vv_FragNormCoord isf_FragNormCoord
+#define isf_FragCoord vec4(isf_FragNormCoord * RENDERSIZE, 0.0, 1.0)
+#define texture2D texture
+#define IMG_SIZE(image) vec2(textureSize(image, 0))
+#define IMG_NORM_PIXEL(image, coord) texture(image, coord)
+#define IMG_PIXEL(image, coord) texture(image, (coord) / IMG_SIZE(image))
+#define IMG_THIS_NORM_PIXEL(image) texture(image, isf_FragNormCoord)
+#define IMG_THIS_PIXEL(image) texture(image, isf_FragNormCoord)
+";
+
+fn as_f32(value: Option<&Value>) -> Option<f32> {
+    match value? {
+        Value::Number(number) => number.as_f64().map(|number| number as f32),
+        Value::Bool(b) => Some(if *b { 1. } else { 0. }),
+        _ => None,
+    }
+}
+
+fn as_vec(value: Option<&Value>) -> Option<Vec<f32>> {
+    value?.as_array()?.iter().map(|value| as_f32(Some(value))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art::ArtOptionType;
+
+    const SHADER: &str = r#"/*{
+    "DESCRIPTION": "test",
+    "INPUTS": [
+        { "NAME": "speed", "TYPE": "float", "DEFAULT": 0.5, "MIN": 0.0, "MAX": 2.0 },
+        { "NAME": "tint", "TYPE": "color", "DEFAULT": [1.0, 0.0, 0.0, 1.0] },
+        { "NAME": "invert", "TYPE": "bool", "DEFAULT": true },
+        { "NAME": "mode", "TYPE": "long", "VALUES": [0, 1, 2], "LABELS": ["a", "b", "c"], "DEFAULT": 1 },
+        { "NAME": "inputImage", "TYPE": "image" }
+    ]
+}*/
+#version 120
+void main() {
+    gl_FragColor = tint * speed;
+}
+"#;
+
+    #[test]
+    fn parse_inputs() {
+        let isf = IsfShader::parse(SHADER).expect("failed to parse");
+        assert_eq!(isf.header.description.as_deref(), Some("test"));
+        assert!(isf.has_image_input());
+
+        let options = isf.options();
+        assert_eq!(options.len(), 4);
+        assert!(matches!(
+            options[0].ty,
+            ArtOptionType::SliderF32 { value, min, max, .. } if (value, min, max) == (0.5, 0., 2.)
+        ));
+        assert!(matches!(options[1].ty, ArtOptionType::Stroke { color, .. } if color == Color32::RED));
+        assert!(matches!(options[2].ty, ArtOptionType::Checkbox { checked: true }));
+        assert!(matches!(options[3].ty, ArtOptionType::SliderI32 { value: 1, min: 0, max: 2 }));
+    }
+
+    #[test]
+    fn generate_glsl() {
+        let glsl = IsfShader::parse(SHADER).expect("failed to parse").glsl();
+        assert!(glsl.contains("#define speed ubo.options[0][0]\n"));
+        assert!(glsl.contains("#define tint vec4(ubo.options[0][1], ubo.options[0][2], ubo.options[0][3], 1.0)\n"));
+        assert!(glsl.contains("#define invert (ubo.options[1][0] > 0.5)\n"));
+        assert!(glsl.contains("#define mode int(ubo.options[1][1])\n"));
+        assert!(glsl.contains("uniform sampler2D inputImage;\n"));
+        assert!(glsl.contains("#line 10\n\n\nvoid main() {\n    isf_FragColor = tint * speed;\n"));
+        assert_eq!(glsl.matches("#version").count(), 1);
+    }
+
+    #[test]
+    fn reject_unsupported() {
+        assert!(IsfShader::parse("void main() {}").is_err());
+        let colors = |count: usize| {
+            let inputs = (0..count).map(|i| format!(r#"{{ "NAME": "c{i}", "TYPE": "color" }}"#));
+            format!(r#"/*{{ "INPUTS": [{}] }}*/"#, inputs.collect::<Vec<_>>().join(", "))
+        };
+        assert!(IsfShader::parse(&colors(MAX_OPTION_SLOTS / 3)).is_ok());
+        assert!(IsfShader::parse(&colors(MAX_OPTION_SLOTS / 3 + 1)).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! Keyboard state that is uploaded as a texture so shaders can react to keys.
+//!
+//! Keys are indexed by their JavaScript key code like on Shadertoy,
+//! see `assets/shaders/includes/keyboard.glsl`.
+
+use image::{Rgba, RgbaImage};
+use winit::keyboard::KeyCode;
+
+const KEY_COUNT: usize = 256;
+
+pub struct KeyboardState {
+    pressed: [bool; KEY_COUNT],
+    just_pressed: [bool; KEY_COUNT],
+    toggled: [bool; KEY_COUNT],
+    /// Whether the state changed since the last call to `take_image`.
+    dirty: bool,
+}
+
+impl Default for KeyboardState {
+    fn default() -> Self {
+        Self {
+            pressed: [false; KEY_COUNT],
+            just_pressed: [false; KEY_COUNT],
+            toggled: [false; KEY_COUNT],
+            dirty: true,
+        }
+    }
+}
+
+impl KeyboardState {
+    /// Records a key press or release, repeated presses should be filtered out before.
+    pub fn key_event(&mut self, code: KeyCode, pressed: bool) {
+        let Some(idx) = js_key_code(code) else { return };
+        let idx = idx as usize;
+        if pressed && !self.pressed[idx] {
+            self.just_pressed[idx] = true;
+            self.toggled[idx] = !self.toggled[idx];
+        }
+        self.pressed[idx] = pressed;
+        self.dirty = true;
+    }
+
+    /// Must be called after each frame so that keys are only just pressed for one frame.
+    pub fn end_frame(&mut self) {
+        if self.just_pressed.contains(&true) {
+            self.just_pressed = [false; KEY_COUNT];
+            self.dirty = true;
+        }
+    }
+
+    /// Returns a 256x1 image with the pressed, just pressed and toggled state
+    /// in the red, green and blue channel if the state changed.
+    pub fn take_image(&mut self) -> Option<RgbaImage> {
+        if !std::mem::take(&mut self.dirty) {
+            return None;
+        }
+        let to_u8 = |state: bool| if state { 255 } else { 0 };
+        Some(RgbaImage::from_fn(KEY_COUNT as u32, 1, |x, _| {
+            let idx = x as usize;
+            Rgba([to_u8(self.pressed[idx]), to_u8(self.just_pressed[idx]), to_u8(self.toggled[idx]), 255])
+        }))
+    }
+}
+
+/// Maps a physical key to the JavaScript key code of the key at that position on a US layout.
+fn js_key_code(code: KeyCode) -> Option<u8> {
+    use KeyCode::*;
+
+    let js_code = match code {
+        KeyA => 65, KeyB => 66, KeyC => 67, KeyD => 68, KeyE => 69, KeyF => 70, KeyG => 71,
+        KeyH => 72, KeyI => 73, KeyJ => 74, KeyK => 75, KeyL => 76, KeyM => 77, KeyN => 78,
+        KeyO => 79, KeyP => 80, KeyQ => 81, KeyR => 82, KeyS => 83, KeyT => 84, KeyU => 85,
+        KeyV => 86, KeyW => 87, KeyX => 88, KeyY => 89, KeyZ => 90,
+        Digit0 => 48, Digit1 => 49, Digit2 => 50, Digit3 => 51, Digit4 => 52,
+        Digit5 => 53, Digit6 => 54, Digit7 => 55, Digit8 => 56, Digit9 => 57,
+        Numpad0 => 96, Numpad1 => 97, Numpad2 => 98, Numpad3 => 99, Numpad4 => 100,
+        Numpad5 => 101, Numpad6 => 102, Numpad7 => 103, Numpad8 => 104, Numpad9 => 105,
+        NumpadMultiply => 106, NumpadAdd => 107, NumpadSubtract => 109, NumpadDecimal => 110,
+        NumpadDivide => 111, NumpadEnter => 13,
+        F1 => 112, F2 => 113, F3 => 114, F4 => 115, F5 => 116, F6 => 117,
+        F7 => 118, F8 => 119, F9 => 120, F10 => 121, F11 => 122, F12 => 123,
+        ArrowLeft => 37, ArrowUp => 38, ArrowRight => 39, ArrowDown => 40,
+        Backspace => 8, Tab => 9, Enter => 13, Escape => 27, Space => 32,
+        ShiftLeft | ShiftRight => 16, ControlLeft | ControlRight => 17, AltLeft | AltRight => 18,
+        PageUp => 33, PageDown => 34, End => 35, Home => 36, Insert => 45, Delete => 46,
+        Semicolon => 186, Equal => 187, Comma => 188, Minus => 189, Period => 190, Slash => 191,
+        Backquote => 192, BracketLeft => 219, Backslash => 220, BracketRight => 221, Quote => 222,
+        _ => return None,
+    };
+    Some(js_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn just_pressed_lasts_one_frame() {
+        let mut keyboard = KeyboardState::default();
+        keyboard.key_event(KeyCode::KeyA, true);
+        let image = keyboard.take_image().expect("state changed");
+        assert_eq!(image.get_pixel(65, 0).0, [255, 255, 255, 255]);
+        assert!(keyboard.take_image().is_none());
+
+        keyboard.end_frame();
+        let image = keyboard.take_image().expect("state changed");
+        assert_eq!(image.get_pixel(65, 0).0, [255, 0, 255, 255]);
+
+        keyboard.key_event(KeyCode::KeyA, false);
+        keyboard.key_event(KeyCode::KeyA, true);
+        keyboard.key_event(KeyCode::KeyA, false);
+        let image = keyboard.take_image().expect("state changed");
+        assert_eq!(image.get_pixel(65, 0).0, [0, 255, 0, 255]);
+    }
+}
@@ -0,0 +1,67 @@
+//! A gallery of shader exhibits rendered with Vulkan.
+//!
+//! The binary runs [`App`], which owns the window, the GUI and the scene.
+//! Set [`App::on_frame`] to move the camera or drive exhibits from your own code each frame.
+//! To host the gallery inside another winit application, use the parts directly:
+//! - load the exhibits with [`get_art_objects`] or build [`ArtObject`]s yourself
+//! - create a [`VkApp`] for your window with the environment model from
+//!   [`model::env_generator::default_env`] or a [`model::env_generator::Layout`] and the exhibits
+//! - each frame move the [`Camera`], set [`VkApp::view_matrix`] to [`Camera::view_matrix`]
+//!   and call [`VkApp::draw`], passing an `egui_winit_vulkano::Gui` created with
+//!   [`VkApp::gui_pass`] if you want to draw an interface on top
+//! - call [`VkApp::recreate_swapchain`] when the window was resized or `draw` returned true
+//! - more windows share the device through [`VkApp::open_window`], either with a GUI of their own
+//!   or showing another view that is passed to `draw`
+//!
+//! To rasterize exhibits without a window or an event loop, e.g. for a catalog or in CI,
+//! call [`render_snapshot`] with a [`SnapshotScene`].
+//!
+//! Shaders are compiled at runtime by [`HotShader`] and reloaded when their files change.
+//! Assets are loaded through [`fs`], which also finds them in a mounted archive.
+
+pub mod animation;
+pub mod app;
+pub mod art;
+pub mod art_objects;
+pub mod attract;
+pub mod avatar;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod audio_input;
+pub mod binding;
+pub mod camera;
+pub mod downloader;
+pub mod export;
+pub mod frame_clock;
+pub mod fs;
+pub mod gallery;
+pub mod gui;
+pub mod isf;
+pub mod keyboard;
+pub mod maze;
+pub mod midi;
+pub mod model;
+pub mod permalink;
+pub mod physics;
+pub mod prelude;
+pub mod preset;
+pub mod quality;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod second_window;
+pub mod settings;
+pub mod stats;
+pub mod sync;
+pub mod text;
+pub mod tour;
+pub mod trigger;
+pub mod uniform_block;
+pub mod vulkan;
+pub mod wallpaper;
+pub mod webcam;
+
+pub use app::{App, FrameCallback};
+pub use art::ArtObject;
+pub use art_objects::get_art_objects;
+pub use camera::Camera;
+pub use vulkan::{render_snapshot, HotShader, SnapshotScene, VkApp};
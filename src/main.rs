@@ -1,21 +1,43 @@
-mod app;
-mod art;
-mod art_objects;
-mod camera;
-mod fs;
-mod gui;
-mod model;
-mod vulkan;
+use shaderpixel_rs::{
+    art_objects, downloader, fs, maze,
+    model::env_generator::{Layout, LAYOUT_PATH},
+    permalink::Permalink,
+    stats, sync, vulkan, wallpaper, App,
+};
 
-use app::App;
+use std::path::Path;
 
 use winit::event_loop::{ControlFlow, EventLoop};
 
+/// Archive with assets that is mounted at startup if it exists.
+const ASSET_ARCHIVE: &str = "assets.pak";
+/// Manifest of assets that are downloaded at startup if missing.
+const DOWNLOAD_MANIFEST: &str = "assets/downloads.txt";
+
 fn main() {
     env_logger::builder()
         .format_timestamp(Some(env_logger::fmt::TimestampPrecision::Millis))
         .init();
 
+    if Path::new(ASSET_ARCHIVE).exists() {
+        if let Err(err) = fs::mount(ASSET_ARCHIVE) {
+            log::error!("failed to mount asset archive {ASSET_ARCHIVE}: {err}");
+            return;
+        }
+    }
+
+    if let Some(path) = std::env::var_os("SHADERPIXEL_EXPORT_ENV") {
+        let result = Layout::load(Path::new(LAYOUT_PATH)).and_then(|layout| {
+            let file = std::fs::File::create(&path)?;
+            Ok(layout.generate().write(std::io::BufWriter::new(file))?)
+        });
+        match result {
+            Ok(()) => log::info!("exported the environment to {}", Path::new(&path).display()),
+            Err(err) => log::error!("failed to export the environment: {err:#}"),
+        }
+        return;
+    }
+
     let art_objects = match art_objects::get_art_objects() {
         Ok(art_objects) => art_objects,
         Err(err) => {
@@ -24,10 +46,60 @@ fn main() {
         }
     };
 
+    let mut app = App::default();
+    app.art_objects = art_objects;
+    if let Ok(spec) = std::env::var("SHADERPIXEL_MAZE") {
+        match maze::Maze::from_spec(&spec).and_then(|maze| maze.generate()) {
+            Ok(maze) => {
+                app.art_objects = maze.art_objects;
+                app.layout = Some(maze.layout);
+                app.scene = "maze".to_owned();
+            }
+            Err(err) => log::error!("maze disabled: {err:#}"),
+        }
+    }
+
+    if let Ok(spec) = std::env::var("SHADERPIXEL_VIEW") {
+        match Permalink::from_spec(&spec) {
+            Ok(permalink) => app.permalink = Some(permalink),
+            Err(err) => log::error!("ignoring the view link: {err:#}"),
+        }
+    }
+
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = App::default();
-    app.art_objects = art_objects;
+    match downloader::Manifest::load(DOWNLOAD_MANIFEST) {
+        Ok(manifest) => app.downloader = Some(downloader::Downloader::start(&manifest)),
+        Err(err) => log::warn!("not downloading assets: {err:#}"),
+    }
+    if std::env::var_os("SHADERPIXEL_DEBUG_PRINTF").is_some() {
+        vulkan::enable_debug_printf();
+    }
+    if let Ok(spec) = std::env::var("SHADERPIXEL_SYNC") {
+        match sync::SyncNode::from_spec(&spec) {
+            Ok(sync) => app.sync = Some(sync),
+            Err(err) => log::error!("synchronization disabled: {err:#}"),
+        }
+    }
+    if let Some(path) = std::env::var_os("SHADERPIXEL_STATS") {
+        log::info!("recording visitor statistics to {}", Path::new(&path).display());
+        app.stats = Some(stats::VisitorStats::new(path));
+    }
+    if let Ok(spec) = std::env::var("SHADERPIXEL_WALLPAPER") {
+        match wallpaper::Wallpaper::from_spec(&spec) {
+            Ok(wallpaper) => app.wallpaper = Some(wallpaper),
+            Err(err) => log::error!("wallpaper mode disabled: {err:#}"),
+        }
+    }
+    #[cfg(feature = "remote")]
+    {
+        use shaderpixel_rs::remote;
+        let addr = std::env::var("SHADERPIXEL_REMOTE_ADDR").unwrap_or_else(|_| remote::DEFAULT_ADDR.to_owned());
+        match remote::RemoteServer::start(&addr) {
+            Ok(remote) => app.remote = Some(remote),
+            Err(err) => log::error!("remote control disabled: {err:#}"),
+        }
+    }
     event_loop.run_app(&mut app).unwrap();
 }
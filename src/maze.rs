@@ -0,0 +1,292 @@
+//! A randomized maze of rooms with shaders exhibited on its walls and pedestals.
+//!
+//! The rooms form a grid, a depth first search connects all of them through doors and the
+//! remaining walls between rooms are closed. Every closed wall face and every room center
+//! is a slot, the shaders are put on randomly chosen slots.
+
+use crate::{
+    art::ArtObject,
+    model::{
+        env_generator::{Layout, Pillar, Wall},
+        obj::NormalizedObj,
+    },
+    settings::Bookmark,
+    vulkan::HotShader,
+};
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use glam::Vec3;
+
+/// Directory the shaders are taken from if the spec does not name one.
+pub const MAZE_DIR: &str = "assets/shaders/maze";
+const ROOM_SIZE: f32 = 6.;
+const WALL_THICKNESS: f32 = 0.2;
+const WALL_HEIGHT: f32 = 3.;
+const DOOR_WIDTH: f32 = 2.;
+const PEDESTAL_WIDTH: f32 = 0.6;
+const PEDESTAL_HEIGHT: f32 = 1.;
+/// Distance of the exhibits to the wall they hang on.
+const WALL_GAP: f32 = 0.01;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Maze {
+    pub seed: u64,
+    /// Directory with the `.frag` files that are exhibited.
+    pub dir: PathBuf,
+}
+
+/// Where an exhibit is placed, `rotate_y` is in degrees like for the `ArtObject` builder.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slot {
+    pub position: Vec3,
+    pub rotate_y: f32,
+    pub scale: f32,
+}
+
+/// The generated architecture and exhibits.
+pub struct MazeGallery {
+    pub layout: Layout,
+    pub art_objects: Vec<ArtObject>,
+}
+
+impl Maze {
+    /// Parses a spec like `<seed>` or `<seed>:<shader directory>`.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        let (seed, dir) = match spec.split_once(':') {
+            Some((seed, dir)) => (seed, PathBuf::from(dir)),
+            None => (spec, PathBuf::from(MAZE_DIR)),
+        };
+        let seed = seed.parse().with_context(|| format!("invalid seed {seed}"))?;
+        Ok(Self { seed, dir })
+    }
+
+    /// Generates a maze big enough for all shaders in the directory.
+    pub fn generate(&self) -> anyhow::Result<MazeGallery> {
+        let mut shaders = std::fs::read_dir(&self.dir)
+            .with_context(|| format!("failed to read {}", self.dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "frag"))
+            .collect::<Vec<_>>();
+        anyhow::ensure!(!shaders.is_empty(), "no shaders in {}", self.dir.display());
+        // the order of read_dir is not specified, the same seed has to give the same maze
+        shaders.sort();
+
+        // every room has at least a pedestal and usually a few closed walls
+        let size = (shaders.len() as f32).sqrt().ceil().max(2.) as usize;
+        let mut rng = SplitMix64(self.seed);
+        let (layout, mut slots) = generate_maze(size, size, &mut rng);
+        shuffle(&mut slots, &mut rng);
+        if shaders.len() > slots.len() {
+            log::warn!("the maze only has room for {} of the {} shaders", slots.len(), shaders.len());
+        }
+
+        let model = Arc::new(NormalizedObj::load("assets/models/square.obj")?);
+        let shader_vert = Arc::new(HotShader::new_vert("assets/shaders/art2d.vert"));
+        let art_objects = shaders.iter().zip(slots).map(|(path, slot)| {
+            ArtObject::builder()
+                .name(path.file_stem().unwrap_or_default().to_string_lossy())
+                .model(model.clone())
+                .vert_shader(shader_vert.clone())
+                .frag_shader(Arc::new(HotShader::new_frag(path)))
+                .scale(Vec3::splat(slot.scale))
+                .rotate_y(slot.rotate_y)
+                .at(slot.position)
+                .groups(["2D", "maze"])
+                .build()
+        }).collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(MazeGallery { layout, art_objects })
+    }
+}
+
+/// Generates `width` by `depth` rooms, the first one is centered at the origin and the others
+/// follow in the +x and -z direction. Returns the layout and the free slots.
+pub fn generate_maze(width: usize, depth: usize, rng: &mut SplitMix64) -> (Layout, Vec<Slot>) {
+    let doors = connect_rooms(width, depth, rng);
+    let center = |x: usize, z: usize| Vec3::new(x as f32 * ROOM_SIZE, 0., -(z as f32) * ROOM_SIZE);
+    let half = ROOM_SIZE / 2.;
+    let mut walls = Vec::new();
+    let mut pillars = Vec::new();
+    let mut slots = Vec::new();
+
+    for z in 0..depth {
+        for x in 0..width {
+            let c = center(x, z);
+            pillars.push(Pillar { position: [c.x, c.z], width: PEDESTAL_WIDTH, height: PEDESTAL_HEIGHT });
+            let position = c + Vec3::new(0., PEDESTAL_HEIGHT + 0.35, 0.);
+            slots.push(Slot { position, rotate_y: 180., scale: 0.3 });
+
+            // the wall on the +x side of the room, on the border for the last room of a row
+            let has_door = x + 1 < width && doors.contains(&((x, z), (x + 1, z)));
+            let wall_x = c.x + half;
+            let (z0, z1) = (c.z - half, c.z + half);
+            if has_door {
+                let gap = DOOR_WIDTH / 2.;
+                walls.push(wall_along_z(wall_x, z0, c.z - gap));
+                walls.push(wall_along_z(wall_x, c.z + gap, z1));
+            } else {
+                walls.push(wall_along_z(wall_x, z0, z1));
+                let position = Vec3::new(wall_x - WALL_THICKNESS / 2. - WALL_GAP, 1.5, c.z);
+                slots.push(Slot { position, rotate_y: 90., scale: 0.5 });
+                if x + 1 < width {
+                    let position = Vec3::new(wall_x + WALL_THICKNESS / 2. + WALL_GAP, 1.5, c.z);
+                    slots.push(Slot { position, rotate_y: -90., scale: 0.5 });
+                }
+            }
+
+            // the wall on the -z side of the room
+            let has_door = z + 1 < depth && doors.contains(&((x, z), (x, z + 1)));
+            let wall_z = c.z - half;
+            let (x0, x1) = (c.x - half, c.x + half);
+            if has_door {
+                let gap = DOOR_WIDTH / 2.;
+                walls.push(wall_along_x(wall_z, x0, c.x - gap));
+                walls.push(wall_along_x(wall_z, c.x + gap, x1));
+            } else {
+                walls.push(wall_along_x(wall_z, x0, x1));
+                let position = Vec3::new(c.x, 1.5, wall_z + WALL_THICKNESS / 2. + WALL_GAP);
+                slots.push(Slot { position, rotate_y: 180., scale: 0.5 });
+                if z + 1 < depth {
+                    let position = Vec3::new(c.x, 1.5, wall_z - WALL_THICKNESS / 2. - WALL_GAP);
+                    slots.push(Slot { position, rotate_y: 0., scale: 0.5 });
+                }
+            }
+
+            // the outer walls on the -x and +z side
+            if x == 0 {
+                walls.push(wall_along_z(c.x - half, z0, z1));
+                let position = Vec3::new(c.x - half + WALL_THICKNESS / 2. + WALL_GAP, 1.5, c.z);
+                slots.push(Slot { position, rotate_y: -90., scale: 0.5 });
+            }
+            if z == 0 {
+                walls.push(wall_along_x(c.z + half, x0, x1));
+                let position = Vec3::new(c.x, 1.5, c.z + half - WALL_THICKNESS / 2. - WALL_GAP);
+                slots.push(Slot { position, rotate_y: 0., scale: 0.5 });
+            }
+        }
+    }
+
+    let far_corner = center(width - 1, depth - 1);
+    let margin = half + 1.;
+    let layout = Layout {
+        floor_start: [-margin, 0., far_corner.z - margin],
+        floor_end: [far_corner.x + margin, 0., margin],
+        walls,
+        pillars,
+        spawn: Some(Bookmark {
+            position: Vec3::new(0., 1.5, ROOM_SIZE / 2. - 1.),
+            yaw: 0.,
+            pitch: 0.,
+        }),
+        ambient: None,
+        clear_colors: None,
+        interaction: None,
+        attract: None,
+        color_lut: None,
+        output: None,
+        projection: None,
+        transition: None,
+        tour: Vec::new(),
+        include_paths: Vec::new(),
+        bindings: BTreeMap::new(),
+        triggers: BTreeMap::new(),
+        groups: BTreeMap::new(),
+        timeline: BTreeMap::new(),
+        uniform_values: BTreeMap::new(),
+    };
+    (layout, slots)
+}
+
+fn wall_along_z(x: f32, z0: f32, z1: f32) -> Wall {
+    let half = WALL_THICKNESS / 2.;
+    Wall { start: [x - half, z0 - half], end: [x + half, z1 + half], height: WALL_HEIGHT }
+}
+
+fn wall_along_x(z: f32, x0: f32, x1: f32) -> Wall {
+    let half = WALL_THICKNESS / 2.;
+    Wall { start: [x0 - half, z - half], end: [x1 + half, z + half], height: WALL_HEIGHT }
+}
+
+type Room = (usize, usize);
+
+/// Depth first search over the rooms in random order, returns the pairs of rooms with a door
+/// between them, the first room of a pair is the one with the lower coordinates.
+fn connect_rooms(width: usize, depth: usize, rng: &mut SplitMix64) -> Vec<(Room, Room)> {
+    let mut visited = vec![false; width * depth];
+    let mut doors = Vec::new();
+    let mut stack = vec![(0, 0)];
+    visited[0] = true;
+    while let Some(&(x, z)) = stack.last() {
+        let mut neighbors = Vec::with_capacity(4);
+        if x > 0 { neighbors.push((x - 1, z)); }
+        if x + 1 < width { neighbors.push((x + 1, z)); }
+        if z > 0 { neighbors.push((x, z - 1)); }
+        if z + 1 < depth { neighbors.push((x, z + 1)); }
+        neighbors.retain(|&(x, z)| !visited[x + z * width]);
+        if neighbors.is_empty() {
+            stack.pop();
+            continue;
+        }
+        let next = neighbors[rng.below(neighbors.len())];
+        visited[next.0 + next.1 * width] = true;
+        doors.push(((x, z).min(next), (x, z).max(next)));
+        stack.push(next);
+    }
+    doors
+}
+
+fn shuffle<T>(items: &mut [T], rng: &mut SplitMix64) {
+    for i in (1..items.len()).rev() {
+        items.swap(i, rng.below(i + 1));
+    }
+}
+
+/// A small deterministic random number generator, the same seed always gives the same maze.
+#[derive(Debug, Clone)]
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec() {
+        assert_eq!(Maze::from_spec("42").unwrap(), Maze { seed: 42, dir: PathBuf::from(MAZE_DIR) });
+        assert_eq!(Maze::from_spec("7:shaders").unwrap(), Maze { seed: 7, dir: PathBuf::from("shaders") });
+        assert!(Maze::from_spec("seed").is_err());
+    }
+
+    #[test]
+    fn all_rooms_are_connected() {
+        let doors = connect_rooms(4, 3, &mut SplitMix64(1));
+        // a spanning tree of the rooms
+        assert_eq!(doors.len(), 4 * 3 - 1);
+        assert_eq!(doors, connect_rooms(4, 3, &mut SplitMix64(1)));
+    }
+
+    #[test]
+    fn every_room_has_a_slot() {
+        let (layout, slots) = generate_maze(3, 3, &mut SplitMix64(5));
+        assert_eq!(layout.pillars.len(), 9);
+        assert!(slots.len() >= 9);
+    }
+}
@@ -0,0 +1,72 @@
+//! Control change messages of the first MIDI input, they can drive options, see `crate::binding`.
+//!
+//! Listening needs the `midi` feature, without it `Midi::connect` always fails.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Status byte of a control change message without the channel.
+const CONTROL_CHANGE: u8 = 0xb0;
+
+#[cfg_attr(not(feature = "midi"), allow(dead_code))]
+pub struct Midi {
+    /// Last value of the controllers by channel and controller number.
+    controllers: Arc<Mutex<HashMap<(u8, u8), f32>>>,
+    // messages are only received while the connection is alive
+    #[cfg(feature = "midi")]
+    _connection: midir::MidiInputConnection<()>,
+}
+
+impl Midi {
+    #[cfg(feature = "midi")]
+    pub fn connect() -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let input = midir::MidiInput::new("shaderpixel")?;
+        let ports = input.ports();
+        let port = ports.first().context("no MIDI input")?;
+        let name = input.port_name(port).unwrap_or_default();
+        let controllers = Arc::new(Mutex::new(HashMap::new()));
+        let controllers_thread = controllers.clone();
+        let connection = input.connect(port, "shaderpixel", move |_, message, _| {
+            let Some((controller, value)) = parse_control_change(message) else { return };
+            if let Ok(mut controllers) = controllers_thread.lock() {
+                controllers.insert(controller, value);
+            }
+        }, ()).map_err(|err| anyhow::anyhow!("failed to connect to {name}: {err}"))?;
+        log::info!("listening to MIDI input {name}");
+        Ok(Self { controllers, _connection: connection })
+    }
+
+    #[cfg(not(feature = "midi"))]
+    pub fn connect() -> anyhow::Result<Self> {
+        anyhow::bail!("compiled without the midi feature")
+    }
+
+    pub fn controllers(&self) -> HashMap<(u8, u8), f32> {
+        self.controllers.lock().map(|controllers| controllers.clone()).unwrap_or_default()
+    }
+}
+
+/// Returns the channel and controller number and the value in [0, 1] of a control change message.
+#[cfg_attr(not(feature = "midi"), allow(dead_code))]
+fn parse_control_change(message: &[u8]) -> Option<((u8, u8), f32)> {
+    let &[status, controller, value] = message else { return None };
+    if status & 0xf0 != CONTROL_CHANGE {
+        return None;
+    }
+    Some(((status & 0x0f, controller), value.min(127) as f32 / 127.))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_change() {
+        assert_eq!(parse_control_change(&[0xb3, 7, 127]), Some(((3, 7), 1.)));
+        // note on
+        assert_eq!(parse_control_change(&[0x90, 60, 100]), None);
+        assert_eq!(parse_control_change(&[0xb0, 1]), None);
+    }
+}
@@ -1,11 +1,184 @@
 use super::obj::{Indices, Obj};
+use crate::animation::Track;
+use crate::binding::Binding;
+use crate::settings::{Attract, Bookmark, Interaction, Projection};
+use crate::tour::TourStep;
+use crate::trigger::{Action, Trigger, Volume};
+use crate::vulkan::{ClearColors, OutputAdjustments, TransitionSettings};
 
+use std::collections::BTreeMap;
 use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
 
+use anyhow::Context;
 use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Layout loaded at startup instead of the default one if it exists.
+pub const LAYOUT_PATH: &str = "assets/layout.json";
+/// Directory of the layouts of the other scenes, named after their file stem.
+pub const SCENE_DIR: &str = "assets/scenes";
+/// Name of the scene with the layout at `LAYOUT_PATH`.
+pub const DEFAULT_SCENE: &str = "default";
+
+/// The architecture of the gallery, a floor with walls and pillars on it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub floor_start: [f32; 3],
+    pub floor_end: [f32; 3],
+    #[serde(default)]
+    pub walls: Vec<Wall>,
+    #[serde(default)]
+    pub pillars: Vec<Pillar>,
+    /// Where the camera starts and is reset to, `crate::art_objects::SPAWN` if not set.
+    #[serde(default)]
+    pub spawn: Option<Bookmark>,
+    /// Sound looped in the background, loaded through `crate::fs`.
+    #[serde(default)]
+    pub ambient: Option<PathBuf>,
+    /// Replaces the clear colors in the options.
+    #[serde(default)]
+    pub clear_colors: Option<ClearColors>,
+    /// Replaces the interaction options.
+    #[serde(default)]
+    pub interaction: Option<Interaction>,
+    /// Replaces the attract mode options, e.g. to enable it for an installation.
+    #[serde(default)]
+    pub attract: Option<Attract>,
+    /// Lookup table the frames are color graded with, see `crate::vulkan::Lut`.
+    #[serde(default)]
+    pub color_lut: Option<PathBuf>,
+    /// Replaces the output adjustments, e.g. for the projector of an installation.
+    #[serde(default)]
+    pub output: Option<OutputAdjustments>,
+    /// Replaces the clip planes and FOV limits, e.g. for a large gallery.
+    #[serde(default)]
+    pub projection: Option<Projection>,
+    /// Replaces the transition shown when the camera jumps.
+    #[serde(default)]
+    pub transition: Option<TransitionSettings>,
+    /// Steps of the guided tour, see `crate::tour`. A tour is generated if there are none.
+    #[serde(default)]
+    pub tour: Vec<TourStep>,
+    /// Directories `#include` searches after the one of the including shader,
+    /// see `crate::vulkan::set_include_paths`.
+    #[serde(default)]
+    pub include_paths: Vec<PathBuf>,
+    /// Bindings of the exhibits by name, see `crate::binding`.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Vec<Binding>>,
+    /// Triggers of the exhibits by name, see `crate::trigger`.
+    #[serde(default)]
+    pub triggers: BTreeMap<String, Vec<Trigger>>,
+    /// Replaces the groups of the exhibits by name, e.g. to hide the exhibits of another scene.
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Animations of the exhibits by name, see `crate::animation`. Written by the timeline window of the GUI.
+    #[serde(default)]
+    pub timeline: BTreeMap<String, Vec<Track>>,
+    /// Initial values of the fields of the uniform blocks by exhibit and field name, see `crate::uniform_block`.
+    #[serde(default)]
+    pub uniform_values: BTreeMap<String, BTreeMap<String, Vec<f32>>>,
+}
+
+/// A box standing on the floor, `start` and `end` are opposite corners on the xz plane.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Wall {
+    pub start: [f32; 2],
+    pub end: [f32; 2],
+    pub height: f32,
+}
+
+/// A square pillar centered at `position` on the xz plane.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pillar {
+    pub position: [f32; 2],
+    pub width: f32,
+    pub height: f32,
+}
+
+impl From<Pillar> for Wall {
+    fn from(pillar: Pillar) -> Self {
+        let [x, z] = pillar.position;
+        let half = pillar.width / 2.;
+        Self { start: [x - half, z - half], end: [x + half, z + half], height: pillar.height }
+    }
+}
+
+impl Layout {
+    /// A missing file is not an error, the default layout is used.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match crate::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("invalid layout {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(default_layout()),
+            Err(err) => Err(err).context(format!("failed to read {}", path.display())),
+        }
+    }
+
+    /// Loads the layout of the scene `name`, see `SCENE_DIR`. Unlike `load` a missing file is an error.
+    pub fn load_scene(name: &str) -> anyhow::Result<Self> {
+        if name == DEFAULT_SCENE {
+            return Self::load(Path::new(LAYOUT_PATH));
+        }
+        let path = scene_path(name)?;
+        let json = crate::fs::read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("invalid layout {}", path.display()))
+    }
+
+    /// Writes the layout to the file of the scene `name` and returns its path.
+    pub fn save_scene(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let path = scene_path(name)?;
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path)
+    }
+
+    pub fn generate(&self) -> Obj {
+        let walls = self.walls.iter().copied()
+            .chain(self.pillars.iter().copied().map(Wall::from))
+            .collect::<Vec<_>>();
+        generate_env(self.floor_start, self.floor_end, &walls)
+    }
+}
+
+/// Names of the scenes in `SCENE_DIR` and the default scene, sorted.
+pub fn scene_names() -> Vec<String> {
+    let mut names = std::fs::read_dir(SCENE_DIR)
+        .map(|entries| entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let stem = path.file_stem()?.to_str()?;
+                (path.extension()? == "json" && is_scene_name(stem)).then(|| stem.to_owned())
+            })
+            .collect::<Vec<_>>())
+        .unwrap_or_default();
+    names.push(DEFAULT_SCENE.to_owned());
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// File of the layout of the scene `name`, `LAYOUT_PATH` for the default scene.
+fn scene_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name == DEFAULT_SCENE {
+        return Ok(PathBuf::from(LAYOUT_PATH));
+    }
+    anyhow::ensure!(is_scene_name(name), "invalid scene name {name}");
+    Ok(Path::new(SCENE_DIR).join(format!("{name}.json")))
+}
+
+/// A file stem without anything that leaves `SCENE_DIR`.
+fn is_scene_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\', ':'])
+}
 
 pub fn default_env() -> Obj {
-    let walls = [
+    default_layout().generate()
+}
+
+pub fn default_layout() -> Layout {
+    let walls = vec![
         // big wall for images
         Wall { start: [6., -14.], end: [6.2, 0.], height: 3. },
 
@@ -21,11 +194,40 @@ pub fn default_env() -> Obj {
         Wall { start: [ 2., -6.], end: [ 3., -5.], height: 1. },
         */
     ];
-    generate_env(
-        [-16.0, 0.0, -16.0],
-        [ 16.2, 0.0,  16.2],
-        &walls,
-    )
+    // stepping into one painting leaves the other one, see `crate::trigger`
+    let portal = |to: &str| vec![Trigger {
+        volume: Volume::Crossing,
+        on_enter: vec![Action::Teleport(to.to_owned())],
+        on_exit: vec![Action::Teleport(to.to_owned())],
+        inside: false,
+        builtin: false,
+    }];
+    let triggers = BTreeMap::from([
+        ("Mandelbrot".to_owned(), portal("Colorful Mozaic")),
+        ("Colorful Mozaic".to_owned(), portal("Mandelbrot")),
+    ]);
+    Layout {
+        floor_start: [-16.0, 0.0, -16.0],
+        floor_end: [ 16.2, 0.0,  16.2],
+        walls,
+        pillars: Vec::new(),
+        spawn: None,
+        ambient: None,
+        clear_colors: None,
+        interaction: None,
+        attract: None,
+        color_lut: None,
+        output: None,
+        projection: None,
+        transition: None,
+        tour: Vec::new(),
+        include_paths: Vec::new(),
+        bindings: BTreeMap::new(),
+        triggers,
+        groups: BTreeMap::new(),
+        timeline: BTreeMap::new(),
+        uniform_values: BTreeMap::new(),
+    }
 }
 
 fn add_surface(
@@ -35,7 +237,7 @@ fn add_surface(
     dir_y: Vec3,
     vertices: &mut Vec<[f32; 3]>,
     normals: &mut Vec<[f32; 3]>,
-    faces: &mut Vec<([Indices; 3], Option<Indices>)>,
+    faces: &mut Vec<Vec<Indices>>,
 ) {
     let vidx = vertices.len() as u32;
     let diag = end - start;
@@ -162,21 +364,39 @@ fn generate_env(
 }
 
 
-fn indices_to_face(indices: [u32; 4], normal: NonZeroU32) -> ([Indices; 3], Option<Indices>) {
+fn indices_to_face(indices: [u32; 4], normal: NonZeroU32) -> Vec<Indices> {
     let normal = Some(normal);
-    let [a, b, c, d] = indices.map(|i| NonZeroU32::new(i + 1).unwrap());
-    (
-        [
-            Indices { vertex: a, texture: None, normal },
-            Indices { vertex: b, texture: None, normal },
-            Indices { vertex: c, texture: None, normal },
-        ],
-        Some(Indices { vertex: d, texture: None, normal }),
-    )
+    indices.into_iter()
+        .map(|i| Indices { vertex: NonZeroU32::new(i + 1).unwrap(), texture: None, normal })
+        .collect()
 }
 
-struct Wall {
-    start: [f32; 2],
-    end: [f32; 2],
-    height: f32,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_layout_is_default() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(LAYOUT_PATH);
+        assert_eq!(Layout::load(&path).unwrap(), default_layout());
+    }
+
+    #[test]
+    fn scene_names_stay_in_the_directory() {
+        assert!(is_scene_name("pillars"));
+        assert!(!is_scene_name("../layout"));
+        assert!(!is_scene_name(".."));
+        assert!(!is_scene_name("C:layout"));
+        assert!(Layout::load_scene("../layout").is_err());
+    }
+
+    #[test]
+    fn shipped_scenes_are_valid() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCENE_DIR);
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let json = std::fs::read_to_string(&path).unwrap();
+            serde_json::from_str::<Layout>(&json).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+        }
+    }
 }
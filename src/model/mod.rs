@@ -1,2 +1,4 @@
 pub mod obj;
 pub mod env_generator;
+pub mod primitive;
+pub mod watch;
@@ -1,16 +1,22 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
 use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
 use std::str;
 
+use glam::{IVec3, Vec3};
+
+use super::primitive::{Primitive, PRIMITIVE_PREFIX};
+
 #[derive(Debug, Default, Clone)]
 pub struct Obj {
     pub vertices: Vec<[f32; 3]>,
     pub tex_coords: Vec<[f32; 2]>,
     pub normals: Vec<[f32; 3]>,
-    pub faces: Vec<([Indices; 3], Option<Indices>)>,
+    /// Polygons with at least three corners, they are triangulated by `normalize`.
+    pub faces: Vec<Vec<Indices>>,
 }
 
 #[allow(unused)]
@@ -35,14 +41,23 @@ impl Obj {
             .filter(|part| !part.is_empty());
         let Some(iden) = parts.next() else { return Ok(()) };
         match iden {
-            b"f" => self.faces.push((
-                [
-                    Self::parse_part::<_, 3>(0, parts.next())?,
-                    Self::parse_part::<_, 3>(1, parts.next())?,
-                    Self::parse_part::<_, 3>(2, parts.next())?,
-                ],
-                parts.next().map(|part| Self::parse_part::<_, 3>(3, Some(part))).transpose()?,
-            )),
+            b"f" => {
+                let counts = [self.vertices.len(), self.tex_coords.len(), self.normals.len()];
+                let face = parts.by_ref()
+                    .take_while(|part| part[0] != b'#')
+                    .map(|part| {
+                        str::from_utf8(part)
+                            .map_err(|_| ObjError::InvalidNum(String::from_utf8_lossy(part).into_owned()))
+                            .and_then(|part| Indices::parse(part, counts))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if face.len() < 3 {
+                    return Err(ObjError::NotEnoughNums(face.len() as u32, 3));
+                }
+                // the rest of the line was a comment
+                self.faces.push(face);
+                return Ok(());
+            }
             b"v" => self.vertices.push([
                 Self::parse_part::<_, 3>(0, parts.next())?,
                 Self::parse_part::<_, 3>(1, parts.next())?,
@@ -71,6 +86,76 @@ impl Obj {
         Ok(())
     }
 
+    /// Writes the vertices, texture coordinates, normals and faces in the OBJ format.
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        for [x, y, z] in self.vertices.iter() {
+            writeln!(writer, "v {x} {y} {z}")?;
+        }
+        for [u, v] in self.tex_coords.iter() {
+            writeln!(writer, "vt {u} {v}")?;
+        }
+        for [x, y, z] in self.normals.iter() {
+            writeln!(writer, "vn {x} {y} {z}")?;
+        }
+        for face in self.faces.iter() {
+            write!(writer, "f")?;
+            for indices in face {
+                write!(writer, " {indices}")?;
+            }
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// Like `normalize`, but positions closer than `tolerance` are merged first, see `weld`.
+    pub fn normalize_welded(&self, tolerance: f32) -> Result<NormalizedObj, ObjError> {
+        let mut obj = self.clone();
+        obj.weld(tolerance)?;
+        obj.normalize()
+    }
+
+    /// Merges positions closer than `tolerance` and drops the faces that collapse, e.g. to close
+    /// the seams of scanned meshes. Corners only share a vertex after `normalize` if their texture
+    /// coordinates and normals also match. Returns the number of removed positions.
+    pub fn weld(&mut self, tolerance: f32) -> Result<usize, ObjError> {
+        let cell_size = tolerance.max(f32::EPSILON);
+        let cell = |pos: Vec3| (pos / cell_size).floor().as_ivec3();
+        let mut grid = HashMap::<IVec3, Vec<u32>>::new();
+        let mut welded = Vec::<[f32; 3]>::new();
+        let remap = self.vertices.iter().map(|&pos| {
+            let pos = Vec3::from(pos);
+            let center = cell(pos);
+            let neighbors = (0..27).map(|i| center + IVec3::new(i % 3, i / 3 % 3, i / 9) - IVec3::ONE);
+            let existing = neighbors
+                .filter_map(|cell| grid.get(&cell))
+                .flatten()
+                .copied()
+                .find(|&idx| Vec3::from(welded[idx as usize]).distance(pos) <= tolerance);
+            existing.unwrap_or_else(|| {
+                welded.push(pos.to_array());
+                let idx = welded.len() as u32 - 1;
+                grid.entry(center).or_default().push(idx);
+                idx
+            })
+        }).collect::<Vec<_>>();
+
+        for face in self.faces.iter_mut() {
+            for indices in face.iter_mut() {
+                let idx = remap.get(indices.vertex.get() as usize - 1)
+                    .ok_or(ObjError::InvalidVertexIndex(indices.vertex.into()))?;
+                indices.vertex = NonZeroU32::new(idx + 1).unwrap();
+            }
+            face.dedup_by_key(|indices| indices.vertex);
+            if face.len() > 1 && face.first().map(|i| i.vertex) == face.last().map(|i| i.vertex) {
+                face.pop();
+            }
+        }
+        self.faces.retain(|face| face.len() >= 3);
+        let removed = self.vertices.len() - welded.len();
+        self.vertices = welded;
+        Ok(removed)
+    }
+
     pub fn normalize(&self) -> Result<NormalizedObj, ObjError> {
         let mut map = HashMap::<Indices, u32>::new();
         let mut nobj = NormalizedObj::default();
@@ -104,17 +189,15 @@ impl Obj {
                 Ok(vert_idx)
             }
 
-            let indices: Vec<_> = if let Some(v4) = face.1 {
-                let v = face.0;
-                [v[0], v[1], v[2], v[2], v4, v[0]]
-                    .map(|x| map_indices(x, self, &mut nobj, &mut map))
-                    .into_iter().collect::<Result<_, _>>()?
-            } else {
-                face.0
-                    .map(|x| map_indices(x, self, &mut nobj, &mut map))
-                    .into_iter().collect::<Result<_, _>>()?
-            };
-            nobj.indices.extend(indices);
+            let corners = face.iter()
+                .map(|&x| map_indices(x, self, &mut nobj, &mut map))
+                .collect::<Result<Vec<_>, _>>()?;
+            let positions = corners.iter()
+                .map(|&idx| Vec3::from(nobj.vertices[idx as usize].pos_coords))
+                .collect::<Vec<_>>();
+            for triangle in triangulate(&positions) {
+                nobj.indices.extend(triangle.map(|i| corners[i]));
+            }
         }
         Ok(nobj)
     }
@@ -133,12 +216,61 @@ impl Obj {
     }
 }
 
+/// Splits a polygon into triangles by ear clipping and returns the indices of their corners in
+/// `positions`. Convex polygons become a fan around the first corner. Falls back to that fan if
+/// the polygon is degenerate or intersects itself.
+fn triangulate(positions: &[Vec3]) -> Vec<[usize; 3]> {
+    let count = positions.len();
+    let fan = || (1..count - 1).map(|i| [0, i, i + 1]).collect();
+    if count == 3 {
+        return fan();
+    }
+    // Newell's method, also works if the polygon is not quite planar
+    let normal = (0..count).fold(Vec3::ZERO, |normal, i| {
+        let (a, b) = (positions[i], positions[(i + 1) % count]);
+        normal + Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+    });
+    if normal.length_squared() <= f32::EPSILON {
+        return fan();
+    }
+    let inside = |p: Vec3, [a, b, c]: [Vec3; 3]| {
+        (b - a).cross(p - a).dot(normal) >= 0.
+            && (c - b).cross(p - b).dot(normal) >= 0.
+            && (a - c).cross(p - c).dot(normal) >= 0.
+    };
+
+    let mut remaining = (0..count).collect::<Vec<_>>();
+    let mut triangles = Vec::with_capacity(count - 2);
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let corners = |i: usize| [remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]];
+        // starting at the second corner keeps the fan of convex polygons
+        let ear = (1..len).chain(0..1).find(|&i| {
+            let triangle = corners(i);
+            let [a, b, c] = triangle.map(|idx| positions[idx]);
+            (b - a).cross(c - b).dot(normal) > 0.
+                && remaining.iter()
+                    .filter(|&&idx| !triangle.contains(&idx))
+                    .all(|&idx| !inside(positions[idx], [a, b, c]))
+        });
+        let Some(ear) = ear else { return fan() };
+        triangles.push(corners(ear));
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct NormalizedObj {
     pub indices: Vec<u32>,
     pub vertices: Vec<Vertex>,
     pub has_tex_coords: bool,
     pub has_normals: bool,
+    /// File the model was loaded from with `load`, it is reloaded when the file changes.
+    pub path: Option<PathBuf>,
+    /// Tolerance the positions were welded with by `load_welded`, applied again on reload.
+    pub weld: Option<f32>,
 }
 
 impl NormalizedObj {
@@ -146,6 +278,52 @@ impl NormalizedObj {
     pub fn from_reader(reader: impl BufRead) -> Result<Self, ObjError> {
         Obj::from_reader(reader).map_err(|(err, _)| err)?.normalize()
     }
+
+    /// Loads the model from a loose file, an archive or the embedded assets, see `crate::fs`.
+    /// Paths like `primitive:sphere` generate a mesh instead, see `super::primitive`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ObjError> {
+        Self::load_welded(path, None)
+    }
+
+    /// Like `load`, but positions closer than `tolerance` are merged if it is set, see `Obj::weld`.
+    /// Primitives have no seams and are never welded.
+    pub fn load_welded(path: impl AsRef<Path>, tolerance: Option<f32>) -> Result<Self, ObjError> {
+        if let Some(name) = path.as_ref().to_str().and_then(|path| path.strip_prefix(PRIMITIVE_PREFIX)) {
+            let primitive = Primitive::from_name(name).ok_or_else(|| ObjError::UnknownPrimitive(name.to_owned()))?;
+            return Ok(primitive.generate());
+        }
+        let obj = Obj::from_reader(crate::fs::load(&path)?).map_err(|(err, _)| err)?;
+        let mut nobj = match tolerance {
+            Some(tolerance) => obj.normalize_welded(tolerance)?,
+            None => obj.normalize()?,
+        };
+        nobj.path = Some(path.as_ref().to_owned());
+        nobj.weld = tolerance;
+        Ok(nobj)
+    }
+
+    /// Minimum and maximum corner of the vertices, `None` if there are none.
+    pub fn bounds(&self) -> Option<[Vec3; 2]> {
+        let positions = self.vertices.iter().map(|vertex| Vec3::from(vertex.pos_coords));
+        positions.fold(None, |bounds, pos| match bounds {
+            Some([min, max]) => Some([pos.min(min), pos.max(max)]),
+            None => Some([pos, pos]),
+        })
+    }
+
+    /// Returns the model centered at the origin and uniformly scaled so its largest side fits
+    /// into the container from -1 to 1 that the exhibit shaders draw in.
+    pub fn fitted(&self) -> Self {
+        let Some([min, max]) = self.bounds() else { return self.clone() };
+        let center = (min + max) / 2.;
+        let half_size = ((max - min) / 2.).max_element();
+        let scale = if half_size > 0. { half_size.recip() } else { 1. };
+        let vertices = self.vertices.iter().map(|vertex| Vertex {
+            pos_coords: ((Vec3::from(vertex.pos_coords) - center) * scale).to_array(),
+            ..*vertex
+        });
+        Self { vertices: vertices.collect(), ..self.clone() }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
@@ -162,22 +340,21 @@ pub struct Indices {
     pub normal: Option<NonZeroU32>,
 }
 
-impl str::FromStr for Indices {
-    type Err = ObjError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Indices {
+    /// Negative indices count back from the end of the vertex, texture coordinate and normal lists,
+    /// `counts` are their lengths at the face.
+    pub fn parse(s: &str, counts: [usize; 3]) -> Result<Self, ObjError> {
         let mut parts = s.split('/');
         let Some(part) = parts.next() else {
             return Err(ObjError::NotEnoughNums(0, 1));
         };
-        let vertex = part.parse().map_err(|_| ObjError::InvalidNum(part.to_owned()))?;
+        let vertex = parse_index(part, counts[0])?;
         let texture = match parts.next() {
-            Some(part) if !part.is_empty() =>
-                Some(part.parse().map_err(|_| ObjError::InvalidNum(part.to_owned()))?),
+            Some(part) if !part.is_empty() => Some(parse_index(part, counts[1])?),
             _ => None,
         };
         let normal = if let Some(part) = parts.next() {
-            Some(part.parse().map_err(|_| ObjError::InvalidNum(part.to_owned()))?)
+            Some(parse_index(part, counts[2])?)
         } else {
             None
         };
@@ -186,9 +363,38 @@ impl str::FromStr for Indices {
     }
 }
 
+/// Only absolute indices, there are no lists that relative ones could refer to.
+impl str::FromStr for Indices {
+    type Err = ObjError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, [0; 3])
+    }
+}
+
+/// Resolves a negative index relative to the end of a list with `count` elements.
+/// Positive indices are checked against the lists by `Obj::normalize`.
+fn parse_index(part: &str, count: usize) -> Result<NonZeroU32, ObjError> {
+    let idx: i64 = part.parse().map_err(|_| ObjError::InvalidNum(part.to_owned()))?;
+    let resolved = if idx < 0 { count as i64 + 1 + idx } else { idx };
+    u32::try_from(resolved).ok().and_then(NonZeroU32::new).ok_or(ObjError::InvalidIndex(idx))
+}
+
+impl fmt::Display for Indices {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.texture, self.normal) {
+            (None, None) => write!(f, "{}", self.vertex),
+            (Some(texture), None) => write!(f, "{}/{texture}", self.vertex),
+            (None, Some(normal)) => write!(f, "{}//{normal}", self.vertex),
+            (Some(texture), Some(normal)) => write!(f, "{}/{texture}/{normal}", self.vertex),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ObjError {
    InvalidIden(String),
+   InvalidIndex(i64),
    InvalidNum(String),
    InvalidTextureIndex(u32),
    InvalidNormalIndex(u32),
@@ -196,12 +402,14 @@ pub enum ObjError {
    Io(io::Error),
    NotEnoughNums(u32, u32),
    TooManyNums,
+   UnknownPrimitive(String),
 }
 
 impl fmt::Display for ObjError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::InvalidIden(iden) => write!(f, "Invalid identifier at line start: {iden}"),
+            Self::InvalidIndex(idx) => write!(f, "Invalid index: {idx}"),
             Self::InvalidNum(num) => write!(f, "Invalid number: {num}"),
             Self::InvalidTextureIndex(idx) => write!(f, "Invalid texture index: {idx}"),
             Self::InvalidNormalIndex(idx) => write!(f, "Invalid normal index: {idx}"),
@@ -210,6 +418,7 @@ impl fmt::Display for ObjError {
             Self::NotEnoughNums(found, expt) =>
                 write!(f, "Not enough numbers at line: found {found} expected at least {expt}"),
             Self::TooManyNums => write!(f, "Too many numbers at line"),
+            Self::UnknownPrimitive(name) => write!(f, "Unknown primitive: {name}"),
         }
     }
 }
@@ -251,6 +460,17 @@ mod tests {
         assert_eq!(obj.vertices, [[1., 2.2, 3.14159], [1., 2., 3.]]);
     }
 
+    #[test]
+    fn write_roundtrip() {
+        let obj = crate::model::env_generator::default_env();
+        let mut buffer = Vec::new();
+        obj.write(&mut buffer).unwrap();
+        let parsed = Obj::from_reader(Cursor::new(buffer)).expect("failed to parse");
+        assert_eq!(parsed.vertices, obj.vertices);
+        assert_eq!(parsed.normals, obj.normals);
+        assert_eq!(parsed.faces, obj.faces);
+    }
+
     #[test]
     fn parse_obj_file_42() {
         let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join("models");
@@ -321,4 +541,86 @@ f 2/1 1/2 3/4
         ]);
         assert_eq!(nobj.indices, [0, 1, 2, 3, 4, 5]);
     }
+
+    #[test]
+    fn parse_relative_indices() {
+        let file = r#"
+v 1 0 0
+v 2 0 0
+vt 0.1 0.2
+v 3 0 0
+vt 0.3 0.4
+f -3/-2 -2/-1 -1/-1
+"#;
+        let obj = Obj::from_reader(Cursor::new(file.as_bytes())).expect("failed to parse");
+        assert_eq!(obj.faces[0], ["1/1", "2/2", "3/2"].map(|s| s.parse::<Indices>().unwrap()));
+
+        for face in ["f -4 -2 -1", "f 0 1 2", "f 1/-3 2 3"] {
+            let file = format!("v 1 0 0\nv 2 0 0\nv 3 0 0\nvt 0 0\nvt 1 1\n{face}");
+            let err = Obj::from_reader(Cursor::new(file.as_bytes())).expect_err(face);
+            assert!(matches!(err, (ObjError::InvalidIndex(_), 6)), "{face}: {err:?}");
+        }
+    }
+
+    #[test]
+    fn weld_close_positions() {
+        let file = r#"
+v 0 0 0
+v 1 0 0
+v 0 1 0
+v 1.00001 0 0
+v 1 1 0
+v 0.000001 1 0
+v 0.5 0.000001 0
+f 1 2 3
+f 4 5 6
+f 1 7 4
+"#;
+        let mut obj = Obj::from_reader(Cursor::new(file.as_bytes())).expect("failed to parse");
+        assert_eq!(obj.weld(1e-4).unwrap(), 2);
+        assert_eq!(obj.vertices.len(), 5);
+        // the last face is a sliver that does not collapse, only faces with shared corners do
+        assert_eq!(obj.faces.len(), 3);
+        let nobj = obj.normalize().expect("failed to normalize");
+        assert_eq!(nobj.vertices.len(), 5);
+        assert_eq!(&nobj.indices[..6], [0, 1, 2, 1, 3, 2]);
+
+        let mut obj = Obj::from_reader(Cursor::new("v 0 0 0\nv 0 0 0.001\nv 1 0 0\nf 1 2 3".as_bytes())).unwrap();
+        obj.weld(0.01).unwrap();
+        assert!(obj.faces.is_empty());
+    }
+
+    #[test]
+    fn fit_into_container() {
+        let file = "v 1 2 3\nv 5 3 4\nv 3 4 3\nf 1 2 3";
+        let nobj = NormalizedObj::from_reader(Cursor::new(file.as_bytes())).expect("failed to parse");
+        let [min, max] = nobj.fitted().bounds().unwrap();
+        assert_eq!(min, Vec3::new(-1., -0.5, -0.25));
+        assert_eq!(max, Vec3::new(1., 0.5, 0.25));
+    }
+
+    #[test]
+    fn parse_normalize_concave() {
+        let file = r#"
+v 0 0 0
+v 3 0 0
+v 3 3 0
+v 2 1 0
+v 0 3 0
+f 1 2 3 4 5 # a fan around the first corner would cover the notch
+"#;
+        let obj = Obj::from_reader(Cursor::new(file.as_bytes())).expect("failed to parse");
+        assert_eq!(obj.faces[0].len(), 5);
+
+        let nobj = obj.normalize().expect("failed to normalize");
+        assert_eq!(nobj.indices.len(), 3 * 3);
+        let mut area = 0.;
+        for triangle in nobj.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(nobj.vertices[triangle[i] as usize].pos_coords));
+            let cross = (b - a).cross(c - a);
+            assert!(cross.z > 0., "{triangle:?} is flipped");
+            area += cross.z / 2.;
+        }
+        assert!((area - 6.).abs() < 1e-5, "{area}");
+    }
 }
@@ -0,0 +1,243 @@
+//! Procedural container meshes, a model path like `primitive:sphere` loads one of them instead of
+//! an OBJ file, see `NormalizedObj::load`. The `_inside` variants like `primitive:torus_inside`
+//! face inwards like `cube_inside.obj`. All of them fit into the container from -1 to 1.
+
+use super::obj::{NormalizedObj, Vertex};
+
+use std::collections::HashMap;
+use std::f32::consts::{PI, TAU};
+
+use glam::{Vec2, Vec3};
+
+pub const PRIMITIVE_PREFIX: &str = "primitive:";
+const SEGMENTS: u32 = 32;
+const RINGS: u32 = 16;
+/// Radius of the center line of the tube and radius of the tube.
+const TORUS_RADII: [f32; 2] = [0.7, 0.3];
+const ICOSPHERE_SUBDIVISIONS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Sphere,
+    /// Along the y axis.
+    Cylinder,
+    /// Around the y axis.
+    Torus,
+    /// A sphere of evenly sized triangles, without the pinched poles of `Sphere`.
+    Icosphere,
+}
+
+impl Shape {
+    pub const ALL: [Self; 4] = [Self::Sphere, Self::Cylinder, Self::Torus, Self::Icosphere];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sphere => "sphere",
+            Self::Cylinder => "cylinder",
+            Self::Torus => "torus",
+            Self::Icosphere => "icosphere",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Primitive {
+    pub shape: Shape,
+    /// The front faces and normals point inwards.
+    pub inside: bool,
+}
+
+impl Primitive {
+    /// Parses the part of the path after `PRIMITIVE_PREFIX`, e.g. `torus` or `sphere_inside`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let (name, inside) = match name.strip_suffix("_inside") {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let shape = Shape::ALL.into_iter().find(|shape| shape.name() == name)?;
+        Some(Self { shape, inside })
+    }
+
+    pub fn generate(self) -> NormalizedObj {
+        let mut mesh = Mesh::default();
+        match self.shape {
+            Shape::Sphere => mesh.push_grid(SEGMENTS, RINGS, |u, v| {
+                let (sin_yaw, cos_yaw) = (u * TAU).sin_cos();
+                let (sin_pitch, cos_pitch) = (v * PI).sin_cos();
+                let pos = Vec3::new(sin_pitch * cos_yaw, cos_pitch, sin_pitch * sin_yaw);
+                (pos, pos)
+            }),
+            Shape::Cylinder => {
+                mesh.push_grid(SEGMENTS, 1, |u, v| {
+                    let (sin, cos) = (u * TAU).sin_cos();
+                    (Vec3::new(cos, 1. - 2. * v, sin), Vec3::new(cos, 0., sin))
+                });
+                mesh.push_disk(1., Vec3::Y);
+                mesh.push_disk(-1., Vec3::NEG_Y);
+            }
+            Shape::Torus => mesh.push_grid(SEGMENTS, RINGS, |u, v| {
+                let [center_radius, tube_radius] = TORUS_RADII;
+                let (sin_around, cos_around) = (u * TAU).sin_cos();
+                let (sin_tube, cos_tube) = (v * TAU).sin_cos();
+                let normal = Vec3::new(cos_tube * cos_around, sin_tube, cos_tube * sin_around);
+                let center = Vec3::new(cos_around, 0., sin_around) * center_radius;
+                (center + normal * tube_radius, normal)
+            }),
+            Shape::Icosphere => mesh.push_icosphere(ICOSPHERE_SUBDIVISIONS),
+        }
+        mesh.into_obj(self.inside)
+    }
+}
+
+#[derive(Default)]
+struct Mesh {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Mesh {
+    fn push_vertex(&mut self, pos: Vec3, normal: Vec3, tex_coords: Vec2) -> u32 {
+        self.vertices.push(Vertex {
+            pos_coords: pos.to_array(),
+            tex_coords: tex_coords.to_array(),
+            normal: normal.to_array(),
+        });
+        self.vertices.len() as u32 - 1
+    }
+
+    /// Adds a triangle wound so that its front faces where the normals of its corners point.
+    fn push_triangle(&mut self, mut corners: [u32; 3]) {
+        let [a, b, c] = corners.map(|idx| self.vertices[idx as usize]);
+        let [pos_a, pos_b, pos_c] = [a, b, c].map(|vertex| Vec3::from(vertex.pos_coords));
+        let face_normal = (pos_b - pos_a).cross(pos_c - pos_a);
+        let normal = Vec3::from(a.normal) + Vec3::from(b.normal) + Vec3::from(c.normal);
+        if face_normal.dot(normal) < 0. {
+            corners.swap(1, 2);
+        }
+        self.indices.extend(corners);
+    }
+
+    /// Adds a surface over `u` and `v` from 0 to 1, which are also its texture coordinates.
+    /// `point` returns the position and normal at `u` and `v`.
+    fn push_grid(&mut self, columns: u32, rows: u32, point: impl Fn(f32, f32) -> (Vec3, Vec3)) {
+        let start = self.vertices.len() as u32;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let uv = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+                let (pos, normal) = point(uv.x, uv.y);
+                self.push_vertex(pos, normal, uv);
+            }
+        }
+        for row in 0..rows {
+            for column in 0..columns {
+                let a = start + row * (columns + 1) + column;
+                let c = a + columns + 1;
+                self.push_triangle([a, c, a + 1]);
+                self.push_triangle([a + 1, c, c + 1]);
+            }
+        }
+    }
+
+    /// A disk with radius 1 at the height `y`, the caps of the cylinder.
+    fn push_disk(&mut self, y: f32, normal: Vec3) {
+        let center = self.push_vertex(Vec3::new(0., y, 0.), normal, Vec2::splat(0.5));
+        let ring = (0..SEGMENTS).map(|i| {
+            let (sin, cos) = (i as f32 / SEGMENTS as f32 * TAU).sin_cos();
+            self.push_vertex(Vec3::new(cos, y, sin), normal, Vec2::new(cos, sin) * 0.5 + 0.5)
+        }).collect::<Vec<_>>();
+        for (i, &corner) in ring.iter().enumerate() {
+            self.push_triangle([center, corner, ring[(i + 1) % ring.len()]]);
+        }
+    }
+
+    /// Subdivides the triangles of an icosahedron and moves the new corners onto the sphere.
+    fn push_icosphere(&mut self, subdivisions: u32) {
+        let t = (1. + 5_f32.sqrt()) / 2.;
+        let mut positions = [
+            [-1., t, 0.], [1., t, 0.], [-1., -t, 0.], [1., -t, 0.],
+            [0., -1., t], [0., 1., t], [0., -1., -t], [0., 1., -t],
+            [t, 0., -1.], [t, 0., 1.], [-t, 0., -1.], [-t, 0., 1.],
+        ].map(|pos| Vec3::from_array(pos).normalize()).to_vec();
+        let mut faces = vec![
+            [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+            [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+            [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+            [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+        ];
+        for _ in 0..subdivisions {
+            let mut midpoints = HashMap::new();
+            let mut midpoint = |a: usize, b: usize| *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(((positions[a] + positions[b]) / 2.).normalize());
+                positions.len() - 1
+            });
+            faces = faces.into_iter().flat_map(|[a, b, c]| {
+                let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+                [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+            }).collect();
+        }
+
+        let start = self.vertices.len() as u32;
+        for pos in positions {
+            let tex_coords = Vec2::new(0.5 + pos.z.atan2(pos.x) / TAU, pos.y.clamp(-1., 1.).acos() / PI);
+            self.push_vertex(pos, pos, tex_coords);
+        }
+        for face in faces {
+            self.push_triangle(face.map(|idx| start + idx as u32));
+        }
+    }
+
+    fn into_obj(mut self, inside: bool) -> NormalizedObj {
+        if inside {
+            for vertex in self.vertices.iter_mut() {
+                vertex.normal = (-Vec3::from(vertex.normal)).to_array();
+            }
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        NormalizedObj {
+            indices: self.indices,
+            vertices: self.vertices,
+            has_tex_coords: true,
+            has_normals: true,
+            path: None,
+            weld: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_names() {
+        let torus = Primitive::from_name("torus_inside").unwrap();
+        assert_eq!(torus, Primitive { shape: Shape::Torus, inside: true });
+        assert!(!Primitive::from_name("icosphere").unwrap().inside);
+        assert_eq!(Primitive::from_name("cube"), None);
+    }
+
+    #[test]
+    fn primitives_face_outwards() {
+        for shape in Shape::ALL {
+            for inside in [false, true] {
+                let obj = Primitive { shape, inside }.generate();
+                let [min, max] = obj.bounds().unwrap();
+                assert!(min.cmpge(Vec3::splat(-1. - 1e-5)).all() && max.cmple(Vec3::splat(1. + 1e-5)).all());
+                for triangle in obj.indices.chunks_exact(3) {
+                    let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(obj.vertices[triangle[i] as usize].pos_coords));
+                    let face_normal = (b - a).cross(c - a);
+                    // the center of the tube of the torus, otherwise the origin is inside
+                    let center = match shape {
+                        Shape::Torus => (a * Vec3::new(1., 0., 1.)).normalize() * TORUS_RADII[0],
+                        _ => Vec3::ZERO,
+                    };
+                    let outwards = face_normal.dot(a + b + c - 3. * center);
+                    let expected = if inside { -1. } else { 1. };
+                    assert!(outwards * expected >= -1e-6, "{shape:?} inside: {inside} {triangle:?}");
+                }
+            }
+        }
+    }
+}
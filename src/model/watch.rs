@@ -0,0 +1,92 @@
+//! Reloads the models of exhibits when their OBJ file changes, like shaders are reloaded.
+
+use crate::art::ArtObject;
+use super::obj::NormalizedObj;
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use notify_debouncer_full::{
+    new_debouncer,
+    notify::{self, RecommendedWatcher},
+    DebounceEventResult, Debouncer, RecommendedCache,
+};
+
+const DEBOUNCE_TIME: Duration = Duration::from_millis(500);
+
+pub struct ModelWatcher {
+    /// Paths of the models by their canonical path, which is what the events contain.
+    paths: HashMap<PathBuf, PathBuf>,
+    rx: mpsc::Receiver<DebounceEventResult>,
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+impl ModelWatcher {
+    /// Watches the files the models of `art_objects` were loaded from.
+    /// Only loose files can be watched, models from archives or embedded ones are never reloaded.
+    pub fn new(art_objects: &[ArtObject]) -> anyhow::Result<Self> {
+        let paths = art_objects.iter()
+            .filter_map(|art| art.model.path.as_ref())
+            .filter_map(|path| Some((std::fs::canonicalize(path).ok()?, path.clone())))
+            .collect::<HashMap<_, _>>();
+        let (tx, rx) = mpsc::channel();
+        let mut debouncer = new_debouncer(DEBOUNCE_TIME, None, tx)?;
+        let dirs_to_watch = paths.keys().filter_map(|path| path.parent()).collect::<HashSet<_>>();
+        for dir in dirs_to_watch {
+            debouncer.watch(dir, notify::RecursiveMode::NonRecursive)?;
+            log::debug!("watching models in {}", dir.display());
+        }
+        Ok(Self { paths, rx, _debouncer: debouncer })
+    }
+
+    /// Returns the paths of the models that changed since the last call.
+    pub fn changed(&self) -> HashSet<&Path> {
+        use notify::EventKind::*;
+        use notify::event::{AccessKind::*, AccessMode::*, ModifyKind::*};
+
+        let mut changed = HashSet::new();
+        for result in self.rx.try_iter() {
+            match result {
+                Ok(events) => {
+                    let events = events.into_iter()
+                        .filter(|event| matches!(event.kind, Access(Close(Write)) | Modify(Data(_))));
+                    for event in events {
+                        changed.extend(event.paths.iter().filter_map(|path| self.paths.get(path)));
+                    }
+                }
+                Err(errors) => log::warn!("watch error: {errors:?}"),
+            }
+        }
+        changed.into_iter().map(PathBuf::as_path).collect()
+    }
+}
+
+/// Replaces the models loaded from `changed` with their new version.
+/// Returns the indices of the exhibits whose model was replaced.
+pub fn reload_models<'a>(art_objects: &mut [ArtObject], changed: impl IntoIterator<Item = &'a Path>) -> Vec<usize> {
+    let mut reloaded = Vec::new();
+    for path in changed {
+        log::info!("model changed {}", path.display());
+        // loaded once per weld tolerance of the exhibits using the file, `None` if loading failed
+        let mut models = HashMap::new();
+        for (idx, art) in art_objects.iter_mut().enumerate() {
+            if art.model.path.as_deref() != Some(path) {
+                continue;
+            }
+            let weld = art.model.weld;
+            let model = models.entry(weld.map(f32::to_bits)).or_insert_with(|| {
+                NormalizedObj::load_welded(path, weld)
+                    .inspect_err(|err| log::error!("failed to reload model {}: {err}", path.display()))
+                    .ok()
+                    .map(Arc::new)
+            });
+            if let Some(model) = model {
+                art.set_model(model.clone());
+                reloaded.push(idx);
+            }
+        }
+    }
+    reloaded
+}
@@ -0,0 +1,124 @@
+//! Links to an exact view of an exhibit that can be shared as text.
+//!
+//! A link names the exhibit, the camera pose and the option values of the exhibit in the form
+//! `<exhibit>@<x>,<y>,<z>,<yaw>,<pitch>:<option values>` like `Mandelbox@1.5,1.7,-3,0.785,-0.1:0.5,1,0`.
+//! It is copied to the clipboard from the options of an exhibit and opened from the options
+//! or at startup with `SHADERPIXEL_VIEW`.
+
+use crate::art::ArtObject;
+use crate::camera::Camera;
+use crate::settings::Bookmark;
+use crate::trigger::leave_portals;
+
+use anyhow::Context;
+use glam::Vec3;
+
+/// Decimals written for each value, enough to not notice a difference in the view.
+const DECIMALS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Permalink {
+    /// Name of the exhibit.
+    pub art: String,
+    pub pose: Bookmark,
+    /// Values in the layout of `ArtObject::option_values`.
+    pub options: Vec<f32>,
+}
+
+impl Permalink {
+    pub fn new(art: &ArtObject, camera: &Camera) -> Self {
+        Self {
+            art: art.name.clone(),
+            pose: Bookmark::from_camera(camera),
+            options: art.option_values(),
+        }
+    }
+
+    /// Parses a link created by `to_spec`, surrounding whitespace is ignored.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        // the name comes first as it may contain any character
+        let (art, values) = spec.trim().rsplit_once('@').context("expected <exhibit>@<camera pose>")?;
+        anyhow::ensure!(!art.is_empty(), "the exhibit name is empty");
+        let (pose, options) = values.split_once(':').unwrap_or((values, ""));
+        let pose = parse_values(pose).context("invalid camera pose")?;
+        let [x, y, z, yaw, pitch] = pose[..] else {
+            anyhow::bail!("expected 5 values for the camera pose, got {}", pose.len());
+        };
+        Ok(Self {
+            art: art.to_owned(),
+            pose: Bookmark { position: Vec3::new(x, y, z), yaw, pitch },
+            options: parse_values(options).context("invalid option values")?,
+        })
+    }
+
+    /// Moves the camera to the view and sets the option values of the exhibit.
+    pub fn apply(&self, art_objects: &mut [ArtObject], camera: &mut Camera) -> anyhow::Result<()> {
+        let art = art_objects.iter_mut()
+            .find(|art| art.name == self.art)
+            .with_context(|| format!("there is no exhibit named {}", self.art))?;
+        art.load_option_values(&self.options);
+        self.pose.apply(camera);
+        leave_portals(art_objects);
+        Ok(())
+    }
+
+    pub fn to_spec(&self) -> String {
+        let Bookmark { position, yaw, pitch } = self.pose;
+        let pose = format_values(&[position.x, position.y, position.z, yaw, pitch]);
+        if self.options.is_empty() {
+            format!("{}@{pose}", self.art)
+        } else {
+            format!("{}@{pose}:{}", self.art, format_values(&self.options))
+        }
+    }
+}
+
+fn parse_values(values: &str) -> anyhow::Result<Vec<f32>> {
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+    values.split(',')
+        .map(|value| {
+            let parsed = value.trim().parse::<f32>().with_context(|| format!("invalid number {value}"))?;
+            anyhow::ensure!(parsed.is_finite(), "number {value} is not finite");
+            Ok(parsed)
+        })
+        .collect()
+}
+
+fn format_values(values: &[f32]) -> String {
+    values.iter()
+        .map(|value| {
+            let value = format!("{value:.DECIMALS$}");
+            let value = value.trim_end_matches('0').trim_end_matches('.');
+            if value == "-0" { "0".to_owned() } else { value.to_owned() }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_round_trip() {
+        let link = Permalink {
+            art: "Mandel@box: 2".to_owned(),
+            pose: Bookmark { position: Vec3::new(1.5, 1.7, -3.), yaw: 0.785, pitch: -0.00001 },
+            options: vec![0.5, 1., 0.],
+        };
+        let spec = link.to_spec();
+        assert_eq!(spec, "Mandel@box: 2@1.5,1.7,-3,0.785,0:0.5,1,0");
+        let parsed = Permalink::from_spec(&format!(" {spec}\n")).unwrap();
+        assert_eq!(parsed, Permalink { pose: Bookmark { pitch: 0., ..link.pose }, ..link });
+
+        let parsed = Permalink::from_spec("Mirror@0,1,2,3,4").unwrap();
+        assert!(parsed.options.is_empty());
+        assert!(Permalink::from_spec("Mirror@0,1,2,3").is_err());
+        assert!(Permalink::from_spec("@0,1,2,3,4").is_err());
+        assert!(Permalink::from_spec("Mirror").is_err());
+        assert!(Permalink::from_spec("Mirror@0,1,NaN,3,4").is_err());
+        assert!(Permalink::from_spec("Mirror@0,1,2,3,4:inf").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! Props that fall, roll and can be pushed around by walking into them, simulated with rapier.
+//!
+//! The props are exhibits built with `prop`, the simulation writes their transform every frame.
+//! The gallery is a static mesh and the camera a kinematic capsule that shoves the props aside.
+//! Simulating needs the `physics` feature, without it the gallery has no props and `Physics::new` always fails.
+
+use crate::{art::ArtObject, model::obj::NormalizedObj};
+
+use glam::Vec3;
+
+/// Collision shape of a prop, sized to fit the container of its exhibit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropShape {
+    /// A ball touching the sides of the container.
+    Ball,
+    /// The container itself.
+    Cube,
+}
+
+impl PropShape {
+    /// Value `assets/shaders/prop.frag` selects the shape with.
+    pub fn shader_index(self) -> f32 {
+        match self {
+            Self::Ball => 0.,
+            Self::Cube => 1.,
+        }
+    }
+}
+
+/// Seconds simulated per step, frames are split into as many steps as fit.
+#[cfg(feature = "physics")]
+const TIME_STEP: f32 = 1. / 60.;
+/// Steps after which the remaining time of a long frame is dropped.
+#[cfg(feature = "physics")]
+const MAX_STEPS: u32 = 4;
+/// Height of the camera above the floor and radius of the capsule around it.
+#[cfg(feature = "physics")]
+const PLAYER_HEIGHT: f32 = 1.5;
+#[cfg(feature = "physics")]
+const PLAYER_RADIUS: f32 = 0.3;
+/// The capsule jumps instead of sweeping through the props if the camera moved further in a frame.
+#[cfg(feature = "physics")]
+const MAX_PUSH_DISTANCE: f32 = 1.;
+/// Props that fell below this height are put back to where they started.
+#[cfg(feature = "physics")]
+const RESET_HEIGHT: f32 = -10.;
+
+#[cfg(feature = "physics")]
+pub struct Physics {
+    pipeline: rapier3d::prelude::PhysicsPipeline,
+    integration_parameters: rapier3d::prelude::IntegrationParameters,
+    islands: rapier3d::prelude::IslandManager,
+    broad_phase: rapier3d::prelude::DefaultBroadPhase,
+    narrow_phase: rapier3d::prelude::NarrowPhase,
+    bodies: rapier3d::prelude::RigidBodySet,
+    colliders: rapier3d::prelude::ColliderSet,
+    impulse_joints: rapier3d::prelude::ImpulseJointSet,
+    multibody_joints: rapier3d::prelude::MultibodyJointSet,
+    ccd_solver: rapier3d::prelude::CCDSolver,
+    player: rapier3d::prelude::RigidBodyHandle,
+    props: Vec<Prop>,
+    /// Time not simulated yet, less than `TIME_STEP`.
+    accumulator: f32,
+}
+
+#[cfg(not(feature = "physics"))]
+pub struct Physics;
+
+/// A simulated exhibit, found again by name as the indices can change.
+#[cfg(feature = "physics")]
+struct Prop {
+    name: String,
+    body: rapier3d::prelude::RigidBodyHandle,
+    scale: Vec3,
+    start: rapier3d::prelude::Isometry<f32>,
+}
+
+impl Physics {
+    /// Builds the static collider of the gallery from `gallery` and a body for every prop.
+    #[cfg(feature = "physics")]
+    pub fn new(gallery: &NormalizedObj, art_objects: &[ArtObject], camera_position: Vec3) -> anyhow::Result<Self> {
+        use rapier3d::na::{Quaternion, UnitQuaternion};
+        use rapier3d::prelude::*;
+
+        let mut bodies = RigidBodySet::new();
+        let mut colliders = ColliderSet::new();
+
+        let vertices = gallery.vertices.iter()
+            .map(|vertex| point![vertex.pos_coords[0], vertex.pos_coords[1], vertex.pos_coords[2]])
+            .collect::<Vec<_>>();
+        let indices = gallery.indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]).collect::<Vec<_>>();
+        anyhow::ensure!(!indices.is_empty(), "the gallery has no triangles");
+        colliders.insert(ColliderBuilder::trimesh(vertices, indices).build());
+
+        let half_height = (PLAYER_HEIGHT - 2. * PLAYER_RADIUS) / 2.;
+        let player = bodies.insert(RigidBodyBuilder::kinematic_position_based()
+            .translation(to_vector(player_center(camera_position)))
+            .build());
+        let capsule = ColliderBuilder::capsule_y(half_height, PLAYER_RADIUS).build();
+        colliders.insert_with_parent(capsule, player, &mut bodies);
+
+        let mut props = Vec::new();
+        for art in art_objects {
+            let Some(shape) = art.prop else { continue };
+            let (scale, rotation, translation) = art.data.matrix.to_scale_rotation_translation();
+            let [min, max] = art.bounds();
+            let half_extents = (max - min) / 2. * scale.abs();
+            let collider = match shape {
+                PropShape::Ball => ColliderBuilder::ball(half_extents.min_element()),
+                PropShape::Cube => ColliderBuilder::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            };
+            let start = Isometry::from_parts(
+                to_vector(translation).into(),
+                UnitQuaternion::from_quaternion(Quaternion::new(rotation.w, rotation.x, rotation.y, rotation.z)),
+            );
+            let body = bodies.insert(RigidBodyBuilder::dynamic().position(start).build());
+            colliders.insert_with_parent(collider.restitution(0.3).friction(0.7).build(), body, &mut bodies);
+            props.push(Prop { name: art.name.clone(), body, scale, start });
+        }
+        log::debug!("simulating {} props", props.len());
+
+        Ok(Self {
+            pipeline: PhysicsPipeline::new(),
+            integration_parameters: IntegrationParameters::default(),
+            islands: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            bodies,
+            colliders,
+            impulse_joints: ImpulseJointSet::new(),
+            multibody_joints: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
+            player,
+            props,
+            accumulator: 0.,
+        })
+    }
+
+    #[cfg(not(feature = "physics"))]
+    pub fn new(_gallery: &NormalizedObj, _art_objects: &[ArtObject], _camera_position: Vec3) -> anyhow::Result<Self> {
+        anyhow::bail!("compiled without the physics feature")
+    }
+
+    /// Moves the player to the camera, advances the simulation by `elapsed` seconds
+    /// and writes the transforms of the props.
+    #[cfg(feature = "physics")]
+    pub fn update(&mut self, elapsed: f32, camera_position: Vec3, art_objects: &mut [ArtObject]) {
+        use glam::{Mat4, Quat};
+        use rapier3d::prelude::*;
+
+        let target = to_vector(player_center(camera_position));
+        if let Some(player) = self.bodies.get_mut(self.player) {
+            if (player.translation() - target).norm() > MAX_PUSH_DISTANCE {
+                player.set_translation(target, true);
+            } else {
+                player.set_next_kinematic_translation(target);
+            }
+        }
+
+        self.integration_parameters.dt = TIME_STEP;
+        self.accumulator += elapsed;
+        let mut steps = 0;
+        while self.accumulator >= TIME_STEP && steps < MAX_STEPS {
+            self.pipeline.step(
+                &vector![0., -9.81, 0.],
+                &self.integration_parameters,
+                &mut self.islands,
+                &mut self.broad_phase,
+                &mut self.narrow_phase,
+                &mut self.bodies,
+                &mut self.colliders,
+                &mut self.impulse_joints,
+                &mut self.multibody_joints,
+                &mut self.ccd_solver,
+                None,
+                &(),
+                &(),
+            );
+            self.accumulator -= TIME_STEP;
+            steps += 1;
+        }
+        if steps == MAX_STEPS {
+            self.accumulator = 0.;
+        }
+
+        for prop in self.props.iter() {
+            let Some(body) = self.bodies.get_mut(prop.body) else { continue };
+            if body.translation().y < RESET_HEIGHT {
+                body.set_position(prop.start, true);
+                body.set_linvel(Vector::zeros(), true);
+                body.set_angvel(Vector::zeros(), true);
+            }
+            let Some(art) = art_objects.iter_mut().find(|art| art.name == prop.name) else { continue };
+            let translation = body.translation();
+            let rotation = body.rotation();
+            art.data.matrix = Mat4::from_scale_rotation_translation(
+                prop.scale,
+                Quat::from_xyzw(rotation.i, rotation.j, rotation.k, rotation.w),
+                Vec3::new(translation.x, translation.y, translation.z),
+            );
+        }
+    }
+
+    #[cfg(not(feature = "physics"))]
+    pub fn update(&mut self, _elapsed: f32, _camera_position: Vec3, _art_objects: &mut [ArtObject]) {}
+}
+
+/// Center of the capsule of the player, whose top is at the camera.
+#[cfg(feature = "physics")]
+fn player_center(camera_position: Vec3) -> Vec3 {
+    camera_position - Vec3::Y * PLAYER_HEIGHT / 2.
+}
+
+#[cfg(feature = "physics")]
+fn to_vector(v: Vec3) -> rapier3d::prelude::Vector<f32> {
+    rapier3d::prelude::vector![v.x, v.y, v.z]
+}
@@ -0,0 +1,98 @@
+//! Generated GLSL prelude for the fragment shaders of exhibits, see `ArtObjectBuilder::prelude`.
+//!
+//! The prelude declares the uniform buffer, the global uniforms and the uniform block of the exhibit
+//! and defines a macro for each option, e.g. `OPT_MAX_ITERATIONS` for the option "Max Iterations".
+//! Dropdowns also get a macro per label, e.g. `OPT_SHAPE_SPHERE`. It is generated from the options the
+//! exhibit is built with and inserted after the `#version` line whenever the shader is compiled, so
+//! renaming or reordering options in Rust needs no change to the slots in GLSL. It belongs to the
+//! shader, an exhibit sharing the shader with another one whose options differ is rejected.
+//! Shaders using it must not declare the uniforms themselves, which rules out `includes/lightning.glsl`.
+
+use crate::art::{ArtObject, ArtOptionType};
+
+use std::fmt::Write;
+
+const HEADER: &str = "// generated by shaderpixel, see src/prelude.rs
+#include <sp/ubo.glsl>
+#include \"includes/globals.glsl\"
+
+// random number in [0, 1) for p
+float sp_random(vec2 p) {
+    vec2 k1 = vec2(23.14069263277926, 2.665144142690225);
+    return fract(cos(dot(p, k1)) * 12345.6789);
+}
+";
+
+pub fn glsl_prelude(art: &ArtObject) -> String {
+    let mut glsl = HEADER.to_owned();
+    if let Some(block) = art.uniform_block.as_ref() {
+        glsl.push_str(&block.glsl());
+    }
+    let mut slot = 0;
+    for option in art.options.iter() {
+        let name = format!("OPT_{}", macro_case(option.label()));
+        // writing to a String does not fail
+        let _ = writeln!(glsl, "#define {name} {}", accessor(&option.ty, slot));
+        if let ArtOptionType::Enum { labels, .. } = option.ty {
+            for (value, label) in labels.iter().enumerate() {
+                let _ = writeln!(glsl, "#define {name}_{} {value}", macro_case(label));
+            }
+        }
+        slot += option.ty.slot_count();
+    }
+    glsl
+}
+
+/// Reads the option whose values start at `slot` in `ubo.options` as the matching GLSL type.
+fn accessor(ty: &ArtOptionType, slot: usize) -> String {
+    let components = (slot..slot + ty.slot_count())
+        .map(|slot| format!("ubo.options[{}][{}]", slot / 4, slot % 4))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match ty {
+        ArtOptionType::Checkbox { .. } => format!("({components} > 0.5)"),
+        ArtOptionType::SliderI32 { .. } | ArtOptionType::Enum { .. } => format!("int({components})"),
+        ArtOptionType::SliderF32 { .. } | ArtOptionType::Angle { .. } => components,
+        ArtOptionType::Vec2 { .. } | ArtOptionType::Vec3 { .. }
+        | ArtOptionType::Stroke { .. } | ArtOptionType::Color { .. } => {
+            format!("vec{}({components})", ty.slot_count())
+        }
+    }
+}
+
+/// Uppercases the label and joins its words with underscores.
+fn macro_case(label: &str) -> String {
+    label.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_uppercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::art::ArtOption;
+
+    #[test]
+    fn options_are_read_from_their_slots() {
+        let art = ArtObject {
+            options: vec![
+                ArtOption::slider_f32("Power", 8., 1., 16.),
+                ArtOption::vec3("Light dir", [0., 1., 0.], -1., 1.),
+                ArtOption::checkbox("Show AO", true),
+                ArtOption::dropdown("Shape", 0, &["Sphere", "Rounded box"]),
+            ],
+            ..Default::default()
+        };
+        let glsl = glsl_prelude(&art);
+        assert!(glsl.contains("#define OPT_POWER ubo.options[0][0]\n"));
+        assert!(glsl.contains(
+            "#define OPT_LIGHT_DIR vec3(ubo.options[0][1], ubo.options[0][2], ubo.options[0][3])\n"
+        ));
+        assert!(glsl.contains("#define OPT_SHOW_AO (ubo.options[1][0] > 0.5)\n"));
+        assert!(glsl.contains("#define OPT_SHAPE int(ubo.options[1][1])\n"));
+        assert!(glsl.contains("#define OPT_SHAPE_ROUNDED_BOX 1\n"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Named presets of the option values of an exhibit.
+//!
+//! The presets of an exhibit are stored in `presets/<name>.json`. Switching to a preset
+//! interpolates the option values over a short time instead of jumping to them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Directory the presets are stored in.
+pub const PRESET_DIR: &str = "presets";
+/// Duration of the interpolation when switching to a preset in seconds.
+const TRANSITION_DURATION: f32 = 1.;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    /// The option values in the same layout as `ArtOptionType::save_value` writes them.
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug)]
+struct Transition {
+    from: Vec<f32>,
+    to: Vec<f32>,
+    progress: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct Presets {
+    path: PathBuf,
+    presets: Vec<Preset>,
+    selected: Option<usize>,
+    transition: Option<Transition>,
+}
+
+impl Presets {
+    /// Loads the presets of the exhibit with the file stem `stem` from `dir`.
+    /// A missing file is not an error, there are just no presets yet.
+    pub fn load(dir: &Path, stem: &str) -> anyhow::Result<Self> {
+        let path = dir.join(format!("{stem}.json"));
+        let presets = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("invalid presets file {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err).context(format!("failed to read {}", path.display())),
+        };
+        Ok(Self { path, presets, ..Default::default() })
+    }
+
+    /// Writes the presets back to the file they were loaded from.
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(&self.presets)?;
+        fs::write(&self.path, json).with_context(|| format!("failed to write {}", self.path.display()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.presets.iter()
+    }
+
+    pub fn selected(&self) -> Option<&Preset> {
+        self.selected.and_then(|idx| self.presets.get(idx))
+    }
+
+    /// Adds a preset or replaces the one with the same name and selects it.
+    pub fn insert(&mut self, name: String, values: Vec<f32>) {
+        let idx = match self.presets.iter().position(|preset| preset.name == name) {
+            Some(idx) => {
+                self.presets[idx].values = values;
+                idx
+            }
+            None => {
+                self.presets.push(Preset { name, values });
+                self.presets.len() - 1
+            }
+        };
+        self.selected = Some(idx);
+    }
+
+    /// Removes the selected preset.
+    pub fn remove_selected(&mut self) {
+        if let Some(idx) = self.selected.take() {
+            self.presets.remove(idx);
+        }
+    }
+
+    /// Selects the preset at `idx` and starts interpolating from `current` to its values.
+    pub fn select(&mut self, idx: usize, current: Vec<f32>) {
+        let Some(preset) = self.presets.get(idx) else { return };
+        if preset.values.len() != current.len() {
+            log::warn!("preset {} does not match the options, ignoring it", preset.name);
+            return;
+        }
+        self.selected = Some(idx);
+        self.transition = Some(Transition { from: current, to: preset.values.clone(), progress: 0. });
+    }
+
+    /// Advances a running transition by `elapsed` seconds and returns the interpolated values.
+    pub fn update(&mut self, elapsed: f32) -> Option<Vec<f32>> {
+        let transition = self.transition.as_mut()?;
+        transition.progress = (transition.progress + elapsed / TRANSITION_DURATION).min(1.);
+        let t = transition.progress;
+        let t = t * t * (3. - 2. * t);
+        let values = transition.from.iter().zip(&transition.to)
+            .map(|(from, to)| from + (to - from) * t)
+            .collect();
+        if transition.progress >= 1. {
+            self.transition = None;
+        }
+        Some(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transition_ends_at_preset() {
+        let mut presets = Presets::default();
+        presets.insert("a".to_owned(), vec![1., 10.]);
+        presets.select(0, vec![0., 0.]);
+        let halfway = presets.update(0.5 * TRANSITION_DURATION).unwrap();
+        assert_eq!(halfway, [0.5, 5.]);
+        assert_eq!(presets.update(TRANSITION_DURATION).unwrap(), [1., 10.]);
+        assert!(presets.update(0.1).is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! Lowers the quality when the frames miss the target frame rate and raises it again once they hold it,
+//! e.g. when the GPU throttles because it got hot or the device saves energy.
+//!
+//! The knobs are the render scale, the frames are rendered smaller and scaled to the window, and the detail
+//! passed to the shaders in `GlobalUniforms`, which ray marchers can scale their iteration counts with.
+//! MSAA is not among them, the render pass the GUI was created for fixes the sample count.
+//!
+//! With vsync the frame time never drops below the refresh interval, so there is no way to tell how much
+//! headroom there is. Instead the quality is raised after the target was held for a while and dropped right
+//! away if that misses it again, which doubles the wait before the next attempt.
+
+/// Smoothing of the average frame time, the weight of the newest frame.
+const SMOOTHING: f32 = 0.1;
+/// Seconds after a change before the frame times are judged again.
+const SETTLE: f32 = 1.;
+/// The quality is lowered if the average frame time exceeds the budget by this factor.
+const LOWER_MARGIN: f32 = 1.15;
+/// The quality is raised only if the average frame time is within the budget times this factor.
+const RAISE_MARGIN: f32 = 1.05;
+/// Seconds the target has to be held before the quality is raised.
+const RAISE_DELAY: f32 = 5.;
+const MAX_RAISE_DELAY: f32 = 80.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityLevel {
+    pub label: &'static str,
+    /// Size of the rendered frames relative to the window.
+    pub render_scale: f32,
+    /// From 0 to 1, how much of their iterations the shaders should spend.
+    pub detail: f32,
+}
+
+/// From the highest to the lowest quality.
+pub const LEVELS: [QualityLevel; 5] = [
+    QualityLevel { label: "High", render_scale: 1., detail: 1. },
+    QualityLevel { label: "Reduced detail", render_scale: 1., detail: 0.75 },
+    QualityLevel { label: "Medium", render_scale: 0.85, detail: 0.75 },
+    QualityLevel { label: "Low", render_scale: 0.7, detail: 0.5 },
+    QualityLevel { label: "Lowest", render_scale: 0.5, detail: 0.35 },
+];
+
+#[derive(Debug, Clone)]
+pub struct AutoQuality {
+    pub enabled: bool,
+    pub target_fps: f32,
+    /// Index into `LEVELS`.
+    level: usize,
+    /// Average frame time in seconds since the last change.
+    average: Option<f32>,
+    /// Seconds since the last change.
+    since_change: f32,
+    raise_delay: f32,
+    /// The last change raised the quality and has not held the target for `RAISE_DELAY` yet.
+    probing: bool,
+}
+
+impl AutoQuality {
+    /// The current level, the highest one while disabled.
+    pub fn level(&self) -> QualityLevel {
+        LEVELS[if self.enabled { self.level } else { 0 }]
+    }
+
+    /// Smoothed frame time in seconds, `None` right after a change.
+    pub fn average(&self) -> Option<f32> {
+        self.average
+    }
+
+    /// Feeds the time the last frame took in seconds, returns `true` if the level changed.
+    pub fn update(&mut self, frame_time: f32) -> bool {
+        if !self.enabled {
+            let changed = self.level != 0;
+            *self = Self { enabled: false, target_fps: self.target_fps, ..Self::default() };
+            return changed;
+        }
+        let average = self.average.map_or(frame_time, |average| average + (frame_time - average) * SMOOTHING);
+        self.average = Some(average);
+        self.since_change += frame_time;
+        if self.since_change < SETTLE {
+            return false;
+        }
+        let budget = 1. / self.target_fps.max(1.);
+        if average > budget * LOWER_MARGIN && self.level + 1 < LEVELS.len() {
+            if self.probing {
+                self.raise_delay = (self.raise_delay * 2.).min(MAX_RAISE_DELAY);
+            }
+            self.change_level(self.level + 1, false);
+            return true;
+        }
+        if self.probing && self.since_change >= RAISE_DELAY {
+            // the higher level holds the target
+            self.probing = false;
+            self.raise_delay = RAISE_DELAY;
+        }
+        if average <= budget * RAISE_MARGIN && self.level > 0 && self.since_change >= self.raise_delay {
+            self.change_level(self.level - 1, true);
+            return true;
+        }
+        false
+    }
+
+    fn change_level(&mut self, level: usize, probing: bool) {
+        log::info!("switching to quality level {}", LEVELS[level].label);
+        self.level = level;
+        self.average = None;
+        self.since_change = 0.;
+        self.probing = probing;
+    }
+}
+
+impl Default for AutoQuality {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            target_fps: 60.,
+            level: 0,
+            average: None,
+            since_change: 0.,
+            raise_delay: RAISE_DELAY,
+            probing: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowers_on_slow_frames_and_backs_off_raising() {
+        let mut quality = AutoQuality { enabled: true, ..Default::default() };
+        let run = |quality: &mut AutoQuality, frame_time: f32, seconds: f32| {
+            let mut changes = 0;
+            for _ in 0..(seconds / frame_time) as usize {
+                changes += quality.update(frame_time) as usize;
+            }
+            changes
+        };
+        assert_eq!(run(&mut quality, 1. / 60., 10.), 0);
+        assert_eq!(run(&mut quality, 1. / 30., 0.5), 1);
+        assert_eq!(quality.level(), LEVELS[1]);
+        // the target is held, the quality is raised after the delay
+        assert_eq!(run(&mut quality, 1. / 60., 4.), 0);
+        assert_eq!(run(&mut quality, 1. / 60., 2.), 1);
+        assert_eq!(quality.level(), LEVELS[0]);
+        // which misses it again, the next attempt waits twice as long
+        assert_eq!(run(&mut quality, 1. / 30., 0.5), 1);
+        assert_eq!(run(&mut quality, 1. / 60., 9.), 0);
+        assert_eq!(run(&mut quality, 1. / 60., 2.), 1);
+        quality.enabled = false;
+        assert!(!quality.update(1. / 60.));
+        assert_eq!(quality.level(), LEVELS[0]);
+    }
+}
@@ -0,0 +1,314 @@
+//! HTTP API to drive the gallery from external scripts or tablets, needs the `remote` feature.
+//!
+//! All bodies and responses are JSON:
+//! - `GET /art` lists the exhibits with their position and option values
+//! - `POST /art/<name>/options` sets options by label, e.g. `{"Iterations": 8, "Color": [1, 0, 0]}`
+//! - `POST /camera` teleports the camera, e.g. `{"position": [0, 1.5, 3], "yaw": 90, "pitch": 0}`
+//!   with the angles in degrees, all fields are optional
+//! - `POST /screenshot` saves the next frame to `screenshots`, the body `{"path": "shot.png"}` is optional
+//!   and must be a file name without directories
+//! - `GET /scene` names the current scene and the available ones
+//! - `POST /scene` switches to another scene before the next frame, e.g. `{"name": "pillars"}`
+
+use crate::{
+    art::ArtObject,
+    camera::Camera,
+    vulkan::VkApp,
+};
+
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+/// Address the server listens on if `SHADERPIXEL_REMOTE_ADDR` is not set.
+pub const DEFAULT_ADDR: &str = "127.0.0.1:7878";
+/// How long a request waits for the next frame before it times out.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Directory all screenshots are saved to.
+const SCREENSHOT_DIR: &str = "screenshots";
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    ListArt,
+    SetOptions { art: String, values: Map<String, Value> },
+    Teleport(Teleport),
+    /// The path is already checked to stay in `SCREENSHOT_DIR`.
+    Screenshot { path: Option<PathBuf> },
+    ListScenes,
+    SwitchScene { name: String },
+}
+
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Teleport {
+    position: Option<[f32; 3]>,
+    yaw: Option<f32>,
+    pitch: Option<f32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScreenshotBody {
+    path: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneBody {
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct RemoteError {
+    status: u16,
+    message: String,
+}
+
+impl RemoteError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+type RemoteResult = Result<Value, RemoteError>;
+
+struct Request {
+    command: Command,
+    responder: mpsc::Sender<RemoteResult>,
+}
+
+pub struct RemoteServer {
+    rx: mpsc::Receiver<Request>,
+}
+
+impl RemoteServer {
+    /// Starts listening on `addr` on a background thread.
+    pub fn start(addr: &str) -> anyhow::Result<Self> {
+        let server = Server::http(addr).map_err(|err| anyhow::anyhow!("failed to listen on {addr}: {err}"))?;
+        log::info!("remote control listening on http://{addr}");
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                let result = match request.as_reader().read_to_string(&mut body) {
+                    Ok(_) => parse_command(request.method(), request.url(), &body),
+                    Err(err) => Err(RemoteError::new(400, format!("failed to read body: {err}"))),
+                };
+                let result = result.and_then(|command| {
+                    let (responder, response_rx) = mpsc::channel();
+                    tx.send(Request { command, responder })
+                        .map_err(|_| RemoteError::new(503, "shutting down"))?;
+                    response_rx.recv_timeout(RESPONSE_TIMEOUT)
+                        .map_err(|_| RemoteError::new(503, "no frame was rendered in time"))?
+                });
+                let (status, body) = match result {
+                    Ok(value) => (200, value),
+                    Err(err) => (err.status, json!({ "error": err.message })),
+                };
+                let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("header is valid");
+                let response = Response::from_string(body.to_string())
+                    .with_status_code(status)
+                    .with_header(header);
+                if let Err(err) = request.respond(response) {
+                    log::warn!("failed to send remote response: {err}");
+                }
+            }
+        });
+        Ok(Self { rx })
+    }
+
+    /// Executes all pending requests, should be called once per frame.
+    /// A scene is switched by setting `scene` to one of `scenes`.
+    pub fn handle_requests(
+        &self,
+        art_objects: &mut [ArtObject],
+        camera: &mut Camera,
+        vk_app: &mut VkApp,
+        scene: &mut String,
+        scenes: &[String],
+    ) {
+        while let Ok(request) = self.rx.try_recv() {
+            log::debug!("remote command {:?}", request.command);
+            let result = match request.command {
+                Command::ListArt => Ok(list_art(art_objects)),
+                Command::SetOptions { art, values } => set_options(art_objects, &art, values),
+                Command::Teleport(teleport) => {
+                    if let Some(position) = teleport.position {
+                        camera.position = position.into();
+                    }
+                    if let Some(yaw) = teleport.yaw {
+                        camera.angle_yaw = yaw.to_radians();
+                    }
+                    if let Some(pitch) = teleport.pitch {
+                        camera.angle_pitch = pitch.to_radians();
+                    }
+                    Ok(json!({ "position": camera.position.to_array() }))
+                }
+                Command::Screenshot { path } => {
+                    let path = path.unwrap_or_else(|| {
+                        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                        PathBuf::from(SCREENSHOT_DIR).join(format!("{}.png", timestamp.as_millis()))
+                    });
+                    vk_app.request_screenshot(path.clone());
+                    Ok(json!({ "path": path }))
+                }
+                Command::ListScenes => Ok(json!({ "name": scene, "scenes": scenes })),
+                Command::SwitchScene { name } => {
+                    if scenes.contains(&name) {
+                        *scene = name;
+                        Ok(json!({ "name": scene }))
+                    } else {
+                        Err(RemoteError::new(404, format!("no scene named {name}")))
+                    }
+                }
+            };
+            let _ = request.responder.send(result);
+        }
+    }
+}
+
+fn parse_command(method: &Method, url: &str, body: &str) -> Result<Command, RemoteError> {
+    let path = url.split('?').next().unwrap_or_default();
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect::<Vec<_>>();
+    let command = match (method, segments.as_slice()) {
+        (Method::Get, ["art"]) => Command::ListArt,
+        (Method::Post, ["art", name, "options"]) => Command::SetOptions {
+            art: percent_decode(name)?,
+            values: parse_body(body)?,
+        },
+        (Method::Post, ["camera"]) => Command::Teleport(parse_body(body)?),
+        (Method::Post, ["screenshot"]) => {
+            let body: ScreenshotBody = if body.trim().is_empty() { Default::default() } else { parse_body(body)? };
+            Command::Screenshot { path: body.path.as_deref().map(screenshot_path).transpose()? }
+        }
+        (Method::Get, ["scene"]) => Command::ListScenes,
+        (Method::Post, ["scene"]) => Command::SwitchScene { name: parse_body::<SceneBody>(body)?.name },
+        (_, ["art"] | ["art", _, "options"] | ["camera"] | ["screenshot"] | ["scene"]) => {
+            return Err(RemoteError::new(405, format!("method {method} not allowed")));
+        }
+        _ => return Err(RemoteError::new(404, format!("unknown endpoint {path}"))),
+    };
+    Ok(command)
+}
+
+/// Joins the file name onto `SCREENSHOT_DIR`, anything that could leave it is rejected.
+fn screenshot_path(name: &str) -> Result<PathBuf, RemoteError> {
+    let path = Path::new(name);
+    let components = path.components().collect::<Vec<_>>();
+    let is_file_name = !name.contains(['/', '\\']) && matches!(components[..], [Component::Normal(_)]);
+    if !is_file_name {
+        return Err(RemoteError::new(400, format!("the screenshot path {name} must be a file name")));
+    }
+    Ok(Path::new(SCREENSHOT_DIR).join(path))
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, RemoteError> {
+    serde_json::from_str(body).map_err(|err| RemoteError::new(400, format!("invalid body: {err}")))
+}
+
+fn percent_decode(s: &str) -> Result<String, RemoteError> {
+    let invalid = || RemoteError::new(400, format!("invalid percent encoding in {s}"));
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut iter = s.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next().ok_or_else(invalid)?, iter.next().ok_or_else(invalid)?];
+            let hex = std::str::from_utf8(&hex).map_err(|_| invalid())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| invalid())?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+fn list_art(art_objects: &[ArtObject]) -> Value {
+    let arts = art_objects.iter().map(|art| {
+        let values = art.option_values();
+        let mut i = 0;
+        let options = art.options.iter().map(|option| {
+            let count = option.ty.slot_count();
+            let option_values = &values[i..i + count];
+            i += count;
+            let value = match option_values {
+                [value] => json!(value),
+                values => json!(values),
+            };
+            (option.label().to_owned(), value)
+        }).collect::<Map<_, _>>();
+        json!({
+            "name": art.name,
+            "position": art.position().to_array(),
+            "options": options,
+        })
+    }).collect();
+    Value::Array(arts)
+}
+
+fn set_options(art_objects: &mut [ArtObject], name: &str, values: Map<String, Value>) -> RemoteResult {
+    let art = art_objects.iter_mut()
+        .find(|art| art.name == name)
+        .ok_or_else(|| RemoteError::new(404, format!("no art named {name}")))?;
+    for (label, value) in values {
+        let option = art.options.iter_mut()
+            .find(|option| option.label() == label)
+            .ok_or_else(|| RemoteError::new(404, format!("{name} has no option {label}")))?;
+        let to_f32 = |value: &Value| match value {
+            Value::Bool(b) => Some(if *b { 1. } else { 0. }),
+            Value::Number(n) => n.as_f64().map(|n| n as f32),
+            _ => None,
+        };
+        let option_values = match &value {
+            Value::Array(array) => array.iter().map(to_f32).collect::<Option<Vec<_>>>(),
+            value => to_f32(value).map(|value| vec![value]),
+        };
+        let option_values = option_values
+            .filter(|values| values.len() == option.ty.slot_count())
+            .ok_or_else(|| RemoteError::new(400, format!("invalid value for {label}: {value}")))?;
+        option.ty.load_value(&option_values, &mut 0);
+    }
+    art.save_options();
+    Ok(json!({ "name": art.name }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse_command(&Method::Get, "/art", ""), Ok(Command::ListArt));
+        let command = parse_command(&Method::Post, "/art/Pillar%20%201/options", r#"{"Speed": 2}"#).unwrap();
+        let Command::SetOptions { art, values } = command else { panic!("wrong command {command:?}") };
+        assert_eq!(art, "Pillar  1");
+        assert_eq!(values["Speed"], json!(2));
+        assert_eq!(
+            parse_command(&Method::Post, "/camera", r#"{"yaw": 90}"#),
+            Ok(Command::Teleport(Teleport { yaw: Some(90.), ..Default::default() })),
+        );
+        assert_eq!(parse_command(&Method::Post, "/screenshot", ""), Ok(Command::Screenshot { path: None }));
+        assert_eq!(
+            parse_command(&Method::Post, "/screenshot", r#"{"path": "shot.png"}"#),
+            Ok(Command::Screenshot { path: Some(Path::new(SCREENSHOT_DIR).join("shot.png")) }),
+        );
+        for path in ["../shot.png", "/tmp/shot.png", "dir/shot.png", "dir\\shot.png", "..", ".", ""] {
+            let body = json!({ "path": path }).to_string();
+            assert_eq!(parse_command(&Method::Post, "/screenshot", &body).unwrap_err().status, 400, "{path}");
+        }
+        assert_eq!(parse_command(&Method::Get, "/scene", ""), Ok(Command::ListScenes));
+        assert_eq!(
+            parse_command(&Method::Post, "/scene", r#"{"name": "pillars"}"#),
+            Ok(Command::SwitchScene { name: "pillars".to_owned() }),
+        );
+        assert_eq!(parse_command(&Method::Get, "/camera", "").unwrap_err().status, 405);
+        assert_eq!(parse_command(&Method::Get, "/lights", "").unwrap_err().status, 404);
+        assert_eq!(parse_command(&Method::Post, "/camera", "{").unwrap_err().status, 400);
+    }
+}
@@ -0,0 +1,77 @@
+//! A second window on the same device, showing either the GUI detached from the scene or the
+//! scene from another place, e.g. to control the exhibits on one monitor while presenting them
+//! on another.
+
+use crate::vulkan::{VkApp, WindowTarget};
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use egui_winit_vulkano::{Gui, GuiConfig};
+use glam::Mat4;
+use winit::{event_loop::ActiveEventLoop, window::WindowAttributes};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SecondWindowMode {
+    #[default]
+    Off,
+    /// The GUI is drawn in the second window instead of over the scene.
+    Gui,
+    /// The scene as seen from where the camera was when the window was opened.
+    View,
+}
+
+impl SecondWindowMode {
+    pub const ALL: [Self; 3] = [Self::Off, Self::Gui, Self::View];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Gui => "GUI",
+            Self::View => "View",
+        }
+    }
+}
+
+pub struct SecondWindow {
+    mode: SecondWindowMode,
+    pub target: WindowTarget,
+    /// Only created for `SecondWindowMode::Gui`.
+    pub gui: Option<Gui>,
+    /// Camera of `SecondWindowMode::View`.
+    pub view_matrix: Mat4,
+}
+
+impl SecondWindow {
+    /// Enables the second view of `vk_app` for `SecondWindowMode::View`, it must be disabled
+    /// again when the window is closed.
+    pub fn open(
+        mode: SecondWindowMode,
+        attributes: WindowAttributes,
+        event_loop: &ActiveEventLoop,
+        vk_app: &mut VkApp,
+        view_matrix: Mat4,
+    ) -> anyhow::Result<Self> {
+        let window = event_loop.create_window(attributes).context("failed to create window")?;
+        let target = vk_app.open_window(Arc::new(window))?;
+        let gui = match mode {
+            SecondWindowMode::Off => anyhow::bail!("no second window in mode {}", mode.label()),
+            SecondWindowMode::Gui => Some(Gui::new(
+                event_loop,
+                target.surface().clone(),
+                vk_app.get_queue().clone(),
+                target.format(),
+                GuiConfig::default(),
+            )),
+            SecondWindowMode::View => {
+                vk_app.set_second_view(true)?;
+                None
+            }
+        };
+        Ok(Self { mode, target, gui, view_matrix })
+    }
+
+    pub fn mode(&self) -> SecondWindowMode {
+        self.mode
+    }
+}
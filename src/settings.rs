@@ -0,0 +1,218 @@
+//! Settings persisted across runs in `settings.json`.
+//!
+//! Holds the camera bookmarks, saved with Ctrl+1 to Ctrl+9 and recalled with 1 to 9,
+//! the movement options, the avatar, see `crate::avatar`, and accessibility options.
+
+use crate::avatar::AvatarSettings;
+use crate::camera::{Camera, Smoothing};
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::Context;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+pub const SETTINGS_PATH: &str = "settings.json";
+pub const BOOKMARK_SLOTS: usize = 9;
+
+/// A saved camera pose, the angles are in radians like in `Camera`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Bookmark {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self { position: camera.position, yaw: camera.angle_yaw, pitch: camera.angle_pitch }
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.position = self.position;
+        camera.angle_yaw = self.yaw;
+        camera.angle_pitch = self.pitch;
+        // the position is not lowered anymore, otherwise standing up would move above it
+        camera.crouched = false;
+        camera.stop();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Movement {
+    /// Speed on the ground in units per second.
+    pub walk_speed: f32,
+    /// Speed in fly mode in units per second.
+    pub fly_speed: f32,
+    /// Factor applied to the speed while the sprint key is held.
+    pub sprint_multiplier: f32,
+    /// Every line scrolled multiplies the speed by `exp(scroll_factor)`.
+    pub scroll_factor: f32,
+    pub smoothing: Smoothing,
+}
+
+impl Movement {
+    /// Units moved per second with the given state and the lines scrolled since the start.
+    pub fn speed(&self, fly_mode: bool, sprint: bool, scroll_lines: f32) -> f32 {
+        let base = if fly_mode { self.fly_speed } else { self.walk_speed };
+        let sprint = if sprint { self.sprint_multiplier } else { 1. };
+        base * sprint * (scroll_lines * self.scroll_factor).exp()
+    }
+}
+
+impl Default for Movement {
+    fn default() -> Self {
+        Self {
+            walk_speed: 2.,
+            fly_speed: 2.,
+            sprint_multiplier: 3.,
+            scroll_factor: 0.4,
+            smoothing: Smoothing::default(),
+        }
+    }
+}
+
+/// How the exhibit next to the camera is interacted with, set per scene in the layout, see
+/// `crate::model::env_generator::Layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Interaction {
+    /// Distance in units within which the options of the nearest exhibit are shown.
+    pub radius: f32,
+    /// Show the options of the nearest exhibit on their own, else only with E or from the browser.
+    pub auto_open: bool,
+    /// Stops the time of the other exhibits while one is within `radius`.
+    pub pause_others: bool,
+}
+
+impl Default for Interaction {
+    fn default() -> Self {
+        Self { radius: 1.5, auto_open: true, pause_others: false }
+    }
+}
+
+/// When the attract mode of unattended exhibitions starts, see `crate::attract`. Set per scene in
+/// the layout like `Interaction`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attract {
+    pub enabled: bool,
+    /// Seconds without input after which the tour starts.
+    pub idle_timeout: f32,
+    /// Slowly change the options of the nearest exhibit during the tour.
+    pub animate_options: bool,
+}
+
+impl Default for Attract {
+    fn default() -> Self {
+        Self { enabled: false, idle_timeout: 120., animate_options: true }
+    }
+}
+
+/// Clip planes of the projection and the range the FOV can be set in. Set per scene in the layout
+/// like `Interaction`, close-ups of fractals want a smaller near plane, large galleries a farther far plane.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Projection {
+    /// Distance of the near clip plane in units.
+    pub near: f32,
+    /// Distance of the far clip plane in units.
+    pub far: f32,
+    /// Smallest FOV in degrees.
+    pub min_fov: f32,
+    /// Largest FOV in degrees.
+    pub max_fov: f32,
+}
+
+impl Projection {
+    /// Limits of the FOV in degrees, a perspective projection breaks down at 0 and 180.
+    pub const FOV_RANGE: std::ops::RangeInclusive<f32> = 1.0..=179.0;
+    /// Largest ratio of the far to the near plane, beyond it the depth buffer is too coarse.
+    pub const MAX_DEPTH_RATIO: f32 = 1e7;
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(self.near > 0., "the near plane {} is not in front of the camera", self.near);
+        anyhow::ensure!(self.far > self.near, "the far plane {} is not behind the near plane {}", self.far, self.near);
+        anyhow::ensure!(
+            self.far / self.near <= Self::MAX_DEPTH_RATIO,
+            "the far plane {} is more than {} times farther than the near plane {}",
+            self.far, Self::MAX_DEPTH_RATIO, self.near,
+        );
+        anyhow::ensure!(
+            Self::FOV_RANGE.contains(&self.min_fov) && Self::FOV_RANGE.contains(&self.max_fov)
+                && self.min_fov <= self.max_fov,
+            "the FOV limits {}..={} are not within {:?}", self.min_fov, self.max_fov, Self::FOV_RANGE,
+        );
+        Ok(())
+    }
+
+    pub fn clamp_fov(&self, fov: f32) -> f32 {
+        fov.clamp(self.min_fov, self.max_fov)
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self { near: 0.01, far: 200., min_fov: 1., max_fov: 179. }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// The bookmark of slot `n` is at index `n - 1`.
+    pub bookmarks: [Option<Bookmark>; BOOKMARK_SLOTS],
+    pub movement: Movement,
+    pub avatar: AvatarSettings,
+    /// Calms the exhibits for users sensitive to motion and flicker, see `crate::gui::Options::reduced_motion`.
+    pub reduced_motion: bool,
+}
+
+impl Settings {
+    /// A missing file is not an error, the defaults are used.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("invalid settings file {}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).context(format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_roundtrip() {
+        let mut settings = Settings::default();
+        settings.bookmarks[2] = Some(Bookmark { position: Vec3::new(1., 1.5, -2.), yaw: 0.5, pitch: -0.25 });
+        let json = serde_json::to_string(&settings).unwrap();
+        let loaded: Settings = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.bookmarks, settings.bookmarks);
+        // files without bookmarks still load
+        let empty: Settings = serde_json::from_str("{}").unwrap();
+        assert!(empty.bookmarks.iter().all(Option::is_none));
+        assert_eq!(empty.movement, Movement::default());
+    }
+
+    #[test]
+    fn projection_validation() {
+        assert!(Projection::default().validate().is_ok());
+        let projection = |near, far, min_fov, max_fov| Projection { near, far, min_fov, max_fov };
+        assert!(projection(0.001, 1000., 10., 120.).validate().is_ok());
+        assert!(projection(0., 200., 1., 179.).validate().is_err());
+        assert!(projection(1., 1., 1., 179.).validate().is_err());
+        assert!(projection(1e-6, 200., 1., 179.).validate().is_err());
+        assert!(projection(0.01, 200., 90., 60.).validate().is_err());
+        assert!(projection(0.01, 200., 1., 180.).validate().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Anonymous statistics of the visitors, so curators learn which exhibits attract attention.
+//!
+//! Recording is enabled by setting `SHADERPIXEL_STATS` to a CSV file, it stays on the machine.
+//! A visit starts with the first input and ends after `IDLE_TIMEOUT` seconds without input, which
+//! are included, or when the app exits. Then a line is appended for every exhibit of the visit with
+//! the seconds the camera was near it, the seconds it was in the center of the screen and how often
+//! its options were changed in the GUI. Nothing but the start time identifies a visit.
+
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+
+/// Seconds without input after which the visitor is considered gone.
+pub const IDLE_TIMEOUT: f32 = 60.;
+const HEADER: &str = "visit_start,visit_seconds,exhibit,seconds_near,seconds_looked_at,option_changes";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct ExhibitStats {
+    near: f32,
+    looked_at: f32,
+    option_changes: u32,
+}
+
+#[derive(Debug)]
+struct Visit {
+    /// Unix time in seconds.
+    start: u64,
+    duration: f32,
+    exhibits: BTreeMap<String, ExhibitStats>,
+    /// Name of the exhibit whose options changed last frame, a drag over several frames is one change.
+    changing: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct VisitorStats {
+    path: PathBuf,
+    /// Seconds since the last input.
+    idle: f32,
+    visit: Option<Visit>,
+}
+
+impl VisitorStats {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into(), idle: 0., visit: None }
+    }
+
+    /// Called for every input of the visitor, starts a visit if there is none.
+    pub fn input(&mut self) {
+        self.idle = 0.;
+        self.visit.get_or_insert_with(|| Visit {
+            start: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            duration: 0.,
+            exhibits: BTreeMap::new(),
+            changing: None,
+        });
+    }
+
+    /// Adds `elapsed` seconds to the visit and the exhibits named in `near` and `looked_at`.
+    /// `changed_options` names the exhibit whose options were changed this frame.
+    pub fn update<'a>(
+        &mut self,
+        elapsed: f32,
+        near: impl IntoIterator<Item = &'a str>,
+        looked_at: Option<&str>,
+        changed_options: Option<&str>,
+    ) {
+        self.idle += elapsed;
+        if self.idle >= IDLE_TIMEOUT {
+            self.end_visit();
+        }
+        let Some(visit) = self.visit.as_mut() else { return };
+        visit.duration += elapsed;
+        for name in near {
+            visit.exhibits.entry(name.to_owned()).or_default().near += elapsed;
+        }
+        if let Some(name) = looked_at {
+            visit.exhibits.entry(name.to_owned()).or_default().looked_at += elapsed;
+        }
+        if let Some(name) = changed_options.filter(|&name| visit.changing.as_deref() != Some(name)) {
+            visit.exhibits.entry(name.to_owned()).or_default().option_changes += 1;
+        }
+        visit.changing = changed_options.map(str::to_owned);
+    }
+
+    /// Appends the lines of the current visit to the file.
+    pub fn end_visit(&mut self) {
+        let Some(visit) = self.visit.take() else { return };
+        if visit.exhibits.is_empty() {
+            return;
+        }
+        if let Err(err) = self.append(&visit) {
+            log::error!("failed to record the visit: {err:#}");
+        }
+    }
+
+    fn append(&self, visit: &Visit) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("failed to open {}", self.path.display()))?;
+        let mut text = String::new();
+        if file.metadata()?.len() == 0 {
+            text.push_str(HEADER);
+            text.push('\n');
+        }
+        text.push_str(&visit.csv_lines());
+        file.write_all(text.as_bytes()).with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+impl Visit {
+    fn csv_lines(&self) -> String {
+        self.exhibits.iter()
+            .map(|(name, stats)| {
+                format!(
+                    "{},{:.0},{},{:.1},{:.1},{}\n",
+                    self.start,
+                    self.duration,
+                    csv_field(name),
+                    stats.near,
+                    stats.looked_at,
+                    stats.option_changes,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Quotes `field` if it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn visit_lines() {
+        let path = std::env::temp_dir().join(format!("shaderpixel_stats_{}.csv", std::process::id()));
+        let mut stats = VisitorStats::new(&path);
+        stats.update(1., ["Mirror"], None, None);
+        assert!(stats.visit.is_none());
+        stats.input();
+        stats.update(1., ["Mandelbox", "Mirror"], Some("Mandelbox"), Some("Mandelbox"));
+        stats.update(1., ["Mandelbox"], Some("Say \"hi\", world"), Some("Mandelbox"));
+        stats.update(0.5, [], None, None);
+        stats.update(0.5, [], None, Some("Mandelbox"));
+        let visit = stats.visit.as_mut().unwrap();
+        visit.start = 10;
+        assert_eq!(
+            visit.csv_lines(),
+            "10,3,Mandelbox,2.0,1.0,2\n10,3,Mirror,1.0,0.0,0\n10,3,\"Say \"\"hi\"\", world\",0.0,1.0,0\n",
+        );
+        stats.update(IDLE_TIMEOUT, [], None, None);
+        assert!(stats.visit.is_none());
+        let written = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+        let written = written.unwrap();
+        assert!(written.starts_with(HEADER));
+        assert!(written.contains("\n10,"));
+    }
+}
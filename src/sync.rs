@@ -0,0 +1,152 @@
+//! Synchronizes several instances over UDP, e.g. for a video wall showing the same walkthrough.
+//!
+//! One instance leads and sends its camera, time and option values to an address,
+//! which can be a broadcast address. Followers listen on that port and copy the state.
+//! The role is set with `SHADERPIXEL_SYNC`, either `lead:<target address>`
+//! like `lead:255.255.255.255:7879` or `follow:<listen address>` like `follow:0.0.0.0:7879`.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// Minimum time between two packets of the leader.
+const SEND_INTERVAL: Duration = Duration::from_millis(1000 / 60);
+/// Maximum size of a packet, the limit of UDP.
+const MAX_PACKET_SIZE: usize = 65507;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncState {
+    pub time: f32,
+    pub skybox_rotation_angle: f32,
+    pub position: [f32; 3],
+    pub angle_yaw: f32,
+    pub angle_pitch: f32,
+    /// Option values of all exhibits with options by name, see `ArtObject::option_values`.
+    pub options: HashMap<String, Vec<f32>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Packet {
+    /// Increases with each packet so followers can drop reordered ones.
+    seq: u64,
+    state: SyncState,
+}
+
+pub enum SyncNode {
+    Leader {
+        socket: UdpSocket,
+        target: SocketAddr,
+        seq: u64,
+        last_sent: Option<Instant>,
+    },
+    Follower {
+        socket: UdpSocket,
+        last_seq: Option<u64>,
+        buffer: Vec<u8>,
+    },
+}
+
+impl SyncNode {
+    /// Creates a leader or follower from a spec like `lead:<address>` or `follow:<address>`.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        let (role, addr) = spec.split_once(':').context("expected lead:<address> or follow:<address>")?;
+        let addr = addr.parse::<SocketAddr>().with_context(|| format!("invalid address {addr}"))?;
+        match role {
+            "lead" => {
+                let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+                socket.set_broadcast(true)?;
+                socket.set_nonblocking(true)?;
+                log::info!("leading synchronization, sending to {addr}");
+                Ok(Self::Leader { socket, target: addr, seq: 0, last_sent: None })
+            }
+            "follow" => {
+                let socket = UdpSocket::bind(addr).with_context(|| format!("failed to bind {addr}"))?;
+                socket.set_nonblocking(true)?;
+                log::info!("following synchronization on {addr}");
+                Ok(Self::Follower { socket, last_seq: None, buffer: vec![0; MAX_PACKET_SIZE] })
+            }
+            role => anyhow::bail!("unknown role {role}, expected lead or follow"),
+        }
+    }
+
+    /// Sends the state if this is the leader and enough time passed since the last packet.
+    /// `state` is only called if a packet is sent.
+    pub fn send(&mut self, state: impl FnOnce() -> SyncState) {
+        let Self::Leader { socket, target, seq, last_sent } = self else { return };
+        let now = Instant::now();
+        if last_sent.is_some_and(|last_sent| now - last_sent < SEND_INTERVAL) {
+            return;
+        }
+        *last_sent = Some(now);
+        *seq += 1;
+        let packet = Packet { seq: *seq, state: state() };
+        let result = serde_json::to_vec(&packet)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(socket.send_to(&bytes, *target)?));
+        if let Err(err) = result {
+            log::warn!("failed to send sync packet: {err}");
+        }
+    }
+
+    /// Returns the newest state received since the last call if this is a follower.
+    pub fn receive(&mut self) -> Option<SyncState> {
+        let Self::Follower { socket, last_seq, buffer } = self else { return None };
+        let mut newest = None;
+        loop {
+            let len = match socket.recv(buffer) {
+                Ok(len) => len,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    log::warn!("failed to receive sync packet: {err}");
+                    break;
+                }
+            };
+            let packet = match serde_json::from_slice::<Packet>(&buffer[..len]) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    log::warn!("received invalid sync packet: {err}");
+                    continue;
+                }
+            };
+            // a restarted leader starts again at 1
+            if last_seq.is_some_and(|last_seq| packet.seq <= last_seq && packet.seq != 1) {
+                continue;
+            }
+            *last_seq = Some(packet.seq);
+            newest = Some(packet.state);
+        }
+        newest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leader_to_follower() {
+        let mut follower = SyncNode::from_spec("follow:127.0.0.1:0").unwrap();
+        let SyncNode::Follower { socket, .. } = &follower else { unreachable!() };
+        let addr = socket.local_addr().unwrap();
+        let mut leader = SyncNode::from_spec(&format!("lead:{addr}")).unwrap();
+
+        let state = || SyncState {
+            time: 1.5,
+            options: HashMap::from([("Mandelbulb".to_owned(), vec![1., 2.])]),
+            ..Default::default()
+        };
+        leader.send(state);
+        let mut received = None;
+        for _ in 0..100 {
+            received = follower.receive();
+            if received.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(received, Some(state()));
+    }
+}
@@ -0,0 +1,258 @@
+//! Text rendered into the scene, used for the plaques next to the exhibits.
+//!
+//! The glyphs of the default interface font are rasterized once at startup and stored as
+//! signed distance fields in an atlas, so the text stays sharp from any distance.
+//! Text is turned into a model with one quad per glyph whose texture coordinates point
+//! into the atlas, `assets/shaders/plaque.frag` draws it.
+
+use crate::model::obj::{NormalizedObj, Vertex};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ab_glyph::{Font, FontRef, ScaleFont};
+use anyhow::Context;
+use glam::Vec2;
+use image::{Rgba, RgbaImage};
+
+/// Height of the rasterized glyphs in pixels.
+const GLYPH_SIZE: f32 = 32.;
+/// Distance in pixels that is covered by the distance field on each side of an outline.
+const SPREAD: i32 = 4;
+const ATLAS_WIDTH: u32 = 512;
+/// Texture coordinates of the plaque background, the shader draws them without the atlas.
+const BACKGROUND_UV: [f32; 2] = [-1., -1.];
+/// Glyphs are lifted above the background to avoid z-fighting.
+const GLYPH_OFFSET: f32 = 0.002;
+
+const PLAQUE_WIDTH: f32 = 0.5;
+const PLAQUE_MARGIN: f32 = 0.03;
+const TITLE_HEIGHT: f32 = 0.05;
+const TEXT_HEIGHT: f32 = 0.028;
+const LINE_SPACING: f32 = 1.25;
+
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    uv_min: [f32; 2],
+    uv_max: [f32; 2],
+    /// Top left corner of the quad relative to the pen position in units of the font height, y up.
+    offset: Vec2,
+    size: Vec2,
+    advance: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct FontAtlas {
+    /// The distance field in the red channel, 0.5 is on the outline.
+    image: Arc<RgbaImage>,
+    glyphs: HashMap<char, Glyph>,
+}
+
+impl FontAtlas {
+    /// Builds the atlas of the printable ASCII characters from the default egui font.
+    pub fn new() -> anyhow::Result<Self> {
+        let definitions = egui::FontDefinitions::default();
+        let name = definitions.families.get(&egui::FontFamily::Proportional)
+            .and_then(|names| names.first())
+            .context("no default proportional font")?;
+        let data = definitions.font_data.get(name).context("missing data of default font")?;
+        let font = FontRef::try_from_slice_and_index(&data.font, data.index)
+            .with_context(|| format!("invalid font {name}"))?;
+        let scaled = font.as_scaled(GLYPH_SIZE);
+
+        let mut fields = Vec::new();
+        for c in ' '..='~' {
+            let glyph_id = font.glyph_id(c);
+            let advance = scaled.h_advance(glyph_id) / GLYPH_SIZE;
+            let field = font.outline_glyph(glyph_id.with_scale(GLYPH_SIZE)).map(|outlined| {
+                let bounds = outlined.px_bounds();
+                let width = bounds.width() as usize;
+                let height = bounds.height() as usize;
+                let mut coverage = vec![0.; width * height];
+                outlined.draw(|x, y, value| {
+                    if let Some(pixel) = coverage.get_mut(y as usize * width + x as usize) {
+                        *pixel = value;
+                    }
+                });
+                let top_left = Vec2::new(bounds.min.x - SPREAD as f32, -bounds.min.y + SPREAD as f32);
+                (distance_field(&coverage, width, height), top_left)
+            });
+            fields.push((c, advance, field));
+        }
+
+        // shelf packing, glyphs are placed in rows from left to right
+        let mut positions = Vec::with_capacity(fields.len());
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for (_, _, field) in fields.iter() {
+            let Some((field, _)) = field else {
+                positions.push((0, 0));
+                continue;
+            };
+            if x + field.width() > ATLAS_WIDTH {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+            positions.push((x, y));
+            x += field.width();
+            row_height = row_height.max(field.height());
+        }
+
+        let mut image = RgbaImage::from_pixel(ATLAS_WIDTH, y + row_height, Rgba([0, 0, 0, 255]));
+        let extent = Vec2::new(image.width() as f32, image.height() as f32);
+        let mut glyphs = HashMap::with_capacity(fields.len());
+        for ((c, advance, field), (x, y)) in fields.into_iter().zip(positions) {
+            let glyph = match field {
+                Some((field, top_left)) => {
+                    image::imageops::replace(&mut image, &field, x as i64, y as i64);
+                    let size = Vec2::new(field.width() as f32, field.height() as f32);
+                    let uv_min = Vec2::new(x as f32, y as f32) / extent;
+                    Glyph {
+                        uv_min: uv_min.into(),
+                        uv_max: (uv_min + size / extent).into(),
+                        offset: top_left / GLYPH_SIZE,
+                        size: size / GLYPH_SIZE,
+                        advance,
+                    }
+                }
+                None => Glyph {
+                    uv_min: [0.; 2],
+                    uv_max: [0.; 2],
+                    offset: Vec2::ZERO,
+                    size: Vec2::ZERO,
+                    advance,
+                },
+            };
+            glyphs.insert(c, glyph);
+        }
+
+        Ok(Self { image: Arc::new(image), glyphs })
+    }
+
+    pub fn image(&self) -> Arc<RgbaImage> {
+        self.image.clone()
+    }
+
+    /// Builds a plaque with the title and the wrapped description, centered on the origin
+    /// in the x-y-plane and readable from positive z. Units are meters.
+    pub fn plaque_model(&self, title: &str, description: &str) -> NormalizedObj {
+        let text_width = PLAQUE_WIDTH - 2. * PLAQUE_MARGIN;
+        let mut lines = vec![(title.to_owned(), TITLE_HEIGHT)];
+        let description = self.wrap(description, TEXT_HEIGHT, text_width);
+        lines.extend(description.into_iter().map(|line| (line, TEXT_HEIGHT)));
+        let text_height = lines.iter().map(|(_, height)| height * LINE_SPACING).sum::<f32>();
+        let half_extent = Vec2::new(PLAQUE_WIDTH, text_height + 2. * PLAQUE_MARGIN) / 2.;
+
+        let mut model = NormalizedObj { has_tex_coords: true, has_normals: true, ..Default::default() };
+        push_quad(&mut model, -half_extent, half_extent, 0., BACKGROUND_UV, BACKGROUND_UV);
+        let mut pen = Vec2::new(-half_extent.x + PLAQUE_MARGIN, half_extent.y - PLAQUE_MARGIN);
+        for (line, height) in lines {
+            pen.y -= height;
+            self.push_line(&mut model, &line, height, pen);
+            pen.y -= height * (LINE_SPACING - 1.);
+        }
+        model
+    }
+
+    fn glyph(&self, c: char) -> &Glyph {
+        self.glyphs.get(&c).unwrap_or_else(|| &self.glyphs[&'?'])
+    }
+
+    fn line_width(&self, line: &str, height: f32) -> f32 {
+        line.chars().map(|c| self.glyph(c).advance * height).sum()
+    }
+
+    /// Splits `text` into lines no wider than `max_width`, words are never broken.
+    fn wrap(&self, text: &str, height: f32, max_width: f32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.lines() {
+            let mut line = String::new();
+            for word in paragraph.split_whitespace() {
+                let candidate = if line.is_empty() { word.to_owned() } else { format!("{line} {word}") };
+                if line.is_empty() || self.line_width(&candidate, height) <= max_width {
+                    line = candidate;
+                } else {
+                    lines.push(std::mem::replace(&mut line, word.to_owned()));
+                }
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    fn push_line(&self, model: &mut NormalizedObj, line: &str, height: f32, mut pen: Vec2) {
+        for c in line.chars() {
+            let glyph = self.glyph(c);
+            if glyph.size != Vec2::ZERO {
+                let top_left = pen + glyph.offset * height;
+                let min = Vec2::new(top_left.x, top_left.y - glyph.size.y * height);
+                let max = Vec2::new(top_left.x + glyph.size.x * height, top_left.y);
+                let uv_min = [glyph.uv_min[0], glyph.uv_max[1]];
+                let uv_max = [glyph.uv_max[0], glyph.uv_min[1]];
+                push_quad(model, min, max, GLYPH_OFFSET, uv_min, uv_max);
+            }
+            pen.x += glyph.advance * height;
+        }
+    }
+}
+
+/// Adds a quad facing positive z, `uv_min` belongs to the corner `min`.
+fn push_quad(model: &mut NormalizedObj, min: Vec2, max: Vec2, z: f32, uv_min: [f32; 2], uv_max: [f32; 2]) {
+    let start = model.vertices.len() as u32;
+    let corners = [
+        ([min.x, min.y], [uv_min[0], uv_min[1]]),
+        ([max.x, min.y], [uv_max[0], uv_min[1]]),
+        ([max.x, max.y], [uv_max[0], uv_max[1]]),
+        ([min.x, max.y], [uv_min[0], uv_max[1]]),
+    ];
+    for ([x, y], tex_coords) in corners {
+        model.vertices.push(Vertex { pos_coords: [x, y, z], tex_coords, normal: [0., 0., 1.] });
+    }
+    model.indices.extend([0, 1, 2, 2, 3, 0].map(|i| start + i));
+}
+
+/// Converts the coverage of a glyph into a distance field with a border of `SPREAD` pixels.
+/// Distances are searched brute force, which is fast enough for the small glyphs.
+fn distance_field(coverage: &[f32], width: usize, height: usize) -> RgbaImage {
+    let inside = |x: i32, y: i32| {
+        x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height
+            && coverage[y as usize * width + x as usize] > 0.5
+    };
+    let padded_width = width as u32 + 2 * SPREAD as u32;
+    let padded_height = height as u32 + 2 * SPREAD as u32;
+    RgbaImage::from_fn(padded_width, padded_height, |px, py| {
+        let (x, y) = (px as i32 - SPREAD, py as i32 - SPREAD);
+        let is_inside = inside(x, y);
+        let mut min_dist_sqr = (SPREAD as f32 + 0.5).powi(2);
+        for dy in -SPREAD..=SPREAD {
+            for dx in -SPREAD..=SPREAD {
+                if inside(x + dx, y + dy) != is_inside {
+                    min_dist_sqr = min_dist_sqr.min((dx * dx + dy * dy) as f32);
+                }
+            }
+        }
+        // the outline lies half way between the pixel centers
+        let dist = (min_dist_sqr.sqrt() - 0.5).max(0.);
+        let signed = if is_inside { dist } else { -dist };
+        let value = (0.5 + signed / (2. * SPREAD as f32)).clamp(0., 1.);
+        Rgba([(value * 255.).round() as u8, 0, 0, 255])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_field_of_square() {
+        let coverage = [1.; 4];
+        let field = distance_field(&coverage, 2, 2);
+        let size = 2 + 2 * SPREAD as u32;
+        assert_eq!(field.dimensions(), (size, size));
+        let value = |x, y| field.get_pixel(x, y)[0];
+        // inside, on the outline and far outside
+        assert!(value(SPREAD as u32, SPREAD as u32) > 128);
+        assert!(value(SPREAD as u32 - 1, SPREAD as u32) < 128);
+        assert_eq!(value(0, 0), 0);
+    }
+}
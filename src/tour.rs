@@ -0,0 +1,238 @@
+//! Guided tour for first-time visitors.
+//!
+//! The tour is a sequence of steps, each moves the camera to a pose or in front of an exhibit and
+//! may highlight a part of the interface while a window explains it. The steps are defined in the
+//! layout, see `crate::model::env_generator::Layout::tour`. Without them the tour introduces the
+//! controls and then visits the visible exhibits. It is started with T or from the welcome window,
+//! the arrow keys go to the next and the previous step.
+
+use crate::{
+    animation::{Easing, Interpolate},
+    art::ArtObject,
+    camera::Camera,
+    settings::Bookmark,
+};
+
+use std::f32::consts::{PI, TAU};
+
+use serde::{Deserialize, Serialize};
+
+/// Seconds the camera takes to move to the pose of a step.
+const MOVE_DURATION: f32 = 2.;
+
+/// Part of the interface that a step draws attention to, it is opened if it is hidden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Highlight {
+    /// The crosshair and the name of the exhibit in the center of the screen.
+    Hud,
+    Options,
+    /// The options of the exhibit of the step or the nearest one.
+    ArtOptions,
+    /// The exhibit browser.
+    Exhibits,
+    Timeline,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TourStep {
+    pub title: String,
+    pub text: String,
+    /// Name of the exhibit the camera is moved in front of.
+    pub art: Option<String>,
+    /// Pose the camera is moved to, replaces the one in front of `art`.
+    pub pose: Option<Bookmark>,
+    pub highlight: Option<Highlight>,
+}
+
+/// Actions on the tour requested with the keys or in the GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourAction {
+    Start,
+    Previous,
+    Next,
+    End,
+}
+
+impl TourAction {
+    /// Applies the action to `tour`. `Start` uses `steps` or the generated ones if there are none,
+    /// going past the last step ends the tour.
+    pub fn apply(self, tour: &mut Option<Tour>, steps: &[TourStep], art_objects: &[ArtObject]) {
+        let end = match (self, tour.as_mut()) {
+            (Self::Start, _) => {
+                let steps = if steps.is_empty() { Tour::generated_steps(art_objects) } else { steps.to_vec() };
+                *tour = Tour::new(steps);
+                if tour.is_some() {
+                    log::info!("starting the tour");
+                }
+                false
+            }
+            (Self::Previous, Some(current)) => {
+                current.previous();
+                false
+            }
+            (Self::Next, Some(current)) => !current.next(),
+            (Self::End, Some(_)) => true,
+            (_, None) => false,
+        };
+        if end {
+            *tour = None;
+            log::info!("ending the tour");
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Tour {
+    steps: Vec<TourStep>,
+    current: usize,
+    /// Set when the step changed, the camera starts moving on the next `update`.
+    step_changed: bool,
+    movement: Option<Movement>,
+}
+
+#[derive(Debug)]
+struct Movement {
+    from: Bookmark,
+    to: Bookmark,
+    progress: f32,
+}
+
+impl Tour {
+    /// `None` if there are no steps.
+    pub fn new(steps: Vec<TourStep>) -> Option<Self> {
+        (!steps.is_empty()).then_some(Self { steps, current: 0, step_changed: true, movement: None })
+    }
+
+    /// Introduces the controls and visits the visible exhibits in the order they are listed.
+    pub fn generated_steps(art_objects: &[ArtObject]) -> Vec<TourStep> {
+        let mut steps = vec![
+            TourStep {
+                title: "Welcome to shaderpixel".to_owned(),
+                text: "This tour shows you around. Press the right arrow key for the next step, the left one \
+                    for the previous step and T to end the tour at any time."
+                    .to_owned(),
+                ..Default::default()
+            },
+            TourStep {
+                title: "Moving around".to_owned(),
+                text: "Walk with WASD and look around by dragging with the left mouse button. Hold tab to \
+                    sprint, the scroll wheel changes the speed and left control toggles fly mode."
+                    .to_owned(),
+                ..Default::default()
+            },
+            TourStep {
+                title: "Exhibits".to_owned(),
+                text: "The exhibit in the center of the screen is named below the crosshair. \
+                    Press E to open its options, F to teleport to it and V to show a flat one full screen."
+                    .to_owned(),
+                highlight: Some(Highlight::Hud),
+                ..Default::default()
+            },
+            TourStep {
+                title: "Exhibit browser".to_owned(),
+                text: "F3 lists all exhibits, go to one or turn it off from there.".to_owned(),
+                highlight: Some(Highlight::Exhibits),
+                ..Default::default()
+            },
+            TourStep {
+                title: "Options".to_owned(),
+                text: "F2 toggles the interface with the options of the gallery, like the quality and the \
+                    movement speed."
+                    .to_owned(),
+                highlight: Some(Highlight::Options),
+                ..Default::default()
+            },
+        ];
+        let exhibits = art_objects.iter()
+            .filter(|art| art.enable_pipeline && !art.hidden && art.passes.scene && !art.is_mirror);
+        for art in exhibits {
+            steps.push(TourStep {
+                title: art.name.clone(),
+                text: art.description.clone().unwrap_or_default(),
+                art: Some(art.name.clone()),
+                highlight: (!art.options.is_empty()).then_some(Highlight::ArtOptions),
+                ..Default::default()
+            });
+        }
+        steps
+    }
+
+    pub fn step(&self) -> &TourStep {
+        &self.steps[self.current]
+    }
+
+    /// Index of the current step and the number of steps.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.current, self.steps.len())
+    }
+
+    /// Goes to the next step, returns `false` if the current one is the last.
+    pub fn next(&mut self) -> bool {
+        if self.current + 1 >= self.steps.len() {
+            return false;
+        }
+        self.current += 1;
+        self.step_changed = true;
+        true
+    }
+
+    pub fn previous(&mut self) {
+        if self.current > 0 {
+            self.current -= 1;
+            self.step_changed = true;
+        }
+    }
+
+    /// Moves the camera towards the pose of the current step, it is free once it arrived.
+    pub fn update(&mut self, elapsed: f32, art_objects: &[ArtObject], camera: &mut Camera) {
+        if std::mem::take(&mut self.step_changed) {
+            let from = Bookmark::from_camera(camera);
+            self.movement = self.target(art_objects, camera).map(|to| Movement {
+                from,
+                to: Bookmark { yaw: from.yaw + (to.yaw - from.yaw + PI).rem_euclid(TAU) - PI, ..to },
+                progress: 0.,
+            });
+        }
+        let Some(movement) = self.movement.as_mut() else { return };
+        movement.progress = (movement.progress + elapsed / MOVE_DURATION).min(1.);
+        let t = Easing::EaseInOut.apply(movement.progress);
+        movement.from.interpolate(&movement.to, t).apply(camera);
+        if movement.progress >= 1. {
+            self.movement = None;
+        }
+    }
+
+    fn target(&self, art_objects: &[ArtObject], camera: &Camera) -> Option<Bookmark> {
+        let step = self.step();
+        if step.pose.is_some() {
+            return step.pose;
+        }
+        let name = step.art.as_ref()?;
+        let Some(art) = art_objects.iter().find(|art| &art.name == name) else {
+            log::warn!("the tour visits the exhibit {name} that does not exist");
+            return None;
+        };
+        let mut camera = *camera;
+        camera.view_target(art.position());
+        Some(Bookmark::from_camera(&camera))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_stay_in_range() {
+        let step = |title: &str| TourStep { title: title.to_owned(), ..Default::default() };
+        assert!(Tour::new(Vec::new()).is_none());
+        let mut tour = Tour::new(vec![step("a"), step("b")]).unwrap();
+        tour.previous();
+        assert_eq!(tour.progress(), (0, 2));
+        assert!(tour.next());
+        assert_eq!(tour.step().title, "b");
+        assert!(!tour.next());
+        assert_eq!(tour.progress(), (1, 2));
+    }
+}
@@ -0,0 +1,271 @@
+//! Volumes around exhibits that run actions when the camera enters or leaves them.
+//!
+//! The triggers of the layout are added to the built-in ones of the exhibits and stored in its `triggers`, e.g.
+//! `{ "Portal": [{ "volume": { "Sphere": { "radius": 3 } }, "on_enter": [{ "PlaySound": "Portal" }],
+//! "on_exit": [{ "StopSound": "Portal" }] }] }`.
+//!
+//! Two exhibits become a pair of portals with a `Crossing` trigger on each that teleports to the
+//! other one on enter and on exit, so passing through either side of one leaves the other on the same side.
+//! `assets/layout.json` pairs two of the paintings this way.
+//! The built-in portal uses a `Crossing` trigger with `EnterPortal` and `LeavePortal` instead.
+
+use crate::{art::ArtObject, camera::Camera};
+
+use std::collections::BTreeMap;
+use std::f32::consts::{FRAC_1_SQRT_2, PI};
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Volume {
+    /// The container of the exhibit expanded by `margin` in world units.
+    Container {
+        #[serde(default)]
+        margin: f32,
+    },
+    /// A sphere around the position of the exhibit.
+    Sphere { radius: f32 },
+    /// The quad of the exhibit like the portal, every pass through it toggles between inside and outside.
+    Crossing,
+}
+
+/// Actions name the exhibit they act on, which can be a different one than the trigger's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    /// Turns the exhibit on or off like the checkbox in the exhibit browser.
+    Enable(String),
+    Disable(String),
+    Toggle(String),
+    PlaySound(String),
+    StopSound(String),
+    /// Plays the animation of the exhibit from the start.
+    StartAnimation(String),
+    StopAnimation(String),
+    /// Moves the camera from the exhibit of the trigger to the same place relative to this one,
+    /// mirrored so that entering the front of one leaves the front of the other, see `portal_transform`.
+    Teleport(String),
+    /// Shows the inside of the exhibit on the portal box while the camera is in it.
+    EnterPortal(String),
+    LeavePortal(String),
+}
+
+impl Action {
+    /// `source` is the transform of the exhibit whose trigger runs the action.
+    fn apply(&self, art_objects: &mut [ArtObject], camera: &mut Camera, source: Mat4) {
+        let (Self::Enable(name) | Self::Disable(name) | Self::Toggle(name) | Self::PlaySound(name)
+            | Self::StopSound(name) | Self::StartAnimation(name) | Self::StopAnimation(name)
+            | Self::Teleport(name) | Self::EnterPortal(name) | Self::LeavePortal(name)) = self;
+        let Some(art) = art_objects.iter_mut().find(|art| art.name == *name) else {
+            log::warn!("trigger action {self:?} on an exhibit that does not exist");
+            return;
+        };
+        match self {
+            Self::Enable(_) => art.disabled = false,
+            Self::Disable(_) => art.disabled = true,
+            Self::Toggle(_) => art.disabled = !art.disabled,
+            Self::PlaySound(_) => art.sound_muted = false,
+            Self::StopSound(_) => art.sound_muted = true,
+            Self::StartAnimation(_) => if let Some(animation) = art.animation.as_mut() {
+                animation.time = 0.;
+                animation.playing = true;
+            }
+            Self::StopAnimation(_) => if let Some(animation) = art.animation.as_mut() {
+                animation.playing = false;
+            }
+            Self::Teleport(_) => camera.transform(portal_transform(source, art.data.matrix)),
+            Self::EnterPortal(_) => art.data.inside_portal = true,
+            Self::LeavePortal(_) => art.data.inside_portal = false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trigger {
+    pub volume: Volume,
+    #[serde(default)]
+    pub on_enter: Vec<Action>,
+    #[serde(default)]
+    pub on_exit: Vec<Action>,
+    /// Whether the camera was inside after the last update.
+    #[serde(skip)]
+    pub inside: bool,
+    /// Whether the trigger was added by the builder, these are kept when the layout changes.
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+/// Whether the segment from `p0` to `p1` passes through the quad of an exhibit with the transform `matrix`.
+pub fn goes_through_rect(p0: Vec3, p1: Vec3, matrix: Mat4) -> bool {
+    let dir = p1 - p0;
+    let p_norm = matrix.inverse().transpose().transform_vector3(Vec3::new(0., 0., 1.));
+    let p_pos = matrix.transform_point3(Vec3::new(0., 0., 0.));
+    let dot = p_norm.dot(dir);
+    if dot == 0.0 {
+        return false; // segment [p0,p1] parallel to plane
+    }
+    let w = p0 - p_pos;
+    let fac = -p_norm.dot(w) / dot;
+    if !(0.0..1.0).contains(&fac) {
+        return false; // segment [p0,p1] not passing through plane
+    }
+    let inter = p0 + dir * fac;
+    let corner0 = matrix.transform_point3(Vec3::new(-1., -1., 0.) * FRAC_1_SQRT_2);
+    let corner1 = matrix.transform_point3(Vec3::new( 1.,  1., 0.) * FRAC_1_SQRT_2);
+    (corner0 - inter).dot(corner1 - inter) < 0.0
+}
+
+/// Maps a point behind the quad `from` to the point in front of the quad `to`.
+pub fn portal_transform(from: Mat4, to: Mat4) -> Mat4 {
+    to * Mat4::from_rotation_y(PI) * from.inverse()
+}
+
+/// Checks the triggers of all exhibits for the camera that moved from `old_position` to its position
+/// and runs the actions of the ones it entered or left.
+pub fn update_triggers(art_objects: &mut [ArtObject], camera: &mut Camera, old_position: Vec3) {
+    let new_position = camera.position;
+    let mut actions = Vec::new();
+    for art in art_objects.iter_mut().filter(|art| !art.triggers.is_empty()) {
+        let matrix = art.data.matrix;
+        let mut triggers = std::mem::take(&mut art.triggers);
+        for trigger in triggers.iter_mut() {
+            let inside = match trigger.volume {
+                Volume::Container { margin } => art.contains(new_position, margin),
+                Volume::Sphere { radius } => art.position().distance(new_position) <= radius,
+                Volume::Crossing => trigger.inside ^ goes_through_rect(old_position, new_position, matrix),
+            };
+            if inside != trigger.inside {
+                trigger.inside = inside;
+                let fired = if inside { &trigger.on_enter } else { &trigger.on_exit };
+                actions.extend(fired.iter().map(|action| (action.clone(), matrix)));
+            }
+        }
+        art.triggers = triggers;
+    }
+    for (action, source) in actions {
+        action.apply(art_objects, camera, source);
+    }
+}
+
+/// Replaces the triggers of the previous layout with `triggers` by exhibit name,
+/// the built-in ones of the exhibits are kept.
+pub fn set_triggers(triggers: &BTreeMap<String, Vec<Trigger>>, art_objects: &mut [ArtObject]) {
+    for art in art_objects.iter_mut() {
+        art.triggers.retain(|trigger| trigger.builtin);
+    }
+    for (name, triggers) in triggers {
+        match art_objects.iter_mut().find(|art| art.name == *name) {
+            Some(art) => art.triggers.extend(triggers.iter().cloned()),
+            None => log::warn!("triggers for {name}, which does not exist"),
+        }
+    }
+}
+
+/// Takes the camera out of all portals after it jumped, without running any actions.
+pub fn leave_portals(art_objects: &mut [ArtObject]) {
+    for art in art_objects.iter_mut() {
+        art.data.inside_portal = false;
+        for trigger in art.triggers.iter_mut().filter(|trigger| trigger.volume == Volume::Crossing) {
+            trigger.inside = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::art::ArtData;
+    use glam::Quat;
+
+    #[test]
+    fn enter_and_exit() {
+        let trigger = Trigger {
+            volume: Volume::Sphere { radius: 2. },
+            on_enter: vec![Action::Disable("b".to_owned())],
+            on_exit: vec![Action::Enable("b".to_owned())],
+            inside: false,
+            builtin: false,
+        };
+        let mut art_objects = vec![
+            ArtObject {
+                name: "a".to_owned(),
+                data: ArtData::new(Mat4::from_translation(Vec3::X * 5.)),
+                triggers: vec![trigger],
+                ..Default::default()
+            },
+            ArtObject { name: "b".to_owned(), ..Default::default() },
+        ];
+        let mut camera = Camera::default();
+        let mut move_to = |art_objects: &mut [ArtObject], position: Vec3| {
+            let old_position = camera.position;
+            camera.position = position;
+            update_triggers(art_objects, &mut camera, old_position);
+        };
+        move_to(&mut art_objects, Vec3::ZERO);
+        assert!(!art_objects[1].disabled);
+        move_to(&mut art_objects, Vec3::X * 4.);
+        assert!(art_objects[1].disabled);
+        // staying inside does not run the actions again
+        art_objects[1].disabled = false;
+        move_to(&mut art_objects, Vec3::X * 4.5);
+        assert!(!art_objects[1].disabled);
+        move_to(&mut art_objects, Vec3::ZERO);
+        assert!(!art_objects[1].disabled);
+    }
+
+    #[test]
+    fn crossing_toggles() {
+        let matrix = Mat4::IDENTITY;
+        assert!(goes_through_rect(Vec3::new(0., 0., 1.), Vec3::new(0., 0., -1.), matrix));
+        assert!(!goes_through_rect(Vec3::new(2., 0., 1.), Vec3::new(2., 0., -1.), matrix));
+        assert!(!goes_through_rect(Vec3::new(0., 0., 1.), Vec3::new(0., 0., 0.5), matrix));
+    }
+
+    #[test]
+    fn enter_and_leave_portal() {
+        let mut art_objects = vec![ArtObject::builder()
+            .name("Portal")
+            .trigger(
+                Volume::Crossing,
+                vec![Action::EnterPortal("Portal".to_owned())],
+                vec![Action::LeavePortal("Portal".to_owned())],
+            )
+            .without_pipeline()
+            .build()
+            .unwrap()];
+        let mut camera = Camera { position: Vec3::new(0., 0., -0.1), ..Default::default() };
+        update_triggers(&mut art_objects, &mut camera, Vec3::new(0., 0., 0.1));
+        assert!(art_objects[0].data.inside_portal);
+        // a jump out of the portal resets the crossing, so the next one enters again
+        leave_portals(&mut art_objects);
+        camera.position = Vec3::new(0., 0., 0.1);
+        update_triggers(&mut art_objects, &mut camera, Vec3::new(0., 0., -0.1));
+        assert!(art_objects[0].data.inside_portal);
+        set_triggers(&BTreeMap::new(), &mut art_objects);
+        assert_eq!(art_objects[0].triggers.len(), 1);
+    }
+
+    #[test]
+    fn teleport_through_portal() {
+        let portal = |name: &str, to: &str, matrix: Mat4| ArtObject {
+            name: name.to_owned(),
+            data: ArtData::new(matrix),
+            triggers: vec![Trigger {
+                volume: Volume::Crossing,
+                on_enter: vec![Action::Teleport(to.to_owned())],
+                on_exit: vec![Action::Teleport(to.to_owned())],
+                inside: false,
+                builtin: false,
+            }],
+            ..Default::default()
+        };
+        let b_matrix = Mat4::from_rotation_translation(Quat::from_rotation_y(PI / 2.), Vec3::X * 10.);
+        let mut art_objects = vec![portal("a", "b", Mat4::IDENTITY), portal("b", "a", b_matrix)];
+        // walk forward through the front of a, looking along -z
+        let mut camera = Camera { position: Vec3::new(0., 0., -0.1), ..Default::default() };
+        update_triggers(&mut art_objects, &mut camera, Vec3::new(0., 0., 0.1));
+        assert!(camera.position.distance(Vec3::new(10.1, 0., 0.)) < 1e-5, "{}", camera.position);
+        // and come out of the front of b, which faces +x
+        let forward = camera.view_matrix().inverse().transform_vector3(Vec3::NEG_Z);
+        assert!(forward.distance(Vec3::X) < 1e-5, "{forward}");
+    }
+}
@@ -0,0 +1,247 @@
+//! Extra uniform buffer of an exhibit with named fields, bound at binding 12.
+//!
+//! The fields are laid out with the std140 rules, so a shader can declare the block
+//! returned by `UniformBlock::glsl`, which is also registered as `#include <sp/blocks/<file stem>.glsl>`
+//! with the file stem of the exhibit's name. Options bound to a field with `ArtOption::with_field`
+//! write their value into it, in addition to their slot in `ubo.options`. The initial values
+//! of the fields can be set in the layout, see `set_uniform_values`.
+
+use crate::art::{ArtObject, ArtOption};
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use anyhow::{bail, ensure, Context};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UniformType {
+    Float,
+    Int,
+    /// Stored as a 32 bit integer that is 0 or 1.
+    Bool,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl UniformType {
+    /// Number of floats an option bound to the field must provide.
+    pub fn components(self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::Bool => 1,
+            Self::Vec2 => 2,
+            Self::Vec3 => 3,
+            Self::Vec4 => 4,
+        }
+    }
+
+    /// Base alignment in bytes according to std140.
+    fn alignment(self) -> usize {
+        match self {
+            Self::Float | Self::Int | Self::Bool => 4,
+            Self::Vec2 => 8,
+            Self::Vec3 | Self::Vec4 => 16,
+        }
+    }
+
+    fn glsl(self) -> &'static str {
+        match self {
+            Self::Float => "float",
+            Self::Int => "int",
+            Self::Bool => "bool",
+            Self::Vec2 => "vec2",
+            Self::Vec3 => "vec3",
+            Self::Vec4 => "vec4",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UniformField {
+    pub name: Cow<'static, str>,
+    pub ty: UniformType,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct UniformBlock {
+    /// Name of the block in GLSL.
+    name: Cow<'static, str>,
+    fields: Vec<UniformField>,
+    /// Contents of the buffer, updated by `write`.
+    data: Vec<u8>,
+}
+
+impl UniformBlock {
+    /// Creates a block with the fields in the given order.
+    pub fn new<N: Into<Cow<'static, str>>>(
+        name: impl Into<Cow<'static, str>>,
+        fields: impl IntoIterator<Item = (N, UniformType)>,
+    ) -> Self {
+        let mut offset = 0;
+        let fields = fields.into_iter().map(|(name, ty)| {
+            offset = offset.next_multiple_of(ty.alignment());
+            let field = UniformField { name: name.into(), ty, offset };
+            offset += 4 * ty.components();
+            field
+        }).collect();
+        // the size of a block is rounded up to the alignment of a vec4
+        let size = offset.next_multiple_of(16).max(16);
+        Self { name: name.into(), fields, data: vec![0; size] }
+    }
+
+    pub fn fields(&self) -> &[UniformField] {
+        &self.fields
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Checks that every bound option has a field with a matching number of components.
+    pub fn validate(&self, options: &[ArtOption]) -> anyhow::Result<()> {
+        for option in options {
+            let Some(name) = option.field() else { continue };
+            let field = self.field(name)
+                .with_context(|| format!("option {} is bound to unknown field {name}", option.label()))?;
+            if field.ty.components() != option.ty.slot_count() {
+                bail!(
+                    "option {} has {} values but field {name} is a {}",
+                    option.label(),
+                    option.ty.slot_count(),
+                    field.ty.glsl(),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the values of the bound options into `data`.
+    pub fn write(&mut self, options: &[ArtOption]) {
+        for option in options {
+            let Some(field) = option.field().and_then(|name| self.field(name)) else { continue };
+            let (ty, offset) = (field.ty, field.offset);
+            let mut values = vec![0.; option.ty.slot_count().max(ty.components())];
+            option.ty.save_value(&mut values, &mut 0);
+            self.write_field(ty, offset, &values[..ty.components()]);
+        }
+    }
+
+    /// Writes `values` into the field `name`, they are converted like the values of bound options.
+    pub fn set(&mut self, name: &str, values: &[f32]) -> anyhow::Result<()> {
+        let field = self.field(name).with_context(|| format!("unknown field {name}"))?;
+        let (ty, offset) = (field.ty, field.offset);
+        ensure!(
+            values.len() == ty.components(),
+            "field {name} is a {} but {} values are given",
+            ty.glsl(),
+            values.len(),
+        );
+        self.write_field(ty, offset, values);
+        Ok(())
+    }
+
+    fn write_field(&mut self, ty: UniformType, offset: usize, values: &[f32]) {
+        for (i, value) in values.iter().enumerate() {
+            let bytes = match ty {
+                UniformType::Int => (value.round() as i32).to_ne_bytes(),
+                UniformType::Bool => ((*value >= 0.5) as u32).to_ne_bytes(),
+                _ => value.to_ne_bytes(),
+            };
+            let start = offset + 4 * i;
+            self.data[start..start + 4].copy_from_slice(&bytes);
+        }
+    }
+
+    /// Declaration of the block for shaders.
+    pub fn glsl(&self) -> String {
+        let mut glsl = format!("layout(set = 0, binding = 12) uniform {} {{\n", self.name);
+        for field in self.fields.iter() {
+            // writing to a String does not fail
+            let _ = writeln!(glsl, "    {} {};", field.ty.glsl(), field.name);
+        }
+        glsl.push_str("} custom;\n");
+        glsl
+    }
+
+    fn field(&self, name: &str) -> Option<&UniformField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Sets the fields of the uniform blocks to `values` by exhibit and field name.
+/// Options bound to a field are set to the value instead, so they show it and keep it.
+pub fn set_uniform_values(values: &BTreeMap<String, BTreeMap<String, Vec<f32>>>, art_objects: &mut [ArtObject]) {
+    for (name, fields) in values {
+        let Some(art) = art_objects.iter_mut().find(|art| art.name == *name) else {
+            log::warn!("uniform values for {name}, which does not exist");
+            continue;
+        };
+        let Some(block) = art.uniform_block.as_mut() else {
+            log::warn!("uniform values for {name}, which has no uniform block");
+            continue;
+        };
+        for (field, values) in fields {
+            if let Err(err) = block.set(field, values) {
+                log::warn!("ignoring the uniform value of {name}: {err:#}");
+                continue;
+            }
+            let option = art.options.iter_mut()
+                .find(|option| option.field() == Some(field.as_str()) && option.ty.slot_count() == values.len());
+            if let Some(option) = option {
+                option.ty.load_value(values, &mut 0);
+            }
+        }
+        art.save_options();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn std140_layout() {
+        let block = UniformBlock::new("Params", [
+            ("speed", UniformType::Float),
+            ("center", UniformType::Vec3),
+            ("count", UniformType::Int),
+            ("offset", UniformType::Vec2),
+        ]);
+        let offsets = block.fields().iter().map(|field| field.offset).collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 16, 28, 32]);
+        assert_eq!(block.data().len(), 48);
+    }
+
+    #[test]
+    fn write_bound_options() {
+        let mut block = UniformBlock::new("Params", [("speed", UniformType::Float), ("steps", UniformType::Int)]);
+        let options = [
+            ArtOption::slider_f32("Speed", 2.5, 0., 10.).with_field("speed"),
+            ArtOption::slider_i32("Steps", 7, 0, 10).with_field("steps"),
+            ArtOption::checkbox("Unbound", true),
+        ];
+        block.validate(&options).unwrap();
+        block.write(&options);
+        assert_eq!(block.data()[0..4], 2.5_f32.to_ne_bytes());
+        assert_eq!(block.data()[4..8], 7_i32.to_ne_bytes());
+
+        let wrong = [ArtOption::vec2("Speed", [0.; 2], 0., 1.).with_field("speed")];
+        assert!(block.validate(&wrong).is_err());
+        let unknown = [ArtOption::checkbox("Other", true).with_field("other")];
+        assert!(block.validate(&unknown).is_err());
+    }
+
+    #[test]
+    fn set_field_values() {
+        let mut block = UniformBlock::new("Params", [("enabled", UniformType::Bool), ("center", UniformType::Vec2)]);
+        block.set("enabled", &[1.]).unwrap();
+        block.set("center", &[0.5, -2.]).unwrap();
+        assert_eq!(block.data()[0..4], 1_u32.to_ne_bytes());
+        assert_eq!(block.data()[8..12], 0.5_f32.to_ne_bytes());
+        assert_eq!(block.data()[12..16], (-2_f32).to_ne_bytes());
+        assert!(block.set("center", &[1.]).is_err());
+        assert!(block.set("other", &[1.]).is_err());
+    }
+}
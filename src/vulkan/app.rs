@@ -1,33 +1,54 @@
 use crate::{
-    art::{ArtData, ArtObject},
+    art::{ArtData, ArtObject, TextureSource},
+    camera::FullscreenMapping,
     model::obj::NormalizedObj,
+    webcam::Webcam,
 };
 use super::{
+    art_cache::ArtCache,
     debug::*,
+    environment::EnvironmentMap,
+    global_set::{CameraUniforms, GlobalSet, GlobalUniforms},
     helpers::*,
     geometry::Geometry,
-    pipeline::{MyPipeline, MyPipelineCreateInfo, MyPipelines},
-    shader::{watch_shaders, HotShader},
-    texture::Texture,
+    noise::NoiseTextures,
+    pipeline::{FrameInfo, LayoutCache, MyPipeline, MyPipelineCreateInfo, MyPipelines, Split},
+    scene_input::SceneInput,
+    screenshot::Screenshot,
+    color_grading::{ColorGrading, Lut, OutputAdjustments},
+    stereo::{eye_views, Stereo, StereoMode},
+    shader::{register_include, watch_shaders, HotShader},
+    sound,
+    texture::{StreamingTexture, Texture, TextureLoader},
+    transition::{Transition, TransitionSettings, TransitionStyle},
     vertex::VertexType,
+    watchdog::{Watchdog, FRAME_TIMEOUT},
+    window_target::WindowTarget,
 };
 
 use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use egui_winit_vulkano::Gui;
 use glam::{Mat4, Vec3};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
 use shaderc::ShaderKind;
 use vulkano::{
     buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo},
     buffer::BufferUsage,
     command_buffer::allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
-    command_buffer::SecondaryAutoCommandBuffer,
+    command_buffer::{
+        AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, SecondaryAutoCommandBuffer,
+    },
     descriptor_set::allocator::StandardDescriptorSetAllocator,
-    device::{Device, DeviceCreateInfo, DeviceExtensions, DeviceFeatures, Queue, QueueCreateInfo},
+    device::{Device, DeviceCreateInfo, DeviceExtensions, Queue, QueueCreateInfo},
     format::Format,
-    image::{ImageUsage, SampleCount},
+    image::{sampler::Filter, view::ImageView, Image, ImageUsage, SampleCount},
     instance::debug::DebugUtilsMessenger,
     instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
     memory::allocator::{MemoryTypeFilter, StandardMemoryAllocator},
@@ -36,9 +57,10 @@ use vulkano::{
         viewport::Viewport,
     },
     render_pass::{Framebuffer, RenderPass, Subpass},
+    shader::ShaderModule,
     swapchain::{
         self,
-        PresentMode, Surface, SurfaceInfo, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
+        CompositeAlpha, PresentMode, Surface, SurfaceInfo, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo,
     },
     sync::{
         self,
@@ -51,27 +73,122 @@ use winit::dpi::PhysicalSize;
 use winit::window::Window;
 
 const PREFFERED_IMAGE_COUNT: u32 = 2;
+/// Frames recorded ahead of the GPU, independent of the number of swapchain images.
+const FRAMES_IN_FLIGHT: usize = 2;
 const SUBPASS_MIRROR: u32 = 0;
 const SUBPASS_SCENE: u32 = 1;
-const SUBPASS_GUI: u32 = 2;
+/// Index of the capture device used for exhibits with a webcam texture.
+const WEBCAM_INDEX: u32 = 0;
+/// Size of the keyboard texture, one texel per JavaScript key code.
+pub(super) const KEYBOARD_EXTENT: [u32; 2] = [256, 1];
+
+/// Colors of the render targets where nothing is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ClearColors {
+    /// Also behind the gallery that the exhibits sample, see `SceneInput`.
+    pub scene: [f32; 4],
+    pub mirror: [f32; 4],
+}
+
+impl ClearColors {
+    /// Colors that hardly appear in the gallery, so areas nothing is drawn to stand out.
+    pub const DEBUG: Self = Self {
+        scene: [1.0, 0.0, 1.0, 1.0],
+        mirror: [1.0, 1.0, 0.0, 1.0],
+    };
+}
+
+impl Default for ClearColors {
+    fn default() -> Self {
+        Self {
+            scene: [0.0, 0.0, 0.8, 1.0],
+            mirror: [0.0, 0.8, 0.0, 1.0],
+        }
+    }
+}
+
+/// Time the CPU spent in the stages of the last `draw`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuTimings {
+    /// Checking the shaders for changes and rebuilding the pipelines.
+    pub shader_reload: Duration,
+    /// Writing the uniform buffers and recording the texture uploads.
+    pub uniforms: Duration,
+    /// Rebuilding the secondary command buffers after the pipelines changed.
+    pub command_buffers: Duration,
+    /// Acquiring the next image and waiting for its fence, long if the GPU is the bottleneck.
+    pub acquire: Duration,
+    /// Recording the primary command buffer, submitting it and presenting.
+    pub submit: Duration,
+}
+
+impl CpuTimings {
+    pub fn stages(&self) -> [(&'static str, Duration); 5] {
+        [
+            ("Shader reload", self.shader_reload),
+            ("Uniforms", self.uniforms),
+            ("Command buffers", self.command_buffers),
+            ("Acquire", self.acquire),
+            ("Submit", self.submit),
+        ]
+    }
+}
+
+/// Returns the time since `start` and restarts it.
+fn lap(start: &mut Instant) -> Duration {
+    let now = Instant::now();
+    let elapsed = now - *start;
+    *start = now;
+    elapsed
+}
 
 pub struct App {
     pub view_matrix: Mat4,
     pub mirror_matrix: Mat4,
     pub fov: f32,
-
-    _instance: Arc<Instance>,
+    /// Distances of the clip planes.
+    pub near: f32,
+    pub far: f32,
+    /// Distance between the eyes in world units, only used with a `StereoMode`.
+    pub ipd: f32,
+    /// Blends from the original colors at 0 to the colors of the lookup table at 1, see `set_color_lut`.
+    pub grading_strength: f32,
+    /// Passed to the shaders in `GlobalUniforms` for users sensitive to motion and flicker.
+    pub reduced_motion: bool,
+    /// Passed to the shaders in `GlobalUniforms`, how much of their iterations they should spend.
+    pub detail: f32,
+
+    instance: Arc<Instance>,
     device: Arc<Device>,
     queue: Arc<Queue>,
     swapchain: Arc<Swapchain>,
+    images: Vec<Arc<Image>>,
+    /// Images the frames in flight are rendered to if not `swapchain_framebuffers`,
+    /// they keep their size while the window is resized.
+    render_targets: Vec<Arc<Image>>,
+    /// Filter to scale the render targets to the swapchain images, `None` if blitting is not supported.
+    blit_filter: Option<Filter>,
+    /// Size of the render targets relative to the swapchain images, see `set_render_scale`.
+    render_scale: f32,
     msaa_sample_count: SampleCount,
     memory_allocator: Arc<StandardMemoryAllocator>,
-    _descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    uniform_buffer_allocator: SubbufferAllocator,
+    /// Resources shared by all exhibits, bound as set 1.
+    global_set: GlobalSet,
     depth_format: Format,
     render_pass: Arc<RenderPass>,
     subpass_mirror: Subpass,
     subpass_scene: Subpass,
     framebuffers: Vec<Arc<Framebuffer>>,
+    /// One per swapchain image, the frames are rendered straight into it while the render targets have the
+    /// same size and nothing reads the frame back. Empty while the window is resized or the render scale is below 1.
+    swapchain_framebuffers: Vec<Arc<Framebuffer>>,
+    /// The interface is drawn in its own render pass after the frame was copied and graded.
+    gui_render_pass: Arc<RenderPass>,
+    /// Like `framebuffers` and `swapchain_framebuffers` for `gui_render_pass`.
+    gui_framebuffers: Vec<Arc<Framebuffer>>,
+    swapchain_gui_framebuffers: Vec<Arc<Framebuffer>>,
     viewport: Viewport,
     command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
     command_buffers_scene: Vec<Arc<SecondaryAutoCommandBuffer>>,
@@ -79,7 +196,67 @@ pub struct App {
     #[allow(clippy::type_complexity)]
     fences: Vec<Option<Arc<FenceSignalFuture<Box<dyn GpuFuture>>>>>,
     previous_fence_i: usize,
+    /// Frame in flight of the next `draw`, indexes `fences`, `render_targets` and `framebuffers`.
+    frame_i: usize,
     pipelines: MyPipelines,
+    /// Color and depth of the mirror subpass that exhibits sample their reflection from.
+    mirror_buffers: [Arc<ImageView>; 2],
+    /// Bound until the texture of an exhibit is loaded.
+    placeholder: Texture,
+    texture_loader: TextureLoader,
+    webcam: Option<Webcam>,
+    /// One per frame in flight, so a frame is never uploaded while an earlier one samples it.
+    /// Empty until the first frame is captured.
+    webcam_textures: Vec<StreamingTexture>,
+    /// The latest frame, it is uploaded to the texture of each frame in flight once.
+    webcam_frame: Option<RgbaImage>,
+    /// Whether the texture of the frame in flight has the latest frame.
+    webcam_uploaded: Vec<bool>,
+    keyboard_texture: StreamingTexture,
+    /// Keyboard state that is uploaded before the next frame.
+    keyboard_pending: Option<RgbaImage>,
+    /// Path the next frame is saved to.
+    screenshot_pending: Option<PathBuf>,
+    /// Shown where nothing is drawn.
+    clear_colors: ClearColors,
+    /// The window is see-through where nothing is drawn, the scene clear color is ignored then.
+    transparent: bool,
+    /// How the frame is rendered for both eyes, see `set_stereo`.
+    stereo: Stereo,
+    /// Whether a view for another window is rendered after the eyes, see `set_second_view`.
+    second_view: bool,
+    /// Created for a lookup table or for output adjustments that are not neutral.
+    color_grading: Option<ColorGrading>,
+    output: OutputAdjustments,
+    /// Created unless the transitions are off, see `set_transition`.
+    transition: Option<Transition>,
+    transition_settings: TransitionSettings,
+    /// Frame in flight whose render target holds the frame drawn last, `None` after they were recreated.
+    last_target: Option<usize>,
+    /// Copies of the last frame for shaders with feedback effects, one per frame in flight.
+    /// A frame samples its own copy and copies itself into the one of the next frame.
+    previous_frames: Vec<Texture>,
+    /// Time passed to the last `draw`.
+    last_time: Option<f32>,
+    frame_index: u32,
+    cpu_timings: CpuTimings,
+    /// `None` if the gallery can not be rendered to textures.
+    scene_input: Option<SceneInput>,
+    /// Exhibits rendered at their own refresh rate, created on the next draw after being cleared.
+    art_caches: Vec<ArtCache>,
+    /// The only exhibit drawn while it covers the window, see `set_fullscreen_art`.
+    fullscreen_art: Option<usize>,
+    /// Draws `fullscreen_art`, created on the next draw after being cleared.
+    fullscreen_pipeline: Option<MyPipeline>,
+    /// Vertex shader and geometry of `fullscreen_pipeline`, see `MyPipeline::fullscreen_variant`.
+    fullscreen_shader: Arc<HotShader>,
+    fullscreen_triangle: Geometry,
+    /// Draws an exhibit from its cache, see `ArtCache`.
+    composite_shader: Arc<HotShader>,
+    watchdog: Watchdog,
+    /// Exhibits that made the GPU time explode without a previous shader to restore,
+    /// see `take_runaway_exhibits`.
+    runaway_exhibits: Vec<usize>,
 
     // If this falls out of scope then there will be no more debug events.
     // Put it at the end so that it gets dropped last.
@@ -92,6 +269,8 @@ impl App {
         window: Arc<Window>,
         model: NormalizedObj,
         art_objs: &[ArtObject],
+        environment_path: &Path,
+        transparent: bool,
     ) -> anyhow::Result<Self> {
         log::debug!("creating vulkan app");
 
@@ -113,6 +292,7 @@ impl App {
                 flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
                 enabled_layers: debug_layers,
                 enabled_extensions,
+                enabled_validation_features: get_validation_features(),
                 ..Default::default()
             },
         ).context("failed to create instance")?;
@@ -127,16 +307,16 @@ impl App {
             khr_swapchain: true,
             ..DeviceExtensions::empty()
         };
-        let device_features = DeviceFeatures {
-            geometry_shader: true,
-            ..DeviceFeatures::empty()
-        };
-
         let (physical_device, queue_family_index) =
-            select_physical_device(&instance, &surface, &device_extensions);
-        if !physical_device.supported_features().contains(&device_features) {
-            panic!("the physical device does not support all required features");
-        }
+            select_physical_device(&instance, Some(&surface), &device_extensions)?;
+        let device_features = physical_device.supported_features().intersection(&optional_device_features());
+        log::debug!("enabled optional features: {device_features:?}");
+        let device_extensions = DeviceExtensions {
+            // needed for debugPrintfEXT before Vulkan 1.3
+            khr_shader_non_semantic_info: debug_printf_enabled()
+                && physical_device.supported_extensions().khr_shader_non_semantic_info,
+            ..device_extensions
+        };
 
         let (device, mut queues) = Device::new(
             physical_device.clone(),
@@ -158,7 +338,20 @@ impl App {
                 .surface_capabilities(&surface, Default::default())
                 .context("failed to get surface capabilities")?;
 
-            let composite_alpha = caps.supported_composite_alpha.into_iter().next().unwrap();
+            let mut composite_alphas = caps.supported_composite_alpha.into_iter();
+            let composite_alpha = if transparent {
+                let supported = caps.supported_composite_alpha;
+                [CompositeAlpha::PreMultiplied, CompositeAlpha::PostMultiplied]
+                    .into_iter()
+                    .find(|composite_alpha| supported.contains_enum(*composite_alpha))
+                    .or_else(|| {
+                        log::warn!("the surface does not support transparency");
+                        composite_alphas.next()
+                    })
+                    .unwrap()
+            } else {
+                composite_alphas.next().unwrap()
+            };
             let image_format = physical_device
                 .surface_formats(&surface, Default::default())
                 .unwrap()[0]
@@ -174,16 +367,26 @@ impl App {
                     min_image_count,
                     image_format,
                     image_extent: dimensions.into(),
-                    image_usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_DST,
+                    // screenshots copy from the swapchain images if it is supported
+                    image_usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_DST
+                        | (caps.supported_usage_flags & ImageUsage::TRANSFER_SRC),
                     composite_alpha,
                     present_mode: PresentMode::Fifo,
                     ..Default::default()
                 },
             ).context("failed to create swapchain")?
         };
-        let frames_in_flight = images.len();
+        let frames_in_flight = FRAMES_IN_FLIGHT;
 
         let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+        let render_targets = get_render_targets(
+            images[0].format(),
+            images[0].extent(),
+            frames_in_flight,
+            memory_allocator.clone(),
+        );
+        let blit_filter = select_blit_filter(&physical_device, images[0].format())?;
+        log::debug!("selected blit filter: {blit_filter:?}");
 
         let msaa_sample_count = select_msaa_sample_count(&physical_device);
         log::debug!("selected msaa sample count: {msaa_sample_count:?}");
@@ -212,7 +415,7 @@ impl App {
             memory_allocator.clone(),
         );
         let framebuffers = get_framebuffers(
-            &images,
+            &render_targets,
             depth_format,
             render_pass.clone(),
             memory_allocator.clone(),
@@ -220,9 +423,18 @@ impl App {
             &mirror_color,
             &mirror_depth,
         );
+        let swapchain_framebuffers = get_swapchain_framebuffers(&images, &framebuffers[0]);
+        let gui_render_pass = get_gui_render_pass(device.clone(), swapchain.image_format());
+        let gui_framebuffers = get_gui_framebuffers(&render_targets, &gui_render_pass);
+        let swapchain_gui_framebuffers = get_gui_framebuffers(&images, &gui_render_pass);
 
         let vs = vs::load(device.clone()).context("failed to load vert shader")?;
-        let fs = fs::load(device.clone()).context("failed to load frag shader")?;
+        let fs = if device.enabled_features().geometry_shader {
+            fs::load(device.clone())
+        } else {
+            log::warn!("geometry shaders are not supported, shaders using gl_PrimitiveID will fail to compile");
+            fs_flat::load(device.clone())
+        }.context("failed to load frag shader")?;
 
         let viewport = Viewport {
             offset: [0.0, 0.0],
@@ -253,22 +465,68 @@ impl App {
             },
         ));
 
+        let environment = EnvironmentMap::new(
+            environment_path,
+            device.clone(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            memory_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        ).or_else(|err| {
+            log::error!("failed to load environment map {}: {err:?}", environment_path.display());
+            EnvironmentMap::fallback(
+                device.clone(),
+                queue.clone(),
+                command_buffer_allocator.clone(),
+                memory_allocator.clone(),
+                descriptor_set_allocator.clone(),
+            )
+        }).context("failed to create environment map")?;
+        let noise = NoiseTextures::new(
+            device.clone(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            memory_allocator.clone(),
+        ).context("failed to create noise textures")?;
+        let keyboard_texture = StreamingTexture::new(KEYBOARD_EXTENT, device.clone(), memory_allocator.clone())
+            .context("failed to create keyboard texture")?;
+        let global_set = GlobalSet::new(
+            environment,
+            noise,
+            keyboard_texture.texture.clone(),
+            device.clone(),
+            frames_in_flight,
+            &uniform_buffer_allocator,
+            descriptor_set_allocator.clone(),
+        ).context("failed to create global descriptor set")?;
+        let previous_frames = (0..frames_in_flight)
+            .map(|_| Texture::frame_copy(
+                render_targets[0].format(),
+                render_targets[0].extent(),
+                device.clone(),
+                memory_allocator.clone(),
+            ))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("failed to create previous frame texture")?;
+
         let geometry = Geometry::from_model(
             &model,
             VertexType::VertexNorm,
             memory_allocator.clone(),
             Vec3::splat(1.),
         ).context("failed to parse model")?;
+        let layouts = LayoutCache::default();
         let mut pipelines_scene = {
             let pipeline = MyPipeline::new(
                 MyPipelineCreateInfo {
                     name: "main".to_owned(),
                     vs: Arc::new(HotShader::new_nonhot(vs.clone(), ShaderKind::Vertex)),
                     fs: Arc::new(HotShader::new_nonhot(fs.clone(), ShaderKind::Fragment)),
+                    layouts: layouts.clone(),
                     ..Default::default()
                 },
                 None,
-                None,
+                Vec::new(),
                 device.clone(),
                 geometry.clone(),
                 subpass_scene.clone(),
@@ -279,6 +537,19 @@ impl App {
             ).context("failed to create pipeline")?;
             vec![pipeline]
         };
+        let scene_input = SceneInput::new(
+            images[0].format(),
+            render_targets[0].extent(),
+            Arc::new(HotShader::new_nonhot(vs.clone(), ShaderKind::Vertex)),
+            Arc::new(HotShader::new_nonhot(fs.clone(), ShaderKind::Fragment)),
+            geometry.clone(),
+            device.clone(),
+            viewport.clone(),
+            frames_in_flight,
+            &uniform_buffer_allocator,
+            descriptor_set_allocator.clone(),
+            memory_allocator.clone(),
+        ).inspect_err(|err| log::error!("exhibits can not sample the gallery: {err:?}")).ok();
         let mut pipelines_mirror = {
             let pipeline = MyPipeline::new(
                 MyPipelineCreateInfo {
@@ -286,10 +557,11 @@ impl App {
                     vs: Arc::new(HotShader::new_nonhot(vs, ShaderKind::Vertex)),
                     fs: Arc::new(HotShader::new_nonhot(fs, ShaderKind::Fragment)),
                     cull_mode: CullMode::Front,
+                    layouts: layouts.clone(),
                     ..Default::default()
                 },
                 None,
-                None,
+                Vec::new(),
                 device.clone(),
                 geometry,
                 subpass_mirror.clone(),
@@ -306,96 +578,517 @@ impl App {
         });
         watch_shaders(shader_iter);
 
-        for (art_idx, art_obj) in art_objs.iter().enumerate() {
-            let geometry = Geometry::from_model(
-                &art_obj.model,
-                VertexType::VertexNorm,
-                memory_allocator.clone(),
-                art_obj.container_scale,
-            ).context("failed to parse model")?;
-            let texture = art_obj.texture.as_ref().and_then(|path| {
-                Texture::new(
-                    path,
+        // textures are loaded in the background, until then a placeholder is bound
+        let texture_loader = TextureLoader::default();
+        let texture_paths = art_objs.iter()
+            .filter_map(|art_obj| match &art_obj.texture {
+                Some(TextureSource::File(path)) => Some(path.clone()),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        for path in texture_paths {
+            texture_loader.load(path);
+        }
+        let placeholder = Texture::placeholder(
+            device.clone(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            memory_allocator.clone(),
+        ).context("failed to create placeholder texture")?;
+
+        let webcam = if art_objs.iter().any(|art_obj| art_obj.texture == Some(TextureSource::Webcam)) {
+            Webcam::start(WEBCAM_INDEX)
+                .inspect_err(|err| log::error!("failed to start webcam: {err:?}"))
+                .ok()
+        } else {
+            None
+        };
+
+        // generated textures are uploaded once and shared by all exhibits using the same image
+        let mut generated_textures = HashMap::new();
+        for art_obj in art_objs {
+            let Some(TextureSource::Generated(image)) = &art_obj.texture else { continue };
+            if !generated_textures.contains_key(&Arc::as_ptr(image)) {
+                let texture = Texture::from_image(
+                    RgbaImage::clone(image),
                     device.clone(),
                     queue.clone(),
                     command_buffer_allocator.clone(),
                     memory_allocator.clone(),
-                ).inspect_err(|err| {
-                    log::error!("failed to load texture {}: {err:?}", path.display())
-                }).ok()
-            });
-            let pipeline = MyPipeline::new(
-                MyPipelineCreateInfo {
-                    mirror_buffers: Some([mirror_color.clone(), mirror_depth.clone()]),
-                    ..art_obj.into()
-                },
-                Some(art_idx),
-                texture.clone(),
-                device.clone(),
-                geometry.clone(),
-                subpass_scene.clone(),
-                viewport.clone(),
-                frames_in_flight,
-                &uniform_buffer_allocator,
-                descriptor_set_allocator.clone(),
-            ).context("failed to create pipeline")?;
-            pipelines_scene.push(pipeline);
-
-            let pipeline = MyPipeline::new(
-                MyPipelineCreateInfo {
-                    name: format!("{} mirror", art_obj.name),
-                    enable_pipeline: art_obj.enable_pipeline && !art_obj.is_mirror,
-                    cull_mode: CullMode::Front,
-                    ..art_obj.into()
-                },
-                Some(art_idx),
-                texture,
-                device.clone(),
-                geometry,
-                subpass_mirror.clone(),
-                viewport.clone(),
-                frames_in_flight,
-                &uniform_buffer_allocator,
-                descriptor_set_allocator.clone(),
-            ).context("failed to create pipeline")?;
-            pipelines_mirror.push(pipeline);
+                ).with_context(|| format!("failed to create texture of {}", art_obj.name))?;
+                generated_textures.insert(Arc::as_ptr(image), texture);
+            }
         }
 
+        let fullscreen_triangle = Geometry::fullscreen_triangle(memory_allocator.clone())
+            .context("failed to create fullscreen triangle")?;
+
         let pipelines = MyPipelines {
-            order: Self::get_pipeline_order(&pipelines_scene, art_objs),
+            order: Vec::new(),
             scene: pipelines_scene,
             mirror: pipelines_mirror,
+            layouts,
         };
 
         let mut app = Self {
             view_matrix: Mat4::IDENTITY,
             mirror_matrix: Mat4::IDENTITY,
             fov: 75_f32,
-            _instance: instance,
+            near: 0.01,
+            far: 200.,
+            ipd: 0.064,
+            grading_strength: 1.,
+            reduced_motion: false,
+            detail: 1.,
+            instance,
             device,
             queue,
             swapchain,
+            images,
+            render_targets,
+            blit_filter,
+            render_scale: 1.,
             msaa_sample_count,
             memory_allocator,
-            _descriptor_set_allocator: descriptor_set_allocator,
+            descriptor_set_allocator,
+            global_set,
+            uniform_buffer_allocator,
             depth_format,
             render_pass,
             subpass_mirror,
             subpass_scene,
             framebuffers,
+            swapchain_framebuffers,
+            gui_render_pass,
+            gui_framebuffers,
+            swapchain_gui_framebuffers,
             viewport,
             command_buffer_allocator,
             command_buffers_scene: Vec::new(),
             command_buffers_mirror: Vec::new(),
             fences: vec![None; frames_in_flight],
             previous_fence_i: 0,
+            frame_i: 0,
             pipelines,
+            mirror_buffers: [mirror_color, mirror_depth],
+            placeholder,
+            texture_loader,
+            webcam,
+            webcam_textures: Vec::new(),
+            webcam_frame: None,
+            webcam_uploaded: Vec::new(),
+            keyboard_texture,
+            keyboard_pending: Some(RgbaImage::new(KEYBOARD_EXTENT[0], KEYBOARD_EXTENT[1])),
+            screenshot_pending: None,
+            clear_colors: ClearColors::default(),
+            transparent,
+            stereo: Stereo::Off,
+            second_view: false,
+            color_grading: None,
+            output: OutputAdjustments::default(),
+            transition: None,
+            transition_settings: TransitionSettings { style: TransitionStyle::Off, ..Default::default() },
+            last_target: None,
+            previous_frames,
+            last_time: None,
+            frame_index: 0,
+            cpu_timings: CpuTimings::default(),
+            scene_input,
+            art_caches: Vec::new(),
+            fullscreen_art: None,
+            fullscreen_pipeline: None,
+            fullscreen_shader: Arc::new(HotShader::new_vert("assets/shaders/fullscreen.vert")),
+            fullscreen_triangle,
+            composite_shader: Arc::new(HotShader::new_frag("assets/shaders/cached.frag")),
+            watchdog: Watchdog::new(frames_in_flight),
+            runaway_exhibits: Vec::new(),
             _debug: debug,
         };
+        for (art_idx, art_obj) in art_objs.iter().enumerate() {
+            let textures = match &art_obj.texture {
+                Some(TextureSource::Generated(image)) => vec![generated_textures[&Arc::as_ptr(image)].clone()],
+                Some(_) => vec![app.placeholder.clone()],
+                None => Vec::new(),
+            };
+            app.push_art_pipelines(art_idx, art_obj, textures)?;
+        }
+        app.pipelines.order = Self::get_pipeline_order(&app.pipelines.scene, art_objs);
         app.update_command_buffers();
         Ok(app)
     }
 
+    /// Creates the scene and mirror pipeline of an exhibit. They are inserted before the pipelines
+    /// of compared exhibits, so the scene and mirror pipelines of an exhibit keep the same index.
+    fn push_art_pipelines(
+        &mut self,
+        art_idx: usize,
+        art_obj: &ArtObject,
+        textures: Vec<Texture>,
+    ) -> anyhow::Result<()> {
+        let vertex_type = if art_obj.model.has_tex_coords {
+            VertexType::VertexTex
+        } else {
+            VertexType::VertexNorm
+        };
+        let geometry = Geometry::from_model(
+            &art_obj.model,
+            vertex_type,
+            self.memory_allocator.clone(),
+            art_obj.container_scale,
+        ).context("failed to parse model")?;
+        if let Some(block) = art_obj.uniform_block.as_ref() {
+            register_include(format!("sp/blocks/{}.glsl", art_obj.file_stem()), block.glsl());
+        }
+        let frames_in_flight = self.uniform_slots();
+        let scene = MyPipeline::new(
+            MyPipelineCreateInfo {
+                mirror_buffers: Some(self.mirror_buffers.clone()),
+                previous_frame: Some(self.previous_frames.clone()),
+                scene_input: self.scene_input.as_ref().map(SceneInput::textures),
+                layouts: self.pipelines.layouts.clone(),
+                ..art_obj.into()
+            },
+            Some(art_idx),
+            textures.clone(),
+            self.device.clone(),
+            geometry.clone(),
+            self.subpass_scene.clone(),
+            self.viewport.clone(),
+            frames_in_flight,
+            &self.uniform_buffer_allocator,
+            self.descriptor_set_allocator.clone(),
+        ).context("failed to create pipeline")?;
+        let mirror = MyPipeline::new(
+            MyPipelineCreateInfo {
+                name: format!("{} mirror", art_obj.name),
+                enable_pipeline: art_obj.enable_pipeline && !art_obj.is_mirror,
+                // the reflection flips the winding order
+                cull_mode: if art_obj.frag_depth { CullMode::Back } else { CullMode::Front },
+                previous_frame: Some(self.previous_frames.clone()),
+                layouts: self.pipelines.layouts.clone(),
+                ..art_obj.into()
+            },
+            Some(art_idx),
+            textures,
+            self.device.clone(),
+            geometry,
+            self.subpass_mirror.clone(),
+            self.viewport.clone(),
+            frames_in_flight,
+            &self.uniform_buffer_allocator,
+            self.descriptor_set_allocator.clone(),
+        ).context("failed to create pipeline")?;
+        self.pipelines.scene.insert(self.pipelines.mirror.len(), scene);
+        self.pipelines.mirror.push(mirror);
+        Ok(())
+    }
+
+    /// Creates the pipelines of the exhibit at `art_idx` that was added after the start.
+    pub fn add_art(&mut self, art_objs: &[ArtObject], art_idx: usize) -> anyhow::Result<()> {
+        let art_obj = &art_objs[art_idx];
+        let textures = match &art_obj.texture {
+            Some(TextureSource::Generated(image)) => vec![Texture::from_image(
+                RgbaImage::clone(image),
+                self.device.clone(),
+                self.queue.clone(),
+                self.command_buffer_allocator.clone(),
+                self.memory_allocator.clone(),
+            ).with_context(|| format!("failed to create texture of {}", art_obj.name))?],
+            Some(TextureSource::File(path)) => {
+                self.texture_loader.load(path.clone());
+                vec![self.placeholder.clone()]
+            }
+            Some(TextureSource::Webcam) if self.webcam_textures.is_empty() => vec![self.placeholder.clone()],
+            Some(TextureSource::Webcam) => self.webcam_textures.iter().map(|webcam| webcam.texture.clone()).collect(),
+            None => Vec::new(),
+        };
+        self.push_art_pipelines(art_idx, art_obj, textures)?;
+        self.pipelines.order = Self::get_pipeline_order(&self.pipelines.scene, art_objs);
+        self.update_command_buffers();
+        Ok(())
+    }
+
+    /// Removes the pipelines of the exhibit that was at `art_idx`, `art_objs` no longer contains it.
+    pub fn remove_art(&mut self, art_objs: &[ArtObject], art_idx: usize) {
+        self.pipelines.scene.retain(|pipeline| pipeline.get_art_idx() != Some(art_idx));
+        self.pipelines.mirror.retain(|pipeline| pipeline.get_art_idx() != Some(art_idx));
+        self.art_caches.retain(|cache| cache.art_idx() != Some(art_idx));
+        self.fullscreen_pipeline.take_if(|pipeline| pipeline.get_art_idx() == Some(art_idx));
+        self.watchdog.clear();
+        self.runaway_exhibits.clear();
+        let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+        for pipeline in self.pipelines.iter_mut(0).chain(caches).chain(self.fullscreen_pipeline.as_mut()) {
+            if let Some(idx) = pipeline.get_art_idx().filter(|&idx| idx > art_idx) {
+                pipeline.set_art_idx(idx - 1);
+            }
+        }
+        self.pipelines.order = Self::get_pipeline_order(&self.pipelines.scene, art_objs);
+        self.update_command_buffers();
+    }
+
+    pub fn set_clear_colors(&mut self, clear_colors: ClearColors) {
+        self.clear_colors = clear_colors;
+    }
+
+    fn clear_colors(&self) -> ClearColors {
+        if self.transparent {
+            ClearColors { scene: [0., 0., 0., 0.], ..self.clear_colors }
+        } else {
+            self.clear_colors
+        }
+    }
+
+    /// Replaces the gallery model, e.g. when another scene is loaded.
+    pub fn set_environment_model(&mut self, model: &NormalizedObj) -> anyhow::Result<()> {
+        let geometry = Geometry::from_model(
+            model,
+            VertexType::VertexNorm,
+            self.memory_allocator.clone(),
+            Vec3::splat(1.),
+        ).context("failed to parse model")?;
+        for pipeline in self.pipelines.scene.iter_mut().chain(self.pipelines.mirror.iter_mut()) {
+            if pipeline.get_art_idx().is_none() {
+                pipeline.set_geometry(geometry.clone());
+                pipeline.update_pipeline(self.device.clone(), self.viewport.clone())
+                    .context("failed to update pipeline")?;
+            }
+        }
+        if let Some(scene_input) = self.scene_input.as_mut() {
+            scene_input.set_geometry(geometry, self.device.clone(), self.viewport.clone())?;
+        }
+        self.update_command_buffers();
+        Ok(())
+    }
+
+    /// Shows or hides the gallery model, e.g. when only one exhibit is shown.
+    pub fn set_environment_visible(&mut self, visible: bool) {
+        for pipeline in self.pipelines.scene.iter_mut().chain(self.pipelines.mirror.iter_mut()) {
+            if pipeline.get_art_idx().is_none() {
+                pipeline.hidden = !visible;
+            }
+        }
+        if let Some(scene_input) = self.scene_input.as_mut() {
+            scene_input.set_hidden(!visible);
+        }
+        self.update_command_buffers();
+    }
+
+    /// Draws only the fragment shader of the flat exhibit at `art_idx` on a triangle covering the window,
+    /// see `FullscreenMapping`, or the whole scene again if it is `None`.
+    pub fn set_fullscreen_art(&mut self, art_idx: Option<usize>) {
+        if self.fullscreen_art != art_idx {
+            self.fullscreen_art = art_idx;
+            self.fullscreen_pipeline = None;
+            self.update_command_buffers();
+        }
+    }
+
+    /// Switches between rendering one frame and one for each eye, waits for all frames in flight
+    /// if the number of eyes changes. Stereo modes need the swapchain images to support blitting.
+    pub fn set_stereo(&mut self, mode: StereoMode) -> anyhow::Result<()> {
+        if mode == self.stereo.mode() {
+            return Ok(());
+        }
+        if mode != StereoMode::Off && self.blit_filter.is_none() {
+            anyhow::bail!("swapchain images do not support scaled blits");
+        }
+        log::info!("switching stereo mode to {}", mode.label());
+        for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+        }
+        let views_changed = mode.eye_count() != self.stereo.mode().eye_count();
+        self.stereo = Stereo::new(
+            mode,
+            &self.render_targets[0],
+            self.device.clone(),
+            self.memory_allocator.clone(),
+            self.descriptor_set_allocator.clone(),
+        )?;
+        if views_changed {
+            self.allocate_uniform_slots()?;
+            self.update_command_buffers();
+        }
+        Ok(())
+    }
+
+    pub fn stereo_mode(&self) -> StereoMode {
+        self.stereo.mode()
+    }
+
+    /// Grades the frames with the lookup table at `path`, see `Lut::load`, or stops grading if it
+    /// is `None`. The graded frame is blitted back, so the render targets must support blitting.
+    pub fn set_color_lut(&mut self, path: Option<&Path>) -> anyhow::Result<()> {
+        let lut = path.map(Lut::load).transpose()?;
+        if lut.is_none() && self.output.is_neutral() {
+            self.color_grading = None;
+            return Ok(());
+        }
+        self.create_color_grading(lut.as_ref())?;
+        if let Some(path) = path {
+            log::info!("grading colors with {}", path.display());
+        }
+        Ok(())
+    }
+
+    pub fn output(&self) -> OutputAdjustments {
+        self.output
+    }
+
+    /// Adjusts the colors of the frames after the color grading. This needs the same blits as
+    /// `set_color_lut` unless the adjustments are neutral.
+    pub fn set_output(&mut self, output: OutputAdjustments) -> anyhow::Result<()> {
+        if !output.is_neutral() && self.color_grading.is_none() {
+            self.create_color_grading(None)?;
+        }
+        self.output = output;
+        Ok(())
+    }
+
+    fn create_color_grading(&mut self, lut: Option<&Lut>) -> anyhow::Result<()> {
+        if self.blit_filter.is_none() {
+            anyhow::bail!("swapchain images do not support blits");
+        }
+        for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+        }
+        self.color_grading = Some(ColorGrading::new(
+            lut,
+            self.render_targets[0].format(),
+            self.render_targets[0].extent(),
+            self.render_targets.len(),
+            self.device.clone(),
+            self.queue.clone(),
+            self.command_buffer_allocator.clone(),
+            self.memory_allocator.clone(),
+            self.descriptor_set_allocator.clone(),
+        ).context("failed to create color grading")?);
+        Ok(())
+    }
+
+    pub fn transition(&self) -> &TransitionSettings {
+        &self.transition_settings
+    }
+
+    /// Sets how `start_transition` blends from the last frame to the new ones.
+    /// Like the color grading this needs the render targets to support blitting.
+    pub fn set_transition(&mut self, settings: &TransitionSettings) -> anyhow::Result<()> {
+        let path = settings.path();
+        if path != self.transition.as_ref().map(Transition::path) {
+            self.transition = match path {
+                Some(path) => {
+                    if self.blit_filter.is_none() {
+                        anyhow::bail!("swapchain images do not support blits");
+                    }
+                    let transition = Transition::new(
+                        path,
+                        self.render_targets[0].format(),
+                        self.render_targets[0].extent(),
+                        self.device.clone(),
+                        self.memory_allocator.clone(),
+                        self.descriptor_set_allocator.clone(),
+                    ).context("failed to create transition")?;
+                    Some(transition)
+                }
+                None => None,
+            };
+        }
+        if let Some(transition) = self.transition.as_mut() {
+            transition.duration = settings.duration;
+        }
+        self.transition_settings = settings.clone();
+        Ok(())
+    }
+
+    /// Blends from the frame drawn last to the next ones, e.g. after the camera jumped.
+    /// Does nothing if the transitions are off.
+    pub fn start_transition(&mut self) {
+        if let Some(transition) = self.transition.as_mut() {
+            transition.start();
+        }
+    }
+
+    /// Creates the swapchain of another window on the device of this app.
+    pub fn open_window(&self, window: Arc<Window>) -> anyhow::Result<WindowTarget> {
+        WindowTarget::new(window, self.instance.clone(), self.device.clone(), self.swapchain.image_format())
+    }
+
+    /// Enables rendering a view for a `WindowTarget` that is passed to `draw`,
+    /// waits for all frames in flight. The view is scaled to the window, which needs blitting.
+    pub fn set_second_view(&mut self, enabled: bool) -> anyhow::Result<()> {
+        if enabled == self.second_view {
+            return Ok(());
+        }
+        if enabled && self.blit_filter.is_none() {
+            anyhow::bail!("swapchain images do not support scaled blits");
+        }
+        for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+        }
+        self.second_view = enabled;
+        self.allocate_uniform_slots()?;
+        self.update_command_buffers();
+        Ok(())
+    }
+
+    /// Number of views rendered per frame, the eyes followed by the second view.
+    fn view_count(&self) -> usize {
+        self.stereo.mode().eye_count() + self.second_view as usize
+    }
+
+    /// Number of uniform buffers and command buffers, one per frame in flight and view.
+    fn uniform_slots(&self) -> usize {
+        self.fences.len() * self.view_count()
+    }
+
+    /// Reallocates the uniform buffers and descriptor sets after the number of slots changed.
+    /// The fences of all frames must have been waited for.
+    fn allocate_uniform_slots(&mut self) -> anyhow::Result<()> {
+        let slots = self.uniform_slots();
+        // the compare pipelines share the old uniform buffers, they are recreated on the next draw
+        let mirror_count = self.pipelines.mirror.len();
+        self.pipelines.scene.truncate(mirror_count);
+        self.pipelines.order.retain(|&idx| idx < mirror_count);
+        self.art_caches.clear();
+        self.fullscreen_pipeline = None;
+        self.global_set.set_frames_in_flight(
+            slots,
+            &self.uniform_buffer_allocator,
+            self.descriptor_set_allocator.clone(),
+        )?;
+        for pipeline in self.pipelines.iter_mut(0) {
+            pipeline.set_frames_in_flight(slots, &self.uniform_buffer_allocator)?;
+        }
+        if let Some(scene_input) = self.scene_input.as_mut() {
+            scene_input.set_frames_in_flight(slots, &self.uniform_buffer_allocator)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the size the frames are rendered at relative to the window, they are scaled to it.
+    /// Returns `true` if the swapchain has to be recreated to apply it.
+    /// Ignored if the frames can not be scaled.
+    pub fn set_render_scale(&mut self, render_scale: f32) -> bool {
+        let render_scale = render_scale.clamp(0.1, 1.);
+        if self.blit_filter.is_none() || self.render_scale == render_scale {
+            return false;
+        }
+        self.render_scale = render_scale;
+        true
+    }
+
+    /// `extent` of the swapchain scaled by the render scale.
+    fn scaled_extent(&self, [width, height, depth]: [u32; 3]) -> [u32; 3] {
+        let scale = |len: u32| ((len as f32 * self.render_scale).round() as u32).max(1);
+        [scale(width), scale(height), depth]
+    }
+
+    /// Size the frames are rendered at, differs from the swapchain while the window is resized
+    /// or with a render scale below 1.
+    fn render_extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.render_targets[0].extent();
+        [width, height]
+    }
+
     pub fn get_queue(&self) -> &Arc<Queue> { &self.queue }
 
     pub fn get_swapchain(&self) -> &Arc<Swapchain> { &self.swapchain }
@@ -407,8 +1100,13 @@ impl App {
         )
     }
 
+    /// Number of swapchain images, the number of frames in flight does not depend on it.
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
     pub fn gui_pass(&self) -> Subpass {
-        Subpass::from(self.render_pass.clone(), SUBPASS_GUI).unwrap()
+        Subpass::from(self.gui_render_pass.clone(), 0).unwrap()
     }
 
     pub fn recreate_swapchain(
@@ -417,8 +1115,15 @@ impl App {
         options: &crate::gui::Options,
     ) -> anyhow::Result<()> {
         log::info!("recreating swapchain with new size {dimensions:?}");
+        let caps = self.device.physical_device()
+            .surface_capabilities(self.swapchain.surface(), Default::default())
+            .context("failed to get surface capabilities")?;
+        let min_image_count = options.image_count
+            .min(caps.max_image_count.unwrap_or(u32::MAX))
+            .max(caps.min_image_count);
         let (new_swapchain, new_images) = self.swapchain
             .recreate(SwapchainCreateInfo {
+                min_image_count,
                 image_extent: dimensions.into(),
                 present_mode: options.present_mode,
                 ..self.swapchain.create_info()
@@ -426,20 +1131,27 @@ impl App {
             .context("failed to recreate swapchain")?;
 
         self.swapchain = new_swapchain;
+        let extent = self.scaled_extent(new_images[0].extent());
+        self.render_targets = get_render_targets(
+            new_images[0].format(),
+            extent,
+            self.fences.len(),
+            self.memory_allocator.clone(),
+        );
         let mirror_color = get_image_view(
             new_images[0].format(),
-            new_images[0].extent(),
+            extent,
             color_usage(),
             self.memory_allocator.clone(),
         );
         let mirror_depth = get_image_view(
             self.depth_format,
-            new_images[0].extent(),
+            extent,
             depth_usage(),
             self.memory_allocator.clone(),
         );
         self.framebuffers = get_framebuffers(
-            &new_images,
+            &self.render_targets,
             self.depth_format,
             self.render_pass.clone(),
             self.memory_allocator.clone(),
@@ -447,38 +1159,166 @@ impl App {
             &mirror_color,
             &mirror_depth,
         );
+        self.gui_framebuffers = get_gui_framebuffers(&self.render_targets, &self.gui_render_pass);
+        (self.swapchain_framebuffers, self.swapchain_gui_framebuffers) = if extent == new_images[0].extent() {
+            (
+                get_swapchain_framebuffers(&new_images, &self.framebuffers[0]),
+                get_gui_framebuffers(&new_images, &self.gui_render_pass),
+            )
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        self.mirror_buffers = [mirror_color, mirror_depth];
+        self.previous_frames = (0..self.fences.len())
+            .map(|_| Texture::frame_copy(
+                new_images[0].format(),
+                extent,
+                self.device.clone(),
+                self.memory_allocator.clone(),
+            ))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("failed to create previous frame texture")?;
+        self.images = new_images;
 
         // we need to wait here before we can update the descriptor sets
         for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
-            image_fence.wait(None).context("failed to wait for fence")?;
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
         }
-
-        self.viewport.extent = dimensions.into();
+        self.stereo = Stereo::new(
+            self.stereo.mode(),
+            &self.render_targets[0],
+            self.device.clone(),
+            self.memory_allocator.clone(),
+            self.descriptor_set_allocator.clone(),
+        )?;
+        if let Some(color_grading) = self.color_grading.as_mut() {
+            color_grading.resize(
+                self.render_targets[0].format(),
+                self.render_targets[0].extent(),
+                self.render_targets.len(),
+                self.device.clone(),
+                self.memory_allocator.clone(),
+                self.descriptor_set_allocator.clone(),
+            )?;
+        }
+        if let Some(transition) = self.transition.as_mut() {
+            transition.resize(
+                self.render_targets[0].format(),
+                self.render_targets[0].extent(),
+                self.device.clone(),
+                self.memory_allocator.clone(),
+            )?;
+        }
+        self.last_target = None;
+
+        self.viewport.extent = [extent[0] as f32, extent[1] as f32];
+        let scene_input = match self.scene_input.as_mut() {
+            Some(scene_input) => {
+                scene_input.resize(
+                    extent,
+                    self.device.clone(),
+                    self.viewport.clone(),
+                    self.memory_allocator.clone(),
+                )?;
+                Some(scene_input.textures())
+            }
+            None => None,
+        };
         for pipeline in self.pipelines.iter_mut(0) {
             pipeline.update_pipeline(self.device.clone(), self.viewport.clone())
                 .context("failed to update pipeline")?;
-            pipeline.update_mirror_buffers([mirror_color.clone(), mirror_depth.clone()])?;
+            pipeline.update_mirror_buffers(self.mirror_buffers.clone())?;
+            pipeline.update_previous_frame(self.previous_frames.clone())?;
+            if let Some(scene_input) = scene_input.as_ref() {
+                pipeline.update_scene_input(scene_input.clone())?;
+            }
         }
+        // recreated at the new size on the next draw
+        self.art_caches.clear();
+        self.fullscreen_pipeline = None;
         self.update_command_buffers();
 
         Ok(())
     }
 
-    /// Draws the render_pass and returns whether the swapchain is dirty.
+    /// Only recreates the swapchain while the window is being resized. The frames are still
+    /// rendered at the old size and scaled to the new one, so nothing else is reallocated.
+    /// Falls back to `recreate_swapchain` if the frames cannot be scaled.
+    pub fn resize_swapchain(
+        &mut self,
+        dimensions: PhysicalSize<u32>,
+        options: &crate::gui::Options,
+    ) -> anyhow::Result<()> {
+        if self.blit_filter.is_none() {
+            return self.recreate_swapchain(dimensions, options);
+        }
+        log::debug!("resizing swapchain to {dimensions:?}");
+        let (new_swapchain, new_images) = self.swapchain
+            .recreate(SwapchainCreateInfo {
+                image_extent: dimensions.into(),
+                ..self.swapchain.create_info()
+            })
+            .context("failed to recreate swapchain")?;
+        self.swapchain = new_swapchain;
+        // rendered at the old size and scaled until the swapchain is recreated
+        self.swapchain_framebuffers.clear();
+        self.swapchain_gui_framebuffers.clear();
+        self.images = new_images;
+        Ok(())
+    }
+
+    /// Draws the render_pass and returns whether the swapchain is dirty. `second_view` is rendered
+    /// with its view matrix after the main view if enabled with `set_second_view`.
     pub fn draw(
         &mut self,
         time: f32,
         gui: Option<&mut Gui>,
         art_objs: &[ArtObject],
+        second_view: Option<(&mut WindowTarget, Mat4)>,
     ) -> anyhow::Result<bool> {
+        let mut timings = CpuTimings::default();
+        let mut stage_start = Instant::now();
+        self.update_textures(art_objs)?;
+        let upload_command_buffer = self.record_uploads(art_objs)?;
+        timings.uniforms += lap(&mut stage_start);
+
         let mut pipeline_changed = false;
-        for pipeline in self.pipelines.iter_mut(1) {
-            if pipeline.reload_shaders(false) {
-                pipeline_changed = true;
-            } else if pipeline.get_pipeline().is_none() {
+        // the reflected camera of the mirror pipelines is behind the mirror, outside of the exhibits
+        let camera = self.view_matrix.inverse().w_axis.truncate();
+        let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+        for pipeline in self.pipelines.scene.iter_mut().skip(1).chain(caches) {
+            let Some(art_idx) = pipeline.get_art_idx() else { continue };
+            let inside = pipeline.contains(art_objs[art_idx].data.matrix, camera, self.near);
+            pipeline_changed |= pipeline.set_camera_inside(inside);
+        }
+        let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+        for pipeline in self.pipelines.iter_mut(1).chain(caches).chain(self.fullscreen_pipeline.as_mut()) {
+            // only a hot reloaded shader is new, the others already ran before
+            let reloaded = pipeline.reload_shaders(false);
+            pipeline_changed |= reloaded;
+            if pipeline.get_pipeline().is_none() {
                 pipeline.update_pipeline(self.device.clone(), self.viewport.clone())
                     .context("failed to update pipeline")?;
-                pipeline_changed |= pipeline.get_pipeline().is_some();
+                if pipeline.get_pipeline().is_some() {
+                    pipeline_changed = true;
+                    if let Some(art_idx) = pipeline.get_art_idx().filter(|_| reloaded) {
+                        self.watchdog.watch(art_idx);
+                    }
+                }
+            }
+        }
+
+        pipeline_changed |= self.update_compare_pipelines(art_objs)?;
+        let scene = self.pipelines.scene.iter_mut().map(|pipeline| (pipeline, false));
+        let mirror = self.pipelines.mirror.iter_mut().map(|pipeline| (pipeline, true));
+        for (pipeline, is_mirror) in scene.chain(mirror) {
+            let Some(art_idx) = pipeline.get_art_idx() else { continue };
+            let art_obj = &art_objs[art_idx];
+            let in_pass = if is_mirror { art_obj.passes.mirror } else { art_obj.passes.scene };
+            let hidden = art_obj.hidden || !in_pass;
+            if pipeline.hidden != hidden {
+                pipeline.hidden = hidden;
+                pipeline_changed = true;
             }
         }
 
@@ -493,14 +1333,21 @@ impl App {
         }) {
             if art_obj.enable_pipeline != pipeline.enable_pipeline {
                 pipeline.enable_pipeline = art_obj.enable_pipeline;
-                pipeline.set_shaders(art_obj.shader_vert.clone(), art_obj.shader_frag.clone());
+                if !pipeline.is_compare() {
+                    pipeline.set_shaders(art_obj.shader_vert.clone(), art_obj.shader_frag.clone());
+                }
                 pipeline_changed = true;
             }
         }
 
+        pipeline_changed |= self.update_art_caches(art_objs)?;
+        pipeline_changed |= self.update_fullscreen_pipeline()?;
+
+        timings.shader_reload = lap(&mut stage_start);
         if pipeline_changed {
             self.update_command_buffers();
         }
+        timings.command_buffers = lap(&mut stage_start);
 
         let (image_i, suboptimal, acquire_future) =
             match swapchain::acquire_next_image(self.swapchain.clone(), None)
@@ -516,11 +1363,31 @@ impl App {
 
         let mut swapchain_dirty = suboptimal;
 
-        // wait for the fence related to this image to finish
-        // (normally this would be the oldest fence)
-        if let Some(image_fence) = &self.fences[image_i] {
-            image_fence.wait(None).context("failed to wait for fence")?;
+        // wait for the oldest frame in flight, whose resources are reused for this one
+        let frame_i = self.frame_i;
+        let wait_start = Instant::now();
+        let mut timed_out = false;
+        if let Some(image_fence) = &self.fences[frame_i] {
+            match image_fence.wait(Some(FRAME_TIMEOUT)).map_err(Validated::unwrap) {
+                Ok(()) => (),
+                Err(VulkanError::Timeout) => timed_out = true,
+                Err(err) => return Err(err).context("failed to wait for fence"),
+            }
+        }
+        for art_idx in self.watchdog.finished(frame_i, wait_start.elapsed()) {
+            self.stop_runaway(art_objs, art_idx);
         }
+        if let Some(image_fence) = self.fences[frame_i].as_ref().filter(|_| timed_out) {
+            // the runaway exhibits are only stopped for the next frames, this one still has to finish
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+        }
+        let second_view = match second_view.filter(|_| self.second_view) {
+            Some((target, view_matrix)) => {
+                let acquired = if target.prepare()? { target.acquire()? } else { None };
+                acquired.map(|acquired| (target, view_matrix, acquired))
+            }
+            None => None,
+        };
 
         let previous_future = match self.fences[self.previous_fence_i].clone() {
             None => {
@@ -530,41 +1397,187 @@ impl App {
             }
             Some(fence) => fence.boxed(),
         };
+        timings.acquire = lap(&mut stage_start);
 
-        self.update_uniform_buffer(image_i, time, art_objs);
-
-        let mut subpasses = vec![
-            self.command_buffers_mirror[image_i].clone(),
-            self.command_buffers_scene[image_i].clone(),
-        ];
-        if let Some(gui) = gui {
-            subpasses.push(gui.draw_on_subpass_image(self.swapchain.image_extent()));
+        let frame = FrameInfo {
+            time,
+            delta_time: self.last_time.map_or(0., |last_time| (time - last_time).max(0.)),
+            index: self.frame_index,
+            extent: self.render_extent(),
+        };
+        self.last_time = Some(time);
+        self.frame_index = self.frame_index.wrapping_add(1);
+        let views = eye_views(self.view_matrix, self.ipd, self.stereo.mode());
+        let eye_count = views.len();
+        let [width, height] = self.render_extent();
+        let aspect_ratio = width as f32 / height as f32;
+        for (eye, &view_matrix) in views.iter().enumerate() {
+            let slot = eye * self.fences.len() + frame_i;
+            self.update_uniform_buffer(slot, view_matrix, aspect_ratio, frame, art_objs);
+        }
+        let second_view_slot = eye_count * self.fences.len() + frame_i;
+        if let Some((target, view_matrix, _)) = second_view.as_ref() {
+            // rendered at the aspect ratio of the window, so it is not distorted when scaled to it
+            let [width, height] = target.window().inner_size().into();
+            let aspect_ratio = width as f32 / height as f32;
+            self.update_uniform_buffer(second_view_slot, *view_matrix, aspect_ratio, frame, art_objs);
         }
+        timings.uniforms += lap(&mut stage_start);
+        let visible_pipelines = || {
+            self.pipelines.scene.iter().filter(|pipeline| pipeline.enable_pipeline && !pipeline.hidden)
+        };
+        let previous_frame = visible_pipelines()
+            .any(|pipeline| pipeline.uses_previous_frame())
+            .then(|| self.previous_frames[(frame_i + 1) % self.previous_frames.len()].view.image().clone());
+        let scene_input = self.scene_input.as_ref()
+            .filter(|_| visible_pipelines().any(|pipeline| pipeline.uses_scene_input()));
+
+        let eye_pass = |slot: usize, view_matrix: Mat4| EyePass {
+            subpasses: vec![
+                self.command_buffers_mirror[slot].clone(),
+                self.command_buffers_scene[slot].clone(),
+            ],
+            scene_input: scene_input.map(|scene_input| scene_input.pass(slot)),
+            art_caches: self.art_caches.iter()
+                .filter_map(|cache| cache.refresh(slot, time, view_matrix))
+                .collect(),
+        };
+        let eyes = views.iter()
+            .enumerate()
+            .map(|(eye, &view_matrix)| eye_pass(eye * self.fences.len() + frame_i, view_matrix))
+            .collect::<Vec<_>>();
+        let color_grading = self.color_grading.as_ref()
+            .filter(|grading| (grading.has_lut() && self.grading_strength > 0.) || !self.output.is_neutral())
+            .map(|grading| (grading, frame_i, self.grading_strength, self.output));
+        // the old frame of a transition is only captured with a single eye
+        let last_frame = self.last_target.filter(|_| eye_count == 1).map(|idx| self.render_targets[idx].clone());
+        let transition_frame = match self.transition.as_mut() {
+            Some(transition) => transition.next_frame(last_frame)?,
+            None => None,
+        };
+        let transition = self.transition.as_ref().zip(transition_frame);
+        // the second view reuses the framebuffer after the main view was transferred to its swapchain
+        let second_view = match second_view {
+            Some((target, view_matrix, (image_i, image, acquire_future))) => {
+                let command_buffer = get_primary_command_buffer(
+                    &self.command_buffer_allocator,
+                    &self.queue,
+                    self.framebuffers[frame_i].clone(),
+                    vec![eye_pass(second_view_slot, view_matrix)],
+                    self.render_targets[frame_i].clone(),
+                    image,
+                    self.blit_filter,
+                    self.clear_colors(),
+                    None,
+                    &Stereo::Off,
+                    color_grading,
+                    None,
+                    None,
+                )?;
+                Some((target, image_i, acquire_future, command_buffer))
+            }
+            None => None,
+        };
+        // the render target is only needed to scale the frame or to read it back afterwards
+        let direct = self.swapchain_framebuffers.get(image_i).cloned().filter(|_| {
+            eye_count == 1 && previous_frame.is_none() && color_grading.is_none() && self.transition.is_none()
+        });
+        let rendered_directly = direct.is_some();
+        let (framebuffer, gui_framebuffer, render_target) = match direct {
+            Some(framebuffer) => {
+                (framebuffer, self.swapchain_gui_framebuffers[image_i].clone(), self.images[image_i].clone())
+            }
+            None => (
+                self.framebuffers[frame_i].clone(),
+                self.gui_framebuffers[frame_i].clone(),
+                self.render_targets[frame_i].clone(),
+            ),
+        };
+        let gui = gui.map(|gui| (gui_framebuffer, gui.draw_on_subpass_image(self.render_extent())));
         let command_buffer = get_primary_command_buffer(
             &self.command_buffer_allocator,
             &self.queue,
-            self.framebuffers[image_i].clone(),
-            subpasses,
+            framebuffer,
+            eyes,
+            render_target,
+            self.images[image_i].clone(),
+            self.blit_filter,
+            self.clear_colors(),
+            previous_frame,
+            &self.stereo,
+            color_grading,
+            gui,
+            transition,
         )?;
+        let screenshot = self.screenshot_pending.take().and_then(|path| {
+            Screenshot::record(
+                self.images[image_i].clone(),
+                path,
+                &self.queue,
+                self.command_buffer_allocator.clone(),
+                self.memory_allocator.clone(),
+            ).inspect_err(|err| log::error!("failed to record screenshot: {err:?}")).ok()
+        });
 
-        let future = previous_future
-            .join(acquire_future)
+        let mut future = previous_future.join(acquire_future).boxed();
+        let second_view = match second_view {
+            Some((target, image_i, acquire_future, command_buffer)) => {
+                future = future.join(acquire_future).boxed();
+                Some((target, image_i, command_buffer))
+            }
+            None => None,
+        };
+        if let Some(upload_command_buffer) = upload_command_buffer {
+            future = future
+                .then_execute(self.queue.clone(), upload_command_buffer)
+                .context("failed to execute uploads")?
+                .boxed();
+        }
+        let mut future = future
             .then_execute(self.queue.clone(), command_buffer)
             .context("failed to execute future")?
+            .boxed();
+        let screenshot = match screenshot {
+            Some((screenshot, copy_command_buffer)) => {
+                future = future
+                    .then_execute(self.queue.clone(), copy_command_buffer)
+                    .context("failed to execute screenshot copy")?
+                    .boxed();
+                Some(screenshot)
+            }
+            None => None,
+        };
+        let mut future = future
             .then_swapchain_present(
                 self.queue.clone(),
                 SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_i as u32),
             )
-            .boxed()
-            .then_signal_fence_and_flush();
+            .boxed();
+        let second_target = match second_view {
+            Some((target, image_i, command_buffer)) => {
+                future = future
+                    .then_execute(self.queue.clone(), command_buffer)
+                    .context("failed to execute second view")?
+                    .then_swapchain_present(self.queue.clone(), target.present_info(image_i))
+                    .boxed();
+                Some(target)
+            }
+            None => None,
+        };
+        let future = future.then_signal_fence_and_flush();
+        self.watchdog.submitted(frame_i);
 
-        self.fences[image_i] = match future.map_err(Validated::unwrap) {
+        self.fences[frame_i] = match future.map_err(Validated::unwrap) {
             // We need to call .boxed() on the future at some point to get a dyn GpuFuture.
             // To do this it needs to be wrapped in an Arc, even if it is not send/sync.
             #[allow(clippy::arc_with_non_send_sync)]
             Ok(value) => Some(Arc::new(value)),
             Err(VulkanError::OutOfDate) => {
+                // either swapchain may be out of date
                 swapchain_dirty = true;
+                if let Some(target) = second_target {
+                    target.dirty = true;
+                }
                 None
             }
             Err(e) => {
@@ -572,19 +1585,330 @@ impl App {
                 None
             }
         };
+        timings.submit = lap(&mut stage_start);
+
+        // not part of the timings as it only happens when requested
+        if let (Some(screenshot), Some(fence)) = (screenshot, &self.fences[frame_i]) {
+            fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+            if let Err(err) = screenshot.save() {
+                log::error!("failed to save screenshot: {err:?}");
+            }
+        }
 
-        self.previous_fence_i = image_i;
+        self.cpu_timings = timings;
+        self.previous_fence_i = frame_i;
+        // only a frame in a render target can be captured by a transition
+        self.last_target = (!rendered_directly).then_some(frame_i);
+        self.frame_i = (frame_i + 1) % self.fences.len();
         Ok(swapchain_dirty)
     }
 
-    fn get_pipeline_order(pipelines: &[MyPipeline], art_objs: &[ArtObject]) -> Vec<usize> {
+    /// Restores the previous shader of the exhibit at `art_idx` after its pipeline made the GPU time
+    /// explode, or else reports the exhibit to be disabled. Takes effect on the next draw.
+    fn stop_runaway(&mut self, art_objs: &[ArtObject], art_idx: usize) {
+        let name = &art_objs[art_idx].name;
+        // the pipelines sharing the shader pick up the restored module in `reload_shaders`
+        let restored = self.pipelines.scene.iter()
+            .find(|pipeline| pipeline.get_art_idx() == Some(art_idx) && !pipeline.is_compare())
+            .is_some_and(MyPipeline::rollback_shaders);
+        if restored {
+            log::error!("the new shader of {name} made the GPU time explode, restored the previous one");
+        } else {
+            log::error!("{name} made the GPU time explode, disabling it");
+            self.runaway_exhibits.push(art_idx);
+        }
+    }
+
+    /// Exhibits whose pipeline made the GPU time explode since the last call,
+    /// they should be disabled before the next draw.
+    pub fn take_runaway_exhibits(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.runaway_exhibits)
+    }
+
+    /// Time spent in the stages of the last completed `draw`.
+    pub fn cpu_timings(&self) -> CpuTimings {
+        self.cpu_timings
+    }
+
+    /// Lets a shader that is not used by a pipeline compile and reload when its file changes.
+    pub fn register_shader(&self, shader: &Arc<HotShader>) {
+        shader.set_device(self.device.clone());
+        watch_shaders([shader.clone()]);
+    }
+
+    /// Renders the samples of a compiled audio shader, see `HotShader::new_sound`.
+    pub fn render_sound(&self, module: Arc<ShaderModule>) -> anyhow::Result<Vec<f32>> {
+        sound::render(
+            module,
+            self.queue.clone(),
+            self.command_buffer_allocator.clone(),
+            self.memory_allocator.clone(),
+            self.descriptor_set_allocator.clone(),
+        )
+    }
+
+    /// Recompiles the shaders of all exhibits, also those whose file did not change.
+    pub fn reload_shaders(&mut self) {
+        log::info!("reloading all shaders");
+        let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+        for pipeline in self.pipelines.iter_mut(1).chain(caches).chain(self.fullscreen_pipeline.as_mut()) {
+            pipeline.reload_shaders(true);
+        }
+    }
+
+    /// Recreates the buffers of the exhibits at `art_indices` after their model was replaced.
+    pub fn reload_models(&mut self, art_objs: &[ArtObject], art_indices: &[usize]) -> anyhow::Result<()> {
+        // recreated from the scene pipeline with the new geometry on the next draw
+        self.art_caches.retain(|cache| cache.art_idx().is_none_or(|idx| !art_indices.contains(&idx)));
+        self.fullscreen_pipeline = None;
+        let mut geometries = HashMap::new();
+        for pipeline in self.pipelines.scene.iter_mut().chain(self.pipelines.mirror.iter_mut()) {
+            let Some(art_idx) = pipeline.get_art_idx().filter(|idx| art_indices.contains(idx)) else { continue };
+            let geometry = match geometries.get(&art_idx) {
+                Some(geometry) => Geometry::clone(geometry),
+                None => {
+                    let art_obj = &art_objs[art_idx];
+                    let vertex_type = if art_obj.model.has_tex_coords {
+                        VertexType::VertexTex
+                    } else {
+                        VertexType::VertexNorm
+                    };
+                    let geometry = Geometry::from_model(
+                        &art_obj.model,
+                        vertex_type,
+                        self.memory_allocator.clone(),
+                        art_obj.container_scale,
+                    ).with_context(|| format!("failed to parse model of {}", art_obj.name))?;
+                    geometries.entry(art_idx).or_insert(geometry).clone()
+                }
+            };
+            pipeline.set_geometry(geometry);
+        }
+        Ok(())
+    }
+
+    /// Swaps placeholders for the textures that finished decoding.
+    fn update_textures(&mut self, art_objs: &[ArtObject]) -> anyhow::Result<()> {
+        while let Some((path, result)) = self.texture_loader.try_recv() {
+            let texture = result.and_then(|image| Texture::from_image(
+                image,
+                self.device.clone(),
+                self.queue.clone(),
+                self.command_buffer_allocator.clone(),
+                self.memory_allocator.clone(),
+            ));
+            let texture = match texture {
+                Ok(texture) => texture,
+                Err(err) => {
+                    log::error!("failed to load texture {}: {err:?}", path.display());
+                    continue;
+                }
+            };
+            log::debug!("loaded texture {}", path.display());
+
+            // we need to wait here before we can update the descriptor sets
+            for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
+                image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+            }
+            let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+            for pipeline in self.pipelines.iter_mut(0).chain(caches).chain(self.fullscreen_pipeline.as_mut()) {
+                let uses_texture = pipeline.get_art_idx().is_some_and(|idx| {
+                    matches!(&art_objs[idx].texture, Some(TextureSource::File(p)) if *p == path)
+                });
+                if uses_texture {
+                    pipeline.set_textures(vec![texture.clone()])?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the next frame as PNG to `path`.
+    pub fn request_screenshot(&mut self, path: PathBuf) {
+        self.screenshot_pending = Some(path);
+    }
+
+    /// Sets the keyboard state that is uploaded to the keyboard texture before the next frame.
+    pub fn set_keyboard(&mut self, image: RgbaImage) {
+        self.keyboard_pending = Some(image);
+    }
+
+    /// Returns a command buffer uploading the latest webcam frame to the texture of the next frame
+    /// and the keyboard state if any of them changed. The webcam textures are recreated if the frame size changed.
+    fn record_uploads(
+        &mut self,
+        art_objs: &[ArtObject],
+    ) -> anyhow::Result<Option<Arc<PrimaryAutoCommandBuffer>>> {
+        if let Some(frame) = self.webcam.as_ref().and_then(Webcam::take_frame) {
+            self.update_webcam_textures(&frame, art_objs)?;
+            self.webcam_uploaded.fill(false);
+            self.webcam_frame = Some(frame);
+        }
+        let frame_i = self.frame_i;
+        let frame = self.webcam_frame.as_ref().filter(|_| !self.webcam_uploaded[frame_i]);
+        if frame.is_none() && self.keyboard_pending.is_none() {
+            return Ok(None);
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.command_buffer_allocator.clone(),
+            self.queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        if let Some(image) = self.keyboard_pending.take() {
+            self.keyboard_texture.upload(&image, &mut builder)?;
+        }
+        if let Some(frame) = frame {
+            self.webcam_textures[frame_i].upload(frame, &mut builder)?;
+            self.webcam_uploaded[frame_i] = true;
+        }
+        Ok(Some(builder.build()?))
+    }
+
+    /// Recreates the webcam textures if the frame size changed.
+    fn update_webcam_textures(&mut self, frame: &RgbaImage, art_objs: &[ArtObject]) -> anyhow::Result<()> {
+        let extent = [frame.width(), frame.height()];
+        if self.webcam_textures.first().is_some_and(|texture| texture.extent() == extent) {
+            return Ok(());
+        }
+
+        log::debug!("creating webcam textures with size {extent:?}");
+        let textures = (0..self.fences.len())
+            .map(|_| StreamingTexture::new(extent, self.device.clone(), self.memory_allocator.clone()))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("failed to create webcam texture")?;
+
+        // we need to wait here before we can update the descriptor sets
+        for image_fence in self.fences.iter().filter_map(|fence| fence.as_ref()) {
+            image_fence.wait(Some(FENCE_TIMEOUT)).context("failed to wait for fence")?;
+        }
+        let caches = self.art_caches.iter_mut().flat_map(ArtCache::pipelines_mut);
+        for pipeline in self.pipelines.iter_mut(0).chain(caches).chain(self.fullscreen_pipeline.as_mut()) {
+            let uses_webcam = pipeline.get_art_idx()
+                .is_some_and(|idx| art_objs[idx].texture == Some(TextureSource::Webcam));
+            if uses_webcam {
+                pipeline.set_textures(textures.iter().map(|texture| texture.texture.clone()).collect())?;
+            }
+        }
+        self.webcam_uploaded = vec![false; textures.len()];
+        self.webcam_textures = textures;
+        Ok(())
+    }
+
+    /// Creates, updates or removes the pipelines of exhibits that are compared with another shader.
+    /// Returns `true` if the command buffers need to be recorded again.
+    fn update_compare_pipelines(&mut self, art_objs: &[ArtObject]) -> anyhow::Result<bool> {
+        let scene = &mut self.pipelines.scene;
+        let mirror_count = self.pipelines.mirror.len();
+        let old_count = scene.len();
+        scene.retain(|pipeline| {
+            !pipeline.is_compare() || pipeline.get_art_idx()
+                .and_then(|idx| art_objs[idx].compare.as_ref())
+                .is_some_and(|compare| Arc::ptr_eq(&compare.shader_frag, pipeline.fs()))
+        });
+        let mut changed = scene.len() != old_count;
+
+        for idx in 0..mirror_count {
+            let Some(art_idx) = scene[idx].get_art_idx() else { continue };
+            let compare = art_objs[art_idx].compare.as_ref();
+            let pipeline = &mut scene[idx];
+            if pipeline.set_split(compare.map(|compare| Split::Left(compare.split))) {
+                changed = true;
+                if pipeline.get_pipeline().is_none() {
+                    pipeline.update_pipeline(self.device.clone(), self.viewport.clone())
+                        .context("failed to update pipeline")?;
+                }
+            }
+
+            let Some(compare) = compare else { continue };
+            let compare_pipeline = scene[mirror_count..].iter_mut()
+                .find(|pipeline| pipeline.get_art_idx() == Some(art_idx));
+            if let Some(compare_pipeline) = compare_pipeline {
+                changed |= compare_pipeline.set_split(Some(Split::Right(compare.split)));
+            } else {
+                log::debug!("comparing {} with {}", art_objs[art_idx].name, compare.label);
+                let compare_pipeline = scene[idx].compare_variant(
+                    compare.shader_frag.clone(),
+                    compare.split,
+                    self.device.clone(),
+                    self.viewport.clone(),
+                ).context("failed to create compare pipeline")?;
+                scene.push(compare_pipeline);
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Creates or removes the caches of exhibits that have a refresh rate, see `ArtCache`.
+    /// Compared exhibits are not cached. Returns `true` if the command buffers need to be recorded again.
+    fn update_art_caches(&mut self, art_objs: &[ArtObject]) -> anyhow::Result<bool> {
+        let refresh_rate = |art_idx: usize| {
+            let art_obj = &art_objs[art_idx];
+            art_obj.refresh_rate.filter(|_| art_obj.enable_pipeline && art_obj.compare.is_none())
+        };
+        let old_count = self.art_caches.len();
+        self.art_caches.retain(|cache| cache.art_idx().and_then(refresh_rate).is_some());
+        let mut changed = self.art_caches.len() != old_count;
+
+        for scene in &self.pipelines.scene[..self.pipelines.mirror.len()] {
+            let Some(art_idx) = scene.get_art_idx() else { continue };
+            let Some(refresh_rate) = refresh_rate(art_idx) else { continue };
+            if let Some(cache) = self.art_caches.iter_mut().find(|cache| cache.art_idx() == Some(art_idx)) {
+                cache.set_refresh_rate(refresh_rate);
+                changed |= cache.set_hidden(scene.hidden);
+                continue;
+            }
+            log::debug!("caching {} at {refresh_rate} Hz", art_objs[art_idx].name);
+            let cache = ArtCache::new(
+                scene,
+                self.composite_shader.clone(),
+                refresh_rate,
+                self.swapchain.image_format(),
+                self.render_targets[0].extent(),
+                self.uniform_slots(),
+                self.fences.len(),
+                self.device.clone(),
+                self.viewport.clone(),
+                self.memory_allocator.clone(),
+            ).with_context(|| format!("failed to create cache of {}", art_objs[art_idx].name))?;
+            self.art_caches.push(cache);
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// Creates the pipeline of the exhibit shown full screen from its scene pipeline, see `set_fullscreen_art`.
+    /// Returns `true` if the command buffers need to be recorded again.
+    fn update_fullscreen_pipeline(&mut self) -> anyhow::Result<bool> {
+        let Some(art_idx) = self.fullscreen_art.filter(|_| self.fullscreen_pipeline.is_none()) else {
+            return Ok(false);
+        };
+        let scene = self.pipelines.scene[..self.pipelines.mirror.len()].iter()
+            .find(|pipeline| pipeline.get_art_idx() == Some(art_idx));
+        let Some(scene) = scene else { return Ok(false) };
+        let pipeline = scene.fullscreen_variant(
+            self.fullscreen_shader.clone(),
+            self.fullscreen_triangle.clone(),
+            self.device.clone(),
+            self.viewport.clone(),
+        ).context("failed to create fullscreen pipeline")?;
+        self.fullscreen_pipeline = Some(pipeline);
+        Ok(true)
+    }
+
+    /// The gallery is drawn first, then the opaque exhibits front to back so that hidden fragments
+    /// fail the depth test early and then the transparent exhibits back to front so they blend correctly.
+    pub(super) fn get_pipeline_order(pipelines: &[MyPipeline], art_objs: &[ArtObject]) -> Vec<usize> {
         let mut pipeline_order = (0..pipelines.len()).collect::<Vec<_>>();
         pipeline_order.sort_unstable_by(|&a, &b| {
             match (pipelines[a].get_art_idx(), pipelines[b].get_art_idx()) {
                 (Some(idx_a), Some(idx_b)) => {
                     let a = &art_objs[idx_a];
                     let b = &art_objs[idx_b];
-                    a.data.dist_to_camera_sqr.total_cmp(&b.data.dist_to_camera_sqr).reverse()
+                    let dist_order = a.data.dist_to_camera_sqr.total_cmp(&b.data.dist_to_camera_sqr);
+                    let dist_order = if a.is_transparent { dist_order.reverse() } else { dist_order };
+                    a.is_transparent.cmp(&b.is_transparent).then(dist_order)
                 }
                 (Some(_), None) => Ordering::Greater,
                 (None, Some(_)) => Ordering::Less,
@@ -594,17 +1918,31 @@ impl App {
         pipeline_order
     }
 
-    fn update_uniform_buffer(&self, image_idx: usize, time: f32, art_objs: &[ArtObject]) {
-        let aspect_ratio = self.swapchain.image_extent()[0] as f32
-            / self.swapchain.image_extent()[1] as f32;
+    fn uniform_block_data<'a>(pipeline: &MyPipeline, art_objs: &'a [ArtObject]) -> Option<&'a [u8]> {
+        let art_idx = pipeline.get_art_idx()?;
+        art_objs[art_idx].uniform_block.as_ref().map(|block| block.data())
+    }
+
+    /// `slot` is the frame in flight of the view `camera_view`, see `uniform_slots`.
+    fn update_uniform_buffer(
+        &self,
+        slot: usize,
+        camera_view: Mat4,
+        aspect_ratio: f32,
+        frame: FrameInfo,
+        art_objs: &[ArtObject],
+    ) {
         let proj = Mat4::perspective_rh(
             self.fov.to_radians(),
             aspect_ratio,
-            0.01,
-            200.0,
+            self.near,
+            self.far,
         );
+        let camera = CameraUniforms::new(camera_view, self.fov.to_radians(), aspect_ratio, self.near, self.far);
 
-        for pipeline in self.pipelines.scene.iter() {
+        // the gallery rendered for the scene input uses the same uniforms as in the scene
+        let scene_input = self.scene_input.as_ref().map(SceneInput::pipeline);
+        for pipeline in self.pipelines.scene.iter().chain(scene_input) {
             let data = pipeline.get_art_idx().map(|idx| art_objs[idx].data).unwrap_or_else(|| {
                 ArtData {
                     dist_to_camera_sqr: f32::MAX,
@@ -613,8 +1951,17 @@ impl App {
                     ..Default::default()
                 }
             });
+            // the view of the exhibit shown full screen maps it to the screen, see `fullscreen.vert`
+            let (view, proj) = match self.fullscreen_art.filter(|&idx| pipeline.get_art_idx() == Some(idx)) {
+                Some(idx) => {
+                    let mapping = FullscreenMapping::new(art_objs[idx].bounds(), aspect_ratio);
+                    (mapping.local_to_screen * data.matrix.inverse(), Mat4::IDENTITY)
+                }
+                None => (camera_view, proj),
+            };
             let data = Some(data);
-            let res = pipeline.update_uniform_buffer(image_idx, self.view_matrix, proj, time, data);
+            let block = Self::uniform_block_data(pipeline, art_objs);
+            let res = pipeline.update_uniform_buffer(slot, view, proj, frame, data, block);
             if let Err(err) = res {
                 log::error!("failed to update uniforms: {err:?}");
             }
@@ -627,7 +1974,7 @@ impl App {
 
         let mut reflect_matrix = Mat4::IDENTITY.to_cols_array_2d();
         reflect_matrix[0][0] = -1.0;
-        let view_matrix = self.view_matrix
+        let view_matrix = camera_view
             * Mat4::from_translation(clip_pos)
             * Mat4::from_cols_array_2d(&reflect_matrix)
             * Mat4::from_translation(-clip_pos);
@@ -636,6 +1983,18 @@ impl App {
         let clip_norm = view_matrix.transform_vector3(clip_norm).normalize();
         let clip_plane = clip_norm.extend(-clip_norm.dot(clip_pos));
         let proj = oblique_projection_matrix(proj, clip_plane);
+        let mirror_camera = CameraUniforms::new(view_matrix, self.fov.to_radians(), aspect_ratio, self.near, self.far);
+        let globals = GlobalUniforms {
+            light_pos: art_objs[0].data.light_pos.to_array(),
+            time: frame.time,
+            delta_time: frame.delta_time,
+            frame_index: frame.index,
+            reduced_motion: self.reduced_motion as u32,
+            detail: self.detail,
+        };
+        if let Err(err) = self.global_set.update(slot, globals, [camera, mirror_camera]) {
+            log::error!("failed to update global uniforms: {err:?}");
+        }
 
         for pipeline in self.pipelines.mirror.iter() {
             let data = pipeline.get_art_idx().map(|idx| art_objs[idx].data).unwrap_or_else(|| {
@@ -648,7 +2007,8 @@ impl App {
             });
 
             let data = Some(data);
-            let res = pipeline.update_uniform_buffer(image_idx, view_matrix, proj, time, data);
+            let block = Self::uniform_block_data(pipeline, art_objs);
+            let res = pipeline.update_uniform_buffer(slot, view_matrix, proj, frame, data, block);
             if let Err(err) = res {
                 log::error!("failed to update uniforms: {err:?}");
             }
@@ -656,21 +2016,59 @@ impl App {
     }
 
     fn update_command_buffers(&mut self) {
+        let slots = self.uniform_slots();
+        // cached exhibits are drawn from their cache instead, only the exhibit shown full screen is drawn
+        let scene = match self.fullscreen_art {
+            Some(_) => self.fullscreen_pipeline.iter().collect::<Vec<_>>(),
+            None => self.pipelines.order.iter()
+                .map(|&idx| &self.pipelines.scene[idx])
+                .map(|pipeline| {
+                    self.art_caches.iter()
+                        .find(|cache| pipeline.get_art_idx().is_some() && cache.art_idx() == pipeline.get_art_idx())
+                        .map_or(pipeline, ArtCache::composite)
+                })
+                .collect(),
+        };
         self.command_buffers_scene = get_command_buffers(
-            self.fences.len(),
+            slots,
             &self.command_buffer_allocator,
             &self.queue,
-            &self.pipelines.scene,
-            &self.pipelines.order,
+            &scene,
+            self.global_set.descriptor_sets(false),
             &self.subpass_scene,
+            self.viewport.extent,
         );
+        // compared exhibits are not split in the mirror
+        let mirror = self.pipelines.order.iter()
+            .filter_map(|&idx| self.pipelines.mirror.get(idx))
+            .filter(|_| self.fullscreen_art.is_none())
+            .collect::<Vec<_>>();
         self.command_buffers_mirror = get_command_buffers(
-            self.fences.len(),
+            slots,
             &self.command_buffer_allocator,
             &self.queue,
-            &self.pipelines.mirror,
-            &self.pipelines.order,
+            &mirror,
+            self.global_set.descriptor_sets(true),
             &self.subpass_mirror,
+            self.viewport.extent,
         );
+        if let Some(scene_input) = self.scene_input.as_mut() {
+            scene_input.update_command_buffers(
+                slots,
+                &self.command_buffer_allocator,
+                &self.queue,
+                self.global_set.descriptor_sets(false),
+                self.viewport.extent,
+            );
+        }
+        for cache in self.art_caches.iter_mut() {
+            cache.update_command_buffers(
+                slots,
+                &self.command_buffer_allocator,
+                &self.queue,
+                self.global_set.descriptor_sets(false),
+                self.viewport.extent,
+            );
+        }
     }
 }
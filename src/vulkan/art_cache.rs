@@ -0,0 +1,168 @@
+use super::{
+    helpers::get_command_buffers,
+    pipeline::MyPipeline,
+    scene_input::{offscreen_attachments, offscreen_render_pass},
+    shader::HotShader,
+};
+
+use std::{cell::Cell, sync::Arc};
+
+use anyhow::Context;
+use glam::Mat4;
+use vulkano::{
+    command_buffer::{allocator::StandardCommandBufferAllocator, SecondaryAutoCommandBuffer},
+    descriptor_set::DescriptorSet,
+    device::{Device, Queue},
+    format::Format,
+    memory::allocator::StandardMemoryAllocator,
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{Framebuffer, RenderPass, Subpass},
+};
+
+/// An exhibit that is rendered to its own color and depth at a lower rate than the frames,
+/// see `crate::art::ArtObject::refresh_rate`. The scene draws it every frame from the cache instead.
+/// Both pipelines share the uniform buffers with the scene pipeline of the exhibit.
+///
+/// Each uniform slot has its own cache, so a frame does not render into the cache an earlier frame
+/// in flight still samples and every view keeps its own refresh interval.
+pub struct ArtCache {
+    render_pass: Arc<RenderPass>,
+    /// One per uniform slot.
+    framebuffers: Vec<Arc<Framebuffer>>,
+    frames_in_flight: usize,
+    /// Draws the exhibit into the cache.
+    pipeline: MyPipeline,
+    /// Draws the cache into the scene.
+    composite: MyPipeline,
+    /// Seconds between refreshes.
+    interval: f32,
+    /// Time and view of the last refresh per view, `None` if the next frame has to refresh the cache.
+    refreshed: Vec<Cell<Option<(f32, Mat4)>>>,
+    /// Set for the slots of a view when it is refreshed, until the frame of the slot rendered it.
+    pending: Vec<Cell<bool>>,
+    command_buffers: Vec<Arc<SecondaryAutoCommandBuffer>>,
+}
+
+impl ArtCache {
+    /// `scene` is the pipeline of the exhibit in the scene subpass. The `slots` are ordered by view
+    /// and then by frame in flight, like the uniform slots of `super::VkApp`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        scene: &MyPipeline,
+        composite_fs: Arc<HotShader>,
+        refresh_rate: f32,
+        color_format: Format,
+        extent: [u32; 3],
+        slots: usize,
+        frames_in_flight: usize,
+        device: Arc<Device>,
+        viewport: Viewport,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        let render_pass = offscreen_render_pass(device.clone(), color_format)?;
+        let mut framebuffers = Vec::with_capacity(slots);
+        let mut textures = Vec::with_capacity(slots);
+        for _ in 0..slots {
+            let (framebuffer, color, depth) =
+                offscreen_attachments(&render_pass, extent, device.clone(), memory_allocator.clone())?;
+            framebuffers.push(framebuffer);
+            textures.push([color, depth]);
+        }
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let pipeline = scene.cache_variant(subpass, device.clone(), viewport.clone())
+            .context("failed to create cache pipeline")?;
+        let composite = scene.composite_variant(composite_fs, textures, device, viewport)
+            .context("failed to create composite pipeline")?;
+        Ok(Self {
+            render_pass,
+            framebuffers,
+            frames_in_flight,
+            pipeline,
+            composite,
+            interval: 1. / refresh_rate,
+            refreshed: (0..slots.div_ceil(frames_in_flight)).map(|_| Cell::new(None)).collect(),
+            pending: (0..slots).map(|_| Cell::new(false)).collect(),
+            command_buffers: Vec::new(),
+        })
+    }
+
+    pub fn art_idx(&self) -> Option<usize> {
+        self.pipeline.get_art_idx()
+    }
+
+    /// The pipeline that is drawn in place of the scene pipeline of the exhibit.
+    pub fn composite(&self) -> &MyPipeline {
+        &self.composite
+    }
+
+    pub fn pipelines_mut(&mut self) -> [&mut MyPipeline; 2] {
+        [&mut self.pipeline, &mut self.composite]
+    }
+
+    pub fn set_refresh_rate(&mut self, refresh_rate: f32) {
+        self.interval = 1. / refresh_rate;
+    }
+
+    /// Follows the scene pipeline of the exhibit. Returns `true` if the command buffers need to be recorded again.
+    pub fn set_hidden(&mut self, hidden: bool) -> bool {
+        if self.composite.hidden == hidden {
+            return false;
+        }
+        self.composite.hidden = hidden;
+        true
+    }
+
+    /// Records the commands rendering the exhibit, the cache is refreshed on the next frame.
+    pub fn update_command_buffers(
+        &mut self,
+        count: usize,
+        command_buffer_allocator: &Arc<StandardCommandBufferAllocator>,
+        queue: &Arc<Queue>,
+        global_sets: &[Arc<DescriptorSet>],
+        extent: [f32; 2],
+    ) {
+        let subpass = Subpass::from(self.render_pass.clone(), 0).unwrap();
+        self.command_buffers = get_command_buffers(
+            count,
+            command_buffer_allocator,
+            queue,
+            &[&self.pipeline],
+            global_sets,
+            &subpass,
+            extent,
+        );
+        for refreshed in &self.refreshed {
+            refreshed.set(None);
+        }
+    }
+
+    /// Returns the framebuffer and the commands refreshing the cache of the uniform `slot`
+    /// if the interval of its view passed at `time` or the view differs from its last refresh.
+    /// The other frames in flight of the view refresh their caches when they come next.
+    pub fn refresh(
+        &self,
+        slot: usize,
+        time: f32,
+        view: Mat4,
+    ) -> Option<(Arc<Framebuffer>, Arc<SecondaryAutoCommandBuffer>)> {
+        if self.composite.hidden || !self.composite.enable_pipeline {
+            return None;
+        }
+        let view_idx = slot / self.frames_in_flight;
+        let refreshed = &self.refreshed[view_idx];
+        let due = refreshed.get().is_none_or(|(last_time, last_view)| {
+            last_view != view || time < last_time || time - last_time >= self.interval
+        });
+        if due {
+            refreshed.set(Some((time, view)));
+            let view_slots = view_idx * self.frames_in_flight..(view_idx + 1) * self.frames_in_flight;
+            for pending in &self.pending[view_slots] {
+                pending.set(true);
+            }
+        }
+        if !self.pending[slot].replace(false) {
+            return None;
+        }
+        Some((self.framebuffers[slot].clone(), self.command_buffers[slot].clone()))
+    }
+}
@@ -0,0 +1,506 @@
+//! Color grading of the finished frame with a 3D lookup table, so the look of the whole gallery
+//! can be changed without editing every shader, followed by the `OutputAdjustments` for the display.
+//!
+//! The table is loaded from an Adobe `.cube` file or from a strip image of `size` squares of
+//! `size` by `size` pixels side by side. Square `b` holds the blue value `b`, red grows to the
+//! right and green downwards. The GUI is drawn after the grading, so it keeps its colors.
+
+use super::{helpers::FENCE_TIMEOUT, texture::Texture};
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use image::{ImageReader, RgbaImage};
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, BlitImageInfo, CommandBufferUsage, CopyBufferToImageInfo, CopyImageInfo,
+        PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
+    },
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet},
+    device::{Device, Queue},
+    format::{Format, NumericFormat},
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    sync::GpuFuture,
+};
+
+/// Workgroup size of the compute shader in x and y.
+const LOCAL_SIZE: u32 = 8;
+
+mod cs_grading {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 450
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D frame;
+            layout(set = 0, binding = 1) uniform sampler3D lut;
+            layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D graded;
+
+            layout(push_constant) uniform PushConstants {
+                float strength;
+                // set if the frame has an sRGB format, its texels are then linear
+                uint srgb;
+                float exposure;
+                float contrast;
+                float saturation;
+                float gamma;
+            } pc;
+
+            vec3 to_srgb(vec3 color) {
+                return mix(color * 12.92, 1.055 * pow(color, vec3(1. / 2.4)) - 0.055, step(0.0031308, color));
+            }
+
+            vec3 to_linear(vec3 color) {
+                return mix(color / 12.92, pow((color + 0.055) / 1.055, vec3(2.4)), step(0.04045, color));
+            }
+
+            void main() {
+                ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pos, imageSize(graded)))) {
+                    return;
+                }
+                vec4 color = texelFetch(frame, pos, 0);
+                // lookup tables map the colors as they are sent to the display
+                vec3 encoded = clamp(pc.srgb != 0 ? to_srgb(color.rgb) : color.rgb, 0., 1.);
+                float size = float(textureSize(lut, 0).x);
+                vec3 mapped = texture(lut, (encoded * (size - 1.) + 0.5) / size).rgb;
+                mapped = mix(encoded, mapped, pc.strength);
+
+                // exposure scales the light, the other adjustments work on the encoded colors
+                mapped = to_srgb(to_linear(mapped) * exp2(pc.exposure));
+                mapped = (mapped - 0.5) * pc.contrast + 0.5;
+                float luminance = dot(mapped, vec3(0.2126, 0.7152, 0.0722));
+                mapped = mix(vec3(luminance), mapped, pc.saturation);
+                mapped = clamp(pow(max(mapped, 0.), vec3(1. / pc.gamma)), 0., 1.);
+                imageStore(graded, pos, vec4(pc.srgb != 0 ? to_linear(mapped) : mapped, color.a));
+            }
+        ",
+    }
+}
+
+/// Adjustments of the final colors for the display, e.g. to brighten the image of a projector.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputAdjustments {
+    /// Stops the light is brightened by, negative values darken it.
+    pub exposure: f32,
+    /// Scales the distance of the colors to middle gray.
+    pub contrast: f32,
+    /// 0 for grayscale, above 1 for more colorful images.
+    pub saturation: f32,
+    /// Values above 1 brighten the midtones.
+    pub gamma: f32,
+}
+
+impl OutputAdjustments {
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Default for OutputAdjustments {
+    fn default() -> Self {
+        Self { exposure: 0., contrast: 1., saturation: 1., gamma: 1. }
+    }
+}
+
+/// A 3D lookup table from one color to another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    /// Number of entries along each axis.
+    pub size: u32,
+    /// `size³` colors, red changes fastest, then green, then blue.
+    pub colors: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    /// Loads a `.cube` file or else a strip image, both through `crate::fs`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let is_cube = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("cube"));
+        let lut = if is_cube {
+            let text = crate::fs::read_to_string(path)?;
+            Self::parse_cube(&text)
+        } else {
+            let image = ImageReader::new(crate::fs::load(path)?)
+                .with_guessed_format()?
+                .decode()?
+                .into_rgba8();
+            Self::from_strip(&image)
+        };
+        lut.with_context(|| format!("failed to load lookup table {}", path.display()))
+    }
+
+    /// Maps every color to itself.
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let colors = (0..size.pow(3))
+            .map(|i| [i % size, i / size % size, i / (size * size)].map(|value| value as f32 / max))
+            .collect();
+        Self { size, colors }
+    }
+
+    pub fn parse_cube(text: &str) -> anyhow::Result<Self> {
+        let mut size = None;
+        let mut colors = Vec::new();
+        for (line_i, line) in text.lines().enumerate() {
+            let mut words = line.split_whitespace();
+            let Some(first) = words.next().filter(|word| !word.starts_with('#')) else { continue };
+            match first {
+                "LUT_3D_SIZE" => size = Some(words.next().unwrap_or_default().parse::<u32>()
+                    .with_context(|| format!("invalid size in line {}", line_i + 1))?),
+                "LUT_1D_SIZE" => anyhow::bail!("1D lookup tables are not supported"),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let expected = if first == "DOMAIN_MIN" { 0. } else { 1. };
+                    if parse_color(words, line_i)? != [expected; 3] {
+                        anyhow::bail!("only the domain from 0 to 1 is supported");
+                    }
+                }
+                // other keywords like TITLE do not matter
+                _ if first.starts_with(|c: char| c.is_ascii_alphabetic()) => {}
+                _ => colors.push(parse_color(line.split_whitespace(), line_i)?),
+            }
+        }
+        let size = size.context("missing LUT_3D_SIZE")?;
+        Self::new(size, colors)
+    }
+
+    pub fn from_strip(image: &RgbaImage) -> anyhow::Result<Self> {
+        let size = image.height();
+        if image.width() != size * size {
+            anyhow::bail!("a strip of height {size} must be {} pixels wide", size * size);
+        }
+        let colors = (0..size * size * size).map(|i| {
+            let [r, g, b] = [i % size, i / size % size, i / (size * size)];
+            let pixel = image.get_pixel(b * size + r, g);
+            [0, 1, 2].map(|channel| pixel[channel] as f32 / 255.)
+        }).collect();
+        Self::new(size, colors)
+    }
+
+    fn new(size: u32, colors: Vec<[f32; 3]>) -> anyhow::Result<Self> {
+        if size < 2 {
+            anyhow::bail!("the size must be at least 2");
+        }
+        let expected = size.pow(3) as usize;
+        if colors.len() != expected {
+            anyhow::bail!("expected {expected} colors for size {size}, found {}", colors.len());
+        }
+        Ok(Self { size, colors })
+    }
+}
+
+/// Three numbers in the line with the index `line_i`.
+fn parse_color<'a>(words: impl Iterator<Item = &'a str>, line_i: usize) -> anyhow::Result<[f32; 3]> {
+    let values = words.map(str::parse).collect::<Result<Vec<f32>, _>>()
+        .with_context(|| format!("invalid number in line {}", line_i + 1))?;
+    values.try_into().map_err(|_| anyhow::anyhow!("expected 3 numbers in line {}", line_i + 1))
+}
+
+/// The lookup table on the GPU with a compute shader that applies it and the output adjustments
+/// to a render target.
+pub struct ColorGrading {
+    pipeline: Arc<ComputePipeline>,
+    lut: Texture,
+    /// Unset if only the output is adjusted, `lut` is then the identity.
+    has_lut: bool,
+    /// One per frame in flight, so a frame does not overwrite the images of one still on the GPU.
+    frames: Vec<FrameImages>,
+    srgb: bool,
+}
+
+struct FrameImages {
+    /// Copy of the render target the shader reads from.
+    input: Texture,
+    /// Linear colors in a format that can be written from the shader, unlike the render target.
+    output: Arc<ImageView>,
+    descriptor_set: Arc<DescriptorSet>,
+}
+
+impl ColorGrading {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        lut: Option<&Lut>,
+        format: Format,
+        extent: [u32; 3],
+        frames_in_flight: usize,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let module = cs_grading::load(device.clone())?;
+        let entry = module.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+        let stage = PipelineShaderStageCreateInfo::new(entry);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+        )?;
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )?;
+
+        let has_lut = lut.is_some();
+        let identity = Lut::identity(2);
+        let lut = Self::upload(
+            lut.unwrap_or(&identity),
+            device.clone(),
+            queue,
+            command_buffer_allocator,
+            memory_allocator.clone(),
+        ).context("failed to upload lookup table")?;
+        let frames = (0..frames_in_flight).map(|_| FrameImages::new(
+            &pipeline,
+            &lut,
+            format,
+            extent,
+            device.clone(),
+            memory_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        )).collect::<anyhow::Result<_>>()?;
+        let srgb = format.numeric_format_color() == Some(NumericFormat::SRGB);
+        Ok(Self { pipeline, lut, has_lut, frames, srgb })
+    }
+
+    pub fn has_lut(&self) -> bool {
+        self.has_lut
+    }
+
+    /// Recreates the images of the size of the render target, the lookup table is kept.
+    pub fn resize(
+        &mut self,
+        format: Format,
+        extent: [u32; 3],
+        frames_in_flight: usize,
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<()> {
+        self.frames = (0..frames_in_flight).map(|_| FrameImages::new(
+            &self.pipeline,
+            &self.lut,
+            format,
+            extent,
+            device.clone(),
+            memory_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        )).collect::<anyhow::Result<_>>()?;
+        self.srgb = format.numeric_format_color() == Some(NumericFormat::SRGB);
+        Ok(())
+    }
+
+    /// Records the grading of `render_target` in place with the images of the frame in flight
+    /// `frame_i`, `strength` blends from 0 for the original colors to 1 for the colors of the table.
+    /// `adjustments` are applied afterwards.
+    pub fn apply(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame_i: usize,
+        render_target: Arc<Image>,
+        strength: f32,
+        adjustments: OutputAdjustments,
+    ) -> anyhow::Result<()> {
+        let FrameImages { input, output, descriptor_set } = &self.frames[frame_i % self.frames.len()];
+        let [width, height, _] = output.image().extent();
+        let push_constants = cs_grading::PushConstants {
+            strength,
+            srgb: self.srgb as u32,
+            exposure: adjustments.exposure,
+            contrast: adjustments.contrast,
+            saturation: adjustments.saturation,
+            // pow with an exponent of 1 / 0 would be infinite
+            gamma: adjustments.gamma.max(0.01),
+        };
+        builder
+            .copy_image(CopyImageInfo::images(render_target.clone(), input.view.image().clone()))?
+            .bind_pipeline_compute(self.pipeline.clone())?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                descriptor_set.clone(),
+            )?
+            .push_constants(self.pipeline.layout().clone(), 0, push_constants)?;
+        unsafe { builder.dispatch([width.div_ceil(LOCAL_SIZE), height.div_ceil(LOCAL_SIZE), 1]) }?;
+        // a blit converts the format, the sizes are the same
+        builder.blit_image(BlitImageInfo {
+            filter: Filter::Nearest,
+            ..BlitImageInfo::images(output.image().clone(), render_target)
+        })?;
+        Ok(())
+    }
+
+    fn upload(
+        lut: &Lut,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Texture> {
+        let upload_buffer = Buffer::from_iter(
+            memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            lut.colors.iter().flat_map(|&[r, g, b]| [to_f16(r), to_f16(g), to_f16(b), to_f16(1.)]),
+        )?;
+        let image = Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim3d,
+                // 8 bits would band smooth gradients
+                format: Format::R16G16B16A16_SFLOAT,
+                extent: [lut.size; 3],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        command_buffer.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(upload_buffer, image.clone()))?;
+        command_buffer.build()?
+            .execute(queue)?
+            .then_signal_fence_and_flush()?
+            .wait(Some(FENCE_TIMEOUT))?;
+
+        let sampler = Sampler::new(device, SamplerCreateInfo {
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+        })?;
+        Ok(Texture { view: ImageView::new_default(image)?, sampler })
+    }
+}
+
+impl FrameImages {
+    fn new(
+        pipeline: &Arc<ComputePipeline>,
+        lut: &Texture,
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let input = Texture::frame_copy(format, extent, device, memory_allocator.clone())?;
+        let output = ImageView::new_default(Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R16G16B16A16_SFLOAT,
+                extent,
+                usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?)?;
+        let descriptor_set = DescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, input.view.clone(), input.sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, lut.view.clone(), lut.sampler.clone()),
+                WriteDescriptorSet::image_view(2, output.clone()),
+            ],
+            [],
+        )?;
+        Ok(Self { input, output, descriptor_set })
+    }
+}
+
+/// Bits of the half precision float nearest to `value` clamped to the range from 0 to 1.
+fn to_f16(value: f32) -> u16 {
+    let value = value.clamp(0., 1.);
+    // below the smallest normal half the values are multiples of 2^-24
+    if value < 2f32.powi(-14) {
+        return (value * 2f32.powi(24)).round() as u16;
+    }
+    // round the mantissa to nearest even, a carry correctly increases the exponent
+    let bits = value.to_bits();
+    let rounded = bits + 0xfff + ((bits >> 13) & 1);
+    ((rounded >> 13) - ((127 - 15) << 10)) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::Rgba;
+
+    #[test]
+    fn parse_cube_file() {
+        let text = "\
+            # inverts red\n\
+            TITLE \"invert\"\n\
+            LUT_3D_SIZE 2\n\
+            DOMAIN_MIN 0 0 0\n\
+            \n\
+            1 0 0\n0 0 0\n1 1 0\n0 1 0\n\
+            1 0 1\n0 0 1\n1 1 1\n0 1 1\n";
+        let lut = Lut::parse_cube(text).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.colors[1], [0., 0., 0.]);
+        assert_eq!(lut.colors[6], [1., 1., 1.]);
+
+        assert!(Lut::parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(Lut::parse_cube("LUT_3D_SIZE 2\nDOMAIN_MAX 2 2 2\n").is_err());
+    }
+
+    #[test]
+    fn half_floats() {
+        assert_eq!(to_f16(0.), 0);
+        assert_eq!(to_f16(1.), 0x3c00);
+        assert_eq!(to_f16(0.5), 0x3800);
+        assert_eq!(to_f16(2.), 0x3c00);
+        assert_eq!(to_f16(-1.), 0);
+        assert_eq!(to_f16(2f32.powi(-24)), 1);
+        // 1 / 3 is 0x3555 rounded down
+        assert_eq!(to_f16(1. / 3.), 0x3555);
+    }
+
+    #[test]
+    fn read_strip() {
+        // identity table of size 2, the blue squares side by side
+        let image = RgbaImage::from_fn(4, 2, |x, y| {
+            let [r, g, b] = [x % 2, y, x / 2].map(|value| value as u8 * 255);
+            Rgba([r, g, b, 255])
+        });
+        let lut = Lut::from_strip(&image).unwrap();
+        for (i, color) in lut.colors.iter().enumerate() {
+            let expected = [i % 2, i / 2 % 2, i / 4].map(|value| value as f32);
+            assert_eq!(*color, expected);
+        }
+        assert_eq!(lut, Lut::identity(2));
+        assert!(Lut::from_strip(&RgbaImage::new(4, 4)).is_err());
+    }
+}
@@ -1,10 +1,12 @@
-use std::sync::Arc;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use vulkano::{
     instance::{
         debug::{
             DebugUtilsMessageSeverity, DebugUtilsMessageType, DebugUtilsMessenger,
-            DebugUtilsMessengerCallback, DebugUtilsMessengerCreateInfo,
+            DebugUtilsMessengerCallback, DebugUtilsMessengerCreateInfo, ValidationFeatureEnable,
         },
         Instance, InstanceExtensions,
     },
@@ -15,6 +17,30 @@ use vulkano::{
 const ENABLE_VALIDATION_LAYERS: bool = true;
 #[cfg(not(debug_assertions))]
 const ENABLE_VALIDATION_LAYERS: bool = false;
+/// Messages kept until they are taken, older ones are dropped.
+const MAX_PRINTF_MESSAGES: usize = 1000;
+
+static DEBUG_PRINTF: AtomicBool = AtomicBool::new(false);
+static PRINTF_MESSAGES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Enables `debugPrintfEXT` in shaders through the validation layer, also in release builds.
+/// Must be called before the `VkApp` is created.
+pub fn enable_debug_printf() {
+    DEBUG_PRINTF.store(true, Ordering::Relaxed);
+}
+
+pub fn debug_printf_enabled() -> bool {
+    DEBUG_PRINTF.load(Ordering::Relaxed)
+}
+
+/// Returns the output of `debugPrintfEXT` since the last call.
+pub fn take_printf_messages() -> VecDeque<String> {
+    PRINTF_MESSAGES.lock().map(|mut messages| std::mem::take(&mut *messages)).unwrap_or_default()
+}
+
+fn enable_validation_layers() -> bool {
+    ENABLE_VALIDATION_LAYERS || debug_printf_enabled()
+}
 
 pub fn check_layer_support<S>(library: &VulkanLibrary, layers: &[S]) -> Result<bool, VulkanError>
 where S: AsRef<str>
@@ -28,11 +54,12 @@ where S: AsRef<str>
 
 pub fn get_debug_extensions_and_layers() -> (InstanceExtensions, Vec<String>) {
     let extensions = InstanceExtensions {
-        ext_debug_utils: ENABLE_VALIDATION_LAYERS,
+        ext_debug_utils: enable_validation_layers(),
+        ext_validation_features: debug_printf_enabled(),
         ..InstanceExtensions::empty()
     };
 
-    let layers = if ENABLE_VALIDATION_LAYERS {
+    let layers = if enable_validation_layers() {
         vec!["VK_LAYER_KHRONOS_validation".to_owned()]
     } else {
         Vec::new()
@@ -41,10 +68,18 @@ pub fn get_debug_extensions_and_layers() -> (InstanceExtensions, Vec<String>) {
     (extensions, layers)
 }
 
+pub fn get_validation_features() -> Vec<ValidationFeatureEnable> {
+    if debug_printf_enabled() {
+        vec![ValidationFeatureEnable::DebugPrintf]
+    } else {
+        Vec::new()
+    }
+}
+
 pub fn setup_debug_callback(
     instance: Arc::<Instance>,
 ) -> Result<Option<DebugUtilsMessenger>, Validated<VulkanError>> {
-    if !ENABLE_VALIDATION_LAYERS {
+    if !enable_validation_layers() {
         return Ok(None);
     }
     unsafe {
@@ -61,6 +96,17 @@ pub fn setup_debug_callback(
                 ..DebugUtilsMessengerCreateInfo::user_callback(DebugUtilsMessengerCallback::new(
                     |message_severity, _message_type, callback_data| {
                         let message = &callback_data.message;
+                        if callback_data.message_id_name.is_some_and(|name| name.contains("DEBUG-PRINTF")) {
+                            // the printed text follows the location of the call
+                            let text = message.lines().last().unwrap_or_default().trim();
+                            if let Ok(mut messages) = PRINTF_MESSAGES.lock() {
+                                if messages.len() >= MAX_PRINTF_MESSAGES {
+                                    messages.pop_front();
+                                }
+                                messages.push_back(text.to_owned());
+                            }
+                            return;
+                        }
                         if message_severity
                             .intersects(DebugUtilsMessageSeverity::ERROR)
                         {
@@ -0,0 +1,550 @@
+use super::{helpers::FENCE_TIMEOUT, texture::Texture};
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo,
+        PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        DescriptorSet, WriteDescriptorSet,
+    },
+    device::{Device, DeviceOwned, Queue},
+    format::Format,
+    image::{
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::{ImageView, ImageViewCreateInfo, ImageViewType},
+        Image, ImageAspects, ImageCreateFlags, ImageCreateInfo, ImageSubresourceRange, ImageType,
+        ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::ShaderModule,
+    sync::GpuFuture,
+};
+
+use image::ImageReader;
+
+/// Face size of the cubemap the panorama is projected onto.
+const SKYBOX_SIZE: u32 = 512;
+/// Face size of the diffuse irradiance cubemap.
+const IRRADIANCE_SIZE: u32 = 32;
+/// Face size of the first mip level of the prefiltered specular cubemap.
+const PREFILTERED_SIZE: u32 = 128;
+/// Number of roughness levels stored in the mips of the prefiltered specular cubemap.
+/// Shaders sample it with `roughness * (PREFILTERED_MIP_LEVELS - 1)` as lod.
+const PREFILTERED_MIP_LEVELS: u32 = 5;
+/// Format of all generated cubemaps.
+const CUBE_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+/// Workgroup size of the compute shaders in x and y.
+const LOCAL_SIZE: u32 = 8;
+
+mod cs_equirect {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 450
+
+            #define PI 3.1415926535
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D equirect;
+            layout(set = 0, binding = 1, rgba16f) uniform writeonly image2DArray cube;
+
+            vec3 cube_dir(uint face, vec2 uv) {
+                uv = uv * 2.0 - 1.0;
+                switch (face) {
+                    case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+                    case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+                    case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+                    case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+                    case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+                    default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+                }
+            }
+
+            void main() {
+                ivec2 size = imageSize(cube).xy;
+                if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+                    return;
+                }
+                vec2 uv = (vec2(gl_GlobalInvocationID.xy) + 0.5) / vec2(size);
+                vec3 dir = cube_dir(gl_GlobalInvocationID.z, uv);
+                vec2 equirect_uv = vec2(atan(dir.z, dir.x) / (2.0 * PI) + 0.5, acos(dir.y) / PI);
+                imageStore(cube, ivec3(gl_GlobalInvocationID), texture(equirect, equirect_uv));
+            }
+        ",
+    }
+}
+
+mod cs_irradiance {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 450
+
+            #define PI 3.1415926535
+            #define SAMPLE_DELTA 0.05
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform samplerCube source;
+            layout(set = 0, binding = 1, rgba16f) uniform writeonly image2DArray cube;
+
+            vec3 cube_dir(uint face, vec2 uv) {
+                uv = uv * 2.0 - 1.0;
+                switch (face) {
+                    case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+                    case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+                    case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+                    case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+                    case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+                    default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+                }
+            }
+
+            void main() {
+                ivec2 size = imageSize(cube).xy;
+                if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+                    return;
+                }
+                vec2 uv = (vec2(gl_GlobalInvocationID.xy) + 0.5) / vec2(size);
+                vec3 normal = cube_dir(gl_GlobalInvocationID.z, uv);
+                vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+                vec3 right = normalize(cross(up, normal));
+                up = cross(normal, right);
+
+                // sample a blurry mip level so the coarse sampling does not alias
+                float lod = max(0.0, float(textureQueryLevels(source)) - 5.0);
+                vec3 irradiance = vec3(0.0);
+                float sample_count = 0.0;
+                for (float phi = 0.0; phi < 2.0 * PI; phi += SAMPLE_DELTA) {
+                    for (float theta = 0.0; theta < 0.5 * PI; theta += SAMPLE_DELTA) {
+                        vec3 tangent_dir = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+                        vec3 dir = tangent_dir.x * right + tangent_dir.y * up + tangent_dir.z * normal;
+                        irradiance += textureLod(source, dir, lod).rgb * cos(theta) * sin(theta);
+                        sample_count += 1.0;
+                    }
+                }
+                irradiance = PI * irradiance / sample_count;
+                imageStore(cube, ivec3(gl_GlobalInvocationID), vec4(irradiance, 1.0));
+            }
+        ",
+    }
+}
+
+mod cs_prefilter {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 450
+
+            #define PI 3.1415926535
+            #define SAMPLE_COUNT 512u
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform samplerCube source;
+            layout(set = 0, binding = 1, rgba16f) uniform writeonly image2DArray cube;
+
+            layout(push_constant) uniform PushConstants {
+                float roughness;
+            } pc;
+
+            vec3 cube_dir(uint face, vec2 uv) {
+                uv = uv * 2.0 - 1.0;
+                switch (face) {
+                    case 0: return normalize(vec3( 1.0, -uv.y, -uv.x));
+                    case 1: return normalize(vec3(-1.0, -uv.y,  uv.x));
+                    case 2: return normalize(vec3( uv.x,  1.0,  uv.y));
+                    case 3: return normalize(vec3( uv.x, -1.0, -uv.y));
+                    case 4: return normalize(vec3( uv.x, -uv.y,  1.0));
+                    default: return normalize(vec3(-uv.x, -uv.y, -1.0));
+                }
+            }
+
+            // from <https://learnopengl.com/PBR/IBL/Specular-IBL>
+            float radical_inverse_vdc(uint bits) {
+                bits = (bits << 16u) | (bits >> 16u);
+                bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+                bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+                bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+                bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+                return float(bits) * 2.3283064365386963e-10;
+            }
+
+            vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+                float a = roughness * roughness;
+                float phi = 2.0 * PI * xi.x;
+                float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+                float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+                vec3 h = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+                vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+                vec3 tangent = normalize(cross(up, normal));
+                vec3 bitangent = cross(normal, tangent);
+                return normalize(tangent * h.x + bitangent * h.y + normal * h.z);
+            }
+
+            float distribution_ggx(float n_dot_h, float roughness) {
+                float a = roughness * roughness;
+                float a2 = a * a;
+                float denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+                return a2 / (PI * denom * denom);
+            }
+
+            void main() {
+                ivec2 size = imageSize(cube).xy;
+                if (any(greaterThanEqual(gl_GlobalInvocationID.xy, uvec2(size)))) {
+                    return;
+                }
+                vec2 uv = (vec2(gl_GlobalInvocationID.xy) + 0.5) / vec2(size);
+                vec3 normal = cube_dir(gl_GlobalInvocationID.z, uv);
+
+                float source_size = float(textureSize(source, 0).x);
+                float sa_texel = 4.0 * PI / (6.0 * source_size * source_size);
+                vec3 color = vec3(0.0);
+                float total_weight = 0.0;
+                for (uint i = 0u; i < SAMPLE_COUNT; ++i) {
+                    vec2 xi = vec2(float(i) / float(SAMPLE_COUNT), radical_inverse_vdc(i));
+                    vec3 h = importance_sample_ggx(xi, normal, pc.roughness);
+                    vec3 l = normalize(2.0 * dot(normal, h) * h - normal);
+                    float n_dot_l = dot(normal, l);
+                    if (n_dot_l > 0.0) {
+                        // sample from a mip level matching the solid angle of the sample
+                        // to avoid bright dots, see <https://developer.nvidia.com/gpugems/gpugems3/part-iii-rendering/chapter-20-gpu-based-importance-sampling>
+                        float n_dot_h = max(dot(normal, h), 0.0);
+                        float pdf = distribution_ggx(n_dot_h, pc.roughness) * 0.25 + 0.0001;
+                        float sa_sample = 1.0 / (float(SAMPLE_COUNT) * pdf + 0.0001);
+                        float lod = pc.roughness == 0.0 ? 0.0 : 0.5 * log2(sa_sample / sa_texel);
+                        color += textureLod(source, l, lod).rgb * n_dot_l;
+                        total_weight += n_dot_l;
+                    }
+                }
+                imageStore(cube, ivec3(gl_GlobalInvocationID), vec4(color / total_weight, 1.0));
+            }
+        ",
+    }
+}
+
+/// Cubemaps generated from an equirectangular HDR panorama used for image based lighting.
+///
+/// Art shaders can access them at the following bindings of the global set 1:
+/// - 2: `samplerCube` with the environment itself, with mipmaps
+/// - 3: `samplerCube` with the diffuse irradiance
+/// - 4: `samplerCube` with the specular radiance prefiltered for increasing roughness per mip level
+///
+/// See `assets/shaders/includes/ibl.glsl` for helpers.
+#[derive(Clone)]
+pub struct EnvironmentMap {
+    pub skybox: Arc<ImageView>,
+    pub irradiance: Arc<ImageView>,
+    pub prefiltered: Arc<ImageView>,
+    pub sampler: Arc<Sampler>,
+}
+
+impl EnvironmentMap {
+    /// Loads an equirectangular panorama, usually a `.hdr` file, and generates the cubemaps.
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let image = ImageReader::new(crate::fs::load(&path)?)
+            .with_guessed_format()
+            .with_context(|| format!("failed to open environment map at {:?}", path.as_ref()))?
+            .decode()
+            .with_context(|| format!("failed to decode environment map at {:?}", path.as_ref()))?
+            .into_rgba32f();
+        Self::from_pixels(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            device,
+            queue,
+            command_buffer_allocator,
+            memory_allocator,
+            descriptor_set_allocator,
+        )
+    }
+
+    /// Creates a black environment, used when no panorama could be loaded
+    /// so that shaders using the bindings still work.
+    pub fn fallback(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        Self::from_pixels(
+            &[0., 0., 0., 1.],
+            1,
+            1,
+            device,
+            queue,
+            command_buffer_allocator,
+            memory_allocator,
+            descriptor_set_allocator,
+        )
+    }
+
+    /// Generates the cubemaps from RGBA32F pixels of an equirectangular panorama.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_pixels(
+        pixels: &[f32],
+        width: u32,
+        height: u32,
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        debug_assert_eq!(pixels.len(), (width * height * 4) as usize);
+        log::debug!("generating environment map from {width}x{height} panorama");
+
+        let skybox_mip_levels = SKYBOX_SIZE.ilog2() + 1;
+        let skybox = Self::create_cube(
+            memory_allocator.clone(),
+            SKYBOX_SIZE,
+            skybox_mip_levels,
+            ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+        )?;
+        let irradiance = Self::create_cube(memory_allocator.clone(), IRRADIANCE_SIZE, 1, ImageUsage::empty())?;
+        let prefiltered = Self::create_cube(
+            memory_allocator.clone(),
+            PREFILTERED_SIZE,
+            PREFILTERED_MIP_LEVELS,
+            ImageUsage::empty(),
+        )?;
+
+        // 32 bit float formats are not guaranteed to support linear filtering,
+        // the panorama is only read once so nearest is good enough here
+        let sampler_nearest = Sampler::new(device.clone(), SamplerCreateInfo {
+            mag_filter: Filter::Nearest,
+            min_filter: Filter::Nearest,
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..SamplerCreateInfo::simple_repeat_linear()
+        })?;
+        let sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..SamplerCreateInfo::simple_repeat_linear()
+        })?;
+
+        // upload the panorama and project it onto the skybox
+        let equirect = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R32G32B32A32_SFLOAT,
+                extent: [width, height, 1],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        let upload_buffer = Buffer::from_iter(
+            memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            pixels.iter().copied(),
+        )?;
+
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator.clone(),
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        command_buffer.copy_buffer_to_image(
+            CopyBufferToImageInfo::buffer_image(upload_buffer, equirect.clone()),
+        )?;
+        Self::dispatch(
+            &mut command_buffer,
+            cs_equirect::load(device.clone())?,
+            descriptor_set_allocator.clone(),
+            ImageView::new_default(equirect)?,
+            sampler_nearest,
+            Self::storage_view(&skybox, 0)?,
+            SKYBOX_SIZE,
+            None,
+        )?;
+        Self::submit(command_buffer, queue.clone())?;
+
+        // the mipmaps of the skybox are sampled while convolving
+        Texture::generate_mipmaps(
+            device.physical_device(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            skybox.clone(),
+            skybox.extent(),
+            CUBE_FORMAT,
+            skybox_mip_levels,
+        )?;
+        let skybox_view = Self::sampled_view(&skybox)?;
+
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        Self::dispatch(
+            &mut command_buffer,
+            cs_irradiance::load(device.clone())?,
+            descriptor_set_allocator.clone(),
+            skybox_view.clone(),
+            sampler.clone(),
+            Self::storage_view(&irradiance, 0)?,
+            IRRADIANCE_SIZE,
+            None,
+        )?;
+        let prefilter_module = cs_prefilter::load(device.clone())?;
+        for level in 0..PREFILTERED_MIP_LEVELS {
+            let roughness = level as f32 / (PREFILTERED_MIP_LEVELS - 1) as f32;
+            Self::dispatch(
+                &mut command_buffer,
+                prefilter_module.clone(),
+                descriptor_set_allocator.clone(),
+                skybox_view.clone(),
+                sampler.clone(),
+                Self::storage_view(&prefiltered, level)?,
+                (PREFILTERED_SIZE >> level).max(1),
+                Some(cs_prefilter::PushConstants { roughness }),
+            )?;
+        }
+        Self::submit(command_buffer, queue)?;
+
+        Ok(Self {
+            skybox: skybox_view,
+            irradiance: Self::sampled_view(&irradiance)?,
+            prefiltered: Self::sampled_view(&prefiltered)?,
+            sampler,
+        })
+    }
+
+    fn create_cube(
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        size: u32,
+        mip_levels: u32,
+        extra_usage: ImageUsage,
+    ) -> anyhow::Result<Arc<Image>> {
+        let image = Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                flags: ImageCreateFlags::CUBE_COMPATIBLE,
+                image_type: ImageType::Dim2d,
+                format: CUBE_FORMAT,
+                extent: [size, size, 1],
+                array_layers: 6,
+                mip_levels,
+                usage: ImageUsage::STORAGE | ImageUsage::SAMPLED | extra_usage,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        Ok(image)
+    }
+
+    /// Creates a view of all six faces of a single mip level to write to from a compute shader.
+    fn storage_view(image: &Arc<Image>, mip_level: u32) -> anyhow::Result<Arc<ImageView>> {
+        let view = ImageView::new(image.clone(), ImageViewCreateInfo {
+            view_type: ImageViewType::Dim2dArray,
+            subresource_range: ImageSubresourceRange {
+                aspects: ImageAspects::COLOR,
+                mip_levels: mip_level..mip_level + 1,
+                array_layers: 0..6,
+            },
+            usage: ImageUsage::STORAGE,
+            ..ImageViewCreateInfo::from_image(image)
+        })?;
+        Ok(view)
+    }
+
+    fn sampled_view(image: &Arc<Image>) -> anyhow::Result<Arc<ImageView>> {
+        let view = ImageView::new(image.clone(), ImageViewCreateInfo {
+            view_type: ImageViewType::Cube,
+            usage: ImageUsage::SAMPLED,
+            ..ImageViewCreateInfo::from_image(image)
+        })?;
+        Ok(view)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn dispatch(
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        module: Arc<ShaderModule>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        source: Arc<ImageView>,
+        sampler: Arc<Sampler>,
+        target: Arc<ImageView>,
+        size: u32,
+        push_constants: Option<cs_prefilter::PushConstants>,
+    ) -> anyhow::Result<()> {
+        let device = module.device().clone();
+        let entry = module.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+        let stage = PipelineShaderStageCreateInfo::new(entry);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+        )?;
+        let pipeline = ComputePipeline::new(
+            device,
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+        )?;
+        let descriptor_set = DescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, source, sampler),
+                WriteDescriptorSet::image_view(1, target),
+            ],
+            [],
+        )?;
+
+        command_buffer
+            .bind_pipeline_compute(pipeline)?
+            .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?;
+        if let Some(push_constants) = push_constants {
+            command_buffer.push_constants(layout, 0, push_constants)?;
+        }
+        let group_count = size.div_ceil(LOCAL_SIZE);
+        unsafe { command_buffer.dispatch([group_count, group_count, 6]) }?;
+        Ok(())
+    }
+
+    fn submit(
+        command_buffer: AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        queue: Arc<Queue>,
+    ) -> anyhow::Result<()> {
+        command_buffer.build()?
+            .execute(queue)?
+            .then_signal_fence_and_flush()?
+            .wait(Some(FENCE_TIMEOUT))?;
+        Ok(())
+    }
+}
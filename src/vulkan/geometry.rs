@@ -1,4 +1,4 @@
-use crate::model::obj::NormalizedObj;
+use crate::model::obj::{self, NormalizedObj};
 use super::vertex::*;
 
 use std::sync::Arc;
@@ -17,8 +17,8 @@ pub struct Geometry {
     vertex_type: VertexType,
     vertex_buffer: Subbuffer<[u8]>,
     index_buffer: Subbuffer<[u32]>,
-    _extent_min: Vec3,
-    _extent_max: Vec3,
+    extent_min: Vec3,
+    extent_max: Vec3,
 }
 
 impl Geometry {
@@ -46,17 +46,39 @@ impl Geometry {
                 let (vb, ib) = Self::model_to_buffers::<VertexNorm>(model, scale, memory_allocator)?;
                 (vb.into_bytes(), ib)
             }
+            VertexType::VertexTex => {
+                let (vb, ib) = Self::model_to_buffers::<VertexTex>(model, scale, memory_allocator)?;
+                (vb.into_bytes(), ib)
+            }
         };
 
         Ok(Self {
             vertex_type,
             vertex_buffer,
             index_buffer,
-            _extent_min: min,
-            _extent_max: max,
+            // a negative scale mirrors the model
+            extent_min: (min * scale).min(max * scale),
+            extent_max: (min * scale).max(max * scale),
         })
     }
 
+    /// One triangle in normalized device coordinates that covers the screen, see `MyPipeline::fullscreen_variant`.
+    pub fn fullscreen_triangle(memory_allocator: Arc<StandardMemoryAllocator>) -> anyhow::Result<Self> {
+        let model = NormalizedObj {
+            indices: vec![0, 1, 2],
+            vertices: [[-1., -1., 0.], [3., -1., 0.], [-1., 3., 0.]]
+                .map(|pos_coords| obj::Vertex { pos_coords, ..Default::default() })
+                .to_vec(),
+            ..Default::default()
+        };
+        Self::from_model(&model, VertexType::VertexPos, memory_allocator, Vec3::ONE)
+    }
+
+    /// Corners of the bounding box of the vertices.
+    pub fn extent(&self) -> (Vec3, Vec3) {
+        (self.extent_min, self.extent_max)
+    }
+
     pub fn vertex_buffer(&self) -> &Subbuffer<[u8]> {
         &self.vertex_buffer
     }
@@ -69,6 +91,7 @@ impl Geometry {
         match self.vertex_type {
             VertexType::VertexPos => VertexPos::per_vertex().definition(entry),
             VertexType::VertexNorm => VertexNorm::per_vertex().definition(entry),
+            VertexType::VertexTex => VertexTex::per_vertex().definition(entry),
         }
     }
 
@@ -0,0 +1,219 @@
+use super::{
+    environment::EnvironmentMap,
+    noise::NoiseTextures,
+    texture::Texture,
+};
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use glam::Mat4;
+use vulkano::{
+    buffer::{allocator::SubbufferAllocator, BufferContents, Subbuffer},
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        layout::{
+            DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType,
+        },
+        DescriptorSet, WriteDescriptorSet,
+    },
+    device::Device,
+    shader::ShaderStages,
+};
+
+/// Index of the set with the uniform buffers and textures of each exhibit.
+pub const ART_SET: u32 = 0;
+/// Index of the set that is shared by all pipelines of a subpass.
+pub const GLOBAL_SET: u32 = 1;
+
+const GLOBALS_BINDING: u32 = 0;
+const CAMERA_BINDING: u32 = 1;
+const ENVIRONMENT_BINDINGS: [u32; 3] = [2, 3, 4];
+const NOISE_BINDINGS: [u32; 3] = [5, 6, 7];
+const KEYBOARD_BINDING: u32 = 8;
+
+/// Scene wide values, laid out like the block in `assets/shaders/includes/globals.glsl`.
+#[derive(BufferContents, Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct GlobalUniforms {
+    pub light_pos: [f32; 4],
+    /// Time of the gallery, it keeps running for paused exhibits, see `crate::art::ArtData::shader_time`.
+    pub time: f32,
+    /// Seconds since the previous frame.
+    pub delta_time: f32,
+    pub frame_index: u32,
+    /// 1 if exhibits should avoid fast motion and flashing, see `App::reduced_motion`.
+    pub reduced_motion: u32,
+    /// From 0 to 1, see `App::detail`.
+    pub detail: f32,
+}
+
+/// The camera of a subpass, laid out like the block in `assets/shaders/includes/camera.glsl`.
+#[derive(BufferContents, Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct CameraUniforms {
+    /// Position in world space, w is 1.
+    pub position: [f32; 4],
+    /// Normalized view direction in world space, w is 0.
+    pub forward: [f32; 4],
+    /// Vertical field of view in radians.
+    pub fov: f32,
+    pub aspect_ratio: f32,
+    /// Distances of the clip planes.
+    pub near: f32,
+    pub far: f32,
+}
+
+impl CameraUniforms {
+    pub fn new(view: Mat4, fov: f32, aspect_ratio: f32, near: f32, far: f32) -> Self {
+        let inverse = view.inverse();
+        Self {
+            position: inverse.w_axis.to_array(),
+            forward: (-inverse.z_axis.truncate().normalize()).extend(0.).to_array(),
+            fov,
+            aspect_ratio,
+            near,
+            far,
+        }
+    }
+}
+
+/// Descriptor set 1 with the resources that are the same for every exhibit, it is bound again only
+/// when the pipeline layout changes. Set 0 only holds the data of the exhibit itself.
+///
+/// Bindings:
+/// - 0: `GlobalUniforms`
+/// - 1: `CameraUniforms`, the reflected camera in the mirror subpass
+/// - 2 to 4: the `EnvironmentMap`
+/// - 5 to 7: the `NoiseTextures`
+/// - 8: the keyboard texture, see `crate::keyboard`
+pub struct GlobalSet {
+    layout: Arc<DescriptorSetLayout>,
+    environment: EnvironmentMap,
+    noise: NoiseTextures,
+    keyboard: Texture,
+    globals: Vec<Subbuffer<GlobalUniforms>>,
+    /// Camera of the scene and the mirror subpass per frame in flight.
+    cameras: [Vec<Subbuffer<CameraUniforms>>; 2],
+    descriptor_sets: [Vec<Arc<DescriptorSet>>; 2],
+}
+
+impl GlobalSet {
+    /// Layout of the global set, every pipeline layout uses it for `GLOBAL_SET`
+    /// so the set stays compatible when switching pipelines.
+    pub fn layout_create_info() -> DescriptorSetLayoutCreateInfo {
+        let buffer = DescriptorSetLayoutBinding {
+            stages: ShaderStages::all_graphics(),
+            ..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::UniformBuffer)
+        };
+        let texture = DescriptorSetLayoutBinding {
+            stages: ShaderStages::all_graphics(),
+            ..DescriptorSetLayoutBinding::descriptor_type(DescriptorType::CombinedImageSampler)
+        };
+        let textures = ENVIRONMENT_BINDINGS.into_iter().chain(NOISE_BINDINGS).chain([KEYBOARD_BINDING]);
+        let bindings = [GLOBALS_BINDING, CAMERA_BINDING].into_iter()
+            .map(|binding| (binding, buffer.clone()))
+            .chain(textures.map(|binding| (binding, texture.clone())))
+            .collect::<BTreeMap<_, _>>();
+        DescriptorSetLayoutCreateInfo {
+            bindings,
+            ..Default::default()
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        environment: EnvironmentMap,
+        noise: NoiseTextures,
+        keyboard: Texture,
+        device: Arc<Device>,
+        frames_in_flight: usize,
+        uniform_buffer_allocator: &SubbufferAllocator,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let layout = DescriptorSetLayout::new(device, Self::layout_create_info())?;
+        let mut global_set = Self {
+            layout,
+            environment,
+            noise,
+            keyboard,
+            globals: Vec::new(),
+            cameras: [Vec::new(), Vec::new()],
+            descriptor_sets: [Vec::new(), Vec::new()],
+        };
+        global_set.set_frames_in_flight(frames_in_flight, uniform_buffer_allocator, descriptor_set_allocator)?;
+        Ok(global_set)
+    }
+
+    /// Allocates new buffers and descriptor sets for every frame in flight.
+    /// The old ones must not be in use anymore.
+    pub fn set_frames_in_flight(
+        &mut self,
+        frames_in_flight: usize,
+        uniform_buffer_allocator: &SubbufferAllocator,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<()> {
+        self.globals = (0..frames_in_flight)
+            .map(|_| uniform_buffer_allocator.allocate_sized::<GlobalUniforms>())
+            .collect::<Result<_, _>>()?;
+        for pass in 0..2 {
+            self.cameras[pass] = (0..frames_in_flight)
+                .map(|_| uniform_buffer_allocator.allocate_sized::<CameraUniforms>())
+                .collect::<Result<_, _>>()?;
+        }
+        self.allocate_descriptor_sets(descriptor_set_allocator)
+    }
+
+    fn allocate_descriptor_sets(
+        &mut self,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<()> {
+        for pass in 0..2 {
+            self.descriptor_sets[pass] = (0..self.globals.len())
+                .map(|i| DescriptorSet::new(
+                    descriptor_set_allocator.clone(),
+                    self.layout.clone(),
+                    self.write_sets(pass, i),
+                    [],
+                ))
+                .collect::<Result<_, _>>()?;
+        }
+        Ok(())
+    }
+
+    fn write_sets(&self, pass: usize, idx: usize) -> Vec<WriteDescriptorSet> {
+        let env = &self.environment;
+        let env_views = [&env.skybox, &env.irradiance, &env.prefiltered];
+        let noise = [&self.noise.noise2d, &self.noise.noise3d, &self.noise.blue_noise];
+        let mut write_sets = vec![
+            WriteDescriptorSet::buffer(GLOBALS_BINDING, self.globals[idx].clone()),
+            WriteDescriptorSet::buffer(CAMERA_BINDING, self.cameras[pass][idx].clone()),
+            WriteDescriptorSet::image_view_sampler(
+                KEYBOARD_BINDING,
+                self.keyboard.view.clone(),
+                self.keyboard.sampler.clone(),
+            ),
+        ];
+        for (binding, view) in ENVIRONMENT_BINDINGS.into_iter().zip(env_views) {
+            write_sets.push(WriteDescriptorSet::image_view_sampler(binding, view.clone(), env.sampler.clone()));
+        }
+        for (binding, Texture { view, sampler }) in NOISE_BINDINGS.into_iter().zip(noise) {
+            write_sets.push(WriteDescriptorSet::image_view_sampler(binding, view.clone(), sampler.clone()));
+        }
+        write_sets
+    }
+
+    /// Writes the uniforms of the frame `idx`, `cameras` are the scene and the mirror camera.
+    pub fn update(&self, idx: usize, globals: GlobalUniforms, cameras: [CameraUniforms; 2]) -> anyhow::Result<()> {
+        *self.globals[idx].write()? = globals;
+        for (buffer, camera) in self.cameras.iter().zip(cameras) {
+            *buffer[idx].write()? = camera;
+        }
+        Ok(())
+    }
+
+    /// One set per frame in flight for the scene or the mirror subpass.
+    pub fn descriptor_sets(&self, mirror: bool) -> &[Arc<DescriptorSet>] {
+        &self.descriptor_sets[mirror as usize]
+    }
+}
@@ -1,33 +1,48 @@
-use super::pipeline::MyPipeline;
+use super::{
+    app::ClearColors,
+    color_grading::{ColorGrading, OutputAdjustments},
+    debug::debug_printf_enabled,
+    global_set::{ART_SET, GLOBAL_SET},
+    pipeline::MyPipeline,
+    stereo::Stereo,
+    transition::{Transition, TransitionFrame},
+};
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use glam::{Mat4, Vec4};
+use anyhow::Context;
+use glam::{BVec3, Mat4, Vec2, Vec3, Vec4};
 use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator,
-        AutoCommandBufferBuilder, CommandBufferInheritanceInfo, CommandBufferUsage, PrimaryAutoCommandBuffer, RenderPassBeginInfo,
-        SecondaryAutoCommandBuffer, SubpassBeginInfo, SubpassContents,
+        AutoCommandBufferBuilder, CommandBufferInheritanceInfo, CommandBufferUsage, CopyImageInfo,
+        PrimaryAutoCommandBuffer, RenderPassBeginInfo, SecondaryAutoCommandBuffer, SubpassBeginInfo, SubpassContents,
     },
+    descriptor_set::DescriptorSet,
     device::{
         physical::{PhysicalDevice, PhysicalDeviceType},
-        Device, DeviceExtensions, Queue, QueueFlags
+        Device, DeviceExtensions, DeviceFeatures, Queue, QueueFlags
     },
-    format::{ClearValue, Format},
+    format::{ClearValue, Format, FormatFeatures},
     image::{
         view::ImageView,
+        sampler::Filter,
         sys::ImageCreateInfo,
         Image, ImageFormatInfo, ImageTiling, ImageType, ImageUsage, SampleCount,
     },
     instance::Instance,
     memory::allocator::{AllocationCreateInfo, MemoryAllocator},
     pipeline::{
-        Pipeline, PipelineBindPoint,
+        Pipeline, PipelineBindPoint, PipelineLayout,
     },
     render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
     swapchain::{Surface, Swapchain},
 };
 
+/// Longest wait for the GPU to finish submitted work, it is considered hung afterwards.
+pub const FENCE_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
@@ -73,10 +88,22 @@ pub mod fs {
 
             // each element in an array takes up the same space as a whole vec4
             // use a vec4 as better alternative
+            // options must have `crate::art::OPTION_VEC4S` elements
             layout(set = 0, binding = 1) uniform UniformBufferObject {
                 vec4 light_pos;
-                vec4 options[2];
+                vec4 options[8];
                 float time;
+                // changes each time the options are randomized
+                float seed;
+                // local hit point of the cursor, w is 0 on miss, 1 on hover and 2 while pressed
+                vec4 mouse;
+                // seconds since the last frame
+                float delta_time;
+                uint frame_index;
+                // size in pixels of the screen rectangle covered by the exhibit
+                vec2 footprint;
+                // size in pixels of the render target, gl_FragCoord.xy is the pixel of the fragment
+                vec2 resolution;
             } ubo;
 
             // from <https://stackoverflow.com/a/10625698>
@@ -107,14 +134,70 @@ pub mod fs {
     }
 }
 
+/// Replaces `fs` on devices without geometry shaders, which `gl_PrimitiveID` needs.
+/// Colors the triangles by their position instead of their index.
+pub mod fs_flat {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: r"
+            #version 450
+
+            layout(location = 0) in vec3 fragPos;
+            layout(location = 1) in vec3 fragNorm;
+
+            layout(location = 0) out vec4 outColor;
+
+            // must match the uniform buffer of fs
+            layout(set = 0, binding = 1) uniform UniformBufferObject {
+                vec4 light_pos;
+                vec4 options[8];
+                float time;
+                float seed;
+                vec4 mouse;
+                float delta_time;
+                uint frame_index;
+                vec2 footprint;
+                vec2 resolution;
+            } ubo;
+
+            float random(vec2 p) {
+                vec2 k1 = vec2(
+                    23.14069263277926, // e^pi
+                    2.665144142690225  // 2^sqrt(2)
+                );
+                return fract(cos(dot(p, k1)) * 12345.6789);
+            }
+
+            void main() {
+                float cell = dot(floor(fragPos * 2.0), vec3(1.0, 57.0, 113.0));
+                vec3 color = vec3(
+                    random(vec2(cell, 1.1)),
+                    random(vec2(cell, 2.2)),
+                    random(vec2(cell, 3.3))
+                );
+
+                vec3 normal = normalize(fragNorm);
+                vec3 to_light_dir = normalize(ubo.light_pos.xyz - fragPos);
+                float ambient_coef = 0.4;
+                float diffuse_coef = max(0.0, dot(normal, to_light_dir));
+                color = color * min(2.0, ambient_coef + diffuse_coef);
+
+                outColor = vec4(color, 1.0);
+            }
+        ",
+    }
+}
+
+/// Picks the device with a graphics queue family, preferring discrete GPUs.
+/// The queue family must be able to present to `surface` unless it is `None`.
 pub fn select_physical_device(
     instance: &Arc<Instance>,
-    surface: &Arc<Surface>,
+    surface: Option<&Arc<Surface>>,
     device_extensions: &DeviceExtensions,
-) -> (Arc<PhysicalDevice>, u32) {
+) -> anyhow::Result<(Arc<PhysicalDevice>, u32)> {
     instance
         .enumerate_physical_devices()
-        .expect("failed to enumerate physical devices")
+        .context("failed to enumerate physical devices")?
         .filter(|p| p.supported_extensions().contains(device_extensions))
         .filter_map(|p| {
             p.queue_family_properties()
@@ -122,7 +205,7 @@ pub fn select_physical_device(
                 .enumerate()
                 .position(|(i, q)| {
                     q.queue_flags.contains(QueueFlags::GRAPHICS)
-                        && p.surface_support(i as u32, surface).unwrap_or(false)
+                        && surface.is_none_or(|surface| p.surface_support(i as u32, surface).unwrap_or(false))
                 })
                 .map(|q| (p, q as u32))
         })
@@ -133,7 +216,34 @@ pub fn select_physical_device(
             PhysicalDeviceType::Cpu => 3,
             _ => 4,
         })
-        .expect("no device available")
+        .context("no device available")
+}
+
+/// Features nothing needs, they are enabled if available.
+pub fn optional_device_features() -> DeviceFeatures {
+    DeviceFeatures {
+        // gl_PrimitiveID in fragment shaders, missing on MoltenVK
+        geometry_shader: true,
+        // exhibits seen from inside their container are not cut by the near plane
+        depth_clamp: true,
+        // the validation layer writes the output of debugPrintfEXT to a buffer
+        vertex_pipeline_stores_and_atomics: debug_printf_enabled(),
+        fragment_stores_and_atomics: debug_printf_enabled(),
+        ..DeviceFeatures::empty()
+    }
+}
+
+/// Returns the filter for scaling images of `format` or `None` if they cannot be blitted.
+pub fn select_blit_filter(device: &PhysicalDevice, format: Format) -> anyhow::Result<Option<Filter>> {
+    let features = device.format_properties(format)?.optimal_tiling_features;
+    if !features.contains(FormatFeatures::BLIT_SRC | FormatFeatures::BLIT_DST) {
+        return Ok(None);
+    }
+    if features.contains(FormatFeatures::SAMPLED_IMAGE_FILTER_LINEAR) {
+        Ok(Some(Filter::Linear))
+    } else {
+        Ok(Some(Filter::Nearest))
+    }
 }
 
 pub fn select_msaa_sample_count(device: &PhysicalDevice) -> SampleCount {
@@ -200,16 +310,46 @@ pub fn get_render_pass(
                 depth_stencil: {depth_stencil},
                 input: [mirror_color, mirror_depth],
             },
-            // Gui render pass
-            {
-                color: [color],
-                depth_stencil: {},
-                input: [],
-            },
         ],
     ).unwrap()
 }
 
+/// Draws the interface on top of the finished frame, after it was copied and graded,
+/// so the feedback shaders and the color grading do not see it.
+pub fn get_gui_render_pass(device: Arc<Device>, format: Format) -> Arc<RenderPass> {
+    vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            color: {
+                format: format,
+                samples: 1,
+                load_op: Load,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {},
+        },
+    ).unwrap()
+}
+
+/// One framebuffer of `render_pass` from `get_gui_render_pass` per image.
+pub fn get_gui_framebuffers(images: &[Arc<Image>], render_pass: &Arc<RenderPass>) -> Vec<Arc<Framebuffer>> {
+    images
+        .iter()
+        .map(|image| {
+            Framebuffer::new(
+                render_pass.clone(),
+                FramebufferCreateInfo {
+                    attachments: vec![ImageView::new_default(image.clone()).unwrap()],
+                    ..Default::default()
+                },
+            ).unwrap()
+        })
+        .collect()
+}
+
 pub fn color_usage() -> ImageUsage {
     ImageUsage::COLOR_ATTACHMENT
         | ImageUsage::INPUT_ATTACHMENT
@@ -243,6 +383,30 @@ pub fn get_image_view(
     ).unwrap()
 }
 
+/// Images the frames are rendered to when they can not be rendered straight into the swapchain images,
+/// e.g. to continue at the old size while the window is resized, see `get_swapchain_framebuffers`.
+pub fn get_render_targets(
+    format: Format,
+    extent: [u32; 3],
+    count: usize,
+    memory_allocator: Arc<dyn MemoryAllocator>,
+) -> Vec<Arc<Image>> {
+    (0..count).map(|_| {
+        Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent,
+                // the color grading writes the graded frame back
+                usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        ).unwrap()
+    }).collect()
+}
+
 pub fn get_framebuffers(
     images: &[Arc<Image>],
     depth_format: Format,
@@ -302,27 +466,132 @@ pub fn get_framebuffers(
         .collect::<Vec<_>>()
 }
 
+/// Framebuffers rendering straight into the swapchain `images`, sharing the other attachments
+/// with `framebuffer`. The images must have the size of its render target.
+pub fn get_swapchain_framebuffers(images: &[Arc<Image>], framebuffer: &Arc<Framebuffer>) -> Vec<Arc<Framebuffer>> {
+    images
+        .iter()
+        .map(|image| {
+            let mut attachments = framebuffer.attachments().to_vec();
+            // the final color is the last attachment
+            attachments.pop();
+            attachments.push(ImageView::new_default(image.clone()).unwrap());
+            Framebuffer::new(
+                framebuffer.render_pass().clone(),
+                FramebufferCreateInfo { attachments, ..Default::default() },
+            ).unwrap()
+        })
+        .collect()
+}
+
+/// The commands rendering the frame of one eye, see `super::stereo`.
+pub struct EyePass {
+    /// Subpasses without commands are skipped.
+    pub subpasses: Vec<Arc<SecondaryAutoCommandBuffer>>,
+    pub scene_input: Option<(Arc<Framebuffer>, Arc<SecondaryAutoCommandBuffer>)>,
+    /// Exhibits whose cache is refreshed before the scene, see `super::art_cache`.
+    pub art_caches: Vec<(Arc<Framebuffer>, Arc<SecondaryAutoCommandBuffer>)>,
+}
+
+/// Records the render pass of every eye into the render target of `framebuffer` and transfers
+/// the result to `swapchain_image` as `stereo` demands. It is scaled with `blit_filter` if the sizes differ.
+/// Nothing is transferred if `render_target` is `swapchain_image`, see `get_swapchain_framebuffers`.
+/// Each eye is graded with `color_grading`, the frame in flight, its strength and the output adjustments
+/// after the previous frame was copied. The `gui` is drawn on the last eye after that, so neither sees it.
+/// The old frame of a `transition` is blended in afterwards.
+#[allow(clippy::too_many_arguments)]
 pub fn get_primary_command_buffer(
     command_buffer_allocator: &Arc<StandardCommandBufferAllocator>,
     queue: &Arc<Queue>,
     framebuffer: Arc<Framebuffer>,
-    subpasses: impl IntoIterator<Item = Arc<SecondaryAutoCommandBuffer>>,
+    eyes: Vec<EyePass>,
+    render_target: Arc<Image>,
+    swapchain_image: Arc<Image>,
+    blit_filter: Option<Filter>,
+    clear_colors: ClearColors,
+    previous_frame: Option<Arc<Image>>,
+    stereo: &Stereo,
+    color_grading: Option<(&ColorGrading, usize, f32, OutputAdjustments)>,
+    gui: Option<(Arc<Framebuffer>, Arc<SecondaryAutoCommandBuffer>)>,
+    transition: Option<(&Transition, TransitionFrame)>,
 ) -> anyhow::Result<Arc<PrimaryAutoCommandBuffer>> {
-    let mut subpasses = subpasses.into_iter();
     let mut builder = AutoCommandBufferBuilder::primary(
         command_buffer_allocator.clone(),
         queue.queue_family_index(),
         CommandBufferUsage::OneTimeSubmit,
     )?;
+    if let Some((transition, frame)) = transition.as_ref() {
+        transition.capture(&mut builder, frame)?;
+    }
+    let eye_count = eyes.len();
+    for (eye, pass) in eyes.into_iter().enumerate() {
+        record_eye(&mut builder, framebuffer.clone(), pass, clear_colors)?;
+        if eye + 1 == eye_count {
+            if let Some(previous_frame) = previous_frame.clone() {
+                builder.copy_image(CopyImageInfo::images(render_target.clone(), previous_frame))?;
+            }
+        }
+        if let Some((color_grading, frame_i, strength, adjustments)) = color_grading {
+            color_grading.apply(&mut builder, frame_i, render_target.clone(), strength, adjustments)?;
+        }
+        if let Some((framebuffer, commands)) = gui.clone().filter(|_| eye + 1 == eye_count) {
+            builder
+                .begin_render_pass(
+                    RenderPassBeginInfo {
+                        clear_values: vec![None],
+                        ..RenderPassBeginInfo::framebuffer(framebuffer)
+                    },
+                    SubpassBeginInfo {
+                        contents: SubpassContents::SecondaryCommandBuffers,
+                        ..Default::default()
+                    },
+                )?
+                .execute_commands(commands)?
+                .end_render_pass(Default::default())?;
+        }
+        if let Some((transition, frame)) = transition.as_ref() {
+            transition.apply(&mut builder, render_target.clone(), frame)?;
+        }
+        stereo.present(&mut builder, eye, render_target.clone(), swapchain_image.clone(), blit_filter)?;
+    }
+    Ok(builder.build()?)
+}
+
+fn record_eye(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    framebuffer: Arc<Framebuffer>,
+    EyePass { subpasses, scene_input, art_caches }: EyePass,
+    clear_colors: ClearColors,
+) -> anyhow::Result<()> {
+    // the caches are cleared to transparent, they are composited where something was drawn
+    let offscreen = scene_input.map(|pass| (pass, ClearValue::from(clear_colors.scene)))
+        .into_iter()
+        .chain(art_caches.into_iter().map(|pass| (pass, ClearValue::Float([0.; 4]))));
+    for ((framebuffer, commands), clear_color) in offscreen {
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some(clear_color), Some(ClearValue::Depth(1.0))],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::SecondaryCommandBuffers,
+                    ..Default::default()
+                },
+            )?
+            .execute_commands(commands)?
+            .end_render_pass(Default::default())?;
+    }
+    let subpass_count = framebuffer.render_pass().subpasses().len();
     builder
         .begin_render_pass(
             RenderPassBeginInfo {
                 clear_values: vec![
-                    Some(ClearValue::Depth(1.0)),       // mirror depth
-                    Some([0.0, 0.8, 0.0, 1.0].into()),  // mirror color
-                    Some([0.0, 0.0, 0.8, 1.0].into()),  // intermediary color
-                    Some(ClearValue::Depth(1.0)),       // depth
-                    None,                               // final color
+                    Some(ClearValue::Depth(1.0)),           // mirror depth
+                    Some(clear_colors.mirror.into()),       // mirror color
+                    Some(clear_colors.scene.into()),        // intermediary color
+                    Some(ClearValue::Depth(1.0)),           // depth
+                    None,                                   // final color
                 ],
                 ..RenderPassBeginInfo::framebuffer(framebuffer)
             },
@@ -331,29 +600,37 @@ pub fn get_primary_command_buffer(
                 ..Default::default()
             },
         )?;
-    builder.execute_commands(subpasses.next().expect("no subpasses"))?;
-    for subpass in subpasses {
-        builder
-            .next_subpass(
+    let mut subpasses = subpasses.into_iter();
+    for subpass_idx in 0..subpass_count {
+        if subpass_idx > 0 {
+            builder.next_subpass(
                 Default::default(),
                 SubpassBeginInfo {
                     contents: SubpassContents::SecondaryCommandBuffers,
                     ..Default::default()
                 }
-            )?
-            .execute_commands(subpass)?;
+            )?;
+        }
+        if let Some(subpass) = subpasses.next() {
+            builder.execute_commands(subpass)?;
+        }
     }
     builder.end_render_pass(Default::default())?;
-    Ok(builder.build()?)
+    Ok(())
 }
 
+/// Draws `pipelines` in the given order.
+/// `global_sets` has the global set of the subpass for each of the `count` frames. It is bound again
+/// only after a pipeline with another layout, binding set 0 of another layout disturbs set 1.
+#[allow(clippy::too_many_arguments)]
 pub fn get_command_buffers(
     count: usize,
     command_buffer_allocator: &Arc<StandardCommandBufferAllocator>,
     queue: &Arc<Queue>,
-    pipelines: &[MyPipeline],
-    pipeline_order: &[usize],
+    pipelines: &[&MyPipeline],
+    global_sets: &[Arc<DescriptorSet>],
     subpass: &Subpass,
+    extent: [f32; 2],
 ) -> Vec<Arc<SecondaryAutoCommandBuffer>> {
     (0..count).map(|i| {
         let mut builder = AutoCommandBufferBuilder::secondary(
@@ -366,9 +643,9 @@ pub fn get_command_buffers(
             },
         )
         .unwrap();
-        for &pip_idx in pipeline_order {
-            let my_pipeline = &pipelines[pip_idx];
-            if !my_pipeline.enable_pipeline {
+        let mut bound_layout: Option<Arc<PipelineLayout>> = None;
+        for my_pipeline in pipelines {
+            if !my_pipeline.enable_pipeline || my_pipeline.hidden {
                 continue;
             }
             let Some(pipeline) = my_pipeline.get_pipeline() else {
@@ -379,14 +656,30 @@ pub fn get_command_buffers(
             let index_buffer = my_pipeline.get_index_buffer();
             builder
                 .bind_pipeline_graphics(pipeline.clone())
-                .unwrap()
+                .unwrap();
+            if let Some(scissor) = my_pipeline.scissor(extent) {
+                builder.set_scissor(0, [scissor].into_iter().collect()).unwrap();
+            }
+            builder
                 .bind_descriptor_sets(
                     PipelineBindPoint::Graphics,
                     pipeline.layout().clone(),
-                    0,
+                    ART_SET,
                     my_pipeline.get_descriptor_sets().unwrap()[i].clone(),
                 )
-                .unwrap()
+                .unwrap();
+            if bound_layout.as_ref().is_none_or(|layout| !Arc::ptr_eq(layout, pipeline.layout())) {
+                builder
+                    .bind_descriptor_sets(
+                        PipelineBindPoint::Graphics,
+                        pipeline.layout().clone(),
+                        GLOBAL_SET,
+                        global_sets[i].clone(),
+                    )
+                    .unwrap();
+                bound_layout = Some(pipeline.layout().clone());
+            }
+            builder
                 .bind_vertex_buffers(0, vertex_buffer.clone())
                 .unwrap()
                 .bind_index_buffer(index_buffer.clone())
@@ -434,3 +727,58 @@ pub fn oblique_projection_matrix(matrix: Mat4, clip_plane: Vec4) -> Mat4 {
 
     Mat4::from_cols_array(&matrix)
 }
+
+/// Size in pixels of the screen rectangle covered by the box from `min` to `max` transformed by `mvp`.
+/// A box that reaches behind the camera covers the whole screen.
+pub fn screen_footprint(mvp: Mat4, min: Vec3, max: Vec3, extent: [u32; 2]) -> [f32; 2] {
+    let extent = Vec2::new(extent[0] as f32, extent[1] as f32);
+    let mut ndc_min = Vec2::splat(f32::MAX);
+    let mut ndc_max = Vec2::splat(f32::MIN);
+    for i in 0..8 {
+        let corner = Vec3::select(BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+        let clip = mvp * corner.extend(1.);
+        if clip.w <= 0. {
+            return extent.to_array();
+        }
+        let ndc = clip.truncate().truncate() / clip.w;
+        ndc_min = ndc_min.min(ndc);
+        ndc_max = ndc_max.max(ndc);
+    }
+    let size = ndc_max.clamp(Vec2::NEG_ONE, Vec2::ONE) - ndc_min.clamp(Vec2::NEG_ONE, Vec2::ONE);
+    (size / 2. * extent).to_array()
+}
+
+/// Whether `point` is inside the box from `min` to `max` transformed by `matrix`.
+/// The box is expanded by `margin` in world units along each of its axes.
+pub fn box_contains(matrix: Mat4, min: Vec3, max: Vec3, point: Vec3, margin: f32) -> bool {
+    let margin = margin / matrix.to_scale_rotation_translation().0.abs();
+    let local = matrix.inverse().transform_point3(point);
+    local.cmpge(min - margin).all() && local.cmple(max + margin).all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn footprint_of_a_box() {
+        let proj = Mat4::perspective_rh(90_f32.to_radians(), 1., 0.1, 100.);
+        let view = Mat4::look_at_rh(Vec3::new(0., 0., 2.), Vec3::ZERO, Vec3::Y);
+        // the front face at distance 1 spans the whole screen at a fov of 90 degrees
+        let [width, height] = screen_footprint(proj * view, Vec3::splat(-1.), Vec3::splat(1.), [800, 600]);
+        assert!((width - 800.).abs() < 1e-3 && (height - 600.).abs() < 1e-3);
+        let small = screen_footprint(proj * view, Vec3::splat(-0.1), Vec3::splat(0.1), [800, 600]);
+        assert!((small[0] - 800. * 0.1 / 1.9).abs() < 1e-2, "{small:?}");
+        let behind = Mat4::look_at_rh(Vec3::new(0., 0., 0.5), Vec3::new(0., 0., 1.), Vec3::Y);
+        assert_eq!(screen_footprint(proj * behind, Vec3::splat(-1.), Vec3::splat(1.), [800, 600]), [800., 600.]);
+    }
+
+    #[test]
+    fn point_in_transformed_box() {
+        let matrix = Mat4::from_translation(Vec3::new(5., 0., 0.)) * Mat4::from_scale(Vec3::splat(0.5));
+        let (min, max) = (Vec3::splat(-1.), Vec3::splat(1.));
+        assert!(box_contains(matrix, min, max, Vec3::new(5.4, 0., 0.), 0.));
+        assert!(!box_contains(matrix, min, max, Vec3::new(5.6, 0., 0.), 0.));
+        assert!(box_contains(matrix, min, max, Vec3::new(5.6, 0., 0.), 0.2));
+    }
+}
@@ -0,0 +1,270 @@
+//! Checks of compiled shaders beyond the warnings of the compiler, shown with them in the GUI.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::ErrorKind,
+    process::Command,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+const HEADER_WORDS: usize = 5;
+const OP_NAME: u32 = 5;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_FUNCTION: u32 = 54;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+/// UniformConstant, Uniform, PushConstant and StorageBuffer, the storage classes of bound resources.
+const RESOURCE_STORAGE_CLASSES: [u32; 4] = [0, 2, 9, 12];
+
+/// Warns about uniforms, samplers and buffers that are declared but never used in any function.
+/// Ids that only appear as literals count as used, so some unused resources may be missed.
+pub fn unused_resources(code: &[u32]) -> Vec<String> {
+    let mut names = HashMap::new();
+    let mut resources = Vec::new();
+    let mut used = HashSet::new();
+    let mut in_functions = false;
+    let mut i = HEADER_WORDS;
+    while i < code.len() {
+        let word_count = (code[i] >> 16) as usize;
+        let opcode = code[i] & 0xffff;
+        if word_count == 0 || i + word_count > code.len() {
+            break;
+        }
+        let operands = &code[i + 1..i + word_count];
+        match opcode {
+            OP_NAME if operands.len() >= 2 => {
+                names.insert(operands[0], literal_string(&operands[1..]));
+            }
+            OP_FUNCTION => in_functions = true,
+            _ if in_functions => used.extend(operands.iter().copied()),
+            OP_VARIABLE if operands.len() >= 3 && RESOURCE_STORAGE_CLASSES.contains(&operands[2]) => {
+                resources.push(operands[1]);
+            }
+            _ => {}
+        }
+        i += word_count;
+    }
+    resources.into_iter()
+        .filter(|id| !used.contains(id))
+        .map(|id| match names.get(&id).filter(|name| !name.is_empty()) {
+            Some(name) => format!("unused uniform `{name}`"),
+            None => format!("unused uniform with id {id}"),
+        })
+        .collect()
+}
+
+/// Byte offsets of the ends of the members of the uniform block at `set` and `binding`,
+/// the last one is the size of the block. `None` if the shader declares no such block.
+pub fn uniform_block_member_ends(code: &[u32], set: u32, binding: u32) -> Option<Vec<u64>> {
+    let mut layout = BlockLayout::default();
+    let mut variables = Vec::new();
+    let mut i = HEADER_WORDS;
+    while i < code.len() {
+        let word_count = (code[i] >> 16) as usize;
+        let opcode = code[i] & 0xffff;
+        if word_count == 0 || i + word_count > code.len() {
+            break;
+        }
+        let operands = &code[i + 1..i + word_count];
+        match opcode {
+            OP_DECORATE if operands.len() >= 2 => {
+                let value = operands.get(2).copied().unwrap_or_default();
+                layout.decorations.insert((operands[0], None, operands[1]), value);
+            }
+            OP_MEMBER_DECORATE if operands.len() >= 3 => {
+                let value = operands.get(3).copied().unwrap_or_default();
+                layout.decorations.insert((operands[0], Some(operands[1]), operands[2]), value);
+            }
+            OP_TYPE_INT | OP_TYPE_FLOAT | OP_TYPE_VECTOR | OP_TYPE_MATRIX | OP_TYPE_ARRAY | OP_TYPE_STRUCT
+            | OP_TYPE_POINTER if !operands.is_empty() => {
+                layout.types.insert(operands[0], (opcode, operands[1..].to_vec()));
+            }
+            OP_CONSTANT if operands.len() >= 3 => {
+                layout.constants.insert(operands[1], operands[2]);
+            }
+            OP_VARIABLE if operands.len() >= 3 && operands[2] == STORAGE_CLASS_UNIFORM => {
+                variables.push((operands[0], operands[1]));
+            }
+            _ => {}
+        }
+        i += word_count;
+    }
+    let (pointer, _) = variables.into_iter().find(|&(_, id)| {
+        layout.decoration(id, None, DECORATION_DESCRIPTOR_SET) == Some(set)
+            && layout.decoration(id, None, DECORATION_BINDING) == Some(binding)
+    })?;
+    match layout.types.get(&pointer)? {
+        (OP_TYPE_POINTER, operands) => layout.member_ends(*operands.get(1)?),
+        _ => None,
+    }
+}
+
+/// The types and decorations needed to lay out a uniform block.
+#[derive(Default)]
+struct BlockLayout {
+    /// Opcode and operands after the result id by result id.
+    types: HashMap<u32, (u32, Vec<u32>)>,
+    /// The first word of the value of integer constants by result id.
+    constants: HashMap<u32, u32>,
+    /// The first literal of the decorations by target, member and decoration.
+    decorations: HashMap<(u32, Option<u32>, u32), u32>,
+}
+
+impl BlockLayout {
+    fn decoration(&self, target: u32, member: Option<u32>, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(target, member, decoration)).copied()
+    }
+
+    fn member_ends(&self, id: u32) -> Option<Vec<u64>> {
+        let (OP_TYPE_STRUCT, members) = self.types.get(&id)? else { return None };
+        members.iter().zip(0..).map(|(&ty, member)| {
+            let offset = self.decoration(id, Some(member), DECORATION_OFFSET)?;
+            let matrix_stride = self.decoration(id, Some(member), DECORATION_MATRIX_STRIDE);
+            Some(u64::from(offset) + self.size(ty, matrix_stride)?)
+        }).collect()
+    }
+
+    /// The stride of the columns of a matrix is decorated on the member it belongs to.
+    fn size(&self, id: u32, matrix_stride: Option<u32>) -> Option<u64> {
+        match self.types.get(&id)? {
+            (OP_TYPE_INT | OP_TYPE_FLOAT, operands) => Some(u64::from(*operands.first()?) / 8),
+            (OP_TYPE_VECTOR, operands) => Some(self.size(*operands.first()?, None)? * u64::from(*operands.get(1)?)),
+            (OP_TYPE_MATRIX, operands) => {
+                let column = match matrix_stride {
+                    Some(stride) => u64::from(stride),
+                    None => self.size(*operands.first()?, None)?,
+                };
+                Some(column * u64::from(*operands.get(1)?))
+            }
+            (OP_TYPE_ARRAY, operands) => {
+                let stride = self.decoration(id, None, DECORATION_ARRAY_STRIDE)?;
+                Some(u64::from(stride) * u64::from(*self.constants.get(operands.get(1)?)?))
+            }
+            (OP_TYPE_STRUCT, _) => self.member_ends(id)?.last().copied(),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes a nul terminated UTF-8 string packed into little endian words.
+fn literal_string(words: &[u32]) -> String {
+    let bytes = words.iter()
+        .flat_map(|word| word.to_le_bytes())
+        .take_while(|&byte| byte != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Validates the code with `spirv-val` from the Vulkan SDK and returns its complaints.
+/// Returns nothing if `spirv-val` is not installed.
+pub fn spirv_val(code: &[u32]) -> Vec<String> {
+    static NEXT_FILE: AtomicU32 = AtomicU32::new(0);
+    static NOT_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+    if NOT_INSTALLED.load(Ordering::Relaxed) {
+        return Vec::new();
+    }
+    let file_name = format!("shaderpixel-{}-{}.spv", std::process::id(), NEXT_FILE.fetch_add(1, Ordering::Relaxed));
+    let path = std::env::temp_dir().join(file_name);
+    let bytes = code.iter().flat_map(|word| word.to_le_bytes()).collect::<Vec<_>>();
+    if let Err(err) = fs::write(&path, bytes) {
+        log::warn!("failed to write {} for spirv-val: {err}", path.display());
+        return Vec::new();
+    }
+    let output = Command::new("spirv-val").args(["--target-env", "vulkan1.0"]).arg(&path).output();
+    let _ = fs::remove_file(&path);
+    match output {
+        Ok(output) if output.status.success() => Vec::new(),
+        Ok(output) => String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .chain(String::from_utf8_lossy(&output.stdout).lines())
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| format!("spirv-val: {line}"))
+            .collect(),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            log::info!("spirv-val is not installed, shaders are not validated");
+            NOT_INSTALLED.store(true, Ordering::Relaxed);
+            Vec::new()
+        }
+        Err(err) => {
+            log::warn!("failed to run spirv-val: {err}");
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_unused_uniforms() {
+        let instruction = |opcode: u32, operands: &[u32]| {
+            let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+            words.extend_from_slice(operands);
+            words
+        };
+        let name = |text: &str| u32::from_le_bytes(std::array::from_fn(|i| *text.as_bytes().get(i).unwrap_or(&0)));
+        let code = [
+            vec![0; HEADER_WORDS],
+            instruction(OP_NAME, &[10, name("ubo")]),
+            instruction(OP_NAME, &[11, name("tex")]),
+            instruction(OP_VARIABLE, &[1, 10, 2]),
+            instruction(OP_VARIABLE, &[1, 11, 0]),
+            // an input is no resource
+            instruction(OP_VARIABLE, &[1, 12, 1]),
+            instruction(OP_FUNCTION, &[2, 20, 0, 3]),
+            // OpAccessChain of ubo
+            instruction(65, &[4, 21, 10, 5]),
+        ].concat();
+        assert_eq!(unused_resources(&code), ["unused uniform `tex`"]);
+    }
+
+    #[test]
+    fn lays_out_uniform_blocks() {
+        let instruction = |opcode: u32, operands: &[u32]| {
+            let mut words = vec![((operands.len() as u32 + 1) << 16) | opcode];
+            words.extend_from_slice(operands);
+            words
+        };
+        // layout(set = 0, binding = 1) uniform Ubo { vec4 light_pos; vec4 options[8]; float time; mat2 m; }
+        let code = [
+            vec![0; HEADER_WORDS],
+            instruction(OP_DECORATE, &[10, DECORATION_DESCRIPTOR_SET, 0]),
+            instruction(OP_DECORATE, &[10, DECORATION_BINDING, 1]),
+            instruction(OP_DECORATE, &[5, DECORATION_ARRAY_STRIDE, 16]),
+            instruction(OP_MEMBER_DECORATE, &[6, 0, DECORATION_OFFSET, 0]),
+            instruction(OP_MEMBER_DECORATE, &[6, 1, DECORATION_OFFSET, 16]),
+            instruction(OP_MEMBER_DECORATE, &[6, 2, DECORATION_OFFSET, 144]),
+            instruction(OP_MEMBER_DECORATE, &[6, 3, DECORATION_OFFSET, 160]),
+            instruction(OP_MEMBER_DECORATE, &[6, 3, DECORATION_MATRIX_STRIDE, 16]),
+            instruction(OP_TYPE_FLOAT, &[1, 32]),
+            instruction(OP_TYPE_VECTOR, &[2, 1, 4]),
+            instruction(OP_TYPE_INT, &[3, 32, 0]),
+            instruction(OP_CONSTANT, &[3, 4, 8]),
+            instruction(OP_TYPE_ARRAY, &[5, 2, 4]),
+            instruction(OP_TYPE_VECTOR, &[8, 1, 2]),
+            instruction(OP_TYPE_MATRIX, &[9, 8, 2]),
+            instruction(OP_TYPE_STRUCT, &[6, 2, 5, 1, 9]),
+            instruction(OP_TYPE_POINTER, &[7, STORAGE_CLASS_UNIFORM, 6]),
+            instruction(OP_VARIABLE, &[7, 10, STORAGE_CLASS_UNIFORM]),
+        ].concat();
+        assert_eq!(uniform_block_member_ends(&code, 0, 1), Some(vec![16, 144, 148, 192]));
+        assert_eq!(uniform_block_member_ends(&code, 0, 2), None);
+    }
+}
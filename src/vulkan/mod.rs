@@ -1,11 +1,33 @@
 mod app;
+mod art_cache;
+mod color_grading;
 mod debug;
+mod environment;
 mod geometry;
+mod global_set;
 mod helpers;
+mod lint;
+mod noise;
 mod pipeline;
+mod scene_input;
+mod screenshot;
 mod shader;
+mod snapshot;
+mod sound;
+mod stereo;
 mod texture;
+mod transition;
 mod vertex;
+mod watchdog;
+mod window_target;
 
-pub use app::App as VkApp;
-pub use shader::HotShader;
+pub use app::{App as VkApp, ClearColors, CpuTimings};
+pub use color_grading::{Lut, OutputAdjustments};
+pub use debug::{debug_printf_enabled, enable_debug_printf, take_printf_messages};
+pub use helpers::box_contains;
+pub use shader::{register_include, set_include_paths, CompileStats, HotShader};
+pub use snapshot::{render_snapshot, SnapshotScene};
+pub use sound::{SAMPLE_RATE, SOUND_SECONDS};
+pub use stereo::StereoMode;
+pub use transition::{TransitionSettings, TransitionStyle};
+pub use window_target::WindowTarget;
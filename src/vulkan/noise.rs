@@ -0,0 +1,380 @@
+use super::{helpers::FENCE_TIMEOUT, texture::Texture};
+
+use std::sync::Arc;
+
+use glam::{IVec2, IVec3, UVec3, Vec2, Vec3};
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, CommandBufferUsage, CopyBufferToImageInfo,
+        PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
+    },
+    device::{Device, Queue},
+    format::Format,
+    image::{
+        sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo},
+        view::ImageView,
+        Image, ImageCreateInfo, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    sync::GpuFuture,
+};
+
+/// Size of the 2D noise texture, must match `NOISE_2D_SIZE` in `noise.glsl`.
+const NOISE_2D_SIZE: u32 = 256;
+/// Size of the 3D noise texture, must match `NOISE_3D_SIZE` in `noise.glsl`.
+const NOISE_3D_SIZE: u32 = 32;
+/// Size of the blue noise texture, must match `BLUE_NOISE_SIZE` in `noise.glsl`.
+const BLUE_NOISE_SIZE: u32 = 64;
+/// Number of Perlin noise cells along each axis of the lowest octave.
+const PERLIN_PERIOD_2D: u32 = 8;
+const PERLIN_PERIOD_3D: u32 = 4;
+const PERLIN_OCTAVES: u32 = 4;
+/// Number of Worley noise cells along each axis.
+const WORLEY_PERIOD_2D: u32 = 16;
+const WORLEY_PERIOD_3D: u32 = 8;
+/// Standard deviation of the gaussian used to find voids and clusters for the blue noise.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+const GRADIENTS: [Vec3; 12] = [
+    Vec3::new(1., 1., 0.), Vec3::new(-1., 1., 0.), Vec3::new(1., -1., 0.), Vec3::new(-1., -1., 0.),
+    Vec3::new(1., 0., 1.), Vec3::new(-1., 0., 1.), Vec3::new(1., 0., -1.), Vec3::new(-1., 0., -1.),
+    Vec3::new(0., 1., 1.), Vec3::new(0., -1., 1.), Vec3::new(0., 1., -1.), Vec3::new(0., -1., -1.),
+];
+
+/// Tileable noise generated at startup so shaders do not have to compute hash noise per fragment.
+///
+/// Art shaders can access them at the following bindings of the global set 1:
+/// - 5: `sampler2D` with Perlin fBm, Worley F1 and two white noise channels
+/// - 6: `sampler3D` with the same channels as the 2D noise
+/// - 7: `sampler2D` with blue noise in the red channel, sampled with nearest filtering
+///
+/// See `assets/shaders/includes/noise.glsl` for helpers.
+#[derive(Clone)]
+pub struct NoiseTextures {
+    pub noise2d: Texture,
+    pub noise3d: Texture,
+    pub blue_noise: Texture,
+}
+
+impl NoiseTextures {
+    pub fn new(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        log::debug!("generating noise textures");
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let linear = Sampler::new(device.clone(), SamplerCreateInfo::simple_repeat_linear_no_mipmap())?;
+        let nearest = Sampler::new(device, SamplerCreateInfo {
+            address_mode: [SamplerAddressMode::Repeat; 3],
+            ..Default::default()
+        })?;
+
+        let noise2d = Self::upload(
+            &mut command_buffer,
+            memory_allocator.clone(),
+            &noise_2d(NOISE_2D_SIZE),
+            Format::R8G8B8A8_UNORM,
+            [NOISE_2D_SIZE, NOISE_2D_SIZE, 1],
+        )?;
+        let noise3d = Self::upload(
+            &mut command_buffer,
+            memory_allocator.clone(),
+            &noise_3d(NOISE_3D_SIZE),
+            Format::R8G8B8A8_UNORM,
+            [NOISE_3D_SIZE; 3],
+        )?;
+        let blue_noise = Self::upload(
+            &mut command_buffer,
+            memory_allocator,
+            &blue_noise(BLUE_NOISE_SIZE),
+            Format::R8_UNORM,
+            [BLUE_NOISE_SIZE, BLUE_NOISE_SIZE, 1],
+        )?;
+
+        command_buffer.build()?
+            .execute(queue)?
+            .then_signal_fence_and_flush()?
+            .wait(Some(FENCE_TIMEOUT))?;
+
+        Ok(Self {
+            noise2d: Texture { view: noise2d, sampler: linear.clone() },
+            noise3d: Texture { view: noise3d, sampler: linear },
+            blue_noise: Texture { view: blue_noise, sampler: nearest },
+        })
+    }
+
+    fn upload(
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        data: &[u8],
+        format: Format,
+        extent: [u32; 3],
+    ) -> anyhow::Result<Arc<ImageView>> {
+        let upload_buffer = Buffer::from_iter(
+            memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            data.iter().copied(),
+        )?;
+        let image = Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: if extent[2] > 1 { ImageType::Dim3d } else { ImageType::Dim2d },
+                format,
+                extent,
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        command_buffer.copy_buffer_to_image(CopyBufferToImageInfo::buffer_image(upload_buffer, image.clone()))?;
+        Ok(ImageView::new_default(image)?)
+    }
+}
+
+/// RGBA pixels with Perlin fBm, Worley F1 and two white noise channels.
+fn noise_2d(size: u32) -> Vec<u8> {
+    let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let p = Vec2::new(x as f32, y as f32) / size as f32;
+            let period = UVec3::new(PERLIN_PERIOD_2D, PERLIN_PERIOD_2D, 1);
+            pixels.extend([
+                to_u8(perlin_fbm((p * PERLIN_PERIOD_2D as f32).extend(0.), period, PERLIN_OCTAVES)),
+                to_u8(worley_2d(p * WORLEY_PERIOD_2D as f32, WORLEY_PERIOD_2D)),
+                hash(x, y, 0, 1) as u8,
+                hash(x, y, 0, 2) as u8,
+            ]);
+        }
+    }
+    pixels
+}
+
+/// RGBA voxels with the same channels as `noise_2d`.
+fn noise_3d(size: u32) -> Vec<u8> {
+    let mut voxels = Vec::with_capacity((size * size * size * 4) as usize);
+    for z in 0..size {
+        for y in 0..size {
+            for x in 0..size {
+                let p = Vec3::new(x as f32, y as f32, z as f32) / size as f32;
+                voxels.extend([
+                    to_u8(perlin_fbm(p * PERLIN_PERIOD_3D as f32, UVec3::splat(PERLIN_PERIOD_3D), PERLIN_OCTAVES)),
+                    to_u8(worley_3d(p * WORLEY_PERIOD_3D as f32, WORLEY_PERIOD_3D)),
+                    hash(x, y, z, 1) as u8,
+                    hash(x, y, z, 2) as u8,
+                ]);
+            }
+        }
+    }
+    voxels
+}
+
+/// Blue noise generated with the void and cluster method,
+/// see <https://cv.ulichney.com/papers/1993-void-cluster.pdf>.
+/// Every value occurs equally often.
+fn blue_noise(size: u32) -> Vec<u8> {
+    let size = size as usize;
+    let len = size * size;
+
+    // gaussian weights for every toroidal offset
+    let kernel = (0..len).map(|i| {
+        let dx = (i % size).min(size - i % size) as f32;
+        let dy = (i / size).min(size - i / size) as f32;
+        (-(dx * dx + dy * dy) / (2. * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp()
+    }).collect::<Vec<_>>();
+    let update = |energy: &mut [f32], idx: usize, sign: f32| {
+        let (x, y) = (idx % size, idx / size);
+        for (i, e) in energy.iter_mut().enumerate() {
+            let dx = (i % size + size - x) % size;
+            let dy = (i / size + size - y) % size;
+            *e += sign * kernel[dy * size + dx];
+        }
+    };
+    let find = |pattern: &[bool], energy: &[f32], value: bool, tightest: bool| {
+        let candidates = (0..len).filter(|&i| pattern[i] == value);
+        let cmp = |a: &usize, b: &usize| energy[*a].total_cmp(&energy[*b]);
+        if tightest { candidates.max_by(cmp) } else { candidates.min_by(cmp) }.unwrap()
+    };
+
+    // random initial pattern with about a tenth of the pixels set
+    let mut pattern = (0..len as u32).map(|i| hash(i, 0, 0, 3) % 10 == 0).collect::<Vec<_>>();
+    let mut energy = vec![0.; len];
+    for idx in (0..len).filter(|&i| pattern[i]) {
+        update(&mut energy, idx, 1.);
+    }
+
+    // move pixels from the tightest clusters into the largest voids until the pattern is stable
+    for _ in 0..len {
+        let cluster = find(&pattern, &energy, true, true);
+        pattern[cluster] = false;
+        update(&mut energy, cluster, -1.);
+        let void = find(&pattern, &energy, false, false);
+        pattern[void] = true;
+        update(&mut energy, void, 1.);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let initial_count = pattern.iter().filter(|&&set| set).count();
+    let mut ranks = vec![0; len];
+
+    // rank the initial pixels by removing the tightest clusters
+    let mut pattern_phase1 = pattern.clone();
+    let mut energy_phase1 = energy.clone();
+    for rank in (0..initial_count).rev() {
+        let cluster = find(&pattern_phase1, &energy_phase1, true, true);
+        pattern_phase1[cluster] = false;
+        update(&mut energy_phase1, cluster, -1.);
+        ranks[cluster] = rank;
+    }
+
+    // rank the remaining pixels by filling the largest voids
+    for rank in initial_count..len {
+        let void = find(&pattern, &energy, false, false);
+        pattern[void] = true;
+        update(&mut energy, void, 1.);
+        ranks[void] = rank;
+    }
+
+    ranks.into_iter().map(|rank| (rank * 256 / len) as u8).collect()
+}
+
+/// Tileable gradient noise in about [-1, 1] with the lattice repeating after `period` cells.
+fn perlin(p: Vec3, period: UVec3) -> f32 {
+    let cell = p.floor();
+    let f = p - cell;
+    let u = f * f * f * (f * (f * 6. - 15.) + 10.);
+    let cell = cell.as_ivec3();
+    let corner = |offset: IVec3| {
+        let c = (cell + offset).rem_euclid(period.as_ivec3()).as_uvec3();
+        let gradient = GRADIENTS[hash(c.x, c.y, c.z, 0) as usize % GRADIENTS.len()];
+        gradient.dot(f - offset.as_vec3())
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corner(IVec3::new(0, 0, 0)), corner(IVec3::new(1, 0, 0)), u.x);
+    let x10 = lerp(corner(IVec3::new(0, 1, 0)), corner(IVec3::new(1, 1, 0)), u.x);
+    let x01 = lerp(corner(IVec3::new(0, 0, 1)), corner(IVec3::new(1, 0, 1)), u.x);
+    let x11 = lerp(corner(IVec3::new(0, 1, 1)), corner(IVec3::new(1, 1, 1)), u.x);
+    lerp(lerp(x00, x10, u.y), lerp(x01, x11, u.y), u.z)
+}
+
+/// Sum of `octaves` octaves of Perlin noise mapped to [0, 1].
+fn perlin_fbm(p: Vec3, period: UVec3, octaves: u32) -> f32 {
+    let mut sum = 0.;
+    let mut amplitude_sum = 0.;
+    for octave in 0..octaves {
+        let scale = (1 << octave) as f32;
+        let amplitude = 1. / scale;
+        sum += perlin(p * scale, period * (1 << octave)) * amplitude;
+        amplitude_sum += amplitude;
+    }
+    (sum / amplitude_sum * 0.5 + 0.5).clamp(0., 1.)
+}
+
+/// Distance to the closest of one random point per cell, clamped to [0, 1].
+fn worley_2d(p: Vec2, period: u32) -> f32 {
+    let cell = p.floor();
+    let mut min_dist = f32::MAX;
+    for y in -1..=1 {
+        for x in -1..=1 {
+            let neighbour = cell + Vec2::new(x as f32, y as f32);
+            let c = neighbour.as_ivec2().rem_euclid(IVec2::splat(period as i32)).as_uvec2();
+            let point = neighbour + Vec2::new(unit(hash(c.x, c.y, 0, 4)), unit(hash(c.x, c.y, 0, 5)));
+            min_dist = min_dist.min(p.distance(point));
+        }
+    }
+    min_dist.min(1.)
+}
+
+/// Like `worley_2d` in three dimensions.
+fn worley_3d(p: Vec3, period: u32) -> f32 {
+    let cell = p.floor();
+    let mut min_dist = f32::MAX;
+    for z in -1..=1 {
+        for y in -1..=1 {
+            for x in -1..=1 {
+                let neighbour = cell + Vec3::new(x as f32, y as f32, z as f32);
+                let c = neighbour.as_ivec3().rem_euclid(IVec3::splat(period as i32)).as_uvec3();
+                let point = neighbour + Vec3::new(
+                    unit(hash(c.x, c.y, c.z, 4)),
+                    unit(hash(c.x, c.y, c.z, 5)),
+                    unit(hash(c.x, c.y, c.z, 6)),
+                );
+                min_dist = min_dist.min(p.distance(point));
+            }
+        }
+    }
+    min_dist.min(1.)
+}
+
+/// Integer hash, see <https://nullprogram.com/blog/2018/07/31/>.
+fn hash(x: u32, y: u32, z: u32, seed: u32) -> u32 {
+    let mut h = seed;
+    for v in [x, y, z] {
+        h ^= v;
+        h ^= h >> 16;
+        h = h.wrapping_mul(0x7feb352d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x846ca68b);
+        h ^= h >> 16;
+    }
+    h
+}
+
+fn unit(h: u32) -> f32 {
+    h as f32 / u32::MAX as f32
+}
+
+fn to_u8(value: f32) -> u8 {
+    (value * 255.).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perlin_tiles() {
+        let period = UVec3::splat(4);
+        for p in [Vec3::new(0.3, 1.7, 2.2), Vec3::new(3.9, 0.1, 0.5)] {
+            let a = perlin_fbm(p, period, PERLIN_OCTAVES);
+            let b = perlin_fbm(p + Vec3::new(4., 0., -4.), period, PERLIN_OCTAVES);
+            assert!((a - b).abs() < 1e-5, "{a} != {b}");
+        }
+    }
+
+    #[test]
+    fn worley_tiles() {
+        let p = Vec2::new(0.4, 7.8);
+        assert!((worley_2d(p, 8) - worley_2d(p + Vec2::new(8., -8.), 8)).abs() < 1e-5);
+        let p = Vec3::new(0.4, 7.8, 3.3);
+        assert!((worley_3d(p, 8) - worley_3d(p + Vec3::new(8., -8., 16.), 8)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn blue_noise_uniform() {
+        let noise = blue_noise(32);
+        let mut histogram = [0; 256];
+        for value in noise {
+            histogram[value as usize] += 1;
+        }
+        assert!(histogram.iter().all(|&count| count == 32 * 32 / 256));
+    }
+}
@@ -1,20 +1,19 @@
-use crate::art::{ArtData, ArtObject};
+use crate::art::{ArtData, ArtObject, OPTION_VEC4S};
 use super::{
     geometry::Geometry,
-    helpers::{fs, vs},
+    global_set::{GlobalSet, ART_SET, GLOBAL_SET},
+    helpers::{box_contains, fs, screen_footprint, vs},
     shader::HotShader,
     texture::Texture,
 };
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, Weak};
 
-use anyhow::Context;
-use glam::Mat4;
+use anyhow::{bail, Context};
+use glam::{Mat4, Vec3};
 use vulkano::{
-    buffer::{
-        allocator::SubbufferAllocator,
-        Subbuffer,
-    },
+    buffer::{allocator::SubbufferAllocator, Subbuffer},
+    DeviceSize,
     device::Device,
     descriptor_set::{
         allocator::StandardDescriptorSetAllocator,
@@ -31,24 +30,57 @@ use vulkano::{
             multisample::MultisampleState,
             rasterization::{CullMode, RasterizationState},
             vertex_input::VertexInputState,
-            viewport::{Viewport, ViewportState},
+            viewport::{Scissor, Viewport, ViewportState},
             GraphicsPipelineCreateInfo,
         },
         layout::PipelineDescriptorSetLayoutCreateInfo,
-        GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
+        DynamicState, GraphicsPipeline, Pipeline, PipelineLayout, PipelineShaderStageCreateInfo,
     },
     render_pass::Subpass,
-    shader::EntryPoint,
+    shader::{EntryPoint, ShaderModule},
 };
 
+/// Bindings of the color and depth of the mirror subpass, read as input attachments.
+const MIRROR_BINDINGS: [u32; 2] = [3, 4];
+/// Binding of the uniform block of an exhibit, see `crate::uniform_block`.
+const UNIFORM_BLOCK_BINDING: u32 = 12;
+/// Binding of the color of the previous frame, including the interface if it was shown.
+const PREVIOUS_FRAME_BINDING: u32 = 13;
+/// Bindings of the color and depth of the gallery without exhibits, see `SceneInput`.
+const SCENE_INPUT_BINDINGS: [u32; 2] = [14, 15];
+/// Bindings of the color and depth an exhibit was rendered to, see `ArtCache`.
+const ART_CACHE_BINDINGS: [u32; 2] = [16, 17];
+
+/// Timing of the frame that is rendered.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FrameInfo {
+    pub time: f32,
+    /// Seconds since the previous frame.
+    pub delta_time: f32,
+    /// Counts the rendered frames, starting at 0.
+    pub index: u32,
+    /// Size of the render targets in pixels.
+    pub extent: [u32; 2],
+}
+
 pub struct MyPipelineCreateInfo {
     pub name: String,
     pub vs: Arc<HotShader>,
     pub fs: Arc<HotShader>,
     pub enable_pipeline: bool,
     pub enable_depth_test: bool,
+    /// Writes the depth of the exhibit. Transparent exhibits do not, so what is behind
+    /// still shows through where they blend, see `ArtObject::is_transparent`.
+    pub depth_write: bool,
     pub cull_mode: CullMode,
     pub mirror_buffers: Option<[Arc<ImageView>; 2]>,
+    /// One copy of the last frame per frame in flight.
+    pub previous_frame: Option<Vec<Texture>>,
+    pub scene_input: Option<[Texture; 2]>,
+    /// Size in bytes of the uniform block of the exhibit if it has one.
+    pub uniform_block_size: Option<usize>,
+    /// Shared with the other pipelines, a new cache only used by this pipeline by default.
+    pub layouts: LayoutCache,
 }
 
 impl Default for MyPipelineCreateInfo {
@@ -59,8 +91,13 @@ impl Default for MyPipelineCreateInfo {
             fs: Default::default(),
             enable_pipeline: true,
             enable_depth_test: true,
+            depth_write: true,
             cull_mode: CullMode::Back,
             mirror_buffers: None,
+            previous_frame: None,
+            scene_input: None,
+            uniform_block_size: None,
+            layouts: LayoutCache::default(),
         }
     }
 }
@@ -73,28 +110,115 @@ impl From<&ArtObject> for MyPipelineCreateInfo {
             fs: Arc::clone(&art_obj.shader_frag),
             enable_pipeline: art_obj.enable_pipeline,
             enable_depth_test: art_obj.enable_depth_test,
+            // the ray marchers write the depth of what they hit, the exhibits behind must still fail on it
+            depth_write: !art_obj.is_transparent || art_obj.frag_depth,
+            cull_mode: if art_obj.frag_depth { CullMode::Front } else { CullMode::Back },
+            uniform_block_size: art_obj.uniform_block.as_ref().map(|block| block.data().len()),
             ..Default::default()
         }
     }
 }
 
+/// Pipeline layouts shared by all pipelines whose shaders have the same bindings, which most 2D
+/// and 3D shaders do. Descriptor sets of the same layout also share the pools of the allocator.
+/// The layouts are owned by the pipelines, they are dropped with the last one that uses them.
+#[derive(Clone, Default)]
+pub struct LayoutCache(Arc<Mutex<Vec<(PipelineDescriptorSetLayoutCreateInfo, Weak<PipelineLayout>)>>>);
+
+impl LayoutCache {
+    /// Returns the cached layout equal to `info` or creates it.
+    fn get_or_create(
+        &self,
+        device: Arc<Device>,
+        info: PipelineDescriptorSetLayoutCreateInfo,
+    ) -> anyhow::Result<Arc<PipelineLayout>> {
+        let mut layouts = self.0.lock().unwrap();
+        layouts.retain(|(_, layout)| layout.strong_count() > 0);
+        let cached = layouts.iter()
+            .filter(|(cached, _)| same_layout(cached, &info))
+            .find_map(|(_, layout)| layout.upgrade());
+        if let Some(layout) = cached {
+            return Ok(layout);
+        }
+        let layout = PipelineLayout::new(
+            device.clone(),
+            info.clone().into_pipeline_layout_create_info(device)
+                .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+        )?;
+        layouts.push((info, Arc::downgrade(&layout)));
+        log::debug!("created pipeline layout, {} are cached", layouts.len());
+        Ok(layout)
+    }
+}
+
+fn same_layout(a: &PipelineDescriptorSetLayoutCreateInfo, b: &PipelineDescriptorSetLayoutCreateInfo) -> bool {
+    a.flags == b.flags
+        && a.push_constant_ranges == b.push_constant_ranges
+        && a.set_layouts.len() == b.set_layouts.len()
+        && a.set_layouts.iter().zip(&b.set_layouts).all(|(a, b)| a.flags == b.flags && a.bindings == b.bindings)
+}
+
+/// Logs an error if the uniform buffer the fragment shader declares at binding 1 does not fit
+/// `fs::UniformBufferObject`, e.g. because its options are not `OPTION_VEC4S` vec4s long.
+fn check_uniform_buffer(name: &str, member_ends: &[u64]) {
+    let options_end = 16 * (1 + OPTION_VEC4S as u64);
+    let size = member_ends.last().copied().unwrap_or_default();
+    let buffer_size = size_of::<fs::UniformBufferObject>() as u64;
+    match member_ends.get(1) {
+        Some(&end) if end != options_end => log::error!(
+            "{name} declares {} option vec4s in its uniform buffer but exhibits have {OPTION_VEC4S}, \
+            declare them as `vec4 options[OPTION_VEC4S]`",
+            end.saturating_sub(16) / 16,
+        ),
+        _ if size > buffer_size => log::error!(
+            "{name} declares a uniform buffer of {size} bytes but exhibits only have {buffer_size}, \
+            compare it with `sp/ubo.glsl`",
+        ),
+        _ => {}
+    }
+}
+
+/// Part of the screen a pipeline draws to while two shaders of an exhibit are compared.
+/// The value is the position of the split line as fraction of the screen width.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Split {
+    Left(f32),
+    Right(f32),
+}
+
 pub struct MyPipeline {
     name: String,
     art_idx: Option<usize>,
-    texture: Option<Texture>,
+    /// One per frame in flight or one shared by all of them, empty if the exhibit has no texture.
+    textures: Vec<Texture>,
     subpass: Subpass,
     pipeline: Option<Arc<GraphicsPipeline>>,
+    /// The vertex and fragment shader modules `pipeline` was created with.
+    modules: Option<[Arc<ShaderModule>; 2]>,
     descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
     descriptor_sets: Option<Vec<Arc<DescriptorSet>>>,
     geometry: Geometry,
     uniform_buffers_vert: Vec<Subbuffer<vs::UniformBufferObject>>,
     uniform_buffers_frag: Vec<Subbuffer<fs::UniformBufferObject>>,
+    /// Empty if the exhibit has no uniform block.
+    uniform_buffers_block: Vec<Subbuffer<[u8]>>,
     vs: Arc<HotShader>,
     fs: Arc<HotShader>,
     pub enable_pipeline: bool,
+    /// Hidden pipelines are skipped when recording the command buffers but kept otherwise.
+    pub hidden: bool,
     enable_depth_test: bool,
+    depth_write: bool,
     mirror_buffers: Option<[Arc<ImageView>; 2]>,
+    previous_frame: Option<Vec<Texture>>,
+    scene_input: Option<[Texture; 2]>,
+    /// Only set for pipelines created by `composite_variant`, one per uniform slot.
+    art_cache: Option<Vec<[Texture; 2]>>,
     cull_mode: CullMode,
+    /// The camera is inside the container, see `set_camera_inside`.
+    camera_inside: bool,
+    split: Option<Split>,
+    layouts: LayoutCache,
 }
 
 impl MyPipeline {
@@ -102,7 +226,7 @@ impl MyPipeline {
     pub fn new(
         create_info: MyPipelineCreateInfo,
         art_idx: Option<usize>,
-        texture: Option<Texture>,
+        textures: Vec<Texture>,
         device: Arc<Device>,
         geometry: Geometry,
         subpass: Subpass,
@@ -122,25 +246,41 @@ impl MyPipeline {
         let uniform_buffers_frag = (0..frames_in_flight).map(|_| {
             uniform_buffer_allocator.allocate_sized::<fs::UniformBufferObject>().unwrap()
         }).collect::<Vec<_>>();
+        let uniform_buffers_block = match create_info.uniform_block_size {
+            Some(size) => (0..frames_in_flight).map(|_| {
+                uniform_buffer_allocator.allocate_slice::<u8>(size as DeviceSize)
+            }).collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
 
 
         let mut pipeline = Self {
             name: create_info.name,
             art_idx,
-            texture,
+            textures,
             pipeline: None,
+            modules: None,
             subpass,
             descriptor_set_allocator,
             descriptor_sets: None,
             geometry,
             uniform_buffers_vert,
             uniform_buffers_frag,
+            uniform_buffers_block,
             vs: create_info.vs,
             fs: create_info.fs,
             enable_pipeline: create_info.enable_pipeline,
+            hidden: false,
             enable_depth_test: create_info.enable_depth_test,
+            depth_write: create_info.depth_write,
             mirror_buffers: create_info.mirror_buffers,
+            previous_frame: create_info.previous_frame,
+            scene_input: create_info.scene_input,
+            art_cache: None,
             cull_mode: create_info.cull_mode,
+            camera_inside: false,
+            split: None,
+            layouts: create_info.layouts,
         };
         pipeline.update_pipeline(
             device,
@@ -172,6 +312,17 @@ impl MyPipeline {
 
     pub fn get_art_idx(&self) -> Option<usize> { self.art_idx }
 
+    /// Updates the index after exhibits before this one were removed.
+    pub fn set_art_idx(&mut self, art_idx: usize) {
+        self.art_idx = Some(art_idx);
+    }
+
+    /// Replaces the vertex and index buffers, the pipeline is recreated as the vertex layout may differ.
+    pub fn set_geometry(&mut self, geometry: Geometry) {
+        self.geometry = geometry;
+        self.pipeline = None;
+    }
+
     pub fn set_shaders(&mut self, vs: Arc<HotShader>, fs: Arc<HotShader>) {
         if !Arc::ptr_eq(&self.vs, &vs) {
             self.vs = vs;
@@ -183,21 +334,216 @@ impl MyPipeline {
         }
     }
 
+    /// Creates a pipeline that draws the same exhibit with the fragment shader `fs`
+    /// on the right side of the split. It shares the uniform buffers with `self`.
+    pub fn compare_variant(
+        &self,
+        fs: Arc<HotShader>,
+        split: f32,
+        device: Arc<Device>,
+        viewport: Viewport,
+    ) -> anyhow::Result<Self> {
+        fs.set_device(device.clone());
+        let mut pipeline = Self {
+            split: Some(Split::Right(split)),
+            ..self.variant(format!("{} compare", self.name), self.subpass.clone(), fs)
+        };
+        pipeline.update_pipeline(device, viewport)?;
+        Ok(pipeline)
+    }
+
+    /// Creates a pipeline that draws the same exhibit opaque into `subpass` of an `ArtCache`.
+    /// It shares the uniform buffers with `self`.
+    pub fn cache_variant(&self, subpass: Subpass, device: Arc<Device>, viewport: Viewport) -> anyhow::Result<Self> {
+        let mut pipeline = Self {
+            depth_write: true,
+            // the mirror attachments only exist in the main render pass
+            mirror_buffers: None,
+            ..self.variant(format!("{} cache", self.name), subpass, self.fs.clone())
+        };
+        pipeline.update_pipeline(device, viewport)?;
+        Ok(pipeline)
+    }
+
+    /// Creates a pipeline that draws the color and depth of `art_cache` with the fragment shader `fs`
+    /// where the exhibit covers the screen, `art_cache` has a pair per uniform slot.
+    /// It shares the uniform buffers with `self`.
+    pub fn composite_variant(
+        &self,
+        fs: Arc<HotShader>,
+        art_cache: Vec<[Texture; 2]>,
+        device: Arc<Device>,
+        viewport: Viewport,
+    ) -> anyhow::Result<Self> {
+        fs.set_device(device.clone());
+        let mut pipeline = Self {
+            art_cache: Some(art_cache),
+            ..self.variant(format!("{} composite", self.name), self.subpass.clone(), fs)
+        };
+        pipeline.update_pipeline(device, viewport)?;
+        Ok(pipeline)
+    }
+
+    /// Creates a pipeline that draws the fragment shader of the exhibit on `triangle` covering the screen
+    /// with the vertex shader `vs`, see `Geometry::fullscreen_triangle`. It shares the uniform buffers with `self`.
+    pub fn fullscreen_variant(
+        &self,
+        vs: Arc<HotShader>,
+        triangle: Geometry,
+        device: Arc<Device>,
+        viewport: Viewport,
+    ) -> anyhow::Result<Self> {
+        vs.set_device(device.clone());
+        let mut pipeline = Self {
+            vs,
+            geometry: triangle,
+            enable_depth_test: false,
+            cull_mode: CullMode::None,
+            camera_inside: false,
+            ..self.variant(format!("{} fullscreen", self.name), self.subpass.clone(), self.fs.clone())
+        };
+        pipeline.update_pipeline(device, viewport)?;
+        Ok(pipeline)
+    }
+
+    /// A copy of `self` without a created pipeline, sharing the uniform buffers.
+    fn variant(&self, name: String, subpass: Subpass, fs: Arc<HotShader>) -> Self {
+        Self {
+            name,
+            art_idx: self.art_idx,
+            textures: self.textures.clone(),
+            subpass,
+            pipeline: None,
+            modules: None,
+            descriptor_set_allocator: self.descriptor_set_allocator.clone(),
+            descriptor_sets: None,
+            geometry: self.geometry.clone(),
+            uniform_buffers_vert: self.uniform_buffers_vert.clone(),
+            uniform_buffers_frag: self.uniform_buffers_frag.clone(),
+            uniform_buffers_block: self.uniform_buffers_block.clone(),
+            vs: self.vs.clone(),
+            fs,
+            enable_pipeline: self.enable_pipeline,
+            hidden: self.hidden,
+            enable_depth_test: self.enable_depth_test,
+            depth_write: self.depth_write,
+            mirror_buffers: self.mirror_buffers.clone(),
+            previous_frame: self.previous_frame.clone(),
+            scene_input: self.scene_input.clone(),
+            art_cache: None,
+            cull_mode: self.cull_mode,
+            camera_inside: self.camera_inside,
+            split: None,
+            layouts: self.layouts.clone(),
+        }
+    }
+
+    /// Allocates or frees uniform buffers so there is one per frame in flight.
+    /// Pipelines created by the variant functions keep the old buffers and have to be recreated.
+    pub fn set_frames_in_flight(
+        &mut self,
+        frames_in_flight: usize,
+        uniform_buffer_allocator: &SubbufferAllocator,
+    ) -> anyhow::Result<()> {
+        self.uniform_buffers_vert.resize_with(frames_in_flight, || {
+            uniform_buffer_allocator.allocate_sized::<vs::UniformBufferObject>().unwrap()
+        });
+        self.uniform_buffers_frag.resize_with(frames_in_flight, || {
+            uniform_buffer_allocator.allocate_sized::<fs::UniformBufferObject>().unwrap()
+        });
+        if let Some(size) = self.uniform_buffers_block.first().map(Subbuffer::len) {
+            self.uniform_buffers_block.truncate(frames_in_flight);
+            while self.uniform_buffers_block.len() < frames_in_flight {
+                self.uniform_buffers_block.push(uniform_buffer_allocator.allocate_slice::<u8>(size)?);
+            }
+        }
+        if let Some(descriptor_sets) = self.descriptor_sets.as_mut() {
+            descriptor_sets.truncate(frames_in_flight);
+        }
+        self.update_descriptor_sets()
+    }
+
+    /// Whether this is the second pipeline of a compared exhibit created by `compare_variant`.
+    pub fn is_compare(&self) -> bool {
+        matches!(self.split, Some(Split::Right(_)))
+    }
+
+    pub fn fs(&self) -> &Arc<HotShader> {
+        &self.fs
+    }
+
+    /// Rolls back the fragment shader, or the vertex shader if that is not possible.
+    /// Returns `false` if neither has a previous module, see `HotShader::rollback`.
+    pub fn rollback_shaders(&self) -> bool {
+        self.fs.rollback() || self.vs.rollback()
+    }
+
+    /// Restricts drawing to one side of the split line or removes the restriction.
+    /// Returns `true` if the command buffers need to be recorded again.
+    pub fn set_split(&mut self, split: Option<Split>) -> bool {
+        if self.split == split {
+            return false;
+        }
+        // the scissor is only dynamic state while the pipeline is split
+        if self.split.is_some() != split.is_some() {
+            self.pipeline = None;
+        }
+        self.split = split;
+        true
+    }
+
+    /// Returns the scissor that must be set before drawing with a split pipeline.
+    pub fn scissor(&self, extent: [f32; 2]) -> Option<Scissor> {
+        let [width, height] = extent.map(|x| x as u32);
+        let (offset, width) = match self.split? {
+            Split::Left(split) => (0, (width as f32 * split) as u32),
+            Split::Right(split) => {
+                let offset = (width as f32 * split) as u32;
+                (offset, width - offset)
+            }
+        };
+        Some(Scissor { offset: [offset, 0], extent: [width, height] })
+    }
+
+    /// Whether the camera position is inside the container, expanded by `margin` in world units.
+    pub fn contains(&self, matrix: Mat4, position: Vec3, margin: f32) -> bool {
+        let (min, max) = self.geometry.extent();
+        box_contains(matrix, min, max, position, margin)
+    }
+
+    /// While the camera is inside the container all its faces are back faces and the closest ones
+    /// may be cut by the near plane, so culling is disabled and the depth is clamped instead.
+    /// Returns `true` if the pipeline has to be recreated.
+    pub fn set_camera_inside(&mut self, inside: bool) -> bool {
+        if self.camera_inside == inside {
+            return false;
+        }
+        self.camera_inside = inside;
+        self.pipeline.take().is_some()
+    }
+
     /// Checks if shaders need to be reloaded or forces them to be reloaded.
-    /// If shaders are reloaded, then `self.pipeline` is set to `None`.
-    /// Returns `true` if shaders are reloaded and `self.pipeline` was not already `None`.
+    /// The pipeline keeps drawing with the old modules until the shaders compiled successfully,
+    /// then `self.pipeline` is set to `None`.
+    /// Returns `true` if `self.pipeline` was set to `None`.
     /// Does nothing if pipeline is not enabled.
     pub fn reload_shaders(&mut self, forced: bool) -> bool {
         if !self.enable_pipeline {
             if self.vs.has_changed() | self.fs.has_changed() {
                 self.pipeline.take();
             }
-            false
-        } else if self.vs.reload(forced) | self.fs.reload(forced) {
-            self.pipeline.take().is_some()
-        } else {
-            false
+            return false;
         }
+        self.vs.reload(forced);
+        self.fs.reload(forced);
+        let current = (self.vs.get_module(), self.fs.get_module());
+        let outdated = match (&self.modules, current) {
+            (Some([vs, fs]), (Ok(Some(new_vs)), Ok(Some(new_fs)))) => {
+                !Arc::ptr_eq(vs, &new_vs) || !Arc::ptr_eq(fs, &new_fs)
+            }
+            _ => false,
+        };
+        outdated && self.pipeline.take().is_some()
     }
 
     pub fn update_uniform_buffer(
@@ -205,8 +551,9 @@ impl MyPipeline {
         idx: usize,
         view: Mat4,
         proj: Mat4,
-        time: f32,
+        frame: FrameInfo,
         data: Option<ArtData>,
+        block: Option<&[u8]>,
     ) -> anyhow::Result<()> {
         let model = data.map(|data| data.matrix).unwrap_or(Mat4::IDENTITY);
         *self.uniform_buffers_vert[idx].write()? = vs::UniformBufferObject {
@@ -216,12 +563,23 @@ impl MyPipeline {
         };
 
         if let Some(data) = data {
+            let (min, max) = self.geometry.extent();
+            let footprint = screen_footprint(proj * view * model, min, max, frame.extent);
             *self.uniform_buffers_frag[idx].write()? = fs::UniformBufferObject {
                 light_pos: data.light_pos.to_array(),
                 options: data.option_values.map(|chunk| chunk.to_array()),
-                time,
+                time: data.shader_time(frame.time),
+                seed: data.seed,
+                mouse: data.mouse.to_array(),
+                delta_time: frame.delta_time * data.speed(),
+                frame_index: frame.index,
+                footprint,
+                resolution: frame.extent.map(|len| len as f32),
             };
         }
+        if let (Some(buffer), Some(block)) = (self.uniform_buffers_block.get(idx), block) {
+            buffer.write()?.copy_from_slice(block);
+        }
 
         Ok(())
     }
@@ -241,19 +599,28 @@ impl MyPipeline {
 
         if let (Some(vs), Some(fs)) = (vs_module, fs_module) {
             log::debug!("updating pipeline {}", self.name);
+            if let Some(member_ends) = self.fs.ubo_member_ends() {
+                check_uniform_buffer(&self.name, &member_ends);
+            }
             let vs_entry = vs.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
             let fs_entry = fs.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+            let depth_clamp = self.camera_inside && device.enabled_features().depth_clamp;
             let pipeline = Self::create_pipeline(
                 device,
+                &self.layouts,
                 self.geometry.definition(&vs_entry)?,
                 vs_entry,
                 fs_entry,
                 self.subpass.clone(),
                 viewport,
                 self.enable_depth_test,
-                self.cull_mode,
+                self.depth_write,
+                if self.camera_inside { CullMode::None } else { self.cull_mode },
+                depth_clamp,
+                self.split.is_some(),
             )?;
             self.pipeline = Some(pipeline);
+            self.modules = Some([vs, fs]);
             self.update_descriptor_sets().context("failed to update descriptor_sets")?;
         } else {
             self.vs.reload(false);
@@ -263,6 +630,16 @@ impl MyPipeline {
         Ok(())
     }
 
+    /// Replaces the textures, used to swap the placeholder for the real texture once it is loaded.
+    /// The descriptor sets must not be in use.
+    pub fn set_textures(&mut self, textures: Vec<Texture>) -> anyhow::Result<()> {
+        if self.textures.is_empty() {
+            return Ok(());
+        }
+        self.textures = textures;
+        self.update_descriptor_sets()
+    }
+
     pub fn update_mirror_buffers(&mut self, mirror_buffers: [Arc<ImageView>; 2]) -> anyhow::Result<()> {
         if self.mirror_buffers.is_none() {
             return Ok(());
@@ -271,6 +648,43 @@ impl MyPipeline {
         self.update_descriptor_sets()
     }
 
+    pub fn update_previous_frame(&mut self, previous_frame: Vec<Texture>) -> anyhow::Result<()> {
+        if self.previous_frame.is_none() {
+            return Ok(());
+        }
+        self.previous_frame = Some(previous_frame);
+        self.update_descriptor_sets()
+    }
+
+    pub fn update_scene_input(&mut self, scene_input: [Texture; 2]) -> anyhow::Result<()> {
+        if self.scene_input.is_none() {
+            return Ok(());
+        }
+        self.scene_input = Some(scene_input);
+        self.update_descriptor_sets()
+    }
+
+    fn uses_binding(&self, binding: u32) -> bool {
+        self.pipeline.as_ref().is_some_and(|pipeline| {
+            pipeline.descriptor_binding_requirements().contains_key(&(ART_SET, binding))
+        })
+    }
+
+    /// Whether the shaders sample the previous frame, it only needs to be copied if one does.
+    pub fn uses_previous_frame(&self) -> bool {
+        self.uses_binding(PREVIOUS_FRAME_BINDING)
+    }
+
+    /// Whether the shaders sample the gallery, it only needs to be rendered if one does.
+    pub fn uses_scene_input(&self) -> bool {
+        SCENE_INPUT_BINDINGS.into_iter().any(|binding| self.uses_binding(binding))
+    }
+
+    /// Whether the shaders read the mirror subpass, which only the render pass of `super::VkApp` has.
+    pub fn uses_mirror_buffers(&self) -> bool {
+        MIRROR_BINDINGS.into_iter().any(|binding| self.uses_binding(binding))
+    }
+
     fn update_descriptor_sets(&mut self) -> anyhow::Result<()> {
         // sanity check
         debug_assert_eq!(self.uniform_buffers_vert.len(), self.uniform_buffers_frag.len());
@@ -278,7 +692,7 @@ impl MyPipeline {
         let Some(pipeline) = self.pipeline.as_ref() else {
             return Ok(());
         };
-        let layout = &pipeline.layout().set_layouts()[0];
+        let layout = &pipeline.layout().set_layouts()[ART_SET as usize];
         let bind_req = pipeline.descriptor_binding_requirements();
         let descriptor_sets = self.descriptor_sets.get_or_insert_with(|| {
             Vec::with_capacity(self.uniform_buffers_vert.len())
@@ -291,15 +705,40 @@ impl MyPipeline {
                 WriteDescriptorSet::buffer(0, self.uniform_buffers_vert[i].clone()),
                 WriteDescriptorSet::buffer(1, self.uniform_buffers_frag[i].clone()),
             ];
-            if let Some(Texture { view, sampler }) = self.texture.as_ref() {
+            // the descriptor set `i` is used by the frame in flight `i % frames`, see `App::draw`
+            if let Some(Texture { view, sampler }) = self.textures.get(i % self.textures.len().max(1)) {
                 let set = WriteDescriptorSet::image_view_sampler(2, view.clone(), sampler.clone());
                 write_sets.push(set);
             }
             if let Some(mirror_buffers) = self.mirror_buffers.as_ref() {
-                write_sets.push(WriteDescriptorSet::image_view(3, mirror_buffers[0].clone()));
-                write_sets.push(WriteDescriptorSet::image_view(4, mirror_buffers[1].clone()));
+                for (binding, view) in MIRROR_BINDINGS.into_iter().zip(mirror_buffers) {
+                    write_sets.push(WriteDescriptorSet::image_view(binding, view.clone()));
+                }
             }
-            write_sets.retain(|set| bind_req.contains_key(&(0, set.binding())));
+            let previous_frame = self.previous_frame.as_ref().and_then(|frames| frames.get(i % frames.len().max(1)));
+            if let Some(Texture { view, sampler }) = previous_frame {
+                let set = WriteDescriptorSet::image_view_sampler(PREVIOUS_FRAME_BINDING, view.clone(), sampler.clone());
+                write_sets.push(set);
+            }
+            if let Some(scene_input) = self.scene_input.as_ref() {
+                for (binding, Texture { view, sampler }) in SCENE_INPUT_BINDINGS.into_iter().zip(scene_input) {
+                    write_sets.push(WriteDescriptorSet::image_view_sampler(binding, view.clone(), sampler.clone()));
+                }
+            }
+            let art_cache = self.art_cache.as_ref().and_then(|caches| caches.get(i % caches.len().max(1)));
+            if let Some(art_cache) = art_cache {
+                for (binding, Texture { view, sampler }) in ART_CACHE_BINDINGS.into_iter().zip(art_cache) {
+                    write_sets.push(WriteDescriptorSet::image_view_sampler(binding, view.clone(), sampler.clone()));
+                }
+            }
+            match self.uniform_buffers_block.get(i) {
+                Some(buffer) => write_sets.push(WriteDescriptorSet::buffer(UNIFORM_BLOCK_BINDING, buffer.clone())),
+                None if bind_req.contains_key(&(ART_SET, UNIFORM_BLOCK_BINDING)) => {
+                    bail!("shaders of {} use binding {UNIFORM_BLOCK_BINDING} but it has no uniform block", self.name);
+                }
+                None => {}
+            }
+            write_sets.retain(|set| bind_req.contains_key(&(ART_SET, set.binding())));
             if let Some(descriptor_set) = descriptor_sets.get_mut(i) {
                 // SAFETY: I have no idea if this safe or not?
                 unsafe { descriptor_set.update_by_ref(write_sets, [])?; }
@@ -318,29 +757,35 @@ impl MyPipeline {
     #[allow(clippy::too_many_arguments)]
     fn create_pipeline(
         device: Arc<Device>,
+        layouts: &LayoutCache,
         vertex_input_state: VertexInputState,
         vs_entry: EntryPoint,
         fs_entry: EntryPoint,
         subpass: Subpass,
         viewport: Viewport,
         enable_depth_test: bool,
+        depth_write: bool,
         cull_mode: CullMode,
+        depth_clamp: bool,
+        dynamic_scissor: bool,
     ) -> anyhow::Result<Arc<GraphicsPipeline>> {
         let stages = [
             PipelineShaderStageCreateInfo::new(vs_entry),
             PipelineShaderStageCreateInfo::new(fs_entry),
         ];
 
-        let layout = PipelineLayout::new(
-            device.clone(),
-            PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages)
-                .into_pipeline_layout_create_info(device.clone())
-                .unwrap(),
-        )
-        .unwrap();
+        // the global set has the same layout in all pipelines, even if the shaders do not use it,
+        // so it stays bound when the next pipeline has the same layout for the set of the exhibit
+        let mut layout_info = PipelineDescriptorSetLayoutCreateInfo::from_stages(&stages);
+        let set_count = GLOBAL_SET.max(ART_SET) as usize + 1;
+        if layout_info.set_layouts.len() < set_count {
+            layout_info.set_layouts.resize_with(set_count, Default::default);
+        }
+        layout_info.set_layouts[GLOBAL_SET as usize] = GlobalSet::layout_create_info();
+        let layout = layouts.get_or_create(device.clone(), layout_info)?;
 
         let depth = if enable_depth_test {
-            Some(DepthState::simple())
+            Some(DepthState { write_enable: depth_write, ..DepthState::simple() })
         } else {
             None
         };
@@ -353,10 +798,12 @@ impl MyPipeline {
                 input_assembly_state: Some(InputAssemblyState::default()),
                 viewport_state: Some(ViewportState {
                     viewports: [viewport].into_iter().collect(),
+                    scissors: [Scissor::default()].into_iter().collect(),
                     ..Default::default()
                 }),
                 rasterization_state: Some(RasterizationState {
                     cull_mode,
+                    depth_clamp_enable: depth_clamp,
                     ..Default::default()
                 }),
                 multisample_state: Some(MultisampleState {
@@ -381,6 +828,11 @@ impl MyPipeline {
                         ..Default::default()
                     },
                 )),
+                dynamic_state: if dynamic_scissor {
+                    [DynamicState::Scissor].into_iter().collect()
+                } else {
+                    Default::default()
+                },
                 subpass: Some(subpass.into()),
                 ..GraphicsPipelineCreateInfo::layout(layout)
             },
@@ -392,8 +844,12 @@ impl MyPipeline {
 
 pub struct MyPipelines {
     pub order: Vec<usize>,
+    /// The scene pipelines, the first ones correspond to the mirror pipelines with the same index.
+    /// Pipelines of compared exhibits are appended after them and have no mirror counterpart.
     pub scene: Vec<MyPipeline>,
     pub mirror: Vec<MyPipeline>,
+    /// Layouts shared by all pipelines.
+    pub layouts: LayoutCache,
 }
 
 impl MyPipelines {
@@ -0,0 +1,230 @@
+use super::{
+    geometry::Geometry,
+    helpers::get_command_buffers,
+    pipeline::{MyPipeline, MyPipelineCreateInfo},
+    shader::HotShader,
+    texture::Texture,
+};
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use vulkano::{
+    buffer::allocator::SubbufferAllocator,
+    command_buffer::{allocator::StandardCommandBufferAllocator, SecondaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, DescriptorSet},
+    device::{physical::PhysicalDevice, Device, Queue},
+    format::Format,
+    image::{
+        view::ImageView,
+        sampler::{Sampler, SamplerAddressMode, SamplerCreateInfo},
+        Image, ImageCreateInfo, ImageFormatInfo, ImageTiling, ImageType, ImageUsage,
+    },
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::graphics::viewport::Viewport,
+    render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass},
+};
+
+/// The gallery rendered on its own before the scene, so exhibits can sample what is behind them.
+/// Ray marched volumes compare the depth to stop at walls, refractive exhibits distort the color.
+/// Both are only rendered if a shader uses them.
+pub struct SceneInput {
+    render_pass: Arc<RenderPass>,
+    framebuffer: Arc<Framebuffer>,
+    color: Texture,
+    depth: Texture,
+    pipeline: MyPipeline,
+    command_buffers: Vec<Arc<SecondaryAutoCommandBuffer>>,
+}
+
+impl SceneInput {
+    /// Fails if no depth format can be sampled.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        color_format: Format,
+        extent: [u32; 3],
+        vs: Arc<HotShader>,
+        fs: Arc<HotShader>,
+        geometry: Geometry,
+        device: Arc<Device>,
+        viewport: Viewport,
+        frames_in_flight: usize,
+        uniform_buffer_allocator: &SubbufferAllocator,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        let render_pass = offscreen_render_pass(device.clone(), color_format)?;
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let pipeline = MyPipeline::new(
+            MyPipelineCreateInfo {
+                name: "main scene input".to_owned(),
+                vs,
+                fs,
+                ..Default::default()
+            },
+            None,
+            Vec::new(),
+            device.clone(),
+            geometry,
+            subpass,
+            viewport,
+            frames_in_flight,
+            uniform_buffer_allocator,
+            descriptor_set_allocator,
+        ).context("failed to create pipeline")?;
+        let (framebuffer, color, depth) = offscreen_attachments(&render_pass, extent, device, memory_allocator)?;
+        Ok(Self { render_pass, framebuffer, color, depth, pipeline, command_buffers: Vec::new() })
+    }
+
+    /// Recreates the attachments with a new size, the textures must be rebound afterwards.
+    pub fn resize(
+        &mut self,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        viewport: Viewport,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<()> {
+        let (framebuffer, color, depth) =
+            offscreen_attachments(&self.render_pass, extent, device.clone(), memory_allocator)?;
+        self.framebuffer = framebuffer;
+        self.color = color;
+        self.depth = depth;
+        self.pipeline.update_pipeline(device, viewport).context("failed to update pipeline")
+    }
+
+    /// The color and depth of the gallery.
+    pub fn textures(&self) -> [Texture; 2] {
+        [self.color.clone(), self.depth.clone()]
+    }
+
+    pub fn pipeline(&self) -> &MyPipeline {
+        &self.pipeline
+    }
+
+    pub fn set_frames_in_flight(
+        &mut self,
+        frames_in_flight: usize,
+        uniform_buffer_allocator: &SubbufferAllocator,
+    ) -> anyhow::Result<()> {
+        self.pipeline.set_frames_in_flight(frames_in_flight, uniform_buffer_allocator)
+    }
+
+    /// Draws the gallery with a new model, e.g. after the scene was switched.
+    pub fn set_geometry(&mut self, geometry: Geometry, device: Arc<Device>, viewport: Viewport) -> anyhow::Result<()> {
+        self.pipeline.set_geometry(geometry);
+        self.pipeline.update_pipeline(device, viewport).context("failed to update pipeline")
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.pipeline.hidden = hidden;
+    }
+
+    pub fn update_command_buffers(
+        &mut self,
+        count: usize,
+        command_buffer_allocator: &Arc<StandardCommandBufferAllocator>,
+        queue: &Arc<Queue>,
+        global_sets: &[Arc<DescriptorSet>],
+        extent: [f32; 2],
+    ) {
+        let subpass = Subpass::from(self.render_pass.clone(), 0).unwrap();
+        self.command_buffers = get_command_buffers(
+            count,
+            command_buffer_allocator,
+            queue,
+            &[&self.pipeline],
+            global_sets,
+            &subpass,
+            extent,
+        );
+    }
+
+    /// The framebuffer and the commands rendering to it for the frame `image_idx`.
+    pub fn pass(&self, image_idx: usize) -> (Arc<Framebuffer>, Arc<SecondaryAutoCommandBuffer>) {
+        (self.framebuffer.clone(), self.command_buffers[image_idx].clone())
+    }
+}
+
+/// A render pass with a color and a depth attachment that can both be sampled afterwards.
+/// Fails if no depth format can be sampled.
+pub(super) fn offscreen_render_pass(device: Arc<Device>, color_format: Format) -> anyhow::Result<Arc<RenderPass>> {
+    let depth_format = find_sampled_depth_format(device.physical_device())
+        .context("no depth format can be sampled")?;
+    Ok(vulkano::single_pass_renderpass!(
+        device,
+        attachments: {
+            color: {
+                format: color_format,
+                samples: 1,
+                load_op: Clear,
+                store_op: Store,
+            },
+            depth: {
+                format: depth_format,
+                samples: 1,
+                load_op: Clear,
+                store_op: Store,
+            },
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {depth},
+        },
+    )?)
+}
+
+/// The framebuffer of an `offscreen_render_pass` and its color and depth attachments.
+pub(super) fn offscreen_attachments(
+    render_pass: &Arc<RenderPass>,
+    extent: [u32; 3],
+    device: Arc<Device>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+) -> anyhow::Result<(Arc<Framebuffer>, Texture, Texture)> {
+    let attachment = |format, usage| -> anyhow::Result<_> {
+        let image = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent,
+                usage: usage | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        Ok(ImageView::new_default(image)?)
+    };
+    let formats = render_pass.attachments();
+    // snapshots copy the color to a buffer
+    let color = attachment(formats[0].format, ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_SRC)?;
+    let depth = attachment(formats[1].format, ImageUsage::DEPTH_STENCIL_ATTACHMENT)?;
+    let framebuffer = Framebuffer::new(render_pass.clone(), FramebufferCreateInfo {
+        attachments: vec![color.clone(), depth.clone()],
+        ..Default::default()
+    })?;
+    let address_mode = [SamplerAddressMode::ClampToEdge; 3];
+    let color_sampler = Sampler::new(device.clone(), SamplerCreateInfo {
+        address_mode,
+        ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+    })?;
+    // depths are not interpolated between the edges of objects
+    let depth_sampler = Sampler::new(device, SamplerCreateInfo { address_mode, ..Default::default() })?;
+    Ok((
+        framebuffer,
+        Texture { view: color, sampler: color_sampler },
+        Texture { view: depth, sampler: depth_sampler },
+    ))
+}
+
+fn find_sampled_depth_format(device: &PhysicalDevice) -> Option<Format> {
+    // formats without stencil, so the image can be sampled through its default view
+    [Format::D32_SFLOAT, Format::D16_UNORM].into_iter().find(|&format| {
+        device.image_format_properties(ImageFormatInfo {
+            format,
+            image_type: ImageType::Dim2d,
+            tiling: ImageTiling::Optimal,
+            usage: ImageUsage::DEPTH_STENCIL_ATTACHMENT | ImageUsage::SAMPLED,
+            ..Default::default()
+        }).ok().flatten().is_some()
+    })
+}
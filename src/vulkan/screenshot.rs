@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Context;
+use image::RgbaImage;
+use vulkano::{
+    buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryAutoCommandBuffer,
+    },
+    device::Queue,
+    format::Format,
+    image::Image,
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    DeviceSize,
+};
+
+/// A copy of a swapchain image that is saved once the GPU finished copying it.
+pub struct Screenshot {
+    buffer: Subbuffer<[u8]>,
+    extent: [u32; 2],
+    format: Format,
+    path: PathBuf,
+}
+
+impl Screenshot {
+    /// Records copying `image` into a host visible buffer.
+    /// The returned command buffer must be executed after the frame was rendered.
+    pub fn record(
+        image: Arc<Image>,
+        path: PathBuf,
+        queue: &Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<(Self, Arc<PrimaryAutoCommandBuffer>)> {
+        let [width, height, _] = image.extent();
+        let format = image.format();
+        let buffer = Buffer::new_slice(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+                ..Default::default()
+            },
+            format.block_size() * width as DeviceSize * height as DeviceSize,
+        )?;
+
+        let mut command_buffer = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        command_buffer.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image, buffer.clone()))?;
+        let screenshot = Self { buffer, extent: [width, height], format, path };
+        Ok((screenshot, command_buffer.build()?))
+    }
+
+    /// Converts the copied pixels and writes them to a PNG on another thread.
+    /// The command buffer returned by `record` must have finished executing.
+    pub fn save(self) -> anyhow::Result<()> {
+        let swap_red_blue = match self.format {
+            Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => false,
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => true,
+            format => anyhow::bail!("screenshots of format {format:?} are not supported"),
+        };
+        let mut pixels = self.buffer.read()?.to_vec();
+        let path = self.path;
+        thread::spawn(move || {
+            if swap_red_blue {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            // the swapchain is not guaranteed to be opaque
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel[3] = 255;
+            }
+            let [width, height] = self.extent;
+            let result = RgbaImage::from_raw(width, height, pixels)
+                .context("buffer has the wrong size")
+                .and_then(|image| {
+                    if let Some(dir) = path.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    Ok(image.save(&path)?)
+                });
+            match result {
+                Ok(()) => log::info!("saved screenshot {}", path.display()),
+                Err(err) => log::error!("failed to save screenshot {}: {err:?}", path.display()),
+            }
+        });
+        Ok(())
+    }
+}
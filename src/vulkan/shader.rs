@@ -2,7 +2,7 @@ use std::{
     collections::{HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
-    sync::{mpsc, Arc, LazyLock, RwLock},
+    sync::{atomic::{AtomicU32, Ordering}, mpsc, Arc, LazyLock, RwLock},
     thread,
     time::{Duration, Instant},
 };
@@ -16,11 +16,85 @@ use vulkano::{
 
 const DEBOUNCE_TIME: Duration = Duration::from_millis(500);
 const MAX_INCLUDE_DEPTH: usize = 16;
+/// Searched by `#include` after the directories of `set_include_paths`.
+const DEFAULT_INCLUDE_DIR: &str = "assets/shaders";
+/// Declaration of the uniform buffer every exhibit gets at binding 1, a test checks that it matches
+/// `fs` in `super::helpers`. ISF shaders include it, see `crate::isf`.
+const UBO_INCLUDE: &str = "layout(set = 0, binding = 1) uniform UniformBufferObject {
+    vec4 light_pos;
+    vec4 options[OPTION_VEC4S];
+    float time;
+    float seed;
+    vec4 mouse;
+    float delta_time;
+    uint frame_index;
+    vec2 footprint;
+    vec2 resolution;
+} ubo;
+";
+
+/// See `set_include_paths`.
+static INCLUDE_PATHS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+/// Include snippets by name that only exist in memory, see `register_include`.
+static VIRTUAL_INCLUDES: LazyLock<RwLock<HashMap<String, String>>> = LazyLock::new(|| {
+    RwLock::new(HashMap::from([("sp/ubo.glsl".to_owned(), UBO_INCLUDE.to_owned())]))
+});
+
+/// Sets the directories `#include` searches when the file is not next to the including one.
+/// `assets/shaders` is searched last, so `#include "includes/noise.glsl"` works from any directory.
+/// Only affects shaders compiled afterwards, returns whether the paths changed.
+pub fn set_include_paths(paths: Vec<PathBuf>) -> bool {
+    match INCLUDE_PATHS.write() {
+        Ok(mut include_paths) if *include_paths != paths => {
+            *include_paths = paths;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Makes `content` includable as `name`, e.g. `#include <sp/ubo.glsl>` for the uniform buffer
+/// every exhibit gets. Virtual includes are found before any file. Only affects shaders compiled afterwards.
+pub fn register_include(name: impl Into<String>, content: impl Into<String>) {
+    if let Ok(mut includes) = VIRTUAL_INCLUDES.write() {
+        includes.insert(name.into(), content.into());
+    }
+}
+
+/// Looks `name` up in the virtual includes, the directory of `src`, the include paths and
+/// `DEFAULT_INCLUDE_DIR` in this order.
+fn resolve_include(name: &str, src: &str) -> Result<ResolvedInclude, String> {
+    if let Some(content) = VIRTUAL_INCLUDES.read().ok().and_then(|includes| includes.get(name).cloned()) {
+        return Ok(ResolvedInclude { resolved_name: name.to_owned(), content });
+    }
+    let src = Path::new(src);
+    let mut dirs = vec![src.parent().unwrap_or(src).to_path_buf()];
+    dirs.extend(INCLUDE_PATHS.read().map(|paths| paths.clone()).unwrap_or_default());
+    dirs.push(PathBuf::from(DEFAULT_INCLUDE_DIR));
+    for dir in dirs {
+        let path = dir.join(name);
+        match crate::fs::read_to_string(&path) {
+            Ok(content) => {
+                return Ok(ResolvedInclude { resolved_name: path.to_string_lossy().into_owned(), content });
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Failed to read file {}: {err}", path.display())),
+        }
+    }
+    Err(format!("Failed to find {name} next to {} or in the include paths", src.display()))
+}
 
 static COMPILE_THREAD: LazyLock<mpsc::Sender<Arc<HotShader>>> = LazyLock::new(|| {
     let (tx, rx) = mpsc::channel::<Arc<HotShader>>();
     thread::spawn(move || {
-        while let Ok(shader) = rx.recv() {
+        let mut queue = Vec::new();
+        loop {
+            if queue.is_empty() {
+                let Ok(shader) = rx.recv() else { break };
+                queue.push(shader);
+            }
+            queue.extend(rx.try_iter());
+            let Some(shader) = pop_most_urgent(&mut queue) else { continue };
             if let Err(err) = shader.compile_code() {
                 match &shader.path {
                     Some(path) => log::error!("Error compiling shader {}: {err:#}", path.display()),
@@ -32,12 +106,27 @@ static COMPILE_THREAD: LazyLock<mpsc::Sender<Arc<HotShader>>> = LazyLock::new(||
     tx
 });
 
+/// Removes the shader with the lowest priority from `queue`, the first queued one among equals.
+fn pop_most_urgent(queue: &mut Vec<Arc<HotShader>>) -> Option<Arc<HotShader>> {
+    let idx = queue.iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.priority().total_cmp(&b.priority()))
+        .map(|(idx, _)| idx)?;
+    Some(queue.remove(idx))
+}
+
 pub fn watch_shaders<S: IntoIterator<Item = Arc<HotShader>>>(shaders: S) {
+    // only loose files can be watched, shaders from archives or embedded ones are never reloaded
     let shaders_by_path = shaders.into_iter()
         .filter_map(|shader| {
-            shader.path.as_ref()
-                .and_then(|path| fs::canonicalize(path).ok())
-                .map(|path| (path, shader))
+            let path = shader.path.as_ref()?;
+            match fs::canonicalize(path) {
+                Ok(path) => Some((path, shader)),
+                Err(_) => {
+                    log::debug!("not watching shader {} as it is not a loose file", path.display());
+                    None
+                }
+            }
         })
         .collect::<HashMap<_, _>>();
 
@@ -87,9 +176,15 @@ pub fn watch_shaders<S: IntoIterator<Item = Arc<HotShader>>>(shaders: S) {
     });
 }
 
+/// Transforms the source of a shader before it is compiled.
+pub type Preprocessor = fn(&str) -> anyhow::Result<String>;
+
 pub struct HotShader {
     path: Option<PathBuf>,
     shader_kind: ShaderKind,
+    preprocessor: Option<Preprocessor>,
+    /// Bits of the `f32` set by `set_priority`.
+    priority: AtomicU32,
     inner: RwLock<HotShaderInner>,
 }
 
@@ -98,6 +193,8 @@ impl HotShader {
         Self {
             path: Some(path.into()),
             shader_kind,
+            preprocessor: None,
+            priority: AtomicU32::new(0),
             inner: RwLock::new(HotShaderInner {
                 code_has_changed: true,
                 ..Default::default()
@@ -109,6 +206,8 @@ impl HotShader {
         Self {
             path: None,
             shader_kind,
+            preprocessor: None,
+            priority: AtomicU32::new(0),
             inner: RwLock::new(HotShaderInner {
                 module: Some(module),
                 ..Default::default()
@@ -124,6 +223,34 @@ impl HotShader {
         Self::new(path, ShaderKind::Fragment)
     }
 
+    /// A fragment shader in the Interactive Shader Format, see `crate::isf`.
+    pub fn new_isf<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            preprocessor: Some(crate::isf::to_glsl),
+            ..Self::new_frag(path)
+        }
+    }
+
+    /// An audio shader that is compiled to a compute shader, see `super::sound`.
+    pub fn new_sound<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            preprocessor: Some(super::sound::to_compute),
+            ..Self::new(path, ShaderKind::Compute)
+        }
+    }
+
+    /// A transition between two frames that is compiled to a compute shader, see `super::transition`.
+    pub fn new_transition<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            preprocessor: Some(super::transition::to_compute),
+            ..Self::new(path, ShaderKind::Compute)
+        }
+    }
+
+    pub fn shader_kind(&self) -> ShaderKind {
+        self.shader_kind
+    }
+
     pub fn set_device(&self, device: Arc<Device>) {
         let mut inner = self.inner.write().unwrap();
         inner.device = Some(device);
@@ -134,6 +261,45 @@ impl HotShader {
         Ok(inner.module.clone())
     }
 
+    /// Returns a non hot shader with the last module that compiled successfully.
+    /// This stays available while the current code fails to compile.
+    pub fn snapshot(&self) -> Option<Self> {
+        let inner = self.inner.read().ok()?;
+        let module = inner.module.clone()?;
+        Some(Self::new_nonhot(module, self.shader_kind))
+    }
+
+    /// The last compilation failed and the module is from an older version of the code.
+    pub fn is_stale(&self) -> bool {
+        self.inner.read().is_ok_and(|inner| inner.stats.failed && inner.module.is_some())
+    }
+
+    /// Goes back to the module that compiled successfully before the current one,
+    /// e.g. when the current one hangs the GPU. Returns `false` if there is none.
+    pub fn rollback(&self) -> bool {
+        let Ok(mut inner) = self.inner.write() else { return false };
+        let Some((previous, ubo_member_ends)) = inner.previous_module.take() else { return false };
+        inner.module = Some(previous);
+        inner.ubo_member_ends = ubo_member_ends;
+        true
+    }
+
+    /// Byte offsets of the ends of the members of the uniform buffer at binding 1 of the exhibit set,
+    /// `None` if the module does not declare it or was not compiled from source.
+    pub fn ubo_member_ends(&self) -> Option<Vec<u64>> {
+        self.inner.read().ok()?.ubo_member_ends.clone()
+    }
+
+    pub fn priority(&self) -> f32 {
+        f32::from_bits(self.priority.load(Ordering::Relaxed))
+    }
+
+    /// Shaders with a lower priority are compiled first when several are queued, e.g. after a checkout.
+    /// Defaults to 0.
+    pub fn set_priority(&self, priority: f32) {
+        self.priority.store(priority.to_bits(), Ordering::Relaxed);
+    }
+
     pub fn has_changed(&self) -> bool {
         let inner = self.inner.read().unwrap();
         inner.code_has_changed || inner.is_compiling
@@ -142,7 +308,10 @@ impl HotShader {
     /// Reloads shader if changed or `forced` is `true`.
     /// Returns `true` if shader is recompiling.
     pub fn reload(self: &Arc<Self>, forced: bool) -> bool {
-        let path = self.path.as_ref().expect("shader must have a path set to load it");
+        // non hot shaders are never reloaded
+        let Some(path) = self.path.as_ref() else {
+            return false;
+        };
         let mut inner = self.inner.write().unwrap();
         if inner.is_compiling {
             return true;
@@ -152,8 +321,8 @@ impl HotShader {
         }
 
         // reset code_has_changed here so we don't loop if an error happens
+        // the module is kept until the new one compiled, so the exhibit does not go blank
         inner.code_has_changed = false;
-        inner.module = None;
 
         let sender = COMPILE_THREAD.clone();
         match sender.send(self.clone()) {
@@ -174,26 +343,116 @@ impl HotShader {
         let Some(device) = inner.device.clone() else {
             return Err(anyhow::anyhow!("device not set"));
         };
+        let instrumentation = inner.instrumentation;
+        let prelude = inner.prelude.clone();
         drop(inner);
         // Compiling takes some time, do not keep a lock while compiling!
-        let result = self.compile_code_helper(device);
+        let result = self.compile_code_helper(device, instrumentation, prelude.as_deref());
         let mut inner = self.inner.write().map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
         inner.is_compiling = false;
+        inner.stats.count += 1;
+        inner.stats.failed = result.is_err();
         match result {
-            Ok(module) => {
-                inner.module = Some(module);
+            Ok(compiled) => {
+                if let Some(path) = self.path.as_ref() {
+                    for warning in compiled.warnings.iter() {
+                        log::warn!("{}: {warning}", path.display());
+                    }
+                }
+                inner.stats.duration = Some(compiled.duration);
+                inner.stats.spirv_size = compiled.spirv_size;
+                inner.stats.warnings = compiled.warnings.len();
+                inner.warnings = compiled.warnings;
+                let ubo_member_ends = std::mem::replace(&mut inner.ubo_member_ends, compiled.ubo_member_ends);
+                inner.previous_module = inner.module.replace(compiled.module).map(|module| (module, ubo_member_ends));
                 Ok(())
             }
             Err(err) => Err(err),
         }
     }
 
-    fn compile_code_helper(&self, device: Arc<Device>) -> anyhow::Result<Arc<ShaderModule>> {
+    /// Compiles the current code without debugging aids for another device than the one of the shader.
+    /// The module of the shader is left alone, so it can keep being drawn by a `super::VkApp`.
+    pub(super) fn compile_for(&self, device: Arc<Device>) -> anyhow::Result<Arc<ShaderModule>> {
+        let prelude = self.inner.read().map_err(|_| anyhow::anyhow!("Lock poisoned"))?.prelude.clone();
+        let compiled = self.compile_code_helper(device, Instrumentation::default(), prelude.as_deref())?;
+        Ok(compiled.module)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn debug_printf(&self) -> bool {
+        self.inner.read().is_ok_and(|inner| inner.instrumentation.debug_printf)
+    }
+
+    /// Compiles the shader with `GL_EXT_debug_printf` enabled and `DEBUG_PRINTF` defined,
+    /// so calls to `debugPrintfEXT` can be guarded with `#ifdef DEBUG_PRINTF`.
+    /// The output is only collected if `enable_debug_printf` was called at startup.
+    pub fn set_debug_printf(self: &Arc<Self>, enabled: bool) {
+        self.set_instrumentation(|instrumentation| instrumentation.debug_printf = enabled);
+    }
+
+    /// Compiles the shader with `COST_HEATMAP` defined, see `assets/shaders/includes/cost.glsl`.
+    pub fn set_cost_heatmap(self: &Arc<Self>, enabled: bool) {
+        self.set_instrumentation(|instrumentation| instrumentation.cost_heatmap = enabled);
+    }
+
+    /// Recompiles the shader if `update` changed the instrumentation.
+    fn set_instrumentation(self: &Arc<Self>, update: impl FnOnce(&mut Instrumentation)) {
+        let Ok(mut inner) = self.inner.write() else { return };
+        let old = inner.instrumentation;
+        update(&mut inner.instrumentation);
+        if inner.instrumentation != old {
+            drop(inner);
+            self.reload(true);
+        }
+    }
+
+    pub fn compile_stats(&self) -> CompileStats {
+        self.inner.read().map(|inner| inner.stats).unwrap_or_default()
+    }
+
+    /// Warnings of the compiler, unused uniforms and complaints of `spirv-val` about the last
+    /// successful compilation, see `super::lint`.
+    pub fn warnings(&self) -> Vec<String> {
+        self.inner.read().map(|inner| inner.warnings.clone()).unwrap_or_default()
+    }
+
+    pub fn prelude(&self) -> Option<String> {
+        self.inner.read().ok().and_then(|inner| inner.prelude.clone())
+    }
+
+    /// Inserts `prelude` after the `#version` line of the source, see `crate::prelude`.
+    /// The shader is compiled again on the next reload if the prelude changed.
+    pub fn set_prelude(&self, prelude: String) {
+        let Ok(mut inner) = self.inner.write() else { return };
+        if inner.prelude.as_ref() != Some(&prelude) {
+            inner.prelude = Some(prelude);
+            inner.code_has_changed = true;
+        }
+    }
+
+    /// Returns the source of the shader with all includes and macros expanded.
+    pub fn preprocessed_source(&self) -> anyhow::Result<String> {
+        let Some(path) = self.path.as_ref() else {
+            return Err(anyhow::anyhow!("non hot shader has no source"));
+        };
+        let prelude = self.inner.read().ok().and_then(|inner| inner.prelude.clone());
+        HotShaderInner::preprocess(path, self.preprocessor, prelude.as_deref())
+    }
+
+    fn compile_code_helper(
+        &self,
+        device: Arc<Device>,
+        instrumentation: Instrumentation,
+        prelude: Option<&str>,
+    ) -> anyhow::Result<Compiled> {
         let Some(path) = self.path.as_ref() else {
             return Err(anyhow::anyhow!("cannot compile non hot shader"));
         };
-        let module = HotShaderInner::compile(path, self.shader_kind, device)?;
-        Ok(module)
+        HotShaderInner::compile(path, self.shader_kind, self.preprocessor, prelude, instrumentation, device)
     }
 }
 
@@ -203,56 +462,89 @@ impl Default for HotShader {
             path: Default::default(),
             // this is just some arbitrary value that should never be used
             shader_kind: ShaderKind::DefaultVertex,
+            preprocessor: None,
+            priority: AtomicU32::new(0),
             inner: Default::default(),
         }
     }
 }
 
+/// Statistics of the compilations of a hot shader.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompileStats {
+    /// Number of compilations including failed ones.
+    pub count: u32,
+    /// Duration of the last successful compilation, `None` if none succeeded yet.
+    pub duration: Option<Duration>,
+    /// Size of the SPIR-V code of the last successful compilation in bytes.
+    pub spirv_size: usize,
+    /// Whether the last compilation failed.
+    pub failed: bool,
+    /// Number of warnings of the last successful compilation, see `HotShader::warnings`.
+    pub warnings: usize,
+}
+
+/// Result of a successful compilation.
+struct Compiled {
+    module: Arc<ShaderModule>,
+    /// Size of the SPIR-V code in bytes.
+    spirv_size: usize,
+    /// See `HotShader::ubo_member_ends`.
+    ubo_member_ends: Option<Vec<u64>>,
+    /// Of the compiler and of `super::lint`.
+    warnings: Vec<String>,
+    /// Time to compile and load the module, the lints are not included.
+    duration: Duration,
+}
+
 #[derive(Default)]
 pub struct HotShaderInner {
     device: Option<Arc<Device>>,
     is_compiling: bool,
     code_has_changed: bool,
+    /// The last module that compiled successfully, kept while a new one compiles or fails to.
     module: Option<Arc<ShaderModule>>,
+    /// See `HotShader::ubo_member_ends`.
+    ubo_member_ends: Option<Vec<u64>>,
+    /// The module before `module` with its `ubo_member_ends`, see `HotShader::rollback`.
+    previous_module: Option<(Arc<ShaderModule>, Option<Vec<u64>>)>,
+    stats: CompileStats,
+    instrumentation: Instrumentation,
+    /// See `HotShader::set_prelude`.
+    prelude: Option<String>,
+    /// See `HotShader::warnings`.
+    warnings: Vec<String>,
+}
+
+/// Debugging aids compiled into a shader.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Instrumentation {
+    debug_printf: bool,
+    cost_heatmap: bool,
 }
 
 impl HotShaderInner {
-    fn compile(path: &Path, kind: ShaderKind, device: Arc<Device>)
-        -> anyhow::Result<Arc<ShaderModule>>
-    {
+    fn compile(
+        path: &Path,
+        kind: ShaderKind,
+        preprocessor: Option<Preprocessor>,
+        prelude: Option<&str>,
+        instrumentation: Instrumentation,
+        device: Arc<Device>,
+    ) -> anyhow::Result<Compiled> {
         log::debug!("compiling shader {} of kind {:?}", path.display(), kind);
         let start = Instant::now();
-        let source = fs::read_to_string(path)?;
+        let mut source = Self::read_source(path, preprocessor, prelude)?;
         let compiler = Compiler::new()
             .ok_or_else(|| anyhow::anyhow!("failed to get compiler"))?;
-        let mut options = CompileOptions::new()
-            .ok_or_else(|| anyhow::anyhow!("failed to get compile options"))?;
-        options.set_include_callback(|name, _ty, src, depth| {
-            // ty returns always IncludeType::Standard for some reason
-            // just ignore it and assume IncludeType::Relative
-            /*
-            if let IncludeType::Standard = ty {
-                return Err(r#"Standard includes (#include <...>) are not supported, please use relative includes (#include "...")."#.to_owned());
-            }
-            */
-
-            if depth > MAX_INCLUDE_DEPTH {
-                return Err(format!("Exceeded max include depth of {MAX_INCLUDE_DEPTH}."));
-            }
-
-            let path = Path::new(src);
-            let path = path.parent().unwrap_or(path).join(name);
-            let content = match std::fs::read_to_string(&path) {
-                Ok(content) => content,
-                Err(err) => {
-                    return Err(format!("Failed to read file {}: {err}", path.display()));
-                }
-            };
-            Ok(ResolvedInclude {
-                resolved_name: path.to_string_lossy().into_owned(),
-                content,
-            })
-        });
+        let mut options = Self::compile_options()?;
+        if instrumentation.debug_printf {
+            source = Self::enable_debug_printf(&source);
+            options.add_macro_definition("DEBUG_PRINTF", None);
+        }
+        if instrumentation.cost_heatmap {
+            options.add_macro_definition("COST_HEATMAP", None);
+        }
 
         let binary_result = compiler.compile_into_spirv(
             &source,
@@ -265,8 +557,157 @@ impl HotShaderInner {
         let module = unsafe {
             ShaderModule::new(device, ShaderModuleCreateInfo::new(code))?
         };
-        let time = start.elapsed();
-        log::debug!("done compiling, took {time:?}");
-        Ok(module)
+        let duration = start.elapsed();
+        log::debug!("done compiling, took {duration:?}");
+        let mut warnings = Vec::new();
+        if binary_result.get_num_warnings() > 0 {
+            let messages = binary_result.get_warning_messages();
+            warnings.extend(messages.lines().filter(|line| !line.trim().is_empty()).map(str::to_owned));
+        }
+        warnings.extend(super::lint::unused_resources(code));
+        warnings.extend(super::lint::spirv_val(code));
+        let ubo_member_ends = super::lint::uniform_block_member_ends(code, super::global_set::ART_SET, 1);
+        Ok(Compiled { module, spirv_size: std::mem::size_of_val(code), ubo_member_ends, warnings, duration })
+    }
+
+    /// Returns the source with all includes and macros expanded.
+    fn preprocess(
+        path: &Path,
+        preprocessor: Option<Preprocessor>,
+        prelude: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let source = Self::read_source(path, preprocessor, prelude)?;
+        let compiler = Compiler::new()
+            .ok_or_else(|| anyhow::anyhow!("failed to get compiler"))?;
+        let options = Self::compile_options()?;
+        let result = compiler.preprocess(&source, &path.to_string_lossy(), "main", Some(&options))?;
+        Ok(result.as_text())
+    }
+
+    /// Splits the source after the `#version` line, which must come first.
+    /// The head ends with a newline unless it is empty.
+    fn split_after_version(source: &str) -> (String, &str) {
+        let insert_at = source.find("#version")
+            .map(|start| source[start..].find('\n').map_or(source.len(), |end| start + end + 1))
+            .unwrap_or(0);
+        let (head, tail) = source.split_at(insert_at);
+        let newline = if head.is_empty() || head.ends_with('\n') { "" } else { "\n" };
+        (format!("{head}{newline}"), tail)
+    }
+
+    /// Inserts the extension directive after the `#version` line.
+    fn enable_debug_printf(source: &str) -> String {
+        let (head, tail) = Self::split_after_version(source);
+        format!("{head}#extension GL_EXT_debug_printf : enable\n{tail}")
+    }
+
+    /// Inserts the prelude after the `#version` line, the lines after it keep their numbers in errors.
+    fn insert_prelude(source: &str, prelude: &str) -> String {
+        let (head, tail) = Self::split_after_version(source);
+        let newline = if prelude.ends_with('\n') { "" } else { "\n" };
+        let next_line = head.lines().count() + 1;
+        format!("{head}{prelude}{newline}#line {next_line}\n{tail}")
+    }
+
+    fn read_source(
+        path: &Path,
+        preprocessor: Option<Preprocessor>,
+        prelude: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let source = crate::fs::read_to_string(path)?;
+        let source = match preprocessor {
+            Some(preprocessor) => preprocessor(&source)?,
+            None => source,
+        };
+        Ok(match prelude {
+            Some(prelude) => Self::insert_prelude(&source, prelude),
+            None => source,
+        })
+    }
+
+    fn compile_options() -> anyhow::Result<CompileOptions<'static>> {
+        let mut options = CompileOptions::new()
+            .ok_or_else(|| anyhow::anyhow!("failed to get compile options"))?;
+        options.add_macro_definition("OPTION_VEC4S", Some(&crate::art::OPTION_VEC4S.to_string()));
+        options.set_include_callback(|name, _ty, src, depth| {
+            // ty returns always IncludeType::Standard for some reason
+            // so <...> and "..." includes are both resolved like relative ones
+            if depth > MAX_INCLUDE_DEPTH {
+                return Err(format!("Exceeded max include depth of {MAX_INCLUDE_DEPTH}."));
+            }
+            resolve_include(name, src)
+        });
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_printf_extension_follows_version() {
+        let source = "#version 450\nvoid main() {}\n";
+        assert_eq!(
+            HotShaderInner::enable_debug_printf(source),
+            "#version 450\n#extension GL_EXT_debug_printf : enable\nvoid main() {}\n",
+        );
+        assert_eq!(
+            HotShaderInner::enable_debug_printf("#version 450"),
+            "#version 450\n#extension GL_EXT_debug_printf : enable\n",
+        );
+    }
+
+    #[test]
+    fn prelude_keeps_line_numbers() {
+        let source = "#version 450\nvoid main() {}\n";
+        assert_eq!(
+            HotShaderInner::insert_prelude(source, "float a;\nfloat b;"),
+            "#version 450\nfloat a;\nfloat b;\n#line 2\nvoid main() {}\n",
+        );
+    }
+
+    #[test]
+    fn ubo_include_matches_the_default_shaders() {
+        // the declaration without comments and with the same whitespace and array size
+        let normalize = |block: &str| {
+            block.lines()
+                .map(|line| line.split("//").next().unwrap_or_default().trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .replace("OPTION_VEC4S", &crate::art::OPTION_VEC4S.to_string())
+        };
+        let helpers = include_str!("helpers.rs");
+        let blocks = ["pub mod fs {", "pub mod fs_flat {"].map(|module| {
+            let module = &helpers[helpers.find(module).unwrap()..];
+            let start = module.find("layout(set = 0, binding = 1) uniform UniformBufferObject").unwrap();
+            let end = start + module[start..].find("} ubo;").unwrap() + "} ubo;".len();
+            normalize(&module[start..end])
+        });
+        assert_eq!(blocks[0], normalize(UBO_INCLUDE));
+        assert_eq!(blocks[1], normalize(UBO_INCLUDE));
+    }
+
+    #[test]
+    fn virtual_includes_resolve() {
+        register_include("sp/test.glsl", "float test;");
+        let resolved = resolve_include("sp/test.glsl", "assets/shaders/gem.frag").unwrap();
+        assert_eq!(resolved.content, "float test;");
+        assert!(resolve_include("sp/missing.glsl", "assets/shaders/gem.frag").is_err());
+    }
+
+    #[test]
+    fn lowest_priority_compiles_first() {
+        let queue = [3., 1., f32::INFINITY, 1.].map(|priority| {
+            let shader = Arc::new(HotShader::default());
+            shader.set_priority(priority);
+            shader
+        });
+        let mut pending = queue.to_vec();
+        let order = std::iter::from_fn(|| pop_most_urgent(&mut pending))
+            .map(|shader| queue.iter().position(|other| Arc::ptr_eq(other, &shader)).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(order, [1, 3, 0, 2]);
     }
 }
@@ -0,0 +1,382 @@
+//! Renders exhibits to an image without a window or an event loop, e.g. for catalogs or CI.
+
+use crate::{
+    art::{ArtData, ArtObject, TextureSource},
+    camera::Camera,
+    model::obj::NormalizedObj,
+};
+use super::{
+    app::{App, ClearColors, KEYBOARD_EXTENT},
+    debug::*,
+    environment::EnvironmentMap,
+    geometry::Geometry,
+    global_set::{CameraUniforms, GlobalSet, GlobalUniforms},
+    helpers::{
+        fs, fs_flat, get_command_buffers, optional_device_features, select_physical_device, vs, FENCE_TIMEOUT,
+    },
+    noise::NoiseTextures,
+    pipeline::{FrameInfo, LayoutCache, MyPipeline, MyPipelineCreateInfo},
+    scene_input::{offscreen_attachments, offscreen_render_pass},
+    shader::HotShader,
+    texture::{StreamingTexture, Texture},
+    vertex::VertexType,
+};
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context;
+use glam::{Mat4, Vec3, Vec4};
+use image::RgbaImage;
+use shaderc::ShaderKind;
+use vulkano::{
+    buffer::allocator::{SubbufferAllocator, SubbufferAllocatorCreateInfo},
+    buffer::{Buffer, BufferCreateInfo, BufferUsage},
+    command_buffer::allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo},
+    command_buffer::{
+        AutoCommandBufferBuilder, ClearColorImageInfo, CommandBufferUsage, CopyImageToBufferInfo,
+        PrimaryCommandBufferAbstract, RenderPassBeginInfo, SubpassBeginInfo, SubpassContents,
+    },
+    descriptor_set::allocator::StandardDescriptorSetAllocator,
+    device::{Device, DeviceCreateInfo, DeviceExtensions, QueueCreateInfo},
+    format::{ClearValue, Format},
+    instance::{Instance, InstanceCreateFlags, InstanceCreateInfo},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::graphics::viewport::Viewport,
+    render_pass::Subpass,
+    sync::GpuFuture,
+    DeviceSize,
+};
+
+/// Format of the snapshot, the shaders write linear colors that are stored as sRGB.
+const COLOR_FORMAT: Format = Format::R8G8B8A8_SRGB;
+
+/// What `render_snapshot` draws.
+#[derive(Clone, Copy)]
+pub struct SnapshotScene<'a> {
+    /// Drawn with the placement, options and time controls in their `ArtData`.
+    pub art_objs: &'a [ArtObject],
+    /// The gallery around the exhibits, only the exhibits are drawn if it is `None`.
+    pub model: Option<&'a NormalizedObj>,
+    /// Environment map of the sky and the reflections, black if it is `None`.
+    pub environment: Option<&'a Path>,
+    pub light_pos: Vec4,
+    /// Time passed to the shaders in seconds.
+    pub time: f32,
+    /// Vertical field of view in degrees.
+    pub fov: f32,
+    /// Distances of the clip planes.
+    pub near: f32,
+    pub far: f32,
+    /// Shown where nothing is drawn.
+    pub clear_color: [f32; 4],
+}
+
+impl<'a> SnapshotScene<'a> {
+    pub fn new(art_objs: &'a [ArtObject]) -> Self {
+        Self {
+            art_objs,
+            model: None,
+            environment: None,
+            light_pos: Vec4::splat(100.),
+            time: 0.,
+            fov: 75.,
+            near: 0.01,
+            far: 200.,
+            clear_color: ClearColors::default().scene,
+        }
+    }
+}
+
+/// Renders `scene` as seen from `camera` into an image of `size` pixels on a device of its own.
+/// The shaders are compiled from their current code, an exhibit failing to compile is an error.
+/// Exhibits reading the mirror are left out, there is no mirror subpass. Those sampling the gallery
+/// see the gallery without exhibits and those sampling the previous frame see black.
+pub fn render_snapshot(scene: &SnapshotScene, camera: &Camera, size: [u32; 2]) -> anyhow::Result<RgbaImage> {
+    anyhow::ensure!(size[0] > 0 && size[1] > 0, "the snapshot size {size:?} is empty");
+    let library = vulkano::VulkanLibrary::new()
+        .context("no local Vulkan library/DLL")?;
+    let (debug_extensions, debug_layers) = get_debug_extensions_and_layers();
+    anyhow::ensure!(check_layer_support(&library, &debug_layers)?, "not all required layers are supported");
+    let instance = Instance::new(
+        library,
+        InstanceCreateInfo {
+            flags: InstanceCreateFlags::ENUMERATE_PORTABILITY,
+            enabled_layers: debug_layers,
+            enabled_extensions: debug_extensions,
+            enabled_validation_features: get_validation_features(),
+            ..Default::default()
+        },
+    ).context("failed to create instance")?;
+    let _debug = setup_debug_callback(instance.clone())
+        .context("failed to setup debug callback")?;
+
+    let (physical_device, queue_family_index) =
+        select_physical_device(&instance, None, &DeviceExtensions::empty())?;
+    let device_features = physical_device.supported_features().intersection(&optional_device_features());
+    let (device, mut queues) = Device::new(
+        physical_device,
+        DeviceCreateInfo {
+            queue_create_infos: vec![QueueCreateInfo {
+                queue_family_index,
+                ..Default::default()
+            }],
+            enabled_features: device_features,
+            ..Default::default()
+        },
+    ).context("failed to create device")?;
+    let queue = queues.next().unwrap();
+
+    let memory_allocator = Arc::new(StandardMemoryAllocator::new_default(device.clone()));
+    let descriptor_set_allocator = Arc::new(StandardDescriptorSetAllocator::new(
+        device.clone(),
+        Default::default(),
+    ));
+    let uniform_buffer_allocator = SubbufferAllocator::new(
+        memory_allocator.clone(),
+        SubbufferAllocatorCreateInfo {
+            buffer_usage: BufferUsage::UNIFORM_BUFFER,
+            memory_type_filter: MemoryTypeFilter::PREFER_DEVICE
+                | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+            ..Default::default()
+        },
+    );
+    let command_buffer_allocator = Arc::new(StandardCommandBufferAllocator::new(
+        device.clone(),
+        StandardCommandBufferAllocatorCreateInfo {
+            secondary_buffer_count: 2,
+            ..Default::default()
+        },
+    ));
+
+    let environment = match scene.environment {
+        Some(path) => EnvironmentMap::new(
+            path,
+            device.clone(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            memory_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        ).with_context(|| format!("failed to load environment map {}", path.display()))?,
+        None => EnvironmentMap::fallback(
+            device.clone(),
+            queue.clone(),
+            command_buffer_allocator.clone(),
+            memory_allocator.clone(),
+            descriptor_set_allocator.clone(),
+        ).context("failed to create environment map")?,
+    };
+    let noise = NoiseTextures::new(
+        device.clone(),
+        queue.clone(),
+        command_buffer_allocator.clone(),
+        memory_allocator.clone(),
+    ).context("failed to create noise textures")?;
+    let keyboard = StreamingTexture::new(KEYBOARD_EXTENT, device.clone(), memory_allocator.clone())
+        .context("failed to create keyboard texture")?;
+    let global_set = GlobalSet::new(
+        environment,
+        noise,
+        keyboard.texture.clone(),
+        device.clone(),
+        1,
+        &uniform_buffer_allocator,
+        descriptor_set_allocator.clone(),
+    ).context("failed to create global descriptor set")?;
+
+    // the gallery is rendered on its own first for the exhibits sampling it, like `super::SceneInput` does
+    let extent = [size[0], size[1], 1];
+    let render_pass = offscreen_render_pass(device.clone(), COLOR_FORMAT)?;
+    let (framebuffer, color, _) =
+        offscreen_attachments(&render_pass, extent, device.clone(), memory_allocator.clone())?;
+    let (scene_framebuffer, scene_color, scene_depth) =
+        offscreen_attachments(&render_pass, extent, device.clone(), memory_allocator.clone())?;
+    let subpass = Subpass::from(render_pass, 0).unwrap();
+    let previous_frame = Texture::frame_copy(COLOR_FORMAT, extent, device.clone(), memory_allocator.clone())
+        .context("failed to create previous frame texture")?;
+    let placeholder = Texture::placeholder(
+        device.clone(),
+        queue.clone(),
+        command_buffer_allocator.clone(),
+        memory_allocator.clone(),
+    ).context("failed to create placeholder texture")?;
+    let viewport = Viewport {
+        offset: [0.0, 0.0],
+        extent: [size[0] as f32, size[1] as f32],
+        depth_range: 0.0..=1.0,
+    };
+
+    let layouts = LayoutCache::default();
+    let mut pipelines = Vec::new();
+    if let Some(model) = scene.model {
+        let vs = vs::load(device.clone()).context("failed to load vert shader")?;
+        let fs = if device.enabled_features().geometry_shader {
+            fs::load(device.clone())
+        } else {
+            fs_flat::load(device.clone())
+        }.context("failed to load frag shader")?;
+        let geometry = Geometry::from_model(model, VertexType::VertexNorm, memory_allocator.clone(), Vec3::splat(1.))
+            .context("failed to parse model")?;
+        pipelines.push(MyPipeline::new(
+            MyPipelineCreateInfo {
+                name: "main".to_owned(),
+                vs: Arc::new(HotShader::new_nonhot(vs, ShaderKind::Vertex)),
+                fs: Arc::new(HotShader::new_nonhot(fs, ShaderKind::Fragment)),
+                layouts: layouts.clone(),
+                ..Default::default()
+            },
+            None,
+            Vec::new(),
+            device.clone(),
+            geometry,
+            subpass.clone(),
+            viewport.clone(),
+            1,
+            &uniform_buffer_allocator,
+            descriptor_set_allocator.clone(),
+        ).context("failed to create pipeline")?);
+    }
+    let gallery = pipelines.len();
+    for (art_idx, art_obj) in scene.art_objs.iter().enumerate() {
+        if !art_obj.enable_pipeline || art_obj.hidden {
+            continue;
+        }
+        let texture = match &art_obj.texture {
+            Some(TextureSource::File(path)) => Some(Texture::decode(path)?),
+            Some(TextureSource::Generated(image)) => Some(RgbaImage::clone(image)),
+            Some(TextureSource::Webcam) | None => None,
+        };
+        let texture = match texture {
+            Some(image) => Some(Texture::from_image(
+                image,
+                device.clone(),
+                queue.clone(),
+                command_buffer_allocator.clone(),
+                memory_allocator.clone(),
+            ).with_context(|| format!("failed to create texture of {}", art_obj.name))?),
+            None => art_obj.texture.as_ref().map(|_| placeholder.clone()),
+        };
+        let vertex_type = if art_obj.model.has_tex_coords {
+            VertexType::VertexTex
+        } else {
+            VertexType::VertexNorm
+        };
+        let geometry = Geometry::from_model(
+            &art_obj.model,
+            vertex_type,
+            memory_allocator.clone(),
+            art_obj.container_scale,
+        ).context("failed to parse model")?;
+        let pipeline = MyPipeline::new(
+            MyPipelineCreateInfo {
+                vs: compile(&art_obj.shader_vert, &device)
+                    .with_context(|| format!("failed to compile the vertex shader of {}", art_obj.name))?,
+                fs: compile(&art_obj.shader_frag, &device)
+                    .with_context(|| format!("failed to compile the fragment shader of {}", art_obj.name))?,
+                previous_frame: Some(vec![previous_frame.clone()]),
+                scene_input: Some([scene_color.clone(), scene_depth.clone()]),
+                layouts: layouts.clone(),
+                ..art_obj.into()
+            },
+            Some(art_idx),
+            texture.into_iter().collect(),
+            device.clone(),
+            geometry,
+            subpass.clone(),
+            viewport.clone(),
+            1,
+            &uniform_buffer_allocator,
+            descriptor_set_allocator.clone(),
+        ).with_context(|| format!("failed to create the pipeline of {}", art_obj.name))?;
+        if pipeline.uses_mirror_buffers() {
+            log::warn!("{} reads the mirror and is left out of the snapshot", art_obj.name);
+            continue;
+        }
+        pipelines.push(pipeline);
+    }
+
+    let aspect_ratio = size[0] as f32 / size[1] as f32;
+    let view = camera.view_matrix();
+    let proj = Mat4::perspective_rh(scene.fov.to_radians(), aspect_ratio, scene.near, scene.far);
+    let camera = CameraUniforms::new(view, scene.fov.to_radians(), aspect_ratio, scene.near, scene.far);
+    let globals = GlobalUniforms {
+        light_pos: scene.light_pos.to_array(),
+        time: scene.time,
+        delta_time: 0.,
+        frame_index: 0,
+        reduced_motion: 0,
+        detail: 1.,
+    };
+    global_set.update(0, globals, [camera, camera])?;
+    let frame = FrameInfo { time: scene.time, delta_time: 0., index: 0, extent: size };
+    for pipeline in pipelines.iter() {
+        let art_obj = pipeline.get_art_idx().map(|idx| &scene.art_objs[idx]);
+        let data = ArtData {
+            light_pos: scene.light_pos,
+            ..art_obj.map_or_else(|| ArtData::new(Mat4::IDENTITY), |art_obj| art_obj.data)
+        };
+        let block = art_obj.and_then(|art_obj| art_obj.uniform_block.as_ref()).map(|block| block.data());
+        pipeline.update_uniform_buffer(0, view, proj, frame, Some(data), block)?;
+    }
+
+    let order = App::get_pipeline_order(&pipelines, scene.art_objs);
+    let ordered = order.iter().map(|&idx| &pipelines[idx]).collect::<Vec<_>>();
+    let global_sets = global_set.descriptor_sets(false);
+    let record = |pipelines: &[&MyPipeline]| {
+        get_command_buffers(1, &command_buffer_allocator, &queue, pipelines, global_sets, &subpass, viewport.extent)
+            .remove(0)
+    };
+    let gallery_pipelines = pipelines[..gallery].iter().collect::<Vec<_>>();
+    let gallery_commands = record(&gallery_pipelines);
+    let commands = record(&ordered);
+
+    let buffer = Buffer::new_slice::<u8>(
+        memory_allocator.clone(),
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        COLOR_FORMAT.block_size() * size[0] as DeviceSize * size[1] as DeviceSize,
+    )?;
+    let mut builder = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator.clone(),
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+    keyboard.upload(&RgbaImage::new(KEYBOARD_EXTENT[0], KEYBOARD_EXTENT[1]), &mut builder)?;
+    builder.clear_color_image(ClearColorImageInfo::image(previous_frame.view.image().clone()))?;
+    for (framebuffer, commands) in [(scene_framebuffer, gallery_commands), (framebuffer, commands)] {
+        builder
+            .begin_render_pass(
+                RenderPassBeginInfo {
+                    clear_values: vec![Some(scene.clear_color.into()), Some(ClearValue::Depth(1.0))],
+                    ..RenderPassBeginInfo::framebuffer(framebuffer)
+                },
+                SubpassBeginInfo {
+                    contents: SubpassContents::SecondaryCommandBuffers,
+                    ..Default::default()
+                },
+            )?
+            .execute_commands(commands)?
+            .end_render_pass(Default::default())?;
+    }
+    builder.copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(color.view.image().clone(), buffer.clone()))?;
+    builder.build()?
+        .execute(queue)?
+        .then_signal_fence_and_flush()?
+        .wait(Some(FENCE_TIMEOUT))?;
+
+    let pixels = buffer.read()?.to_vec();
+    RgbaImage::from_raw(size[0], size[1], pixels).context("buffer has the wrong size")
+}
+
+/// A copy of `shader` compiled for `device`. Non hot shaders have no code and fail.
+fn compile(shader: &HotShader, device: &Arc<Device>) -> anyhow::Result<Arc<HotShader>> {
+    let module = shader.compile_for(device.clone())?;
+    Ok(Arc::new(HotShader::new_nonhot(module, shader.shader_kind())))
+}
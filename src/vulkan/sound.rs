@@ -0,0 +1,153 @@
+//! Audio shaders that generate their samples on the GPU like the sound shaders of Shadertoy.
+//!
+//! An audio shader defines `vec2 mainSound(int samp, float time)` which returns the left and
+//! right sample in the range -1 to 1 for the sample with index `samp` played at `time` seconds.
+//! It is wrapped in a compute shader that renders `SOUND_SECONDS` of audio at once.
+
+use super::helpers::FENCE_TIMEOUT;
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use vulkano::{
+    buffer::{Buffer, BufferContents, BufferCreateInfo, BufferUsage},
+    command_buffer::{
+        allocator::StandardCommandBufferAllocator,
+        AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBufferAbstract,
+    },
+    descriptor_set::{
+        allocator::StandardDescriptorSetAllocator,
+        DescriptorSet, WriteDescriptorSet,
+    },
+    device::{DeviceOwned, Queue},
+    memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::ShaderModule,
+    sync::GpuFuture,
+};
+
+pub const SAMPLE_RATE: u32 = 44_100;
+/// Length of the rendered audio, it is looped after that.
+pub const SOUND_SECONDS: u32 = 60;
+/// Must match `local_size_x` in `SOUND_MAIN`.
+const LOCAL_SIZE: u32 = 64;
+
+/// Appended to the source of an audio shader.
+const SOUND_MAIN: &str = "
+layout(local_size_x = 64) in;
+layout(set = 0, binding = 0) writeonly buffer Samples { vec2 samples[]; };
+layout(push_constant) uniform SoundParams {
+    int sample_count;
+    float sample_rate;
+};
+
+void main() {
+    int samp = int(gl_GlobalInvocationID.x);
+    if (samp >= sample_count) {
+        return;
+    }
+    samples[samp] = clamp(mainSound(samp, float(samp) / sample_rate), -1., 1.);
+}
+";
+
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct SoundParams {
+    sample_count: i32,
+    sample_rate: f32,
+}
+
+/// Turns the source of an audio shader into a compute shader, used as preprocessor of `HotShader`.
+pub fn to_compute(source: &str) -> anyhow::Result<String> {
+    anyhow::ensure!(source.contains("mainSound"), "audio shader does not define mainSound");
+    // keep the line numbers of errors the same as in the file
+    let header = if source.contains("#version") { "" } else { "#version 450\n#line 1\n" };
+    Ok(format!("{header}{source}\n{SOUND_MAIN}"))
+}
+
+/// Runs the compiled audio shader and returns the interleaved stereo samples.
+pub fn render(
+    module: Arc<ShaderModule>,
+    queue: Arc<Queue>,
+    command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+) -> anyhow::Result<Vec<f32>> {
+    let start = Instant::now();
+    let device = module.device().clone();
+    let entry = module.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+    let stage = PipelineShaderStageCreateInfo::new(entry);
+    let layout = PipelineLayout::new(
+        device.clone(),
+        PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+            .into_pipeline_layout_create_info(device.clone())
+            .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+    )?;
+    let pipeline = ComputePipeline::new(
+        device,
+        None,
+        ComputePipelineCreateInfo::stage_layout(stage, layout.clone()),
+    )?;
+
+    let sample_count = SAMPLE_RATE * SOUND_SECONDS;
+    let samples = Buffer::new_slice::<[f32; 2]>(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                | MemoryTypeFilter::HOST_RANDOM_ACCESS,
+            ..Default::default()
+        },
+        sample_count as u64,
+    )?;
+    let descriptor_set = DescriptorSet::new(
+        descriptor_set_allocator,
+        pipeline.layout().set_layouts()[0].clone(),
+        [WriteDescriptorSet::buffer(0, samples.clone())],
+        [],
+    )?;
+
+    let mut command_buffer = AutoCommandBufferBuilder::primary(
+        command_buffer_allocator,
+        queue.queue_family_index(),
+        CommandBufferUsage::OneTimeSubmit,
+    )?;
+    command_buffer
+        .bind_pipeline_compute(pipeline)?
+        .bind_descriptor_sets(PipelineBindPoint::Compute, layout.clone(), 0, descriptor_set)?
+        .push_constants(layout, 0, SoundParams {
+            sample_count: sample_count as i32,
+            sample_rate: SAMPLE_RATE as f32,
+        })?;
+    unsafe { command_buffer.dispatch([sample_count.div_ceil(LOCAL_SIZE), 1, 1]) }?;
+    command_buffer.build()?
+        .execute(queue)?
+        .then_signal_fence_and_flush()?
+        .wait(Some(FENCE_TIMEOUT))?;
+
+    let samples = samples.read()?.iter().flatten().copied().collect::<Vec<_>>();
+    log::debug!("rendered {SOUND_SECONDS}s of audio in {:?}", start.elapsed());
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_is_added_if_missing() {
+        let source = "vec2 mainSound(int samp, float time) { return vec2(0.); }";
+        assert!(to_compute(source).unwrap().starts_with("#version 450\n#line 1\nvec2 mainSound"));
+        let source = format!("#version 460\n{source}");
+        assert!(to_compute(&source).unwrap().starts_with("#version 460\nvec2 mainSound"));
+        assert!(to_compute("void main() {}").is_err());
+    }
+}
@@ -0,0 +1,280 @@
+//! Renders the scene once per eye for 3D without a headset, either squeezed side by side
+//! for 3D displays or combined into a red-cyan anaglyph for glasses.
+//!
+//! Each eye has its own uniform buffers and command buffers, the frame `i` of the eye `e`
+//! uses the slot `e * frames_in_flight + i`. The GUI is only drawn for the right eye.
+
+use super::texture::Texture;
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use glam::{Mat4, Vec3};
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, BlitImageInfo, CopyImageInfo, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet},
+    device::Device,
+    format::Format,
+    image::{sampler::Filter, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+};
+
+/// Workgroup size of the compute shader in x and y.
+const LOCAL_SIZE: u32 = 8;
+
+mod cs_anaglyph {
+    vulkano_shaders::shader! {
+        ty: "compute",
+        src: r"
+            #version 450
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+            layout(set = 0, binding = 0) uniform sampler2D left;
+            layout(set = 0, binding = 1) uniform sampler2D right;
+            layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D anaglyph;
+
+            void main() {
+                ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+                if (any(greaterThanEqual(pos, imageSize(anaglyph)))) {
+                    return;
+                }
+                vec4 left_color = texelFetch(left, pos, 0);
+                vec4 right_color = texelFetch(right, pos, 0);
+                // half color anaglyph, a saturated red in only one eye is hard to look at
+                float luminance = dot(left_color.rgb, vec3(0.299, 0.587, 0.114));
+                float alpha = max(left_color.a, right_color.a);
+                imageStore(anaglyph, pos, vec4(luminance, right_color.gb, alpha));
+            }
+        ",
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StereoMode {
+    #[default]
+    Off,
+    /// The left eye in the left half of the window, the right eye in the right half.
+    SideBySide,
+    /// Red for the left eye and cyan for the right eye.
+    Anaglyph,
+}
+
+impl StereoMode {
+    pub const ALL: [Self; 3] = [Self::Off, Self::SideBySide, Self::Anaglyph];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::SideBySide => "Side by side",
+            Self::Anaglyph => "Anaglyph",
+        }
+    }
+
+    pub fn eye_count(self) -> usize {
+        match self {
+            Self::Off => 1,
+            Self::SideBySide | Self::Anaglyph => 2,
+        }
+    }
+}
+
+/// View matrices of the eyes, `ipd` apart in world units around the camera of `view`.
+/// Only the camera itself if `mode` is `StereoMode::Off`.
+pub fn eye_views(view: Mat4, ipd: f32, mode: StereoMode) -> Vec<Mat4> {
+    match mode {
+        StereoMode::Off => vec![view],
+        StereoMode::SideBySide | StereoMode::Anaglyph => vec![
+            Mat4::from_translation(Vec3::X * ipd / 2.) * view,
+            Mat4::from_translation(Vec3::NEG_X * ipd / 2.) * view,
+        ],
+    }
+}
+
+/// The stereo mode with the resources to combine the eyes.
+pub enum Stereo {
+    Off,
+    SideBySide,
+    Anaglyph(Anaglyph),
+}
+
+impl Stereo {
+    pub fn new(
+        mode: StereoMode,
+        render_target: &Arc<Image>,
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        Ok(match mode {
+            StereoMode::Off => Self::Off,
+            StereoMode::SideBySide => Self::SideBySide,
+            StereoMode::Anaglyph => Self::Anaglyph(Anaglyph::new(
+                render_target.format(),
+                render_target.extent(),
+                device,
+                memory_allocator,
+                descriptor_set_allocator,
+            ).context("failed to create anaglyph")?),
+        })
+    }
+
+    pub fn mode(&self) -> StereoMode {
+        match self {
+            Self::Off => StereoMode::Off,
+            Self::SideBySide => StereoMode::SideBySide,
+            Self::Anaglyph(_) => StereoMode::Anaglyph,
+        }
+    }
+
+    /// Records the transfer of `render_target` with the frame of `eye` to its place in `swapchain_image`.
+    /// It is scaled with `blit_filter` if the sizes differ.
+    pub fn present(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        eye: usize,
+        render_target: Arc<Image>,
+        swapchain_image: Arc<Image>,
+        blit_filter: Option<Filter>,
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Off => {
+                if Arc::ptr_eq(&render_target, &swapchain_image) {
+                    // the frame was rendered straight into the swapchain image
+                } else if render_target.extent() == swapchain_image.extent() {
+                    builder.copy_image(CopyImageInfo::images(render_target, swapchain_image))?;
+                } else {
+                    blit(builder, render_target, swapchain_image, blit_filter, None)?;
+                }
+            }
+            Self::SideBySide => {
+                let [width, height, _] = swapchain_image.extent();
+                let half = width / 2;
+                let left = eye as u32 * half;
+                let region = [[left, 0, 0], [left + half, height, 1]];
+                blit(builder, render_target, swapchain_image, blit_filter, Some(region))?;
+            }
+            Self::Anaglyph(anaglyph) => {
+                let eye_copy = anaglyph.eyes[eye].view.image().clone();
+                builder.copy_image(CopyImageInfo::images(render_target, eye_copy))?;
+                if eye == 1 {
+                    anaglyph.combine(builder)?;
+                    let output = anaglyph.output.image().clone();
+                    blit(builder, output, swapchain_image, blit_filter, None)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `dst_region` is the whole `dst` if `None`.
+fn blit(
+    builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    src: Arc<Image>,
+    dst: Arc<Image>,
+    filter: Option<Filter>,
+    dst_region: Option<[[u32; 3]; 2]>,
+) -> anyhow::Result<()> {
+    let filter = filter.context("swapchain images do not support scaled blits")?;
+    let mut info = BlitImageInfo { filter, ..BlitImageInfo::images(src, dst) };
+    if let Some(dst_region) = dst_region {
+        info.regions[0].dst_offsets = dst_region;
+    }
+    builder.blit_image(info)?;
+    Ok(())
+}
+
+/// Copies of both eyes that a compute shader combines into one image.
+pub struct Anaglyph {
+    pipeline: Arc<ComputePipeline>,
+    eyes: [Texture; 2],
+    /// Linear colors in a format that can be written from the shader, unlike the swapchain format.
+    output: Arc<ImageView>,
+    descriptor_set: Arc<DescriptorSet>,
+}
+
+impl Anaglyph {
+    fn new(
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let module = cs_anaglyph::load(device.clone())?;
+        let entry = module.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+        let stage = PipelineShaderStageCreateInfo::new(entry);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+        )?;
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            None,
+            ComputePipelineCreateInfo::stage_layout(stage, layout),
+        )?;
+
+        let eye = || Texture::frame_copy(format, extent, device.clone(), memory_allocator.clone());
+        let eyes = [eye()?, eye()?];
+        let output = ImageView::new_default(Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R16G16B16A16_SFLOAT,
+                extent,
+                usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?)?;
+        let descriptor_set = DescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, eyes[0].view.clone(), eyes[0].sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, eyes[1].view.clone(), eyes[1].sampler.clone()),
+                WriteDescriptorSet::image_view(2, output.clone()),
+            ],
+            [],
+        )?;
+        Ok(Self { pipeline, eyes, output, descriptor_set })
+    }
+
+    fn combine(&self, builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>) -> anyhow::Result<()> {
+        let [width, height, _] = self.output.image().extent();
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())?
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                self.descriptor_set.clone(),
+            )?;
+        unsafe { builder.dispatch([width.div_ceil(LOCAL_SIZE), height.div_ceil(LOCAL_SIZE), 1]) }?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eyes_are_ipd_apart() {
+        let view = Mat4::look_at_rh(Vec3::new(1., 2., 3.), Vec3::new(1., 2., 0.), Vec3::Y);
+        let [left, right] = eye_views(view, 0.064, StereoMode::Anaglyph).try_into().unwrap();
+        let left = left.inverse().w_axis.truncate();
+        let right = right.inverse().w_axis.truncate();
+        assert!(left.distance(Vec3::new(0.968, 2., 3.)) < 1e-5, "{left}");
+        assert!(right.distance(Vec3::new(1.032, 2., 3.)) < 1e-5, "{right}");
+    }
+}
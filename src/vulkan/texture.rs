@@ -1,5 +1,6 @@
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
 
 use anyhow::Context;
 use vulkano::{
@@ -7,20 +8,23 @@ use vulkano::{
     command_buffer::{
         allocator::StandardCommandBufferAllocator,
         AutoCommandBufferBuilder, BlitImageInfo, CommandBufferUsage, CopyBufferToImageInfo,
-        ImageBlit, PrimaryCommandBufferAbstract,
+        ImageBlit, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract,
     },
     device::{physical::PhysicalDevice, Device, Queue},
     format::{Format, FormatFeatures},
     image::{
         view::ImageView,
-        sampler::{Filter, Sampler, SamplerCreateInfo},
+        sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
         Image, ImageAspects, ImageCreateInfo, ImageSubresourceLayers, ImageType, ImageUsage,
     },
     memory::allocator::{AllocationCreateInfo, MemoryTypeFilter, StandardMemoryAllocator},
     DeviceSize,
 };
 
-use image::ImageReader;
+use image::{ImageReader, Rgba, RgbaImage};
+
+/// Color of the placeholder shown while the real texture is loading.
+const PLACEHOLDER_COLOR: [u8; 4] = [128, 128, 128, 255];
 
 pub struct Texture {
     pub view: Arc<ImageView>,
@@ -28,8 +32,58 @@ pub struct Texture {
 }
 
 impl Texture {
-    pub fn new<P: AsRef<Path>>(
-        path: P,
+    /// Reads and decodes an image. This is the slow part of loading a texture
+    /// and does not need the device, so it can be done on another thread.
+    pub fn decode<P: AsRef<Path>>(path: P) -> anyhow::Result<RgbaImage> {
+        let image = ImageReader::new(crate::fs::load(&path)?)
+            .with_guessed_format()
+            .with_context(|| format!("failed to open image at {:?}", path.as_ref()))?
+            .decode()
+            .with_context(|| format!("failed to decode image at {:?}", path.as_ref()))?
+            .flipv();
+        Ok(image.into_rgba8())
+    }
+
+    /// A 1x1 texture that is bound until the real texture is loaded.
+    pub fn placeholder(
+        device: Arc<Device>,
+        queue: Arc<Queue>,
+        command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        let image = RgbaImage::from_pixel(1, 1, Rgba(PLACEHOLDER_COLOR));
+        Self::from_image(image, device, queue, command_buffer_allocator, memory_allocator)
+    }
+
+    /// Creates a texture that frames of the size `extent` can be copied to.
+    pub fn frame_copy(
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        let image = Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format,
+                extent,
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        let view = ImageView::new_default(image)?;
+        let sampler = Sampler::new(device, SamplerCreateInfo {
+            address_mode: [SamplerAddressMode::ClampToEdge; 3],
+            ..SamplerCreateInfo::simple_repeat_linear_no_mipmap()
+        })?;
+        Ok(Self { view, sampler })
+    }
+
+    /// Uploads a decoded image and generates its mipmaps.
+    pub fn from_image(
+        image_as_rgba: RgbaImage,
         device: Arc<Device>,
         queue: Arc<Queue>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
@@ -41,12 +95,6 @@ impl Texture {
             CommandBufferUsage::OneTimeSubmit,
         )?;
 
-        let image = ImageReader::open(&path)
-            .with_context(|| format!("failed to open image at {:?}", path.as_ref()))?
-            .decode()
-            .with_context(|| format!("failed to decode image at {:?}", path.as_ref()))?
-            .flipv();
-        let image_as_rgba = image.into_rgba8();
         let width = image_as_rgba.width();
         let height = image_as_rgba.height();
         let mip_levels = ((width.min(height) as f32).log2().floor() + 1.0) as u32;
@@ -109,7 +157,8 @@ impl Texture {
         })
     }
 
-   fn generate_mipmaps(
+    /// Generates the mipmaps of all array layers of `image` by blitting down from level 0.
+    pub(super) fn generate_mipmaps(
         device: &PhysicalDevice,
         queue: Arc<Queue>,
         command_buffer_allocator: Arc<StandardCommandBufferAllocator>,
@@ -180,13 +229,13 @@ impl Texture {
                 src_subresource: ImageSubresourceLayers {
                     aspects: ImageAspects::COLOR,
                     mip_level: level - 1,
-                    array_layers: 0..1,
+                    array_layers: 0..image.array_layers(),
                 },
                 src_offsets: [[0; 3], [mip_width, mip_height, 1]],
                 dst_subresource: ImageSubresourceLayers {
                     aspects: ImageAspects::COLOR,
                     mip_level: level,
-                    array_layers: 0..1,
+                    array_layers: 0..image.array_layers(),
                 },
                 dst_offsets: [[0; 3], [next_mip_width, next_mip_height, 1]],
                 ..Default::default()
@@ -253,3 +302,106 @@ impl Clone for Texture {
         }
     }
 }
+
+/// A texture without mipmaps whose content is replaced regularly, e.g. by webcam frames.
+pub struct StreamingTexture {
+    pub texture: Texture,
+    image: Arc<Image>,
+    memory_allocator: Arc<StandardMemoryAllocator>,
+}
+
+impl StreamingTexture {
+    pub fn new(
+        extent: [u32; 2],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<Self> {
+        let image = Image::new(
+            memory_allocator.clone(),
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R8G8B8A8_UNORM,
+                extent: [extent[0], extent[1], 1],
+                usage: ImageUsage::TRANSFER_DST | ImageUsage::SAMPLED,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?;
+        let view = ImageView::new_default(image.clone())?;
+        let sampler = Sampler::new(
+            device,
+            SamplerCreateInfo::simple_repeat_linear_no_mipmap(),
+        )?;
+
+        Ok(Self {
+            texture: Texture { view, sampler },
+            image,
+            memory_allocator,
+        })
+    }
+
+    pub fn extent(&self) -> [u32; 2] {
+        let [width, height, _] = self.image.extent();
+        [width, height]
+    }
+
+    /// Records the upload of `frame` into `command_buffer`, the frame must have the size of the texture.
+    pub fn upload(
+        &self,
+        frame: &RgbaImage,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> anyhow::Result<()> {
+        let [width, height] = self.extent();
+        anyhow::ensure!(frame.width() == width && frame.height() == height, "frame size does not match texture size");
+        let upload_buffer = Buffer::from_iter(
+            self.memory_allocator.clone(),
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                memory_type_filter: MemoryTypeFilter::PREFER_HOST
+                    | MemoryTypeFilter::HOST_SEQUENTIAL_WRITE,
+                ..Default::default()
+            },
+            frame.as_raw().iter().copied(),
+        )?;
+        command_buffer.copy_buffer_to_image(
+            CopyBufferToImageInfo::buffer_image(upload_buffer, self.image.clone()),
+        )?;
+        Ok(())
+    }
+}
+
+type DecodeResult = (PathBuf, anyhow::Result<RgbaImage>);
+
+/// Decodes images on background threads.
+pub struct TextureLoader {
+    tx: mpsc::Sender<DecodeResult>,
+    rx: mpsc::Receiver<DecodeResult>,
+}
+
+impl Default for TextureLoader {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+}
+
+impl TextureLoader {
+    /// Starts decoding the image at `path`, the result can be retrieved with `try_recv`.
+    pub fn load(&self, path: PathBuf) {
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            log::debug!("decoding texture {}", path.display());
+            let result = Texture::decode(&path);
+            // the receiver is gone if the app exited in the meantime
+            let _ = tx.send((path, result));
+        });
+    }
+
+    /// Returns a decoded image if one is ready.
+    pub fn try_recv(&self) -> Option<DecodeResult> {
+        self.rx.try_recv().ok()
+    }
+}
@@ -0,0 +1,375 @@
+//! Transitions from the frame before a cut, like a teleport, to the frames after it.
+//!
+//! A transition is a GLSL function `vec4 transition(vec2 uv)` in the format of gl-transitions. It mixes
+//! `getFromColor(uv)` of the old frame and `getToColor(uv)` of the new one by `progress` going from 0 to 1,
+//! `ratio` is the aspect ratio and `uv` starts at the bottom left. Parameters declared as
+//! `uniform float name; // = value` become constants. The function is wrapped in a compute shader that
+//! blends the frames after the color grading, so the interface is blended as well.
+
+use super::{
+    shader::{watch_shaders, HotShader},
+    texture::Texture,
+};
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use vulkano::{
+    buffer::BufferContents,
+    command_buffer::{AutoCommandBufferBuilder, BlitImageInfo, CopyImageInfo, PrimaryAutoCommandBuffer},
+    descriptor_set::{allocator::StandardDescriptorSetAllocator, DescriptorSet, WriteDescriptorSet},
+    device::{Device, DeviceOwned},
+    format::Format,
+    image::{sampler::Filter, view::ImageView, Image, ImageCreateInfo, ImageType, ImageUsage},
+    memory::allocator::{AllocationCreateInfo, StandardMemoryAllocator},
+    pipeline::{
+        compute::ComputePipelineCreateInfo,
+        layout::PipelineDescriptorSetLayoutCreateInfo,
+        ComputePipeline, Pipeline, PipelineBindPoint, PipelineLayout,
+        PipelineShaderStageCreateInfo,
+    },
+    shader::ShaderModule,
+};
+
+/// Workgroup size of the compute shader in x and y, must match `TRANSITION_HEADER`.
+const LOCAL_SIZE: u32 = 8;
+
+/// Inserted before the source of a transition.
+const TRANSITION_HEADER: &str = "
+layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+
+layout(set = 0, binding = 0) uniform sampler2D from_frame;
+layout(set = 0, binding = 1) uniform sampler2D to_frame;
+layout(set = 0, binding = 2, rgba16f) uniform writeonly image2D blended;
+
+layout(push_constant) uniform TransitionParams {
+    float progress;
+    float ratio;
+} params;
+
+#define progress params.progress
+#define ratio params.ratio
+
+// uv starts at the bottom left like in OpenGL, the frames at the top left
+vec4 getFromColor(vec2 uv) {
+    return texture(from_frame, vec2(uv.x, 1. - uv.y));
+}
+
+vec4 getToColor(vec2 uv) {
+    return texture(to_frame, vec2(uv.x, 1. - uv.y));
+}
+";
+
+/// Appended to the source of a transition.
+const TRANSITION_MAIN: &str = "
+void main() {
+    ivec2 pos = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(blended);
+    if (any(greaterThanEqual(pos, size))) {
+        return;
+    }
+    vec2 uv = (vec2(pos.x, size.y - 1 - pos.y) + 0.5) / vec2(size);
+    imageStore(blended, pos, transition(uv));
+}
+";
+
+/// Push constants of `TRANSITION_HEADER`.
+#[derive(BufferContents, Clone, Copy)]
+#[repr(C)]
+struct TransitionParams {
+    progress: f32,
+    ratio: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransitionStyle {
+    Off,
+    /// Fades from the old to the new frame.
+    #[default]
+    Crossfade,
+    /// The new frame is wiped in from the left.
+    Wipe,
+    /// The file `TransitionSettings::shader`, see the module documentation.
+    Shader,
+}
+
+impl TransitionStyle {
+    pub const ALL: [Self; 4] = [Self::Off, Self::Crossfade, Self::Wipe, Self::Shader];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Crossfade => "Crossfade",
+            Self::Wipe => "Wipe",
+            Self::Shader => "Shader",
+        }
+    }
+}
+
+/// How the view changes when the camera jumps, e.g. to a bookmark, or an exhibit is shown full screen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransitionSettings {
+    pub style: TransitionStyle,
+    /// Seconds from the old to the new frame.
+    pub duration: f32,
+    /// Transition used by `TransitionStyle::Shader`.
+    pub shader: PathBuf,
+}
+
+impl TransitionSettings {
+    /// The file of the transition, `None` if it is off.
+    pub fn path(&self) -> Option<&Path> {
+        match self.style {
+            TransitionStyle::Off => None,
+            TransitionStyle::Crossfade => Some(Path::new("assets/shaders/transitions/crossfade.glsl")),
+            TransitionStyle::Wipe => Some(Path::new("assets/shaders/transitions/wipe.glsl")),
+            TransitionStyle::Shader => Some(&self.shader),
+        }
+    }
+}
+
+impl Default for TransitionSettings {
+    fn default() -> Self {
+        Self {
+            style: TransitionStyle::default(),
+            duration: 0.5,
+            shader: PathBuf::from("assets/shaders/transitions/circle.glsl"),
+        }
+    }
+}
+
+/// Turns the source of a transition into a compute shader, used as preprocessor of `HotShader`.
+pub fn to_compute(source: &str) -> anyhow::Result<String> {
+    anyhow::ensure!(source.contains("transition("), "transition shader does not define transition");
+    // keep the line numbers of errors the same as in the file
+    let body = source.lines()
+        .map(|line| if line.trim_start().starts_with("#version") {
+            String::new()
+        } else {
+            uniform_to_const(line).unwrap_or_else(|| line.to_owned())
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(format!("#version 450\n{TRANSITION_HEADER}\n#line 1\n{body}\n{TRANSITION_MAIN}"))
+}
+
+/// Turns `uniform vec2 direction; // = vec2(1., 0.)` into `const vec2 direction = vec2(1., 0.);`.
+fn uniform_to_const(line: &str) -> Option<String> {
+    let (declaration, default) = line.trim_start().strip_prefix("uniform ")?.split_once("//")?;
+    let declaration = declaration.trim().strip_suffix(';')?;
+    let default = default.trim().strip_prefix('=')?.trim().trim_end_matches(';');
+    Some(format!("const {declaration} = {default};"))
+}
+
+/// What a frame does with the transition, see `Transition::next_frame`.
+pub struct TransitionFrame {
+    /// Render target of the last frame, which is copied as the old frame.
+    capture: Option<Arc<Image>>,
+    progress: f32,
+}
+
+/// The compiled transition with copies of the old and new frame.
+pub struct Transition {
+    path: PathBuf,
+    shader: Arc<HotShader>,
+    /// Created once the shader compiled, again after it changed.
+    pipeline: Option<(Arc<ShaderModule>, Arc<ComputePipeline>, Arc<DescriptorSet>)>,
+    /// The last frame before the cut.
+    from: Texture,
+    /// Copy of the render target the shader reads from.
+    to: Texture,
+    /// Linear colors in a format that can be written from the shader, unlike the render target.
+    output: Arc<ImageView>,
+    descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    /// Seconds from the old to the new frame.
+    pub duration: f32,
+    /// Set by `start`, the old frame is captured on the next frame.
+    pending: bool,
+    started: Option<Instant>,
+}
+
+impl Transition {
+    pub fn new(
+        path: &Path,
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+        descriptor_set_allocator: Arc<StandardDescriptorSetAllocator>,
+    ) -> anyhow::Result<Self> {
+        let shader = Arc::new(HotShader::new_transition(path));
+        shader.set_device(device.clone());
+        watch_shaders([shader.clone()]);
+        let (from, to, output) = Self::frame_resources(format, extent, device, memory_allocator)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            shader,
+            pipeline: None,
+            from,
+            to,
+            output,
+            descriptor_set_allocator,
+            duration: TransitionSettings::default().duration,
+            pending: false,
+            started: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Recreates the frame copies at the size of the render target, an ongoing transition ends.
+    pub fn resize(
+        &mut self,
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<()> {
+        (self.from, self.to, self.output) = Self::frame_resources(format, extent, device, memory_allocator)?;
+        self.pipeline = None;
+        self.started = None;
+        Ok(())
+    }
+
+    /// Starts the transition from the frame drawn last.
+    pub fn start(&mut self) {
+        self.pending = true;
+    }
+
+    /// Advances the transition, `last_frame` is the render target holding the frame drawn last.
+    /// Returns `None` if there is nothing to blend or the shader is not compiled yet.
+    pub fn next_frame(&mut self, last_frame: Option<Arc<Image>>) -> anyhow::Result<Option<TransitionFrame>> {
+        let pending = std::mem::take(&mut self.pending);
+        if !self.update_pipeline()? {
+            return Ok(None);
+        }
+        let capture = last_frame.filter(|_| pending);
+        if capture.is_some() {
+            self.started = Some(Instant::now());
+        }
+        let Some(started) = self.started else { return Ok(None) };
+        let progress = started.elapsed().as_secs_f32() / self.duration.max(f32::EPSILON);
+        if progress >= 1. {
+            self.started = None;
+            return Ok(None);
+        }
+        Ok(Some(TransitionFrame { capture, progress }))
+    }
+
+    /// Records copying the old frame, before the new one is drawn to a render target it might share.
+    pub fn capture(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        frame: &TransitionFrame,
+    ) -> anyhow::Result<()> {
+        if let Some(last_frame) = frame.capture.clone() {
+            builder.copy_image(CopyImageInfo::images(last_frame, self.from.view.image().clone()))?;
+        }
+        Ok(())
+    }
+
+    /// Records blending the old frame into `render_target` in place.
+    pub fn apply(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        render_target: Arc<Image>,
+        frame: &TransitionFrame,
+    ) -> anyhow::Result<()> {
+        let Some((_, pipeline, descriptor_set)) = self.pipeline.as_ref() else { return Ok(()) };
+        let [width, height, _] = self.output.image().extent();
+        let params = TransitionParams {
+            progress: frame.progress,
+            ratio: width as f32 / height as f32,
+        };
+        builder
+            .copy_image(CopyImageInfo::images(render_target.clone(), self.to.view.image().clone()))?
+            .bind_pipeline_compute(pipeline.clone())?
+            .bind_descriptor_sets(PipelineBindPoint::Compute, pipeline.layout().clone(), 0, descriptor_set.clone())?
+            .push_constants(pipeline.layout().clone(), 0, params)?;
+        unsafe { builder.dispatch([width.div_ceil(LOCAL_SIZE), height.div_ceil(LOCAL_SIZE), 1]) }?;
+        // a blit converts the format, the sizes are the same
+        builder.blit_image(BlitImageInfo {
+            filter: Filter::Nearest,
+            ..BlitImageInfo::images(self.output.image().clone(), render_target)
+        })?;
+        Ok(())
+    }
+
+    /// Returns `false` while the shader is not compiled.
+    fn update_pipeline(&mut self) -> anyhow::Result<bool> {
+        self.shader.reload(false);
+        let Some(module) = self.shader.get_module()? else { return Ok(false) };
+        if self.pipeline.as_ref().is_some_and(|(current, _, _)| Arc::ptr_eq(current, &module)) {
+            return Ok(true);
+        }
+        let device = module.device().clone();
+        let entry = module.entry_point("main").ok_or_else(|| anyhow::anyhow!("no entrypoint"))?;
+        let stage = PipelineShaderStageCreateInfo::new(entry);
+        let layout = PipelineLayout::new(
+            device.clone(),
+            PipelineDescriptorSetLayoutCreateInfo::from_stages([&stage])
+                .into_pipeline_layout_create_info(device.clone())
+                .map_err(|err| anyhow::anyhow!("failed to create pipeline layout info: {err:?}"))?,
+        )?;
+        let pipeline = ComputePipeline::new(device, None, ComputePipelineCreateInfo::stage_layout(stage, layout))?;
+        let descriptor_set = DescriptorSet::new(
+            self.descriptor_set_allocator.clone(),
+            pipeline.layout().set_layouts()[0].clone(),
+            [
+                WriteDescriptorSet::image_view_sampler(0, self.from.view.clone(), self.from.sampler.clone()),
+                WriteDescriptorSet::image_view_sampler(1, self.to.view.clone(), self.to.sampler.clone()),
+                WriteDescriptorSet::image_view(2, self.output.clone()),
+            ],
+            [],
+        )?;
+        self.pipeline = Some((module, pipeline, descriptor_set));
+        Ok(true)
+    }
+
+    fn frame_resources(
+        format: Format,
+        extent: [u32; 3],
+        device: Arc<Device>,
+        memory_allocator: Arc<StandardMemoryAllocator>,
+    ) -> anyhow::Result<(Texture, Texture, Arc<ImageView>)> {
+        let from = Texture::frame_copy(format, extent, device.clone(), memory_allocator.clone())?;
+        let to = Texture::frame_copy(format, extent, device, memory_allocator.clone())?;
+        let output = ImageView::new_default(Image::new(
+            memory_allocator,
+            ImageCreateInfo {
+                image_type: ImageType::Dim2d,
+                format: Format::R16G16B16A16_SFLOAT,
+                extent,
+                usage: ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo::default(),
+        )?)?;
+        Ok((from, to, output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uniforms_become_constants() {
+        let source = "#version 330\nuniform float smoothness; // = 0.5\nuniform vec2 dir; // = vec2(1., 0.);\n\
+            vec4 transition(vec2 uv) { return getToColor(uv); }";
+        let compute = to_compute(source).unwrap();
+        let body = compute.split("#line 1\n").nth(1).unwrap();
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "");
+        assert_eq!(lines[1], "const float smoothness = 0.5;");
+        assert_eq!(lines[2], "const vec2 dir = vec2(1., 0.);");
+        assert!(compute.starts_with("#version 450\n"));
+        assert_eq!(uniform_to_const("uniform float strength;"), None);
+        assert!(to_compute("void main() {}").is_err());
+    }
+}
@@ -12,6 +12,7 @@ pub enum VertexType {
     #[allow(unused)]
     VertexPos,
     VertexNorm,
+    VertexTex,
 }
 
 #[derive(Debug, Default, Clone, Copy, BufferContents, Vertex)]
@@ -41,3 +42,20 @@ impl MyVertexTrait for VertexNorm {
         Self { position, normal }
     }
 }
+
+#[derive(Debug, Default, Clone, Copy, BufferContents, Vertex)]
+#[repr(C)]
+pub struct VertexTex {
+    #[format(R32G32B32_SFLOAT)]
+    pub position: [f32; 3],
+    #[format(R32G32B32_SFLOAT)]
+    pub normal: [f32; 3],
+    #[format(R32G32_SFLOAT)]
+    pub tex_coords: [f32; 2],
+}
+
+impl MyVertexTrait for VertexTex {
+    fn new(position: [f32; 3], tex_coords: [f32; 2], normal: [f32; 3]) -> Self {
+        Self { position, normal, tex_coords }
+    }
+}
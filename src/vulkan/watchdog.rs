@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+/// Frames a pipeline is watched after it was rebuilt.
+const WATCH_FRAMES: u32 = 60;
+/// Waits shorter than this never count as runaway, the GPU may just be slow.
+const MIN_RUNAWAY: Duration = Duration::from_millis(250);
+/// Factor by which the wait for a frame has to exceed the average wait to count as runaway.
+const SLOWDOWN: u32 = 8;
+/// Longest wait for a frame, a wait that times out always counts as runaway.
+pub const FRAME_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Detects exhibits whose pipeline makes the GPU time explode right after their shader was hot reloaded,
+/// e.g. because it ray-marches forever.
+/// The time the CPU waits for the fence of a frame stands in for the GPU time of the frame.
+pub struct Watchdog {
+    /// Moving average of the waits for frames without watched pipelines.
+    average: Option<Duration>,
+    /// Exhibits whose pipeline was rebuilt and the frames left to watch them.
+    watched: Vec<(usize, u32)>,
+    /// Exhibits that were watched when each frame in flight was submitted.
+    in_flight: Vec<Vec<usize>>,
+}
+
+impl Watchdog {
+    pub fn new(frames_in_flight: usize) -> Self {
+        Self {
+            average: None,
+            watched: Vec::new(),
+            in_flight: vec![Vec::new(); frames_in_flight],
+        }
+    }
+
+    /// Watches the exhibit at `art_idx` for the next frames, its shader was just hot reloaded.
+    pub fn watch(&mut self, art_idx: usize) {
+        match self.watched.iter_mut().find(|(idx, _)| *idx == art_idx) {
+            Some((_, frames)) => *frames = WATCH_FRAMES,
+            None => self.watched.push((art_idx, WATCH_FRAMES)),
+        }
+    }
+
+    /// Forgets all watched exhibits, their indices are no longer valid.
+    pub fn clear(&mut self) {
+        self.watched.clear();
+        self.in_flight.iter_mut().for_each(Vec::clear);
+    }
+
+    /// Remembers the watched exhibits for the frame in flight `frame_i` that is being submitted.
+    pub fn submitted(&mut self, frame_i: usize) {
+        self.in_flight[frame_i] = self.watched.iter().map(|&(idx, _)| idx).collect();
+        self.watched.retain_mut(|(_, frames)| {
+            *frames -= 1;
+            *frames > 0
+        });
+    }
+
+    /// Returns the exhibits that were watched during the frame in flight `frame_i`
+    /// if waiting `wait` for it to finish took far longer than usual.
+    pub fn finished(&mut self, frame_i: usize, wait: Duration) -> Vec<usize> {
+        let suspects = std::mem::take(&mut self.in_flight[frame_i]);
+        if suspects.is_empty() {
+            self.average = Some(match self.average {
+                Some(average) => average.mul_f32(0.9) + wait.mul_f32(0.1),
+                None => wait,
+            });
+            return Vec::new();
+        }
+        let limit = self.average.map_or(MIN_RUNAWAY, |average| (average * SLOWDOWN).max(MIN_RUNAWAY));
+        if wait <= limit && wait < FRAME_TIMEOUT {
+            return Vec::new();
+        }
+        self.watched.retain(|(idx, _)| !suspects.contains(idx));
+        for frame in &mut self.in_flight {
+            frame.retain(|idx| !suspects.contains(idx));
+        }
+        suspects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_slow_frames_with_rebuilt_pipelines_are_runaway() {
+        let ms = Duration::from_millis;
+        let mut watchdog = Watchdog::new(2);
+        watchdog.submitted(0);
+        assert!(watchdog.finished(0, ms(400)).is_empty());
+        watchdog.watch(3);
+        watchdog.submitted(1);
+        watchdog.submitted(0);
+        // the average of 400ms is slow already, 8 times that is needed
+        assert!(watchdog.finished(1, ms(3000)).is_empty());
+        assert_eq!(watchdog.finished(0, ms(4000)), [3]);
+        watchdog.submitted(1);
+        assert!(watchdog.finished(1, ms(4000)).is_empty());
+
+        // a timeout counts even if the frames were slow before
+        for _ in 0..10 {
+            watchdog.submitted(0);
+            watchdog.finished(0, ms(1000));
+        }
+        watchdog.watch(5);
+        watchdog.submitted(0);
+        assert_eq!(watchdog.finished(0, FRAME_TIMEOUT), [5]);
+    }
+}
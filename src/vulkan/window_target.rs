@@ -0,0 +1,143 @@
+//! Surface and swapchain of an additional window that shares the device of `super::VkApp`,
+//! see `VkApp::open_window`.
+
+use std::sync::Arc;
+
+use anyhow::Context;
+use egui_winit_vulkano::Gui;
+use vulkano::{
+    device::{Device, Queue},
+    format::Format,
+    image::{view::ImageView, Image, ImageUsage},
+    instance::Instance,
+    swapchain::{
+        self, PresentMode, Surface, Swapchain, SwapchainAcquireFuture, SwapchainCreateInfo, SwapchainPresentInfo,
+    },
+    sync::{self, GpuFuture},
+    Validated, VulkanError,
+};
+use winit::window::{Window, WindowId};
+
+pub struct WindowTarget {
+    window: Arc<Window>,
+    swapchain: Arc<Swapchain>,
+    images: Vec<Arc<Image>>,
+    /// Set if the swapchain no longer matches the window, it is recreated before the next frame.
+    pub dirty: bool,
+    /// Only used while the window draws on its own, frames of the scene are waited for by `VkApp`.
+    previous_frame_end: Option<Box<dyn GpuFuture>>,
+}
+
+impl WindowTarget {
+    /// Uses `format` if the surface supports it, so frames can be copied from the main window.
+    pub(super) fn new(
+        window: Arc<Window>,
+        instance: Arc<Instance>,
+        device: Arc<Device>,
+        format: Format,
+    ) -> anyhow::Result<Self> {
+        let surface = Surface::from_window(instance, window.clone()).context("failed to get surface")?;
+        let physical_device = device.physical_device();
+        let caps = physical_device
+            .surface_capabilities(&surface, Default::default())
+            .context("failed to get surface capabilities")?;
+        let formats = physical_device
+            .surface_formats(&surface, Default::default())
+            .context("failed to get surface formats")?;
+        let image_format = formats.iter()
+            .map(|(format, _)| *format)
+            .find(|&supported| supported == format)
+            .unwrap_or(formats[0].0);
+        let (swapchain, images) = Swapchain::new(
+            device,
+            surface,
+            SwapchainCreateInfo {
+                min_image_count: 2.min(caps.max_image_count.unwrap_or(u32::MAX)).max(caps.min_image_count),
+                image_format,
+                image_extent: window.inner_size().into(),
+                image_usage: ImageUsage::COLOR_ATTACHMENT | ImageUsage::TRANSFER_DST,
+                composite_alpha: caps.supported_composite_alpha.into_iter().next().unwrap(),
+                present_mode: PresentMode::Fifo,
+                ..Default::default()
+            },
+        ).context("failed to create swapchain")?;
+        Ok(Self { window, swapchain, images, dirty: false, previous_frame_end: None })
+    }
+
+    pub fn window(&self) -> &Arc<Window> { &self.window }
+
+    pub fn id(&self) -> WindowId { self.window.id() }
+
+    pub fn surface(&self) -> &Arc<Surface> { self.swapchain.surface() }
+
+    pub fn format(&self) -> Format { self.swapchain.image_format() }
+
+    /// Recreates the swapchain if it is dirty. Returns `false` while the window is minimized.
+    pub fn prepare(&mut self) -> anyhow::Result<bool> {
+        let extent = self.window.inner_size();
+        if extent.width == 0 || extent.height == 0 {
+            return Ok(false);
+        }
+        if self.dirty {
+            let (swapchain, images) = self.swapchain
+                .recreate(SwapchainCreateInfo {
+                    image_extent: extent.into(),
+                    ..self.swapchain.create_info()
+                })
+                .context("failed to recreate swapchain")?;
+            self.swapchain = swapchain;
+            self.images = images;
+            self.dirty = false;
+        }
+        Ok(true)
+    }
+
+    /// Returns the index of the next image and its image, `None` if the swapchain is out of date.
+    pub(super) fn acquire(&mut self) -> anyhow::Result<Option<(u32, Arc<Image>, SwapchainAcquireFuture)>> {
+        match swapchain::acquire_next_image(self.swapchain.clone(), None).map_err(Validated::unwrap) {
+            Ok((image_i, suboptimal, future)) => {
+                self.dirty |= suboptimal;
+                Ok(Some((image_i, self.images[image_i as usize].clone(), future)))
+            }
+            Err(VulkanError::OutOfDate) => {
+                self.dirty = true;
+                Ok(None)
+            }
+            Err(err) => Err(err).context("failed to acquire next image"),
+        }
+    }
+
+    pub(super) fn present_info(&self, image_i: u32) -> SwapchainPresentInfo {
+        SwapchainPresentInfo::swapchain_image_index(self.swapchain.clone(), image_i)
+    }
+
+    /// Draws `gui` alone, e.g. the GUI detached from the scene. `gui` must have been created
+    /// for the surface of this window with its own render pass.
+    pub fn draw_gui(&mut self, gui: &mut Gui, queue: &Arc<Queue>) -> anyhow::Result<()> {
+        if !self.prepare()? {
+            return Ok(());
+        }
+        if let Some(previous_frame_end) = self.previous_frame_end.as_mut() {
+            previous_frame_end.cleanup_finished();
+        }
+        let Some((image_i, image, acquire_future)) = self.acquire()? else { return Ok(()) };
+        let previous_frame_end = self.previous_frame_end.take()
+            .unwrap_or_else(|| sync::now(queue.device().clone()).boxed());
+        let view = ImageView::new_default(image).context("failed to create image view")?;
+        let future = gui.draw_on_image(previous_frame_end.join(acquire_future), view)
+            .then_swapchain_present(queue.clone(), self.present_info(image_i))
+            .then_signal_fence_and_flush();
+        self.previous_frame_end = match future.map_err(Validated::unwrap) {
+            Ok(future) => Some(future.boxed()),
+            Err(VulkanError::OutOfDate) => {
+                self.dirty = true;
+                None
+            }
+            Err(err) => {
+                log::error!("failed to flush future of the window: {err}");
+                None
+            }
+        };
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! Runs the gallery or a single exhibit as live wallpaper or as overlay over other windows.
+//!
+//! The mode is set with `SHADERPIXEL_WALLPAPER`, either `bottom` for a window below all
+//! others or `overlay` for a transparent window above all others that ignores the mouse.
+//! An exhibit can be appended like `bottom:Mandelbulb` to only show that exhibit.
+
+use crate::{
+    art::ArtObject,
+    camera::Camera,
+};
+
+use winit::event_loop::ActiveEventLoop;
+use winit::window::{WindowAttributes, WindowLevel};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WallpaperLayer {
+    /// Below all other windows like a desktop wallpaper.
+    Bottom,
+    /// Transparent and above all other windows.
+    Overlay,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wallpaper {
+    pub layer: WallpaperLayer,
+    /// Name of the only exhibit that is shown, all are shown if `None`.
+    pub exhibit: Option<String>,
+}
+
+impl Wallpaper {
+    /// Parses a spec like `bottom`, `overlay` or `bottom:<exhibit name>`.
+    pub fn from_spec(spec: &str) -> anyhow::Result<Self> {
+        let (layer, exhibit) = match spec.split_once(':') {
+            Some((layer, exhibit)) => (layer, Some(exhibit.to_owned())),
+            None => (spec, None),
+        };
+        let layer = match layer {
+            "bottom" => WallpaperLayer::Bottom,
+            "overlay" => WallpaperLayer::Overlay,
+            layer => anyhow::bail!("unknown layer {layer}, expected bottom or overlay"),
+        };
+        Ok(Self { layer, exhibit })
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.layer == WallpaperLayer::Overlay
+    }
+
+    /// Makes the window borderless, covering the primary monitor and on the right layer.
+    pub fn window_attributes(&self, attrs: WindowAttributes, event_loop: &ActiveEventLoop) -> WindowAttributes {
+        let level = match self.layer {
+            WallpaperLayer::Bottom => WindowLevel::AlwaysOnBottom,
+            WallpaperLayer::Overlay => WindowLevel::AlwaysOnTop,
+        };
+        let mut attrs = attrs
+            .with_decorations(false)
+            .with_resizable(false)
+            .with_window_level(level)
+            .with_transparent(self.is_transparent());
+        let monitor = event_loop.primary_monitor()
+            .or_else(|| event_loop.available_monitors().next());
+        if let Some(monitor) = monitor {
+            attrs = attrs
+                .with_position(monitor.position())
+                .with_inner_size(monitor.size());
+        }
+        attrs
+    }
+
+    /// Hides all other exhibits and moves the camera in front of the shown one.
+    pub fn setup_scene(&self, art_objects: &mut [ArtObject], camera: &mut Camera) {
+        let Some(name) = self.exhibit.as_ref() else { return };
+        let Some(exhibit) = art_objects.iter().find(|art| art.name == *name) else {
+            log::error!("wallpaper exhibit {name} does not exist, showing the whole gallery");
+            return;
+        };
+        camera.view_target(exhibit.position());
+        for art in art_objects.iter_mut() {
+            art.visible = art.name == *name;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spec() {
+        assert_eq!(
+            Wallpaper::from_spec("bottom").unwrap(),
+            Wallpaper { layer: WallpaperLayer::Bottom, exhibit: None },
+        );
+        assert_eq!(
+            Wallpaper::from_spec("overlay:Menger Sponge").unwrap(),
+            Wallpaper { layer: WallpaperLayer::Overlay, exhibit: Some("Menger Sponge".to_owned()) },
+        );
+        assert!(Wallpaper::from_spec("top").is_err());
+    }
+}
@@ -0,0 +1,95 @@
+//! Captures frames from a webcam so they can be used as a texture by art shaders.
+//!
+//! Capturing needs the `webcam` feature, without it `Webcam::start` always fails and the webcam exhibit is left out.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use image::RgbaImage;
+
+/// A webcam that is read on a background thread. Only the most recent frame is kept.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub struct Webcam {
+    frame: Arc<Mutex<Option<RgbaImage>>>,
+    running: Arc<AtomicBool>,
+}
+
+impl Webcam {
+    #[cfg(feature = "webcam")]
+    pub fn start(index: u32) -> anyhow::Result<Self> {
+        use nokhwa::{
+            pixel_format::RgbAFormat,
+            utils::{CameraIndex, RequestedFormat, RequestedFormatType},
+            Camera,
+        };
+        use std::sync::mpsc;
+        use std::thread;
+
+        let frame = Arc::new(Mutex::new(None));
+        let running = Arc::new(AtomicBool::new(true));
+        let frame_thread = frame.clone();
+        let running_thread = running.clone();
+        let (open_tx, open_rx) = mpsc::channel();
+
+        // the camera is not Send on all platforms so it has to be created on the capture thread
+        thread::spawn(move || {
+            let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+            let camera = Camera::new(CameraIndex::Index(index), format)
+                .and_then(|mut camera| camera.open_stream().map(|_| camera));
+            let mut camera = match camera {
+                Ok(camera) => {
+                    let _ = open_tx.send(Ok(camera.info().human_name()));
+                    camera
+                }
+                Err(err) => {
+                    let _ = open_tx.send(Err(err));
+                    return;
+                }
+            };
+
+            while running_thread.load(Ordering::Relaxed) {
+                let image = match camera.frame().and_then(|buffer| buffer.decode_image::<RgbAFormat>()) {
+                    Ok(image) => image,
+                    Err(err) => {
+                        log::error!("failed to capture webcam frame: {err}");
+                        break;
+                    }
+                };
+                // nokhwa uses a different version of the image crate, so convert through the raw pixels
+                let (width, height) = image.dimensions();
+                let Some(mut image) = RgbaImage::from_raw(width, height, image.into_raw()) else {
+                    log::error!("webcam frame has an invalid size");
+                    break;
+                };
+                image::imageops::flip_vertical_in_place(&mut image);
+                match frame_thread.lock() {
+                    Ok(mut frame) => *frame = Some(image),
+                    Err(_) => break,
+                }
+            }
+            if let Err(err) = camera.stop_stream() {
+                log::warn!("failed to stop webcam stream: {err}");
+            }
+        });
+
+        let name = open_rx.recv()??;
+        log::info!("opened webcam {index}: {name}");
+        Ok(Self { frame, running })
+    }
+
+    #[cfg(not(feature = "webcam"))]
+    pub fn start(_index: u32) -> anyhow::Result<Self> {
+        anyhow::bail!("compiled without the webcam feature")
+    }
+
+    /// Returns the latest frame if there is a new one since the last call.
+    pub fn take_frame(&self) -> Option<RgbaImage> {
+        self.frame.lock().ok()?.take()
+    }
+}
+
+impl Drop for Webcam {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}